@@ -89,6 +89,32 @@ pub struct CacheConfig {
         deserialize_with = "deserialize_percent"
     )]
     files_total_size_limit_percent_if_deleting: u8,
+    #[serde(default)]
+    http: Option<HttpStoreConfig>,
+}
+
+/// Configuration of a remote cache shared over HTTP, see [`HttpStore`].
+///
+/// [`HttpStore`]: crate::HttpStore
+#[derive(serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HttpStoreConfig {
+    url: String,
+    #[serde(rename = "key-file")]
+    key_file: PathBuf,
+    #[serde(
+        default = "default_http_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    timeout: Duration,
+    #[serde(default, rename = "read-only")]
+    read_only: bool,
+    #[serde(
+        default = "default_http_max_entry_size",
+        rename = "max-entry-size",
+        deserialize_with = "deserialize_disk_space"
+    )]
+    max_entry_size: u64,
 }
 
 impl Default for CacheConfig {
@@ -109,6 +135,7 @@ impl Default for CacheConfig {
             file_count_limit_percent_if_deleting: default_file_count_limit_percent_if_deleting(),
             files_total_size_limit_percent_if_deleting:
                 default_files_total_size_limit_percent_if_deleting(),
+            http: None,
         }
     }
 }
@@ -219,6 +246,14 @@ const fn default_file_count_limit_percent_if_deleting() -> u8 {
 const fn default_files_total_size_limit_percent_if_deleting() -> u8 {
     70
 }
+// if changed, update cli-cache.md
+const fn default_http_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+// if changed, update cli-cache.md
+const fn default_http_max_entry_size() -> u64 {
+    256 * 1024 * 1024
+}
 
 fn project_dirs() -> Option<ProjectDirs> {
     ProjectDirs::from("", "BytecodeAlliance", "wasmtime")
@@ -380,6 +415,18 @@ impl CacheConfig {
     generate_setting_getter!(file_count_limit_percent_if_deleting: u8);
     generate_setting_getter!(files_total_size_limit_percent_if_deleting: u8);
 
+    /// Returns the configuration of the remote HTTP cache if one is set.
+    pub fn http(&self) -> Option<&HttpStoreConfig> {
+        self.http.as_ref()
+    }
+
+    /// Shares compiled artifacts with other hosts through the remote HTTP
+    /// cache described by `http`, in addition to the cache directory.
+    pub fn with_http(&mut self, http: HttpStoreConfig) -> &mut Self {
+        self.http = Some(http);
+        self
+    }
+
     /// Returns path to the cache directory if one is set.
     pub fn directory(&self) -> Option<&PathBuf> {
         self.directory.as_ref()
@@ -511,6 +558,7 @@ impl CacheConfig {
         self.validate_optimized_compression_level()?;
         self.validate_file_count_limit_percent_if_deleting()?;
         self.validate_files_total_size_limit_percent_if_deleting()?;
+        self.validate_http()?;
         Ok(())
     }
 
@@ -596,6 +644,13 @@ impl CacheConfig {
         Ok(())
     }
 
+    fn validate_http(&self) -> Result<()> {
+        if let Some(http) = &self.http {
+            crate::store::parse_url(&http.url)?;
+        }
+        Ok(())
+    }
+
     fn validate_files_total_size_limit_percent_if_deleting(&self) -> Result<()> {
         if self.files_total_size_limit_percent_if_deleting > 100 {
             bail!(
@@ -607,6 +662,59 @@ impl CacheConfig {
     }
 }
 
+impl HttpStoreConfig {
+    /// Creates the configuration of a remote cache at `url`, which must be an
+    /// `http://` URL, with default settings.
+    ///
+    /// Entries of the cache are authenticated with the secret key stored in
+    /// `key_file`, which must be shared by all hosts using the cache.
+    pub fn new(url: impl Into<String>, key_file: impl Into<PathBuf>) -> Self {
+        Self {
+            url: url.into(),
+            key_file: key_file.into(),
+            timeout: default_http_timeout(),
+            read_only: false,
+            max_entry_size: default_http_max_entry_size(),
+        }
+    }
+
+    /// Returns the URL under which cached artifacts are stored.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the path of the file holding the secret key authenticating
+    /// entries.
+    pub fn key_file(&self) -> &Path {
+        &self.key_file
+    }
+
+    generate_setting_getter!(timeout: Duration);
+    generate_setting_getter!(read_only: bool);
+    generate_setting_getter!(max_entry_size: u64);
+
+    /// Timeout for connecting to the remote cache and for each read from and
+    /// write to it.
+    pub fn with_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// When set, artifacts are only fetched from the remote cache and newly
+    /// compiled artifacts aren't uploaded to it.
+    pub fn with_read_only(&mut self, read_only: bool) -> &mut Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Maximum size in bytes of an entry fetched from the remote cache, larger
+    /// responses are rejected without being read.
+    pub fn with_max_entry_size(&mut self, max_entry_size: u64) -> &mut Self {
+        self.max_entry_size = max_entry_size;
+        self
+    }
+}
+
 #[cfg(test)]
 #[macro_use]
 pub mod tests;
@@ -1,6 +1,6 @@
 use super::CacheConfig;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tempfile::TempDir;
 
@@ -456,6 +456,101 @@ fn test_percent_settings() {
     );
 }
 
+#[test]
+fn test_http_settings() {
+    let (_td, cd, cp) = test_prolog();
+    let conf = load_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'",
+        cd
+    );
+    assert!(conf.http().is_none());
+
+    let conf = load_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         url = 'http://cache.example.com:8080/wasmtime'\n\
+         key-file = '/etc/wasmtime/cache.key'",
+        cd
+    );
+    let http = conf.http().unwrap();
+    assert_eq!(http.url(), "http://cache.example.com:8080/wasmtime");
+    assert_eq!(http.key_file(), Path::new("/etc/wasmtime/cache.key"));
+    assert_eq!(http.timeout(), Duration::from_secs(2));
+    assert!(!http.read_only());
+    assert_eq!(http.max_entry_size(), 256 * 1024 * 1024);
+
+    let conf = load_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         url = 'http://cache.example.com'\n\
+         key-file = 'cache.key'\n\
+         timeout = '5m'\n\
+         read-only = true\n\
+         max-entry-size = '16Mi'",
+        cd
+    );
+    let http = conf.http().unwrap();
+    assert_eq!(http.timeout(), Duration::from_secs(5 * 60));
+    assert!(http.read_only());
+    assert_eq!(http.max_entry_size(), 16 * 1024 * 1024);
+
+    // different errors
+    bad_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         timeout = '5m'",
+        cd
+    );
+
+    bad_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         url = 'http://cache.example.com'",
+        cd
+    );
+
+    bad_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         url = 'https://cache.example.com'\n\
+         key-file = 'cache.key'",
+        cd
+    );
+
+    bad_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         url = 'http:///wasmtime'\n\
+         key-file = 'cache.key'",
+        cd
+    );
+
+    bad_config!(
+        cp,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         url = 'http://cache.example.com'\n\
+         key-file = 'cache.key'\n\
+         unrecognized-setting = 42",
+        cd
+    );
+}
+
 /// Default builder produces a disabled cache configuration with the same defaults.
 #[test]
 fn test_builder_default() {
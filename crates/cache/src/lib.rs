@@ -7,7 +7,6 @@
 
 use anyhow::Result;
 use base64::Engine;
-use log::{debug, trace, warn};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::hash::Hash;
@@ -21,16 +20,19 @@ use std::{fs, io};
 
 #[macro_use] // for tests
 mod config;
+mod store;
 mod worker;
 
-pub use config::{CacheConfig, create_new_config};
+pub use config::{CacheConfig, HttpStoreConfig, create_new_config};
+use store::FileStore;
+pub use store::{ArtifactStore, HttpStore};
 use worker::Worker;
 
 /// Global configuration for how the cache is managed
 #[derive(Debug, Clone)]
 pub struct Cache {
     config: CacheConfig,
-    worker: Worker,
+    stores: Vec<Arc<dyn ArtifactStore>>,
     state: Arc<CacheState>,
 }
 
@@ -53,13 +55,34 @@ impl Cache {
     /// Returns an error if the configuration is invalid.
     pub fn new(mut config: CacheConfig) -> Result<Self> {
         config.validate()?;
+        let worker = Worker::start_new(&config);
+        let mut stores: Vec<Arc<dyn ArtifactStore>> =
+            vec![Arc::new(FileStore::new(&config, worker))];
+        if let Some(http) = config.http() {
+            stores.push(Arc::new(HttpStore::new(
+                http,
+                config.baseline_compression_level(),
+            )?));
+        }
         Ok(Self {
-            worker: Worker::start_new(&config),
             config,
+            stores,
             state: Default::default(),
         })
     }
 
+    /// Adds `store` to the stores consulted by this cache.
+    ///
+    /// Stores are consulted in the order they were added, starting with the
+    /// cache directory and followed by the remote store from the
+    /// configuration, if any. When an artifact is found in a store it is also
+    /// inserted into all the stores before it, and newly compiled artifacts
+    /// are inserted into every store.
+    pub fn with_store(&mut self, store: impl ArtifactStore + 'static) -> &mut Self {
+        self.stores.push(Arc::new(store));
+        self
+    }
+
     /// Loads cache configuration specified at `path`.
     ///
     /// This method will read the file specified by `path` on the filesystem and
@@ -102,11 +125,6 @@ impl Cache {
             .expect("directory should be validated in Config::new")
     }
 
-    #[cfg(test)]
-    fn worker(&self) -> &Worker {
-        &self.worker
-    }
-
     /// Returns the number of cache hits seen so far
     pub fn cache_hits(&self) -> usize {
         self.state.hits.load(SeqCst)
@@ -116,16 +134,6 @@ impl Cache {
     pub fn cache_misses(&self) -> usize {
         self.state.misses.load(SeqCst)
     }
-
-    pub(crate) fn on_cache_get_async(&self, path: impl AsRef<Path>) {
-        self.state.hits.fetch_add(1, SeqCst);
-        self.worker.on_cache_get_async(path)
-    }
-
-    pub(crate) fn on_cache_update_async(&self, path: impl AsRef<Path>) {
-        self.state.misses.fetch_add(1, SeqCst);
-        self.worker.on_cache_update_async(path)
-    }
 }
 
 #[derive(Default, Debug)]
@@ -138,7 +146,7 @@ struct CacheState {
 pub struct ModuleCacheEntry<'cache>(Option<ModuleCacheEntryInner<'cache>>);
 
 struct ModuleCacheEntryInner<'cache> {
-    root_path: PathBuf,
+    compiler_dir: String,
     cache: &'cache Cache,
 }
 
@@ -201,18 +209,37 @@ impl<'cache> ModuleCacheEntry<'cache> {
         // standard encoding uses '/' which can't be used for filename
         let hash = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&hash);
 
-        if let Some(cached_val) = inner.get_data(&hash) {
+        let key = format!("{}/{hash}", inner.compiler_dir);
+        let stores = &inner.cache.stores;
+        for (i, store) in stores.iter().enumerate() {
+            let Some(cached_val) = store.get(&key) else {
+                continue;
+            };
+            // Keep a copy to populate the stores which missed, which are
+            // expected to be cheaper to query next time.
+            let backfill = if i > 0 {
+                Some(cached_val.clone())
+            } else {
+                None
+            };
             if let Some(val) = deserialize(state, cached_val) {
-                let mod_cache_path = inner.root_path.join(&hash);
-                inner.cache.on_cache_get_async(&mod_cache_path); // call on success
+                if let Some(bytes) = backfill {
+                    for store in &stores[..i] {
+                        store.insert(&key, &bytes);
+                    }
+                }
+                inner.cache.state.hits.fetch_add(1, SeqCst);
                 return Ok(val);
             }
         }
         let val_to_cache = compute(state)?;
         if let Some(bytes) = serialize(state, &val_to_cache) {
-            if inner.update_data(&hash, &bytes).is_some() {
-                let mod_cache_path = inner.root_path.join(&hash);
-                inner.cache.on_cache_update_async(&mod_cache_path); // call on success
+            let mut inserted = false;
+            for store in stores {
+                inserted |= store.insert(&key, &bytes);
+            }
+            if inserted {
+                inner.cache.state.misses.fetch_add(1, SeqCst);
             }
         }
         Ok(val_to_cache)
@@ -254,64 +281,9 @@ impl<'cache> ModuleCacheEntryInner<'cache> {
                 comp_ver = env!("GIT_REV"),
             )
         };
-        let root_path = cache.directory().join("modules").join(compiler_dir);
-
-        Self { root_path, cache }
-    }
-
-    fn get_data(&self, hash: &str) -> Option<Vec<u8>> {
-        let mod_cache_path = self.root_path.join(hash);
-        trace!("get_data() for path: {}", mod_cache_path.display());
-        let compressed_cache_bytes = fs::read(&mod_cache_path).ok()?;
-        let cache_bytes = zstd::decode_all(&compressed_cache_bytes[..])
-            .map_err(|err| warn!("Failed to decompress cached code: {err}"))
-            .ok()?;
-        Some(cache_bytes)
-    }
-
-    fn update_data(&self, hash: &str, serialized_data: &[u8]) -> Option<()> {
-        let mod_cache_path = self.root_path.join(hash);
-        trace!("update_data() for path: {}", mod_cache_path.display());
-        let compressed_data = zstd::encode_all(
-            &serialized_data[..],
-            self.cache.baseline_compression_level(),
-        )
-        .map_err(|err| warn!("Failed to compress cached code: {err}"))
-        .ok()?;
-
-        // Optimize syscalls: first, try writing to disk. It should succeed in most cases.
-        // Otherwise, try creating the cache directory and retry writing to the file.
-        if fs_write_atomic(&mod_cache_path, "mod", &compressed_data).is_ok() {
-            return Some(());
-        }
-
-        debug!(
-            "Attempting to create the cache directory, because \
-             failed to write cached code to disk, path: {}",
-            mod_cache_path.display(),
-        );
-
-        let cache_dir = mod_cache_path.parent().unwrap();
-        fs::create_dir_all(cache_dir)
-            .map_err(|err| {
-                warn!(
-                    "Failed to create cache directory, path: {}, message: {}",
-                    cache_dir.display(),
-                    err
-                )
-            })
-            .ok()?;
-
-        match fs_write_atomic(&mod_cache_path, "mod", &compressed_data) {
-            Ok(_) => Some(()),
-            Err(err) => {
-                warn!(
-                    "Failed to write file with rename, target path: {}, err: {}",
-                    mod_cache_path.display(),
-                    err
-                );
-                None
-            }
+        Self {
+            compiler_dir,
+            cache,
         }
     }
 }
@@ -0,0 +1,487 @@
+//! Backends holding cached artifacts.
+
+use crate::config::HttpStoreConfig;
+use crate::worker::Worker;
+use crate::{CacheConfig, fs_write_atomic};
+use anyhow::{Context, Result, bail};
+use hmac::{Hmac, Mac};
+use log::{debug, trace, warn};
+use sha2::Sha256;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A key/value store holding cached artifacts.
+///
+/// Keys consist of ASCII alphanumerics along with `-`, `_`, `.` and `/`, and
+/// values are serialized artifacts. Stores are free to compress values in
+/// whichever way they see fit.
+///
+/// This is named `ArtifactStore` rather than `CacheStore` as the latter is
+/// already the name of the `wasmtime_environ` trait backing Cranelift's
+/// incremental compilation cache, which stores compiled functions rather than
+/// whole artifacts and which embedders may implement alongside this one.
+pub trait ArtifactStore: Send + Sync + std::fmt::Debug {
+    /// Try to retrieve the bytes that were inserted under `key` via
+    /// `Self::insert` before.
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    /// Given a key and bytes, stores them in the cache.
+    ///
+    /// Returns false when insertion in the cache failed.
+    fn insert(&self, key: &str, value: &[u8]) -> bool;
+}
+
+/// The default [`ArtifactStore`], which keeps zstd-compressed artifacts in the
+/// cache directory that is managed by the cache worker.
+#[derive(Debug)]
+pub(crate) struct FileStore {
+    root_path: PathBuf,
+    compression_level: i32,
+    worker: Worker,
+}
+
+impl FileStore {
+    pub(crate) fn new(config: &CacheConfig, worker: Worker) -> Self {
+        let root_path = config
+            .directory()
+            .expect("directory should be validated in Config::new")
+            .join("modules");
+        Self {
+            root_path,
+            compression_level: config.baseline_compression_level(),
+            worker,
+        }
+    }
+}
+
+impl ArtifactStore for FileStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mod_cache_path = self.root_path.join(key);
+        trace!("get_data() for path: {}", mod_cache_path.display());
+        let compressed_cache_bytes = fs::read(&mod_cache_path).ok()?;
+        let cache_bytes = zstd::decode_all(&compressed_cache_bytes[..])
+            .map_err(|err| warn!("Failed to decompress cached code: {err}"))
+            .ok()?;
+        self.worker.on_cache_get_async(&mod_cache_path);
+        Some(cache_bytes)
+    }
+
+    fn insert(&self, key: &str, serialized_data: &[u8]) -> bool {
+        let mod_cache_path = self.root_path.join(key);
+        trace!("update_data() for path: {}", mod_cache_path.display());
+        let compressed_data = match zstd::encode_all(serialized_data, self.compression_level) {
+            Ok(data) => data,
+            Err(err) => {
+                warn!("Failed to compress cached code: {err}");
+                return false;
+            }
+        };
+
+        // Optimize syscalls: first, try writing to disk. It should succeed in most cases.
+        // Otherwise, try creating the cache directory and retry writing to the file.
+        if fs_write_atomic(&mod_cache_path, "mod", &compressed_data).is_ok() {
+            self.worker.on_cache_update_async(&mod_cache_path);
+            return true;
+        }
+
+        debug!(
+            "Attempting to create the cache directory, because \
+             failed to write cached code to disk, path: {}",
+            mod_cache_path.display(),
+        );
+
+        let cache_dir = mod_cache_path.parent().unwrap();
+        if let Err(err) = fs::create_dir_all(cache_dir) {
+            warn!(
+                "Failed to create cache directory, path: {}, message: {}",
+                cache_dir.display(),
+                err
+            );
+            return false;
+        }
+
+        match fs_write_atomic(&mod_cache_path, "mod", &compressed_data) {
+            Ok(_) => {
+                self.worker.on_cache_update_async(&mod_cache_path);
+                true
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to write file with rename, target path: {}, err: {}",
+                    mod_cache_path.display(),
+                    err
+                );
+                false
+            }
+        }
+    }
+}
+
+/// An [`ArtifactStore`] which fetches and uploads artifacts from a remote
+/// server with HTTP `GET` and `PUT` requests, allowing multiple hosts to share
+/// a cache.
+///
+/// Each artifact is stored at `{url}/{key}`. The body of an entry is an
+/// HMAC-SHA256 tag of the key and the zstd-compressed artifact, followed by
+/// the compressed artifact itself. The tag is computed with a secret key read
+/// from a local file, and fetched entries whose tag doesn't match are ignored,
+/// so only hosts knowing the key can add artifacts to the cache.
+///
+/// Fetches are made synchronously on the compilation path, so after a failed
+/// request the remote isn't consulted again for a minute. Uploads are
+/// made in the background and dropped if too many of them are pending.
+///
+/// Responses are limited to the configured
+/// [maximum entry size](HttpStoreConfig::max_entry_size), so a misbehaving
+/// remote can't make the host run out of memory.
+///
+/// Only `http://` URLs are supported, so connections to a remote over an
+/// untrusted network should go through a proxy terminating TLS.
+#[derive(Debug)]
+pub struct HttpStore {
+    remote: Arc<Remote>,
+    /// Queue of the uploader thread, `None` for read-only stores.
+    uploads: Option<SyncSender<(String, Vec<u8>)>>,
+}
+
+/// How long the remote is skipped after a request to it failed.
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Maximum number of uploads waiting to be sent to the remote.
+const MAX_PENDING_UPLOADS: usize = 16;
+
+/// Maximum length of the status line and headers of a response.
+const MAX_RESPONSE_HEAD_LEN: u64 = 64 * 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug)]
+struct Remote {
+    /// The `host[:port]` part of the URL, used for the `Host` header.
+    authority: String,
+    /// The `host:port` to connect to.
+    addr: String,
+    /// The path of the URL, without a trailing slash.
+    path: String,
+    timeout: Duration,
+    /// The secret key authenticating entries.
+    key: Vec<u8>,
+    /// The maximum length of a response body.
+    max_entry_size: u64,
+    compression_level: i32,
+    /// Until when the remote is skipped after a failed request.
+    unavailable_until: Mutex<Option<Instant>>,
+}
+
+impl HttpStore {
+    /// Creates a store for the server described by `config`, compressing
+    /// artifacts with the given zstd `compression_level` before uploading
+    /// them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured URL is invalid, or if the key file
+    /// can't be read or is empty.
+    pub fn new(config: &HttpStoreConfig, compression_level: i32) -> Result<Self> {
+        let (authority, path) = parse_url(config.url())?;
+        let addr = if authority.ends_with(']') || !authority.contains(':') {
+            format!("{authority}:80")
+        } else {
+            authority.to_string()
+        };
+        let key = fs::read(config.key_file()).with_context(|| {
+            format!(
+                "failed to read cache key file: {}",
+                config.key_file().display()
+            )
+        })?;
+        if key.is_empty() {
+            bail!("Cache key file is empty: {}", config.key_file().display());
+        }
+        let remote = Arc::new(Remote {
+            authority: authority.to_string(),
+            addr,
+            path: path.trim_end_matches('/').to_string(),
+            timeout: config.timeout(),
+            key,
+            max_entry_size: config.max_entry_size(),
+            compression_level,
+            unavailable_until: Mutex::new(None),
+        });
+
+        let uploads = if config.read_only() {
+            None
+        } else {
+            let (tx, rx) = mpsc::sync_channel::<(String, Vec<u8>)>(MAX_PENDING_UPLOADS);
+            let remote = remote.clone();
+            thread::Builder::new()
+                .name("wasmtime-cache-upload".to_string())
+                .spawn(move || {
+                    for (key, data) in rx {
+                        remote.upload(&key, &data);
+                    }
+                })?;
+            Some(tx)
+        };
+        Ok(Self { remote, uploads })
+    }
+}
+
+impl Remote {
+    fn request(&self, method: &str, key: &str, body: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+        let result = self.send(method, key, body);
+        if result.is_err() {
+            *self.unavailable_until.lock().unwrap() = Some(Instant::now() + RETRY_AFTER);
+        }
+        result
+    }
+
+    fn send(&self, method: &str, key: &str, body: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+        let mut stream = self.connect()?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        write!(
+            stream,
+            "{method} {path}/{key} HTTP/1.1\r\n\
+             Host: {authority}\r\n\
+             Content-Length: {len}\r\n\
+             Connection: close\r\n\
+             \r\n",
+            path = self.path,
+            authority = self.authority,
+            len = body.len(),
+        )?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        read_response(BufReader::new(stream), self.max_entry_size)
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_err = None;
+        for addr in self.addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "host resolved to no addresses")
+        }))
+    }
+
+    fn is_available(&self) -> bool {
+        let mut until = self.unavailable_until.lock().unwrap();
+        match *until {
+            Some(t) if Instant::now() < t => false,
+            _ => {
+                *until = None;
+                true
+            }
+        }
+    }
+
+    fn mac(&self, key: &str, data: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(key.as_bytes());
+        mac.update(&[0]);
+        mac.update(data);
+        mac
+    }
+
+    fn upload(&self, key: &str, serialized_data: &[u8]) {
+        if !self.is_available() {
+            debug!("Skipping upload to unavailable remote, key: {key}");
+            return;
+        }
+        trace!("update_data() for remote key: {key}");
+        let compressed_data = match zstd::encode_all(serialized_data, self.compression_level) {
+            Ok(data) => data,
+            Err(err) => {
+                warn!("Failed to compress cached code: {err}");
+                return;
+            }
+        };
+        let mut body = Vec::with_capacity(32 + compressed_data.len());
+        body.extend_from_slice(&self.mac(key, &compressed_data).finalize().into_bytes());
+        body.extend_from_slice(&compressed_data);
+
+        match self.request("PUT", key, &body) {
+            Ok((200..=299, _)) => {}
+            Ok((status, _)) => warn!(
+                "Unexpected status uploading cached code to remote, key: {key}, status: {status}"
+            ),
+            Err(err) => warn!("Failed to upload cached code to remote, key: {key}, err: {err}"),
+        }
+    }
+}
+
+impl ArtifactStore for HttpStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let remote = &self.remote;
+        if !remote.is_available() {
+            debug!("Skipping fetch from unavailable remote, key: {key}");
+            return None;
+        }
+        trace!("get_data() for remote key: {key}");
+        let (status, body) = remote
+            .request("GET", key, &[])
+            .map_err(|err| warn!("Failed to fetch cached code from remote, key: {key}, err: {err}"))
+            .ok()?;
+        match status {
+            200 => {}
+            404 => return None,
+            _ => {
+                warn!(
+                    "Unexpected status fetching cached code from remote, key: {key}, status: {status}"
+                );
+                return None;
+            }
+        }
+
+        if body.len() < 32
+            || remote
+                .mac(key, &body[32..])
+                .verify_slice(&body[..32])
+                .is_err()
+        {
+            warn!("Ignoring cached code from remote with an invalid tag, key: {key}");
+            return None;
+        }
+        zstd::decode_all(&body[32..])
+            .map_err(|err| warn!("Failed to decompress cached code: {err}"))
+            .ok()
+    }
+
+    /// Queues `serialized_data` for upload, returning whether it was queued.
+    fn insert(&self, key: &str, serialized_data: &[u8]) -> bool {
+        let Some(uploads) = &self.uploads else {
+            return false;
+        };
+        match uploads.try_send((key.to_string(), serialized_data.to_vec())) {
+            Ok(()) => true,
+            Err(_) => {
+                debug!("Dropping upload to remote, too many are pending, key: {key}");
+                false
+            }
+        }
+    }
+}
+
+/// Splits an `http://` URL into its authority and path.
+pub(crate) fn parse_url(url: &str) -> Result<(&str, &str)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        bail!("Invalid cache URL: {url}, only `http://` URLs are supported");
+    };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if authority.is_empty() {
+        bail!("Invalid cache URL: {url}, missing host");
+    }
+    Ok((authority, path))
+}
+
+/// Reads an HTTP/1.1 response, returning its status code and body.
+///
+/// Fails without reading the rest of the response once the body is known to
+/// be longer than `max_body_len`.
+fn read_response(mut reader: impl BufRead, max_body_len: u64) -> io::Result<(u16, Vec<u8>)> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let too_large = || invalid("response body is too large");
+
+    // Reads a line, without its terminating CRLF, of at most `max_len` bytes.
+    fn read_line(reader: &mut impl BufRead, max_len: u64) -> io::Result<Vec<u8>> {
+        let mut line = Vec::new();
+        reader.take(max_len + 2).read_until(b'\n', &mut line)?;
+        match line.strip_suffix(b"\r\n") {
+            Some(l) => Ok(l.to_vec()),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "incomplete or too long line in response",
+            )),
+        }
+    }
+
+    let mut head_len = 0;
+    let mut head = Vec::new();
+    loop {
+        let line = read_line(&mut reader, MAX_RESPONSE_HEAD_LEN - head_len)?;
+        if line.is_empty() {
+            break;
+        }
+        head_len += line.len() as u64 + 2;
+        head.push(String::from_utf8(line).map_err(|_| invalid("invalid response head"))?);
+    }
+
+    let mut lines = head.iter();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("invalid status line"))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = Some(
+                value
+                    .parse::<u64>()
+                    .map_err(|_| invalid("invalid content length"))?,
+            );
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let line = read_line(&mut reader, 1024)?;
+            let line = std::str::from_utf8(&line).map_err(|_| invalid("invalid chunk size"))?;
+            let size = line.split(';').next().unwrap_or("").trim();
+            let size = u64::from_str_radix(size, 16).map_err(|_| invalid("invalid chunk size"))?;
+            if size == 0 {
+                break;
+            }
+            if body.len() as u64 + size > max_body_len {
+                return Err(too_large());
+            }
+            let start = body.len();
+            (&mut reader).take(size).read_to_end(&mut body)?;
+            if (body.len() - start) as u64 != size || !read_line(&mut reader, 0)?.is_empty() {
+                return Err(invalid("invalid chunked body"));
+            }
+        }
+    } else {
+        match content_length {
+            Some(len) => {
+                if len > max_body_len {
+                    return Err(too_large());
+                }
+                reader.take(len).read_to_end(&mut body)?;
+                if body.len() as u64 != len {
+                    return Err(invalid("truncated body"));
+                }
+            }
+            None => {
+                reader.take(max_body_len + 1).read_to_end(&mut body)?;
+                if body.len() as u64 > max_body_len {
+                    return Err(too_large());
+                }
+            }
+        }
+    }
+    Ok((status, body))
+}
@@ -33,10 +33,8 @@ fn test_cache_init() {
     );
 
     // test if we can use worker
-    Cache::new(cache_config)
-        .unwrap()
-        .worker()
-        .on_cache_update_async(config_path);
+    Cache::new(cache_config.clone()).unwrap();
+    Worker::start_new(&cache_config).on_cache_update_async(config_path);
 }
 
 #[test]
@@ -88,3 +86,251 @@ fn test_write_read_cache() {
     entry1.get_data::<_, i32, i32>(4, |_| panic!()).unwrap();
     entry2.get_data::<_, i32, i32>(1, |_| panic!()).unwrap();
 }
+
+/// A minimal HTTP server standing in for a remote cache, storing the bodies of
+/// `PUT` requests in memory and serving them to `GET` requests.
+fn spawn_http_server() -> (
+    String,
+    Arc<std::sync::Mutex<std::collections::HashMap<String, Vec<u8>>>>,
+) {
+    use std::io::{BufRead, BufReader, Read};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/cache", listener.local_addr().unwrap());
+    let entries = Arc::new(std::sync::Mutex::new(std::collections::HashMap::<
+        String,
+        Vec<u8>,
+    >::new()));
+    let server_entries = entries.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap().to_string();
+            let path = parts.next().unwrap().to_string();
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut entries = server_entries.lock().unwrap();
+            match method.as_str() {
+                "GET" => match entries.get(&path) {
+                    Some(data) => {
+                        write!(
+                            stream,
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                            data.len()
+                        )
+                        .unwrap();
+                        stream.write_all(data).unwrap();
+                    }
+                    None => {
+                        write!(
+                            stream,
+                            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+                        )
+                        .unwrap();
+                    }
+                },
+                "PUT" => {
+                    entries.insert(path, body);
+                    write!(stream, "HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n").unwrap();
+                }
+                _ => panic!("unexpected method: {method}"),
+            }
+        }
+    });
+    (url, entries)
+}
+
+/// Waits for the background uploads of an [`HttpStore`] to reach the server.
+fn wait_for_uploads(
+    entries: &std::sync::Mutex<std::collections::HashMap<String, Vec<u8>>>,
+    count: usize,
+) {
+    for _ in 0..500 {
+        if entries.lock().unwrap().len() >= count {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("uploads didn't reach the remote");
+}
+
+#[test]
+fn test_http_store() {
+    let (url, entries) = spawn_http_server();
+    let new_cache_at = |url: &str, key: &str| {
+        let (tempdir, cache_dir, config_path) = test_prolog();
+        let key_file = tempdir.path().join("cache.key");
+        fs::write(&key_file, key).unwrap();
+        let key_file = key_file.display();
+        let cache_config = load_config!(
+            config_path,
+            "[cache]\n\
+             directory = '{cache_dir}'\n\
+             [cache.http]\n\
+             url = '{url}'\n\
+             key-file = '{key_file}'\n",
+            cache_dir
+        );
+        (tempdir, Cache::new(cache_config).unwrap())
+    };
+    let new_cache = |key: &str| new_cache_at(&url, key);
+
+    // A newly compiled artifact is uploaded to the remote.
+    let (_tempdir1, cache1) = new_cache("secret");
+    let entry1 = ModuleCacheEntry::from_inner(ModuleCacheEntryInner::new("test", &cache1));
+    entry1.get_data::<_, i32, i32>(1, |_| Ok(100)).unwrap();
+    assert_eq!(cache1.cache_misses(), 1);
+    wait_for_uploads(&entries, 1);
+    assert_eq!(entries.lock().unwrap().len(), 1);
+    assert!(
+        entries
+            .lock()
+            .unwrap()
+            .keys()
+            .all(|k| k.starts_with("/cache/test-"))
+    );
+
+    // Another host with an empty cache directory finds it on the remote...
+    let (_tempdir2, cache2) = new_cache("secret");
+    let entry2 = ModuleCacheEntry::from_inner(ModuleCacheEntryInner::new("test", &cache2));
+    assert_eq!(
+        entry2.get_data::<_, i32, i32>(1, |_| panic!()).unwrap(),
+        100
+    );
+    assert_eq!(cache2.cache_hits(), 1);
+
+    // ... and stores it locally, so it's found even without the remote.
+    entries.lock().unwrap().clear();
+    assert_eq!(
+        entry2.get_data::<_, i32, i32>(1, |_| panic!()).unwrap(),
+        100
+    );
+    assert_eq!(cache2.cache_hits(), 2);
+
+    // Entries uploaded by a host with another key are ignored.
+    let (_tempdir3, cache3) = new_cache("another secret");
+    let entry3 = ModuleCacheEntry::from_inner(ModuleCacheEntryInner::new("test", &cache3));
+    entry3.get_data::<_, i32, i32>(2, |_| Ok(200)).unwrap();
+    wait_for_uploads(&entries, 1);
+    let (_tempdir4, cache4) = new_cache("secret");
+    let entry4 = ModuleCacheEntry::from_inner(ModuleCacheEntryInner::new("test", &cache4));
+    assert_eq!(entry4.get_data::<_, i32, i32>(2, |_| Ok(300)).unwrap(), 300);
+    assert_eq!(cache4.cache_hits(), 0);
+
+    // Corrupted entries on the remote are ignored. Use another remote, which
+    // the upload of the previous host can't race with.
+    let (url, entries) = spawn_http_server();
+    let (_tempdir5, cache5) = new_cache_at(&url, "secret");
+    let entry5 = ModuleCacheEntry::from_inner(ModuleCacheEntryInner::new("test", &cache5));
+    entry5.get_data::<_, i32, i32>(3, |_| Ok(400)).unwrap();
+    wait_for_uploads(&entries, 1);
+    for data in entries.lock().unwrap().values_mut() {
+        *data.last_mut().unwrap() ^= 1;
+    }
+    let (_tempdir5, cache5) = new_cache_at(&url, "secret");
+    let entry5 = ModuleCacheEntry::from_inner(ModuleCacheEntryInner::new("test", &cache5));
+    assert_eq!(entry5.get_data::<_, i32, i32>(3, |_| Ok(500)).unwrap(), 500);
+    assert_eq!(cache5.cache_hits(), 0);
+    assert_eq!(cache5.cache_misses(), 1);
+}
+
+#[test]
+fn test_http_store_unavailable() {
+    let (tempdir, cache_dir, config_path) = test_prolog();
+    let key_file = tempdir.path().join("cache.key");
+    fs::write(&key_file, "secret").unwrap();
+    // Nothing listens on the address of a listener which was dropped.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let key_file = key_file.display();
+    let cache_config = load_config!(
+        config_path,
+        "[cache]\n\
+         directory = '{cache_dir}'\n\
+         [cache.http]\n\
+         url = 'http://{addr}/cache'\n\
+         key-file = '{key_file}'\n",
+        cache_dir
+    );
+    let store = HttpStore::new(cache_config.http().unwrap(), 0).unwrap();
+    assert!(store.get("test/a").is_none());
+
+    // After a failed request the remote isn't contacted again for a while.
+    let listener = std::net::TcpListener::bind(addr).unwrap();
+    listener.set_nonblocking(true).unwrap();
+    assert!(store.get("test/a").is_none());
+    assert_eq!(
+        listener.accept().unwrap_err().kind(),
+        std::io::ErrorKind::WouldBlock
+    );
+}
+
+#[test]
+fn test_http_store_rejects_large_entries() {
+    use std::io::{BufRead, BufReader};
+
+    let (tempdir, _cache_dir, _config_path) = test_prolog();
+    let key_file = tempdir.path().join("cache.key");
+    fs::write(&key_file, "secret").unwrap();
+
+    // Each connection is answered with the next response, after which the
+    // connection is kept open so that reading past the response would block
+    // until the timeout.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/cache", listener.local_addr().unwrap());
+    let responses = [
+        "HTTP/1.1 200 OK\r\nContent-Length: 1048576\r\n\r\n".to_string(),
+        format!("HTTP/1.1 200 OK\r\n\r\n{}", "x".repeat(2048)),
+        format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n400\r\n{}\r\n400\r\n{}\r\n",
+            "x".repeat(1024),
+            "x".repeat(1024)
+        ),
+    ];
+    let count = responses.len();
+    std::thread::spawn(move || {
+        let mut open = Vec::new();
+        for (stream, response) in listener.incoming().zip(responses) {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            open.push(stream);
+        }
+        std::thread::sleep(Duration::from_secs(60));
+    });
+
+    for _ in 0..count {
+        let mut config = HttpStoreConfig::new(url.clone(), &key_file);
+        config
+            .with_timeout(Duration::from_secs(60))
+            .with_max_entry_size(1024);
+        let store = HttpStore::new(&config, 0).unwrap();
+        let start = std::time::Instant::now();
+        assert!(store.get("test/a").is_none());
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+}
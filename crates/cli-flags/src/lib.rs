@@ -217,13 +217,16 @@ wasmtime_option_group! {
         #[serde(default)]
         #[serde(deserialize_with = "crate::opt::cli_parse_wrapper")]
         pub compiler: Option<wasmtime::Strategy>,
-        /// Which garbage collector to use: `drc` or `null`.
+        /// Which garbage collector to use: `drc`, `null`, or `copying`.
         ///
         /// `drc` is the deferred reference-counting collector.
         ///
         /// `null` is the null garbage collector, which does not collect any
         /// garbage.
         ///
+        /// `copying` is the semi-space copying collector, which can also
+        /// collect cycles.
+        ///
         /// Note that not all builds of Wasmtime will have support for garbage
         /// collection included.
         #[serde(default)]
@@ -272,6 +275,8 @@ wasmtime_option_group! {
         pub log_to_files: Option<bool>,
         /// Enable coredump generation to this file after a WebAssembly trap.
         pub coredump: Option<String>,
+        /// Record the values of wasm locals and the operand stack in coredumps.
+        pub coredump_locals: Option<bool>,
     }
 
     enum Debug {
@@ -318,6 +323,9 @@ wasmtime_option_group! {
         pub unknown_imports_default: Option<bool>,
         /// Enables memory error checking. (see wmemcheck.md for more info)
         pub wmemcheck: Option<bool>,
+        /// Track uninitialized bytes across all of linear memory when memory
+        /// error checking is enabled. (see wmemcheck.md for more info)
+        pub wmemcheck_uninit: Option<bool>,
         /// Maximum size, in bytes, that a linear memory is allowed to reach.
         ///
         /// Growth beyond this limit will cause `memory.grow` instructions in
@@ -480,9 +488,28 @@ wasmtime_option_group! {
         /// Pass a wasi config variable to the program.
         #[serde(skip)]
         pub config_var: Vec<KeyValuePair>,
+        /// Provide wasi config variables from a TOML or JSON file.
+        ///
+        /// Nested tables are flattened into `a.b` names. `wasmtime serve`
+        /// checks the file for changes every second and on `SIGHUP`, keeping
+        /// the previous values if it can't be loaded.
+        /// Values from `-S config-var` take precedence over values from files.
+        #[serde(skip)]
+        pub config_file: Vec<String>,
+        /// Provide host environment variables starting with this prefix as
+        /// wasi config variables, with the prefix removed.
+        #[serde(skip)]
+        pub config_var_prefix: Vec<String>,
         /// Preset data for the In-Memory provider of WASI key-value API.
         #[serde(skip)]
         pub keyvalue_in_memory_data: Vec<KeyValuePair>,
+        /// Back a WASI key-value store with a database file on the host.
+        ///
+        /// `-S keyvalue-file=<identifier>=<path>` makes `store.open` with the
+        /// given identifier read and write the file at `path`, creating it if
+        /// it doesn't exist. Data written there persists across runs.
+        #[serde(skip)]
+        pub keyvalue_file: Vec<KeyValuePair>,
         /// Enable support for WASIp3 APIs.
         pub p3: Option<bool>,
     }
@@ -625,7 +652,13 @@ impl CommonOptions {
         }
     }
 
-    fn configure(&mut self) -> Result<()> {
+    /// Resolves the options parsed from the command line and the file passed
+    /// to `--config`, if any, into the `opts`, `codegen`, `debug`, `wasm`, and
+    /// `wasi` fields.
+    ///
+    /// This is done automatically by [`CommonOptions::init_logging`] and
+    /// [`CommonOptions::config`], and does nothing if it was already done.
+    pub fn configure(&mut self) -> Result<()> {
         if self.configured {
             return Ok(());
         }
@@ -710,6 +743,11 @@ impl CommonOptions {
             #[cfg(not(feature = "coredump"))]
             anyhow::bail!("support for coredumps disabled at compile time");
         }
+        match_feature! {
+            ["coredump" : self.debug.coredump_locals]
+            enable => config.coredump_locals(enable),
+            true => err,
+        }
         match_feature! {
             ["cranelift" : self.opts.opt_level]
             level => config.cranelift_opt_level(level),
@@ -989,6 +1027,11 @@ impl CommonOptions {
             enable => config.wmemcheck(enable),
             true => err,
         }
+        match_feature! {
+            ["cranelift" : self.wasm.wmemcheck_uninit]
+            enable => config.wmemcheck_uninit(enable),
+            true => err,
+        }
 
         if let Some(enable) = self.wasm.gc_support {
             config.gc_support(enable);
@@ -1184,6 +1227,7 @@ mod tests {
                 Some(wasmtime::Collector::DeferredReferenceCounting),
             ),
             ("\"null\"", Some(wasmtime::Collector::Null)),
+            ("\"copying\"", Some(wasmtime::Collector::Copying)),
             ("\"hello\"", None), // should fail
             ("5", None),         // should fail
             ("true", None),      // should fail
@@ -3,7 +3,7 @@ use cranelift_codegen::{
     Final, MachBufferFinalized, MachSrcLoc, ValueLabelsRanges, ir, isa::unwind::CfaUnwindInfo,
     isa::unwind::UnwindInfo,
 };
-use wasmtime_environ::{FilePos, InstructionAddressMap, PrimaryMap, TrapInformation};
+use wasmtime_environ::{CoverageSite, FilePos, InstructionAddressMap, PrimaryMap, TrapInformation};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 /// Metadata to translate from binary offsets back to the original
@@ -50,6 +50,21 @@ pub struct CompiledFunctionMetadata {
     pub start_srcloc: FilePos,
     /// End source location.
     pub end_srcloc: FilePos,
+    /// The sites of the function's code coverage counters, if any.
+    pub coverage_sites: Vec<CoverageSite>,
+    /// The location of the frame-state slot of functions whose frames are laid
+    /// out by a compiler other than Cranelift, such as Winch.
+    pub frame_state: Option<FrameStateLocation>,
+}
+
+/// Where the frame-state slot of a function lives within its stack frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStateLocation {
+    /// Offset, relative to the start of the function, of the first instruction
+    /// at which the slot holds initialized state.
+    pub valid_from: u32,
+    /// The number of bytes below the frame pointer at which the slot starts.
+    pub fp_offset: u32,
 }
 
 /// Compiled function: machine code body, jump table offsets, and unwind information.
@@ -155,6 +170,26 @@ impl CompiledFunction {
         self.metadata.cfa_unwind_info = Some(unwind);
     }
 
+    /// Get the sites of the function's code coverage counters.
+    pub fn coverage_sites(&self) -> &[CoverageSite] {
+        &self.metadata.coverage_sites
+    }
+
+    /// Set the sites of the function's code coverage counters.
+    pub fn set_coverage_sites(&mut self, sites: Vec<CoverageSite>) {
+        self.metadata.coverage_sites = sites;
+    }
+
+    /// Get the location of the function's frame-state slot, if explicitly set.
+    pub fn frame_state(&self) -> Option<FrameStateLocation> {
+        self.metadata.frame_state
+    }
+
+    /// Set the location of the function's frame-state slot.
+    pub fn set_frame_state(&mut self, location: FrameStateLocation) {
+        self.metadata.frame_state = Some(location);
+    }
+
     /// Set the sized stack slots.
     pub fn set_sized_stack_slots(&mut self, slots: ir::StackSlots) {
         self.metadata.sized_stack_slots = slots;
@@ -14,7 +14,7 @@ use cranelift_codegen::isa::{
     unwind::{UnwindInfo, UnwindInfoKind},
 };
 use cranelift_codegen::print_errors::pretty_error;
-use cranelift_codegen::{CompiledCode, Context, FinalizedMachCallSite};
+use cranelift_codegen::{CompiledCode, Context, Final, FinalizedMachCallSite, MachBufferFinalized};
 use cranelift_entity::PrimaryMap;
 use cranelift_frontend::FunctionBuilder;
 use object::write::{Object, StandardSegment, SymbolId};
@@ -31,10 +31,10 @@ use wasmparser::{FuncValidatorAllocations, FunctionBody};
 use wasmtime_environ::obj::ELF_WASMTIME_EXCEPTIONS;
 use wasmtime_environ::{
     Abi, AddressMapSection, BuiltinFunctionIndex, CacheStore, CompileError, CompiledFunctionBody,
-    DefinedFuncIndex, FlagValue, FuncKey, FunctionBodyData, FunctionLoc, HostCall,
-    InliningCompiler, ModuleTranslation, ModuleTypesBuilder, PtrSize, StackMapSection,
-    StaticModuleIndex, TrapEncodingBuilder, TrapSentinel, TripleExt, Tunables, VMOffsets,
-    WasmFuncType, WasmValType,
+    CoverageSection, CoverageSite, DefinedFuncIndex, FRAME_STATE_SLOT_KEY, FlagValue,
+    FrameStateSection, FuncKey, FunctionBodyData, FunctionLoc, HostCall, InliningCompiler,
+    ModuleTranslation, ModuleTypesBuilder, PtrSize, StackMapSection, StaticModuleIndex,
+    TrapEncodingBuilder, TrapSentinel, TripleExt, Tunables, VMOffsets, WasmFuncType, WasmValType,
 };
 use wasmtime_unwinder::ExceptionTableBuilder;
 
@@ -54,6 +54,7 @@ struct CompilerContext {
     incremental_cache_ctx: Option<IncrementalCacheContext>,
     validator_allocations: FuncValidatorAllocations,
     abi: Option<Abi>,
+    coverage_sites: Vec<CoverageSite>,
 }
 
 impl Default for CompilerContext {
@@ -64,6 +65,7 @@ impl Default for CompilerContext {
             incremental_cache_ctx: None,
             validator_allocations: Default::default(),
             abi: None,
+            coverage_sites: Vec::new(),
         }
     }
 }
@@ -317,6 +319,7 @@ impl wasmtime_environ::Compiler for Compiler {
             &mut context.func,
             &mut func_env,
         )?;
+        compiler.cx.coverage_sites = mem::take(&mut func_env.coverage_sites);
 
         if self.tunables.inlining {
             compiler
@@ -574,6 +577,8 @@ impl wasmtime_environ::Compiler for Compiler {
         let mut addrs = AddressMapSection::default();
         let mut traps = TrapEncodingBuilder::default();
         let mut stack_maps = StackMapSection::default();
+        let mut frame_states = FrameStateSection::default();
+        let mut coverage = CoverageSection::default();
         let mut exception_tables = ExceptionTableBuilder::default();
 
         let mut ret = Vec::with_capacity(funcs.len());
@@ -596,6 +601,21 @@ impl wasmtime_environ::Compiler for Compiler {
                 func.buffer.user_stack_maps(),
             );
 
+            if self.tunables.debug_frame_state {
+                match func.frame_state() {
+                    Some(location) => frame_states.push(
+                        range.start + u64::from(location.valid_from)..range.end,
+                        location.fp_offset,
+                    ),
+                    None => clif_to_env_frame_state(&mut frame_states, range.clone(), &func.buffer),
+                }
+            }
+
+            if self.tunables.coverage {
+                let body_end = func.address_map().end_srcloc.file_offset().unwrap_or(0);
+                coverage.push(range.clone(), body_end, func.coverage_sites());
+            }
+
             traps.push(range.clone(), &func.traps().collect::<Vec<_>>());
             clif_to_env_exception_tables(
                 &mut exception_tables,
@@ -617,6 +637,8 @@ impl wasmtime_environ::Compiler for Compiler {
             addrs.append_to(obj);
         }
         stack_maps.append_to(obj);
+        frame_states.append_to(obj);
+        coverage.append_to(obj);
         traps.append_to(obj);
 
         let exception_section = obj.add_section(
@@ -1359,6 +1381,8 @@ impl FunctionCompiler<'_> {
             alignment,
         );
 
+        compiled_function.set_coverage_sites(mem::take(&mut self.cx.coverage_sites));
+
         if let Some((body, tunables)) = body_and_tunables {
             let data = body.get_binary_reader();
             let offset = data.original_position();
@@ -1432,6 +1456,40 @@ fn clif_to_env_stack_maps(
     }
 }
 
+/// Records where the frame-state slot, if any, of the function occupying
+/// `range` lives in its stack frame.
+///
+/// The slot is only considered valid from the first `sequence_point` tagged
+/// with it onwards, since before that point its contents are uninitialized.
+fn clif_to_env_frame_state(
+    section: &mut FrameStateSection,
+    range: Range<u64>,
+    buffer: &MachBufferFinalized<Final>,
+) {
+    let Some(layout) = buffer.frame_layout() else {
+        return;
+    };
+    let key = ir::StackSlotKey::new(FRAME_STATE_SLOT_KEY);
+    let Some((slot, data)) = layout
+        .stackslots
+        .iter()
+        .find(|(_, data)| data.key == Some(key))
+    else {
+        return;
+    };
+    let Some(valid_from) = buffer
+        .debug_tags()
+        .filter(|list| list.tags.contains(&ir::DebugTag::StackSlot(slot)))
+        .map(|list| list.offset)
+        .min()
+    else {
+        return;
+    };
+    let start = range.start + u64::from(valid_from);
+    assert!(start < range.end);
+    section.push(start..range.end, layout.frame_to_fp_offset - data.offset);
+}
+
 /// Convert from Cranelift's representation of exception handler
 /// metadata to Wasmtime's compiler-agnostic representation.
 ///
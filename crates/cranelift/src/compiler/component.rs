@@ -174,6 +174,7 @@ impl<'a> TrampolineCompiler<'a> {
                             ),
                             me.index_value(*lower_ty),
                             me.index_value(*options),
+                            me.index_value(*index),
                         ]);
                     },
                 );
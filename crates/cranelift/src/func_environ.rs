@@ -1,5 +1,7 @@
 mod gc;
 pub(crate) mod stack_switching;
+#[cfg(feature = "wmemcheck")]
+mod wmemcheck;
 
 use crate::compiler::Compiler;
 use crate::translate::{
@@ -23,13 +25,17 @@ use smallvec::{SmallVec, smallvec};
 use std::mem;
 use wasmparser::{Operator, WasmFeatures};
 use wasmtime_environ::{
-    BuiltinFunctionIndex, DataIndex, DefinedFuncIndex, ElemIndex, EngineOrModuleTypeIndex,
-    FuncIndex, FuncKey, GlobalIndex, IndexType, Memory, MemoryIndex, Module,
-    ModuleInternedTypeIndex, ModuleTranslation, ModuleTypesBuilder, PtrSize, Table, TableIndex,
-    TagIndex, TripleExt, Tunables, TypeConvert, TypeIndex, VMOffsets, WasmCompositeInnerType,
-    WasmFuncType, WasmHeapTopType, WasmHeapType, WasmRefType, WasmResult, WasmValType,
+    BuiltinFunctionIndex, CoverageSite, CoverageSiteKind, DataIndex, DefinedFuncIndex, ElemIndex,
+    EngineOrModuleTypeIndex, FuncIndex, FuncKey, GlobalIndex, IndexType, Memory, MemoryIndex,
+    Module, ModuleInternedTypeIndex, ModuleTranslation, ModuleTypesBuilder, PtrSize, Table,
+    TableIndex, TagIndex, TripleExt, Tunables, TypeConvert, TypeIndex, VMOffsets,
+    WasmCompositeInnerType, WasmFuncType, WasmHeapTopType, WasmHeapType, WasmRefType, WasmResult,
+    WasmValType,
+};
+use wasmtime_environ::{
+    DEBUG_VALUE_SLOT_SIZE, DebugValueKind, FRAME_STATE_HEADER_SIZE, FRAME_STATE_NUM_LOCALS,
+    FRAME_STATE_NUM_STACK, FRAME_STATE_SLOT_KEY, FUNCREF_INIT_BIT, FUNCREF_MASK,
 };
-use wasmtime_environ::{FUNCREF_INIT_BIT, FUNCREF_MASK};
 use wasmtime_math::f64_cvt_to_int_bounds;
 
 #[derive(Debug)]
@@ -182,6 +188,32 @@ pub struct FuncEnvironment<'module_environment> {
     /// slot on this function's stack to be used for the
     /// current continuation's `values` field.
     stack_switching_values_buffer: Option<ir::StackSlot>,
+
+    /// The slot in which the values of locals and the operand stack are
+    /// recorded for core dumps, if `Tunables::debug_frame_state` is enabled
+    /// and any have been recorded so far.
+    frame_state_slot: Option<ir::StackSlot>,
+
+    /// The slots holding the values and the kinds of locals and operand stack
+    /// values passed to the `debug_hook` builtin, if
+    /// `Tunables::debug_instrumentation` is enabled.
+    debug_slots: Option<(ir::StackSlot, ir::StackSlot)>,
+
+    /// The kinds of this function's locals, recorded at function entry if
+    /// `Tunables::debug_instrumentation` is enabled.
+    debug_local_kinds: Vec<DebugValueKind>,
+
+    /// The base address of this function's code coverage counters, loaded at
+    /// function entry if `Tunables::coverage` is enabled.
+    coverage_counters: Option<ir::Value>,
+
+    /// The sites of this function's code coverage counters, in counter order.
+    pub(crate) coverage_sites: Vec<CoverageSite>,
+
+    /// Whether each wasm value was computed from uninitialized memory, if
+    /// `Tunables::wmemcheck_uninit` is enabled.
+    #[cfg(feature = "wmemcheck")]
+    wmemcheck_shadows: wmemcheck::Shadows,
 }
 
 impl<'module_environment> FuncEnvironment<'module_environment> {
@@ -238,6 +270,17 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
 
             stack_switching_handler_list_buffer: None,
             stack_switching_values_buffer: None,
+
+            frame_state_slot: None,
+
+            debug_slots: None,
+            debug_local_kinds: Vec::new(),
+
+            coverage_counters: None,
+            coverage_sites: Vec::new(),
+
+            #[cfg(feature = "wmemcheck")]
+            wmemcheck_shadows: Default::default(),
         }
     }
 
@@ -483,6 +526,35 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
+    /// Notifies the store that this function has been entered so that the
+    /// fuel it consumes can be attributed to it.
+    ///
+    /// This must come before `fuel_function_entry`, whose fuel check may flush
+    /// this function's own consumption to the store, which would then be
+    /// charged to the caller.
+    fn fuel_profile_enter(&mut self, builder: &mut FunctionBuilder<'_>) {
+        let enter = self.builtin_functions.fuel_profile_enter(builder.func);
+        let vmctx = self.vmctx_val(&mut builder.cursor());
+        let func_index = self.current_func_index(builder);
+        let func_index = builder
+            .ins()
+            .iconst(ir::types::I32, i64::from(func_index.as_u32()));
+        let fp = builder.ins().get_frame_pointer(self.pointer_type());
+        builder.ins().call(enter, &[vmctx, func_index, fp]);
+    }
+
+    /// Notifies the store that this function is about to return, or to
+    /// replace itself with a tail call.
+    ///
+    /// Fuel must already have been saved to `VMStoreContext` at this point,
+    /// which is the case for all instructions that leave a function.
+    fn fuel_profile_exit(&mut self, builder: &mut FunctionBuilder<'_>) {
+        let exit = self.builtin_functions.fuel_profile_exit(builder.func);
+        let vmctx = self.vmctx_val(&mut builder.cursor());
+        let fp = builder.ins().get_frame_pointer(self.pointer_type());
+        builder.ins().call(exit, &[vmctx, fp]);
+    }
+
     /// Adds `self.fuel_consumed` to the `fuel_var`, zero-ing out the amount of
     /// fuel consumed at that point.
     fn fuel_increment_var(&mut self, builder: &mut FunctionBuilder<'_>) {
@@ -602,27 +674,63 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         self.epoch_check_full(builder, cur_epoch_value, continuation_block);
     }
 
+    /// Call `check` with the allocator's return value followed by the wasm
+    /// parameters at `params`, when the allocator has that signature.
     #[cfg(feature = "wmemcheck")]
-    fn hook_malloc_exit(&mut self, builder: &mut FunctionBuilder, retvals: &[ir::Value]) {
-        let check_malloc = self.builtin_functions.check_malloc(builder.func);
+    fn hook_alloc_exit(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        retvals: &[ir::Value],
+        check: ir::FuncRef,
+        params: &[usize],
+    ) {
         let vmctx = self.vmctx_val(&mut builder.cursor());
+        // The first two block parameters are the callee and caller vmctx, and
+        // the wasm parameters follow them.
         let func_args = builder
             .func
             .dfg
             .block_params(builder.func.layout.entry_block().unwrap());
-        let len = if func_args.len() < 3 {
+        let Some(&retval) = retvals.first() else {
             return;
-        } else {
-            // If a function named `malloc` has at least one argument, we assume the
-            // first argument is the requested allocation size.
-            func_args[2]
         };
-        let retval = if retvals.len() < 1 {
-            return;
-        } else {
-            retvals[0]
-        };
-        builder.ins().call(check_malloc, &[vmctx, retval, len]);
+        let mut args = vec![vmctx, retval];
+        for i in params {
+            match func_args.get(i + 2) {
+                Some(arg) => args.push(*arg),
+                None => return,
+            }
+        }
+        builder.ins().call(check, &args);
+    }
+
+    #[cfg(feature = "wmemcheck")]
+    fn hook_malloc_exit(&mut self, builder: &mut FunctionBuilder, retvals: &[ir::Value]) {
+        // If a function named `malloc` has at least one argument, we assume the
+        // first argument is the requested allocation size.
+        let check_malloc = self.builtin_functions.check_malloc(builder.func);
+        self.hook_alloc_exit(builder, retvals, check_malloc, &[0]);
+    }
+
+    #[cfg(feature = "wmemcheck")]
+    fn hook_aligned_alloc_exit(&mut self, builder: &mut FunctionBuilder, retvals: &[ir::Value]) {
+        // `aligned_alloc(alignment, size)`
+        let check_malloc = self.builtin_functions.check_malloc(builder.func);
+        self.hook_alloc_exit(builder, retvals, check_malloc, &[1]);
+    }
+
+    #[cfg(feature = "wmemcheck")]
+    fn hook_calloc_exit(&mut self, builder: &mut FunctionBuilder, retvals: &[ir::Value]) {
+        // `calloc(count, size)`
+        let check_calloc = self.builtin_functions.check_calloc(builder.func);
+        self.hook_alloc_exit(builder, retvals, check_calloc, &[0, 1]);
+    }
+
+    #[cfg(feature = "wmemcheck")]
+    fn hook_realloc_exit(&mut self, builder: &mut FunctionBuilder, retvals: &[ir::Value]) {
+        // `realloc(ptr, size)`
+        let check_realloc = self.builtin_functions.check_realloc(builder.func);
+        self.hook_alloc_exit(builder, retvals, check_realloc, &[0, 1]);
     }
 
     #[cfg(feature = "wmemcheck")]
@@ -916,6 +1024,17 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             .copied()
     }
 
+    /// Returns the index of the function currently being translated.
+    fn current_func_index(&self, builder: &FunctionBuilder) -> FuncIndex {
+        let (namespace, index) = match &builder.func.name {
+            ir::UserFuncName::User(user) => (user.namespace, user.index),
+            _ => panic!("function name not a UserFuncName::User as expected"),
+        };
+        let (_, def_func_index) =
+            FuncKey::from_raw_parts(namespace, index).unwrap_defined_wasm_function();
+        self.module.func_index(def_func_index)
+    }
+
     /// Proof-carrying code: create a memtype describing an empty
     /// runtime struct (to be updated later).
     fn create_empty_struct_memtype(&self, func: &mut ir::Function) -> ir::MemoryType {
@@ -3527,13 +3646,27 @@ impl FuncEnvironment<'_> {
     pub fn before_translate_operator(
         &mut self,
         op: &Operator,
-        _operand_types: Option<&[WasmValType]>,
+        operand_types: Option<&[WasmValType]>,
         builder: &mut FunctionBuilder,
         state: &FuncTranslationStacks,
     ) -> WasmResult<()> {
+        #[cfg(feature = "wmemcheck")]
+        if self.tunables.wmemcheck_uninit {
+            self.wmemcheck_before_op(op, operand_types, builder, state);
+        }
+        #[cfg(not(feature = "wmemcheck"))]
+        let _ = operand_types;
         if self.tunables.consume_fuel {
             self.fuel_before_op(op, builder, state.reachable());
         }
+        if self.tunables.fuel_profiling && state.reachable() {
+            match op {
+                Operator::ReturnCall { .. }
+                | Operator::ReturnCallIndirect { .. }
+                | Operator::ReturnCallRef { .. } => self.fuel_profile_exit(builder),
+                _ => {}
+            }
+        }
         Ok(())
     }
 
@@ -3544,12 +3677,337 @@ impl FuncEnvironment<'_> {
         builder: &mut FunctionBuilder,
         state: &FuncTranslationStacks,
     ) -> WasmResult<()> {
+        #[cfg(feature = "wmemcheck")]
+        if self.tunables.wmemcheck_uninit {
+            self.wmemcheck_after_op(op, builder, state);
+        }
         if self.tunables.consume_fuel && state.reachable() {
             self.fuel_after_op(op, builder);
         }
+        if self.tunables.debug_instrumentation && state.reachable() {
+            match op {
+                Operator::LocalSet { local_index } | Operator::LocalTee { local_index } => {
+                    self.debug_local_set(builder, *local_index)
+                }
+                _ => {}
+            }
+        }
         Ok(())
     }
 
+    /// Emits a call to the `debug_hook` builtin before the operator at
+    /// `offset`, guarded by the store's `debug_hook_enabled` flag.
+    ///
+    /// The function's locals followed by the operand stack are passed to the
+    /// debugger in this function's debug slots, where `kinds` describes each
+    /// of those values. To keep the size of the code emitted for each operator
+    /// independent of the number of locals, locals are written to the slots
+    /// once at function entry and then whenever they're set, and only the
+    /// operand stack and GC references are spilled before calling the hook.
+    pub fn debug_hook(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        stack: &FuncTranslationStacks,
+        offset: usize,
+        entry: bool,
+        kinds: &[DebugValueKind],
+    ) {
+        let pointer_type = self.pointer_type();
+        let num_stack = stack.stack.len();
+        debug_assert!(kinds.len() >= num_stack);
+        let num_locals = kinds.len() - num_stack;
+        let (values_slot, kinds_slot) = self.debug_slots(builder, kinds.len());
+
+        if entry {
+            self.debug_local_kinds = kinds[..num_locals].to_vec();
+            for (i, kind) in kinds[..num_locals].iter().enumerate() {
+                let i = u32::try_from(i).unwrap();
+                let kind_val = builder.ins().iconst(ir::types::I8, i64::from(*kind as u8));
+                builder
+                    .ins()
+                    .stack_store(kind_val, kinds_slot, i32::try_from(i).unwrap());
+                if !kind.is_gc_ref() {
+                    let val = builder.use_var(Variable::from_u32(i));
+                    store_debug_value(builder, values_slot, i, val);
+                }
+            }
+        }
+
+        let hook_block = builder.create_block();
+        let continuation_block = builder.create_block();
+
+        // Only call into the host when the store's debugger asked to observe
+        // execution, which is the rare case.
+        let vm_store_context = self.get_vmstore_context_ptr(builder);
+        let enabled = builder.ins().load(
+            pointer_type,
+            ir::MemFlags::trusted(),
+            vm_store_context,
+            i32::from(self.offsets.ptr.vmstore_context_debug_hook_enabled()),
+        );
+        builder
+            .ins()
+            .brif(enabled, hook_block, &[], continuation_block, &[]);
+        builder.seal_block(hook_block);
+        builder.set_cold_block(hook_block);
+        builder.switch_to_block(hook_block);
+
+        // GC references may be moved by a collection after they were written
+        // to the slot, so they're spilled anew right before the call.
+        for (i, kind) in kinds[..num_locals].iter().enumerate() {
+            if kind.is_gc_ref() {
+                let i = u32::try_from(i).unwrap();
+                let val = builder.use_var(Variable::from_u32(i));
+                store_debug_value(builder, values_slot, i, val);
+            }
+        }
+        for (depth, kind) in kinds[num_locals..].iter().enumerate() {
+            let i = u32::try_from(num_locals + depth).unwrap();
+            store_debug_value(builder, values_slot, i, stack.stack[depth]);
+            let kind = builder.ins().iconst(ir::types::I8, i64::from(*kind as u8));
+            builder
+                .ins()
+                .stack_store(kind, kinds_slot, i32::try_from(i).unwrap());
+        }
+        let values = builder.ins().stack_addr(pointer_type, values_slot, 0);
+        let kinds = builder.ins().stack_addr(pointer_type, kinds_slot, 0);
+
+        let func_index = self.current_func_index(builder);
+        let debug_hook = self.builtin_functions.debug_hook(builder.func);
+        let vmctx = self.vmctx_val(&mut builder.cursor());
+        let func_index = builder
+            .ins()
+            .iconst(ir::types::I32, i64::from(func_index.as_u32()));
+        let offset = builder
+            .ins()
+            .iconst(ir::types::I32, i64::try_from(offset).unwrap());
+        let entry = builder.ins().iconst(ir::types::I32, i64::from(entry));
+        let num_locals = builder
+            .ins()
+            .iconst(ir::types::I32, i64::try_from(num_locals).unwrap());
+        let num_stack = builder
+            .ins()
+            .iconst(ir::types::I32, i64::try_from(num_stack).unwrap());
+        builder.ins().call(
+            debug_hook,
+            &[
+                vmctx, func_index, offset, entry, values, kinds, num_locals, num_stack,
+            ],
+        );
+        builder.ins().jump(continuation_block, &[]);
+        builder.seal_block(continuation_block);
+
+        builder.switch_to_block(continuation_block);
+    }
+
+    /// Returns this function's debug slots, growing them to hold at least
+    /// `len` values.
+    fn debug_slots(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        len: usize,
+    ) -> (ir::StackSlot, ir::StackSlot) {
+        let len = u32::try_from(len).unwrap().max(1);
+        match self.debug_slots {
+            Some((values, kinds)) => {
+                let data = &mut builder.func.sized_stack_slots[values];
+                data.size = data.size.max(len * DEBUG_VALUE_SLOT_SIZE);
+                let data = &mut builder.func.sized_stack_slots[kinds];
+                data.size = data.size.max(len);
+                (values, kinds)
+            }
+            None => {
+                let values = builder.func.create_sized_stack_slot(ir::StackSlotData::new(
+                    ir::StackSlotKind::ExplicitSlot,
+                    len * DEBUG_VALUE_SLOT_SIZE,
+                    4,
+                ));
+                let kinds = builder.func.create_sized_stack_slot(ir::StackSlotData::new(
+                    ir::StackSlotKind::ExplicitSlot,
+                    len,
+                    0,
+                ));
+                self.debug_slots = Some((values, kinds));
+                (values, kinds)
+            }
+        }
+    }
+
+    /// Writes the new value of the local `local_index` through to this
+    /// function's debug slots after it was set.
+    fn debug_local_set(&mut self, builder: &mut FunctionBuilder, local_index: u32) {
+        let Some((values_slot, _)) = self.debug_slots else {
+            return;
+        };
+        let kind = self.debug_local_kinds[usize::try_from(local_index).unwrap()];
+        if !kind.is_gc_ref() {
+            let val = builder.use_var(Variable::from_u32(local_index));
+            store_debug_value(builder, values_slot, local_index, val);
+        }
+    }
+
+    /// Records the current values of all locals and the operand stack, along
+    /// with the instance's `VMContext`, in this function's frame-state slot so
+    /// that core dumps can recover them if the following code traps.
+    ///
+    /// The slot is laid out as described in `wasmtime_environ::frame_state`
+    /// and `kinds` describes each of the values as in `debug_hook`.
+    pub fn record_frame_state(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        stack: &FuncTranslationStacks,
+        kinds: &[DebugValueKind],
+    ) {
+        let len = u32::try_from(kinds.len()).unwrap();
+        let size = FRAME_STATE_HEADER_SIZE + len * (DEBUG_VALUE_SLOT_SIZE + 1);
+        let slot = match self.frame_state_slot {
+            Some(slot) => {
+                let data = &mut builder.func.sized_stack_slots[slot];
+                data.size = data.size.max(size);
+                slot
+            }
+            None => {
+                let slot = builder
+                    .func
+                    .create_sized_stack_slot(ir::StackSlotData::new_with_key(
+                        ir::StackSlotKind::ExplicitSlot,
+                        size,
+                        4,
+                        ir::StackSlotKey::new(FRAME_STATE_SLOT_KEY),
+                    ));
+                self.frame_state_slot = Some(slot);
+                slot
+            }
+        };
+
+        let num_stack = stack.stack.len();
+        debug_assert!(kinds.len() >= num_stack);
+        let num_locals = kinds.len() - num_stack;
+        let vmctx = self.vmctx_val(&mut builder.cursor());
+        builder.ins().stack_store(vmctx, slot, 0);
+        let num_locals = builder
+            .ins()
+            .iconst(ir::types::I32, i64::try_from(num_locals).unwrap());
+        let num_locals_offset = i32::try_from(FRAME_STATE_NUM_LOCALS).unwrap();
+        builder
+            .ins()
+            .stack_store(num_locals, slot, num_locals_offset);
+        let num_stack = builder
+            .ins()
+            .iconst(ir::types::I32, i64::try_from(num_stack).unwrap());
+        let num_stack_offset = i32::try_from(FRAME_STATE_NUM_STACK).unwrap();
+        builder.ins().stack_store(num_stack, slot, num_stack_offset);
+        spill_debug_values(builder, stack, kinds, slot, FRAME_STATE_HEADER_SIZE);
+
+        // Tag a sequence point with the slot so that the final location of the
+        // slot, and the first point at which it's initialized, are known after
+        // compilation.
+        let point = builder.ins().sequence_point();
+        builder
+            .func
+            .debug_tags
+            .set(point, [ir::DebugTag::StackSlot(slot)]);
+    }
+
+    /// Loads the base address of this function's code coverage counters and
+    /// counts the entry of the function, whose body starts at `offset`.
+    pub fn coverage_function_entry(&mut self, builder: &mut FunctionBuilder, offset: usize) {
+        let pointer_type = self.pointer_type();
+        let flags = ir::MemFlags::trusted().with_readonly().with_can_move();
+        let vmctx = self.vmctx_val(&mut builder.cursor());
+        let table = builder.ins().load(
+            pointer_type,
+            flags,
+            vmctx,
+            i32::try_from(self.offsets.vmctx_coverage_counters()).unwrap(),
+        );
+        let def_func_index = self
+            .module
+            .defined_func_index(self.current_func_index(builder))
+            .unwrap();
+        let entry = def_func_index.as_u32() * pointer_type.bytes();
+        let counters =
+            builder
+                .ins()
+                .load(pointer_type, flags, table, i32::try_from(entry).unwrap());
+        self.coverage_counters = Some(counters);
+        self.coverage_count(builder, offset, CoverageSiteKind::Block);
+    }
+
+    /// Counts the evaluation of a conditional branch at `offset` before it
+    /// executes.
+    pub fn coverage_before_op(
+        &mut self,
+        op: &Operator,
+        builder: &mut FunctionBuilder,
+        state: &FuncTranslationStacks,
+        offset: usize,
+    ) {
+        if !state.reachable() {
+            return;
+        }
+        match op {
+            Operator::If { .. } => self.coverage_count(builder, offset, CoverageSiteKind::If),
+            Operator::BrIf { .. } => self.coverage_count(builder, offset, CoverageSiteKind::BrIf),
+            _ => {}
+        }
+    }
+
+    /// Counts the entry of the basic block that the operator at `offset`
+    /// starts, if any.
+    ///
+    /// For `if` and `br_if` this counts the `then` arm and the fallthrough
+    /// respectively, which is how the direction of the branch counted by
+    /// `coverage_before_op` is recovered.
+    pub fn coverage_after_op(
+        &mut self,
+        op: &Operator,
+        builder: &mut FunctionBuilder,
+        state: &FuncTranslationStacks,
+        offset: usize,
+    ) {
+        if !state.reachable() {
+            return;
+        }
+        match op {
+            Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Else
+            | Operator::BrIf { .. } => {
+                self.coverage_count(builder, offset, CoverageSiteKind::Block)
+            }
+            // The function's final `end` returns rather than starting a
+            // block.
+            Operator::End if !state.control_stack.is_empty() => {
+                self.coverage_count(builder, offset, CoverageSiteKind::Block)
+            }
+            _ => {}
+        }
+    }
+
+    /// Records a new coverage site and increments its counter.
+    fn coverage_count(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        offset: usize,
+        kind: CoverageSiteKind,
+    ) {
+        let counters = self.coverage_counters.unwrap();
+        let index = self.coverage_sites.len();
+        self.coverage_sites.push(CoverageSite {
+            offset: u32::try_from(offset).unwrap(),
+            kind,
+        });
+        let offset = i32::try_from(index * 8).unwrap();
+        let count = builder
+            .ins()
+            .load(I64, ir::MemFlags::trusted(), counters, offset);
+        let count = builder.ins().iadd_imm(count, 1);
+        builder
+            .ins()
+            .store(ir::MemFlags::trusted(), count, counters, offset);
+    }
+
     pub fn before_unconditionally_trapping_memory_access(&mut self, builder: &mut FunctionBuilder) {
         if self.tunables.consume_fuel {
             self.fuel_increment_var(builder);
@@ -3571,6 +4029,10 @@ impl FuncEnvironment<'_> {
             self.conditionally_trap(builder, overflow, ir::TrapCode::STACK_OVERFLOW);
         }
 
+        if self.tunables.fuel_profiling {
+            self.fuel_profile_enter(builder);
+        }
+
         // Additionally we initialize `fuel_var` if it will get used.
         if self.tunables.consume_fuel {
             self.fuel_function_entry(builder);
@@ -3583,11 +4045,12 @@ impl FuncEnvironment<'_> {
 
         #[cfg(feature = "wmemcheck")]
         if self.compiler.wmemcheck {
-            let func_name = self.current_func_name(builder);
-            if func_name == Some("malloc") {
-                self.check_malloc_start(builder);
-            } else if func_name == Some("free") {
-                self.check_free_start(builder);
+            match self.current_func_name(builder) {
+                Some("malloc" | "calloc" | "realloc" | "aligned_alloc") => {
+                    self.check_malloc_start(builder)
+                }
+                Some("free") => self.check_free_start(builder),
+                _ => {}
             }
         }
 
@@ -3742,13 +4205,18 @@ impl FuncEnvironment<'_> {
     }
 
     pub fn handle_before_return(&mut self, retvals: &[ir::Value], builder: &mut FunctionBuilder) {
+        if self.tunables.fuel_profiling {
+            self.fuel_profile_exit(builder);
+        }
         #[cfg(feature = "wmemcheck")]
         if self.compiler.wmemcheck {
-            let func_name = self.current_func_name(builder);
-            if func_name == Some("malloc") {
-                self.hook_malloc_exit(builder, retvals);
-            } else if func_name == Some("free") {
-                self.hook_free_exit(builder);
+            match self.current_func_name(builder) {
+                Some("malloc") => self.hook_malloc_exit(builder, retvals),
+                Some("calloc") => self.hook_calloc_exit(builder, retvals),
+                Some("realloc") => self.hook_realloc_exit(builder, retvals),
+                Some("aligned_alloc") => self.hook_aligned_alloc_exit(builder, retvals),
+                Some("free") => self.hook_free_exit(builder),
+                _ => {}
             }
         }
         #[cfg(not(feature = "wmemcheck"))]
@@ -3758,43 +4226,66 @@ impl FuncEnvironment<'_> {
     pub fn before_load(
         &mut self,
         builder: &mut FunctionBuilder,
+        memory: MemoryIndex,
         val_size: u8,
         addr: ir::Value,
         offset: u64,
     ) {
         #[cfg(feature = "wmemcheck")]
-        if self.compiler.wmemcheck {
+        if self.compiler.wmemcheck && builder.func.dfg.value_type(addr) == I32 {
+            let (addr_shadow, _) = self.wmemcheck_access_shadows();
+            if self.tunables.wmemcheck_uninit {
+                self.wmemcheck_check(builder, addr_shadow, wasmtime_environ::UninitUse::Address);
+            }
             let check_load = self.builtin_functions.check_load(builder.func);
             let vmctx = self.vmctx_val(&mut builder.cursor());
-            let num_bytes = builder.ins().iconst(I32, val_size as i64);
-            let offset_val = builder.ins().iconst(I64, offset as i64);
-            builder
+            let num_bytes = builder.ins().iconst(I32, i64::from(val_size));
+            let offset_val = builder.ins().iconst(I32, offset as i64);
+            let memory = builder.ins().iconst(I32, i64::from(memory.as_u32()));
+            let call = builder
                 .ins()
-                .call(check_load, &[vmctx, num_bytes, addr, offset_val]);
+                .call(check_load, &[vmctx, num_bytes, addr, offset_val, memory]);
+            if self.tunables.wmemcheck_uninit {
+                let loaded = builder.func.dfg.first_result(call);
+                self.wmemcheck_set_loaded(builder, loaded);
+            }
         }
         #[cfg(not(feature = "wmemcheck"))]
-        let _ = (builder, val_size, addr, offset);
+        let _ = (builder, memory, val_size, addr, offset);
     }
 
     pub fn before_store(
         &mut self,
         builder: &mut FunctionBuilder,
+        memory: MemoryIndex,
         val_size: u8,
         addr: ir::Value,
         offset: u64,
     ) {
         #[cfg(feature = "wmemcheck")]
-        if self.compiler.wmemcheck {
+        if self.compiler.wmemcheck && builder.func.dfg.value_type(addr) == I32 {
+            let (addr_shadow, value_shadow) = self.wmemcheck_access_shadows();
+            if self.tunables.wmemcheck_uninit {
+                self.wmemcheck_check(builder, addr_shadow, wasmtime_environ::UninitUse::Address);
+            }
             let check_store = self.builtin_functions.check_store(builder.func);
             let vmctx = self.vmctx_val(&mut builder.cursor());
-            let num_bytes = builder.ins().iconst(I32, val_size as i64);
-            let offset_val = builder.ins().iconst(I64, offset as i64);
-            builder
-                .ins()
-                .call(check_store, &[vmctx, num_bytes, addr, offset_val]);
+            let num_bytes = builder.ins().iconst(I32, i64::from(val_size));
+            let offset_val = builder.ins().iconst(I32, offset as i64);
+            let memory = builder.ins().iconst(I32, i64::from(memory.as_u32()));
+            let poisoned = match value_shadow {
+                Some(shadow) if self.tunables.wmemcheck_uninit => {
+                    builder.ins().uextend(I32, shadow)
+                }
+                _ => builder.ins().iconst(I32, 0),
+            };
+            builder.ins().call(
+                check_store,
+                &[vmctx, num_bytes, addr, offset_val, memory, poisoned],
+            );
         }
         #[cfg(not(feature = "wmemcheck"))]
-        let _ = (builder, val_size, addr, offset);
+        let _ = (builder, memory, val_size, addr, offset);
     }
 
     pub fn update_global(
@@ -3817,22 +4308,6 @@ impl FuncEnvironment<'_> {
         let _ = (builder, global_index, value);
     }
 
-    pub fn before_memory_grow(
-        &mut self,
-        builder: &mut FunctionBuilder,
-        num_pages: ir::Value,
-        mem_index: MemoryIndex,
-    ) {
-        #[cfg(feature = "wmemcheck")]
-        if self.compiler.wmemcheck && mem_index.as_u32() == 0 {
-            let update_mem_size = self.builtin_functions.update_mem_size(builder.func);
-            let vmctx = self.vmctx_val(&mut builder.cursor());
-            builder.ins().call(update_mem_size, &[vmctx, num_pages]);
-        }
-        #[cfg(not(feature = "wmemcheck"))]
-        let _ = (builder, num_pages, mem_index);
-    }
-
     /// If the ISA has rounding instructions, let Cranelift use them. But if
     /// not, lower to a libcall here, rather than having Cranelift do it. We
     /// can pass our libcall the vmctx pointer, which we use for stack
@@ -4311,3 +4786,37 @@ fn index_type_to_ir_type(index_type: IndexType) -> ir::Type {
         IndexType::I64 => I64,
     }
 }
+
+/// Stores `val` into the `i`th value slot of a debug hook's spill area in
+/// `slot`.
+fn store_debug_value(builder: &mut FunctionBuilder, slot: ir::StackSlot, i: u32, val: ir::Value) {
+    let offset = i32::try_from(i * DEBUG_VALUE_SLOT_SIZE).unwrap();
+    builder.ins().stack_store(val, slot, offset);
+}
+
+/// Stores all locals followed by all operand stack values into `slot`
+/// starting at `base`, in the layout described in
+/// `wasmtime_environ::guest_debug`, where `kinds` describes each value.
+fn spill_debug_values(
+    builder: &mut FunctionBuilder,
+    stack: &FuncTranslationStacks,
+    kinds: &[DebugValueKind],
+    slot: ir::StackSlot,
+    base: u32,
+) {
+    let len = u32::try_from(kinds.len()).unwrap();
+    let num_locals = len - u32::try_from(stack.stack.len()).unwrap();
+    let kinds_offset = base + len * DEBUG_VALUE_SLOT_SIZE;
+    for (i, kind) in kinds.iter().enumerate() {
+        let i = u32::try_from(i).unwrap();
+        let val = match i.checked_sub(num_locals) {
+            None => builder.use_var(Variable::from_u32(i)),
+            Some(depth) => stack.stack[usize::try_from(depth).unwrap()],
+        };
+        let val_offset = i32::try_from(base + i * DEBUG_VALUE_SLOT_SIZE).unwrap();
+        builder.ins().stack_store(val, slot, val_offset);
+        let kind = builder.ins().iconst(ir::types::I8, i64::from(*kind as u8));
+        let kind_offset = i32::try_from(kinds_offset + i).unwrap();
+        builder.ins().stack_store(kind, slot, kind_offset);
+    }
+}
@@ -20,6 +20,8 @@ use wasmtime_environ::{
     WasmRefType, WasmResult, WasmStorageType, WasmValType, wasm_unsupported,
 };
 
+#[cfg(feature = "gc-copying")]
+mod copying;
 #[cfg(feature = "gc-drc")]
 mod drc;
 #[cfg(feature = "gc-null")]
@@ -48,21 +50,29 @@ pub fn gc_compiler(func_env: &mut FuncEnvironment<'_>) -> WasmResult<Box<dyn GcC
              was disabled at compile time",
         )),
 
-        #[cfg(any(feature = "gc-drc", feature = "gc-null"))]
+        #[cfg(feature = "gc-copying")]
+        Some(Collector::Copying) => Ok(Box::new(copying::CopyingCompiler::default())),
+        #[cfg(not(feature = "gc-copying"))]
+        Some(Collector::Copying) => Err(wasm_unsupported!(
+            "the copying collector is unavailable because the `gc-copying` \
+             feature was disabled at compile time",
+        )),
+
+        #[cfg(any(feature = "gc-drc", feature = "gc-null", feature = "gc-copying"))]
         None => Err(wasm_unsupported!(
             "support for GC types disabled at configuration time"
         )),
-        #[cfg(not(any(feature = "gc-drc", feature = "gc-null")))]
+        #[cfg(not(any(feature = "gc-drc", feature = "gc-null", feature = "gc-copying")))]
         None => Err(wasm_unsupported!(
             "support for GC types disabled because no collector implementation \
-             was selected at compile time; enable one of the `gc-drc` or \
-             `gc-null` features",
+             was selected at compile time; enable one of the `gc-drc`, \
+             `gc-null`, or `gc-copying` features",
         )),
     }
 }
 
 #[cfg_attr(
-    not(feature = "gc-drc"),
+    not(any(feature = "gc-drc", feature = "gc-copying")),
     expect(dead_code, reason = "easier to define")
 )]
 fn unbarriered_load_gc_ref(
@@ -80,7 +90,7 @@ fn unbarriered_load_gc_ref(
 }
 
 #[cfg_attr(
-    not(any(feature = "gc-drc", feature = "gc-null")),
+    not(any(feature = "gc-drc", feature = "gc-null", feature = "gc-copying")),
     expect(dead_code, reason = "easier to define")
 )]
 fn unbarriered_store_gc_ref(
@@ -95,6 +105,37 @@ fn unbarriered_store_gc_ref(
     Ok(())
 }
 
+/// Emit CLIF to call the `gc_raw_alloc` libcall.
+#[cfg(any(feature = "gc-drc", feature = "gc-copying"))]
+fn emit_gc_raw_alloc(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    kind: VMGcKind,
+    ty: ModuleInternedTypeIndex,
+    size: ir::Value,
+    align: u32,
+) -> ir::Value {
+    let gc_alloc_raw_builtin = func_env.builtin_functions.gc_alloc_raw(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+
+    let kind = builder
+        .ins()
+        .iconst(ir::types::I32, i64::from(kind.as_u32()));
+
+    let ty = builder.ins().iconst(ir::types::I32, i64::from(ty.as_u32()));
+
+    assert!(align.is_power_of_two());
+    let align = builder.ins().iconst(ir::types::I32, i64::from(align));
+
+    let call_inst = builder
+        .ins()
+        .call(gc_alloc_raw_builtin, &[vmctx, kind, ty, size, align]);
+
+    let gc_ref = builder.func.dfg.first_result(call_inst);
+    builder.declare_value_needs_stack_map(gc_ref);
+    gc_ref
+}
+
 /// Emit code to read a struct field or array element from its raw address in
 /// the GC heap.
 ///
@@ -584,7 +625,7 @@ pub fn translate_array_new_fixed(
 impl ArrayInit<'_> {
     /// Get the length (as an `i32`-typed `ir::Value`) of these array elements.
     #[cfg_attr(
-        not(any(feature = "gc-drc", feature = "gc-null")),
+        not(any(feature = "gc-drc", feature = "gc-null", feature = "gc-copying")),
         expect(dead_code, reason = "easier to define")
     )]
     fn len(self, pos: &mut FuncCursor) -> ir::Value {
@@ -599,7 +640,7 @@ impl ArrayInit<'_> {
 
     /// Initialize a newly-allocated array's elements.
     #[cfg_attr(
-        not(any(feature = "gc-drc", feature = "gc-null")),
+        not(any(feature = "gc-drc", feature = "gc-null", feature = "gc-copying")),
         expect(dead_code, reason = "easier to define")
     )]
     fn initialize(
@@ -1270,7 +1311,7 @@ fn uextend_i32_to_pointer_type(
 ///
 /// Traps if the size overflows.
 #[cfg_attr(
-    not(any(feature = "gc-drc", feature = "gc-null")),
+    not(any(feature = "gc-drc", feature = "gc-null", feature = "gc-copying")),
     expect(dead_code, reason = "easier to define")
 )]
 fn emit_array_size(
@@ -1318,7 +1359,7 @@ fn emit_array_size(
 /// Common helper for struct-field initialization that can be reused across
 /// collectors.
 #[cfg_attr(
-    not(any(feature = "gc-drc", feature = "gc-null")),
+    not(any(feature = "gc-drc", feature = "gc-null", feature = "gc-copying")),
     expect(dead_code, reason = "easier to define")
 )]
 fn initialize_struct_fields(
@@ -1418,7 +1459,7 @@ impl FuncEnvironment<'_> {
     }
 
     /// Get the GC heap's base.
-    #[cfg(any(feature = "gc-null", feature = "gc-drc"))]
+    #[cfg(any(feature = "gc-null", feature = "gc-drc", feature = "gc-copying"))]
     fn get_gc_heap_base(&mut self, builder: &mut FunctionBuilder) -> ir::Value {
         let global = self.get_gc_heap_base_global(&mut builder.func);
         builder.ins().global_value(self.pointer_type(), global)
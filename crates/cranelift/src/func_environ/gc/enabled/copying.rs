@@ -0,0 +1,227 @@
+//! Compiler for the copying collector.
+//!
+//! The copying collector moves objects during collection, so every GC
+//! reference that is live across a safepoint must be in a stack map, where the
+//! collector can find and update it. It does not require any read or write
+//! barriers beyond that. Allocation always goes through the `gc_alloc_raw`
+//! libcall, which may trigger a collection.
+
+use super::*;
+use crate::func_environ::FuncEnvironment;
+use cranelift_codegen::ir::{self, InstBuilder};
+use cranelift_frontend::FunctionBuilder;
+use wasmtime_environ::copying::{EXCEPTION_TAG_DEFINED_OFFSET, EXCEPTION_TAG_INSTANCE_OFFSET};
+use wasmtime_environ::{
+    GcTypeLayouts, TypeIndex, VMGcKind, WasmRefType, WasmResult, copying::CopyingTypeLayouts,
+};
+
+#[derive(Default)]
+pub struct CopyingCompiler {
+    layouts: CopyingTypeLayouts,
+}
+
+impl CopyingCompiler {
+    /// Allocate a struct or exception object of the given type via the
+    /// `gc_alloc_raw` libcall and initialize its fields.
+    ///
+    /// Returns the new object's GC reference and a raw pointer to it.
+    fn alloc_struct_or_exn(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder<'_>,
+        kind: VMGcKind,
+        interned_type_index: ModuleInternedTypeIndex,
+        field_vals: &[ir::Value],
+    ) -> WasmResult<(ir::Value, ir::Value)> {
+        let layout = func_env.struct_or_exn_layout(interned_type_index);
+
+        // Copy some stuff out of the layout to avoid borrowing issues.
+        let size = layout.size;
+        let align = layout.align;
+
+        let size = builder.ins().iconst(ir::types::I32, i64::from(size));
+        let gc_ref = emit_gc_raw_alloc(func_env, builder, kind, interned_type_index, size, align);
+
+        // Initialize the object's fields.
+        //
+        // Note: we don't need to bounds-check the GC ref access here, since we
+        // trust the results of the allocation libcall, and there are no
+        // safepoints between the allocation and the initialization at which
+        // the object could move.
+        let base = func_env.get_gc_heap_base(builder);
+        let extended_gc_ref = uextend_i32_to_pointer_type(builder, func_env.pointer_type(), gc_ref);
+        let raw_ptr = builder.ins().iadd(base, extended_gc_ref);
+        initialize_struct_fields(
+            func_env,
+            builder,
+            interned_type_index,
+            raw_ptr,
+            field_vals,
+            |func_env, builder, ty, field_addr, val| {
+                write_field_at_addr(func_env, builder, ty, field_addr, val)
+            },
+        )?;
+
+        Ok((gc_ref, raw_ptr))
+    }
+}
+
+impl GcCompiler for CopyingCompiler {
+    fn layouts(&self) -> &dyn GcTypeLayouts {
+        &self.layouts
+    }
+
+    fn alloc_array(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder<'_>,
+        array_type_index: TypeIndex,
+        init: super::ArrayInit<'_>,
+    ) -> WasmResult<ir::Value> {
+        let interned_type_index =
+            func_env.module.types[array_type_index].unwrap_module_type_index();
+        let ptr_ty = func_env.pointer_type();
+
+        let len_offset = gc_compiler(func_env)?.layouts().array_length_field_offset();
+        let array_layout = func_env.array_layout(interned_type_index).clone();
+        let base_size = array_layout.base_size;
+        let align = array_layout.align;
+        let len_to_elems_delta = base_size.checked_sub(len_offset).unwrap();
+
+        // First, compute the array's total size from its base size, element
+        // size, and length.
+        let len = init.len(&mut builder.cursor());
+        let size = emit_array_size(func_env, builder, &array_layout, len);
+
+        // Second, allocate the array.
+        let array_ref = emit_gc_raw_alloc(
+            func_env,
+            builder,
+            VMGcKind::ArrayRef,
+            interned_type_index,
+            size,
+            align,
+        );
+
+        // Write the array's length into its field.
+        //
+        // Note: we don't need to bounds-check the GC ref access here, since we
+        // trust the results of the allocation libcall.
+        let base = func_env.get_gc_heap_base(builder);
+        let extended_array_ref =
+            uextend_i32_to_pointer_type(builder, func_env.pointer_type(), array_ref);
+        let object_addr = builder.ins().iadd(base, extended_array_ref);
+        let len_addr = builder.ins().iadd_imm(object_addr, i64::from(len_offset));
+        let len = init.len(&mut builder.cursor());
+        builder
+            .ins()
+            .store(ir::MemFlags::trusted(), len, len_addr, 0);
+
+        // Finally, initialize the elements.
+        let len_to_elems_delta = builder.ins().iconst(ptr_ty, i64::from(len_to_elems_delta));
+        let elems_addr = builder.ins().iadd(len_addr, len_to_elems_delta);
+        init.initialize(
+            func_env,
+            builder,
+            interned_type_index,
+            base_size,
+            size,
+            elems_addr,
+            |func_env, builder, elem_ty, elem_addr, val| {
+                write_field_at_addr(func_env, builder, elem_ty, elem_addr, val)
+            },
+        )?;
+
+        Ok(array_ref)
+    }
+
+    fn alloc_struct(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder<'_>,
+        struct_type_index: TypeIndex,
+        field_vals: &[ir::Value],
+    ) -> WasmResult<ir::Value> {
+        let interned_type_index =
+            func_env.module.types[struct_type_index].unwrap_module_type_index();
+        let (struct_ref, _) = self.alloc_struct_or_exn(
+            func_env,
+            builder,
+            VMGcKind::StructRef,
+            interned_type_index,
+            field_vals,
+        )?;
+        Ok(struct_ref)
+    }
+
+    fn alloc_exn(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        field_vals: &[ir::Value],
+        instance_id: ir::Value,
+        tag: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        let interned_type_index = func_env.module.tags[tag_index]
+            .exception
+            .unwrap_module_type_index();
+        let (exn_ref, raw_exn_pointer) = self.alloc_struct_or_exn(
+            func_env,
+            builder,
+            VMGcKind::ExnRef,
+            interned_type_index,
+            field_vals,
+        )?;
+
+        // Initialize the tag fields.
+        let instance_id_addr = builder
+            .ins()
+            .iadd_imm(raw_exn_pointer, i64::from(EXCEPTION_TAG_INSTANCE_OFFSET));
+        write_field_at_addr(
+            func_env,
+            builder,
+            WasmStorageType::Val(WasmValType::I32),
+            instance_id_addr,
+            instance_id,
+        )?;
+        let tag_addr = builder
+            .ins()
+            .iadd_imm(raw_exn_pointer, i64::from(EXCEPTION_TAG_DEFINED_OFFSET));
+        write_field_at_addr(
+            func_env,
+            builder,
+            WasmStorageType::Val(WasmValType::I32),
+            tag_addr,
+            tag,
+        )?;
+
+        Ok(exn_ref)
+    }
+
+    fn translate_read_gc_reference(
+        &mut self,
+        _func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder,
+        ty: WasmRefType,
+        src: ir::Value,
+        flags: ir::MemFlags,
+    ) -> WasmResult<ir::Value> {
+        // NB: unlike the null collector, this must mark the loaded reference
+        // as requiring inclusion in stack maps, since its referent may be
+        // moved by a collection.
+        unbarriered_load_gc_ref(builder, ty.heap_type, src, flags)
+    }
+
+    fn translate_write_gc_reference(
+        &mut self,
+        _func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder,
+        ty: WasmRefType,
+        dst: ir::Value,
+        new_val: ir::Value,
+        flags: ir::MemFlags,
+    ) -> WasmResult<()> {
+        unbarriered_store_gc_ref(builder, ty.heap_type, dst, new_val, flags)
+    }
+}
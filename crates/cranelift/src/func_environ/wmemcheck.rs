@@ -0,0 +1,423 @@
+//! Tracking of values computed from uninitialized memory, for wmemcheck's
+//! uninitialized-memory mode.
+//!
+//! Every wasm value gets a shadow: an `i8` that is nonzero if the value was
+//! computed from uninitialized memory. Loads get their shadow from the
+//! `check_load` builtin, most other instructions combine the shadows of their
+//! operands, and stores pass the shadow of the stored value to `check_store`
+//! so that it's kept in memory. Shadows are only checked, through the
+//! `report_uninit_use` builtin, where a value affects control flow or leaves
+//! the guest, as described by `UninitUse`.
+//!
+//! Shadows of locals, and of values passed to branch targets, live in
+//! Cranelift variables so that they're merged at control flow joins.
+
+use crate::func_environ::FuncEnvironment;
+use crate::translate::FuncTranslationStacks;
+use cranelift_codegen::ir::{self, InstBuilder, types::*};
+use cranelift_frontend::{FunctionBuilder, Variable};
+use wasmparser::Operator;
+use wasmtime_environ::{FuncIndex, UninitUse, WasmValType};
+
+/// The shadows of the function being translated.
+#[derive(Default)]
+pub(crate) struct Shadows {
+    /// The shadow of each value on the operand stack, or `None` if the value
+    /// is known to be initialized.
+    stack: Vec<Option<ir::Value>>,
+
+    /// The variable holding the shadow of each local, created on first use.
+    locals: Vec<Option<Variable>>,
+
+    /// One entry per frame of the translator's control stack.
+    frames: Vec<Frame>,
+
+    /// The shadows of the operands of the instruction being translated.
+    operands: Vec<Option<ir::Value>>,
+
+    /// The shadow of the value loaded by the instruction being translated.
+    loaded: Option<ir::Value>,
+
+    /// Variables holding the shadows of the parameters of the `if` or `loop`
+    /// being translated.
+    params: Vec<Variable>,
+
+    /// Whether the instruction being translated is reachable.
+    reachable: bool,
+}
+
+/// The shadows passed to a control frame's branch target and to its end.
+struct Frame {
+    /// The shadows of the values passed by branches to this frame, which are
+    /// its parameters if it's a loop and its results otherwise.
+    branch: Vec<Variable>,
+    /// The shadows of this frame's results.
+    end: Vec<Variable>,
+}
+
+impl FuncEnvironment<'_> {
+    /// Propagates shadows through, and checks the operands of, `op` before
+    /// it's translated.
+    pub(crate) fn wmemcheck_before_op(
+        &mut self,
+        op: &Operator,
+        operand_types: Option<&[WasmValType]>,
+        builder: &mut FunctionBuilder,
+        state: &FuncTranslationStacks,
+    ) {
+        self.wmemcheck_sync_frames(builder, state);
+        let shadows = &mut self.wmemcheck_shadows;
+        shadows.reachable = state.reachable();
+        shadows.operands.clear();
+        shadows.loaded = None;
+        if !state.reachable() {
+            return;
+        }
+
+        let height = shadows.stack.len();
+        debug_assert_eq!(height, state.stack.len());
+        let num_operands = operand_types.map_or(0, |tys| tys.len()).min(height);
+        shadows
+            .operands
+            .extend_from_slice(&shadows.stack[height - num_operands..]);
+        let operands = shadows.operands.clone();
+
+        match *op {
+            Operator::If { .. } => {
+                let (cond, params) = operands.split_last().unwrap();
+                self.wmemcheck_check(builder, *cond, UninitUse::Branch);
+                // Without an `else` the parameters are passed straight to the
+                // `if`'s results, so define them in the head.
+                self.wmemcheck_shadows.params = self.wmemcheck_new_vars(builder, params);
+            }
+            Operator::Loop { .. } => {
+                self.wmemcheck_shadows.params = self.wmemcheck_new_vars(builder, &operands);
+            }
+            Operator::Br { relative_depth } => {
+                self.wmemcheck_def_branch(builder, relative_depth, &operands);
+            }
+            Operator::BrIf { relative_depth } => {
+                let (cond, args) = operands.split_last().unwrap();
+                self.wmemcheck_check(builder, *cond, UninitUse::Branch);
+                self.wmemcheck_def_branch(builder, relative_depth, args);
+            }
+            Operator::BrTable { ref targets } => {
+                let (index, args) = operands.split_last().unwrap();
+                self.wmemcheck_check(builder, *index, UninitUse::Branch);
+                let mut depths = targets
+                    .targets()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap_or_default();
+                depths.push(targets.default());
+                depths.sort_unstable();
+                depths.dedup();
+                for depth in depths {
+                    self.wmemcheck_def_branch(builder, depth, args);
+                }
+            }
+            Operator::BrOnNull { relative_depth }
+            | Operator::BrOnNonNull { relative_depth }
+            | Operator::BrOnCast { relative_depth, .. }
+            | Operator::BrOnCastFail { relative_depth, .. } => {
+                // References never come from linear memory.
+                let frame = self.wmemcheck_frame(relative_depth);
+                let defined = vec![None; frame.branch.len()];
+                self.wmemcheck_def_branch(builder, relative_depth, &defined);
+            }
+            Operator::Else | Operator::End => {
+                let frame = self.wmemcheck_shadows.frames.last().unwrap();
+                let vars = frame.end.clone();
+                let stack = &self.wmemcheck_shadows.stack;
+                let results = stack[stack.len().saturating_sub(vars.len())..].to_vec();
+                self.wmemcheck_def_vars(builder, &vars, &results);
+            }
+            Operator::Call { function_index } | Operator::ReturnCall { function_index }
+                if self
+                    .module
+                    .is_imported_function(FuncIndex::from_u32(function_index)) =>
+            {
+                for arg in operands {
+                    self.wmemcheck_check(builder, arg, UninitUse::HostCall);
+                }
+            }
+            Operator::CallIndirect { .. } | Operator::ReturnCallIndirect { .. } => {
+                let index = *operands.last().unwrap();
+                self.wmemcheck_check(builder, index, UninitUse::Address);
+            }
+            Operator::MemoryCopy { .. } | Operator::MemoryInit { .. } => {
+                for operand in operands {
+                    self.wmemcheck_check(builder, operand, UninitUse::Address);
+                }
+            }
+            Operator::MemoryFill { .. } => {
+                self.wmemcheck_check(builder, operands[0], UninitUse::Address);
+                self.wmemcheck_check(builder, operands[2], UninitUse::Address);
+            }
+            _ => {}
+        }
+    }
+
+    /// Records the shadows of the values that `op` pushed after it's
+    /// translated.
+    pub(crate) fn wmemcheck_after_op(
+        &mut self,
+        op: &Operator,
+        builder: &mut FunctionBuilder,
+        state: &FuncTranslationStacks,
+    ) {
+        let new_height = state.stack.len();
+        let shadows = &mut self.wmemcheck_shadows;
+        if !shadows.reachable {
+            // In unreachable code only the end of a frame changes the operand
+            // stack. Its results are reachable only through branches.
+            if let Operator::End = op {
+                let frame = shadows.frames.pop().unwrap();
+                let num_results = frame.end.len().min(new_height);
+                shadows.stack.truncate(new_height - num_results);
+                for var in frame.end.iter().take(num_results) {
+                    let shadow = state.reachable().then(|| builder.use_var(*var));
+                    shadows.stack.push(shadow);
+                }
+            }
+            shadows.stack.resize(new_height, None);
+            self.wmemcheck_sync_frames(builder, state);
+            return;
+        }
+
+        let num_operands = shadows.operands.len();
+        let base = shadows.stack.len() - num_operands;
+        match *op {
+            Operator::Block { .. } | Operator::TryTable { .. } => {
+                let frame = state.control_stack.last().unwrap();
+                let vars = self.wmemcheck_new_vars(builder, &vec![None; frame.num_return_values()]);
+                self.wmemcheck_shadows.frames.push(Frame {
+                    branch: vars.clone(),
+                    end: vars,
+                });
+                return;
+            }
+            Operator::If { .. } => {
+                let frame = state.control_stack.last().unwrap();
+                let num_results = frame.num_return_values();
+                let params = core::mem::take(&mut self.wmemcheck_shadows.params);
+                let vars = if params.len() == num_results {
+                    params
+                } else {
+                    self.wmemcheck_new_vars(builder, &vec![None; num_results])
+                };
+                let shadows = &mut self.wmemcheck_shadows;
+                shadows.frames.push(Frame {
+                    branch: vars.clone(),
+                    end: vars,
+                });
+                // The translator pushes a second copy of the parameters for
+                // the `else` block.
+                shadows.stack.pop();
+                let params = shadows.stack[base..].to_vec();
+                shadows.stack.extend(params);
+                return;
+            }
+            Operator::Loop { .. } => {
+                let frame = state.control_stack.last().unwrap();
+                let params = core::mem::take(&mut self.wmemcheck_shadows.params);
+                let end = self.wmemcheck_new_vars(builder, &vec![None; frame.num_return_values()]);
+                let shadows = &mut self.wmemcheck_shadows;
+                shadows.stack.truncate(base);
+                for var in &params {
+                    shadows.stack.push(Some(builder.use_var(*var)));
+                }
+                shadows.frames.push(Frame {
+                    branch: params,
+                    end,
+                });
+                return;
+            }
+            Operator::Else => {
+                shadows.stack.truncate(new_height);
+                return;
+            }
+            Operator::End => {
+                let frame = shadows.frames.pop().unwrap();
+                let num_results = frame.end.len().min(new_height);
+                shadows.stack.truncate(new_height - num_results);
+                for var in frame.end.iter().take(num_results) {
+                    let shadow = state.reachable().then(|| builder.use_var(*var));
+                    shadows.stack.push(shadow);
+                }
+                return;
+            }
+            Operator::LocalGet { local_index } => {
+                let var = self.wmemcheck_local(builder, local_index);
+                let shadow = builder.use_var(var);
+                self.wmemcheck_shadows.stack.push(Some(shadow));
+                return;
+            }
+            Operator::LocalSet { local_index } | Operator::LocalTee { local_index } => {
+                let var = self.wmemcheck_local(builder, local_index);
+                let shadow = self.wmemcheck_shadows.operands[0];
+                let shadow = self.wmemcheck_materialize(builder, shadow);
+                builder.def_var(var, shadow);
+            }
+            Operator::Call { .. } | Operator::CallIndirect { .. } | Operator::CallRef { .. } => {
+                // Shadows aren't tracked through calls.
+                shadows.operands.clear();
+            }
+            _ => {}
+        }
+
+        // Everything else replaces its operands with results computed from
+        // them, or from the memory that it loaded.
+        let shadows = &mut self.wmemcheck_shadows;
+        let keep = base.min(new_height);
+        shadows.stack.truncate(keep);
+        let result = match shadows.loaded.take() {
+            Some(loaded) => Some(loaded),
+            // Nothing can be emitted after an instruction that ends the block.
+            None if !state.reachable() => None,
+            None => {
+                let operands = core::mem::take(&mut shadows.operands);
+                operands
+                    .into_iter()
+                    .flatten()
+                    .reduce(|a, b| builder.ins().bor(a, b))
+            }
+        };
+        self.wmemcheck_shadows.stack.resize(new_height, result);
+    }
+
+    /// Returns the shadows of the address and, for stores, of the stored
+    /// value of the memory access being translated.
+    pub(crate) fn wmemcheck_access_shadows(&self) -> (Option<ir::Value>, Option<ir::Value>) {
+        let operands = &self.wmemcheck_shadows.operands;
+        (
+            operands.first().copied().flatten(),
+            operands.get(1).copied().flatten(),
+        )
+    }
+
+    /// Records the shadow of the value being loaded, as returned by
+    /// `check_load`.
+    pub(crate) fn wmemcheck_set_loaded(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        loaded: ir::Value,
+    ) {
+        let loaded = builder.ins().ireduce(I8, loaded);
+        self.wmemcheck_shadows.loaded = Some(loaded);
+    }
+
+    /// Reports a use of uninitialized memory if `shadow` is set.
+    pub(crate) fn wmemcheck_check(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        shadow: Option<ir::Value>,
+        kind: UninitUse,
+    ) {
+        let Some(shadow) = shadow else {
+            return;
+        };
+        let report_block = builder.create_block();
+        let continuation_block = builder.create_block();
+        builder.set_cold_block(report_block);
+        builder
+            .ins()
+            .brif(shadow, report_block, &[], continuation_block, &[]);
+        builder.seal_block(report_block);
+
+        builder.switch_to_block(report_block);
+        let report_uninit_use = self.builtin_functions.report_uninit_use(builder.func);
+        let vmctx = self.vmctx_val(&mut builder.cursor());
+        let kind = builder.ins().iconst(I32, kind as i64);
+        builder.ins().call(report_uninit_use, &[vmctx, kind]);
+        builder.ins().jump(continuation_block, &[]);
+        builder.seal_block(continuation_block);
+
+        builder.switch_to_block(continuation_block);
+    }
+
+    /// Returns an `i8` holding `shadow`.
+    fn wmemcheck_materialize(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        shadow: Option<ir::Value>,
+    ) -> ir::Value {
+        shadow.unwrap_or_else(|| builder.ins().iconst(I8, 0))
+    }
+
+    /// Returns the variable holding the shadow of `local_index`.
+    fn wmemcheck_local(&mut self, builder: &mut FunctionBuilder, local_index: u32) -> Variable {
+        let index = usize::try_from(local_index).unwrap();
+        let locals = &mut self.wmemcheck_shadows.locals;
+        if locals.len() <= index {
+            locals.resize(index + 1, None);
+        }
+        // Variables which are used before being defined read as zero, so
+        // parameters and locals start out initialized.
+        *locals[index].get_or_insert_with(|| builder.declare_var(I8))
+    }
+
+    /// Creates variables defined as `shadows`.
+    fn wmemcheck_new_vars(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        shadows: &[Option<ir::Value>],
+    ) -> Vec<Variable> {
+        let vars = shadows
+            .iter()
+            .map(|_| builder.declare_var(I8))
+            .collect::<Vec<_>>();
+        self.wmemcheck_def_vars(builder, &vars, shadows);
+        vars
+    }
+
+    fn wmemcheck_def_vars(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        vars: &[Variable],
+        shadows: &[Option<ir::Value>],
+    ) {
+        for (var, shadow) in vars.iter().zip(shadows) {
+            let shadow = self.wmemcheck_materialize(builder, *shadow);
+            builder.def_var(*var, shadow);
+        }
+    }
+
+    /// Defines the shadows passed to the branch target `relative_depth` as
+    /// the last of `args`.
+    fn wmemcheck_def_branch(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        relative_depth: u32,
+        args: &[Option<ir::Value>],
+    ) {
+        let vars = self.wmemcheck_frame(relative_depth).branch.clone();
+        let args = &args[args.len().saturating_sub(vars.len())..];
+        self.wmemcheck_def_vars(builder, &vars, args);
+    }
+
+    fn wmemcheck_frame(&self, relative_depth: u32) -> &Frame {
+        let frames = &self.wmemcheck_shadows.frames;
+        &frames[frames.len() - 1 - usize::try_from(relative_depth).unwrap()]
+    }
+
+    /// Adds frames for control frames that the translator pushed without
+    /// `wmemcheck_after_op` seeing them: the function body, and frames in
+    /// unreachable code.
+    fn wmemcheck_sync_frames(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        state: &FuncTranslationStacks,
+    ) {
+        let frames = &mut self.wmemcheck_shadows.frames;
+        for frame in &state.control_stack[frames.len()..] {
+            let mut new_vars = |n| (0..n).map(|_| builder.declare_var(I8)).collect::<Vec<_>>();
+            let end = new_vars(frame.num_return_values());
+            let branch = if frame.is_loop() {
+                new_vars(frame.num_param_values())
+            } else {
+                end.clone()
+            };
+            frames.push(Frame { branch, end });
+        }
+    }
+}
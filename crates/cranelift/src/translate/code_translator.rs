@@ -787,7 +787,6 @@ pub fn translate_operator(
             let mem = MemoryIndex::from_u32(*mem);
             let _heap = environ.get_or_create_heap(builder.func, mem);
             let val = stack.pop1();
-            environ.before_memory_grow(builder, val, mem);
             stack.push1(environ.translate_memory_grow(builder, mem, val)?)
         }
         Operator::MemorySize { mem } => {
@@ -3570,7 +3569,13 @@ fn translate_load(
             Reachability::Reachable((f, i, b)) => (f, i, b),
         };
 
-    environ.before_load(builder, mem_op_size, wasm_index, memarg.offset);
+    environ.before_load(
+        builder,
+        MemoryIndex::from_u32(memarg.memory),
+        mem_op_size,
+        wasm_index,
+        memarg.offset,
+    );
 
     let (load, dfg) = builder
         .ins()
@@ -3596,7 +3601,13 @@ fn translate_store(
         prepare_addr(memarg, mem_op_size, builder, stack, environ)?
     );
 
-    environ.before_store(builder, mem_op_size, wasm_index, memarg.offset);
+    environ.before_store(
+        builder,
+        MemoryIndex::from_u32(memarg.memory),
+        mem_op_size,
+        wasm_index,
+        memarg.offset,
+    );
 
     builder
         .ins()
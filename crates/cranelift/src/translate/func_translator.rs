@@ -14,7 +14,7 @@ use cranelift_codegen::ir::{self, Block, InstBuilder, ValueLabel};
 use cranelift_codegen::timing;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
 use wasmparser::{BinaryReader, FuncValidator, FunctionBody, OperatorsReader, WasmModuleResources};
-use wasmtime_environ::{TypeConvert, WasmResult};
+use wasmtime_environ::{DebugValueKind, TypeConvert, WasmResult, records_frame_state};
 
 /// WebAssembly to Cranelift IR function translator.
 ///
@@ -240,22 +240,63 @@ fn parse_function_body(
     // The control stack is initialized with a single block representing the whole function.
     debug_assert_eq!(stack.control_stack.len(), 1, "State not initialized");
 
+    let mut debug_kinds = vec![];
+
+    // Record the initial state of the frame for core dumps before anything
+    // else, such as fuel or epoch checks, has a chance to trap.
+    let frame_state = environ.tunables().debug_frame_state;
+    if frame_state {
+        debug_value_kinds(validator, environ, 0, &mut debug_kinds)?;
+        environ.record_frame_state(builder, stack, &debug_kinds);
+    }
+
     environ.before_translate_function(builder, stack)?;
 
+    let coverage = environ.tunables().coverage;
+    if coverage {
+        environ.coverage_function_entry(builder, reader.original_position());
+    }
+
     let mut reader = OperatorsReader::new(reader);
     let mut operand_types = vec![];
+    let mut entry = true;
 
     while !reader.eof() {
         let pos = reader.original_position();
         builder.set_srcloc(cur_srcloc(&reader.get_binary_reader()));
 
         let op = reader.read()?;
+
+        // Guest debugging needs the types of all locals and operand stack
+        // values as they are *before* this operator executes, so collect them
+        // prior to validating the operator.
+        let hook = environ.tunables().debug_instrumentation;
+        let record = frame_state && records_frame_state(&op);
+        let debug = (hook || record) && stack.reachable();
+        if debug {
+            debug_value_kinds(validator, environ, stack.stack.len(), &mut debug_kinds)?;
+        }
+
         let operand_types =
             validate_op_and_get_operand_types(validator, environ, &mut operand_types, &op, pos)?;
 
+        if debug && hook {
+            environ.debug_hook(builder, stack, pos, entry, &debug_kinds);
+        }
+        if debug && record {
+            environ.record_frame_state(builder, stack, &debug_kinds);
+        }
+        entry = false;
+
+        if coverage {
+            environ.coverage_before_op(&op, builder, stack, pos);
+        }
         environ.before_translate_operator(&op, operand_types, builder, stack)?;
         translate_operator(validator, &op, operand_types, builder, stack, environ)?;
         environ.after_translate_operator(&op, operand_types, builder, stack)?;
+        if coverage {
+            environ.coverage_after_op(&op, builder, stack, pos);
+        }
     }
     environ.after_translate_function(builder, stack)?;
     reader.finish()?;
@@ -314,6 +355,44 @@ fn validate_op_and_get_operand_types<'a>(
     Ok(operand_types)
 }
 
+/// Fills `kinds` with the kinds of all locals followed by all operand stack
+/// values at the validator's current position.
+///
+/// In reachable code the validator's operand stack lines up with the
+/// `stack_len` values tracked during translation, and the types of all of its
+/// values are known.
+fn debug_value_kinds(
+    validator: &FuncValidator<impl WasmModuleResources>,
+    environ: &FuncEnvironment<'_>,
+    stack_len: usize,
+    kinds: &mut Vec<DebugValueKind>,
+) -> WasmResult<()> {
+    kinds.clear();
+    for i in 0..validator.len_locals() {
+        let ty = validator
+            .get_local_type(i)
+            .expect("local index should be in bounds");
+        kinds.push(DebugValueKind::from_wasm_type(
+            &environ.convert_valtype(ty)?,
+        ));
+    }
+    let height = usize::try_from(validator.operand_stack_height()).unwrap();
+    assert_eq!(
+        height, stack_len,
+        "validator and translation operand stacks should have the same height"
+    );
+    for depth in (0..height).rev() {
+        let ty = validator
+            .get_operand_type(depth)
+            .flatten()
+            .expect("operand types should be known in reachable code");
+        kinds.push(DebugValueKind::from_wasm_type(
+            &environ.convert_valtype(ty)?,
+        ));
+    }
+    Ok(())
+}
+
 /// Get the current source location from a reader.
 fn cur_srcloc(reader: &BinaryReader) -> ir::SourceLoc {
     // We record source locations as byte code offsets relative to the beginning of the file.
@@ -39,6 +39,18 @@ macro_rules! foreach_builtin_function {
             memory_atomic_wait64(vmctx: vmctx, memory: u32, addr: u64, expected: u64, timeout: u64) -> u64;
             // Invoked when fuel has run out while executing a function.
             out_of_gas(vmctx: vmctx) -> bool;
+            // Invoked before a wasm instruction when the store's debugger
+            // wants to observe execution. `entry` is nonzero for the first
+            // instruction of a function. `values` points to `num_locals`
+            // locals followed by `num_stack` operand stack values, each in a
+            // 16-byte slot, and `kinds` to one `DebugValueKind` byte per value.
+            debug_hook(vmctx: vmctx, func: u32, offset: u32, entry: u32, values: pointer, kinds: pointer, num_locals: u32, num_stack: u32) -> bool;
+            // Invoked on entry to a function when fuel profiling is enabled,
+            // with the function's index and frame pointer.
+            fuel_profile_enter(vmctx: vmctx, func: u32, fp: pointer);
+            // Invoked before a function with the frame pointer `fp` returns
+            // when fuel profiling is enabled.
+            fuel_profile_exit(vmctx: vmctx, fp: pointer);
             // Invoked when we reach a new epoch.
             #[cfg(target_has_atomic = "64")]
             new_epoch(vmctx: vmctx) -> u64;
@@ -48,12 +60,25 @@ macro_rules! foreach_builtin_function {
             // Invoked before the free returns.
             #[cfg(feature = "wmemcheck")]
             check_free(vmctx: vmctx, addr: u32) -> bool;
-            // Invoked before a load is executed.
+            // Invoked before calloc returns.
             #[cfg(feature = "wmemcheck")]
-            check_load(vmctx: vmctx, num_bytes: u32, addr: u32, offset: u32) -> bool;
-            // Invoked before a store is executed.
+            check_calloc(vmctx: vmctx, addr: u32, count: u32, size: u32) -> bool;
+            // Invoked before realloc returns.
             #[cfg(feature = "wmemcheck")]
-            check_store(vmctx: vmctx, num_bytes: u32, addr: u32, offset: u32) -> bool;
+            check_realloc(vmctx: vmctx, addr: u32, old_addr: u32, len: u32) -> bool;
+            // Invoked before a load from `memory` is executed. Returns whether
+            // any loaded byte is uninitialized, in wmemcheck's
+            // uninitialized-memory mode.
+            #[cfg(feature = "wmemcheck")]
+            check_load(vmctx: vmctx, num_bytes: u32, addr: u32, offset: u32, memory: u32) -> u64;
+            // Invoked before a store to `memory` is executed. `poisoned` is
+            // whether the stored value was computed from uninitialized memory.
+            #[cfg(feature = "wmemcheck")]
+            check_store(vmctx: vmctx, num_bytes: u32, addr: u32, offset: u32, memory: u32, poisoned: u32) -> bool;
+            // Invoked when a value computed from uninitialized memory is used
+            // in the way described by `UninitUse`.
+            #[cfg(feature = "wmemcheck")]
+            report_uninit_use(vmctx: vmctx, kind: u32) -> bool;
             // Invoked after malloc is called.
             #[cfg(feature = "wmemcheck")]
             malloc_start(vmctx: vmctx);
@@ -62,10 +87,7 @@ macro_rules! foreach_builtin_function {
             free_start(vmctx: vmctx);
             // Invoked when wasm stack pointer is updated.
             #[cfg(feature = "wmemcheck")]
-            update_stack_pointer(vmctx: vmctx, value: u32);
-            // Invoked before memory.grow is called.
-            #[cfg(feature = "wmemcheck")]
-            update_mem_size(vmctx: vmctx, num_bytes: u32);
+            update_stack_pointer(vmctx: vmctx, value: u32) -> bool;
 
             // Drop a non-stack GC reference (eg an overwritten table entry)
             // once it will no longer be used again. (Note: `val` is not of type
@@ -82,7 +104,7 @@ macro_rules! foreach_builtin_function {
 
             // Allocate a new, uninitialized GC object and return a reference to
             // it.
-            #[cfg(feature = "gc-drc")]
+            #[cfg(any(feature = "gc-drc", feature = "gc-copying"))]
             gc_alloc_raw(
                 vmctx: vmctx,
                 kind: u32,
@@ -422,6 +444,9 @@ impl BuiltinFunctionIndex {
             // The final epoch represents a trap
             (@get new_epoch u64) => (TrapSentinel::NegativeOne);
 
+            // Whether the loaded bytes are initialized, or -1 for a trap.
+            (@get check_load u64) => (TrapSentinel::NegativeOne);
+
             // These libcalls can't trap
             (@get ref_func pointer) => (return None);
             (@get table_get_lazy_init_func_ref pointer) => (return None);
@@ -0,0 +1,173 @@
+use crate::CoverageSite;
+use crate::obj::ELF_WASMTIME_COVERAGE;
+use crate::prelude::*;
+use core::ops::Range;
+use object::write::{Object, StandardSegment};
+use object::{LittleEndian, SectionKind, U32Bytes};
+
+/// Builder for the `ELF_WASMTIME_COVERAGE` section in compiled executables.
+///
+/// This format is parsed by `crate::coverage`.
+///
+/// The current layout of the format is:
+///
+/// ```text
+/// ┌──────────────────────────┬───── 0x00 (relative, not necessarily aligned)
+/// │ count: 4-byte LE         │
+/// ├──────────────────────────┼───── 0x04
+/// │ start1: 4-byte LE        │
+/// │ ...                      │
+/// │ startN: 4-byte LE        │
+/// ├──────────────────────────┼───── 0x04 + 4 * count
+/// │ end1: 4-byte LE          │
+/// │ ...                      │
+/// │ endN: 4-byte LE          │
+/// ├──────────────────────────┼───── 0x04 + 8 * count
+/// │ body_end1: 4-byte LE     │
+/// │ ...                      │
+/// │ body_endN: 4-byte LE     │
+/// ├──────────────────────────┼───── 0x04 + 12 * count
+/// │ offset1: 4-byte LE       │
+/// │ ...                      │
+/// │ offsetM: 4-byte LE       │
+/// ├──────────────────────────┼───── 0x04 + 12 * count + 4 * M
+/// │ kind1: 1-byte            │
+/// │ ...                      │
+/// │ kindM: 1-byte            │
+/// └──────────────────────────┴───── 0x04 + 12 * count + 5 * M
+/// ```
+///
+/// Each of the `count` entries describes one function starting at `start` in
+/// the text section, whose sites are those from the previous entry's `end`
+/// (or zero) up to its own `end` in the `offset` and `kind` arrays. `M` is
+/// the `end` of the last entry. The `body_end` of an entry is the offset in
+/// the original wasm binary where the function's body ends. A lookup performs
+/// a binary search on the `start` array.
+#[derive(Default)]
+pub struct CoverageSection {
+    starts: Vec<U32Bytes<LittleEndian>>,
+    ends: Vec<U32Bytes<LittleEndian>>,
+    body_ends: Vec<U32Bytes<LittleEndian>>,
+    offsets: Vec<U32Bytes<LittleEndian>>,
+    kinds: Vec<u8>,
+}
+
+impl CoverageSection {
+    /// Appends the coverage `sites` of the function occupying `range` of the
+    /// text section, whose body ends at `body_end` in the original wasm
+    /// binary.
+    pub fn push(&mut self, range: Range<u64>, body_end: u32, sites: &[CoverageSite]) {
+        if sites.is_empty() {
+            return;
+        }
+
+        // NB: for now this only supports <=4GB text sections in object files.
+        let start = u32::try_from(range.start).unwrap();
+
+        // Sanity-check to ensure that functions are pushed in-order, otherwise
+        // the `starts` array won't be sorted which is our goal.
+        assert!(
+            self.starts
+                .last()
+                .map_or(true, |s| s.get(LittleEndian) < start)
+        );
+
+        for site in sites {
+            self.offsets.push(U32Bytes::new(LittleEndian, site.offset));
+            self.kinds.push(site.kind as u8);
+        }
+        let end = u32::try_from(self.offsets.len()).unwrap();
+        self.starts.push(U32Bytes::new(LittleEndian, start));
+        self.ends.push(U32Bytes::new(LittleEndian, end));
+        self.body_ends.push(U32Bytes::new(LittleEndian, body_end));
+    }
+
+    /// Finishes encoding this section into the `Object` provided.
+    pub fn append_to(self, obj: &mut Object) {
+        if self.starts.is_empty() {
+            return;
+        }
+        let section = obj.add_section(
+            obj.segment_name(StandardSegment::Data).to_vec(),
+            ELF_WASMTIME_COVERAGE.as_bytes().to_vec(),
+            SectionKind::ReadOnlyData,
+        );
+
+        // NB: this matches the encoding expected by `lookup` in the
+        // `crate::coverage` module.
+        let amt = u32::try_from(self.starts.len()).unwrap();
+        obj.append_section_data(section, &amt.to_le_bytes(), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.starts), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.ends), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.body_ends), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.offsets), 1);
+        obj.append_section_data(section, &self.kinds, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CoverageSiteKind, CoverageSites};
+    use object::{Object, ObjectSection};
+
+    #[test]
+    fn roundtrip() {
+        let block = |offset| CoverageSite {
+            offset,
+            kind: CoverageSiteKind::Block,
+        };
+        let mut section = CoverageSection::default();
+        section.push(
+            0..100,
+            20,
+            &[
+                block(10),
+                CoverageSite {
+                    offset: 14,
+                    kind: CoverageSiteKind::BrIf,
+                },
+                block(14),
+            ],
+        );
+        section.push(100..110, 20, &[]);
+        section.push(120..200, 40, &[block(30)]);
+        let mut object = object::write::Object::new(
+            object::BinaryFormat::Elf,
+            object::Architecture::X86_64,
+            object::Endianness::Little,
+        );
+        section.append_to(&mut object);
+        let elf = object.write().unwrap();
+
+        let image = object::File::parse(&elf[..]).unwrap();
+        let data = image
+            .sections()
+            .find(|s| s.name().ok() == Some(ELF_WASMTIME_COVERAGE))
+            .unwrap()
+            .data()
+            .unwrap();
+
+        let lookup = |start| {
+            CoverageSites::lookup(start, data)
+                .map(|sites| (sites.body_end(), sites.iter().collect::<Vec<_>>()))
+        };
+        assert_eq!(
+            lookup(0),
+            Some((
+                20,
+                vec![
+                    block(10),
+                    CoverageSite {
+                        offset: 14,
+                        kind: CoverageSiteKind::BrIf,
+                    },
+                    block(14),
+                ]
+            ))
+        );
+        assert_eq!(lookup(50), None);
+        assert_eq!(lookup(100), None);
+        assert_eq!(lookup(120), Some((40, vec![block(30)])));
+    }
+}
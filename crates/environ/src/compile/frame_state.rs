@@ -0,0 +1,279 @@
+use crate::obj::ELF_WASMTIME_FRAME_STATE;
+use crate::prelude::*;
+use core::ops::Range;
+use object::write::{Object, StandardSegment};
+use object::{LittleEndian, SectionKind, U32Bytes};
+use wasmparser::Operator;
+
+/// Builder for the `ELF_WASMTIME_FRAME_STATE` section in compiled executables.
+///
+/// This format is parsed by `crate::frame_state`.
+///
+/// The current layout of the format is:
+///
+/// ```text
+/// ┌──────────────────────────┬───── 0x00 (relative, not necessarily aligned)
+/// │ count: 4-byte LE         │
+/// ├──────────────────────────┼───── 0x04
+/// │ start1: 4-byte LE        │
+/// │ ...                      │
+/// │ startN: 4-byte LE        │
+/// ├──────────────────────────┼───── 0x04 + 4 * count
+/// │ end1: 4-byte LE          │
+/// │ ...                      │
+/// │ endN: 4-byte LE          │
+/// ├──────────────────────────┼───── 0x04 + 8 * count
+/// │ fp_offset1: 4-byte LE    │
+/// │ ...                      │
+/// │ fp_offsetN: 4-byte LE    │
+/// └──────────────────────────┴───── 0x04 + 12 * count
+/// ```
+///
+/// Each entry describes one function: the slot at `fp_offset` bytes below the
+/// frame pointer holds valid state for pcs within `start..end`, where `start`
+/// is the first write of the slot and `end` is the end of the function. A
+/// lookup performs a binary search on the `start` array.
+#[derive(Default)]
+pub struct FrameStateSection {
+    starts: Vec<U32Bytes<LittleEndian>>,
+    ends: Vec<U32Bytes<LittleEndian>>,
+    fp_offsets: Vec<U32Bytes<LittleEndian>>,
+}
+
+impl FrameStateSection {
+    /// Appends the frame-state slot of a function which is valid for the
+    /// `range` of the text section, located `fp_offset` bytes below the frame
+    /// pointer.
+    pub fn push(&mut self, range: Range<u64>, fp_offset: u32) {
+        // NB: for now this only supports <=4GB text sections in object files.
+        let start = u32::try_from(range.start).unwrap();
+        let end = u32::try_from(range.end).unwrap();
+
+        // Sanity-check to ensure that functions are pushed in-order, otherwise
+        // the `starts` array won't be sorted which is our goal.
+        assert!(
+            self.ends
+                .last()
+                .map_or(true, |e| e.get(LittleEndian) <= start)
+        );
+
+        self.starts.push(U32Bytes::new(LittleEndian, start));
+        self.ends.push(U32Bytes::new(LittleEndian, end));
+        self.fp_offsets.push(U32Bytes::new(LittleEndian, fp_offset));
+    }
+
+    /// Finishes encoding this section into the `Object` provided.
+    pub fn append_to(self, obj: &mut Object) {
+        if self.starts.is_empty() {
+            return;
+        }
+        let section = obj.add_section(
+            obj.segment_name(StandardSegment::Data).to_vec(),
+            ELF_WASMTIME_FRAME_STATE.as_bytes().to_vec(),
+            SectionKind::ReadOnlyData,
+        );
+
+        // NB: this matches the encoding expected by `lookup` in the
+        // `crate::frame_state` module.
+        let amt = u32::try_from(self.starts.len()).unwrap();
+        obj.append_section_data(section, &amt.to_le_bytes(), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.starts), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.ends), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.fp_offsets), 1);
+    }
+}
+
+/// Returns whether `op` may trap or call another function, in which case the
+/// state of the frame is recorded before it for core dumps.
+///
+/// This errs on the side of returning `true` and only excludes operators which
+/// are known to neither trap nor call, such as control flow within the
+/// function, local and global accesses, and non-trapping arithmetic.
+pub fn records_frame_state(op: &Operator) -> bool {
+    !matches!(
+        op,
+        Operator::Nop
+            | Operator::Block { .. }
+            | Operator::If { .. }
+            | Operator::Else
+            | Operator::End
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return
+            | Operator::Drop
+            | Operator::Select
+            | Operator::TypedSelect { .. }
+            | Operator::LocalGet { .. }
+            | Operator::LocalSet { .. }
+            | Operator::LocalTee { .. }
+            | Operator::GlobalGet { .. }
+            | Operator::GlobalSet { .. }
+            | Operator::I32Const { .. }
+            | Operator::I64Const { .. }
+            | Operator::F32Const { .. }
+            | Operator::F64Const { .. }
+            | Operator::V128Const { .. }
+            | Operator::RefNull { .. }
+            | Operator::RefIsNull
+            | Operator::I32Eqz
+            | Operator::I32Eq
+            | Operator::I32Ne
+            | Operator::I32LtS
+            | Operator::I32LtU
+            | Operator::I32GtS
+            | Operator::I32GtU
+            | Operator::I32LeS
+            | Operator::I32LeU
+            | Operator::I32GeS
+            | Operator::I32GeU
+            | Operator::I64Eqz
+            | Operator::I64Eq
+            | Operator::I64Ne
+            | Operator::I64LtS
+            | Operator::I64LtU
+            | Operator::I64GtS
+            | Operator::I64GtU
+            | Operator::I64LeS
+            | Operator::I64LeU
+            | Operator::I64GeS
+            | Operator::I64GeU
+            | Operator::F32Eq
+            | Operator::F32Ne
+            | Operator::F32Lt
+            | Operator::F32Gt
+            | Operator::F32Le
+            | Operator::F32Ge
+            | Operator::F64Eq
+            | Operator::F64Ne
+            | Operator::F64Lt
+            | Operator::F64Gt
+            | Operator::F64Le
+            | Operator::F64Ge
+            | Operator::I32Clz
+            | Operator::I32Ctz
+            | Operator::I32Popcnt
+            | Operator::I32Add
+            | Operator::I32Sub
+            | Operator::I32Mul
+            | Operator::I32And
+            | Operator::I32Or
+            | Operator::I32Xor
+            | Operator::I32Shl
+            | Operator::I32ShrS
+            | Operator::I32ShrU
+            | Operator::I32Rotl
+            | Operator::I32Rotr
+            | Operator::I64Clz
+            | Operator::I64Ctz
+            | Operator::I64Popcnt
+            | Operator::I64Add
+            | Operator::I64Sub
+            | Operator::I64Mul
+            | Operator::I64And
+            | Operator::I64Or
+            | Operator::I64Xor
+            | Operator::I64Shl
+            | Operator::I64ShrS
+            | Operator::I64ShrU
+            | Operator::I64Rotl
+            | Operator::I64Rotr
+            | Operator::F32Abs
+            | Operator::F32Neg
+            | Operator::F32Ceil
+            | Operator::F32Floor
+            | Operator::F32Trunc
+            | Operator::F32Nearest
+            | Operator::F32Sqrt
+            | Operator::F32Add
+            | Operator::F32Sub
+            | Operator::F32Mul
+            | Operator::F32Div
+            | Operator::F32Min
+            | Operator::F32Max
+            | Operator::F32Copysign
+            | Operator::F64Abs
+            | Operator::F64Neg
+            | Operator::F64Ceil
+            | Operator::F64Floor
+            | Operator::F64Trunc
+            | Operator::F64Nearest
+            | Operator::F64Sqrt
+            | Operator::F64Add
+            | Operator::F64Sub
+            | Operator::F64Mul
+            | Operator::F64Div
+            | Operator::F64Min
+            | Operator::F64Max
+            | Operator::F64Copysign
+            | Operator::I32WrapI64
+            | Operator::I64ExtendI32S
+            | Operator::I64ExtendI32U
+            | Operator::F32ConvertI32S
+            | Operator::F32ConvertI32U
+            | Operator::F32ConvertI64S
+            | Operator::F32ConvertI64U
+            | Operator::F32DemoteF64
+            | Operator::F64ConvertI32S
+            | Operator::F64ConvertI32U
+            | Operator::F64ConvertI64S
+            | Operator::F64ConvertI64U
+            | Operator::F64PromoteF32
+            | Operator::I32ReinterpretF32
+            | Operator::I64ReinterpretF64
+            | Operator::F32ReinterpretI32
+            | Operator::F64ReinterpretI64
+            | Operator::I32Extend8S
+            | Operator::I32Extend16S
+            | Operator::I64Extend8S
+            | Operator::I64Extend16S
+            | Operator::I64Extend32S
+            | Operator::I32TruncSatF32S
+            | Operator::I32TruncSatF32U
+            | Operator::I32TruncSatF64S
+            | Operator::I32TruncSatF64U
+            | Operator::I64TruncSatF32S
+            | Operator::I64TruncSatF32U
+            | Operator::I64TruncSatF64S
+            | Operator::I64TruncSatF64U
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_state::FrameStateSlot;
+    use object::{Object, ObjectSection};
+
+    #[test]
+    fn roundtrip() {
+        let mut section = FrameStateSection::default();
+        section.push(4..100, 16);
+        section.push(120..200, 48);
+        let mut object = object::write::Object::new(
+            object::BinaryFormat::Elf,
+            object::Architecture::X86_64,
+            object::Endianness::Little,
+        );
+        section.append_to(&mut object);
+        let elf = object.write().unwrap();
+
+        let image = object::File::parse(&elf[..]).unwrap();
+        let data = image
+            .sections()
+            .find(|s| s.name().ok() == Some(ELF_WASMTIME_FRAME_STATE))
+            .unwrap()
+            .data()
+            .unwrap();
+
+        let lookup = |pc| FrameStateSlot::lookup(pc, data).map(|s| s.fp_offset());
+        assert_eq!(lookup(0), None);
+        assert_eq!(lookup(4), Some(16));
+        assert_eq!(lookup(99), Some(16));
+        assert_eq!(lookup(100), None);
+        assert_eq!(lookup(119), None);
+        assert_eq!(lookup(120), Some(48));
+        assert_eq!(lookup(199), Some(48));
+        assert_eq!(lookup(200), None);
+    }
+}
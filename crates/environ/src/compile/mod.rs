@@ -16,6 +16,8 @@ use std::path;
 use std::sync::Arc;
 
 mod address_map;
+mod coverage;
+mod frame_state;
 mod module_artifacts;
 mod module_environ;
 mod module_types;
@@ -23,6 +25,8 @@ mod stack_maps;
 mod trap_encoding;
 
 pub use self::address_map::*;
+pub use self::coverage::*;
+pub use self::frame_state::*;
 pub use self::module_artifacts::*;
 pub use self::module_environ::*;
 pub use self::module_types::*;
@@ -86,6 +86,13 @@ pub struct ComponentDfg {
     /// phase (this is not edited after creation).
     pub num_runtime_component_instances: u32,
 
+    /// The component instance which created each instance in `instances`.
+    pub instance_owners: PrimaryMap<InstanceId, RuntimeComponentInstanceIndex>,
+
+    /// Same as `Component::runtime_component_instances`
+    pub runtime_component_instances:
+        PrimaryMap<RuntimeComponentInstanceIndex, RuntimeComponentInstance>,
+
     /// Known adapter modules and how they are instantiated.
     ///
     /// This map is not filled in on the initial creation of a `ComponentDfg`.
@@ -619,6 +626,17 @@ impl ComponentDfg {
             exports.insert(name, &mut NameMapNoIntern, false, export)?;
         }
 
+        // Record which component instance created each core instance now that
+        // they've all been assigned a `RuntimeInstanceIndex`. Adapter modules
+        // are synthesized by Wasmtime and aren't owned by any component.
+        let mut runtime_instance_owners = vec![None; linearize.runtime_instances.len()];
+        for (instance, index) in linearize.runtime_instances.iter() {
+            runtime_instance_owners[index.index()] = match instance {
+                RuntimeInstance::Normal(id) => Some(self.instance_owners[*id]),
+                RuntimeInstance::Adapter(_) => None,
+            };
+        }
+
         // With all those pieces done the results of the dataflow-based
         // linearization are recorded into the `Component`. The number of
         // runtime values used for each index space is used from the `linearize`
@@ -642,6 +660,8 @@ impl ComponentDfg {
                 imports: self.imports,
                 import_types: self.import_types,
                 num_runtime_component_instances: self.num_runtime_component_instances,
+                runtime_instance_owners: runtime_instance_owners.into_iter().collect(),
+                runtime_component_instances: self.runtime_component_instances,
                 num_future_tables: self.num_future_tables,
                 num_stream_tables: self.num_stream_tables,
                 num_error_context_tables: self.num_error_context_tables,
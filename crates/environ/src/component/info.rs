@@ -133,6 +133,16 @@ pub struct Component {
     /// instead.
     pub num_runtime_component_instances: u32,
 
+    /// The component instance which created each runtime instance, or `None`
+    /// for adapter modules synthesized by Wasmtime.
+    pub runtime_instance_owners:
+        PrimaryMap<RuntimeInstanceIndex, Option<RuntimeComponentInstanceIndex>>,
+
+    /// Metadata about each component instance created when instantiating
+    /// this component, the first of which is the root component itself.
+    pub runtime_component_instances:
+        PrimaryMap<RuntimeComponentInstanceIndex, RuntimeComponentInstance>,
+
     /// The number of runtime memories (maximum `RuntimeMemoryIndex`) needed to
     /// instantiate this component.
     ///
@@ -219,6 +229,19 @@ impl Component {
     }
 }
 
+/// Metadata about a component instance created when instantiating a
+/// [`Component`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeComponentInstance {
+    /// The name of the instantiated component from its `component-name` custom
+    /// section, if present.
+    pub name: Option<String>,
+
+    /// The component instance which instantiated this one, or `None` for the
+    /// root component.
+    pub parent: Option<RuntimeComponentInstanceIndex>,
+}
+
 /// GlobalInitializer instructions to get processed when instantiating a
 /// component.
 ///
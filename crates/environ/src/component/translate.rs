@@ -19,7 +19,10 @@ use wasmparser::component_types::{
     ComponentFuncTypeId, ComponentInstanceTypeId, ComponentValType,
 };
 use wasmparser::types::Types;
-use wasmparser::{Chunk, ComponentImportName, Encoding, Parser, Payload, Validator};
+use wasmparser::{
+    Chunk, ComponentImportName, ComponentNameSectionReader, Encoding, KnownCustom, Parser, Payload,
+    Validator,
+};
 
 mod adapt;
 pub use self::adapt::*;
@@ -165,6 +168,10 @@ struct Translation<'data> {
     /// component has finished, e.g. for the `inline` pass, but beforehand this
     /// is set to `None`.
     types: Option<Types>,
+
+    /// The name of this component from its `component-name` custom section,
+    /// if present.
+    name: Option<&'data str>,
 }
 
 // NB: the type information contained in `LocalInitializer` should always point
@@ -625,6 +632,21 @@ impl<'a, 'data> Translator<'a, 'data> {
         }
     }
 
+    fn component_name_section(&mut self, names: ComponentNameSectionReader<'data>) {
+        for name in names {
+            match name {
+                Ok(wasmparser::ComponentName::Component { name, .. }) => {
+                    self.result.name = Some(name)
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("failed to parse component name section {e:?}");
+                    break;
+                }
+            }
+        }
+    }
+
     fn translate_payload(
         &mut self,
         payload: Payload<'data>,
@@ -1275,11 +1297,14 @@ impl<'a, 'data> Translator<'a, 'data> {
                 }
             }
 
-            // All custom sections are ignored by Wasmtime at this time.
-            //
-            // FIXME(WebAssembly/component-model#14): probably want to specify
-            // and parse a `name` section here.
-            Payload::CustomSection { .. } => {}
+            // Only the names of components are recorded, to describe them in
+            // diagnostics such as core dumps. All other custom sections are
+            // ignored by Wasmtime at this time.
+            Payload::CustomSection(section) => {
+                if let KnownCustom::ComponentName(names) = section.as_known() {
+                    self.component_name_section(names);
+                }
+            }
 
             // Anything else is either not reachable since we never enable the
             // feature in Wasmtime or we do enable it and it's a bug we don't
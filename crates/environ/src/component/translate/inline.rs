@@ -123,6 +123,13 @@ pub(super) fn run(
     // the root frame which are then used for recording the exports of the
     // component.
     inliner.result.num_runtime_component_instances += 1;
+    inliner
+        .result
+        .runtime_component_instances
+        .push(RuntimeComponentInstance {
+            name: result.name.map(|name| name.to_string()),
+            parent: None,
+        });
     let frame = InlinerFrame::new(index, result, ComponentClosure::default(), args, None);
     let resources_snapshot = types.resources_mut().clone();
     let mut frames = vec![(frame, resources_snapshot)];
@@ -1161,6 +1168,8 @@ impl<'a> Inliner<'a> {
                 let instance = self.result.instances.push(init);
                 let instance2 = self.runtime_instances.push(instance_module);
                 assert_eq!(instance, instance2);
+                let instance3 = self.result.instance_owners.push(frame.instance);
+                assert_eq!(instance, instance3);
 
                 self.result
                     .side_effects
@@ -1215,6 +1224,16 @@ impl<'a> Inliner<'a> {
                     self.result.num_runtime_component_instances,
                 );
                 self.result.num_runtime_component_instances += 1;
+                let index2 =
+                    self.result
+                        .runtime_component_instances
+                        .push(RuntimeComponentInstance {
+                            name: self.nested_components[component.index]
+                                .name
+                                .map(|name| name.to_string()),
+                            parent: Some(frame.instance),
+                        });
+                assert_eq!(index, index2);
                 let frame = InlinerFrame::new(
                     index,
                     &self.nested_components[component.index],
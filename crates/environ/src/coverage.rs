@@ -0,0 +1,124 @@
+//! Counter sites used to collect code coverage of wasm functions.
+//!
+//! When `Tunables::coverage` is enabled each compiled function increments a
+//! 64-bit counter at every site listed here. The counters of a function are
+//! contiguous and in the same order as its sites, and the `VMContext` of an
+//! instance points to a table, indexed by `DefinedFuncIndex`, of pointers to
+//! the first counter of each function.
+//!
+//! The sites of each function are recorded in the `ELF_WASMTIME_COVERAGE`
+//! section, built by `CoverageSection` in the `compile::coverage` module.
+
+use object::{Bytes, LittleEndian, U32Bytes};
+
+/// The reason a counter was placed at a [`CoverageSite`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CoverageSiteKind {
+    /// The start of a basic block, such as the entry of a function or the
+    /// code following a `loop`, `else` or `end`.
+    Block = 0,
+    /// An `if` instruction was evaluated. The next site of the function
+    /// counts how often its `then` arm was entered.
+    If = 1,
+    /// A `br_if` instruction was evaluated. The next site of the function
+    /// counts how often the branch was not taken.
+    BrIf = 2,
+}
+
+impl CoverageSiteKind {
+    fn from_u8(kind: u8) -> Option<CoverageSiteKind> {
+        match kind {
+            0 => Some(CoverageSiteKind::Block),
+            1 => Some(CoverageSiteKind::If),
+            2 => Some(CoverageSiteKind::BrIf),
+            _ => None,
+        }
+    }
+}
+
+/// A location in a wasm function where a coverage counter is incremented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoverageSite {
+    /// The offset, within the original wasm binary, of the instruction this
+    /// site is associated with.
+    pub offset: u32,
+    /// What this site counts.
+    pub kind: CoverageSiteKind,
+}
+
+/// The coverage sites of a single function.
+#[derive(Debug, Clone, Copy)]
+pub struct CoverageSites<'a> {
+    body_end: u32,
+    offsets: &'a [U32Bytes<LittleEndian>],
+    kinds: &'a [u8],
+}
+
+impl<'a> CoverageSites<'a> {
+    /// Looks up the coverage sites of the function starting at `func_start`
+    /// within the `section` provided.
+    ///
+    /// The `section` should be produced by `CoverageSection` in the
+    /// `compile::coverage` module. The `func_start` should be relative to the
+    /// start of the `.text` section in the final executable. Returns `None`
+    /// if the function isn't instrumented.
+    pub fn lookup(func_start: u32, section: &'a [u8]) -> Option<CoverageSites<'a>> {
+        let mut section = Bytes(section);
+        // NB: this matches the encoding written by `append_to` in the
+        // `compile::coverage` module.
+        let count = section.read::<U32Bytes<LittleEndian>>().ok()?;
+        let count = usize::try_from(count.get(LittleEndian)).ok()?;
+        let (starts, section) =
+            object::slice_from_bytes::<U32Bytes<LittleEndian>>(section.0, count).ok()?;
+        let (ends, section) =
+            object::slice_from_bytes::<U32Bytes<LittleEndian>>(section, count).ok()?;
+        let (body_ends, section) =
+            object::slice_from_bytes::<U32Bytes<LittleEndian>>(section, count).ok()?;
+        let num_sites = usize::try_from(ends.last()?.get(LittleEndian)).ok()?;
+        let (offsets, section) =
+            object::slice_from_bytes::<U32Bytes<LittleEndian>>(section, num_sites).ok()?;
+        let kinds = section.get(..num_sites)?;
+
+        let index = starts
+            .binary_search_by_key(&func_start, |v| v.get(LittleEndian))
+            .ok()?;
+        let start = match index {
+            0 => 0,
+            i => usize::try_from(ends[i - 1].get(LittleEndian)).ok()?,
+        };
+        let end = usize::try_from(ends[index].get(LittleEndian)).ok()?;
+        Some(CoverageSites {
+            body_end: body_ends[index].get(LittleEndian),
+            offsets: offsets.get(start..end)?,
+            kinds: kinds.get(start..end)?,
+        })
+    }
+
+    /// Returns the offset, within the original wasm binary, of the end of this
+    /// function's body.
+    pub fn body_end(&self) -> u32 {
+        self.body_end
+    }
+
+    /// Returns the number of sites, and therefore counters, of this function.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns whether this function has no sites.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Returns the sites of this function in counter order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = CoverageSite> + 'a {
+        self.offsets
+            .iter()
+            .zip(self.kinds)
+            .map(|(offset, kind)| CoverageSite {
+                offset: offset.get(LittleEndian),
+                kind: CoverageSiteKind::from_u8(*kind).unwrap(),
+            })
+    }
+}
@@ -0,0 +1,78 @@
+//! Frame-state slots used to recover wasm locals and operand stack values in
+//! core dumps.
+//!
+//! When `Tunables::debug_frame_state` is enabled each compiled function
+//! reserves a single stack slot in its frame and, before any instruction which
+//! may trap or call, writes the current values of its locals and operand stack
+//! into it. The slot starts with a `FRAME_STATE_HEADER_SIZE`-byte header
+//! holding the instance's `VMContext` pointer followed by the number of locals
+//! and operand stack values as 32-bit integers at `FRAME_STATE_NUM_LOCALS` and
+//! `FRAME_STATE_NUM_STACK`. The values follow the header in the same layout as
+//! the spill area of the `debug_hook` builtin, described in `guest_debug.rs`.
+//!
+//! The location of each function's slot relative to its frame pointer is
+//! recorded in the `ELF_WASMTIME_FRAME_STATE` section, built by
+//! `FrameStateSection` in the `compile::frame_state` module.
+
+use object::{Bytes, LittleEndian, U32Bytes};
+
+/// Size, in bytes, of the header at the start of a frame-state slot.
+pub const FRAME_STATE_HEADER_SIZE: u32 = 16;
+
+/// Offset of the number of locals within a frame-state slot.
+pub const FRAME_STATE_NUM_LOCALS: u32 = 8;
+
+/// Offset of the number of operand stack values within a frame-state slot.
+pub const FRAME_STATE_NUM_STACK: u32 = 12;
+
+/// The `StackSlotKey` given to frame-state slots so they can be found in the
+/// compiled function's frame layout.
+pub const FRAME_STATE_SLOT_KEY: u64 = u64::from_le_bytes(*b"wasmfram");
+
+/// The location of a function's frame-state slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStateSlot {
+    fp_offset: u32,
+}
+
+impl FrameStateSlot {
+    /// Looks up the frame-state slot of the function containing `pc` within
+    /// the `section` provided.
+    ///
+    /// The `section` should be produced by `FrameStateSection` in the
+    /// `compile::frame_state` module. The `pc` should be relative to the start
+    /// of the `.text` section in the final executable. Returns `None` if the
+    /// function has no slot or if `pc` precedes the first write of the slot,
+    /// for example in the function's prologue.
+    pub fn lookup(pc: u32, section: &[u8]) -> Option<FrameStateSlot> {
+        let mut section = Bytes(section);
+        // NB: this matches the encoding written by `append_to` in the
+        // `compile::frame_state` module.
+        let count = section.read::<U32Bytes<LittleEndian>>().ok()?;
+        let count = usize::try_from(count.get(LittleEndian)).ok()?;
+        let (starts, section) =
+            object::slice_from_bytes::<U32Bytes<LittleEndian>>(section.0, count).ok()?;
+        let (ends, section) =
+            object::slice_from_bytes::<U32Bytes<LittleEndian>>(section, count).ok()?;
+        let (fp_offsets, _) =
+            object::slice_from_bytes::<U32Bytes<LittleEndian>>(section, count).ok()?;
+
+        let index = match starts.binary_search_by_key(&pc, |v| v.get(LittleEndian)) {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        if pc >= ends[index].get(LittleEndian) {
+            return None;
+        }
+        Some(FrameStateSlot {
+            fp_offset: fp_offsets[index].get(LittleEndian),
+        })
+    }
+
+    /// Returns the distance, in bytes, from the frame pointer down to the
+    /// start of the slot.
+    pub fn fp_offset(&self) -> u32 {
+        self.fp_offset
+    }
+}
@@ -0,0 +1,79 @@
+//! Definitions shared between compiled code and the runtime for guest
+//! debugging instrumentation.
+//!
+//! When `Tunables::debug_instrumentation` is enabled compiled code calls the
+//! `debug_hook` builtin before each wasm instruction, passing a pointer to a
+//! spill area containing the function's locals followed by its operand stack,
+//! and a pointer to one `DebugValueKind` byte per value describing how to
+//! interpret it. Each value occupies a slot of `DEBUG_VALUE_SLOT_SIZE` bytes.
+//!
+//! Core dumps record values in the same slots, but in a single area where the
+//! kind bytes follow all of the value slots, see `frame_state`.
+
+use crate::{WasmHeapTopType, WasmValType};
+
+/// Size, in bytes, of each value slot in a debug hook's spill area.
+pub const DEBUG_VALUE_SLOT_SIZE: u32 = 16;
+
+/// The kind of a value spilled for the `debug_hook` builtin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+#[expect(missing_docs, reason = "self-describing variants")]
+pub enum DebugValueKind {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    FuncRef,
+    ExternRef,
+    AnyRef,
+    ExnRef,
+    ContRef,
+}
+
+impl DebugValueKind {
+    /// Returns the kind used to spill values of type `ty`.
+    pub fn from_wasm_type(ty: &WasmValType) -> DebugValueKind {
+        match ty {
+            WasmValType::I32 => DebugValueKind::I32,
+            WasmValType::I64 => DebugValueKind::I64,
+            WasmValType::F32 => DebugValueKind::F32,
+            WasmValType::F64 => DebugValueKind::F64,
+            WasmValType::V128 => DebugValueKind::V128,
+            WasmValType::Ref(r) => match r.heap_type.top() {
+                WasmHeapTopType::Func => DebugValueKind::FuncRef,
+                WasmHeapTopType::Extern => DebugValueKind::ExternRef,
+                WasmHeapTopType::Any => DebugValueKind::AnyRef,
+                WasmHeapTopType::Exn => DebugValueKind::ExnRef,
+                WasmHeapTopType::Cont => DebugValueKind::ContRef,
+            },
+        }
+    }
+
+    /// Returns whether values of this kind are references to GC-managed
+    /// objects, which may be moved by collections.
+    pub fn is_gc_ref(&self) -> bool {
+        matches!(
+            self,
+            DebugValueKind::ExternRef | DebugValueKind::AnyRef | DebugValueKind::ExnRef
+        )
+    }
+
+    /// Decodes a kind byte previously produced with `DebugValueKind as u8`.
+    pub fn from_u8(byte: u8) -> Option<DebugValueKind> {
+        Some(match byte {
+            0 => DebugValueKind::I32,
+            1 => DebugValueKind::I64,
+            2 => DebugValueKind::F32,
+            3 => DebugValueKind::F64,
+            4 => DebugValueKind::V128,
+            5 => DebugValueKind::FuncRef,
+            6 => DebugValueKind::ExternRef,
+            7 => DebugValueKind::AnyRef,
+            8 => DebugValueKind::ExnRef,
+            9 => DebugValueKind::ContRef,
+            _ => return None,
+        })
+    }
+}
@@ -21,10 +21,13 @@ pub mod prelude;
 mod address_map;
 #[macro_use]
 mod builtin;
+mod coverage;
 mod demangling;
 mod error;
 mod ext;
+mod frame_state;
 mod gc;
+mod guest_debug;
 mod hostcall;
 mod key;
 mod module;
@@ -39,13 +42,17 @@ mod trap_encoding;
 mod tunables;
 mod types;
 mod vmoffsets;
+mod wmemcheck;
 
 pub use self::ext::*;
 pub use crate::address_map::*;
 pub use crate::builtin::*;
+pub use crate::coverage::*;
 pub use crate::demangling::*;
 pub use crate::error::*;
+pub use crate::frame_state::*;
 pub use crate::gc::*;
+pub use crate::guest_debug::*;
 pub use crate::hostcall::*;
 pub use crate::key::*;
 pub use crate::module::*;
@@ -59,6 +66,7 @@ pub use crate::trap_encoding::*;
 pub use crate::tunables::*;
 pub use crate::types::*;
 pub use crate::vmoffsets::*;
+pub use crate::wmemcheck::*;
 pub use object;
 
 pub use wasmparser;
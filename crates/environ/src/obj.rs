@@ -71,6 +71,24 @@ pub const ELF_WASMTIME_ADDRMAP: &str = ".wasmtime.addrmap";
 /// >=4gb text sections.
 pub const ELF_WASMTIME_STACK_MAP: &str = ".wasmtime.stackmap";
 
+/// A custom Wasmtime-specific section of compilation which stores where each
+/// function's frame-state slot lives in its stack frame.
+///
+/// This section is only present when `Tunables::debug_frame_state` is enabled
+/// and has a custom binary encoding described in `frame_state.rs`. Like the
+/// stack map section it has an alignment of 1 with unaligned reads and doesn't
+/// support >=4gb text sections.
+pub const ELF_WASMTIME_FRAME_STATE: &str = ".wasmtime.framestate";
+
+/// A custom Wasmtime-specific section of compilation which lists the sites of
+/// each function's code coverage counters.
+///
+/// This section is only present when `Tunables::coverage` is enabled and has
+/// a custom binary encoding described in `coverage.rs`. Like the stack map
+/// section it has an alignment of 1 with unaligned reads and doesn't support
+/// >=4gb text sections.
+pub const ELF_WASMTIME_COVERAGE: &str = ".wasmtime.coverage";
+
 /// A custom binary-encoded section of wasmtime compilation artifacts which
 /// encodes the ability to map an offset in the text section to the trap code
 /// that it corresponds to.
@@ -117,6 +135,13 @@ pub const ELF_WASM_BTI: &str = ".wasmtime.bti";
 /// double-check that an artifact can be loaded into the current host.
 pub const ELF_WASM_ENGINE: &str = ".wasmtime.engine";
 
+/// A section containing an Ed25519 public key followed by a signature, made
+/// with the corresponding private key, over the entire ELF image with the
+/// signature bytes zeroed out.
+///
+/// This section is only present in artifacts compiled with a signing key.
+pub const ELF_WASM_SIGNATURE: &str = ".wasmtime.signature";
+
 /// This is the name of the section in the final ELF image which contains
 /// concatenated data segments from the original wasm module.
 ///
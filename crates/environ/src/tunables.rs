@@ -86,6 +86,28 @@ define_tunables! {
         /// Whether or not we use epoch-based interruption.
         pub epoch_interruption: bool,
 
+        /// Whether or not generated code reports function entries and exits
+        /// to the store so that consumed fuel can be attributed to functions.
+        pub fuel_profiling: bool,
+
+        /// Whether or not generated code counts how many times each basic
+        /// block executes, for code coverage.
+        pub coverage: bool,
+
+        /// Whether or not generated code is instrumented to call into the
+        /// host's debugger before each wasm instruction.
+        pub debug_instrumentation: bool,
+
+        /// Whether or not generated code records the values of wasm locals and
+        /// the operand stack in its stack frame before instructions which may
+        /// trap or call, so that core dumps can recover them.
+        pub debug_frame_state: bool,
+
+        /// Whether or not generated code tracks whether wasm values were
+        /// computed from uninitialized linear memory, for wmemcheck's
+        /// uninitialized-memory mode.
+        pub wmemcheck_uninit: bool,
+
         /// Whether or not linear memories are allowed to be reallocated after
         /// initial allocation at runtime.
         pub memory_may_move: bool,
@@ -197,6 +219,11 @@ impl Tunables {
             parse_wasm_debuginfo: true,
             consume_fuel: false,
             epoch_interruption: false,
+            fuel_profiling: false,
+            coverage: false,
+            debug_instrumentation: false,
+            debug_frame_state: false,
+            wmemcheck_uninit: false,
             memory_may_move: true,
             guard_before_linear_memory: true,
             table_lazy_init: true,
@@ -276,6 +303,8 @@ pub enum Collector {
     DeferredReferenceCounting,
     /// The null collector.
     Null,
+    /// The semi-space copying collector.
+    Copying,
 }
 
 impl fmt::Display for Collector {
@@ -283,6 +312,7 @@ impl fmt::Display for Collector {
         match self {
             Collector::DeferredReferenceCounting => write!(f, "deferred reference-counting"),
             Collector::Null => write!(f, "null"),
+            Collector::Copying => write!(f, "copying"),
         }
     }
 }
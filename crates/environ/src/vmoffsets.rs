@@ -31,6 +31,7 @@
 //      globals: [VMGlobalDefinition; module.num_defined_globals],
 //      tags: [VMTagDefinition; module.num_defined_tags],
 //      func_refs: [VMFuncRef; module.num_escaped_funcs],
+//      coverage_counters: *const *mut u64,
 // }
 
 use crate::{
@@ -96,6 +97,7 @@ pub struct VMOffsets<P> {
     defined_globals: u32,
     defined_tags: u32,
     defined_func_refs: u32,
+    coverage_counters: u32,
     size: u32,
 }
 
@@ -233,9 +235,14 @@ pub trait PtrSize {
         self.vmstore_context_last_wasm_entry_fp() + self.size()
     }
 
+    /// Return the offset of the `debug_hook_enabled` field of `VMStoreContext`.
+    fn vmstore_context_debug_hook_enabled(&self) -> u8 {
+        self.vmstore_context_last_wasm_entry_trap_handler() + self.size()
+    }
+
     /// Return the offset of the `stack_chain` field of `VMStoreContext`.
     fn vmstore_context_stack_chain(&self) -> u8 {
-        self.vmstore_context_last_wasm_entry_trap_handler() + self.size()
+        self.vmstore_context_debug_hook_enabled() + self.size()
     }
 
     // Offsets within `VMMemoryDefinition`
@@ -596,6 +603,7 @@ impl<P: PtrSize> VMOffsets<P> {
         }
 
         calculate_sizes! {
+            coverage_counters: "coverage counters",
             defined_func_refs: "module functions",
             defined_tags: "defined tags",
             defined_globals: "defined globals",
@@ -637,6 +645,7 @@ impl<P: PtrSize> From<VMOffsetsFields<P>> for VMOffsets<P> {
             defined_globals: 0,
             defined_tags: 0,
             defined_func_refs: 0,
+            coverage_counters: 0,
             size: 0,
         };
 
@@ -695,6 +704,7 @@ impl<P: PtrSize> From<VMOffsetsFields<P>> for VMOffsets<P> {
                 ret.num_escaped_funcs,
                 ret.ptr.size_of_vm_func_ref(),
             ),
+            size(coverage_counters) = ret.ptr.size(),
         }
 
         ret.size = next_field_offset;
@@ -937,6 +947,15 @@ impl<P: PtrSize> VMOffsets<P> {
         self.defined_func_refs
     }
 
+    /// The offset of the pointer to the table of each defined function's code
+    /// coverage counters.
+    ///
+    /// This is null unless `Tunables::coverage` is enabled.
+    #[inline]
+    pub fn vmctx_coverage_counters(&self) -> u32 {
+        self.coverage_counters
+    }
+
     /// Return the size of the `VMContext` allocation.
     #[inline]
     pub fn size_of_vmctx(&self) -> u32 {
@@ -0,0 +1,45 @@
+//! Definitions shared between compiled code and the runtime for wmemcheck's
+//! uninitialized-memory mode.
+//!
+//! When `Tunables::wmemcheck_uninit` is enabled compiled code tracks, for
+//! each wasm value, whether it was computed from uninitialized linear memory.
+//! Such values are allowed to flow through locals, the operand stack and
+//! memory, and are only reported, through the `report_uninit_use` builtin,
+//! when they are used in one of the ways described here.
+
+use core::fmt;
+
+/// How a value computed from uninitialized memory was used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum UninitUse {
+    /// As the condition of a branch, `if`, or `br_table`.
+    Branch = 0,
+    /// As the address of a memory access or as a table index.
+    Address = 1,
+    /// As an argument to an imported function.
+    HostCall = 2,
+}
+
+impl UninitUse {
+    /// Converts the `kind` passed to `report_uninit_use` back into an
+    /// `UninitUse`.
+    pub fn from_u32(kind: u32) -> Option<UninitUse> {
+        match kind {
+            0 => Some(UninitUse::Branch),
+            1 => Some(UninitUse::Address),
+            2 => Some(UninitUse::HostCall),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UninitUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UninitUse::Branch => "conditional branch",
+            UninitUse::Address => "memory address or table index",
+            UninitUse::HostCall => "argument to an imported function",
+        })
+    }
+}
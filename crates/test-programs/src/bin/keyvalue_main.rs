@@ -17,6 +17,8 @@ fn main() {
     assert_eq!(bucket.exists("hello").unwrap(), true);
     bucket.delete("hello").unwrap();
     assert_eq!(bucket.exists("hello").unwrap(), false);
+    // deleting a missing key is not an error
+    bucket.delete("hello").unwrap();
 
     batch::set_many(
         &bucket,
@@ -27,7 +29,11 @@ fn main() {
         ],
     )
     .unwrap();
-    batch::delete_many(&bucket, &["a1".to_string(), "c1".to_string()]).unwrap();
+    batch::delete_many(
+        &bucket,
+        &["a1".to_string(), "c1".to_string(), "d1".to_string()],
+    )
+    .unwrap();
     let values = batch::get_many(
         &bucket,
         &["a1".to_string(), "b1".to_string(), "c1".to_string()],
@@ -0,0 +1,18 @@
+use test_programs::keyvalue_watcher::exports::wasi::keyvalue::watcher::Guest;
+use test_programs::wasi::keyvalue::store::Bucket;
+
+struct T;
+
+test_programs::keyvalue_watcher::export!(T);
+
+impl Guest for T {
+    fn on_set(bucket: Bucket, key: String, value: Vec<u8>) {
+        bucket.set(&format!("on-set/{key}"), &value).unwrap();
+    }
+
+    fn on_delete(bucket: Bucket, key: String) {
+        bucket.set(&format!("on-delete/{key}"), &[]).unwrap();
+    }
+}
+
+fn main() {}
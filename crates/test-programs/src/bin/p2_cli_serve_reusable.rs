@@ -0,0 +1,26 @@
+use test_programs::proxy;
+use test_programs::wasi::http::types::{
+    Fields, IncomingRequest, OutgoingResponse, ResponseOutparam,
+};
+
+// Declares to `wasmtime serve --reuse-instances` that this component may
+// handle more than one request.
+#[unsafe(link_section = "wasmtime-serve-reuse-instances")]
+#[used]
+static REUSE_INSTANCES: [u8; 1] = [1];
+
+struct T;
+
+proxy::export!(T);
+
+impl proxy::exports::wasi::http::incoming_handler::Guest for T {
+    fn handle(_request: IncomingRequest, outparam: ResponseOutparam) {
+        let fields = Fields::new();
+        let id = std::env::var("REQUEST_ID").unwrap();
+        fields.set("request-id", &[id.into_bytes()]).unwrap();
+        let resp = OutgoingResponse::new(fields);
+        ResponseOutparam::set(outparam, Ok(resp));
+    }
+}
+
+fn main() {}
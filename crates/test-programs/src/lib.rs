@@ -51,6 +51,20 @@ pub mod proxy {
     });
 }
 
+pub mod keyvalue_watcher {
+    wit_bindgen::generate!({
+        path: "../wasi-keyvalue/wit",
+        world: "wasi:keyvalue/watch-service",
+        default_bindings_module: "test_programs::keyvalue_watcher",
+        pub_export_macro: true,
+        with: {
+            "wasi:keyvalue/store@0.2.0-draft": crate::wasi::keyvalue::store,
+            "wasi:keyvalue/atomics@0.2.0-draft": crate::wasi::keyvalue::atomics,
+            "wasi:keyvalue/batch@0.2.0-draft": crate::wasi::keyvalue::batch,
+        },
+    });
+}
+
 impl std::fmt::Display for wasi::io::error::Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&self.to_debug_string())
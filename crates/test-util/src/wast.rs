@@ -385,6 +385,7 @@ pub enum Collector {
     Auto,
     Null,
     DeferredReferenceCounting,
+    Copying,
 }
 
 impl WastTest {
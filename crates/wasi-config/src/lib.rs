@@ -4,6 +4,13 @@
 //! API. With this crate, the runtime can run components that call APIs in
 //! [wasi-config] and provide configuration variables for the component.
 //!
+//! Variables are either a fixed [`WasiConfigVariables`] map, or are produced
+//! on demand by a set of [`WasiConfigProviders`] reading from, for example,
+//! the host environment ([`EnvPrefix`]), a TOML/JSON file ([`ConfigFile`]) or
+//! a host callback. Providers are re-queried each time they're loaded, which
+//! allows long-running embeddings to pick up new configuration for each new
+//! store without rebuilding anything else.
+//!
 //! # Examples
 //!
 //! The usage of this crate is very similar to other WASI API implementations
@@ -70,6 +77,10 @@ use anyhow::Result;
 use std::collections::HashMap;
 use wasmtime::component::HasData;
 
+mod provider;
+
+pub use self::provider::{ConfigFile, EnvPrefix, Provider, WasiConfigProviders};
+
 mod gen_ {
     wasmtime::component::bindgen!({
         path: "wit",
@@ -80,7 +91,7 @@ mod gen_ {
 use self::gen_::wasi::config::store as generated;
 
 /// Capture the state necessary for use in the `wasi-config` API implementation.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct WasiConfigVariables(HashMap<String, String>);
 
 impl<S: Into<String>> FromIterator<(S, S)> for WasiConfigVariables {
@@ -104,6 +115,11 @@ impl WasiConfigVariables {
         self.0.insert(key.into(), value.into());
         self
     }
+
+    /// Returns the value of the variable `key`, if it is set.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|s| s.as_str())
+    }
 }
 
 /// A wrapper capturing the needed internal `wasi-config` state.
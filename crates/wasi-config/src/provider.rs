@@ -0,0 +1,237 @@
+//! Dynamic sources of configuration variables.
+
+use crate::WasiConfigVariables;
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A source of configuration variables which may change over time.
+///
+/// Providers are consulted each time [`WasiConfigProviders::load`] is called,
+/// which typically happens once per store. Implementations which are expensive
+/// to query should cache their results internally.
+pub trait Provider: Send + Sync {
+    /// Returns the variables currently supplied by this provider.
+    fn load(&self) -> Result<Vec<(String, String)>>;
+}
+
+impl<F> Provider for F
+where
+    F: Fn() -> Result<Vec<(String, String)>> + Send + Sync,
+{
+    fn load(&self) -> Result<Vec<(String, String)>> {
+        self()
+    }
+}
+
+/// A fixed set of variables.
+impl Provider for WasiConfigVariables {
+    fn load(&self) -> Result<Vec<(String, String)>> {
+        Ok(self.0.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+}
+
+/// A [`Provider`] supplying host environment variables whose name starts with
+/// a prefix.
+///
+/// The prefix is stripped from the name, so with a prefix of `APP_` the host
+/// variable `APP_PORT=80` is provided as `PORT=80`. Variables whose name or
+/// value isn't valid unicode are skipped.
+pub struct EnvPrefix {
+    prefix: String,
+}
+
+impl EnvPrefix {
+    /// Creates a provider for variables starting with `prefix`.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl Provider for EnvPrefix {
+    fn load(&self) -> Result<Vec<(String, String)>> {
+        Ok(std::env::vars()
+            .filter_map(|(k, v)| Some((k.strip_prefix(&self.prefix)?.to_string(), v)))
+            .collect())
+    }
+}
+
+/// A [`Provider`] reading variables from a TOML or JSON file.
+///
+/// The format is chosen by the file's extension: `.json` files are parsed as
+/// JSON and everything else as TOML. The document must be a table/object;
+/// nested tables are flattened by joining keys with `.`, strings are provided
+/// as-is and other scalars are provided in their textual form. Arrays are
+/// rejected.
+///
+/// Reading JSON files requires the `json` feature of this crate, and reading
+/// TOML files requires the `toml` feature, both enabled by default.
+///
+/// The file is read on every load and parsed again whenever its contents
+/// change, so edits to it are picked up by the next
+/// [`WasiConfigProviders::load`]. Its contents are compared rather than its
+/// modification time, which may be too coarse to tell apart two writes in
+/// quick succession.
+pub struct ConfigFile {
+    path: PathBuf,
+    /// The contents of the file when last parsed, and the variables parsed
+    /// from them.
+    cache: Mutex<Option<(String, Arc<Vec<(String, String)>>)>>,
+}
+
+impl ConfigFile {
+    /// Creates a provider for the file at `path`.
+    ///
+    /// The file isn't read until the provider is first loaded.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Returns the path of the file read by this provider.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn parse(&self, contents: &str) -> Result<Vec<(String, String)>> {
+        if self.path.extension().is_some_and(|e| e == "json") {
+            parse_json(contents)
+        } else {
+            parse_toml(contents)
+        }
+    }
+}
+
+impl Provider for ConfigFile {
+    fn load(&self) -> Result<Vec<(String, String)>> {
+        let read = || -> Result<_> {
+            let contents = std::fs::read_to_string(&self.path)?;
+            let mut cache = self.cache.lock().unwrap();
+            if let Some((_, vars)) = cache.as_ref().filter(|(cached, _)| *cached == contents) {
+                return Ok(vars.clone());
+            }
+            let vars = Arc::new(self.parse(&contents)?);
+            *cache = Some((contents, vars.clone()));
+            Ok(vars)
+        };
+        let vars = read()
+            .with_context(|| format!("failed to load config file `{}`", self.path.display()))?;
+        Ok((*vars).clone())
+    }
+}
+
+#[cfg(any(feature = "json", feature = "toml"))]
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(contents: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    flatten_toml("", toml::from_str(contents)?, &mut vars)?;
+    Ok(vars)
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_contents: &str) -> Result<Vec<(String, String)>> {
+    bail!("support for TOML config files was disabled at compile time")
+}
+
+#[cfg(feature = "toml")]
+fn flatten_toml(prefix: &str, table: toml::Table, vars: &mut Vec<(String, String)>) -> Result<()> {
+    for (key, value) in table {
+        let key = join_key(prefix, &key);
+        let value = match value {
+            toml::Value::Table(table) => {
+                flatten_toml(&key, table, vars)?;
+                continue;
+            }
+            toml::Value::Array(_) => bail!("arrays are not supported (for key `{key}`)"),
+            toml::Value::String(s) => s,
+            other => other.to_string(),
+        };
+        vars.push((key, value));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn parse_json(contents: &str) -> Result<Vec<(String, String)>> {
+    let serde_json::Value::Object(map) = serde_json::from_str(contents)? else {
+        bail!("expected a JSON object at the top level");
+    };
+    let mut vars = Vec::new();
+    flatten_json("", map, &mut vars)?;
+    Ok(vars)
+}
+
+#[cfg(not(feature = "json"))]
+fn parse_json(_contents: &str) -> Result<Vec<(String, String)>> {
+    bail!("support for JSON config files was disabled at compile time")
+}
+
+#[cfg(feature = "json")]
+fn flatten_json(
+    prefix: &str,
+    map: serde_json::Map<String, serde_json::Value>,
+    vars: &mut Vec<(String, String)>,
+) -> Result<()> {
+    for (key, value) in map {
+        let key = join_key(prefix, &key);
+        let value = match value {
+            serde_json::Value::Object(map) => {
+                flatten_json(&key, map, vars)?;
+                continue;
+            }
+            serde_json::Value::Array(_) => bail!("arrays are not supported (for key `{key}`)"),
+            serde_json::Value::String(s) => s,
+            other => other.to_string(),
+        };
+        vars.push((key, value));
+    }
+    Ok(())
+}
+
+/// A layered, reloadable collection of [`Provider`]s.
+///
+/// Providers are consulted in the order they were added and variables from
+/// later providers override variables of the same name from earlier ones.
+/// Cloning is cheap and clones share the same providers, so a single
+/// collection can be created up front and used to create fresh
+/// [`WasiConfigVariables`] for every store.
+#[derive(Clone, Default)]
+pub struct WasiConfigProviders {
+    providers: Vec<Arc<dyn Provider>>,
+}
+
+impl WasiConfigProviders {
+    /// Creates an empty collection of providers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `provider` on top of all previously added providers.
+    pub fn push(&mut self, provider: impl Provider + 'static) -> &mut Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    /// Queries every provider and returns the resulting variables.
+    pub fn load(&self) -> Result<WasiConfigVariables> {
+        let mut vars = WasiConfigVariables::new();
+        for provider in self.providers.iter() {
+            for (key, value) in provider.load()? {
+                vars.insert(key, value);
+            }
+        }
+        Ok(vars)
+    }
+}
@@ -6,7 +6,7 @@ use wasmtime::{
 };
 use wasmtime_wasi::p2::{add_to_linker_async, bindings::Command};
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
-use wasmtime_wasi_config::{WasiConfig, WasiConfigVariables};
+use wasmtime_wasi_config::{ConfigFile, WasiConfig, WasiConfigProviders, WasiConfigVariables};
 
 struct Ctx {
     table: ResourceTable,
@@ -65,3 +65,82 @@ async fn config_get() -> Result<()> {
     )
     .await
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn config_get_from_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "hello = \"world\"\n")?;
+
+    let mut providers = WasiConfigProviders::new();
+    providers.push(ConfigFile::new(&path));
+    run_wasi(
+        CONFIG_GET_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtxBuilder::new().build(),
+            wasi_config_vars: providers.load()?,
+        },
+    )
+    .await
+}
+
+#[test]
+fn config_providers_layer_and_reload() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("config.json");
+    std::fs::write(&path, r#"{"a": "file", "b": {"c": 1, "d": true}}"#)?;
+
+    let mut providers = WasiConfigProviders::new();
+    providers
+        .push(|| -> Result<Vec<(String, String)>> {
+            Ok(vec![("a".to_string(), "callback".to_string())])
+        })
+        .push(ConfigFile::new(&path))
+        .push(WasiConfigVariables::from_iter([("b.d", "overridden")]));
+
+    let get = |providers: &WasiConfigProviders| -> Result<Vec<(String, String)>> {
+        let vars = providers.load()?;
+        let mut vars = ["a", "b.c", "b.d"]
+            .iter()
+            .filter_map(|k| Some((k.to_string(), vars.get(k)?.to_string())))
+            .collect::<Vec<_>>();
+        vars.sort();
+        Ok(vars)
+    };
+    let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+    assert_eq!(
+        get(&providers)?,
+        [
+            pair("a", "file"),
+            pair("b.c", "1"),
+            pair("b.d", "overridden")
+        ]
+    );
+
+    std::fs::write(&path, r#"{"b": {"c": 2}}"#)?;
+    assert_eq!(
+        get(&providers)?,
+        [
+            pair("a", "callback"),
+            pair("b.c", "2"),
+            pair("b.d", "overridden")
+        ]
+    );
+
+    // Rewrites are noticed even if they don't change the file's length or,
+    // as filesystem timestamps may be coarse, its modification time.
+    let modified = std::fs::metadata(&path)?.modified()?;
+    std::fs::write(&path, r#"{"b": {"c": 3}}"#)?;
+    let file = std::fs::File::options().write(true).open(&path)?;
+    file.set_modified(modified)?;
+    assert_eq!(
+        get(&providers)?,
+        [
+            pair("a", "callback"),
+            pair("b.c", "3"),
+            pair("b.d", "overridden")
+        ]
+    );
+    Ok(())
+}
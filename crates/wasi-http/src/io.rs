@@ -1,6 +1,7 @@
 //! I/O utility for bridging between `tokio::io` and `hyper::rt`.
 
 use hyper::rt::{Read, ReadBufCursor, Write};
+use std::future::Future;
 use std::io::Error;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -53,3 +54,21 @@ impl<T: AsyncWrite + Unpin> Write for TokioIo<T> {
         Pin::new(&mut self.inner).poll_shutdown(cx)
     }
 }
+
+/// A [`hyper::rt::Executor`] which spawns tasks onto the ambient tokio runtime.
+///
+/// This is used to drive HTTP/2 connections, which spawn a task per stream.
+#[derive(Debug, Clone, Copy)]
+pub struct TokioExecutor;
+
+impl<F> hyper::rt::Executor<F> for TokioExecutor
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        // The spawned tasks live as long as the stream they belong to, so
+        // detach them rather than using `wasmtime_wasi::runtime::spawn`.
+        wasmtime_wasi::runtime::with_ambient_tokio_runtime(|| tokio::task::spawn(fut));
+    }
+}
@@ -32,7 +32,9 @@
 //!
 //! The [`WasiHttpView`] trait additionally offers a few other configuration
 //! methods such as [`WasiHttpView::send_request`] to customize how outgoing
-//! HTTP requests are handled.
+//! HTTP requests are handled. By default outgoing requests are sent over a
+//! [`ConnectionPool`] which keeps connections open for reuse across requests
+//! and stores, and which speaks HTTP/2 with servers that support it.
 //!
 //! # Async and Sync
 //!
@@ -222,6 +224,8 @@
 
 mod error;
 mod http_impl;
+#[cfg(feature = "default-send-request")]
+mod pool;
 mod types_impl;
 
 pub mod body;
@@ -236,6 +240,8 @@ pub mod p3;
 pub use crate::error::{
     HttpError, HttpResult, http_request_error, hyper_request_error, hyper_response_error,
 };
+#[cfg(feature = "default-send-request")]
+pub use crate::pool::{ConnectionPool, ConnectionPoolConfig};
 #[doc(inline)]
 pub use crate::types::{
     DEFAULT_OUTGOING_BODY_BUFFER_CHUNKS, DEFAULT_OUTGOING_BODY_CHUNK_SIZE, WasiHttpCtx,
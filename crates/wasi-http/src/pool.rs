@@ -0,0 +1,625 @@
+//! Pooling of the connections used to send outgoing requests.
+//!
+//! A [`ConnectionPool`] keeps connections to each host open after a request
+//! completes so that later requests to the same host, possibly from other
+//! stores, can skip the TCP and TLS handshakes. HTTP/1.1 connections carry one
+//! request at a time and are returned to the pool once their response body has
+//! been read in full. HTTP/2, when negotiated with ALPN during the TLS
+//! handshake, multiplexes all requests to a host over a single connection.
+
+use crate::bindings::http::types::ErrorCode;
+use crate::body::HyperOutgoingBody;
+use crate::io::{TokioExecutor, TokioIo};
+use crate::types::{HostFutureIncomingResponse, IncomingResponse, OutgoingRequestConfig};
+use crate::{error::dns_error, hyper_request_error};
+use futures::future::{self, Either};
+use http_body_util::BodyExt;
+use hyper::body::{Body, Bytes, Frame, Incoming, SizeHint};
+use hyper::client::conn::{http1, http2};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::{Pin, pin};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::time::timeout;
+use wasmtime_wasi::runtime::AbortOnDropJoinHandle;
+
+/// Configuration of a [`ConnectionPool`].
+#[derive(Debug, Clone)]
+pub struct ConnectionPoolConfig {
+    /// The maximum number of idle HTTP/1.1 connections kept open per host.
+    ///
+    /// Connections returned to the pool beyond this limit are closed. A value
+    /// of zero disables reuse of HTTP/1.1 connections.
+    ///
+    /// Default: 32.
+    pub max_idle_per_host: usize,
+    /// How long a connection may stay unused in the pool before it is closed.
+    ///
+    /// Default: 90 seconds.
+    pub idle_timeout: Duration,
+    /// The maximum number of connections, idle or in use, open to a single
+    /// host at any time.
+    ///
+    /// Requests which would exceed this limit wait for a connection to become
+    /// available, subject to the request's connect timeout. `None` means no
+    /// limit.
+    ///
+    /// Default: `None`.
+    pub max_connections_per_host: Option<usize>,
+    /// Whether to offer HTTP/2 with ALPN when connecting over TLS.
+    ///
+    /// Plaintext connections always use HTTP/1.1.
+    ///
+    /// Default: `true`.
+    pub http2: bool,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 32,
+            idle_timeout: Duration::from_secs(90),
+            max_connections_per_host: None,
+            http2: true,
+        }
+    }
+}
+
+/// A pool of connections used to send outgoing requests.
+///
+/// Cloning a pool is cheap and the clones share the same connections, so a
+/// single pool may be used by any number of
+/// [`WasiHttpCtx`](crate::WasiHttpCtx)s across stores and engines. Unless
+/// configured otherwise with
+/// [`WasiHttpCtx::set_connection_pool`](crate::WasiHttpCtx::set_connection_pool)
+/// outgoing requests use the process-wide [`ConnectionPool::shared`] pool.
+#[derive(Clone)]
+pub struct ConnectionPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: ConnectionPoolConfig,
+    tls: tokio_rustls::TlsConnector,
+    hosts: Mutex<HashMap<(bool, String), Arc<Host>>>,
+}
+
+impl ConnectionPool {
+    /// Creates a new, empty pool with the given configuration.
+    ///
+    /// TLS connections made by this pool trust the Mozilla root certificates
+    /// from the `webpki-roots` crate.
+    pub fn new(config: ConnectionPoolConfig) -> Self {
+        // derived from https://github.com/rustls/rustls/blob/main/examples/src/bin/simpleclient.rs
+        let root_cert_store = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.into(),
+        };
+        let tls = rustls::ClientConfig::builder()
+            .with_root_certificates(root_cert_store)
+            .with_no_client_auth();
+        Self::with_client_config(config, tls)
+    }
+
+    /// Creates a new, empty pool which makes TLS connections with the `tls`
+    /// client configuration, for example to trust other root certificates.
+    ///
+    /// The ALPN protocols of `tls` are replaced according to
+    /// [`ConnectionPoolConfig::http2`].
+    pub fn with_client_config(config: ConnectionPoolConfig, mut tls: rustls::ClientConfig) -> Self {
+        tls.alpn_protocols = if config.http2 {
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        } else {
+            Vec::new()
+        };
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                tls: tokio_rustls::TlsConnector::from(Arc::new(tls)),
+                hosts: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Returns the process-wide pool, created with the default
+    /// [`ConnectionPoolConfig`] on first use.
+    pub fn shared() -> ConnectionPool {
+        static SHARED: OnceLock<ConnectionPool> = OnceLock::new();
+        SHARED.get_or_init(ConnectionPool::default).clone()
+    }
+
+    /// Returns the configuration of this pool.
+    pub fn config(&self) -> &ConnectionPoolConfig {
+        &self.inner.config
+    }
+
+    /// Sends `request` over a connection from this pool in a new task.
+    pub fn send_request(
+        &self,
+        request: hyper::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> HostFutureIncomingResponse {
+        let pool = self.clone();
+        let handle = wasmtime_wasi::runtime::spawn(async move {
+            Ok(pool.send_request_handler(request, config).await)
+        });
+        HostFutureIncomingResponse::pending(handle)
+    }
+
+    /// Sends `request` over a connection from this pool, opening a new
+    /// connection if none is available.
+    ///
+    /// This is called from [`ConnectionPool::send_request`] to actually send
+    /// the request.
+    pub async fn send_request_handler(
+        &self,
+        mut request: hyper::Request<HyperOutgoingBody>,
+        OutgoingRequestConfig {
+            use_tls,
+            connect_timeout,
+            first_byte_timeout,
+            between_bytes_timeout,
+        }: OutgoingRequestConfig,
+    ) -> Result<IncomingResponse, ErrorCode> {
+        let authority = if let Some(authority) = request.uri().authority() {
+            if authority.port().is_some() {
+                authority.to_string()
+            } else {
+                let port = if use_tls { 443 } else { 80 };
+                format!("{authority}:{port}")
+            }
+        } else {
+            return Err(ErrorCode::HttpRequestUriInvalid);
+        };
+        let host = self.inner.host(use_tls, &authority);
+        let uri = request.uri().clone();
+        let path_and_query = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+
+        loop {
+            let (conn, reused) = timeout(
+                connect_timeout,
+                self.inner.checkout(&host, use_tls, &authority),
+            )
+            .await
+            .map_err(|_| ErrorCode::ConnectionTimeout)??;
+
+            let result = match conn {
+                Conn::Http1(mut conn) => {
+                    // at this point, the request contains the scheme and the
+                    // authority, but the http packet should only include those
+                    // if addressing a proxy, so remove them here, since
+                    // SendRequest::send_request does not do it for us
+                    *request.uri_mut() = http::Uri::builder()
+                        .path_and_query(path_and_query)
+                        .build()
+                        .expect("comes from valid request");
+                    let result =
+                        timeout(first_byte_timeout, conn.sender.try_send_request(request)).await;
+                    result.map(|r| r.map(|resp| (resp, Checkin::Http1(host.clone(), conn))))
+                }
+                Conn::Http2(mut conn) => {
+                    let result =
+                        timeout(first_byte_timeout, conn.sender.try_send_request(request)).await;
+                    result.map(|r| r.map(|resp| (resp, Checkin::Http2 { _guard: conn.guard })))
+                }
+            };
+
+            let (resp, checkin) = match result {
+                Ok(Ok(resp)) => resp,
+                Ok(Err(mut e)) => match e.take_message() {
+                    // A pooled connection may have been closed by the server
+                    // while it was idle, so retry requests which were never
+                    // sent on a fresh connection.
+                    Some(req) if reused => {
+                        request = req;
+                        *request.uri_mut() = uri.clone();
+                        continue;
+                    }
+                    _ => return Err(hyper_request_error(e.into_error())),
+                },
+                Err(_) => return Err(ErrorCode::ConnectionReadTimeout),
+            };
+
+            let resp = resp.map(|body| {
+                PooledBody::new(body, checkin)
+                    .map_err(hyper_request_error)
+                    .boxed()
+            });
+            return Ok(IncomingResponse {
+                resp,
+                worker: None,
+                between_bytes_timeout,
+            });
+        }
+    }
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self::new(ConnectionPoolConfig::default())
+    }
+}
+
+impl fmt::Debug for ConnectionPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionPool")
+            .field("config", &self.inner.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    fn host(&self, use_tls: bool, authority: &str) -> Arc<Host> {
+        let mut hosts = self.hosts.lock().unwrap();
+        let key = (use_tls, authority.to_string());
+        if let Some(host) = hosts.get(&key) {
+            return host.clone();
+        }
+        // Forget about hosts without any connections before adding a new one
+        // so that the map doesn't grow without bound.
+        hosts.retain(|_, host| Arc::strong_count(host) > 1 || !host.is_unused());
+        let host = Arc::new(Host {
+            idle_timeout: self.config.idle_timeout,
+            max_idle: self.config.max_idle_per_host,
+            limit: self
+                .config
+                .max_connections_per_host
+                .map(|max| (Arc::new(Semaphore::new(max)), max)),
+            available: Notify::new(),
+            state: Mutex::new(HostState::default()),
+        });
+        hosts.insert(key, host.clone());
+        host
+    }
+
+    /// Takes an idle connection to `host` from the pool or opens a new one.
+    ///
+    /// Also returns whether the connection was taken from the pool.
+    async fn checkout(
+        &self,
+        host: &Host,
+        use_tls: bool,
+        authority: &str,
+    ) -> Result<(Conn, bool), ErrorCode> {
+        let may_http2 = use_tls && self.config.http2;
+        loop {
+            // Register interest in connections becoming available before
+            // looking for one so that none are missed while waiting.
+            let mut available = pin!(host.available.notified());
+            available.as_mut().enable();
+
+            let pending = match host.take_idle(may_http2) {
+                Checkout::Idle(conn) => return Ok((conn, true)),
+                // Another request is connecting to the host and may negotiate
+                // an HTTP/2 connection that this one can share, so wait for it
+                // rather than opening another connection.
+                Checkout::Wait => {
+                    available.await;
+                    continue;
+                }
+                Checkout::Connect(pending) => pending,
+            };
+
+            let permit = match &host.limit {
+                Some((limit, _)) => {
+                    let acquire = pin!(limit.clone().acquire_owned());
+                    match future::select(acquire, available).await {
+                        Either::Left((permit, _)) => {
+                            Some(permit.expect("semaphore is never closed"))
+                        }
+                        Either::Right(_) => continue,
+                    }
+                }
+                None => None,
+            };
+            let conn = self.connect(host, use_tls, authority, permit).await?;
+            drop(pending);
+            return Ok((conn, false));
+        }
+    }
+
+    async fn connect(
+        &self,
+        host: &Host,
+        use_tls: bool,
+        authority: &str,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Result<Conn, ErrorCode> {
+        let tcp_stream = TcpStream::connect(authority)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AddrNotAvailable => {
+                    dns_error("address not available".to_string(), 0)
+                }
+
+                _ => {
+                    if e.to_string()
+                        .starts_with("failed to lookup address information")
+                    {
+                        dns_error("address not available".to_string(), 0)
+                    } else {
+                        ErrorCode::ConnectionRefused
+                    }
+                }
+            })?;
+
+        if !use_tls {
+            return handshake_http1(TokioIo::new(tcp_stream), permit).await;
+        }
+
+        use rustls::pki_types::ServerName;
+
+        let mut parts = authority.split(":");
+        let host_name = parts.next().unwrap_or(authority);
+        let domain = ServerName::try_from(host_name)
+            .map_err(|e| {
+                tracing::warn!("dns lookup error: {e:?}");
+                dns_error("invalid dns name".to_string(), 0)
+            })?
+            .to_owned();
+        let stream = self.tls.connect(domain, tcp_stream).await.map_err(|e| {
+            tracing::warn!("tls protocol error: {e:?}");
+            ErrorCode::TlsProtocolError
+        })?;
+
+        if stream.get_ref().1.alpn_protocol() != Some(&b"h2"[..]) {
+            host.state.lock().unwrap().http1_only = true;
+            return handshake_http1(TokioIo::new(stream), permit).await;
+        }
+
+        let (sender, conn) = http2::handshake(TokioExecutor, TokioIo::new(stream))
+            .await
+            .map_err(hyper_request_error)?;
+        let conn = Http2Conn {
+            sender,
+            guard: Arc::new(ConnGuard {
+                _worker: spawn_worker(conn),
+                _permit: permit,
+            }),
+        };
+        let mut state = host.state.lock().unwrap();
+        state.http2 = Some((conn.clone(), Instant::now()));
+        state.http1_only = false;
+        drop(state);
+        Ok(Conn::Http2(conn))
+    }
+}
+
+async fn handshake_http1<T>(io: T, permit: Option<OwnedSemaphorePermit>) -> Result<Conn, ErrorCode>
+where
+    T: hyper::rt::Read + hyper::rt::Write + Send + Unpin + 'static,
+{
+    // TODO: we should plumb the builder through the http context, and use it here
+    let (sender, conn) = http1::handshake(io).await.map_err(hyper_request_error)?;
+    Ok(Conn::Http1(Http1Conn {
+        sender,
+        _guard: ConnGuard {
+            _worker: spawn_worker(conn),
+            _permit: permit,
+        },
+    }))
+}
+
+fn spawn_worker<F>(conn: F) -> AbortOnDropJoinHandle<()>
+where
+    F: Future<Output = hyper::Result<()>> + Send + 'static,
+{
+    wasmtime_wasi::runtime::spawn(async move {
+        match conn.await {
+            Ok(()) => {}
+            // TODO: shouldn't throw away this error and ideally should
+            // surface somewhere.
+            Err(e) => tracing::warn!("dropping error {e}"),
+        }
+    })
+}
+
+/// The connections open to a single host.
+struct Host {
+    idle_timeout: Duration,
+    max_idle: usize,
+    /// Limits the number of connections to this host, along with the limit.
+    limit: Option<(Arc<Semaphore>, usize)>,
+    /// Notified whenever a connection is returned to `state.idle` or an
+    /// attempt to open an HTTP/2 connection finishes.
+    available: Notify,
+    state: Mutex<HostState>,
+}
+
+#[derive(Default)]
+struct HostState {
+    idle: Vec<(Http1Conn, Instant)>,
+    /// The HTTP/2 connection shared by all requests along with when it was
+    /// last used.
+    http2: Option<(Http2Conn, Instant)>,
+    /// Whether a request is opening a TLS connection which may negotiate
+    /// HTTP/2.
+    connecting_http2: bool,
+    /// Whether the host negotiated HTTP/1.1 the last time a TLS connection was
+    /// opened to it, in which case requests don't wait for each other's
+    /// connections.
+    http1_only: bool,
+}
+
+/// The result of [`Host::take_idle`].
+enum Checkout<'a> {
+    /// A connection from the pool which is ready to send a request.
+    Idle(Conn),
+    /// Wait for [`Host::available`] before trying again.
+    Wait,
+    /// Open a new connection, holding onto the guard while doing so if it may
+    /// negotiate HTTP/2.
+    Connect(Option<ConnectingHttp2<'a>>),
+}
+
+/// Marks that a request is opening a connection which may negotiate HTTP/2,
+/// until dropped.
+struct ConnectingHttp2<'a>(&'a Host);
+
+impl Drop for ConnectingHttp2<'_> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().connecting_http2 = false;
+        self.0.available.notify_waiters();
+    }
+}
+
+impl Host {
+    fn take_idle(&self, may_http2: bool) -> Checkout<'_> {
+        let mut state = self.state.lock().unwrap();
+        self.prune(&mut state);
+        if let Some((conn, last_used)) = &mut state.http2 {
+            *last_used = Instant::now();
+            return Checkout::Idle(Conn::Http2(conn.clone()));
+        }
+        if let Some((conn, _)) = state.idle.pop() {
+            return Checkout::Idle(Conn::Http1(conn));
+        }
+        if !may_http2 || state.http1_only {
+            return Checkout::Connect(None);
+        }
+        if state.connecting_http2 {
+            return Checkout::Wait;
+        }
+        state.connecting_http2 = true;
+        Checkout::Connect(Some(ConnectingHttp2(self)))
+    }
+
+    fn checkin(&self, conn: Http1Conn) {
+        let mut state = self.state.lock().unwrap();
+        self.prune(&mut state);
+        if state.idle.len() < self.max_idle {
+            state.idle.push((conn, Instant::now()));
+            drop(state);
+            self.available.notify_one();
+        }
+    }
+
+    /// Closes connections which were idle for too long or were closed by the
+    /// other side.
+    fn prune(&self, state: &mut HostState) {
+        let live = |closed: bool, since: &Instant| !closed && since.elapsed() < self.idle_timeout;
+        state
+            .idle
+            .retain(|(conn, since)| live(!conn.sender.is_ready(), since));
+        if state
+            .http2
+            .as_ref()
+            .is_some_and(|(conn, last_used)| !live(conn.sender.is_closed(), last_used))
+        {
+            state.http2 = None;
+        }
+    }
+
+    fn is_unused(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        self.prune(&mut state);
+        state.idle.is_empty()
+            && state.http2.is_none()
+            && self
+                .limit
+                .as_ref()
+                .is_none_or(|(limit, max)| limit.available_permits() == *max)
+    }
+}
+
+enum Conn {
+    Http1(Http1Conn),
+    Http2(Http2Conn),
+}
+
+struct Http1Conn {
+    sender: http1::SendRequest<HyperOutgoingBody>,
+    _guard: ConnGuard,
+}
+
+#[derive(Clone)]
+struct Http2Conn {
+    sender: http2::SendRequest<HyperOutgoingBody>,
+    guard: Arc<ConnGuard>,
+}
+
+/// Keeps a connection open and counted against its host's limit.
+struct ConnGuard {
+    _worker: AbortOnDropJoinHandle<()>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// What to do with a connection once its response body is done.
+enum Checkin {
+    /// Return the connection to the host's idle connections.
+    Http1(Arc<Host>, Http1Conn),
+    /// Keep the shared connection open until the body is done.
+    Http2 { _guard: Arc<ConnGuard> },
+}
+
+/// A response body which hands its connection back to the pool once it has
+/// been read in full.
+struct PooledBody {
+    body: Incoming,
+    checkin: Option<Checkin>,
+}
+
+impl PooledBody {
+    fn new(body: Incoming, checkin: Checkin) -> Self {
+        let mut body = PooledBody {
+            body,
+            checkin: Some(checkin),
+        };
+        if body.body.is_end_stream() {
+            body.finish();
+        }
+        body
+    }
+
+    fn finish(&mut self) {
+        let Some(Checkin::Http1(host, mut conn)) = self.checkin.take() else {
+            return;
+        };
+        if conn.sender.is_ready() {
+            host.checkin(conn);
+            return;
+        }
+        // The request body may still be streaming, or the connection has yet
+        // to notice that the response is done. Only return the connection to
+        // the pool once it's ready, so that nothing waits on it in the
+        // meantime.
+        wasmtime_wasi::runtime::with_ambient_tokio_runtime(|| {
+            tokio::task::spawn(async move {
+                if conn.sender.ready().await.is_ok() {
+                    host.checkin(conn);
+                }
+            })
+        });
+    }
+}
+
+impl Body for PooledBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, hyper::Error>>> {
+        let frame = ready!(Pin::new(&mut self.body).poll_frame(cx));
+        match &frame {
+            Some(Ok(_)) if !self.body.is_end_stream() => {}
+            Some(Ok(_)) | None => self.finish(),
+            // The connection can't be reused after an error, so close it.
+            Some(Err(_)) => self.checkin = None,
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
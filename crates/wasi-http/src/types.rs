@@ -17,23 +17,42 @@ use wasmtime_wasi::p2::Pollable;
 use wasmtime_wasi::runtime::AbortOnDropJoinHandle;
 
 #[cfg(feature = "default-send-request")]
-use {
-    crate::io::TokioIo,
-    crate::{error::dns_error, hyper_request_error},
-    tokio::net::TcpStream,
-    tokio::time::timeout,
-};
+use crate::pool::ConnectionPool;
 
 /// Capture the state necessary for use in the wasi-http API implementation.
 #[derive(Debug)]
 pub struct WasiHttpCtx {
     _priv: (),
+    #[cfg(feature = "default-send-request")]
+    pool: Option<ConnectionPool>,
 }
 
 impl WasiHttpCtx {
     /// Create a new context.
     pub fn new() -> Self {
-        Self { _priv: () }
+        Self {
+            _priv: (),
+            #[cfg(feature = "default-send-request")]
+            pool: None,
+        }
+    }
+
+    /// Configures the pool of connections used by
+    /// [`WasiHttpView::send_request`] to send outgoing requests.
+    ///
+    /// By default the process-wide [`ConnectionPool::shared`] pool is used.
+    #[cfg(feature = "default-send-request")]
+    pub fn set_connection_pool(&mut self, pool: ConnectionPool) {
+        self.pool = Some(pool);
+    }
+
+    /// Returns the pool of connections used to send outgoing requests.
+    #[cfg(feature = "default-send-request")]
+    pub fn connection_pool(&self) -> ConnectionPool {
+        match &self.pool {
+            Some(pool) => pool.clone(),
+            None => ConnectionPool::shared(),
+        }
     }
 }
 
@@ -118,13 +137,16 @@ pub trait WasiHttpView {
     }
 
     /// Send an outgoing request.
+    ///
+    /// By default requests are sent over the
+    /// [connection pool](WasiHttpCtx::connection_pool) of the context.
     #[cfg(feature = "default-send-request")]
     fn send_request(
         &mut self,
         request: hyper::Request<HyperOutgoingBody>,
         config: OutgoingRequestConfig,
     ) -> crate::HttpResult<HostFutureIncomingResponse> {
-        Ok(default_send_request(request, config))
+        Ok(self.ctx().connection_pool().send_request(request, config))
     }
 
     /// Send an outgoing request.
@@ -337,152 +359,29 @@ pub struct OutgoingRequestConfig {
 
 /// The default implementation of how an outgoing request is sent.
 ///
-/// This implementation is used by the `wasi:http/outgoing-handler` interface
-/// default implementation.
+/// This sends the request over the process-wide [`ConnectionPool::shared`]
+/// pool.
 #[cfg(feature = "default-send-request")]
 pub fn default_send_request(
     request: hyper::Request<HyperOutgoingBody>,
     config: OutgoingRequestConfig,
 ) -> HostFutureIncomingResponse {
-    let handle = wasmtime_wasi::runtime::spawn(async move {
-        Ok(default_send_request_handler(request, config).await)
-    });
-    HostFutureIncomingResponse::pending(handle)
+    ConnectionPool::shared().send_request(request, config)
 }
 
 /// The underlying implementation of how an outgoing request is sent. This should likely be spawned
 /// in a task.
 ///
-/// This is called from [default_send_request] to actually send the request.
+/// This sends the request over the process-wide [`ConnectionPool::shared`]
+/// pool, see [`ConnectionPool::send_request_handler`].
 #[cfg(feature = "default-send-request")]
 pub async fn default_send_request_handler(
-    mut request: hyper::Request<HyperOutgoingBody>,
-    OutgoingRequestConfig {
-        use_tls,
-        connect_timeout,
-        first_byte_timeout,
-        between_bytes_timeout,
-    }: OutgoingRequestConfig,
+    request: hyper::Request<HyperOutgoingBody>,
+    config: OutgoingRequestConfig,
 ) -> Result<IncomingResponse, types::ErrorCode> {
-    let authority = if let Some(authority) = request.uri().authority() {
-        if authority.port().is_some() {
-            authority.to_string()
-        } else {
-            let port = if use_tls { 443 } else { 80 };
-            format!("{}:{port}", authority.to_string())
-        }
-    } else {
-        return Err(types::ErrorCode::HttpRequestUriInvalid);
-    };
-    let tcp_stream = timeout(connect_timeout, TcpStream::connect(&authority))
-        .await
-        .map_err(|_| types::ErrorCode::ConnectionTimeout)?
-        .map_err(|e| match e.kind() {
-            std::io::ErrorKind::AddrNotAvailable => {
-                dns_error("address not available".to_string(), 0)
-            }
-
-            _ => {
-                if e.to_string()
-                    .starts_with("failed to lookup address information")
-                {
-                    dns_error("address not available".to_string(), 0)
-                } else {
-                    types::ErrorCode::ConnectionRefused
-                }
-            }
-        })?;
-
-    let (mut sender, worker) = if use_tls {
-        use rustls::pki_types::ServerName;
-
-        // derived from https://github.com/rustls/rustls/blob/main/examples/src/bin/simpleclient.rs
-        let root_cert_store = rustls::RootCertStore {
-            roots: webpki_roots::TLS_SERVER_ROOTS.into(),
-        };
-        let config = rustls::ClientConfig::builder()
-            .with_root_certificates(root_cert_store)
-            .with_no_client_auth();
-        let connector = tokio_rustls::TlsConnector::from(std::sync::Arc::new(config));
-        let mut parts = authority.split(":");
-        let host = parts.next().unwrap_or(&authority);
-        let domain = ServerName::try_from(host)
-            .map_err(|e| {
-                tracing::warn!("dns lookup error: {e:?}");
-                dns_error("invalid dns name".to_string(), 0)
-            })?
-            .to_owned();
-        let stream = connector.connect(domain, tcp_stream).await.map_err(|e| {
-            tracing::warn!("tls protocol error: {e:?}");
-            types::ErrorCode::TlsProtocolError
-        })?;
-        let stream = TokioIo::new(stream);
-
-        let (sender, conn) = timeout(
-            connect_timeout,
-            hyper::client::conn::http1::handshake(stream),
-        )
-        .await
-        .map_err(|_| types::ErrorCode::ConnectionTimeout)?
-        .map_err(hyper_request_error)?;
-
-        let worker = wasmtime_wasi::runtime::spawn(async move {
-            match conn.await {
-                Ok(()) => {}
-                // TODO: shouldn't throw away this error and ideally should
-                // surface somewhere.
-                Err(e) => tracing::warn!("dropping error {e}"),
-            }
-        });
-
-        (sender, worker)
-    } else {
-        let tcp_stream = TokioIo::new(tcp_stream);
-        let (sender, conn) = timeout(
-            connect_timeout,
-            // TODO: we should plumb the builder through the http context, and use it here
-            hyper::client::conn::http1::handshake(tcp_stream),
-        )
-        .await
-        .map_err(|_| types::ErrorCode::ConnectionTimeout)?
-        .map_err(hyper_request_error)?;
-
-        let worker = wasmtime_wasi::runtime::spawn(async move {
-            match conn.await {
-                Ok(()) => {}
-                // TODO: same as above, shouldn't throw this error away.
-                Err(e) => tracing::warn!("dropping error {e}"),
-            }
-        });
-
-        (sender, worker)
-    };
-
-    // at this point, the request contains the scheme and the authority, but
-    // the http packet should only include those if addressing a proxy, so
-    // remove them here, since SendRequest::send_request does not do it for us
-    *request.uri_mut() = http::Uri::builder()
-        .path_and_query(
-            request
-                .uri()
-                .path_and_query()
-                .map(|p| p.as_str())
-                .unwrap_or("/"),
-        )
-        .build()
-        .expect("comes from valid request");
-
-    let resp = timeout(first_byte_timeout, sender.send_request(request))
+    ConnectionPool::shared()
+        .send_request_handler(request, config)
         .await
-        .map_err(|_| types::ErrorCode::ConnectionReadTimeout)?
-        .map_err(hyper_request_error)?
-        .map(|body| body.map_err(hyper_request_error).boxed());
-
-    Ok(IncomingResponse {
-        resp,
-        worker: Some(worker),
-        between_bytes_timeout,
-    })
 }
 
 impl From<http::Method> for types::Method {
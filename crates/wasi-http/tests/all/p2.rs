@@ -6,7 +6,16 @@ use http_body::Frame;
 use http_body_util::{BodyExt, Collected, Empty, StreamBody, combinators::BoxBody};
 use hyper::{Method, StatusCode, body::Bytes, server::conn::http1, service::service_fn};
 use sha2::{Digest, Sha256};
-use std::{collections::HashMap, iter, net::Ipv4Addr, str, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    iter,
+    net::Ipv4Addr,
+    str,
+    sync::Arc,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 use tokio::task;
 use wasmtime::{
     Config, Engine, Store,
@@ -14,9 +23,10 @@ use wasmtime::{
 };
 use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView, p2::pipe::MemoryOutputPipe};
 use wasmtime_wasi_http::{
-    HttpResult, WasiHttpCtx, WasiHttpView,
+    ConnectionPool, ConnectionPoolConfig, HttpResult, WasiHttpCtx, WasiHttpView,
     bindings::http::types::{ErrorCode, Scheme},
     body::HyperOutgoingBody,
+    io::TokioExecutor,
     io::TokioIo,
     types::{self, HostFutureIncomingResponse, IncomingResponse, OutgoingRequestConfig},
 };
@@ -583,3 +593,161 @@ async fn wasi_http_no_trap_on_early_drop() -> Result<()> {
         panic!("test expects an error");
     }
 }
+
+/// Spawns a server which serves `hello` to every request over connections set
+/// up by `serve`, returning its address and the number of accepted
+/// connections.
+async fn spawn_counting_server<F>(
+    serve: impl Fn(tokio::net::TcpStream) -> F + Send + 'static,
+) -> Result<(String, Arc<AtomicUsize>, task::JoinHandle<()>)>
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0)).await?;
+    let port = listener.local_addr()?.port();
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let server = task::spawn({
+        let accepted = accepted.clone();
+        async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let conn = serve(stream);
+                task::spawn(async move {
+                    if let Err(e) = conn.await {
+                        eprintln!("error serving connection: {e:?}");
+                    }
+                });
+            }
+        }
+    });
+    Ok((format!("localhost:{port}"), accepted, server))
+}
+
+async fn hello(
+    _: hyper::Request<hyper::body::Incoming>,
+) -> Result<hyper::Response<BoxBody<Bytes, hyper::Error>>> {
+    Ok(hyper::Response::new(body::full(Bytes::from_static(
+        b"hello",
+    ))))
+}
+
+async fn serve_http1(stream: tokio::net::TcpStream) -> Result<()> {
+    http1::Builder::new()
+        .keep_alive(true)
+        .serve_connection(TokioIo::new(stream), service_fn(hello))
+        .await?;
+    Ok(())
+}
+
+/// Sends a `GET` request for `uri` over `pool` and returns the response body.
+async fn get(pool: &ConnectionPool, uri: &str) -> Result<(http::Version, Bytes)> {
+    let request = hyper::Request::builder()
+        .uri(uri)
+        .header(
+            hyper::header::HOST,
+            hyper::Uri::try_from(uri)?.authority().unwrap().as_str(),
+        )
+        .body(Empty::new().map_err(|e| match e {}).boxed())?;
+    let config = OutgoingRequestConfig {
+        use_tls: uri.starts_with("https:"),
+        connect_timeout: Duration::from_secs(10),
+        first_byte_timeout: Duration::from_secs(10),
+        between_bytes_timeout: Duration::from_secs(10),
+    };
+    let response = pool.send_request_handler(request, config).await?;
+    assert_eq!(response.resp.status(), StatusCode::OK);
+    let version = response.resp.version();
+    let body = response.resp.into_body().collect().await?.to_bytes();
+    Ok((version, body))
+}
+
+#[test_log::test(tokio::test)]
+async fn wasi_http_connection_pool_reuses_connections() -> Result<()> {
+    let (addr, accepted, server) = spawn_counting_server(serve_http1).await?;
+
+    let pool = ConnectionPool::new(ConnectionPoolConfig::default());
+    for _ in 0..3 {
+        let (_, body) = get(&pool, &format!("http://{addr}/")).await?;
+        assert_eq!(&body[..], b"hello");
+    }
+
+    // All requests were sent sequentially, so the connection opened by the
+    // first one should have been reused by the others.
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    server.abort();
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn wasi_http_connection_pool_limits_connections() -> Result<()> {
+    let (addr, accepted, server) = spawn_counting_server(serve_http1).await?;
+
+    let pool = ConnectionPool::new(ConnectionPoolConfig {
+        max_connections_per_host: Some(1),
+        ..ConnectionPoolConfig::default()
+    });
+    let uri = format!("http://{addr}/");
+    let bodies = future::try_join_all((0..3).map(|_| get(&pool, &uri))).await?;
+    for (_, body) in bodies {
+        assert_eq!(&body[..], b"hello");
+    }
+
+    // The requests were sent concurrently, but had to take turns using the
+    // only connection allowed.
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    server.abort();
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn wasi_http_connection_pool_shares_http2_connections() -> Result<()> {
+    let certs = rustls_pemfile::certs(&mut &include_bytes!("localhost.crt")[..])
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut &include_bytes!("localhost.key")[..])?
+        .context("missing private key")?;
+    let mut config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs.clone(), key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+    let (addr, accepted, server) = spawn_counting_server(move |stream| {
+        let acceptor = acceptor.clone();
+        async move {
+            let stream = acceptor.accept(stream).await?;
+            anyhow::ensure!(stream.get_ref().1.alpn_protocol() == Some(&b"h2"[..]));
+            hyper::server::conn::http2::Builder::new(TokioExecutor)
+                .serve_connection(TokioIo::new(stream), service_fn(hello))
+                .await?;
+            Ok(())
+        }
+    })
+    .await?;
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in certs {
+        roots.add(cert)?;
+    }
+    let tls = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let pool = ConnectionPool::with_client_config(ConnectionPoolConfig::default(), tls);
+
+    // Send the first requests concurrently, so that they race to open a
+    // connection, and then another one once that connection is established.
+    let uri = format!("https://{addr}/");
+    let mut responses = future::try_join_all((0..3).map(|_| get(&pool, &uri))).await?;
+    responses.push(get(&pool, &uri).await?);
+    for (version, body) in responses {
+        assert_eq!(version, http::Version::HTTP_2);
+        assert_eq!(&body[..], b"hello");
+    }
+
+    // All requests should have been multiplexed over one connection.
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    server.abort();
+    Ok(())
+}
@@ -0,0 +1,673 @@
+//! Storage backends which `wasi:keyvalue/store.open` identifiers map onto.
+
+use crate::Error;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A storage backend for a single `wasi:keyvalue` bucket.
+///
+/// Backends are registered with [`WasiKeyValueCtxBuilder::backend`] under an
+/// identifier, and every `store.open` call with that identifier returns a
+/// bucket which operates on the same backend. Backends are shared between all
+/// buckets (and all stores) that the context is cloned into, so all methods
+/// take `&self` and implementations must synchronize internally.
+///
+/// [`WasiKeyValueCtxBuilder::backend`]: crate::WasiKeyValueCtxBuilder::backend
+pub trait Backend: Send + Sync {
+    /// Returns the value associated with `key`, if any.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Associates `value` with `key`, overwriting any previous value.
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), Error>;
+
+    /// Removes `key` from the backend, returning whether it had a value.
+    /// Removing a missing key is not an error.
+    fn delete(&self, key: &str) -> Result<bool, Error>;
+
+    /// Returns whether `key` currently has a value.
+    fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Returns all keys currently stored in the backend.
+    ///
+    /// The returned keys must be in a stable order so that cursors handed out
+    /// by `list-keys` stay meaningful between calls.
+    fn list_keys(&self) -> Result<Vec<String>, Error>;
+
+    /// Atomically replaces the value of `key` with `new` if, and only if, its
+    /// current value is `current`.
+    ///
+    /// `None` for `current` means "the key is absent" and `None` for `new`
+    /// deletes the key. Returns whether the swap took place. This is the
+    /// primitive that all of `wasi:keyvalue/atomics` is built on, so it must
+    /// be atomic with respect to every other method of this backend.
+    fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, Error>;
+
+    /// Atomically adds `delta` to the decimal integer stored at `key` and
+    /// returns the new value, treating a missing key as zero.
+    ///
+    /// The default implementation retries [`Backend::compare_and_swap`] until
+    /// it succeeds.
+    fn increment(&self, key: &str, delta: u64) -> Result<u64, Error> {
+        loop {
+            let current = self.get(key)?;
+            let new = parse_counter(current.as_deref())?
+                .checked_add(delta)
+                .ok_or_else(|| Error::Other(format!("incrementing `{key}` overflowed")))?;
+            let new_bytes = new.to_string().into_bytes();
+            if self.compare_and_swap(key, current.as_deref(), Some(&new_bytes))? {
+                return Ok(new);
+            }
+        }
+    }
+
+    /// Returns the values of all of `keys`, in order.
+    ///
+    /// The default implementation calls [`Backend::get`] for each key and so
+    /// is not atomic; backends should override it if they can do better.
+    fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        keys.iter().map(|key| self.get(key)).collect()
+    }
+
+    /// Sets all of `key_values`.
+    ///
+    /// The default implementation calls [`Backend::set`] for each pair and so
+    /// is not atomic; backends should override it if they can do better.
+    fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        for (key, value) in key_values {
+            self.set(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes all of `keys`, returning whether each of them had a value
+    /// when it was deleted.
+    ///
+    /// The default implementation calls [`Backend::delete`] for each key and
+    /// so is not atomic; backends should override it if they can do better.
+    fn delete_many(&self, keys: &[String]) -> Result<Vec<bool>, Error> {
+        keys.iter().map(|key| self.delete(key)).collect()
+    }
+}
+
+/// Parses the value of a key used as an `atomics.increment` counter.
+fn parse_counter(value: Option<&[u8]>) -> Result<u64, Error> {
+    let Some(value) = value else {
+        return Ok(0);
+    };
+    std::str::from_utf8(value)
+        .map_err(|e| Error::Other(e.to_string()))?
+        .parse::<u64>()
+        .map_err(|e| Error::Other(e.to_string()))
+}
+
+/// Implementation of [`Backend::compare_and_swap`] for data kept in a
+/// `BTreeMap`.
+fn compare_and_swap_map(
+    data: &mut BTreeMap<String, Vec<u8>>,
+    key: &str,
+    current: Option<&[u8]>,
+    new: Option<&[u8]>,
+) -> bool {
+    if data.get(key).map(|v| &v[..]) != current {
+        return false;
+    }
+    match new {
+        Some(new) => {
+            data.insert(key.to_string(), new.to_vec());
+        }
+        None => {
+            data.remove(key);
+        }
+    }
+    true
+}
+
+/// A [`Backend`] which keeps all data in memory.
+///
+/// Data stored here lives as long as the backend itself, which is as long as
+/// any [`WasiKeyValueCtx`](crate::WasiKeyValueCtx) referencing it.
+#[derive(Default)]
+pub struct InMemory {
+    data: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl InMemory {
+    /// Creates a new, empty, in-memory backend.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new in-memory backend preloaded with `data`.
+    pub fn with_data<I, K, V>(data: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<Vec<u8>>,
+    {
+        Self {
+            data: Mutex::new(
+                data.into_iter()
+                    .map(|(k, v)| (k.into(), v.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Backend for InMemory {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.data.lock().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.data
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.data.lock().unwrap().remove(key).is_some())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.data.lock().unwrap().contains_key(key))
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>, Error> {
+        Ok(self.data.lock().unwrap().keys().cloned().collect())
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, Error> {
+        let mut data = self.data.lock().unwrap();
+        Ok(compare_and_swap_map(&mut data, key, current, new))
+    }
+
+    fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(keys.iter().map(|key| data.get(key).cloned()).collect())
+    }
+
+    fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for (key, value) in key_values {
+            data.insert(key.clone(), value.clone());
+        }
+        Ok(())
+    }
+
+    fn delete_many(&self, keys: &[String]) -> Result<Vec<bool>, Error> {
+        let mut data = self.data.lock().unwrap();
+        Ok(keys.iter().map(|key| data.remove(key).is_some()).collect())
+    }
+}
+
+/// Magic bytes at the start of every file written by [`FileBackend`].
+const FILE_MAGIC: &[u8; 8] = b"wasmtkv\x01";
+
+/// The size below which the log of a [`FileBackend`] is never compacted.
+const COMPACT_MIN_LEN: u64 = 1 << 20;
+
+/// A [`Backend`] which persists all data to a single file on the host.
+///
+/// The whole database is held in memory while the file is a log of every
+/// mutation, each of which is appended and synced to disk before it takes
+/// effect. Every mutation is checksummed, and a mutation interrupted by a
+/// crash, which can only be the last one in the log, is discarded when the
+/// file is next opened. A damaged mutation anywhere else fails opening the
+/// file rather than discarding the mutations after it. Once the log is more than twice the size of the data it
+/// describes it's compacted by writing the data to a sibling temporary file
+/// and atomically renaming it over the log.
+///
+/// While open, the file is locked by taking an exclusive advisory lock on a
+/// sibling file with a `.lock` extension appended, so opening the same file
+/// again, in this or another process, fails. Within a process, share one
+/// backend by cloning the [`WasiKeyValueCtx`](crate::WasiKeyValueCtx) it is
+/// registered with.
+pub struct FileBackend {
+    path: PathBuf,
+    state: Mutex<FileState>,
+    /// The lock file, which is unlocked when closed.
+    _lock: File,
+}
+
+/// The state of a [`FileBackend`] modified by mutations.
+struct FileState {
+    data: BTreeMap<String, Vec<u8>>,
+    /// The log, opened for appending.
+    log: File,
+    /// The length of `log` in bytes.
+    log_len: u64,
+    /// The length in bytes of the log which compacting `data` would write.
+    live_len: u64,
+}
+
+/// A mutation recorded in the log of a [`FileBackend`].
+enum Op<'a> {
+    Set(&'a str, &'a [u8]),
+    Delete(&'a str),
+}
+
+impl Op<'_> {
+    const SET: u8 = 0;
+    const DELETE: u8 = 1;
+
+    fn key(&self) -> &str {
+        match self {
+            Op::Set(key, _) | Op::Delete(key) => key,
+        }
+    }
+}
+
+impl FileBackend {
+    /// Opens the database stored at `path`, creating an empty one if the
+    /// file does not exist yet.
+    ///
+    /// The parent directory of `path` must already exist. Fails if the
+    /// database is already open.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<FileBackend> {
+        let path = path.as_ref().to_path_buf();
+        let lock = lock(&sibling(&path, ".lock"))?;
+
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+        if bytes.is_empty() {
+            log.write_all(FILE_MAGIC)?;
+            log.sync_all()?;
+            bytes.extend_from_slice(FILE_MAGIC);
+        }
+        let (data, valid_len) = replay(&bytes).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "`{}` is not a valid key-value database: {e}",
+                    path.display()
+                ),
+            )
+        })?;
+        // Discard a mutation which was interrupted while being appended.
+        let log_len = u64::try_from(valid_len).unwrap();
+        if valid_len < bytes.len() {
+            log.set_len(log_len)?;
+            log.sync_all()?;
+        }
+
+        let live_len = data.iter().map(|(k, v)| op_len(&Op::Set(k, v))).sum();
+        Ok(FileBackend {
+            path,
+            state: Mutex::new(FileState {
+                data,
+                log,
+                log_len,
+                live_len,
+            }),
+            _lock: lock,
+        })
+    }
+
+    /// Returns the path of the file backing this database.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Durably appends `ops` to the log as a single mutation and then applies
+    /// them to the in-memory data.
+    fn apply(&self, state: &mut FileState, ops: &[Op<'_>]) -> Result<(), Error> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+        self.append(state, ops)
+            .map_err(|e| self.error("write", e))?;
+        for op in ops {
+            if let Some(old) = state.data.get(op.key()) {
+                state.live_len -= op_len(&Op::Set(op.key(), old));
+            }
+            match *op {
+                Op::Set(key, value) => {
+                    state.live_len += op_len(op);
+                    state.data.insert(key.to_string(), value.to_vec());
+                }
+                Op::Delete(key) => {
+                    state.data.remove(key);
+                }
+            }
+        }
+        if state.log_len > COMPACT_MIN_LEN && state.log_len > 2 * state.live_len {
+            self.compact(state).map_err(|e| self.error("compact", e))?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, state: &mut FileState, ops: &[Op<'_>]) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        encode(&mut bytes, ops);
+        let result = state
+            .log
+            .write_all(&bytes)
+            .and_then(|()| state.log.sync_data());
+        if let Err(e) = result {
+            // Remove whatever part of the mutation was written so that the
+            // log stays valid for the following ones.
+            let _ = state.log.set_len(state.log_len);
+            return Err(e);
+        }
+        state.log_len += u64::try_from(bytes.len()).unwrap();
+        Ok(())
+    }
+
+    /// Replaces the log with one describing only the current data.
+    fn compact(&self, state: &mut FileState) -> io::Result<()> {
+        let tmp = sibling(&self.path, ".tmp");
+        let mut bytes = FILE_MAGIC.to_vec();
+        let ops = state
+            .data
+            .iter()
+            .map(|(k, v)| Op::Set(k, v))
+            .collect::<Vec<_>>();
+        encode(&mut bytes, &ops);
+        let mut file = File::create(&tmp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&tmp, &self.path)?;
+
+        state.log = OpenOptions::new().append(true).open(&self.path)?;
+        state.log_len = u64::try_from(bytes.len()).unwrap();
+        Ok(())
+    }
+
+    fn error(&self, action: &str, e: io::Error) -> Error {
+        Error::Other(format!(
+            "failed to {action} key-value database `{}`: {e}",
+            self.path.display()
+        ))
+    }
+}
+
+impl Backend for FileBackend {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.state.lock().unwrap().data.get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        self.apply(&mut state, &[Op::Set(key, value)])
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        if !state.data.contains_key(key) {
+            return Ok(false);
+        }
+        self.apply(&mut state, &[Op::Delete(key)])?;
+        Ok(true)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.state.lock().unwrap().data.contains_key(key))
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>, Error> {
+        Ok(self.state.lock().unwrap().data.keys().cloned().collect())
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.data.get(key).map(|v| &v[..]) != current {
+            return Ok(false);
+        }
+        let op = match new {
+            Some(new) => Op::Set(key, new),
+            None if current.is_none() => return Ok(true),
+            None => Op::Delete(key),
+        };
+        self.apply(&mut state, &[op])?;
+        Ok(true)
+    }
+
+    fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        let state = self.state.lock().unwrap();
+        Ok(keys
+            .iter()
+            .map(|key| state.data.get(key).cloned())
+            .collect())
+    }
+
+    fn set_many(&self, key_values: &[(String, Vec<u8>)]) -> Result<(), Error> {
+        let ops = key_values
+            .iter()
+            .map(|(key, value)| Op::Set(key, value))
+            .collect::<Vec<_>>();
+        let mut state = self.state.lock().unwrap();
+        self.apply(&mut state, &ops)
+    }
+
+    fn delete_many(&self, keys: &[String]) -> Result<Vec<bool>, Error> {
+        let mut state = self.state.lock().unwrap();
+        // Only the first of duplicate keys deletes anything.
+        let mut deleted = HashSet::new();
+        let existed = keys
+            .iter()
+            .map(|key| state.data.contains_key(key) && deleted.insert(key))
+            .collect::<Vec<_>>();
+        let ops = deleted
+            .iter()
+            .map(|key| Op::Delete(key))
+            .collect::<Vec<_>>();
+        self.apply(&mut state, &ops)?;
+        Ok(existed)
+    }
+}
+
+/// Returns `path` with `suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Opens the file at `path`, creating it if needed, and takes an exclusive
+/// advisory lock on it which is held until the returned file is closed.
+fn lock(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true);
+    let already_locked = || {
+        io::Error::new(
+            io::ErrorKind::WouldBlock,
+            format!("`{}` is locked by another user", path.display()),
+        )
+    };
+
+    #[cfg(unix)]
+    {
+        use rustix::fs::{FlockOperation, flock};
+        let file = options.open(path)?;
+        match flock(&file, FlockOperation::NonBlockingLockExclusive) {
+            Ok(()) => Ok(file),
+            Err(rustix::io::Errno::WOULDBLOCK) => Err(already_locked()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        // Opening the file without sharing it prevents anyone else from
+        // opening it until it's closed.
+        use std::os::windows::fs::OpenOptionsExt;
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+        match options.share_mode(0).open(path) {
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Err(already_locked()),
+            result => result,
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = already_locked;
+        options.open(path)
+    }
+}
+
+/// The length of `op` when encoded in the log.
+fn op_len(op: &Op<'_>) -> u64 {
+    let len = match op {
+        Op::Set(key, value) => 1 + 8 + key.len() + 8 + value.len(),
+        Op::Delete(key) => 1 + 8 + key.len(),
+    };
+    u64::try_from(len).unwrap()
+}
+
+/// The length of the header preceding each mutation in the log: the length
+/// of the mutation followed by its CRC-32.
+const MUTATION_HEADER_LEN: usize = 8 + 4;
+
+/// Encodes `ops` as a single mutation of the log.
+fn encode(dst: &mut Vec<u8>, ops: &[Op<'_>]) {
+    fn write_bytes(dst: &mut Vec<u8>, bytes: &[u8]) {
+        dst.extend_from_slice(&u64::try_from(bytes.len()).unwrap().to_le_bytes());
+        dst.extend_from_slice(bytes);
+    }
+
+    let header = dst.len();
+    dst.extend_from_slice(&[0; MUTATION_HEADER_LEN]);
+    let start = dst.len();
+    dst.extend_from_slice(&u64::try_from(ops.len()).unwrap().to_le_bytes());
+    for op in ops {
+        match op {
+            Op::Set(key, value) => {
+                dst.push(Op::SET);
+                write_bytes(dst, key.as_bytes());
+                write_bytes(dst, value);
+            }
+            Op::Delete(key) => {
+                dst.push(Op::DELETE);
+                write_bytes(dst, key.as_bytes());
+            }
+        }
+    }
+    let len = u64::try_from(dst.len() - start).unwrap();
+    let crc = crc32fast::hash(&dst[start..]);
+    dst[header..][..8].copy_from_slice(&len.to_le_bytes());
+    dst[header + 8..][..4].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Replays the mutations in the log `bytes`, returning the resulting data and
+/// the length of the log up to the end of the last complete mutation.
+///
+/// A mutation which is cut short by the end of the log, or which is followed
+/// only by zeros, is assumed to have been interrupted while being appended
+/// and is discarded along with everything after it. Any other mutation which
+/// can't be read means the log is corrupt, which is an error.
+fn replay(bytes: &[u8]) -> Result<(BTreeMap<String, Vec<u8>>, usize), String> {
+    fn read_u64(bytes: &mut &[u8]) -> Option<u64> {
+        let (n, rest) = bytes.split_first_chunk::<8>()?;
+        *bytes = rest;
+        Some(u64::from_le_bytes(*n))
+    }
+    fn read_bytes<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+        let len = usize::try_from(read_u64(bytes)?).ok()?;
+        if bytes.len() < len {
+            return None;
+        }
+        let (ret, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Some(ret)
+    }
+    /// Reads the checksummed contents of one mutation, returning `None` if
+    /// it's incomplete or its checksum doesn't match.
+    fn read_checked<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+        let len = usize::try_from(read_u64(bytes)?).ok()?;
+        let (crc, rest) = bytes.split_first_chunk::<4>()?;
+        if rest.len() < len {
+            return None;
+        }
+        let (contents, rest) = rest.split_at(len);
+        if crc32fast::hash(contents) != u32::from_le_bytes(*crc) {
+            return None;
+        }
+        *bytes = rest;
+        Some(contents)
+    }
+    /// Decodes the contents of one mutation, returning `None` if they're
+    /// invalid.
+    fn decode(mut bytes: &[u8]) -> Option<Vec<(&str, Option<&[u8]>)>> {
+        let bytes = &mut bytes;
+        let count = read_u64(bytes)?;
+        let mut ops = Vec::new();
+        for _ in 0..count {
+            let (tag, rest) = bytes.split_first()?;
+            *bytes = rest;
+            let key = std::str::from_utf8(read_bytes(bytes)?).ok()?;
+            let value = match *tag {
+                Op::SET => Some(read_bytes(bytes)?),
+                Op::DELETE => None,
+                _ => return None,
+            };
+            ops.push((key, value));
+        }
+        bytes.is_empty().then_some(ops)
+    }
+
+    let mut rest = bytes
+        .strip_prefix(FILE_MAGIC)
+        .ok_or("unrecognized file header")?;
+    let mut data = BTreeMap::new();
+    let mut valid_len = FILE_MAGIC.len();
+    while !rest.is_empty() {
+        let start = rest;
+        let Some(ops) = read_checked(&mut rest).and_then(decode) else {
+            // The length in the header of a torn mutation may be garbage,
+            // so it's torn if anything goes past the end of the log. Some
+            // filesystems also extend files with zeros before writing their
+            // contents.
+            let torn = match read_u64(&mut &start[..]) {
+                Some(len) => usize::try_from(len).map_or(true, |len| {
+                    len.saturating_add(MUTATION_HEADER_LEN) >= start.len()
+                }),
+                None => true,
+            };
+            if torn || start.iter().all(|b| *b == 0) {
+                break;
+            }
+            return Err(format!("mutation at offset {valid_len} is corrupt"));
+        };
+        for (key, value) in ops {
+            match value {
+                Some(value) => {
+                    data.insert(key.to_string(), value.to_vec());
+                }
+                None => {
+                    data.remove(key);
+                }
+            }
+        }
+        valid_len = bytes.len() - rest.len();
+    }
+    Ok((data, valid_len))
+}
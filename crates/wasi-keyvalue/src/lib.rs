@@ -4,8 +4,19 @@
 //! API. With this crate, the runtime can run components that call APIs in
 //! [wasi-keyvalue] and provide components with access to key-value storages.
 //!
+//! Each identifier passed to `store.open` is mapped to a [`Backend`] which was
+//! registered with [`WasiKeyValueCtxBuilder::backend`]. Opening an identifier
+//! without a registered backend fails with `no-such-store`.
+//!
 //! Currently supported storage backends:
-//! * In-Memory (empty identifier)
+//! * [`InMemory`] (used for the empty identifier unless configured otherwise)
+//! * [`FileBackend`], a single-file database which persists across runs
+//!
+//! All of the `store`, `atomics` and `batch` interfaces are implemented in
+//! terms of [`Backend`], and atomic operations are atomic with respect to every
+//! store sharing a [`WasiKeyValueCtx`]. Components exporting the
+//! `wasi:keyvalue/watcher` interface can be notified of changes, see the
+//! [`watch`] module.
 //!
 //! # Examples
 //!
@@ -83,12 +94,25 @@ mod generated {
 use self::generated::wasi::keyvalue;
 use anyhow::Result;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, mpsc};
 use wasmtime::component::{HasData, Resource, ResourceTable, ResourceTableError};
 
+mod backend;
+pub mod watch;
+
+pub use self::backend::{Backend, FileBackend, InMemory};
+
+/// Errors which may be returned by a [`Backend`] and are reported to the
+/// guest as `wasi:keyvalue/store.error`.
 #[doc(hidden)]
+#[derive(Debug)]
 pub enum Error {
+    /// The requested store does not exist.
     NoSuchStore,
+    /// The guest is not allowed to perform the requested operation.
     AccessDenied,
+    /// Some other, implementation-specific, error.
     Other(String),
 }
 
@@ -98,15 +122,64 @@ impl From<ResourceTableError> for Error {
     }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoSuchStore => f.write_str("no such store"),
+            Error::AccessDenied => f.write_str("access denied"),
+            Error::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 #[doc(hidden)]
 pub struct Bucket {
-    in_memory_data: HashMap<String, Vec<u8>>,
+    identifier: String,
+    backend: Arc<dyn Backend>,
+}
+
+/// A change made by a guest to a bucket, as observed by [`WasiKeyValueCtx::watch`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// `key` was set to `value` in the store opened with `identifier`.
+    Set {
+        /// The identifier the bucket was opened with.
+        identifier: String,
+        /// The key which was set.
+        key: String,
+        /// The new value of the key.
+        value: Vec<u8>,
+    },
+    /// `key` was deleted from the store opened with `identifier`.
+    Delete {
+        /// The identifier the bucket was opened with.
+        identifier: String,
+        /// The key which was deleted.
+        key: String,
+    },
+}
+
+impl WatchEvent {
+    /// Returns the identifier of the store this event happened in.
+    pub fn identifier(&self) -> &str {
+        match self {
+            WatchEvent::Set { identifier, .. } | WatchEvent::Delete { identifier, .. } => {
+                identifier
+            }
+        }
+    }
 }
 
 /// Builder-style structure used to create a [`WasiKeyValueCtx`].
-#[derive(Default)]
+///
+/// Cloning a builder is cheap and the clone shares the backends registered so
+/// far, so a builder with shared backends can be used to create contexts which
+/// each add backends of their own.
+#[derive(Clone, Default)]
 pub struct WasiKeyValueCtxBuilder {
-    in_memory_data: HashMap<String, Vec<u8>>,
+    backends: HashMap<String, Arc<dyn Backend>>,
 }
 
 impl WasiKeyValueCtxBuilder {
@@ -116,30 +189,55 @@ impl WasiKeyValueCtxBuilder {
     }
 
     /// Preset data for the In-Memory provider.
-    pub fn in_memory_data<I, K, V>(mut self, data: I) -> Self
+    ///
+    /// This registers an [`InMemory`] backend under the empty identifier,
+    /// replacing any backend previously registered there.
+    pub fn in_memory_data<I, K, V>(self, data: I) -> Self
     where
         I: IntoIterator<Item = (K, V)>,
         K: Into<String>,
         V: Into<Vec<u8>>,
     {
-        self.in_memory_data = data
-            .into_iter()
-            .map(|(k, v)| (k.into(), v.into()))
-            .collect();
+        self.backend("", InMemory::with_data(data))
+    }
+
+    /// Makes `store.open(identifier)` return buckets operating on `backend`.
+    ///
+    /// Registering a second backend under the same identifier replaces the
+    /// first one.
+    pub fn backend(
+        mut self,
+        identifier: impl Into<String>,
+        backend: impl Backend + 'static,
+    ) -> Self {
+        self.backends.insert(identifier.into(), Arc::new(backend));
         self
     }
 
     /// Uses the configured context so far to construct the final [`WasiKeyValueCtx`].
-    pub fn build(self) -> WasiKeyValueCtx {
+    ///
+    /// If no backend was registered for the empty identifier then an empty
+    /// [`InMemory`] backend is used for it.
+    pub fn build(mut self) -> WasiKeyValueCtx {
+        self.backends
+            .entry(String::new())
+            .or_insert_with(|| Arc::new(InMemory::new()));
         WasiKeyValueCtx {
-            in_memory_data: self.in_memory_data,
+            backends: Arc::new(self.backends),
+            watchers: Default::default(),
         }
     }
 }
 
 /// Capture the state necessary for use in the `wasi-keyvalue` API implementation.
+///
+/// Cloning a context is cheap and the clone shares all of its backends with
+/// the original, so one context may be cloned into many stores which then all
+/// observe each other's writes.
+#[derive(Clone)]
 pub struct WasiKeyValueCtx {
-    in_memory_data: HashMap<String, Vec<u8>>,
+    backends: Arc<HashMap<String, Arc<dyn Backend>>>,
+    watchers: Arc<Mutex<Vec<mpsc::SyncSender<WatchEvent>>>>,
 }
 
 impl WasiKeyValueCtx {
@@ -147,6 +245,43 @@ impl WasiKeyValueCtx {
     pub fn builder() -> WasiKeyValueCtxBuilder {
         WasiKeyValueCtxBuilder::new()
     }
+
+    /// Returns the backend that `store.open(identifier)` operates on, if any.
+    ///
+    /// This may be used by the host to read or modify a store's contents
+    /// outside of any guest. Such modifications are not reported to
+    /// [`WasiKeyValueCtx::watch`].
+    pub fn backend(&self, identifier: &str) -> Option<&Arc<dyn Backend>> {
+        self.backends.get(identifier)
+    }
+
+    /// Subscribes to all changes guests make through this context, or any
+    /// clone of it.
+    ///
+    /// Every successful `set`, `delete`, `increment`, `set-many` and
+    /// `delete-many` produces one [`WatchEvent`] per affected key, where
+    /// deleting a key which has no value affects nothing. Events can be
+    /// forwarded to a component exporting `wasi:keyvalue/watcher` with
+    /// [`watch::deliver`]. Dropping the receiver unsubscribes.
+    ///
+    /// At most `capacity` events are buffered for the receiver. Guests are
+    /// never blocked on a slow receiver: if it falls further behind it's
+    /// unsubscribed instead, which it observes as a disconnection once it
+    /// has received the buffered events.
+    pub fn watch(&self, capacity: usize) -> mpsc::Receiver<WatchEvent> {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        self.watchers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn notify(&self, events: impl FnOnce() -> Vec<WatchEvent>) {
+        let mut watchers = self.watchers.lock().unwrap();
+        if watchers.is_empty() {
+            return;
+        }
+        let events = events();
+        watchers.retain(|tx| events.iter().all(|e| tx.try_send(e.clone()).is_ok()));
+    }
 }
 
 /// A wrapper capturing the needed internal `wasi-keyvalue` state.
@@ -160,16 +295,30 @@ impl<'a> WasiKeyValue<'a> {
     pub fn new(ctx: &'a WasiKeyValueCtx, table: &'a mut ResourceTable) -> Self {
         Self { ctx, table }
     }
+
+    /// Opens the store named `identifier` and pushes a bucket for it into
+    /// the resource table, exactly as a guest's `store.open` would.
+    pub fn open_bucket(&mut self, identifier: &str) -> Result<Resource<Bucket>, Error> {
+        let backend = self
+            .ctx
+            .backends
+            .get(identifier)
+            .ok_or(Error::NoSuchStore)?
+            .clone();
+        Ok(self.table.push(Bucket {
+            identifier: identifier.to_string(),
+            backend,
+        })?)
+    }
+
+    fn bucket(&self, bucket: &Resource<Bucket>) -> Result<&Bucket, Error> {
+        Ok(self.table.get(bucket)?)
+    }
 }
 
 impl keyvalue::store::Host for WasiKeyValue<'_> {
     fn open(&mut self, identifier: String) -> Result<Resource<Bucket>, Error> {
-        match identifier.as_str() {
-            "" => Ok(self.table.push(Bucket {
-                in_memory_data: self.ctx.in_memory_data.clone(),
-            })?),
-            _ => Err(Error::NoSuchStore),
-        }
+        self.open_bucket(&identifier)
     }
 
     fn convert_error(&mut self, err: Error) -> Result<keyvalue::store::Error> {
@@ -183,25 +332,38 @@ impl keyvalue::store::Host for WasiKeyValue<'_> {
 
 impl keyvalue::store::HostBucket for WasiKeyValue<'_> {
     fn get(&mut self, bucket: Resource<Bucket>, key: String) -> Result<Option<Vec<u8>>, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        Ok(bucket.in_memory_data.get(&key).cloned())
+        self.bucket(&bucket)?.backend.get(&key)
     }
 
     fn set(&mut self, bucket: Resource<Bucket>, key: String, value: Vec<u8>) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        bucket.in_memory_data.insert(key, value);
+        let bucket = self.bucket(&bucket)?;
+        bucket.backend.set(&key, &value)?;
+        self.ctx.notify(|| {
+            vec![WatchEvent::Set {
+                identifier: bucket.identifier.clone(),
+                key,
+                value,
+            }]
+        });
         Ok(())
     }
 
     fn delete(&mut self, bucket: Resource<Bucket>, key: String) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        bucket.in_memory_data.remove(&key);
+        let bucket = self.bucket(&bucket)?;
+        if !bucket.backend.delete(&key)? {
+            return Ok(());
+        }
+        self.ctx.notify(|| {
+            vec![WatchEvent::Delete {
+                identifier: bucket.identifier.clone(),
+                key,
+            }]
+        });
         Ok(())
     }
 
     fn exists(&mut self, bucket: Resource<Bucket>, key: String) -> Result<bool, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        Ok(bucket.in_memory_data.contains_key(&key))
+        self.bucket(&bucket)?.backend.exists(&key)
     }
 
     fn list_keys(
@@ -209,12 +371,10 @@ impl keyvalue::store::HostBucket for WasiKeyValue<'_> {
         bucket: Resource<Bucket>,
         cursor: Option<u64>,
     ) -> Result<keyvalue::store::KeyResponse, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        let keys: Vec<String> = bucket.in_memory_data.keys().cloned().collect();
-        let cursor = cursor.unwrap_or(0) as usize;
-        let keys_slice = &keys[cursor..];
+        let keys = self.bucket(&bucket)?.backend.list_keys()?;
+        let cursor = usize::try_from(cursor.unwrap_or(0)).unwrap_or(usize::MAX);
         Ok(keyvalue::store::KeyResponse {
-            keys: keys_slice.to_vec(),
+            keys: keys.get(cursor..).unwrap_or_default().to_vec(),
             cursor: None,
         })
     }
@@ -232,17 +392,15 @@ impl keyvalue::atomics::Host for WasiKeyValue<'_> {
         key: String,
         delta: u64,
     ) -> Result<u64, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        let value = bucket
-            .in_memory_data
-            .entry(key.clone())
-            .or_insert("0".to_string().into_bytes());
-        let current_value = String::from_utf8(value.clone())
-            .map_err(|e| Error::Other(e.to_string()))?
-            .parse::<u64>()
-            .map_err(|e| Error::Other(e.to_string()))?;
-        let new_value = current_value + delta;
-        *value = new_value.to_string().into_bytes();
+        let bucket = self.bucket(&bucket)?;
+        let new_value = bucket.backend.increment(&key, delta)?;
+        self.ctx.notify(|| {
+            vec![WatchEvent::Set {
+                identifier: bucket.identifier.clone(),
+                key,
+                value: new_value.to_string().into_bytes(),
+            }]
+        });
         Ok(new_value)
     }
 }
@@ -253,15 +411,11 @@ impl keyvalue::batch::Host for WasiKeyValue<'_> {
         bucket: Resource<Bucket>,
         keys: Vec<String>,
     ) -> Result<Vec<Option<(String, Vec<u8>)>>, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
+        let values = self.bucket(&bucket)?.backend.get_many(&keys)?;
         Ok(keys
             .into_iter()
-            .map(|key| {
-                bucket
-                    .in_memory_data
-                    .get(&key)
-                    .map(|value| (key.clone(), value.clone()))
-            })
+            .zip(values)
+            .map(|(key, value)| value.map(|value| (key, value)))
             .collect())
     }
 
@@ -270,18 +424,34 @@ impl keyvalue::batch::Host for WasiKeyValue<'_> {
         bucket: Resource<Bucket>,
         key_values: Vec<(String, Vec<u8>)>,
     ) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        for (key, value) in key_values {
-            bucket.in_memory_data.insert(key, value);
-        }
+        let bucket = self.bucket(&bucket)?;
+        bucket.backend.set_many(&key_values)?;
+        self.ctx.notify(|| {
+            key_values
+                .into_iter()
+                .map(|(key, value)| WatchEvent::Set {
+                    identifier: bucket.identifier.clone(),
+                    key,
+                    value,
+                })
+                .collect()
+        });
         Ok(())
     }
 
     fn delete_many(&mut self, bucket: Resource<Bucket>, keys: Vec<String>) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        for key in keys {
-            bucket.in_memory_data.remove(&key);
-        }
+        let bucket = self.bucket(&bucket)?;
+        let existed = bucket.backend.delete_many(&keys)?;
+        self.ctx.notify(|| {
+            keys.into_iter()
+                .zip(existed)
+                .filter(|(_, existed)| *existed)
+                .map(|(key, _)| WatchEvent::Delete {
+                    identifier: bucket.identifier.clone(),
+                    key,
+                })
+                .collect()
+        });
         Ok(())
     }
 }
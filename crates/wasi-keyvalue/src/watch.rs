@@ -0,0 +1,55 @@
+//! Support for components exporting `wasi:keyvalue/watcher`.
+//!
+//! A component targeting the `wasi:keyvalue/watch-service` world imports the
+//! same interfaces as `wasi:keyvalue/imports`, which are added to a linker with
+//! [`add_to_linker`](crate::add_to_linker), and additionally exports callbacks
+//! which the host invokes when keys change. Changes are observed with
+//! [`WasiKeyValueCtx::watch`](crate::WasiKeyValueCtx::watch) and handed to the
+//! component with [`deliver`].
+
+use crate::{WasiKeyValue, WatchEvent};
+use anyhow::Result;
+use wasmtime::AsContextMut;
+
+#[expect(missing_docs, reason = "bindgen-generated code")]
+mod generated {
+    wasmtime::component::bindgen!({
+        path: "wit",
+        world: "wasi:keyvalue/watch-service",
+        exports: { default: async },
+        with: {
+            "wasi:keyvalue/store": crate::generated::wasi::keyvalue::store,
+            "wasi:keyvalue/atomics": crate::generated::wasi::keyvalue::atomics,
+            "wasi:keyvalue/batch": crate::generated::wasi::keyvalue::batch,
+        },
+    });
+}
+
+/// Raw bindings to the `wasi:keyvalue/watch-service` exports.
+pub use self::generated::exports;
+
+/// Bindings to the `wasi:keyvalue/watch-service` world.
+pub use self::generated::{WatchService, WatchServiceIndices, WatchServicePre};
+
+/// Delivers `event` to the `wasi:keyvalue/watcher` export of `service`.
+///
+/// A fresh bucket for the store named by the event is opened in `store`, using
+/// `f` to project the `wasi-keyvalue` state out of the store's data just like
+/// [`add_to_linker`](crate::add_to_linker), and ownership of it is passed to
+/// the component's `on-set` or `on-delete` callback.
+pub async fn deliver<T: Send + 'static>(
+    mut store: impl AsContextMut<Data = T>,
+    service: &WatchService,
+    f: fn(&mut T) -> WasiKeyValue<'_>,
+    event: &WatchEvent,
+) -> Result<()> {
+    let mut store = store.as_context_mut();
+    let bucket = f(store.data_mut()).open_bucket(event.identifier())?;
+    let watcher = service.wasi_keyvalue_watcher();
+    match event {
+        WatchEvent::Set { key, value, .. } => {
+            watcher.call_on_set(&mut store, bucket, key, value).await
+        }
+        WatchEvent::Delete { key, .. } => watcher.call_on_delete(&mut store, bucket, key).await,
+    }
+}
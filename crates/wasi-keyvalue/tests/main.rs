@@ -1,11 +1,17 @@
 use anyhow::{Result, anyhow};
-use test_programs_artifacts::{KEYVALUE_MAIN_COMPONENT, foreach_keyvalue};
+use std::sync::Arc;
+use test_programs_artifacts::{
+    KEYVALUE_MAIN_COMPONENT, KEYVALUE_WATCHER_COMPONENT, foreach_keyvalue,
+};
 use wasmtime::{
     Store,
     component::{Component, Linker, ResourceTable},
 };
 use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView, p2::bindings::Command};
-use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx, WasiKeyValueCtxBuilder};
+use wasmtime_wasi_keyvalue::{
+    Backend, FileBackend, InMemory, WasiKeyValue, WasiKeyValueCtx, WasiKeyValueCtxBuilder,
+    WatchEvent, watch,
+};
 
 struct Ctx {
     table: ResourceTable,
@@ -66,3 +72,271 @@ async fn keyvalue_main() -> Result<()> {
     )
     .await
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyvalue_file_backend() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("store.db");
+
+    let backend = FileBackend::open(&path)?;
+    backend.set("atomics_key", b"5").unwrap();
+    drop(backend);
+
+    run_wasi(
+        KEYVALUE_MAIN_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtx::builder().inherit_stderr().build(),
+            wasi_keyvalue_ctx: WasiKeyValueCtxBuilder::new()
+                .backend("", FileBackend::open(&path)?)
+                .build(),
+        },
+    )
+    .await?;
+
+    // Everything the guest wrote should have survived the store.
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.get("atomics_key").unwrap(), Some(b"6".to_vec()));
+    assert_eq!(backend.get("b1").unwrap(), Some(b"v1".to_vec()));
+    assert!(!backend.exists("a1").unwrap());
+    assert!(!backend.exists("hello").unwrap());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyvalue_watcher() -> Result<()> {
+    let wasi_keyvalue_ctx = WasiKeyValueCtxBuilder::new()
+        .in_memory_data([("atomics_key", "5")])
+        .build();
+    let events = wasi_keyvalue_ctx.watch(16);
+    let lagging = wasi_keyvalue_ctx.watch(1);
+
+    run_wasi(
+        KEYVALUE_MAIN_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtx::builder().inherit_stderr().build(),
+            wasi_keyvalue_ctx: wasi_keyvalue_ctx.clone(),
+        },
+    )
+    .await?;
+
+    // The receiver which fell behind got as many events as it could buffer
+    // and was then unsubscribed.
+    assert_eq!(lagging.try_iter().count(), 1);
+    assert!(lagging.recv().is_err());
+
+    // Deleting keys which have no value doesn't produce events.
+    let events = events.try_iter().collect::<Vec<_>>();
+    let set = |key: &str, value: &str| WatchEvent::Set {
+        identifier: String::new(),
+        key: key.to_string(),
+        value: value.as_bytes().to_vec(),
+    };
+    let delete = |key: &str| WatchEvent::Delete {
+        identifier: String::new(),
+        key: key.to_string(),
+    };
+    assert_eq!(
+        events,
+        [
+            set("atomics_key", "6"),
+            set("hello", "world"),
+            delete("hello"),
+            set("a1", "v1"),
+            set("b1", "v1"),
+            set("c1", "v1"),
+            delete("a1"),
+            delete("c1"),
+        ]
+    );
+
+    let engine = test_programs_artifacts::engine(|config| {
+        config.async_support(true);
+    });
+    let mut store = Store::new(
+        &engine,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtx::builder().inherit_stderr().build(),
+            wasi_keyvalue_ctx: wasi_keyvalue_ctx.clone(),
+        },
+    );
+    let component = Component::from_file(&engine, KEYVALUE_WATCHER_COMPONENT)?;
+    let mut linker = Linker::new(&engine);
+    wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+    wasmtime_wasi_keyvalue::add_to_linker(&mut linker, |h: &mut Ctx| {
+        WasiKeyValue::new(&h.wasi_keyvalue_ctx, &mut h.table)
+    })?;
+    let service = watch::WatchService::instantiate_async(&mut store, &component, &linker).await?;
+
+    for event in events.iter() {
+        watch::deliver(
+            &mut store,
+            &service,
+            |h: &mut Ctx| WasiKeyValue::new(&h.wasi_keyvalue_ctx, &mut h.table),
+            event,
+        )
+        .await?;
+    }
+
+    let backend = wasi_keyvalue_ctx.backend("").unwrap();
+    assert_eq!(
+        backend.get("on-set/atomics_key").unwrap(),
+        Some(b"6".to_vec())
+    );
+    assert_eq!(backend.get("on-set/b1").unwrap(), Some(b"v1".to_vec()));
+    assert!(backend.exists("on-delete/hello").unwrap());
+    assert!(backend.exists("on-delete/c1").unwrap());
+    Ok(())
+}
+
+fn increment_concurrently(backend: Arc<dyn Backend>, threads: u64, per_thread: u64) {
+    let handles = (0..threads)
+        .map(|_| {
+            let backend = backend.clone();
+            std::thread::spawn(move || {
+                for _ in 0..per_thread {
+                    backend.increment("counter", 1).unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let expected = (threads * per_thread).to_string().into_bytes();
+    assert_eq!(backend.get("counter").unwrap(), Some(expected));
+}
+
+#[test]
+fn keyvalue_increment_is_atomic() -> Result<()> {
+    increment_concurrently(Arc::new(InMemory::new()), 8, 1000);
+
+    let dir = tempfile::tempdir()?;
+    increment_concurrently(Arc::new(FileBackend::open(dir.path().join("db"))?), 4, 25);
+    Ok(())
+}
+
+#[test]
+fn keyvalue_compare_and_swap() {
+    let backend = InMemory::new();
+    assert!(backend.compare_and_swap("k", None, Some(b"a")).unwrap());
+    assert!(!backend.compare_and_swap("k", None, Some(b"b")).unwrap());
+    assert!(
+        !backend
+            .compare_and_swap("k", Some(b"b"), Some(b"c"))
+            .unwrap()
+    );
+    assert!(
+        backend
+            .compare_and_swap("k", Some(b"a"), Some(b"c"))
+            .unwrap()
+    );
+    assert_eq!(backend.get("k").unwrap(), Some(b"c".to_vec()));
+    assert!(backend.compare_and_swap("k", Some(b"c"), None).unwrap());
+    assert!(!backend.exists("k").unwrap());
+}
+
+#[test]
+fn keyvalue_file_backend_is_locked() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("db");
+    let backend = FileBackend::open(&path)?;
+    assert!(FileBackend::open(&path).is_err());
+    drop(backend);
+    FileBackend::open(&path)?;
+    Ok(())
+}
+
+#[test]
+fn keyvalue_file_backend_discards_torn_writes() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("db");
+    let backend = FileBackend::open(&path)?;
+    backend.set("a", b"1").unwrap();
+    backend
+        .set_many(&[
+            ("b".to_string(), b"2".to_vec()),
+            ("c".to_string(), b"3".to_vec()),
+        ])
+        .unwrap();
+    drop(backend);
+
+    // Cut the last mutation short, as if the process crashed while writing
+    // it, which should discard all of it.
+    let len = std::fs::metadata(&path)?.len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&path)?
+        .set_len(len - 1)?;
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.list_keys().unwrap(), ["a"]);
+
+    // Writes after the discarded mutation are kept.
+    backend.set("d", b"4").unwrap();
+    drop(backend);
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.list_keys().unwrap(), ["a", "d"]);
+    Ok(())
+}
+
+#[test]
+fn keyvalue_file_backend_rejects_corruption() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("db");
+    let backend = FileBackend::open(&path)?;
+    backend.set("a", b"first").unwrap();
+    backend.set("b", b"second").unwrap();
+    drop(backend);
+
+    // Damaging a mutation which isn't the last one fails opening the file,
+    // and leaves the later mutations in place.
+    let mut bytes = std::fs::read(&path)?;
+    let pos = bytes.windows(5).position(|w| w == b"first").unwrap();
+    bytes[pos] ^= 1;
+    std::fs::write(&path, &bytes)?;
+    let err = FileBackend::open(&path).err().unwrap();
+    assert!(err.to_string().contains("is corrupt"), "{err}");
+    assert_eq!(std::fs::read(&path)?, bytes);
+
+    // Damage to the last mutation is indistinguishable from a torn write.
+    bytes[pos] ^= 1;
+    let pos = bytes.windows(6).position(|w| w == b"second").unwrap();
+    bytes[pos] ^= 1;
+    std::fs::write(&path, &bytes)?;
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.list_keys().unwrap(), ["a"]);
+    drop(backend);
+
+    // As are zeros appended by the filesystem.
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
+    std::io::Write::write_all(&mut file, &[0; 64])?;
+    drop(file);
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.get("a").unwrap().as_deref(), Some(&b"first"[..]));
+    Ok(())
+}
+
+#[test]
+fn keyvalue_file_backend_compacts() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("db");
+    let backend = FileBackend::open(&path)?;
+    let value = vec![0; 64 << 10];
+    for i in 0..64 {
+        backend.set("big", &value).unwrap();
+        backend.set(&format!("small{i}"), b"x").unwrap();
+        backend.delete(&format!("small{}", i / 2)).unwrap();
+    }
+
+    // Far more than the live data was written, but the log stays small.
+    let len = std::fs::metadata(&path)?.len();
+    assert!(len < 2 << 20, "log is {len} bytes");
+    drop(backend);
+
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.get("big").unwrap(), Some(value));
+    assert_eq!(backend.list_keys().unwrap().len(), 33);
+    Ok(())
+}
@@ -267,6 +267,9 @@ impl<'a> CodeBuilder<'a> {
     /// Note that this method will never cache compilations, even if the
     /// `cache` feature is enabled.
     ///
+    /// If the engine was configured with a signing key, through
+    /// `Config::signing_key`, then the returned artifact is signed with it.
+    ///
     /// # Errors
     ///
     /// This can fail if the input wasm module was not valid or if another
@@ -274,7 +277,9 @@ impl<'a> CodeBuilder<'a> {
     pub fn compile_module_serialized(&self) -> Result<Vec<u8>> {
         let wasm = self.get_wasm()?;
         let dwarf_package = self.get_dwarf_package();
-        let (v, _) = super::build_artifacts(self.engine, &wasm, dwarf_package.as_deref(), &())?;
+        let (mut v, _) =
+            super::build_artifacts::<Vec<u8>>(self.engine, &wasm, dwarf_package.as_deref(), &())?;
+        self.engine.sign_artifact(&mut v)?;
         Ok(v)
     }
 
@@ -284,7 +289,9 @@ impl<'a> CodeBuilder<'a> {
     #[cfg(feature = "component-model")]
     pub fn compile_component_serialized(&self) -> Result<Vec<u8>> {
         let bytes = self.get_wasm()?;
-        let (v, _) = super::build_component_artifacts(self.engine, &bytes, None, &())?;
+        let (mut v, _) =
+            super::build_component_artifacts::<Vec<u8>>(self.engine, &bytes, None, &())?;
+        self.engine.sign_artifact(&mut v)?;
         Ok(v)
     }
 }
@@ -310,6 +317,8 @@ impl std::hash::Hash for HashedEngineCompileEnv<'_> {
         self.0.tunables().hash(hasher);
         self.0.features().hash(hasher);
         config.wmemcheck.hash(hasher);
+        #[cfg(feature = "signing")]
+        config.signing_key.is_some().hash(hasher);
 
         // Catch accidental bugs of reusing across crate versions.
         config.module_version.hash(hasher);
@@ -27,7 +27,7 @@ use wasmtime_fiber::RuntimeFiberStackCreator;
 #[cfg(feature = "runtime")]
 pub use crate::runtime::code_memory::CustomCodeMemory;
 #[cfg(feature = "cache")]
-pub use wasmtime_cache::{Cache, CacheConfig};
+pub use wasmtime_cache::{Cache, CacheConfig, HttpStoreConfig};
 #[cfg(all(feature = "incremental-cache", feature = "cranelift"))]
 pub use wasmtime_environ::CacheStore;
 
@@ -164,6 +164,10 @@ pub struct Config {
     pub(crate) macos_use_mach_ports: bool,
     pub(crate) detect_host_feature: Option<fn(&str) -> Option<bool>>,
     pub(crate) x86_float_abi_ok: Option<bool>,
+    #[cfg(feature = "signing")]
+    pub(crate) signing_key: Option<ed25519_dalek::SigningKey>,
+    #[cfg(feature = "signing")]
+    pub(crate) trusted_signing_keys: Vec<ed25519_dalek::VerifyingKey>,
 }
 
 /// User-provided configuration for the compiler.
@@ -273,6 +277,10 @@ impl Config {
             #[cfg(not(feature = "std"))]
             detect_host_feature: None,
             x86_float_abi_ok: None,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+            #[cfg(feature = "signing")]
+            trusted_signing_keys: Vec::new(),
         };
         #[cfg(any(feature = "cranelift", feature = "winch"))]
         {
@@ -445,6 +453,31 @@ impl Config {
         self
     }
 
+    /// Configures whether compiled code is instrumented to support the
+    /// [`Store`](crate::Store)-level guest debugger.
+    ///
+    /// When enabled, generated code checks before each WebAssembly instruction
+    /// whether the store's debugger wants to observe execution, for example
+    /// because a breakpoint was set with
+    /// [`Store::add_breakpoint`](crate::Store::add_breakpoint) or
+    /// single-stepping was requested. Paused frames can then be inspected via
+    /// [`DebugFrame`](crate::DebugFrame) from within the handler installed
+    /// with [`Store::debug_handler`](crate::Store::debug_handler).
+    ///
+    /// The instrumentation is inexpensive while no breakpoints are set, but
+    /// it is not free: locals are also written to the stack whenever they're
+    /// set so that the debugger can read them, so this should only be enabled
+    /// when debugging.
+    ///
+    /// By default this option is `false`.
+    ///
+    /// **Note** Enabling this option is not compatible with the Winch compiler.
+    #[cfg(feature = "guest-debug")]
+    pub fn guest_debug(&mut self, enable: bool) -> &mut Self {
+        self.tunables.debug_instrumentation = Some(enable);
+        self
+    }
+
     /// Configures whether [`WasmBacktrace`] will be present in the context of
     /// errors returned from Wasmtime.
     ///
@@ -558,6 +591,58 @@ impl Config {
         self
     }
 
+    /// Configures whether the fuel consumed by each WebAssembly function is
+    /// tracked so that it can be reported with
+    /// [`Store::fuel_profile`](crate::Store::fuel_profile).
+    ///
+    /// When enabled, compiled code notifies the [`Store`] on entry to and exit
+    /// from every function. The store attributes the fuel consumed between
+    /// these notifications to the functions that were executing, giving both
+    /// the fuel consumed by each function itself (exclusive) and by it and
+    /// everything it called (inclusive). Because fuel counts instructions
+    /// rather than time, the resulting profile is deterministic and
+    /// comparable across machines, which makes it suitable for catching
+    /// performance regressions in CI.
+    ///
+    /// This requires [`Config::consume_fuel`] to be enabled. Notifying the
+    /// store on every call is expensive, so this also disables
+    /// [`Config::compiler_inlining`], which would otherwise hide inlined
+    /// callees from the profile.
+    ///
+    /// By default this option is `false`.
+    ///
+    /// **Note** Enabling this option is not compatible with the Winch compiler
+    /// or with the stack switching proposal.
+    ///
+    /// [`Store`]: crate::Store
+    pub fn fuel_profiling(&mut self, enable: bool) -> &mut Self {
+        self.tunables.fuel_profiling = Some(enable);
+        self
+    }
+
+    /// Configures whether compiled WebAssembly code counts how many times each
+    /// of its basic blocks executes, for code coverage.
+    ///
+    /// When enabled, Cranelift inserts a counter increment at the entry of
+    /// every function and at the start of every basic block reached through
+    /// `loop`, `if`, `else`, `end` or `br_if`. The counts of a module, summed
+    /// across all of its instances, can be read with [`Module::coverage`],
+    /// which can also render them as an LCOV report.
+    ///
+    /// Counters are updated without synchronization, so modules executing on
+    /// several threads at once may undercount. Counting has a modest runtime
+    /// cost and is intended for test suites rather than production use.
+    ///
+    /// By default this option is `false`.
+    ///
+    /// **Note** Enabling this option is not compatible with the Winch compiler.
+    ///
+    /// [`Module::coverage`]: crate::Module::coverage
+    pub fn coverage(&mut self, enable: bool) -> &mut Self {
+        self.tunables.coverage = Some(enable);
+        self
+    }
+
     /// Enables epoch-based interruption.
     ///
     /// When executing code in async mode, we sometimes want to
@@ -1888,6 +1973,51 @@ impl Config {
         Ok(self)
     }
 
+    /// Configures the Ed25519 private key used to sign precompiled artifacts.
+    ///
+    /// When configured, artifacts produced by [`Engine::precompile_module`],
+    /// [`CodeBuilder::compile_module_serialized`] and their component
+    /// counterparts contain a signature over the entire artifact. These
+    /// artifacts can then be loaded with [`Module::deserialize_verified`] by
+    /// engines which trust the corresponding public key, see
+    /// [`Config::trust_signing_key`].
+    ///
+    /// Note that artifacts produced by [`Module::serialize`] are never signed.
+    ///
+    /// The `secret_key` is the 32-byte Ed25519 secret key. By default no
+    /// artifacts are signed.
+    ///
+    /// [`Engine::precompile_module`]: crate::Engine::precompile_module
+    /// [`CodeBuilder::compile_module_serialized`]: crate::CodeBuilder::compile_module_serialized
+    /// [`Module::deserialize_verified`]: crate::Module::deserialize_verified
+    /// [`Module::serialize`]: crate::Module::serialize
+    #[cfg(all(feature = "signing", any(feature = "cranelift", feature = "winch")))]
+    pub fn signing_key(&mut self, secret_key: &[u8; 32]) -> &mut Self {
+        self.signing_key = Some(ed25519_dalek::SigningKey::from_bytes(secret_key));
+        self
+    }
+
+    /// Adds an Ed25519 public key to the set of keys trusted to sign
+    /// precompiled artifacts.
+    ///
+    /// Artifacts loaded with [`Module::deserialize_verified`] or
+    /// [`Component::deserialize_verified`] must have been signed, see
+    /// [`Config::signing_key`], by one of the trusted keys. This method may be
+    /// called multiple times to trust multiple keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `public_key` is not a valid Ed25519 public key.
+    ///
+    /// [`Module::deserialize_verified`]: crate::Module::deserialize_verified
+    /// [`Component::deserialize_verified`]: crate::component::Component::deserialize_verified
+    #[cfg(feature = "signing")]
+    pub fn trust_signing_key(&mut self, public_key: &[u8; 32]) -> Result<&mut Self> {
+        let key = crate::engine::signing::decode_public_key(public_key)?;
+        self.trusted_signing_keys.push(key);
+        Ok(self)
+    }
+
     /// Configure whether wasmtime should compile a module using multiple
     /// threads.
     ///
@@ -2004,6 +2134,28 @@ impl Config {
         self
     }
 
+    /// Configures whether compiled code records the values of wasm locals and
+    /// the operand stack so that they're included in core dumps.
+    ///
+    /// By default the frames of a core dump generated through
+    /// [`Config::coredump_on_trap`] only describe which function and
+    /// instruction each frame was executing. When this option is enabled,
+    /// generated code additionally saves the values of all locals and operand
+    /// stack values to its stack frame before each instruction which may trap
+    /// or call another function, and core dumps include these values for every
+    /// frame. Values of types which can't be represented in core dumps, such
+    /// as `v128` and references, are recorded as missing.
+    ///
+    /// This has a runtime cost for all compiled code and disables
+    /// [`Config::compiler_inlining`].
+    ///
+    /// This option is disabled by default.
+    #[cfg(feature = "coredump")]
+    pub fn coredump_locals(&mut self, enable: bool) -> &mut Self {
+        self.tunables.debug_frame_state = Some(enable);
+        self
+    }
+
     /// Enables memory error checking for wasm programs.
     ///
     /// This option is disabled by default.
@@ -2014,6 +2166,45 @@ impl Config {
         self
     }
 
+    /// Configures the memory checker to track uninitialized memory across all
+    /// of linear memory.
+    ///
+    /// By default [`Config::wmemcheck`] only checks accesses to memory
+    /// returned by `malloc` and to the stack. With this option enabled it
+    /// instead tracks whether every byte of every linear memory is defined,
+    /// similar to MemorySanitizer: `malloc`, `free`, and new stack frames
+    /// poison memory, while Wasm stores, `memory.fill`, `memory.copy`,
+    /// `memory.init`, and [`Memory::write`] define it.
+    ///
+    /// Loading uninitialized bytes isn't an error by itself. Instead compiled
+    /// code tracks which values were computed from uninitialized memory
+    /// through locals, the operand stack, and stores back to memory, and traps
+    /// when such a value is used as a branch condition, a memory address or
+    /// table index, or an argument to an imported function. Definedness isn't
+    /// tracked through globals, values passed to exception handlers, calls
+    /// between wasm functions, SIMD loads and stores, or 64-bit memories;
+    /// values from any of these are considered defined.
+    ///
+    /// Writes that the host makes through the slice returned by
+    /// [`Memory::data_mut`] aren't observed, since tracking them would require
+    /// comparing all of memory after every host call. Hosts that write to
+    /// guest memory that way should call [`Memory::wmemcheck_define`]
+    /// afterwards.
+    ///
+    /// This requires [`Config::wmemcheck`] to be enabled and is only
+    /// supported by Cranelift.
+    ///
+    /// This option is disabled by default.
+    ///
+    /// [`Memory::write`]: crate::Memory::write
+    /// [`Memory::data_mut`]: crate::Memory::data_mut
+    /// [`Memory::wmemcheck_define`]: crate::Memory::wmemcheck_define
+    #[cfg(any(feature = "cranelift", feature = "winch"))]
+    pub fn wmemcheck_uninit(&mut self, enable: bool) -> &mut Self {
+        self.tunables.wmemcheck_uninit = Some(enable);
+        self
+    }
+
     /// Configures the "guaranteed dense image size" for copy-on-write
     /// initialized memories.
     ///
@@ -2292,6 +2483,9 @@ impl Config {
         if !cfg!(feature = "wmemcheck") && self.wmemcheck {
             bail!("wmemcheck (memory checker) was requested but is not enabled in this build");
         }
+        if self.tunables.wmemcheck_uninit == Some(true) && !self.wmemcheck {
+            bail!("wmemcheck uninitialized-memory tracking requires wmemcheck to be enabled");
+        }
 
         if !cfg!(feature = "gc") && features.gc_types() {
             bail!("support for GC was disabled at compile time")
@@ -2301,6 +2495,18 @@ impl Config {
             bail!("exceptions support requires garbage collection (GC) to be enabled in the build");
         }
 
+        if self.tunables.fuel_profiling == Some(true) && self.tunables.consume_fuel != Some(true) {
+            bail!("fuel profiling requires fuel consumption to be enabled");
+        }
+
+        // Frames on stacks created by stack switching aren't distinguished
+        // from each other by the fuel profiler.
+        if self.tunables.fuel_profiling == Some(true)
+            && features.contains(WasmFeatures::STACK_SWITCHING)
+        {
+            bail!("fuel profiling is not compatible with the stack switching proposal");
+        }
+
         let mut tunables = Tunables::default_for_target(&self.compiler_target())?;
 
         // If no target is explicitly specified then further refine `tunables`
@@ -2330,6 +2536,19 @@ impl Config {
 
         self.tunables.configure(&mut tunables);
 
+        // Each function records its locals in a single slot of its own frame,
+        // and inlined callees don't have frames of their own, so don't inline
+        // when those locals are going to be recovered in core dumps.
+        if tunables.debug_frame_state {
+            tunables.inlining = false;
+        }
+
+        // Likewise inlined callees don't report their entry and exit for fuel
+        // profiling.
+        if tunables.fuel_profiling {
+            tunables.inlining = false;
+        }
+
         // If we're going to compile with winch, we must use the winch calling convention.
         #[cfg(any(feature = "cranelift", feature = "winch"))]
         {
@@ -2343,6 +2562,7 @@ impl Config {
                 Some(match self.collector.try_not_auto()? {
                     Collector::DeferredReferenceCounting => EnvCollector::DeferredReferenceCounting,
                     Collector::Null => EnvCollector::Null,
+                    Collector::Copying => EnvCollector::Copying,
                     Collector::Auto => unreachable!(),
                 })
             }
@@ -2404,7 +2624,7 @@ impl Config {
 
         #[cfg(feature = "gc")]
         #[cfg_attr(
-            not(any(feature = "gc-null", feature = "gc-drc")),
+            not(any(feature = "gc-null", feature = "gc-drc", feature = "gc-copying")),
             expect(unreachable_code, reason = "definitions known to be dummy")
         )]
         {
@@ -2423,6 +2643,13 @@ impl Config {
                 #[cfg(not(feature = "gc-null"))]
                 Collector::Null => unreachable!(),
 
+                #[cfg(feature = "gc-copying")]
+                Collector::Copying => {
+                    Arc::new(crate::runtime::vm::CopyingCollector::default()) as Arc<dyn GcRuntime>
+                }
+                #[cfg(not(feature = "gc-copying"))]
+                Collector::Copying => unreachable!(),
+
                 Collector::Auto => unreachable!(),
             }))
         }
@@ -2908,6 +3135,7 @@ impl Strategy {
 /// |-----------------------------|----------------------|-------------|----------------|----------------------|----------------------|
 /// | `DeferredReferenceCounting` | Yes, but not cycles  | 🙂         | 🙁             | 😐                   | 😐                  |
 /// | `Null`                      | No                   | 🙂         | 🙂             | 🙂                   | 🙂                  |
+/// | `Copying`                   | Yes                  | 🙁         | 🙂             | 😐                   | 🙁                  |
 ///
 /// [^1]: Whether or not the collector is capable of collecting garbage and cyclic garbage.
 ///
@@ -2972,6 +3200,20 @@ pub enum Collector {
     /// collectors, as this collector imposes as close to zero throughput and
     /// latency overhead as possible.
     Null,
+
+    /// The semi-space copying collector.
+    ///
+    /// A tracing collector that, when the heap fills up, copies every object
+    /// reachable from the roots into a fresh region of the heap and discards
+    /// everything left behind. Because it traces the heap, it can collect
+    /// cycles, and because it copies, it compacts the heap on every
+    /// collection. It does not require any read or write barriers, so Wasm
+    /// code runs with low overhead between collections, but every collection
+    /// pauses the program for time proportional to the amount of live data.
+    ///
+    /// Up to half of the GC heap is reserved as space to copy live objects
+    /// into during collection.
+    Copying,
 }
 
 impl Default for Collector {
@@ -2987,6 +3229,8 @@ impl Collector {
             Collector::Auto => {
                 if cfg!(feature = "gc-drc") {
                     Some(Collector::DeferredReferenceCounting)
+                } else if cfg!(feature = "gc-copying") {
+                    Some(Collector::Copying)
                 } else if cfg!(feature = "gc-null") {
                     Some(Collector::Null)
                 } else {
@@ -3016,12 +3260,20 @@ impl Collector {
                  the `gc-null` feature was not enabled at compile time",
             ),
 
+            #[cfg(feature = "gc-copying")]
+            Some(c @ Collector::Copying) => Ok(c),
+            #[cfg(not(feature = "gc-copying"))]
+            Some(Collector::Copying) => bail!(
+                "cannot create an engine using the copying collector because \
+                 the `gc-copying` feature was not enabled at compile time",
+            ),
+
             Some(Collector::Auto) => unreachable!(),
 
             None => bail!(
                 "cannot create an engine with GC support when none of the \
                  collectors are available; enable one of the following \
-                 features: `gc-drc`, `gc-null`",
+                 features: `gc-drc`, `gc-null`, `gc-copying`",
             ),
         }
     }
@@ -18,6 +18,8 @@ use wasmparser::WasmFeatures;
 use wasmtime_environ::{FlagValue, ObjectKind, TripleExt, Tunables};
 
 mod serialization;
+#[cfg(feature = "signing")]
+pub(crate) mod signing;
 
 /// An `Engine` which is a global context for compilation and management of wasm
 /// modules.
@@ -673,7 +675,22 @@ impl Engine {
     /// The blob of bytes is inserted into the object file specified to become part
     /// of the final compiled artifact.
     pub(crate) fn append_compiler_info(&self, obj: &mut Object<'_>) {
-        serialization::append_compiler_info(self, obj, &serialization::Metadata::new(&self))
+        serialization::append_compiler_info(self, obj, &serialization::Metadata::new(&self));
+        #[cfg(feature = "signing")]
+        if self.config().signing_key.is_some() {
+            signing::append_signature_placeholder(obj);
+        }
+    }
+
+    /// Signs the serialized artifact `bytes`, produced with this engine, if
+    /// this engine was configured with a signing key.
+    pub(crate) fn sign_artifact(&self, bytes: &mut [u8]) -> Result<()> {
+        #[cfg(feature = "signing")]
+        if let Some(key) = &self.config().signing_key {
+            signing::sign(key, bytes)?;
+        }
+        let _ = bytes;
+        Ok(())
     }
 
     #[cfg(any(feature = "cranelift", feature = "winch"))]
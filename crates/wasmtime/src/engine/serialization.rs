@@ -272,6 +272,11 @@ impl Metadata<'_> {
             parse_wasm_debuginfo,
             consume_fuel,
             epoch_interruption,
+            fuel_profiling,
+            coverage,
+            debug_instrumentation,
+            debug_frame_state,
+            wmemcheck_uninit,
             memory_may_move,
             guard_before_linear_memory,
             table_lazy_init,
@@ -324,6 +329,23 @@ impl Metadata<'_> {
             other.epoch_interruption,
             "epoch interruption",
         )?;
+        Self::check_bool(fuel_profiling, other.fuel_profiling, "fuel profiling")?;
+        Self::check_bool(coverage, other.coverage, "code coverage")?;
+        Self::check_bool(
+            debug_instrumentation,
+            other.debug_instrumentation,
+            "guest debugging instrumentation",
+        )?;
+        Self::check_bool(
+            debug_frame_state,
+            other.debug_frame_state,
+            "recording of locals for core dumps",
+        )?;
+        Self::check_bool(
+            wmemcheck_uninit,
+            other.wmemcheck_uninit,
+            "wmemcheck uninitialized-memory tracking",
+        )?;
         Self::check_bool(memory_may_move, other.memory_may_move, "memory may move")?;
         Self::check_bool(
             guard_before_linear_memory,
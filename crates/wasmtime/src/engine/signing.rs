@@ -0,0 +1,118 @@
+//! Signing and verification of precompiled artifacts.
+//!
+//! When an `Engine` is configured with a signing key then compiled artifacts
+//! contain an extra `ELF_WASM_SIGNATURE` section. This section is initially
+//! zeroed and is filled in once the final ELF image has been produced, at which
+//! point the location of the section within the image is known. The section
+//! contains:
+//!
+//! 1. The 32-byte Ed25519 public key of the signer.
+//! 2. A 64-byte Ed25519 signature over the whole ELF image where the bytes of
+//!    the signature itself are zeroed out.
+//!
+//! Notably the public key is covered by the signature, and verification only
+//! ever considers keys which the `Engine` was configured to trust.
+
+use crate::Engine;
+use crate::prelude::*;
+use ed25519_dalek::{PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH, Signature, VerifyingKey};
+use object::endian::Endianness;
+use object::read::elf::ElfFile64;
+use object::{Object as _, ObjectSection};
+use wasmtime_environ::obj;
+
+#[cfg(any(feature = "cranelift", feature = "winch"))]
+use {
+    ed25519_dalek::{Signer, SigningKey},
+    object::write::{Object, StandardSegment},
+};
+
+const SECTION_LEN: usize = PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH;
+
+/// Appends a zeroed signature section to `obj`, to be filled in later by
+/// `sign`.
+#[cfg(any(feature = "cranelift", feature = "winch"))]
+pub fn append_signature_placeholder(obj: &mut Object<'_>) {
+    let section = obj.add_section(
+        obj.segment_name(StandardSegment::Data).to_vec(),
+        obj::ELF_WASM_SIGNATURE.as_bytes().to_vec(),
+        object::SectionKind::ReadOnlyData,
+    );
+    obj.set_section_data(section, vec![0; SECTION_LEN], 1);
+}
+
+/// Signs the finished `artifact` in-place with `key`.
+#[cfg(any(feature = "cranelift", feature = "winch"))]
+pub fn sign(key: &SigningKey, artifact: &mut [u8]) -> Result<()> {
+    let start = signature_section(artifact)?
+        .ok_or_else(|| anyhow!("failed to find section `{}`", obj::ELF_WASM_SIGNATURE))?;
+    let (public_key, signature) = split(start);
+    artifact[public_key].copy_from_slice(key.verifying_key().as_bytes());
+    artifact[signature.clone()].fill(0);
+    let signature_bytes = key.sign(artifact).to_bytes();
+    artifact[signature].copy_from_slice(&signature_bytes);
+    Ok(())
+}
+
+/// Verifies that `artifact` was signed by one of the keys which `engine`
+/// trusts.
+#[cfg(feature = "runtime")]
+pub fn verify(engine: &Engine, artifact: &[u8]) -> Result<()> {
+    let trusted = &engine.config().trusted_signing_keys;
+    if trusted.is_empty() {
+        bail!("no trusted signing keys are configured for this engine");
+    }
+    let start = match signature_section(artifact)? {
+        Some(start) => start,
+        None => bail!("precompiled artifact is not signed"),
+    };
+    let (public_key, signature_range) = split(start);
+
+    let public_key = &artifact[public_key];
+    let key = match trusted.iter().find(|k| k.as_bytes()[..] == *public_key) {
+        Some(key) => key,
+        None => bail!("precompiled artifact was not signed by a trusted key"),
+    };
+    let signature = Signature::from_slice(&artifact[signature_range.clone()])
+        .map_err(|_| anyhow!("invalid signature section"))?;
+
+    let mut message = artifact.to_vec();
+    message[signature_range].fill(0);
+    key.verify_strict(&message, &signature)
+        .map_err(|_| anyhow!("signature of precompiled artifact is invalid"))
+}
+
+/// Returns the offset within `artifact` of the signature section, if any.
+fn signature_section(artifact: &[u8]) -> Result<Option<usize>> {
+    let obj = ElfFile64::<Endianness>::parse(artifact)
+        .map_err(obj::ObjectCrateErrorWrapper)
+        .context("failed to parse precompiled artifact as an ELF")?;
+    let section = match obj.section_by_name(obj::ELF_WASM_SIGNATURE) {
+        Some(section) => section,
+        None => return Ok(None),
+    };
+    match section.file_range() {
+        Some((start, len))
+            if len == SECTION_LEN as u64
+                && start
+                    .checked_add(len)
+                    .is_some_and(|end| end <= artifact.len() as u64) =>
+        {
+            Ok(Some(usize::try_from(start)?))
+        }
+        _ => bail!("invalid signature section"),
+    }
+}
+
+/// Returns the ranges of the public key and the signature within an artifact
+/// whose signature section starts at `start`.
+fn split(start: usize) -> (core::ops::Range<usize>, core::ops::Range<usize>) {
+    let public_key = start..start + PUBLIC_KEY_LENGTH;
+    let signature = public_key.end..public_key.end + SIGNATURE_LENGTH;
+    (public_key, signature)
+}
+
+/// Decodes a trusted public key for `Config::trust_signing_key`.
+pub fn decode_public_key(public_key: &[u8; PUBLIC_KEY_LENGTH]) -> Result<VerifyingKey> {
+    VerifyingKey::from_bytes(public_key).map_err(|_| anyhow!("invalid Ed25519 public key"))
+}
@@ -232,6 +232,10 @@
 //!   a core dump when a trap happens. This can be configured via
 //!   [`Config::coredump_on_trap`].
 //!
+//! * `guest-debug` - Disabled by default, this provides the [`Store`]-level
+//!   guest debugger which can set breakpoints, single-step, and inspect paused
+//!   WebAssembly frames. This can be configured via [`Config::guest_debug`].
+//!
 //! * `addr2line` - Enabled by default, this feature configures whether traps
 //!   will attempt to parse DWARF debug information and convert WebAssembly
 //!   addresses to source filenames and line numbers.
@@ -31,6 +31,7 @@ pub(crate) mod func;
 
 pub(crate) mod code;
 pub(crate) mod code_memory;
+pub(crate) mod coverage;
 #[cfg(feature = "debug-builtins")]
 pub(crate) mod debug;
 #[cfg(feature = "gc")]
@@ -38,6 +39,7 @@ pub(crate) mod exception;
 pub(crate) mod externals;
 #[cfg(feature = "async")]
 pub(crate) mod fiber;
+pub(crate) mod fuel_profile;
 pub(crate) mod gc;
 pub(crate) mod instance;
 pub(crate) mod instantiate;
@@ -74,9 +76,11 @@ cfg_if::cfg_if! {
 }
 
 pub use code_memory::CodeMemory;
+pub use coverage::{BlockCoverage, BranchCoverage, FunctionCoverage, ModuleCoverage};
 #[cfg(feature = "gc")]
 pub use exception::*;
 pub use externals::*;
+pub use fuel_profile::{FuelProfile, FuelProfileEntry};
 pub use func::*;
 pub use gc::*;
 pub use instance::{Instance, InstancePre};
@@ -95,6 +99,8 @@ pub use trap::*;
 pub use types::*;
 pub use v128::V128;
 pub use values::*;
+#[cfg(feature = "wmemcheck")]
+pub use wasmtime_wmemcheck::{Leak, LeakSummary};
 
 pub(crate) use uninhabited::*;
 
@@ -104,7 +110,7 @@ pub use vm::{PoolConcurrencyLimitError, PoolingAllocatorMetrics};
 #[cfg(feature = "profiling")]
 mod profiling;
 #[cfg(feature = "profiling")]
-pub use profiling::GuestProfiler;
+pub use profiling::{GuestProfiler, SampledFrame, SampledFunction, SamplingProfiler};
 
 #[cfg(feature = "async")]
 pub(crate) mod stack;
@@ -116,6 +122,11 @@ mod coredump;
 #[cfg(feature = "coredump")]
 pub use coredump::*;
 
+#[cfg(feature = "guest-debug")]
+mod debugger;
+#[cfg(feature = "guest-debug")]
+pub use debugger::*;
+
 #[cfg(feature = "wave")]
 mod wave;
 
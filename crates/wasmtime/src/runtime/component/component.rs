@@ -6,7 +6,7 @@ use crate::prelude::*;
 use crate::runtime::vm::open_file_for_mmap;
 use crate::runtime::vm::{CompiledModuleId, VMArrayCallFunction, VMFuncRef, VMWasmCallFunction};
 use crate::{
-    Engine, Module, ResourcesRequired, code::CodeObject, code_memory::CodeMemory,
+    Engine, Module, ModuleCoverage, ResourcesRequired, code::CodeObject, code_memory::CodeMemory,
     type_registry::TypeCollection,
 };
 use crate::{FuncType, ValType};
@@ -214,6 +214,22 @@ impl Component {
         Component::from_parts(engine, code, None)
     }
 
+    /// Same as [`Module::deserialize_verified`], but for components.
+    ///
+    /// Note that the bytes referenced here must contain contents previously
+    /// produced by [`Engine::precompile_component`] with an engine configured
+    /// with a signing key.
+    ///
+    /// [`Module::deserialize_verified`]: crate::Module::deserialize_verified
+    #[cfg(feature = "signing")]
+    pub fn deserialize_verified(engine: &Engine, bytes: impl AsRef<[u8]>) -> Result<Component> {
+        let bytes = bytes.as_ref();
+        crate::engine::signing::verify(engine, bytes)?;
+        // SAFETY: the signature was verified above with a trusted key, so these
+        // are the unmodified bytes of a trusted compilation.
+        unsafe { Component::deserialize(engine, bytes) }
+    }
+
     /// Same as [`Module::deserialize_raw`], but for components.
     ///
     /// See [`Component::deserialize`] for additional information; this method
@@ -685,6 +701,20 @@ impl Component {
         Ok(())
     }
 
+    /// Returns the code coverage of each core wasm module within this
+    /// component, in the order in which they're defined.
+    ///
+    /// This is empty unless the component was compiled with
+    /// [`Config::coverage`](crate::Config::coverage) enabled. For more
+    /// information see [`Module::coverage`](crate::Module::coverage).
+    pub fn coverage(&self) -> Vec<ModuleCoverage> {
+        self.inner
+            .static_modules
+            .values()
+            .filter_map(|module| module.coverage())
+            .collect()
+    }
+
     /// Looks up a specific export of this component by `name` optionally nested
     /// within the `instance` provided.
     ///
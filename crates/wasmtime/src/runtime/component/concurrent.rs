@@ -51,7 +51,9 @@
 //! in host functions.
 
 use crate::component::func::{self, Func, Options};
-use crate::component::{HasData, HasSelf, Instance, Resource, ResourceTable, ResourceTableError};
+use crate::component::{
+    ComponentCall, HasData, HasSelf, Instance, Resource, ResourceTable, ResourceTableError,
+};
 use crate::fiber::{self, StoreFiber, StoreFiberYield};
 use crate::store::{Store, StoreId, StoreInner, StoreOpaque, StoreToken};
 use crate::vm::component::{
@@ -1670,6 +1672,7 @@ impl Instance {
         /// the returned closure is called.
         unsafe fn make_call<T: 'static>(
             store: StoreContextMut<T>,
+            instance: Instance,
             guest_task: TableId<GuestTask>,
             callee: SendSyncPtr<VMFuncRef>,
             param_count: usize,
@@ -1689,6 +1692,7 @@ impl Instance {
 
                 lower(store, &mut storage[..param_count])?;
 
+                let depth = instance.export_call_start(store, guest_task)?;
                 let mut store = token.as_context_mut(store);
 
                 // SAFETY: Per the contract documented in `make_call's`
@@ -1697,7 +1701,7 @@ impl Instance {
                     if let Some(mut flags) = flags {
                         flags.set_may_enter(false);
                     }
-                    crate::Func::call_unchecked_raw(
+                    let result = crate::Func::call_unchecked_raw(
                         &mut store,
                         callee.as_non_null(),
                         NonNull::new(
@@ -1705,7 +1709,11 @@ impl Instance {
                                 as *mut [MaybeUninit<ValRaw>] as _,
                         )
                         .unwrap(),
-                    )?;
+                    );
+                    if let Some(depth) = depth {
+                        store.0.component_call_finish(depth);
+                    }
+                    result?;
                     if let Some(mut flags) = flags {
                         flags.set_may_enter(may_enter_after_call);
                     }
@@ -1721,6 +1729,7 @@ impl Instance {
         let call = unsafe {
             make_call(
                 store.as_context_mut(),
+                self,
                 guest_task,
                 callee,
                 param_count,
@@ -2124,18 +2133,41 @@ impl Instance {
         // `wasmtime-cranelift`-generated fused adapter code or
         // `component::Options`.  Per `wasmparser` callback signature
         // validation, we know it takes three parameters and returns one.
+        let depth = match store.0.concurrent_state_mut().guest_task {
+            Some(task) => self.export_call_start(store.0, task)?,
+            None => None,
+        };
         unsafe {
             flags.set_may_enter(false);
-            crate::Func::call_unchecked_raw(
+            let result = crate::Func::call_unchecked_raw(
                 &mut store,
                 function.as_non_null(),
                 params.as_mut_slice().into(),
-            )?;
+            );
+            if let Some(depth) = depth {
+                store.0.component_call_finish(depth);
+            }
+            result?;
             flags.set_may_enter(may_enter_after_call);
         }
         Ok(params[0].get_u32())
     }
 
+    /// Records that `task` is about to enter the guest so that core dumps can
+    /// describe the call, if it's a call of an export made by the host.
+    ///
+    /// Returns the depth to later pass to `component_call_finish`.
+    fn export_call_start(
+        self,
+        store: &mut StoreOpaque,
+        task: TableId<GuestTask>,
+    ) -> Result<Option<usize>> {
+        let export = store.concurrent_state_mut().get_mut(task)?.function_index;
+        Ok(export.map(|export| {
+            store.component_call_start(ComponentCall::Lifted(self.id().instance(), export))
+        }))
+    }
+
     /// Start a guest->guest call previously prepared using
     /// `Self::prepare_call`.
     ///
@@ -1,3 +1,4 @@
+use crate::component::ComponentCall;
 use crate::component::instance::Instance;
 use crate::component::matching::InstanceType;
 use crate::component::storage::storage_as_slice;
@@ -582,6 +583,9 @@ impl Func {
             lower(cx, ty, map_maybe_uninit!(space.params))
         })?;
 
+        let call = ComponentCall::Lifted(self.instance.id().instance(), self.index);
+        let depth = store.0.component_call_start(call);
+
         // SAFETY: We are providing the guarantee that all the inputs are valid.
         // The various pointers passed in for the function are all valid since
         // they're coming from our store, and the `params_and_results` should
@@ -589,7 +593,7 @@ impl Func {
         // Note that this latter point relies on the correctness of this module
         // and `ComponentType` implementations, hence `ComponentType` being an
         // `unsafe` trait.
-        unsafe {
+        let result = unsafe {
             crate::Func::call_unchecked_raw(
                 &mut store,
                 export,
@@ -598,8 +602,10 @@ impl Func {
                     mem::size_of_val(space) / mem::size_of::<ValRaw>(),
                 ))
                 .unwrap(),
-            )?;
-        }
+            )
+        };
+        store.0.component_call_finish(depth);
+        result?;
 
         // SAFETY: We're relying on the correctness of the structure of
         // `LowerReturn` and the type-checking performed to acquire the
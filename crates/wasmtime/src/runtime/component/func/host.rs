@@ -4,7 +4,9 @@ use crate::component::concurrent::{Accessor, Status};
 use crate::component::func::{LiftContext, LowerContext, Options};
 use crate::component::matching::InstanceType;
 use crate::component::storage::slice_to_storage_mut;
-use crate::component::{ComponentNamedList, ComponentType, Instance, Lift, Lower, Val};
+use crate::component::{
+    ComponentCall, ComponentNamedList, ComponentType, Instance, Lift, Lower, Val,
+};
 use crate::prelude::*;
 use crate::runtime::vm::component::{
     ComponentInstance, VMComponentContext, VMLowering, VMLoweringCallee,
@@ -18,8 +20,8 @@ use core::mem::{self, MaybeUninit};
 use core::pin::Pin;
 use core::ptr::NonNull;
 use wasmtime_environ::component::{
-    CanonicalAbiInfo, ComponentTypes, InterfaceType, MAX_FLAT_ASYNC_PARAMS, MAX_FLAT_PARAMS,
-    MAX_FLAT_RESULTS, OptionsIndex, TypeFuncIndex, TypeTuple,
+    CanonicalAbiInfo, ComponentTypes, InterfaceType, LoweredIndex, MAX_FLAT_ASYNC_PARAMS,
+    MAX_FLAT_PARAMS, MAX_FLAT_RESULTS, OptionsIndex, TypeFuncIndex, TypeTuple,
 };
 
 pub struct HostFunc {
@@ -93,6 +95,7 @@ impl HostFunc {
         data: NonNull<u8>,
         ty: u32,
         options: u32,
+        lowering: u32,
         storage: NonNull<MaybeUninit<ValRaw>>,
         storage_len: usize,
     ) -> bool
@@ -104,16 +107,20 @@ impl HostFunc {
     {
         let data = SendSyncPtr::new(NonNull::new(data.as_ptr() as *mut F).unwrap());
         unsafe {
-            call_host_and_handle_result::<T>(cx, |store, instance| {
-                call_host(
-                    store,
-                    instance,
-                    TypeFuncIndex::from_u32(ty),
-                    OptionsIndex::from_u32(options),
-                    NonNull::slice_from_raw_parts(storage, storage_len).as_mut(),
-                    move |store, args| (*data.as_ptr())(store, args),
-                )
-            })
+            call_host_and_handle_result::<T>(
+                cx,
+                LoweredIndex::from_u32(lowering),
+                |store, instance| {
+                    call_host(
+                        store,
+                        instance,
+                        TypeFuncIndex::from_u32(ty),
+                        OptionsIndex::from_u32(options),
+                        NonNull::slice_from_raw_parts(storage, storage_len).as_mut(),
+                        move |store, args| (*data.as_ptr())(store, args),
+                    )
+                },
+            )
         }
     }
 
@@ -672,6 +679,7 @@ pub(crate) fn validate_inbounds<T: ComponentType>(memory: &[u8], ptr: &ValRaw) -
 
 unsafe fn call_host_and_handle_result<T>(
     cx: NonNull<VMOpaqueContext>,
+    lowering: LoweredIndex,
     func: impl FnOnce(StoreContextMut<'_, T>, Instance) -> Result<()>,
 ) -> bool
 where
@@ -681,9 +689,17 @@ where
     unsafe {
         ComponentInstance::enter_host_from_wasm(cx, |store, instance| {
             let mut store = store.unchecked_context_mut();
+            let call = ComponentCall::Lowered(instance.id().instance(), lowering);
+            let depth = store.0.component_call_start(call);
             store.0.call_hook(CallHook::CallingHost)?;
             let res = func(store.as_context_mut(), instance);
             store.0.call_hook(CallHook::ReturningFromHost)?;
+            // Leave failed calls in place for core dumps to observe when
+            // they're nested within a lifted call which will finish them, see
+            // `component_call_finish`.
+            if res.is_ok() || depth == 0 {
+                store.0.component_call_finish(depth);
+            }
             res
         })
     }
@@ -909,6 +925,7 @@ extern "C" fn dynamic_entrypoint<T, F>(
     data: NonNull<u8>,
     ty: u32,
     options: u32,
+    lowering: u32,
     storage: NonNull<MaybeUninit<ValRaw>>,
     storage_len: usize,
 ) -> bool
@@ -925,7 +942,7 @@ where
 {
     let data = SendSyncPtr::new(NonNull::new(data.as_ptr() as *mut F).unwrap());
     unsafe {
-        call_host_and_handle_result(cx, |store, instance| {
+        call_host_and_handle_result(cx, LoweredIndex::from_u32(lowering), |store, instance| {
             call_host_dynamic::<T, _>(
                 store,
                 instance,
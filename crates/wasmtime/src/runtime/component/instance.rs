@@ -369,6 +369,51 @@ impl Instance {
         unsafe { data.instance_pre() }
     }
 
+    /// Returns the core wasm instances created while instantiating this
+    /// component, in the order in which they were created.
+    ///
+    /// Each instance is paired with the index of the module it instantiates,
+    /// if that module was defined within the component itself. Modules are
+    /// numbered in the order they appear in the component's binary, including
+    /// modules within nested components, followed by any modules synthesized
+    /// internally by Wasmtime such as adapters. Instances of imported modules
+    /// are paired with `None`.
+    ///
+    /// This is a low-level introspection API for `wasmtime-wizer`, which
+    /// needs to snapshot the state of each core instance within a component.
+    /// It exposes implementation details of how components are instantiated
+    /// and isn't part of Wasmtime's stable API.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `store` does not own this instance.
+    #[doc(hidden)]
+    pub fn core_instances(
+        &self,
+        mut store: impl AsContextMut,
+    ) -> Vec<(Option<usize>, crate::Instance)> {
+        let store = store.as_context_mut().0;
+        let data = self.id().get(store);
+        let ids = data
+            .component()
+            .env_component()
+            .initializers
+            .iter()
+            .filter_map(|init| match init {
+                GlobalInitializer::InstantiateModule(InstantiateModule::Static(idx, _)) => {
+                    Some(Some(idx.as_u32() as usize))
+                }
+                GlobalInitializer::InstantiateModule(InstantiateModule::Import(..)) => Some(None),
+                _ => None,
+            })
+            .zip(0..)
+            .map(|(module, i)| (module, data.instance(RuntimeInstanceIndex::from_u32(i))))
+            .collect::<Vec<_>>();
+        ids.into_iter()
+            .map(|(module, id)| (module, crate::Instance::from_wasmtime(id, store)))
+            .collect()
+    }
+
     pub(crate) fn id(&self) -> StoreComponentInstanceId {
         self.id
     }
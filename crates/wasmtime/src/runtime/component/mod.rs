@@ -169,7 +169,7 @@ pub mod __internal {
     pub use wasmtime_environ::component::{CanonicalAbiInfo, ComponentTypes, InterfaceType};
 }
 
-pub(crate) use self::store::ComponentStoreData;
+pub(crate) use self::store::{ComponentCall, ComponentStoreData};
 
 /// Generate bindings for a [WIT world].
 ///
@@ -1,15 +1,37 @@
+#[cfg(feature = "coredump")]
+use crate::hash_map::HashMap;
 #[cfg(feature = "component-model-async")]
 use crate::runtime::vm::VMStore;
 use crate::runtime::vm::component::{ComponentInstance, OwnedComponentInstance};
 use crate::store::{StoreData, StoreId, StoreOpaque};
-#[cfg(feature = "component-model-async")]
+#[cfg(any(feature = "component-model-async", feature = "coredump"))]
 use alloc::vec::Vec;
 use core::pin::Pin;
 use wasmtime_environ::PrimaryMap;
+use wasmtime_environ::component::{ExportIndex, LoweredIndex};
 
 #[derive(Default)]
 pub struct ComponentStoreData {
     instances: PrimaryMap<ComponentInstanceId, Option<OwnedComponentInstance>>,
+
+    /// Component-level calls which are currently in progress, recorded so
+    /// that core dumps can describe them. Only maintained when core dumps are
+    /// enabled.
+    ///
+    /// Calls are kept per native stack, keyed by
+    /// `StoreOpaque::current_stack_id`, since calls on different fibers may
+    /// be suspended and resumed in any order.
+    #[cfg(feature = "coredump")]
+    calls: HashMap<usize, Vec<ComponentCall>>,
+}
+
+/// A component-level call which is in progress within a store.
+#[derive(Copy, Clone, Debug)]
+pub enum ComponentCall {
+    /// The host called a lifted export of a component instance.
+    Lifted(ComponentInstanceId, ExportIndex),
+    /// A component instance called one of its lowered imports.
+    Lowered(ComponentInstanceId, LoweredIndex),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -84,6 +106,72 @@ impl StoreOpaque {
     pub(crate) fn component_instance(&self, id: ComponentInstanceId) -> &ComponentInstance {
         self.store_data().component_instance(id)
     }
+
+    /// Returns all component instances within this store.
+    #[cfg(feature = "coredump")]
+    pub(crate) fn all_component_instances(&self) -> impl Iterator<Item = &ComponentInstance> {
+        self.store_data()
+            .components
+            .instances
+            .values()
+            .filter_map(|i| Some(i.as_ref()?.get()))
+    }
+
+    /// Returns the component-level calls in progress on the current stack,
+    /// from outermost to innermost.
+    #[cfg(feature = "coredump")]
+    pub(crate) fn component_calls(&self) -> &[ComponentCall] {
+        let stack = self.current_stack_id();
+        match self.store_data().components.calls.get(&stack) {
+            Some(calls) => calls,
+            None => &[],
+        }
+    }
+
+    /// Records that `call` has started, returning the depth to later pass to
+    /// `component_call_finish`.
+    ///
+    /// Calls are only recorded when core dumps are enabled since they're only
+    /// used to describe what was executing in a core dump.
+    #[inline]
+    pub(crate) fn component_call_start(&mut self, call: ComponentCall) -> usize {
+        let _ = call;
+        #[cfg(feature = "coredump")]
+        if self.engine().config().coredump_on_trap {
+            let stack = self.current_stack_id();
+            let calls = self
+                .store_data_mut()
+                .components
+                .calls
+                .entry(stack)
+                .or_default();
+            calls.push(call);
+            return calls.len() - 1;
+        }
+        0
+    }
+
+    /// Records that the call started at `depth` on the current stack, and all
+    /// calls nested within it, have finished.
+    ///
+    /// Note that lowered calls which fail aren't finished so that core dumps
+    /// generated as the failure propagates can still observe them, and they're
+    /// instead finished along with the lifted call they're nested within.
+    #[inline]
+    pub(crate) fn component_call_finish(&mut self, depth: usize) {
+        let _ = depth;
+        #[cfg(feature = "coredump")]
+        if self.engine().config().coredump_on_trap {
+            let stack = self.current_stack_id();
+            let calls = &mut self.store_data_mut().components.calls;
+            if let Some(stack_calls) = calls.get_mut(&stack) {
+                stack_calls.truncate(depth);
+                if stack_calls.is_empty() {
+                    calls.remove(&stack);
+                }
+            }
+        }
+    }
 }
 
 /// A type used to represent an allocated `ComponentInstance` located within a
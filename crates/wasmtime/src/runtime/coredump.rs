@@ -1,5 +1,6 @@
 use crate::hash_map::HashMap;
 use crate::prelude::*;
+use crate::runtime::vm::CoreDumpFrame;
 use crate::{
     AsContextMut, FrameInfo, Global, HeapType, Instance, Memory, Module, StoreContextMut, Val,
     ValType, WasmBacktrace, store::StoreOpaque,
@@ -16,9 +17,10 @@ use std::fmt;
 /// error returned this will get printed along with the rest of the error when
 /// the error is logged.
 ///
-/// Note that some state, such as Wasm locals or values on the operand stack,
-/// may be optimized away by the compiler or otherwise not recovered in the
-/// coredump.
+/// Note that Wasm locals and values on the operand stack are only recovered
+/// when [`Config::coredump_locals`][crate::Config::coredump_locals] is
+/// enabled, and otherwise frames in the coredump have no locals or operand
+/// stack.
 ///
 /// Capturing of wasm coredumps can be configured through the
 /// [`Config::coredump_on_trap`][crate::Config::coredump_on_trap] method.
@@ -35,10 +37,20 @@ pub struct WasmCoreDump {
     memories: Vec<Memory>,
     globals: Vec<Global>,
     backtrace: WasmBacktrace,
+    frame_states: Vec<CoreDumpFrame>,
+    #[cfg(feature = "component-model")]
+    component_instances: Vec<CoreDumpComponentInstance>,
+    #[cfg(feature = "component-model")]
+    component_calls: Vec<CoreDumpComponentCall>,
 }
 
 impl WasmCoreDump {
-    pub(crate) fn new(store: &mut StoreOpaque, backtrace: WasmBacktrace) -> WasmCoreDump {
+    pub(crate) fn new(
+        store: &mut StoreOpaque,
+        backtrace: WasmBacktrace,
+        frame_states: Vec<CoreDumpFrame>,
+    ) -> Result<WasmCoreDump> {
+        debug_assert_eq!(backtrace.frames().len(), frame_states.len());
         let modules: Vec<_> = store.modules().all_modules().cloned().collect();
         let instances: Vec<Instance> = store.all_instances().collect();
         let store_memories: Vec<Memory> = store.all_memories().collect();
@@ -46,14 +58,22 @@ impl WasmCoreDump {
         let mut store_globals: Vec<Global> = vec![];
         store.for_each_global(|_store, global| store_globals.push(global));
 
-        WasmCoreDump {
+        #[cfg(feature = "component-model")]
+        let (component_instances, component_calls) = component::capture(store, &instances)?;
+
+        Ok(WasmCoreDump {
             name: String::from("store_name"),
             modules,
             instances,
             memories: store_memories,
             globals: store_globals,
             backtrace,
-        }
+            frame_states,
+            #[cfg(feature = "component-model")]
+            component_instances,
+            #[cfg(feature = "component-model")]
+            component_calls,
+        })
     }
 
     /// The stack frames for this core dump.
@@ -64,6 +84,48 @@ impl WasmCoreDump {
         self.backtrace.frames()
     }
 
+    /// The instance which the `frame`th entry of [`WasmCoreDump::frames`] was
+    /// executing within.
+    ///
+    /// Returns `None` if the instance wasn't recorded, which is the case
+    /// unless [`Config::coredump_locals`][crate::Config::coredump_locals] is
+    /// enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` is out of bounds.
+    pub fn frame_instance(&self, frame: usize) -> Option<Instance> {
+        let id = self.frame_states[frame].instance?;
+        self.instances.iter().copied().find(|i| i.id() == id)
+    }
+
+    /// The values of the locals of the `frame`th entry of
+    /// [`WasmCoreDump::frames`].
+    ///
+    /// Locals whose values weren't recovered, for example because they're of
+    /// a type such as `v128` or a reference, are `None`. No locals are
+    /// recorded unless [`Config::coredump_locals`][crate::Config::coredump_locals]
+    /// is enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` is out of bounds.
+    pub fn frame_locals(&self, frame: usize) -> &[Option<Val>] {
+        &self.frame_states[frame].locals
+    }
+
+    /// The values on the operand stack of the `frame`th entry of
+    /// [`WasmCoreDump::frames`], from bottom to top.
+    ///
+    /// This follows the same rules as [`WasmCoreDump::frame_locals`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` is out of bounds.
+    pub fn frame_operand_stack(&self, frame: usize) -> &[Option<Val>] {
+        &self.frame_states[frame].operand_stack
+    }
+
     /// All modules instantiated inside the store when the core dump was
     /// created.
     pub fn modules(&self) -> &[Module] {
@@ -75,6 +137,24 @@ impl WasmCoreDump {
         self.instances.as_ref()
     }
 
+    /// All component instances within the store when the core dump was
+    /// created, each the root of a tree of the component instances nested
+    /// within it.
+    #[cfg(feature = "component-model")]
+    pub fn component_instances(&self) -> &[CoreDumpComponentInstance] {
+        &self.component_instances
+    }
+
+    /// The component-level calls which were in progress when the core dump was
+    /// created, from outermost to innermost.
+    ///
+    /// The last entry is typically the lifted export which trapped, or the
+    /// lowered import which returned an error.
+    #[cfg(feature = "component-model")]
+    pub fn component_calls(&self) -> &[CoreDumpComponentCall] {
+        &self.component_calls
+    }
+
     /// All globals, instance- or host-defined, within the store when the core
     /// dump was created.
     pub fn globals(&self) -> &[Global] {
@@ -248,12 +328,12 @@ impl WasmCoreDump {
             core_dump.section(&modules);
         }
 
-        // TODO: We can't currently recover instances from stack frames. We can
-        // recover module via the frame's PC, but if there are multiple
-        // instances of the same module, we don't know which instance the frame
-        // is associated with. Therefore, we do a best effort job: remember the
-        // last instance of each module and always choose that one. We record
-        // that information here.
+        // Frames only record their instance when locals are recorded too. For
+        // other frames we can recover the module via the frame's PC, but if
+        // there are multiple instances of the same module, we don't know which
+        // instance the frame is associated with. Therefore, we do a best
+        // effort job: remember the last instance of each module and always
+        // choose that one. We record that information here.
         let mut module_to_instance = HashMap::new();
 
         {
@@ -286,11 +366,15 @@ impl WasmCoreDump {
         {
             let thread_name = "main";
             let mut stack = wasm_encoder::CoreDumpStackSection::new(thread_name);
-            for frame in self.frames() {
-                // This isn't necessarily the right instance if there are
-                // multiple instances of the same module. See comment above
-                // `module_to_instance` for details.
-                let instance = module_to_instance[&frame.module().id()];
+            for (frame, state) in self.frames().iter().zip(&self.frame_states) {
+                // Without a recorded instance this isn't necessarily the right
+                // one if there are multiple instances of the same module. See
+                // comment above `module_to_instance` for details.
+                let instance = state
+                    .instance
+                    .and_then(|id| self.instances.iter().position(|i| i.id() == id))
+                    .map(|i| u32::try_from(i).unwrap())
+                    .unwrap_or_else(|| module_to_instance[&frame.module().id()]);
 
                 let func = frame.func_index();
 
@@ -299,20 +383,36 @@ impl WasmCoreDump {
                     .and_then(|o| u32::try_from(o).ok())
                     .unwrap_or(0);
 
-                // We can't currently recover locals and the operand stack. We
-                // should eventually be able to do that with Winch though.
-                let locals = [];
-                let operand_stack = [];
+                let locals = state.locals.iter().map(coredump_value);
+                let operand_stack = state.operand_stack.iter().map(coredump_value);
 
                 stack.frame(instance, func, offset, locals, operand_stack);
             }
             core_dump.section(&stack);
         }
 
+        #[cfg(feature = "component-model")]
+        if !self.component_instances.is_empty() {
+            core_dump.section(&component::section(
+                &self.component_instances,
+                &self.component_calls,
+            ));
+        }
+
         core_dump.finish()
     }
 }
 
+fn coredump_value(val: &Option<Val>) -> wasm_encoder::CoreDumpValue {
+    match val {
+        Some(Val::I32(x)) => wasm_encoder::CoreDumpValue::I32(*x),
+        Some(Val::I64(x)) => wasm_encoder::CoreDumpValue::I64(*x),
+        Some(Val::F32(x)) => wasm_encoder::CoreDumpValue::F32(f32::from_bits(*x).into()),
+        Some(Val::F64(x)) => wasm_encoder::CoreDumpValue::F64(f64::from_bits(*x).into()),
+        _ => wasm_encoder::CoreDumpValue::Missing,
+    }
+}
+
 impl fmt::Display for WasmCoreDump {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "wasm coredump generated while executing {}:", self.name)?;
@@ -326,6 +426,18 @@ impl fmt::Display for WasmCoreDump {
             writeln!(f, "  {instance:?}")?;
         }
 
+        #[cfg(feature = "component-model")]
+        if !self.component_instances.is_empty() {
+            writeln!(f, "component instances:")?;
+            for (i, instance) in self.component_instances.iter().enumerate() {
+                instance.fmt_tree(f, &i.to_string(), 2)?;
+            }
+            writeln!(f, "component calls:")?;
+            for call in self.component_calls.iter() {
+                writeln!(f, "  {call}")?;
+            }
+        }
+
         writeln!(f, "memories:")?;
         for memory in self.memories.iter() {
             writeln!(f, "  {memory:?}")?;
@@ -348,3 +460,325 @@ impl fmt::Debug for WasmCoreDump {
         write!(f, "<wasm core dump>")
     }
 }
+
+#[cfg(feature = "component-model")]
+pub use self::component::*;
+
+#[cfg(feature = "component-model")]
+mod component {
+    use crate::Instance;
+    use crate::component::ComponentCall;
+    use crate::prelude::*;
+    use crate::runtime::vm::component::{ComponentInstance, HandleCounts};
+    use crate::store::StoreOpaque;
+    use core::fmt;
+    use wasmtime_environ::EntityRef;
+    use wasmtime_environ::component::{
+        Export, ExportIndex, GlobalInitializer, LoweredIndex, NameMap,
+    };
+
+    /// The name of the custom section which describes component instances in
+    /// serialized core dumps.
+    const SECTION_NAME: &str = "wasmtime-components";
+
+    /// A component instance captured in a [`WasmCoreDump`](super::WasmCoreDump).
+    ///
+    /// Component instances form a tree: the instance of a component which was
+    /// instantiated through the embedding API is the root, and any components
+    /// that it instantiated itself are its children.
+    #[derive(Debug, Clone)]
+    pub struct CoreDumpComponentInstance {
+        name: Option<String>,
+        instances: Vec<usize>,
+        handles: CoreDumpHandleTable,
+        children: Vec<CoreDumpComponentInstance>,
+    }
+
+    impl CoreDumpComponentInstance {
+        /// The name of the component, from its `component-name` custom
+        /// section, if present.
+        pub fn name(&self) -> Option<&str> {
+            self.name.as_deref()
+        }
+
+        /// The core instances created by this component instance, as indices
+        /// into [`WasmCoreDump::instances`](super::WasmCoreDump::instances).
+        ///
+        /// The root component instance additionally owns the adapter modules
+        /// which Wasmtime generates for calls between its children.
+        pub fn instances(&self) -> &[usize] {
+            &self.instances
+        }
+
+        /// A summary of this component instance's table of handles.
+        pub fn handles(&self) -> CoreDumpHandleTable {
+            self.handles
+        }
+
+        /// The component instances which this component instance
+        /// instantiated, in the order they were instantiated.
+        pub fn children(&self) -> &[CoreDumpComponentInstance] {
+            &self.children
+        }
+
+        pub(super) fn fmt_tree(
+            &self,
+            f: &mut fmt::Formatter<'_>,
+            label: &str,
+            indent: usize,
+        ) -> fmt::Result {
+            let name = self.name().unwrap_or("<component>");
+            writeln!(f, "{:indent$}{label}: {name}", "")?;
+            for (i, child) in self.children.iter().enumerate() {
+                child.fmt_tree(f, &format!("{label}.{i}"), indent + 2)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// A summary of a component instance's table of handles in a
+    /// [`WasmCoreDump`](super::WasmCoreDump).
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct CoreDumpHandleTable {
+        own: usize,
+        borrow: usize,
+        other: usize,
+    }
+
+    impl CoreDumpHandleTable {
+        /// The number of `own` resource handles in the table.
+        pub fn own(&self) -> usize {
+            self.own
+        }
+
+        /// The number of `borrow` resource handles in the table.
+        pub fn borrow(&self) -> usize {
+            self.borrow
+        }
+
+        /// The number of other handles, such as for tasks, streams, and
+        /// futures, in the table.
+        pub fn other(&self) -> usize {
+            self.other
+        }
+    }
+
+    /// A component-level call which was in progress when a
+    /// [`WasmCoreDump`](super::WasmCoreDump) was created.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum CoreDumpComponentCall {
+        /// The host called a lifted export of a component instance.
+        Export {
+            /// Index of the component instance within
+            /// [`WasmCoreDump::component_instances`](super::WasmCoreDump::component_instances).
+            instance: usize,
+            /// The name of the export, such as `run` or
+            /// `wasi:cli/run@0.2.0#run`.
+            name: String,
+        },
+        /// A component instance called one of its lowered imports.
+        Import {
+            /// Index of the component instance within
+            /// [`WasmCoreDump::component_instances`](super::WasmCoreDump::component_instances).
+            instance: usize,
+            /// The name of the import, such as `wasi:cli/stdout@0.2.0#get-stdout`.
+            name: String,
+        },
+    }
+
+    impl fmt::Display for CoreDumpComponentCall {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CoreDumpComponentCall::Export { instance, name } => {
+                    write!(f, "export `{name}` of component instance {instance}")
+                }
+                CoreDumpComponentCall::Import { instance, name } => {
+                    write!(f, "import `{name}` of component instance {instance}")
+                }
+            }
+        }
+    }
+
+    pub(super) fn capture(
+        store: &StoreOpaque,
+        instances: &[Instance],
+    ) -> Result<(Vec<CoreDumpComponentInstance>, Vec<CoreDumpComponentCall>)> {
+        let components = store.all_component_instances().collect::<Vec<_>>();
+        let component_index = |id| {
+            components
+                .iter()
+                .position(|c| c.id() == id)
+                .ok_or_else(|| anyhow!("component instance of an active call is not in the store"))
+        };
+
+        let component_instances = components
+            .iter()
+            .map(|component| instance_tree(component, instances))
+            .collect::<Result<_>>()?;
+
+        let component_calls = store
+            .component_calls()
+            .iter()
+            .map(|call| {
+                Ok(match *call {
+                    ComponentCall::Lifted(id, export) => CoreDumpComponentCall::Export {
+                        instance: component_index(id)?,
+                        name: export_name(store.component_instance(id), export),
+                    },
+                    ComponentCall::Lowered(id, lowering) => CoreDumpComponentCall::Import {
+                        instance: component_index(id)?,
+                        name: import_name(store.component_instance(id), lowering)
+                            .unwrap_or_else(|| "<unknown>".to_string()),
+                    },
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok((component_instances, component_calls))
+    }
+
+    /// Builds the tree of component instances within `component`, whose root
+    /// is the component itself.
+    fn instance_tree(
+        component: &ComponentInstance,
+        instances: &[Instance],
+    ) -> Result<CoreDumpComponentInstance> {
+        let env = component.component().env_component();
+        let mut nodes = env
+            .runtime_component_instances
+            .iter()
+            .map(|(index, info)| {
+                let HandleCounts { own, borrow, other } = component.handle_tables()[index].counts();
+                Some(CoreDumpComponentInstance {
+                    name: info.name.clone(),
+                    instances: Vec::new(),
+                    handles: CoreDumpHandleTable { own, borrow, other },
+                    children: Vec::new(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (index, id) in component.instance_ids() {
+            let instance = instances
+                .iter()
+                .position(|i| i.id() == id)
+                .ok_or_else(|| anyhow!("core instance of a component is not in the store"))?;
+            // Adapter modules aren't owned by any component, so attribute
+            // them to the root component which they're generated for.
+            let owner = env.runtime_instance_owners[index].map_or(0, |i| i.index());
+            nodes[owner].as_mut().unwrap().instances.push(instance);
+        }
+
+        // Component instances are numbered in the order that they're
+        // instantiated so children always come after their parents, and
+        // walking backwards moves each child into its parent before the parent
+        // itself is moved.
+        for (index, info) in env.runtime_component_instances.iter().skip(1).rev() {
+            let node = nodes[index.index()].take().unwrap();
+            let parent = info.parent.unwrap().index();
+            nodes[parent].as_mut().unwrap().children.insert(0, node);
+        }
+
+        Ok(nodes.swap_remove(0).unwrap())
+    }
+
+    /// Returns the name of the lifted `export` of `component`, qualified with
+    /// the names of the instances it's nested within.
+    fn export_name(component: &ComponentInstance, export: ExportIndex) -> String {
+        fn find(
+            env: &wasmtime_environ::component::Component,
+            exports: &NameMap<String, ExportIndex>,
+            export: ExportIndex,
+        ) -> Option<String> {
+            for (name, index) in exports.raw_iter() {
+                if *index == export {
+                    return Some(name.clone());
+                }
+                if let Export::Instance { exports, .. } = &env.export_items[*index] {
+                    if let Some(inner) = find(env, exports, export) {
+                        return Some(format!("{name}#{inner}"));
+                    }
+                }
+            }
+            None
+        }
+        let env = component.component().env_component();
+        find(env, &env.exports, export).unwrap_or_else(|| "<unknown>".to_string())
+    }
+
+    /// Returns the name of the import of `component` which was lowered as
+    /// `lowering`.
+    fn import_name(component: &ComponentInstance, lowering: LoweredIndex) -> Option<String> {
+        let env = component.component().env_component();
+        let import = env.initializers.iter().find_map(|init| match init {
+            GlobalInitializer::LowerImport { index, import } if *index == lowering => Some(*import),
+            _ => None,
+        })?;
+        let (index, names) = &env.imports[import];
+        let mut name = env.import_types[*index].0.clone();
+        for n in names {
+            name.push('#');
+            name.push_str(n);
+        }
+        Some(name)
+    }
+
+    /// Encodes the `SECTION_NAME` custom section.
+    ///
+    /// The section contains a vector of component instances, each encoded as
+    /// an optional name (a `0x00` byte, or a `0x01` byte and a string), a
+    /// vector of core instance indices, three `u32` counts of own, borrow, and
+    /// other handles, and a vector of child component instances encoded the
+    /// same way. That is followed by a vector of calls, each encoded as a
+    /// `0x00` byte for exports or `0x01` byte for imports, the index of a root
+    /// component instance, and a name.
+    pub(super) fn section(
+        instances: &[CoreDumpComponentInstance],
+        calls: &[CoreDumpComponentCall],
+    ) -> wasm_encoder::CustomSection<'static> {
+        use wasm_encoder::Encode;
+
+        fn len(n: usize) -> u32 {
+            u32::try_from(n).unwrap()
+        }
+
+        fn encode_instances(data: &mut Vec<u8>, instances: &[CoreDumpComponentInstance]) {
+            len(instances.len()).encode(data);
+            for instance in instances {
+                match &instance.name {
+                    Some(name) => {
+                        data.push(0x01);
+                        name.as_str().encode(data);
+                    }
+                    None => data.push(0x00),
+                }
+                len(instance.instances.len()).encode(data);
+                for i in instance.instances.iter() {
+                    len(*i).encode(data);
+                }
+                len(instance.handles.own).encode(data);
+                len(instance.handles.borrow).encode(data);
+                len(instance.handles.other).encode(data);
+                encode_instances(data, &instance.children);
+            }
+        }
+
+        let mut data = Vec::new();
+        encode_instances(&mut data, instances);
+        len(calls.len()).encode(&mut data);
+        for call in calls {
+            let (kind, instance, name) = match call {
+                CoreDumpComponentCall::Export { instance, name } => (0x00, instance, name),
+                CoreDumpComponentCall::Import { instance, name } => (0x01, instance, name),
+            };
+            data.push(kind);
+            len(*instance).encode(&mut data);
+            name.as_str().encode(&mut data);
+        }
+
+        wasm_encoder::CustomSection {
+            name: SECTION_NAME.into(),
+            data: data.into(),
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! Code coverage of WebAssembly modules.
+//!
+//! When [`Config::coverage`](crate::Config::coverage) is enabled compiled code
+//! increments a counter at each of the sites described in
+//! `wasmtime_environ::coverage`. The counters of a module are allocated along
+//! with the `Module` itself and are shared by all of its instances, each of
+//! which points at them from its `VMContext`.
+
+use crate::Module;
+use crate::instantiate::CompiledModule;
+use crate::prelude::*;
+use crate::runtime::vm::VmPtr;
+use core::ops::Range;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+use wasmtime_environ::{
+    CoverageSiteKind, CoverageSites, DefinedFuncIndex, PrimaryMap, demangle_function_name_or_index,
+};
+
+/// The number of times each basic block of a [`Module`] has executed.
+///
+/// Returned by [`Module::coverage`]. This is a snapshot of the module's
+/// counters at the time it was taken.
+#[derive(Clone, Debug)]
+pub struct ModuleCoverage {
+    module: Module,
+    functions: Vec<FunctionCoverage>,
+}
+
+impl ModuleCoverage {
+    /// The coverage of each function defined in the module, in the order in
+    /// which they're defined.
+    pub fn functions(&self) -> &[FunctionCoverage] {
+        &self.functions
+    }
+
+    /// Writes this coverage to `out` in the LCOV tracefile format understood
+    /// by tools such as `genhtml`.
+    ///
+    /// If the module has DWARF debugging information then blocks and branches
+    /// are mapped to the source lines they were compiled from, and functions
+    /// without line information are omitted. Otherwise `source_name` is
+    /// reported as the only source file and offsets within the original wasm
+    /// binary are used as line numbers.
+    #[cfg(feature = "std")]
+    pub fn write_lcov(&self, mut out: impl std::io::Write, source_name: &str) -> Result<()> {
+        let mut files = BTreeMap::new();
+        #[cfg(feature = "addr2line")]
+        self.map_through_dwarf(&mut files)?;
+
+        if files.is_empty() {
+            let file: &mut LcovFile = files.entry(source_name.to_string()).or_default();
+            for func in self.functions.iter() {
+                if let Some(entry) = func.blocks.first() {
+                    file.functions
+                        .push((entry.wasm_offset, func.name.clone(), func.calls()));
+                }
+                for block in func.blocks.iter() {
+                    file.line(block.wasm_offset, block.count);
+                }
+                for branch in func.branches.iter() {
+                    file.branches.push((branch.wasm_offset, *branch));
+                }
+            }
+        }
+
+        for (name, file) in files.iter() {
+            file.write(&mut out, name)?;
+        }
+        Ok(())
+    }
+
+    /// Attributes coverage to source lines with the module's DWARF, if any.
+    #[cfg(feature = "addr2line")]
+    fn map_through_dwarf(&self, files: &mut BTreeMap<String, LcovFile>) -> Result<()> {
+        let Some(cx) = self.module.compiled_module().symbolize_context()? else {
+            return Ok(());
+        };
+        let base = cx.code_section_offset();
+        let cx = cx.addr2line();
+        let probe = |offset: u32| u64::from(offset).checked_sub(base);
+        let locate = |offset: u32| -> Result<Option<(String, u32)>> {
+            let Some(probe) = probe(offset) else {
+                return Ok(None);
+            };
+            let location = cx.find_location(probe)?;
+            Ok(location.and_then(|l| Some((l.file?.to_string(), l.line?))))
+        };
+
+        for func in self.functions.iter() {
+            let Some(entry) = func.blocks.first() else {
+                continue;
+            };
+            let Some((file, line)) = locate(entry.wasm_offset)? else {
+                continue;
+            };
+            files
+                .entry(file)
+                .or_default()
+                .functions
+                .push((line, func.name.clone(), func.calls()));
+
+            // Each block extends up to the start of the next one. Other than
+            // through calls and traps, control can only leave a block early
+            // by an unconditional branch, after which code is unreachable.
+            for (i, block) in func.blocks.iter().enumerate() {
+                let end = func.blocks[i + 1..]
+                    .iter()
+                    .map(|b| b.wasm_offset)
+                    .find(|offset| *offset > block.wasm_offset)
+                    .unwrap_or(func.body_end);
+                let (Some(low), Some(high)) = (probe(block.wasm_offset), probe(end)) else {
+                    continue;
+                };
+                if low >= high {
+                    continue;
+                }
+                for (_, _, location) in cx.find_location_range(low, high)? {
+                    if let (Some(file), Some(line)) = (location.file, location.line) {
+                        files
+                            .entry(file.to_string())
+                            .or_default()
+                            .line(line, block.count);
+                    }
+                }
+            }
+
+            for branch in func.branches.iter() {
+                if let Some((file, line)) = locate(branch.wasm_offset)? {
+                    files
+                        .entry(file)
+                        .or_default()
+                        .branches
+                        .push((line, *branch));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The records of a single source file in an LCOV tracefile.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct LcovFile {
+    /// The line, name and number of calls of each function.
+    functions: Vec<(u32, String, u64)>,
+    /// The execution count of each line.
+    lines: BTreeMap<u32, u64>,
+    /// The line of each conditional branch.
+    branches: Vec<(u32, BranchCoverage)>,
+}
+
+#[cfg(feature = "std")]
+impl LcovFile {
+    /// Records that `line` executed `count` times, taking the maximum of all
+    /// the blocks which share a line.
+    fn line(&mut self, line: u32, count: u64) {
+        let total = self.lines.entry(line).or_default();
+        *total = (*total).max(count);
+    }
+
+    fn write(&self, out: &mut impl std::io::Write, name: &str) -> std::io::Result<()> {
+        writeln!(out, "TN:")?;
+        writeln!(out, "SF:{name}")?;
+        for (line, name, _) in self.functions.iter() {
+            writeln!(out, "FN:{line},{name}")?;
+        }
+        for (_, name, calls) in self.functions.iter() {
+            writeln!(out, "FNDA:{calls},{name}")?;
+        }
+        writeln!(out, "FNF:{}", self.functions.len())?;
+        let hit = self.functions.iter().filter(|f| f.2 > 0).count();
+        writeln!(out, "FNH:{hit}")?;
+
+        let mut hit = 0;
+        for (block, (line, branch)) in self.branches.iter().enumerate() {
+            let evaluated = branch.taken + branch.not_taken > 0;
+            for (i, count) in [branch.taken, branch.not_taken].into_iter().enumerate() {
+                if evaluated {
+                    writeln!(out, "BRDA:{line},{block},{i},{count}")?;
+                } else {
+                    writeln!(out, "BRDA:{line},{block},{i},-")?;
+                }
+                if count > 0 {
+                    hit += 1;
+                }
+            }
+        }
+        writeln!(out, "BRF:{}", self.branches.len() * 2)?;
+        writeln!(out, "BRH:{hit}")?;
+
+        for (line, count) in self.lines.iter() {
+            writeln!(out, "DA:{line},{count}")?;
+        }
+        writeln!(out, "LF:{}", self.lines.len())?;
+        let hit = self.lines.values().filter(|count| **count > 0).count();
+        writeln!(out, "LH:{hit}")?;
+        writeln!(out, "end_of_record")
+    }
+}
+
+/// The coverage of a single WebAssembly function in a [`ModuleCoverage`].
+#[derive(Clone, Debug)]
+pub struct FunctionCoverage {
+    func_index: u32,
+    name: String,
+    /// The offset of the end of the function's body in the original wasm
+    /// binary.
+    body_end: u32,
+    blocks: Vec<BlockCoverage>,
+    branches: Vec<BranchCoverage>,
+}
+
+impl FunctionCoverage {
+    /// The index of this function within its module's function index space.
+    pub fn func_index(&self) -> u32 {
+        self.func_index
+    }
+
+    /// The demangled name of this function, or a name derived from its index
+    /// if it doesn't have one.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of times this function was called.
+    pub fn calls(&self) -> u64 {
+        self.blocks.first().map_or(0, |block| block.count)
+    }
+
+    /// The basic blocks of this function, ordered by their offset.
+    ///
+    /// The first block is the entry of the function.
+    pub fn blocks(&self) -> &[BlockCoverage] {
+        &self.blocks
+    }
+
+    /// The `if` and `br_if` instructions of this function, ordered by their
+    /// offset.
+    pub fn branches(&self) -> &[BranchCoverage] {
+        &self.branches
+    }
+}
+
+/// The number of times a basic block executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockCoverage {
+    wasm_offset: u32,
+    count: u64,
+}
+
+impl BlockCoverage {
+    /// The offset, within the original wasm binary, of the instruction which
+    /// starts this block.
+    ///
+    /// Blocks which follow a `loop`, `if`, `else`, `end` or `br_if` start at
+    /// that instruction. The entry block of a function starts at its first
+    /// instruction.
+    pub fn wasm_offset(&self) -> u32 {
+        self.wasm_offset
+    }
+
+    /// The number of times this block executed.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// The number of times each direction of a conditional branch was taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BranchCoverage {
+    wasm_offset: u32,
+    taken: u64,
+    not_taken: u64,
+}
+
+impl BranchCoverage {
+    /// The offset, within the original wasm binary, of the `if` or `br_if`
+    /// instruction.
+    pub fn wasm_offset(&self) -> u32 {
+        self.wasm_offset
+    }
+
+    /// The number of times the branch was taken, which for an `if` means that
+    /// its `then` arm was entered.
+    pub fn taken(&self) -> u64 {
+        self.taken
+    }
+
+    /// The number of times the branch was not taken, which for an `if` means
+    /// that its `else` arm, if any, was entered.
+    pub fn not_taken(&self) -> u64 {
+        self.not_taken
+    }
+}
+
+/// The coverage counters of a module.
+pub(crate) struct CoverageCounters {
+    counters: Box<[AtomicU64]>,
+    /// The range of `counters` belonging to each defined function.
+    functions: PrimaryMap<DefinedFuncIndex, Range<usize>>,
+    /// The address of the first counter of each defined function, which is
+    /// the table that compiled code reads through its `VMContext`.
+    table: Box<[VmPtr<u64>]>,
+}
+
+impl CoverageCounters {
+    /// Allocates counters for each of the coverage sites of `module`.
+    ///
+    /// Returns `None` if the module wasn't compiled with coverage enabled.
+    pub(crate) fn new(module: &CompiledModule) -> Option<CoverageCounters> {
+        let data = module.code_memory().coverage_data();
+        if data.is_empty() {
+            return None;
+        }
+
+        let mut functions = PrimaryMap::new();
+        let mut len = 0;
+        for index in module.module().defined_func_indices() {
+            let sites = CoverageSites::lookup(module.func_loc(index).start, data)
+                .map_or(0, |sites| sites.len());
+            functions.push(len..len + sites);
+            len += sites;
+        }
+
+        let counters: Box<[AtomicU64]> = (0..len).map(|_| AtomicU64::new(0)).collect();
+        let table = functions
+            .values()
+            .map(|range| {
+                let first = counters
+                    .get(range.start)
+                    .map_or(NonNull::dangling(), |c| NonNull::new(c.as_ptr()).unwrap());
+                VmPtr::from(first)
+            })
+            .collect();
+        Some(CoverageCounters {
+            counters,
+            functions,
+            table,
+        })
+    }
+
+    /// Returns the table which each `VMContext` of the module points to.
+    pub(crate) fn table(&self) -> NonNull<VmPtr<u64>> {
+        NonNull::from(&self.table[..]).cast()
+    }
+
+    /// Reads the current value of all counters of `module`.
+    pub(crate) fn snapshot(&self, module: &Module) -> ModuleCoverage {
+        let compiled = module.compiled_module();
+        let data = compiled.code_memory().coverage_data();
+        let mut functions = Vec::new();
+        for (index, range) in self.functions.iter() {
+            let Some(sites) = CoverageSites::lookup(compiled.func_loc(index).start, data) else {
+                continue;
+            };
+            let func_index = compiled.module().func_index(index);
+            let mut name = String::new();
+            demangle_function_name_or_index(
+                &mut name,
+                compiled.func_name(func_index),
+                func_index.as_u32() as usize,
+            )
+            .unwrap();
+
+            let body_end = sites.body_end();
+            let mut blocks = Vec::new();
+            let mut branches = Vec::new();
+            let mut sites = sites
+                .iter()
+                .zip(self.counters[range.clone()].iter())
+                .map(|(site, count)| (site, count.load(Ordering::Relaxed)));
+            while let Some((site, count)) = sites.next() {
+                if let CoverageSiteKind::Block = site.kind {
+                    blocks.push(BlockCoverage {
+                        wasm_offset: site.offset,
+                        count,
+                    });
+                    continue;
+                }
+
+                // Conditional branches are always followed by the block that's
+                // entered when their condition is true for `if` and false for
+                // `br_if`. Counters aren't updated atomically so saturate in
+                // case the two are out of sync.
+                let (next, next_count) = sites.next().unwrap();
+                debug_assert_eq!(next.kind, CoverageSiteKind::Block);
+                blocks.push(BlockCoverage {
+                    wasm_offset: next.offset,
+                    count: next_count,
+                });
+                let other = count.saturating_sub(next_count);
+                let (taken, not_taken) = match site.kind {
+                    CoverageSiteKind::If => (next_count, other),
+                    _ => (other, next_count),
+                };
+                branches.push(BranchCoverage {
+                    wasm_offset: site.offset,
+                    taken,
+                    not_taken,
+                });
+            }
+
+            functions.push(FunctionCoverage {
+                func_index: func_index.as_u32(),
+                name,
+                body_end,
+                blocks,
+                branches,
+            });
+        }
+        ModuleCoverage {
+            module: module.clone(),
+            functions,
+        }
+    }
+}
@@ -0,0 +1,487 @@
+//! A `Store`-level debugger for WebAssembly guests.
+//!
+//! Code compiled with [`Config::guest_debug`](crate::Config::guest_debug)
+//! calls the `debug_hook` builtin before each instruction whenever the store's
+//! `VMStoreContext::debug_hook_enabled` flag is set. That flag is kept in sync
+//! with the breakpoints and single-stepping state tracked here, so execution
+//! only leaves compiled code when the debugger may actually want to pause.
+
+use crate::prelude::*;
+use crate::runtime::vm::VMStore;
+use crate::store::{AutoAssertNoGc, InstanceId};
+use crate::{
+    AsContext, Engine, Global, Instance, Memory, Module, StoreContextMut, Val, ValRaw, ValType,
+};
+use core::ffi::c_void;
+use core::fmt;
+use wasmtime_environ::{
+    DEBUG_VALUE_SLOT_SIZE, DebugValueKind, EntityRef, FuncIndex, GlobalIndex, MemoryIndex,
+};
+
+/// A location at which a [`Store`](crate::Store) pauses execution and invokes
+/// its debug handler.
+///
+/// Breakpoints are added with
+/// [`Store::add_breakpoint`](crate::Store::add_breakpoint) and only take effect
+/// for code compiled with [`Config::guest_debug`](crate::Config::guest_debug)
+/// enabled.
+#[derive(Clone)]
+pub struct Breakpoint {
+    module: Module,
+    location: BreakpointLocation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BreakpointLocation {
+    Function(u32),
+    Offset(u32),
+}
+
+impl Breakpoint {
+    /// Creates a breakpoint which pauses before the first instruction of the
+    /// function at `func_index` within `module`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `func_index` does not refer to a function defined,
+    /// rather than imported, by `module`.
+    pub fn function(module: &Module, func_index: u32) -> Result<Breakpoint> {
+        let env_module = module.env_module();
+        let index = FuncIndex::from_u32(func_index);
+        if index.index() >= env_module.functions.len() {
+            bail!("function index {func_index} is out of bounds");
+        }
+        if env_module.is_imported_function(index) {
+            bail!("function index {func_index} refers to an imported function");
+        }
+        Ok(Breakpoint {
+            module: module.clone(),
+            location: BreakpointLocation::Function(func_index),
+        })
+    }
+
+    /// Creates a breakpoint which pauses before the instruction at `offset`
+    /// within `module`.
+    ///
+    /// The `offset` is the byte offset of the instruction within the original
+    /// WebAssembly binary, the same offset reported by
+    /// [`FrameInfo::module_offset`](crate::FrameInfo::module_offset). If no
+    /// instruction starts at `offset` then the breakpoint is never hit.
+    pub fn offset(module: &Module, offset: u32) -> Breakpoint {
+        Breakpoint {
+            module: module.clone(),
+            location: BreakpointLocation::Offset(offset),
+        }
+    }
+
+    /// Returns the module that this breakpoint applies to.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    fn matches(&self, module: &Module, func_index: u32, offset: u32, entry: bool) -> bool {
+        if self.module.id() != module.id() {
+            return false;
+        }
+        match self.location {
+            BreakpointLocation::Function(f) => entry && f == func_index,
+            BreakpointLocation::Offset(o) => o == offset,
+        }
+    }
+}
+
+impl PartialEq for Breakpoint {
+    fn eq(&self, other: &Breakpoint) -> bool {
+        self.module.id() == other.module.id() && self.location == other.location
+    }
+}
+
+impl Eq for Breakpoint {}
+
+impl fmt::Debug for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Breakpoint");
+        if let Some(name) = self.module.name() {
+            d.field("module", &name);
+        }
+        match self.location {
+            BreakpointLocation::Function(i) => d.field("function", &i),
+            BreakpointLocation::Offset(o) => d.field("offset", &o),
+        };
+        d.finish()
+    }
+}
+
+/// What a debug handler wants to happen once it returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Resume execution until the next breakpoint is hit.
+    Continue,
+    /// Resume execution and pause again before the next instruction, which
+    /// may be in a different function.
+    Step,
+}
+
+/// The reason that execution paused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugPauseReason {
+    /// A [`Breakpoint`] was hit.
+    Breakpoint,
+    /// The store is single-stepping, either because a previous handler
+    /// returned [`DebugAction::Step`] or because of
+    /// [`Store::single_step`](crate::Store::single_step).
+    Step,
+}
+
+/// A WebAssembly frame paused by the store's debugger.
+///
+/// This is passed to the handler configured with
+/// [`Store::debug_handler`](crate::Store::debug_handler) and describes the
+/// innermost WebAssembly frame, which is about to execute the instruction at
+/// [`DebugFrame::offset`].
+pub struct DebugFrame {
+    reason: DebugPauseReason,
+    instance: Instance,
+    module: Module,
+    func_index: u32,
+    offset: u32,
+    locals: Vec<Option<Val>>,
+    operand_stack: Vec<Option<Val>>,
+}
+
+impl DebugFrame {
+    /// Returns why execution paused at this frame.
+    pub fn reason(&self) -> DebugPauseReason {
+        self.reason
+    }
+
+    /// Returns the instance that this frame belongs to.
+    pub fn instance(&self) -> Instance {
+        self.instance
+    }
+
+    /// Returns the module that this frame's function is defined in.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Returns the index of this frame's function within its module.
+    pub fn func_index(&self) -> u32 {
+        self.func_index
+    }
+
+    /// Returns the name of this frame's function, if the module has a name
+    /// section describing it.
+    pub fn func_name(&self) -> Option<&str> {
+        self.module
+            .compiled_module()
+            .func_name(FuncIndex::from_u32(self.func_index))
+    }
+
+    /// Returns the byte offset, within the original WebAssembly binary, of the
+    /// instruction that is about to execute.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the values of this frame's locals, starting with the function's
+    /// parameters.
+    ///
+    /// Values which can't be represented as a [`Val`], which are currently
+    /// only continuation references, are `None`.
+    pub fn locals(&self) -> &[Option<Val>] {
+        &self.locals
+    }
+
+    /// Returns the values on this frame's operand stack, where the last value
+    /// is the top of the stack.
+    ///
+    /// As with [`DebugFrame::locals`], values which can't be represented as a
+    /// [`Val`] are `None`.
+    pub fn operand_stack(&self) -> &[Option<Val>] {
+        &self.operand_stack
+    }
+
+    /// Returns the global at `index` within this frame's instance, whether or
+    /// not it is exported.
+    ///
+    /// Returns `None` if `index` is out of bounds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `store` does not own this frame.
+    pub fn global(&self, store: impl AsContext, index: u32) -> Option<Global> {
+        self.instance
+            .debug_global(store.as_context().0, GlobalIndex::from_u32(index))
+    }
+
+    /// Returns the memory at `index` within this frame's instance, whether or
+    /// not it is exported.
+    ///
+    /// Returns `None` if `index` is out of bounds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `store` does not own this frame.
+    pub fn memory(&self, store: impl AsContext, index: u32) -> Option<Memory> {
+        self.instance
+            .debug_memory(store.as_context().0, MemoryIndex::from_u32(index))
+    }
+}
+
+impl fmt::Debug for DebugFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugFrame")
+            .field("reason", &self.reason)
+            .field("func_index", &self.func_index)
+            .field("func_name", &self.func_name())
+            .field("offset", &self.offset)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An asynchronous handler invoked whenever the guest debugger pauses
+/// execution.
+///
+/// This is configured with
+/// [`Store::debug_handler_async`](crate::Store::debug_handler_async) and is the
+/// asynchronous counterpart of
+/// [`Store::debug_handler`](crate::Store::debug_handler), allowing execution to
+/// stay paused while, for example, waiting for a remote debugger's commands.
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+pub trait DebugHandler<T>: Send {
+    /// Invoked when execution pauses at `frame`, returning how to resume.
+    async fn handle_pause(
+        &self,
+        store: StoreContextMut<'_, T>,
+        frame: &DebugFrame,
+    ) -> Result<DebugAction>;
+}
+
+/// Debugger state tracked by each store.
+#[derive(Default)]
+pub(crate) struct DebugState {
+    breakpoints: Vec<Breakpoint>,
+    single_step: bool,
+}
+
+impl DebugState {
+    /// Whether compiled code needs to call into the host before each
+    /// instruction.
+    pub(crate) fn hook_enabled(&self) -> bool {
+        self.single_step || !self.breakpoints.is_empty()
+    }
+
+    pub(crate) fn add_breakpoint(&mut self, engine: &Engine, breakpoint: Breakpoint) -> Result<()> {
+        check_engine(engine)?;
+        if !Engine::same(engine, breakpoint.module.engine()) {
+            bail!("cross-`Engine` breakpoints are not supported");
+        }
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|b| b != breakpoint);
+        self.breakpoints.len() != len
+    }
+
+    pub(crate) fn set_single_step(&mut self, engine: &Engine, enable: bool) -> Result<()> {
+        if enable {
+            check_engine(engine)?;
+        }
+        self.single_step = enable;
+        Ok(())
+    }
+
+    fn pause_reason(
+        &self,
+        module: &Module,
+        func_index: u32,
+        offset: u32,
+        entry: bool,
+    ) -> Option<DebugPauseReason> {
+        if self.single_step {
+            Some(DebugPauseReason::Step)
+        } else if self
+            .breakpoints
+            .iter()
+            .any(|b| b.matches(module, func_index, offset, entry))
+        {
+            Some(DebugPauseReason::Breakpoint)
+        } else {
+            None
+        }
+    }
+}
+
+fn check_engine(engine: &Engine) -> Result<()> {
+    if !engine.tunables().debug_instrumentation {
+        bail!("guest debugging requires enabling `Config::guest_debug`");
+    }
+    Ok(())
+}
+
+/// Implementation of the `debug_hook` builtin.
+///
+/// # Safety
+///
+/// `values` and `kinds` must point to the spill area described in
+/// `wasmtime_environ::guest_debug` holding `num_locals + num_stack` values
+/// owned by `store`.
+pub(crate) unsafe fn debug_hook(
+    store: &mut dyn VMStore,
+    instance: InstanceId,
+    func_index: u32,
+    offset: u32,
+    entry: bool,
+    values: *mut u8,
+    kinds: *mut u8,
+    num_locals: u32,
+    num_stack: u32,
+) -> Result<()> {
+    let module = match store.instance(instance).runtime_module() {
+        Some(module) => module.clone(),
+        None => return Ok(()),
+    };
+    let reason = match store
+        .debug_state()
+        .pause_reason(&module, func_index, offset, entry)
+    {
+        Some(reason) => reason,
+        None => return Ok(()),
+    };
+
+    // Any GC references read out of the frame are only rooted for the
+    // duration of the handler.
+    let gc_lifo_scope = store.gc_roots().enter_lifo_scope();
+    // SAFETY: forwarded from this function's contract.
+    let result = unsafe {
+        pause(
+            store, reason, instance, module, func_index, offset, values, kinds, num_locals,
+            num_stack,
+        )
+    };
+    store.exit_gc_lifo_scope(gc_lifo_scope);
+    result
+}
+
+/// Captures the paused frame and invokes the store's debug handler.
+///
+/// # Safety
+///
+/// Same as `debug_hook`.
+unsafe fn pause(
+    store: &mut dyn VMStore,
+    reason: DebugPauseReason,
+    instance: InstanceId,
+    module: Module,
+    func_index: u32,
+    offset: u32,
+    values: *mut u8,
+    kinds: *mut u8,
+    num_locals: u32,
+    num_stack: u32,
+) -> Result<()> {
+    let num_locals = usize::try_from(num_locals).unwrap();
+    let len = num_locals + usize::try_from(num_stack).unwrap();
+    let mut locals = Vec::with_capacity(num_locals);
+    let mut operand_stack = Vec::with_capacity(len - num_locals);
+    {
+        let mut store = AutoAssertNoGc::new(store.store_opaque_mut());
+        for i in 0..len {
+            // SAFETY: it's a contract of this function that `values` is valid
+            // for `len` values.
+            let val = unsafe { read_value(&mut store, values, kinds, i)? };
+            if i < num_locals {
+                locals.push(val);
+            } else {
+                operand_stack.push(val);
+            }
+        }
+    }
+
+    let frame = DebugFrame {
+        reason,
+        instance: Instance::from_wasmtime(instance, store),
+        module,
+        func_index,
+        offset,
+        locals,
+        operand_stack,
+    };
+    let action = store.debug_pause(&frame)?;
+    store.debug_state_mut().single_step = action == DebugAction::Step;
+    store.update_debug_hook_enabled();
+    Ok(())
+}
+
+/// Reads the `i`th value from the spill area at `values` and `kinds`,
+/// returning `None` if it can't be represented as a `Val`.
+///
+/// # Safety
+///
+/// Same as `debug_hook`.
+unsafe fn read_value(
+    store: &mut AutoAssertNoGc<'_>,
+    values: *mut u8,
+    kinds: *mut u8,
+    i: usize,
+) -> Result<Option<Val>> {
+    let slot_size = usize::try_from(DEBUG_VALUE_SLOT_SIZE).unwrap();
+    // SAFETY: the spill area holds a slot and a kind byte for the `i`th value.
+    let (slot, kind) = unsafe { (values.add(i * slot_size), kinds.add(i).read()) };
+    let kind =
+        DebugValueKind::from_u8(kind).ok_or_else(|| anyhow!("invalid debug value kind {kind}"))?;
+    // SAFETY: the slot was written by compiled code with a value of type
+    // `kind`, and reference values are owned by `store`.
+    unsafe {
+        let (raw, ty) = match kind {
+            DebugValueKind::I32 => (
+                ValRaw::i32(slot.cast::<i32>().read_unaligned()),
+                ValType::I32,
+            ),
+            DebugValueKind::I64 => (
+                ValRaw::i64(slot.cast::<i64>().read_unaligned()),
+                ValType::I64,
+            ),
+            DebugValueKind::F32 => (
+                ValRaw::f32(slot.cast::<u32>().read_unaligned()),
+                ValType::F32,
+            ),
+            DebugValueKind::F64 => (
+                ValRaw::f64(slot.cast::<u64>().read_unaligned()),
+                ValType::F64,
+            ),
+            DebugValueKind::V128 => (
+                ValRaw::v128(slot.cast::<u128>().read_unaligned()),
+                ValType::V128,
+            ),
+            DebugValueKind::FuncRef => (
+                ValRaw::funcref(slot.cast::<*mut c_void>().read_unaligned()),
+                ValType::FUNCREF,
+            ),
+            DebugValueKind::ExternRef => (
+                ValRaw::externref(slot.cast::<u32>().read_unaligned()),
+                ValType::EXTERNREF,
+            ),
+            DebugValueKind::AnyRef => (
+                ValRaw::anyref(slot.cast::<u32>().read_unaligned()),
+                ValType::ANYREF,
+            ),
+            DebugValueKind::ExnRef => (
+                ValRaw::exnref(slot.cast::<u32>().read_unaligned()),
+                ValType::EXNREF,
+            ),
+            // TODO(#10248): continuation references can't yet be represented
+            // in the embedder API, and `Val::ContRef(None)` would claim that
+            // the value is null.
+            DebugValueKind::ContRef => return Ok(None),
+        };
+        Ok(Some(Val::_from_raw(store, raw, &ty)))
+    }
+}
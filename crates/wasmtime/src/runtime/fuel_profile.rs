@@ -0,0 +1,314 @@
+//! Attribution of consumed fuel to the WebAssembly functions which consumed
+//! it.
+//!
+//! When [`Config::fuel_profiling`](crate::Config::fuel_profiling) is enabled
+//! compiled code calls into the store on entry to and exit from every
+//! function, passing along its frame pointer. The store keeps a shadow stack
+//! of active functions and charges the fuel consumed between two such events
+//! to the functions on that stack.
+//!
+//! Frames which are unwound without running their exit hook, for example due
+//! to a trap or a thrown exception, are detected because a later event
+//! happens at a frame pointer at or above theirs: the stack grows down, so a
+//! live frame can't be at or below the frame pointer of a function which is
+//! currently executing.
+//!
+//! Frame pointers are only comparable within a single native stack, and with
+//! async support a store may interleave execution across several fibers, so
+//! a separate shadow stack is kept for each fiber that runs WebAssembly.
+
+use crate::Module;
+use crate::hash_map::HashMap;
+use crate::prelude::*;
+use crate::runtime::vm::CompiledModuleId;
+use core::fmt;
+use wasmtime_environ::{FuncIndex, demangle_function_name_or_index};
+
+/// A report of how much fuel each WebAssembly function has consumed.
+///
+/// Returned by [`Store::fuel_profile`](crate::Store::fuel_profile). Because
+/// fuel is a deterministic measure of the number of instructions executed, two
+/// runs of the same program with the same inputs produce the same profile
+/// regardless of the machine they run on.
+///
+/// The [`Display`](fmt::Display) implementation of this type renders the
+/// profile as a table sorted by exclusive fuel.
+#[derive(Clone, Debug)]
+pub struct FuelProfile {
+    total_fuel: u64,
+    entries: Vec<FuelProfileEntry>,
+}
+
+impl FuelProfile {
+    /// The total amount of fuel consumed while profiling.
+    ///
+    /// This includes fuel consumed outside of any profiled function, such as
+    /// in trampolines.
+    pub fn total_fuel(&self) -> u64 {
+        self.total_fuel
+    }
+
+    /// The functions which were called while profiling, sorted by descending
+    /// exclusive fuel.
+    pub fn entries(&self) -> &[FuelProfileEntry] {
+        &self.entries
+    }
+}
+
+impl fmt::Display for FuelProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "total fuel consumed: {}", self.total_fuel)?;
+        writeln!(
+            f,
+            "{:>14} {:>7} {:>14} {:>7} {:>10}  function",
+            "exclusive", "%", "inclusive", "%", "calls"
+        )?;
+        let percent = |fuel: u64| {
+            if self.total_fuel == 0 {
+                0.0
+            } else {
+                fuel as f64 * 100.0 / self.total_fuel as f64
+            }
+        };
+        for entry in self.entries.iter() {
+            write!(
+                f,
+                "{:>14} {:>6.2}% {:>14} {:>6.2}% {:>10}  ",
+                entry.exclusive_fuel,
+                percent(entry.exclusive_fuel),
+                entry.inclusive_fuel,
+                percent(entry.inclusive_fuel),
+                entry.calls,
+            )?;
+            match &entry.module {
+                Some(module) => writeln!(f, "{module}!{}", entry.name)?,
+                None => writeln!(f, "{}", entry.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fuel consumed by a single WebAssembly function in a [`FuelProfile`].
+#[derive(Clone, Debug)]
+pub struct FuelProfileEntry {
+    module: Option<String>,
+    func_index: u32,
+    name: String,
+    calls: u64,
+    inclusive_fuel: u64,
+    exclusive_fuel: u64,
+}
+
+impl FuelProfileEntry {
+    /// The name of the module defining this function, if it has one.
+    pub fn module_name(&self) -> Option<&str> {
+        self.module.as_deref()
+    }
+
+    /// The index of this function within its module's function index space.
+    pub fn func_index(&self) -> u32 {
+        self.func_index
+    }
+
+    /// The demangled name of this function, or a name derived from its index
+    /// if it doesn't have one.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of times this function was called.
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    /// The fuel consumed by this function and everything it called.
+    ///
+    /// Fuel consumed by recursive calls is only counted once.
+    pub fn inclusive_fuel(&self) -> u64 {
+        self.inclusive_fuel
+    }
+
+    /// The fuel consumed by this function itself, excluding the functions it
+    /// called.
+    pub fn exclusive_fuel(&self) -> u64 {
+        self.exclusive_fuel
+    }
+}
+
+/// Per-store state for attributing fuel to functions.
+#[derive(Clone, Default)]
+pub(crate) struct FuelProfiler {
+    /// The total fuel consumed up to the most recent event.
+    consumed: u64,
+    /// The fuel remaining in the store as of the most recent event.
+    remaining: u64,
+    /// The shadow stack of each native stack which is executing WebAssembly,
+    /// keyed by the [`StackId`] of that stack.
+    stacks: HashMap<StackId, Vec<Frame>>,
+    functions: Vec<Function>,
+    function_ids: HashMap<(CompiledModuleId, FuncIndex), usize>,
+}
+
+/// Identifies the native stack that WebAssembly is executing on: the start of
+/// the guard range of the current fiber's stack, or zero when not executing
+/// on a fiber.
+pub(crate) type StackId = usize;
+
+#[derive(Clone)]
+struct Frame {
+    function: usize,
+    fp: usize,
+    /// The value of `FuelProfiler::consumed` when this frame was entered.
+    entry: u64,
+    /// The inclusive fuel of the frames this frame called.
+    children: u64,
+}
+
+#[derive(Clone)]
+struct Function {
+    entry: FuelProfileEntry,
+    /// The number of frames for this function currently on the stack, used to
+    /// avoid counting recursive calls twice in inclusive fuel.
+    active: u32,
+}
+
+impl FuelProfiler {
+    /// Charges any fuel consumed since the previous event given that `remaining`
+    /// fuel is now left in the store.
+    fn advance(&mut self, remaining: u64) {
+        self.consumed += self.remaining.saturating_sub(remaining);
+        self.remaining = remaining;
+    }
+
+    /// Invoked when the embedder sets the store's fuel to `new`, which
+    /// shouldn't be counted as consumption.
+    pub(crate) fn set_fuel(&mut self, remaining: u64, new: u64) {
+        self.advance(remaining);
+        self.remaining = new;
+    }
+
+    /// Invoked when the function `func` of `module` is entered on `stack` with
+    /// the frame pointer `fp`.
+    pub(crate) fn enter(
+        &mut self,
+        remaining: u64,
+        stack: StackId,
+        module: &Module,
+        func: FuncIndex,
+        fp: usize,
+    ) {
+        self.advance(remaining);
+        self.pop_frames_while(stack, |frame, _| frame.fp <= fp);
+
+        let key = (module.id(), func);
+        let function = match self.function_ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                let compiled = module.compiled_module();
+                let mut name = String::new();
+                demangle_function_name_or_index(
+                    &mut name,
+                    compiled.func_name(func),
+                    func.as_u32() as usize,
+                )
+                .unwrap();
+                let id = self.functions.len();
+                self.functions.push(Function {
+                    entry: FuelProfileEntry {
+                        module: module.name().map(|s| s.to_string()),
+                        func_index: func.as_u32(),
+                        name,
+                        calls: 0,
+                        inclusive_fuel: 0,
+                        exclusive_fuel: 0,
+                    },
+                    active: 0,
+                });
+                self.function_ids.insert(key, id);
+                id
+            }
+        };
+
+        let stats = &mut self.functions[function];
+        stats.entry.calls += 1;
+        stats.active += 1;
+        self.stacks.entry(stack).or_default().push(Frame {
+            function,
+            fp,
+            entry: self.consumed,
+            children: 0,
+        });
+    }
+
+    /// Invoked when the function with frame pointer `fp` on `stack` returns.
+    pub(crate) fn exit(&mut self, remaining: u64, stack: StackId, fp: usize) {
+        self.advance(remaining);
+        self.pop_frames_while(stack, |frame, _| frame.fp <= fp);
+    }
+
+    /// Returns the current depth of the shadow stack of `stack`.
+    pub(crate) fn depth(&self, stack: StackId) -> usize {
+        self.stacks.get(&stack).map_or(0, |frames| frames.len())
+    }
+
+    /// Pops frames until the shadow stack of `stack` is `depth` frames deep.
+    ///
+    /// Used when control returns to the host, possibly by a trap, to discard
+    /// any frames that were entered since the stack was `depth` frames deep.
+    pub(crate) fn unwind(&mut self, remaining: u64, stack: StackId, depth: usize) {
+        self.advance(remaining);
+        self.pop_frames_while(stack, |_, len| len > depth);
+    }
+
+    /// Pops frames off the shadow stack of `stack` while `pop` returns true
+    /// for the newest frame and the current depth of the stack.
+    fn pop_frames_while(&mut self, stack: StackId, mut pop: impl FnMut(&Frame, usize) -> bool) {
+        let Some(frames) = self.stacks.get_mut(&stack) else {
+            return;
+        };
+        while frames.last().is_some_and(|frame| pop(frame, frames.len())) {
+            let frame = frames.pop().unwrap();
+            let inclusive = self.consumed - frame.entry;
+            let stats = &mut self.functions[frame.function];
+            stats.entry.exclusive_fuel += inclusive - frame.children;
+            stats.active -= 1;
+            if stats.active == 0 {
+                stats.entry.inclusive_fuel += inclusive;
+            }
+            if let Some(parent) = frames.last_mut() {
+                parent.children += inclusive;
+            }
+        }
+        if frames.is_empty() {
+            self.stacks.remove(&stack);
+        }
+    }
+
+    /// Creates a report of the fuel consumed so far, treating any functions
+    /// which are still executing as if they had just returned.
+    pub(crate) fn report(&self, remaining: u64) -> FuelProfile {
+        let mut profiler = self.clone();
+        profiler.advance(remaining);
+        let stacks: Vec<_> = profiler.stacks.keys().copied().collect();
+        for stack in stacks {
+            profiler.pop_frames_while(stack, |_, _| true);
+        }
+        let mut entries: Vec<_> = profiler
+            .functions
+            .into_iter()
+            .map(|function| function.entry)
+            .collect();
+        entries.sort_by(|a, b| {
+            b.exclusive_fuel
+                .cmp(&a.exclusive_fuel)
+                .then_with(|| b.inclusive_fuel.cmp(&a.inclusive_fuel))
+                .then_with(|| a.module.cmp(&b.module))
+                .then_with(|| a.func_index.cmp(&b.func_index))
+        });
+        FuelProfile {
+            total_fuel: profiler.consumed,
+            entries,
+        }
+    }
+}
@@ -1069,6 +1069,28 @@ impl Func {
         self.vm_func_ref(store.as_context_mut().0).as_ptr().cast()
     }
 
+    /// Returns a key identifying the definition of this function.
+    ///
+    /// Unlike [`Func::to_raw`], which differs between the instance defining a
+    /// WebAssembly function and each instance importing it, this is the same
+    /// for every `Func` referring to the same function of the same instance.
+    ///
+    /// This is a low-level API intended for snapshotting pre-initializers,
+    /// which need to find a function referenced by one instance among the
+    /// functions of another, and it isn't covered by semver.
+    #[doc(hidden)]
+    pub fn definition_key(&self, store: impl AsContext) -> (usize, usize) {
+        // SAFETY: the function reference is owned by the store and valid for
+        // as long as it is.
+        let func_ref = unsafe { self.vm_func_ref(store.as_context().0).as_ref() };
+        // Imported functions copy the `vmctx` and `array_call` of their
+        // definition, which together identify that definition.
+        (
+            func_ref.vmctx.as_ptr().addr(),
+            func_ref.array_call.as_ptr().addr(),
+        )
+    }
+
     /// Invokes this function with the `params` given, returning the results
     /// asynchronously.
     ///
@@ -1504,6 +1526,9 @@ pub(crate) fn invoke_wasm_and_catch_traps<T>(
     // created by the `catch_traps` call below will store a pointer to this
     // stack-allocated `previous_runtime_state`.
     let mut previous_runtime_state = EntryStoreContext::enter_wasm(store, &mut initial_stack_csi);
+    let fuel_profile_depth = store.0.fuel_profile_depth();
+    #[cfg(feature = "wmemcheck")]
+    let wmemcheck_depths = store.0.wmemcheck_allocator_depths();
 
     if let Err(trap) = store.0.call_hook(CallHook::CallingWasm) {
         // `previous_runtime_state` implicitly dropped here
@@ -1511,6 +1536,9 @@ pub(crate) fn invoke_wasm_and_catch_traps<T>(
     }
     let result = crate::runtime::vm::catch_traps(store, &mut previous_runtime_state, closure);
     core::mem::drop(previous_runtime_state);
+    store.0.fuel_profile_unwind(fuel_profile_depth);
+    #[cfg(feature = "wmemcheck")]
+    store.0.wmemcheck_unwind(&wmemcheck_depths);
     store.0.call_hook(CallHook::ReturningFromWasm)?;
     result
 }
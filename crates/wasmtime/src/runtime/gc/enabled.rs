@@ -8,6 +8,8 @@ mod exnref;
 mod externref;
 mod i31;
 mod rooting;
+mod snapshot;
+mod stats;
 mod structref;
 
 pub use anyref::*;
@@ -17,4 +19,6 @@ pub use exnref::*;
 pub use externref::*;
 pub use i31::*;
 pub use rooting::*;
+pub use snapshot::*;
+pub use stats::*;
 pub use structref::*;
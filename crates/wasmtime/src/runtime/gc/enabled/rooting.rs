@@ -161,7 +161,7 @@
 //! can. However, if you really must, consider also using an `AutoAssertNoGc`
 //! across the block of code that is manipulating raw GC references.
 
-use crate::runtime::vm::{GcRootsList, GcStore, VMGcRef};
+use crate::runtime::vm::{GcRootKind, GcRootsList, GcStore, VMGcRef};
 use crate::{
     AsContext, AsContextMut, GcRef, Result, RootedGcRef,
     store::{AsStoreOpaque, AutoAssertNoGc, StoreId, StoreOpaque},
@@ -451,7 +451,7 @@ impl RootSet {
         log::trace!("Begin trace user LIFO roots");
         for root in &mut self.lifo_roots {
             unsafe {
-                gc_roots_list.add_root((&mut root.gc_ref).into(), "user LIFO root");
+                gc_roots_list.add_root((&mut root.gc_ref).into(), GcRootKind::UserLifo);
             }
         }
         log::trace!("End trace user LIFO roots");
@@ -459,7 +459,7 @@ impl RootSet {
         log::trace!("Begin trace user owned roots");
         for (_id, root) in self.owned_rooted.iter_mut() {
             unsafe {
-                gc_roots_list.add_root(root.into(), "user owned root");
+                gc_roots_list.add_root(root.into(), GcRootKind::UserOwned);
             }
         }
         log::trace!("End trace user owned roots");
@@ -0,0 +1,530 @@
+//! Snapshots of a store's GC object graph.
+
+use crate::Engine;
+use crate::hash_map::HashMap;
+use crate::prelude::*;
+use crate::runtime::vm::{GcStore, VMGcRef};
+use core::fmt::Write;
+use wasmtime_environ::{GcLayout, VMGcKind, VMSharedTypeIndex};
+
+pub use crate::runtime::vm::GcRootKind;
+
+/// A snapshot of the objects reachable from a store's GC roots.
+///
+/// Created with [`Store::gc_heap_snapshot`][crate::Store::gc_heap_snapshot].
+/// The snapshot contains every object that is reachable from a GC root at the
+/// time it was taken, the edges between those objects, and the roots
+/// themselves.
+///
+/// A snapshot can be exported as plain JSON with [`GcHeapSnapshot::to_json`]
+/// or in the `.heapsnapshot` format that Chrome's DevTools memory panel loads
+/// with [`GcHeapSnapshot::to_chrome_heap_snapshot`].
+#[derive(Clone, Debug, Default)]
+pub struct GcHeapSnapshot {
+    objects: Vec<GcHeapSnapshotObject>,
+    roots: Vec<GcHeapSnapshotRoot>,
+}
+
+/// The kind of a [`GcHeapSnapshotObject`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GcHeapSnapshotObjectKind {
+    /// A Wasm struct.
+    Struct,
+    /// A Wasm array.
+    Array,
+    /// A Wasm exception object.
+    Exception,
+    /// An `externref` wrapping host data.
+    ExternRef,
+}
+
+impl GcHeapSnapshotObjectKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Struct => "struct",
+            Self::Array => "array",
+            Self::Exception => "exception",
+            Self::ExternRef => "externref",
+        }
+    }
+}
+
+/// An object in a [`GcHeapSnapshot`].
+#[derive(Clone, Debug)]
+pub struct GcHeapSnapshotObject {
+    id: u32,
+    kind: GcHeapSnapshotObjectKind,
+    type_name: String,
+    size: usize,
+    edges: Vec<GcHeapSnapshotEdge>,
+}
+
+impl GcHeapSnapshotObject {
+    /// This object's identifier: its index within the GC heap.
+    ///
+    /// Identifiers are unique within a snapshot, but moving collectors may
+    /// relocate objects, so the same object may have a different identifier in
+    /// a later snapshot.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// This object's kind.
+    pub fn kind(&self) -> GcHeapSnapshotObjectKind {
+        self.kind
+    }
+
+    /// A human-readable name for this object's type.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// The size of this object in the GC heap, in bytes, including its header.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The references from this object to other objects in the snapshot.
+    pub fn edges(&self) -> &[GcHeapSnapshotEdge] {
+        &self.edges
+    }
+}
+
+/// A reference from one object in a [`GcHeapSnapshot`] to another.
+///
+/// References to `i31ref`s are not included in snapshots, since they are not
+/// heap objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GcHeapSnapshotEdge {
+    /// A struct or exception field referencing an object.
+    Field {
+        /// The index of the field.
+        field: u32,
+        /// The index of the referenced object in
+        /// [`GcHeapSnapshot::objects`].
+        object: usize,
+    },
+
+    /// An array element referencing an object.
+    Element {
+        /// The index of the element.
+        index: u32,
+        /// The index of the referenced object in
+        /// [`GcHeapSnapshot::objects`].
+        object: usize,
+    },
+}
+
+impl GcHeapSnapshotEdge {
+    /// The index of the referenced object in [`GcHeapSnapshot::objects`].
+    pub fn object(&self) -> usize {
+        match *self {
+            Self::Field { object, .. } | Self::Element { object, .. } => object,
+        }
+    }
+
+    fn kind_str(&self) -> &'static str {
+        match self {
+            Self::Field { .. } => "field",
+            Self::Element { .. } => "element",
+        }
+    }
+
+    fn index(&self) -> u32 {
+        match *self {
+            Self::Field { field, .. } => field,
+            Self::Element { index, .. } => index,
+        }
+    }
+}
+
+/// A GC root in a [`GcHeapSnapshot`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GcHeapSnapshotRoot {
+    kind: GcRootKind,
+    object: usize,
+}
+
+impl GcHeapSnapshotRoot {
+    /// Where this root lives.
+    pub fn kind(&self) -> GcRootKind {
+        self.kind
+    }
+
+    /// The index of the rooted object in [`GcHeapSnapshot::objects`].
+    pub fn object(&self) -> usize {
+        self.object
+    }
+}
+
+impl GcHeapSnapshot {
+    /// The objects in this snapshot.
+    pub fn objects(&self) -> &[GcHeapSnapshotObject] {
+        &self.objects
+    }
+
+    /// The GC roots in this snapshot.
+    ///
+    /// The same object may be rooted more than once.
+    pub fn roots(&self) -> &[GcHeapSnapshotRoot] {
+        &self.roots
+    }
+
+    /// The total size of all objects in this snapshot, in bytes.
+    pub fn total_size(&self) -> usize {
+        self.objects.iter().map(|o| o.size).sum()
+    }
+
+    /// Build a snapshot of everything reachable from `roots`.
+    ///
+    /// `roots` must not contain `i31ref`s.
+    pub(crate) fn build(
+        engine: &Engine,
+        gc_store: &GcStore,
+        roots: impl IntoIterator<Item = (GcRootKind, VMGcRef)>,
+    ) -> Self {
+        let mut builder = SnapshotBuilder {
+            engine,
+            gc_store,
+            snapshot: GcHeapSnapshot::default(),
+            index_of: HashMap::new(),
+            types: HashMap::new(),
+            worklist: Vec::new(),
+        };
+
+        for (kind, gc_ref) in roots {
+            debug_assert!(!gc_ref.is_i31());
+            let object = builder.visit(gc_ref);
+            builder
+                .snapshot
+                .roots
+                .push(GcHeapSnapshotRoot { kind, object });
+        }
+
+        while let Some((index, gc_ref)) = builder.worklist.pop() {
+            builder.trace(index, &gc_ref);
+        }
+
+        builder.snapshot
+    }
+
+    /// Serialize this snapshot as JSON.
+    ///
+    /// The output is an object with two arrays:
+    ///
+    /// * `"objects"`: each entry has an `"id"`, `"kind"`, `"type"`, `"size"`,
+    ///   and an `"edges"` array whose entries have a `"kind"` (`"field"` or
+    ///   `"element"`), an `"index"`, and the index of the referenced object in
+    ///   `"objects"` as `"to"`.
+    ///
+    /// * `"roots"`: each entry has a `"kind"` describing where the root lives
+    ///   and the index of the rooted object in `"objects"` as `"object"`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push_str("{\"objects\":[");
+        for (i, object) in self.objects.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(
+                out,
+                "{{\"id\":{},\"kind\":\"{}\",\"type\":",
+                object.id,
+                object.kind.as_str()
+            )
+            .unwrap();
+            write_json_string(&mut out, &object.type_name);
+            write!(out, ",\"size\":{},\"edges\":[", object.size).unwrap();
+            for (j, edge) in object.edges.iter().enumerate() {
+                if j > 0 {
+                    out.push(',');
+                }
+                write!(
+                    out,
+                    "{{\"kind\":\"{}\",\"index\":{},\"to\":{}}}",
+                    edge.kind_str(),
+                    edge.index(),
+                    edge.object()
+                )
+                .unwrap();
+            }
+            out.push_str("]}");
+        }
+        out.push_str("],\"roots\":[");
+        for (i, root) in self.roots.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"kind\":");
+            write_json_string(&mut out, &root.kind.to_string());
+            write!(out, ",\"object\":{}}}", root.object).unwrap();
+        }
+        out.push_str("]}");
+        out
+    }
+
+    /// Serialize this snapshot in Chrome's `.heapsnapshot` format.
+    ///
+    /// The result can be loaded into the memory panel of Chrome's DevTools.
+    /// Objects are named after their types. A synthetic `(GC roots)` node
+    /// references every rooted object, with each edge named after the kind of
+    /// its root.
+    pub fn to_chrome_heap_snapshot(&self) -> String {
+        const NODE_FIELDS: usize = 6;
+
+        // Node type indices into the `node_types` meta entry below.
+        const NODE_TYPE_SYNTHETIC: usize = 0;
+        const NODE_TYPE_OBJECT: usize = 1;
+        const NODE_TYPE_ARRAY: usize = 2;
+
+        // Edge type indices into the `edge_types` meta entry below.
+        const EDGE_TYPE_ELEMENT: usize = 0;
+        const EDGE_TYPE_PROPERTY: usize = 1;
+
+        let mut strings = StringTable::default();
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        let mut edge_count = 0;
+
+        // The root node comes first, and node indices in edges are offsets
+        // into the flat `nodes` array, so object `i` lives at `(i + 1) *
+        // NODE_FIELDS`.
+        let root_name = strings.intern("(GC roots)");
+        write!(
+            nodes,
+            "{NODE_TYPE_SYNTHETIC},{root_name},0,0,{},0",
+            self.roots.len()
+        )
+        .unwrap();
+        for root in &self.roots {
+            let name = strings.intern(&root.kind.to_string());
+            if edge_count > 0 {
+                edges.push(',');
+            }
+            edge_count += 1;
+            write!(
+                edges,
+                "{EDGE_TYPE_PROPERTY},{name},{}",
+                (root.object + 1) * NODE_FIELDS
+            )
+            .unwrap();
+        }
+
+        for (i, object) in self.objects.iter().enumerate() {
+            let ty = match object.kind {
+                GcHeapSnapshotObjectKind::Array => NODE_TYPE_ARRAY,
+                _ => NODE_TYPE_OBJECT,
+            };
+            let name = strings.intern(&object.type_name);
+            // Node id 0 is taken by the root node, so offset ids by one.
+            write!(
+                nodes,
+                ",{ty},{name},{},{},{},0",
+                i + 1,
+                object.size,
+                object.edges.len()
+            )
+            .unwrap();
+            for edge in &object.edges {
+                let (ty, name_or_index) = match *edge {
+                    GcHeapSnapshotEdge::Element { index, .. } => {
+                        (EDGE_TYPE_ELEMENT, usize::try_from(index).unwrap())
+                    }
+                    GcHeapSnapshotEdge::Field { field, .. } => (
+                        EDGE_TYPE_PROPERTY,
+                        strings.intern(&format!("field {field}")),
+                    ),
+                };
+                if edge_count > 0 {
+                    edges.push(',');
+                }
+                edge_count += 1;
+                write!(
+                    edges,
+                    "{ty},{name_or_index},{}",
+                    (edge.object() + 1) * NODE_FIELDS
+                )
+                .unwrap();
+            }
+        }
+
+        let mut out = String::new();
+        out.push_str(
+            "{\"snapshot\":{\"meta\":{\
+             \"node_fields\":[\"type\",\"name\",\"id\",\"self_size\",\"edge_count\",\"trace_node_id\"],\
+             \"node_types\":[[\"synthetic\",\"object\",\"array\"],\"string\",\"number\",\"number\",\"number\",\"number\"],\
+             \"edge_fields\":[\"type\",\"name_or_index\",\"to_node\"],\
+             \"edge_types\":[[\"element\",\"property\"],\"string_or_number\",\"node\"],\
+             \"trace_function_info_fields\":[],\"trace_node_fields\":[],\
+             \"sample_fields\":[],\"location_fields\":[]},",
+        );
+        write!(
+            out,
+            "\"node_count\":{},\"edge_count\":{edge_count},\"trace_function_count\":0}},",
+            self.objects.len() + 1
+        )
+        .unwrap();
+        write!(out, "\"nodes\":[{nodes}],\"edges\":[{edges}],").unwrap();
+        out.push_str(
+            "\"trace_function_infos\":[],\"trace_tree\":[],\"samples\":[],\"locations\":[],\
+             \"strings\":[",
+        );
+        for (i, s) in strings.strings.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_json_string(&mut out, s);
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+struct SnapshotBuilder<'a> {
+    engine: &'a Engine,
+    gc_store: &'a GcStore,
+    snapshot: GcHeapSnapshot,
+
+    /// Map from heap index to index in `snapshot.objects`.
+    index_of: HashMap<u32, usize>,
+
+    /// Cache of type names and layouts.
+    types: HashMap<VMSharedTypeIndex, (String, Option<GcLayout>)>,
+
+    /// Objects whose edges have not been traced yet.
+    worklist: Vec<(usize, VMGcRef)>,
+}
+
+impl SnapshotBuilder<'_> {
+    /// Get the snapshot index of the given object, adding it to the snapshot
+    /// if it hasn't been seen before.
+    fn visit(&mut self, gc_ref: VMGcRef) -> usize {
+        let id = gc_ref.as_heap_index().unwrap().get();
+        if let Some(index) = self.index_of.get(&id) {
+            return *index;
+        }
+
+        let gc_store = self.gc_store;
+        let header = gc_store.header(&gc_ref);
+        let kind = header.kind();
+        let kind = if kind.matches(VMGcKind::ExternRef) {
+            GcHeapSnapshotObjectKind::ExternRef
+        } else if kind.matches(VMGcKind::ArrayRef) {
+            GcHeapSnapshotObjectKind::Array
+        } else if kind.matches(VMGcKind::ExnRef) {
+            GcHeapSnapshotObjectKind::Exception
+        } else {
+            GcHeapSnapshotObjectKind::Struct
+        };
+        let type_name = match header.ty() {
+            None => String::from("externref"),
+            Some(ty) => self.ty(ty).0.clone(),
+        };
+
+        let index = self.snapshot.objects.len();
+        self.snapshot.objects.push(GcHeapSnapshotObject {
+            id,
+            kind,
+            type_name,
+            size: gc_store.gc_heap.object_size(&gc_ref),
+            edges: Vec::new(),
+        });
+        self.index_of.insert(id, index);
+        self.worklist.push((index, gc_ref));
+        index
+    }
+
+    fn ty(&mut self, ty: VMSharedTypeIndex) -> &(String, Option<GcLayout>) {
+        let engine = self.engine;
+        self.types.entry(ty).or_insert_with(|| {
+            let name = match engine.signatures().borrow(ty) {
+                Some(ty) => ty.to_string(),
+                None => String::from("<unknown>"),
+            };
+            (name, engine.signatures().layout(ty))
+        })
+    }
+
+    /// Record the edges out of the given object.
+    fn trace(&mut self, index: usize, gc_ref: &VMGcRef) {
+        let gc_store = self.gc_store;
+        let Some(ty) = gc_store.header(gc_ref).ty() else {
+            // `externref`s do not reference other GC objects.
+            return;
+        };
+
+        let mut children = vec![];
+        let data = gc_store.gc_heap.gc_object_data(gc_ref);
+        match &self.ty(ty).1 {
+            None => {}
+            Some(GcLayout::Struct(layout)) => {
+                for (field, f) in layout.fields.iter().enumerate() {
+                    if f.is_gc_ref {
+                        let field = u32::try_from(field).unwrap();
+                        children.push((field, true, data.read_u32(f.offset)));
+                    }
+                }
+            }
+            Some(GcLayout::Array(layout)) => {
+                if layout.elems_are_gc_refs {
+                    let len = gc_store.array_len(gc_ref.as_arrayref_unchecked());
+                    for i in 0..len {
+                        children.push((i, false, data.read_u32(layout.elem_offset(i))));
+                    }
+                }
+            }
+        }
+
+        for (i, is_field, raw) in children {
+            let Some(child) = VMGcRef::from_raw_u32(raw) else {
+                continue;
+            };
+            if child.is_i31() {
+                continue;
+            }
+            let object = self.visit(child);
+            self.snapshot.objects[index].edges.push(if is_field {
+                GcHeapSnapshotEdge::Field { field: i, object }
+            } else {
+                GcHeapSnapshotEdge::Element { index: i, object }
+            });
+        }
+    }
+}
+
+/// Interned strings for the Chrome heap snapshot format.
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl StringTable {
+    fn intern(&mut self, s: &str) -> usize {
+        if let Some(i) = self.indices.get(s) {
+            return *i;
+        }
+        let i = self.strings.len();
+        self.strings.push(s.to_string());
+        self.indices.insert(s.to_string(), i);
+        i
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => write!(out, "\\u{:04x}", u32::from(c)).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,49 @@
+//! Statistics about a store's GC heap.
+
+use core::time::Duration;
+
+/// Statistics about a store's garbage-collected heap.
+///
+/// Returned by [`Store::gc_stats`][crate::Store::gc_stats].
+///
+/// All values are zero if the store has not allocated its GC heap yet.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct GcStats {
+    /// The size of the GC heap's memory, in bytes.
+    pub heap_size: usize,
+
+    /// The number of bytes currently occupied by objects in the GC heap.
+    ///
+    /// This includes garbage objects that have not been reclaimed yet.
+    pub used_bytes: usize,
+
+    /// The number of bytes occupied by objects at the end of the most recent
+    /// collection.
+    ///
+    /// This approximates the size of the live data in the heap. Collectors
+    /// that cannot reclaim all garbage (for example, the deferred
+    /// reference-counting collector and cycles) will over-approximate it.
+    pub live_bytes: usize,
+
+    /// The total number of bytes allocated in the GC heap.
+    pub bytes_allocated: u64,
+
+    /// The number of collections performed.
+    pub collections: u64,
+
+    /// The total time that collections have spent running.
+    ///
+    /// Collections in async stores yield to the executor between increments,
+    /// and time spent yielded is not counted here. WebAssembly in the store
+    /// doesn't run while it is yielded either, so this is a lower bound on how
+    /// long the store's WebAssembly was paused by collections. Without the
+    /// `std` Cargo feature, pause times are not measured and are always zero.
+    pub total_pause_time: Duration,
+
+    /// The longest time that any collection ran without yielding to the
+    /// executor.
+    ///
+    /// For synchronous stores this is the duration of the longest collection.
+    pub max_pause_time: Duration,
+}
@@ -627,6 +627,24 @@ impl Instance {
         let store_id = store.id();
         store[self.id].all_memories(store_id)
     }
+
+    /// Get the global at `index` within this instance, whether or not it is
+    /// exported.
+    #[cfg(feature = "guest-debug")]
+    pub(crate) fn debug_global(&self, store: &StoreOpaque, index: GlobalIndex) -> Option<Global> {
+        let instance = &store[self.id];
+        instance.env_module().globals.get(index)?;
+        Some(instance.get_exported_global(store.id(), index))
+    }
+
+    /// Get the memory at `index` within this instance, whether or not it is
+    /// exported.
+    #[cfg(feature = "guest-debug")]
+    pub(crate) fn debug_memory(&self, store: &StoreOpaque, index: MemoryIndex) -> Option<Memory> {
+        let instance = &store[self.id];
+        instance.env_module().memories.get(index)?;
+        Some(instance.get_exported_memory(store.id(), index))
+    }
 }
 
 pub(crate) struct OwnedImports {
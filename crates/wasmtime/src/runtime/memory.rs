@@ -361,6 +361,8 @@ impl Memory {
             .and_then(|s| s.get_mut(..buffer.len()))
             .ok_or(MemoryAccessError { _private: () })?
             .copy_from_slice(buffer);
+        #[cfg(feature = "wmemcheck")]
+        self.wmemcheck_define(&mut context, offset, buffer.len());
         Ok(())
     }
 
@@ -401,6 +403,43 @@ impl Memory {
         }
     }
 
+    /// Marks `len` bytes of this memory starting at `offset` as initialized
+    /// for wmemcheck's uninitialized-memory mode.
+    ///
+    /// Writes made through [`Memory::write`] are tracked automatically, but
+    /// writes made through the slice returned by [`Memory::data_mut`] aren't.
+    /// Hosts that write guest memory that way should call this afterwards so
+    /// that the written bytes aren't reported as uninitialized. This has no
+    /// effect unless [`Config::wmemcheck_uninit`](crate::Config::wmemcheck_uninit)
+    /// is enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this memory doesn't belong to `store`.
+    #[cfg(feature = "wmemcheck")]
+    pub fn wmemcheck_define(&self, mut store: impl AsContextMut, offset: usize, len: usize) {
+        let store = store.as_context_mut().0;
+        if let Some(wmemcheck_state) = self.wmemcheck_state(store) {
+            wmemcheck_state.define(offset, len);
+        }
+    }
+
+    /// Returns the memory checker state of this memory, first accounting for
+    /// any growth since it was last checked.
+    #[cfg(feature = "wmemcheck")]
+    pub(crate) fn wmemcheck_state<'a>(
+        &self,
+        store: &'a mut StoreOpaque,
+    ) -> Option<&'a mut wasmtime_wmemcheck::Wmemcheck> {
+        let instance = self.instance.get_mut(store);
+        let len = instance.memory(self.index).current_length();
+        let wmemcheck_state = instance.wmemcheck_state_mut(self.index)?;
+        if len > wmemcheck_state.mem_size() {
+            wmemcheck_state.update_mem_size(len - wmemcheck_state.mem_size());
+        }
+        Some(wmemcheck_state)
+    }
+
     /// Same as [`Memory::data_mut`], but also returns the `T` from the
     /// [`StoreContextMut`].
     ///
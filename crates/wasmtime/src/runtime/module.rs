@@ -7,6 +7,7 @@ use crate::{
     Engine,
     code::CodeObject,
     code_memory::CodeMemory,
+    coverage::{CoverageCounters, ModuleCoverage},
     instantiate::CompiledModule,
     resources::ResourcesRequired,
     types::{ExportType, ExternType, ImportType},
@@ -159,6 +160,10 @@ struct ModuleInner {
 
     /// Runtime offset information for `VMContext`.
     offsets: VMOffsets<HostPtr>,
+
+    /// Code coverage counters shared by all instances of this module, if it
+    /// was compiled with coverage enabled.
+    coverage: Option<CoverageCounters>,
 }
 
 impl fmt::Debug for Module {
@@ -404,6 +409,37 @@ impl Module {
         Module::from_parts(engine, code, None)
     }
 
+    /// Same as [`Module::deserialize`], except that the artifact must be
+    /// signed by a key which `engine` trusts.
+    ///
+    /// Artifacts are signed by [`Engine::precompile_module`] when the engine
+    /// used to produce them was configured with [`Config::signing_key`], and
+    /// keys are trusted with [`Config::trust_signing_key`]. The signature
+    /// covers the entire artifact, so any modification to it after it was
+    /// produced is detected.
+    ///
+    /// Unlike [`Module::deserialize`] this function is safe since the
+    /// signature guarantees that `bytes` is the unmodified output of a
+    /// trusted Wasmtime compilation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `engine` trusts no keys, if the artifact is not
+    /// signed, if it was signed by a key which isn't trusted, or if the
+    /// signature doesn't match the artifact's contents. Otherwise errors are
+    /// the same as those of [`Module::deserialize`].
+    ///
+    /// [`Config::signing_key`]: crate::Config::signing_key
+    /// [`Config::trust_signing_key`]: crate::Config::trust_signing_key
+    #[cfg(feature = "signing")]
+    pub fn deserialize_verified(engine: &Engine, bytes: impl AsRef<[u8]>) -> Result<Module> {
+        let bytes = bytes.as_ref();
+        crate::engine::signing::verify(engine, bytes)?;
+        // SAFETY: the signature was verified above with a trusted key, so these
+        // are the unmodified bytes of a trusted compilation.
+        unsafe { Module::deserialize(engine, bytes) }
+    }
+
     /// In-place deserialization of an in-memory compiled module previously
     /// created with [`Module::serialize`] or [`Engine::precompile_module`].
     ///
@@ -545,6 +581,8 @@ impl Module {
 
         let _ = serializable;
 
+        let coverage = CoverageCounters::new(&module);
+
         Ok(Self {
             inner: Arc::new(ModuleInner {
                 engine: engine.clone(),
@@ -554,6 +592,7 @@ impl Module {
                 #[cfg(any(feature = "cranelift", feature = "winch"))]
                 serializable,
                 offsets,
+                coverage,
             }),
         })
     }
@@ -653,7 +692,11 @@ impl Module {
         self.inner.code.module_types()
     }
 
-    #[cfg(any(feature = "component-model", feature = "gc-drc"))]
+    #[cfg(any(
+        feature = "component-model",
+        feature = "gc-drc",
+        feature = "gc-copying"
+    ))]
     pub(crate) fn signatures(&self) -> &crate::type_registry::TypeCollection {
         self.inner.code.signatures()
     }
@@ -1072,6 +1115,44 @@ impl Module {
         })
     }
 
+    /// Returns the number of times each basic block of this module has
+    /// executed so far, summed across all of its instances.
+    ///
+    /// Returns `None` if this module wasn't compiled with
+    /// [`Config::coverage`](crate::Config::coverage) enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut config = Config::new();
+    /// config.coverage(true);
+    /// let engine = Engine::new(&config)?;
+    /// let module = Module::new(&engine, r#"
+    ///     (module (func (export "run") (param i32)
+    ///         (if (local.get 0) (then nop))))
+    /// "#)?;
+    /// let mut store = Store::new(&engine, ());
+    /// let instance = Instance::new(&mut store, &module, &[])?;
+    /// let run = instance.get_typed_func::<i32, ()>(&mut store, "run")?;
+    /// run.call(&mut store, 1)?;
+    ///
+    /// let coverage = module.coverage().unwrap();
+    /// let branch = coverage.functions()[0].branches()[0];
+    /// assert_eq!((branch.taken(), branch.not_taken()), (1, 0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn coverage(&self) -> Option<ModuleCoverage> {
+        let counters = self.inner.coverage.as_ref()?;
+        Some(counters.snapshot(self))
+    }
+
+    pub(crate) fn coverage_counters(&self) -> Option<&CoverageCounters> {
+        self.inner.coverage.as_ref()
+    }
+
     pub(crate) fn id(&self) -> CompiledModuleId {
         self.inner.module.unique_id()
     }
@@ -16,6 +16,10 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use wasmtime_environ::demangle_function_name_or_index;
 
+mod sampling;
+
+pub use sampling::{SampledFrame, SampledFunction, SamplingProfiler};
+
 // TODO: collect more data
 // - On non-Windows, measure thread-local CPU usage between events with
 //   rustix::time::clock_gettime(ClockId::ThreadCPUTime)
@@ -0,0 +1,317 @@
+//! A self-contained sampling profiler which aggregates samples by WebAssembly
+//! function.
+
+#[cfg(feature = "component-model")]
+use crate::component::Component;
+use crate::prelude::*;
+use crate::runtime::vm::Backtrace;
+use crate::{AsContext, Module};
+use core::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::Range;
+use std::time::{Duration, Instant, SystemTime};
+use wasmtime_environ::{DefinedFuncIndex, demangle_function_name_or_index};
+
+/// A sampling profiler which aggregates samples by WebAssembly function.
+///
+/// Unlike [`GuestProfiler`](crate::GuestProfiler), which records a timeline
+/// of every sample, this profiler only keeps a count of how many times each
+/// distinct stack was observed. This keeps memory usage proportional to the
+/// number of distinct stacks rather than the length of the run.
+///
+/// The profile can be written out as collapsed stacks with
+/// [`SamplingProfiler::write_collapsed`], or inspected through
+/// [`SamplingProfiler::stacks`] and [`SamplingProfiler::functions`] to
+/// produce other formats such as flame graphs or pprof profiles.
+///
+/// Samples are collected by calling [`SamplingProfiler::sample`] at regular
+/// intervals while the guest is on the stack, typically from a callback
+/// registered with
+/// [`Store::epoch_deadline_callback()`](crate::Store::epoch_deadline_callback).
+/// The accuracy caveats documented on [`GuestProfiler`](crate::GuestProfiler)
+/// apply here too.
+///
+/// Each frame is attributed to the module it was found in. Frames in modules
+/// that were not passed to the constructor are omitted, following the same
+/// security considerations as [`GuestProfiler`](crate::GuestProfiler).
+#[derive(Debug)]
+pub struct SamplingProfiler {
+    interval: Duration,
+    start: Instant,
+    start_time: SystemTime,
+    modules: Vec<SampledModule>,
+    functions: Vec<SampledFunction>,
+    function_ids: HashMap<(usize, DefinedFuncIndex), usize>,
+    stacks: HashMap<Vec<SampledFrame>, u64>,
+    samples: u64,
+}
+
+#[derive(Debug)]
+struct SampledModule {
+    name: String,
+    module: Module,
+    text_range: Range<usize>,
+}
+
+/// A WebAssembly function which appears in a [`SamplingProfiler`]'s
+/// samples.
+#[derive(Debug)]
+pub struct SampledFunction {
+    module: String,
+    name: String,
+}
+
+impl SampledFunction {
+    /// The name of the module this function is in, as passed to
+    /// [`SamplingProfiler::new`].
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    /// The demangled name of this function, or its index if it has no name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of this function qualified by its module's name, if that
+    /// isn't empty.
+    pub fn label(&self) -> String {
+        if self.module.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}!{}", self.module, self.name)
+        }
+    }
+}
+
+/// A single frame of a sampled stack: the function it's in and the offset
+/// of the instruction in the original WebAssembly binary, if known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SampledFrame {
+    function: usize,
+    wasm_offset: Option<u32>,
+}
+
+impl SampledFrame {
+    /// The index of this frame's function within
+    /// [`SamplingProfiler::functions`].
+    pub fn function(&self) -> usize {
+        self.function
+    }
+
+    /// The offset of the sampled instruction within the original WebAssembly
+    /// binary, if known.
+    pub fn wasm_offset(&self) -> Option<u32> {
+        self.wasm_offset
+    }
+}
+
+impl SamplingProfiler {
+    /// Begin profiling with the provided `modules`.
+    ///
+    /// The `interval` parameter should match the rate at which you intend to
+    /// call `sample`. It isn't used by the profiler itself, but is available
+    /// through [`SamplingProfiler::interval`] to convert sample counts into
+    /// durations.
+    ///
+    /// Only modules which are present in `modules` will appear in stack
+    /// traces. Each module's name is used to attribute its functions in the
+    /// output; it may be empty, in which case function names are used alone.
+    pub fn new(interval: Duration, modules: impl IntoIterator<Item = (String, Module)>) -> Self {
+        let mut modules: Vec<_> = modules
+            .into_iter()
+            .filter_map(|(name, module)| {
+                let compiled = module.compiled_module();
+                let start = compiled.finished_functions().next()?.1.as_ptr_range().start as usize;
+                let end = compiled.finished_functions().last()?.1.as_ptr_range().end as usize;
+                Some(SampledModule {
+                    name,
+                    module,
+                    text_range: start..end,
+                })
+            })
+            .collect();
+        modules.sort_unstable_by_key(|m| m.text_range.start);
+
+        Self {
+            interval,
+            start: Instant::now(),
+            start_time: SystemTime::now(),
+            modules,
+            functions: Vec::new(),
+            function_ids: HashMap::new(),
+            stacks: HashMap::new(),
+            samples: 0,
+        }
+    }
+
+    /// Begin profiling the provided component.
+    ///
+    /// See [`SamplingProfiler::new`] for additional information. Each of the
+    /// component's core modules is named `component_name/module_name` so that
+    /// samples are attributed to the component they came from.
+    #[cfg(feature = "component-model")]
+    pub fn new_component(
+        component_name: &str,
+        interval: Duration,
+        component: Component,
+        extra_modules: impl IntoIterator<Item = (String, Module)>,
+    ) -> Self {
+        let modules = component
+            .static_modules()
+            .map(|m| {
+                let name = m.name().unwrap_or("<unknown>");
+                (format!("{component_name}/{name}"), m.clone())
+            })
+            .chain(extra_modules);
+        Self::new(interval, modules)
+    }
+
+    /// Add a sample of the current stack to the profile.
+    ///
+    /// Samples where no frames belong to a profiled module are counted
+    /// towards [`SamplingProfiler::samples`] but don't otherwise appear in
+    /// the output.
+    pub fn sample(&mut self, store: impl AsContext) {
+        let backtrace = Backtrace::new(store.as_context().0);
+        let mut stack = Vec::with_capacity(backtrace.frames().len());
+        // Stacks are stored with the oldest frame first.
+        for frame in backtrace.frames().rev() {
+            if let Some(location) = self.lookup(frame.pc()) {
+                stack.push(location);
+            }
+        }
+        self.samples += 1;
+        if !stack.is_empty() {
+            *self.stacks.entry(stack).or_insert(0) += 1;
+        }
+    }
+
+    /// Returns the total number of samples taken so far.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Returns the interval between samples passed to
+    /// [`SamplingProfiler::new`].
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the time at which profiling began.
+    pub fn start_time(&self) -> SystemTime {
+        self.start_time
+    }
+
+    /// Returns how long it has been since profiling began.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Returns every function which appears in a sampled stack.
+    pub fn functions(&self) -> &[SampledFunction] {
+        &self.functions
+    }
+
+    /// Returns each distinct stack that was sampled, oldest frame first, with
+    /// the number of times that it was sampled.
+    ///
+    /// Stacks are returned in an unspecified order.
+    pub fn stacks(&self) -> impl Iterator<Item = (&[SampledFrame], u64)> + '_ {
+        self.stacks
+            .iter()
+            .map(|(stack, count)| (stack.as_slice(), *count))
+    }
+
+    fn lookup(&mut self, pc: usize) -> Option<SampledFrame> {
+        let module_idx = self
+            .modules
+            .binary_search_by(|probe| {
+                if probe.text_range.contains(&pc) {
+                    Ordering::Equal
+                } else {
+                    probe.text_range.start.cmp(&pc)
+                }
+            })
+            .ok()?;
+        let module = &self.modules[module_idx];
+        let compiled = module.module.compiled_module();
+        let text_offset = pc - module.module.text().as_ptr_range().start as usize;
+        let def_func_index = compiled.func_by_text_offset(text_offset)?;
+        let wasm_offset = wasmtime_environ::lookup_file_pos(
+            compiled.code_memory().address_map_data(),
+            text_offset,
+        )
+        .and_then(|pos| pos.file_offset());
+
+        let function = match self.function_ids.get(&(module_idx, def_func_index)) {
+            Some(id) => *id,
+            None => {
+                let func_index = compiled.module().func_index(def_func_index);
+                let mut name = String::new();
+                demangle_function_name_or_index(
+                    &mut name,
+                    compiled.func_name(func_index),
+                    func_index.as_u32() as usize,
+                )
+                .unwrap();
+                let id = self.functions.len();
+                self.functions.push(SampledFunction {
+                    module: module.name.clone(),
+                    name,
+                });
+                self.function_ids.insert((module_idx, def_func_index), id);
+                id
+            }
+        };
+        Some(SampledFrame {
+            function,
+            wasm_offset,
+        })
+    }
+
+    /// Aggregates sampled stacks by function, discarding instruction offsets,
+    /// and returns them, oldest frame first, sorted by the
+    /// [labels](SampledFunction::label) of their frames.
+    pub fn collapsed_stacks(&self) -> Vec<(Vec<String>, u64)> {
+        let mut by_function = HashMap::<Vec<usize>, u64>::new();
+        for (stack, count) in self.stacks.iter() {
+            let key = stack.iter().map(|l| l.function).collect();
+            *by_function.entry(key).or_insert(0) += count;
+        }
+        let mut stacks: Vec<_> = by_function
+            .into_iter()
+            .map(|(stack, count)| {
+                let labels = stack.iter().map(|f| self.functions[*f].label()).collect();
+                (labels, count)
+            })
+            .collect();
+        stacks.sort();
+        stacks
+    }
+
+    /// Write the profile in the "collapsed stacks" format used by
+    /// [`inferno`] and [FlameGraph].
+    ///
+    /// Each line consists of a semicolon-separated list of frames, oldest
+    /// first, followed by a space and the number of samples of that stack.
+    ///
+    /// [`inferno`]: https://github.com/jonhoo/inferno
+    /// [FlameGraph]: https://github.com/brendangregg/FlameGraph
+    pub fn write_collapsed(&self, mut output: impl Write) -> Result<()> {
+        for (stack, count) in self.collapsed_stacks() {
+            let mut line = String::new();
+            for (i, frame) in stack.iter().enumerate() {
+                if i > 0 {
+                    line.push(';');
+                }
+                // Semicolons delimit frames, so they can't appear in names.
+                line.extend(frame.chars().map(|c| if c == ';' { ':' } else { c }));
+            }
+            writeln!(output, "{line} {count}")?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
@@ -85,6 +85,7 @@ use crate::component::ComponentStoreData;
 use crate::component::concurrent;
 #[cfg(feature = "async")]
 use crate::fiber;
+use crate::fuel_profile::FuelProfiler;
 use crate::module::RegisteredModuleId;
 use crate::prelude::*;
 #[cfg(feature = "gc")]
@@ -98,7 +99,7 @@ use crate::runtime::vm::{
     SignalHandler, StoreBox, Unwind, VMContext, VMFuncRef, VMGcRef, VMStore, VMStoreContext,
 };
 use crate::trampoline::VMHostGlobalContext;
-use crate::{Engine, Module, Val, ValRaw, module::ModuleRegistry};
+use crate::{Engine, FuelProfile, Module, Val, ValRaw, module::ModuleRegistry};
 #[cfg(feature = "gc")]
 use crate::{ExnRef, Rooted};
 use crate::{Global, Instance, Memory, Table, Uninhabited};
@@ -111,7 +112,9 @@ use core::ops::{Deref, DerefMut};
 use core::pin::Pin;
 use core::ptr::NonNull;
 use wasmtime_environ::StaticModuleIndex;
-use wasmtime_environ::{DefinedGlobalIndex, DefinedTableIndex, EntityRef, PrimaryMap, TripleExt};
+use wasmtime_environ::{
+    DefinedGlobalIndex, DefinedTableIndex, EntityRef, FuncIndex, PrimaryMap, TripleExt,
+};
 
 mod context;
 pub use self::context::*;
@@ -242,6 +245,8 @@ pub struct StoreInner<T: 'static> {
 
     limiter: Option<ResourceLimiterInner<T>>,
     call_hook: Option<CallHookInner<T>>,
+    #[cfg(feature = "guest-debug")]
+    debug_handler: Option<DebugHandlerInner<T>>,
     #[cfg(target_has_atomic = "64")]
     epoch_deadline_behavior:
         Option<Box<dyn FnMut(StoreContextMut<T>) -> Result<UpdateDeadline> + Send + Sync>>,
@@ -328,6 +333,19 @@ enum CallHookInner<T: 'static> {
     },
 }
 
+#[cfg(feature = "guest-debug")]
+enum DebugHandlerInner<T: 'static> {
+    Sync(
+        Box<
+            dyn FnMut(StoreContextMut<'_, T>, &crate::DebugFrame) -> Result<crate::DebugAction>
+                + Send
+                + Sync,
+        >,
+    ),
+    #[cfg(feature = "async")]
+    Async(Box<dyn crate::DebugHandler<T> + Send + Sync>),
+}
+
 /// What to do after returning from a callback when the engine epoch reaches
 /// the deadline for a Store during execution of a function using that store.
 #[non_exhaustive]
@@ -402,6 +420,8 @@ pub struct StoreOpaque {
 
     engine: Engine,
     vm_store_context: VMStoreContext,
+    #[cfg(feature = "guest-debug")]
+    debug: crate::runtime::debugger::DebugState,
 
     // Contains all continuations ever allocated throughout the lifetime of this
     // store.
@@ -452,6 +472,9 @@ pub struct StoreOpaque {
     // until the reserve is empty.
     fuel_reserve: u64,
     pub(crate) fuel_yield_interval: Option<NonZeroU64>,
+    /// Attribution of consumed fuel to functions, present when
+    /// `Config::fuel_profiling` is enabled.
+    fuel_profiler: Option<FuelProfiler>,
     /// Indexed data within this `Store`, used to store information about
     /// globals, functions, memories, etc.
     store_data: StoreData,
@@ -654,6 +677,8 @@ impl<T> Store<T> {
             _marker: marker::PhantomPinned,
             engine: engine.clone(),
             vm_store_context: Default::default(),
+            #[cfg(feature = "guest-debug")]
+            debug: Default::default(),
             #[cfg(feature = "stack-switching")]
             continuations: Vec::new(),
             instances: PrimaryMap::new(),
@@ -681,6 +706,11 @@ impl<T> Store<T> {
             async_state: Default::default(),
             fuel_reserve: 0,
             fuel_yield_interval: None,
+            fuel_profiler: if engine.tunables().fuel_profiling {
+                Some(FuelProfiler::default())
+            } else {
+                None
+            },
             store_data,
             traitobj: StorePtr(None),
             default_caller_vmctx: SendSyncPtr::new(NonNull::dangling()),
@@ -701,6 +731,8 @@ impl<T> Store<T> {
             inner,
             limiter: None,
             call_hook: None,
+            #[cfg(feature = "guest-debug")]
+            debug_handler: None,
             #[cfg(target_has_atomic = "64")]
             epoch_deadline_behavior: None,
             data: ManuallyDrop::new(data),
@@ -904,6 +936,76 @@ impl<T> Store<T> {
         self.inner.call_hook = Some(CallHookInner::Sync(Box::new(hook)));
     }
 
+    /// Configures a function that runs whenever the guest debugger pauses
+    /// execution of WebAssembly.
+    ///
+    /// Execution pauses before an instruction when a [`Breakpoint`] added with
+    /// [`Store::add_breakpoint`] is hit, or before every instruction while
+    /// single-stepping. The function is passed the paused
+    /// [`DebugFrame`](crate::DebugFrame), which can be used to inspect locals,
+    /// the operand stack, globals and memories, and returns a
+    /// [`DebugAction`](crate::DebugAction) describing how to resume. If the
+    /// function returns an error then it is raised as a trap in the paused
+    /// frame.
+    ///
+    /// If no handler is configured then breakpoints are ignored.
+    ///
+    /// This requires code to be compiled with
+    /// [`Config::guest_debug`](crate::Config::guest_debug) enabled.
+    ///
+    /// [`Breakpoint`]: crate::Breakpoint
+    #[cfg(feature = "guest-debug")]
+    pub fn debug_handler(
+        &mut self,
+        handler: impl FnMut(StoreContextMut<'_, T>, &crate::DebugFrame) -> Result<crate::DebugAction>
+        + Send
+        + Sync
+        + 'static,
+    ) {
+        self.inner.debug_handler = Some(DebugHandlerInner::Sync(Box::new(handler)));
+    }
+
+    /// Adds a breakpoint which pauses execution and invokes the handler
+    /// configured with [`Store::debug_handler`].
+    ///
+    /// Adding a breakpoint that is already present has no effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this store's [`Engine`] was not configured with
+    /// [`Config::guest_debug`](crate::Config::guest_debug), or if the
+    /// breakpoint's module belongs to a different [`Engine`].
+    #[cfg(feature = "guest-debug")]
+    pub fn add_breakpoint(&mut self, breakpoint: crate::Breakpoint) -> Result<()> {
+        self.inner.add_breakpoint(breakpoint)
+    }
+
+    /// Removes a breakpoint previously added with [`Store::add_breakpoint`].
+    ///
+    /// Returns whether the breakpoint was present.
+    #[cfg(feature = "guest-debug")]
+    pub fn remove_breakpoint(&mut self, breakpoint: &crate::Breakpoint) -> bool {
+        self.inner.remove_breakpoint(breakpoint)
+    }
+
+    /// Configures whether execution pauses before every WebAssembly
+    /// instruction, invoking the handler configured with
+    /// [`Store::debug_handler`].
+    ///
+    /// Single-stepping is also enabled or disabled by the
+    /// [`DebugAction`](crate::DebugAction) returned from each invocation of
+    /// the handler.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if single-stepping is being enabled and this store's
+    /// [`Engine`] was not configured with
+    /// [`Config::guest_debug`](crate::Config::guest_debug).
+    #[cfg(feature = "guest-debug")]
+    pub fn single_step(&mut self, enable: bool) -> Result<()> {
+        self.inner.single_step(enable)
+    }
+
     /// Returns the [`Engine`] that this store is associated with.
     pub fn engine(&self) -> &Engine {
         self.inner.engine()
@@ -927,6 +1029,38 @@ impl<T> Store<T> {
         StoreContextMut(&mut self.inner).gc(why)
     }
 
+    /// Returns statistics about this store's GC heap.
+    ///
+    /// See [`GcStats`][crate::GcStats] for the statistics that are collected.
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub fn gc_stats(&self) -> crate::GcStats {
+        self.inner.gc_stats()
+    }
+
+    /// Takes a snapshot of the GC objects that are reachable from this store's
+    /// GC roots.
+    ///
+    /// The snapshot records each reachable object's type and size, the
+    /// references between objects, and the roots that keep them alive. It can
+    /// be exported as JSON or in Chrome's heap-snapshot format; see
+    /// [`GcHeapSnapshot`][crate::GcHeapSnapshot] for details.
+    ///
+    /// Taking a snapshot does not perform a collection and does not modify the
+    /// GC heap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this store is configured for async support; use
+    /// [`Store::gc_heap_snapshot_async`] instead.
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub fn gc_heap_snapshot(&mut self) -> crate::GcHeapSnapshot {
+        StoreContextMut(&mut self.inner).gc_heap_snapshot()
+    }
+
     /// Returns the amount fuel in this [`Store`]. When fuel is enabled, it must
     /// be configured via [`Store::set_fuel`].
     ///
@@ -961,6 +1095,36 @@ impl<T> Store<T> {
         self.inner.set_fuel(fuel)
     }
 
+    /// Returns a report of the fuel consumed by each WebAssembly function
+    /// executed in this [`Store`] so far.
+    ///
+    /// Functions which are still executing, for example because this is
+    /// called from a host function, are treated as if they returned just now.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if fuel profiling is not enabled
+    /// via [`Config::fuel_profiling`](crate::Config::fuel_profiling).
+    pub fn fuel_profile(&self) -> Result<FuelProfile> {
+        self.inner.fuel_profile()
+    }
+
+    /// Returns wmemcheck's summary of heap usage and leaked blocks for each
+    /// instance in this [`Store`] whose allocator was called, in
+    /// instantiation order.
+    ///
+    /// The allocation backtraces of leaked blocks are symbolized here, and are
+    /// only recorded if [`Config::wasm_backtrace`](crate::Config::wasm_backtrace)
+    /// is enabled.
+    ///
+    /// This is the only way to get leak summaries: nothing is reported when
+    /// the store is dropped, so embedders which want a report should call
+    /// this once the program has finished running.
+    #[cfg(feature = "wmemcheck")]
+    pub fn wmemcheck_leak_summaries(&self) -> Vec<crate::LeakSummary> {
+        self.inner.wmemcheck_leak_summaries()
+    }
+
     /// Configures a [`Store`] to yield execution of async WebAssembly code
     /// periodically.
     ///
@@ -1192,6 +1356,13 @@ impl<'a, T> StoreContext<'a, T> {
     pub fn get_fuel(&self) -> Result<u64> {
         self.0.get_fuel()
     }
+
+    /// Returns a report of the fuel consumed by each function in this store.
+    ///
+    /// For more information see [`Store::fuel_profile`].
+    pub fn fuel_profile(&self) -> Result<FuelProfile> {
+        self.0.fuel_profile()
+    }
 }
 
 impl<'a, T> StoreContextMut<'a, T> {
@@ -1226,6 +1397,27 @@ impl<'a, T> StoreContextMut<'a, T> {
         vm::assert_ready(store.gc(limiter.as_mut(), None, why.map(|e| e.bytes_needed())));
     }
 
+    /// Returns statistics about this store's GC heap.
+    ///
+    /// Same as [`Store::gc_stats`].
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub fn gc_stats(&self) -> crate::GcStats {
+        self.0.gc_stats()
+    }
+
+    /// Takes a snapshot of this store's reachable GC objects.
+    ///
+    /// Same as [`Store::gc_heap_snapshot`].
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub fn gc_heap_snapshot(&mut self) -> crate::GcHeapSnapshot {
+        assert!(!self.0.async_support());
+        vm::assert_ready(self.0.gc_heap_snapshot())
+    }
+
     /// Returns remaining fuel in this store.
     ///
     /// For more information see [`Store::get_fuel`]
@@ -1240,6 +1432,21 @@ impl<'a, T> StoreContextMut<'a, T> {
         self.0.set_fuel(fuel)
     }
 
+    /// Returns a report of the fuel consumed by each function in this store.
+    ///
+    /// For more information see [`Store::fuel_profile`]
+    pub fn fuel_profile(&self) -> Result<FuelProfile> {
+        self.0.fuel_profile()
+    }
+
+    /// Returns wmemcheck's leak summary for each instance in this store.
+    ///
+    /// For more information see [`Store::wmemcheck_leak_summaries`]
+    #[cfg(feature = "wmemcheck")]
+    pub fn wmemcheck_leak_summaries(&self) -> Vec<crate::LeakSummary> {
+        self.0.wmemcheck_leak_summaries()
+    }
+
     /// Configures this `Store` to periodically yield while executing futures.
     ///
     /// For more information see [`Store::fuel_async_yield_interval`]
@@ -1263,6 +1470,30 @@ impl<'a, T> StoreContextMut<'a, T> {
         self.0.epoch_deadline_trap();
     }
 
+    /// Adds a guest debugger breakpoint.
+    ///
+    /// For more information see [`Store::add_breakpoint`].
+    #[cfg(feature = "guest-debug")]
+    pub fn add_breakpoint(&mut self, breakpoint: crate::Breakpoint) -> Result<()> {
+        self.0.add_breakpoint(breakpoint)
+    }
+
+    /// Removes a guest debugger breakpoint.
+    ///
+    /// For more information see [`Store::remove_breakpoint`].
+    #[cfg(feature = "guest-debug")]
+    pub fn remove_breakpoint(&mut self, breakpoint: &crate::Breakpoint) -> bool {
+        self.0.remove_breakpoint(breakpoint)
+    }
+
+    /// Configures single-stepping in the guest debugger.
+    ///
+    /// For more information see [`Store::single_step`].
+    #[cfg(feature = "guest-debug")]
+    pub fn single_step(&mut self, enable: bool) -> Result<()> {
+        self.0.single_step(enable)
+    }
+
     /// Set an exception as the currently pending exception, and
     /// return an error that propagates the throw.
     ///
@@ -1513,6 +1744,22 @@ impl StoreOpaque {
         self.instances[id].handle.get_mut()
     }
 
+    /// Returns the memory checker state of `instance`'s memory `index`,
+    /// following imports to the instance that defines the memory.
+    #[cfg(feature = "wmemcheck")]
+    pub(crate) fn wmemcheck_state(
+        &mut self,
+        instance: InstanceId,
+        index: wasmtime_environ::MemoryIndex,
+    ) -> Option<&mut wasmtime_wmemcheck::Wmemcheck> {
+        let instance = self.instance(instance);
+        if index.index() >= instance.env_module().memories.len() {
+            return None;
+        }
+        let memory = instance.get_exported_memory(self.id(), index);
+        memory.wmemcheck_state(self)
+    }
+
     /// Access multiple instances specified via `ids`.
     ///
     /// # Panics
@@ -1623,6 +1870,45 @@ impl StoreOpaque {
         &mut self.vm_store_context
     }
 
+    #[cfg(feature = "guest-debug")]
+    pub(crate) fn debug_state(&self) -> &crate::runtime::debugger::DebugState {
+        &self.debug
+    }
+
+    #[cfg(feature = "guest-debug")]
+    pub(crate) fn debug_state_mut(&mut self) -> &mut crate::runtime::debugger::DebugState {
+        &mut self.debug
+    }
+
+    /// Updates the flag read by compiled code to decide whether to call into
+    /// the guest debugger, to be called whenever `debug_state` changes.
+    #[cfg(feature = "guest-debug")]
+    pub(crate) fn update_debug_hook_enabled(&mut self) {
+        *self.vm_store_context.debug_hook_enabled.get_mut() =
+            usize::from(self.debug.hook_enabled());
+    }
+
+    #[cfg(feature = "guest-debug")]
+    fn add_breakpoint(&mut self, breakpoint: crate::Breakpoint) -> Result<()> {
+        self.debug.add_breakpoint(&self.engine, breakpoint)?;
+        self.update_debug_hook_enabled();
+        Ok(())
+    }
+
+    #[cfg(feature = "guest-debug")]
+    fn remove_breakpoint(&mut self, breakpoint: &crate::Breakpoint) -> bool {
+        let removed = self.debug.remove_breakpoint(breakpoint);
+        self.update_debug_hook_enabled();
+        removed
+    }
+
+    #[cfg(feature = "guest-debug")]
+    fn single_step(&mut self, enable: bool) -> Result<()> {
+        self.debug.set_single_step(&self.engine, enable)?;
+        self.update_debug_hook_enabled();
+        Ok(())
+    }
+
     /// Performs a lazy allocation of the `GcStore` within this store, returning
     /// the previous allocation if it's already present.
     ///
@@ -1981,7 +2267,7 @@ impl StoreOpaque {
         if let Some(pending_exception) = self.pending_exception.as_mut() {
             unsafe {
                 let root = pending_exception.as_gc_ref_mut();
-                gc_roots_list.add_root(root.into(), "Pending exception");
+                gc_roots_list.add_root(root.into(), vm::GcRootKind::PendingException);
             }
         }
         log::trace!("End trace GC roots :: pending exception");
@@ -2053,6 +2339,12 @@ impl StoreOpaque {
             self.engine().tunables().consume_fuel,
             "fuel is not configured in this store"
         );
+        if self.fuel_profiler.is_some() {
+            let remaining = self.get_fuel()?;
+            if let Some(profiler) = &mut self.fuel_profiler {
+                profiler.set_fuel(remaining, fuel);
+            }
+        }
         let injected_fuel = unsafe { &mut *self.vm_store_context.fuel_consumed.get() };
         set_fuel(
             injected_fuel,
@@ -2063,6 +2355,121 @@ impl StoreOpaque {
         Ok(())
     }
 
+    #[cfg(feature = "wmemcheck")]
+    pub fn wmemcheck_leak_summaries(&self) -> Vec<crate::LeakSummary> {
+        self.instances
+            .keys()
+            .flat_map(|id| self.instance(id).wmemcheck_states())
+            .filter(|state| state.has_allocated())
+            .map(|state| state.leak_summary(|pcs| self.wmemcheck_symbolize(pcs)))
+            .collect()
+    }
+
+    /// Returns the number of active allocator calls for each memory checked
+    /// by wmemcheck in this store, to later pass to `wmemcheck_unwind`.
+    #[cfg(feature = "wmemcheck")]
+    pub(crate) fn wmemcheck_allocator_depths(&self) -> Vec<usize> {
+        self.instances
+            .keys()
+            .flat_map(|id| self.instance(id).wmemcheck_states())
+            .map(|state| state.allocator_depth())
+            .collect()
+    }
+
+    /// Restores the number of active allocator calls of each memory checked
+    /// by wmemcheck once execution leaves WebAssembly, since a trap may have
+    /// unwound allocator calls which never returned.
+    ///
+    /// Memories of instances created since `depths` was taken are reset to
+    /// having no active allocator calls.
+    #[cfg(feature = "wmemcheck")]
+    pub(crate) fn wmemcheck_unwind(&mut self, depths: &[usize]) {
+        let ids = self.instances.keys().collect::<Vec<_>>();
+        let mut depths = depths.iter().copied();
+        for id in ids {
+            for state in self.instance_mut(id).wmemcheck_states_mut() {
+                state.unwind_allocator(depths.next().unwrap_or(0));
+            }
+        }
+    }
+
+    /// Formats the frames of a backtrace recorded by wmemcheck, given the
+    /// return address of each frame.
+    #[cfg(feature = "wmemcheck")]
+    fn wmemcheck_symbolize(&self, pcs: &[usize]) -> Option<String> {
+        use core::fmt::Write;
+
+        let mut s = String::new();
+        for pc in pcs {
+            let Some((frame, _)) = self.modules().lookup_frame_info(pc - 1) else {
+                continue;
+            };
+            let at = if s.is_empty() { "at" } else { "by" };
+            let module = frame.module().name().unwrap_or("<unknown>");
+            let offset = frame.module_offset().unwrap_or(0);
+            let _ = write!(s, "{at} {offset:#x}: ");
+            let _ = match frame.func_name() {
+                Some(name) => write!(s, "{name}"),
+                None => write!(s, "<wasm function {}>", frame.func_index()),
+            };
+            let _ = writeln!(s, " ({module})");
+        }
+        (!s.is_empty()).then_some(s)
+    }
+
+    pub fn fuel_profile(&self) -> Result<FuelProfile> {
+        let profiler = self
+            .fuel_profiler
+            .as_ref()
+            .ok_or_else(|| anyhow!("fuel profiling is not configured in this store"))?;
+        Ok(profiler.report(self.get_fuel()?))
+    }
+
+    pub(crate) fn fuel_profile_enter(&mut self, instance: InstanceId, func: FuncIndex, fp: usize) {
+        let remaining = self.get_fuel().unwrap_or(0);
+        let stack = self.current_stack_id();
+        let Some(profiler) = &mut self.fuel_profiler else {
+            return;
+        };
+        if let Some(module) = self.instances[instance].handle.get().runtime_module() {
+            profiler.enter(remaining, stack, module, func, fp);
+        }
+    }
+
+    pub(crate) fn fuel_profile_exit(&mut self, fp: usize) {
+        let remaining = self.get_fuel().unwrap_or(0);
+        let stack = self.current_stack_id();
+        if let Some(profiler) = &mut self.fuel_profiler {
+            profiler.exit(remaining, stack, fp);
+        }
+    }
+
+    /// Returns the depth of the fuel profiler's shadow stack for the current
+    /// fiber, if fuel profiling is enabled, to later pass to
+    /// `fuel_profile_unwind`.
+    pub(crate) fn fuel_profile_depth(&self) -> Option<usize> {
+        let stack = self.current_stack_id();
+        self.fuel_profiler.as_ref().map(|p| p.depth(stack))
+    }
+
+    /// Discards frames from the fuel profiler's shadow stack for the current
+    /// fiber which were entered after it was `depth` frames deep, such as
+    /// those unwound by a trap.
+    pub(crate) fn fuel_profile_unwind(&mut self, depth: Option<usize>) {
+        let Some(depth) = depth else { return };
+        let remaining = self.get_fuel().unwrap_or(0);
+        let stack = self.current_stack_id();
+        if let Some(profiler) = &mut self.fuel_profiler {
+            profiler.unwind(remaining, stack, depth);
+        }
+    }
+
+    /// Identifies the native stack that WebAssembly is currently running on,
+    /// which is unique to each fiber.
+    pub(crate) fn current_stack_id(&self) -> usize {
+        self.vm_store_context.async_guard_range.start.addr()
+    }
+
     pub fn fuel_async_yield_interval(&mut self, interval: Option<u64>) -> Result<()> {
         anyhow::ensure!(
             self.engine().tunables().consume_fuel,
@@ -2555,6 +2962,30 @@ unsafe impl<T> VMStore for StoreInner<T> {
         update
     }
 
+    #[cfg(feature = "guest-debug")]
+    fn debug_pause(&mut self, frame: &crate::DebugFrame) -> Result<crate::DebugAction> {
+        // Temporarily take the handler to avoid mutably borrowing multiple
+        // times, as with `new_epoch_updated_deadline` above.
+        let mut handler = self.debug_handler.take();
+        let action = match &mut handler {
+            Some(DebugHandlerInner::Sync(handler)) => handler((&mut *self).as_context_mut(), frame),
+            #[cfg(feature = "async")]
+            Some(DebugHandlerInner::Async(handler)) => {
+                if !self.can_block() {
+                    Err(anyhow!("couldn't grab async_cx for debug handler"))
+                } else {
+                    (&mut *self)
+                        .as_context_mut()
+                        .with_blocking(|store, cx| cx.block_on(handler.handle_pause(store, frame)))
+                        .and_then(|r| r)
+                }
+            }
+            None => Ok(crate::DebugAction::Continue),
+        };
+        self.debug_handler = handler;
+        action
+    }
+
     #[cfg(feature = "component-model")]
     fn component_calls(&mut self) -> &mut vm::component::CallContexts {
         &mut self.component_calls
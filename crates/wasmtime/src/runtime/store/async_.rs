@@ -83,6 +83,21 @@ impl<T> Store<T> {
         self.inner.call_hook = Some(crate::store::CallHookInner::Async(Box::new(hook)));
     }
 
+    /// Configures an asynchronous handler that runs whenever the guest
+    /// debugger pauses execution of WebAssembly.
+    ///
+    /// This is the asynchronous variant of [`Store::debug_handler`], where
+    /// execution of WebAssembly stays suspended until the future returned by
+    /// the handler resolves. This requires
+    /// [`Config::async_support`](crate::Config::async_support) to be enabled.
+    #[cfg(feature = "guest-debug")]
+    pub fn debug_handler_async(
+        &mut self,
+        handler: impl crate::DebugHandler<T> + Send + Sync + 'static,
+    ) {
+        self.inner.debug_handler = Some(crate::store::DebugHandlerInner::Async(Box::new(handler)));
+    }
+
     /// Perform garbage collection asynchronously.
     ///
     /// Note that it is not required to actively call this function. GC will
@@ -98,6 +113,22 @@ impl<T> Store<T> {
         StoreContextMut(&mut self.inner).gc_async(why).await
     }
 
+    /// Takes a snapshot of this store's reachable GC objects asynchronously.
+    ///
+    /// Same as [`Store::gc_heap_snapshot`], but for stores configured with
+    /// async support.
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub async fn gc_heap_snapshot_async(&mut self) -> crate::GcHeapSnapshot
+    where
+        T: Send,
+    {
+        StoreContextMut(&mut self.inner)
+            .gc_heap_snapshot_async()
+            .await
+    }
+
     /// Configures epoch-deadline expiration to yield to the async
     /// caller and the update the deadline.
     ///
@@ -144,6 +175,19 @@ impl<'a, T> StoreContextMut<'a, T> {
             .await;
     }
 
+    /// Takes a snapshot of this store's reachable GC objects asynchronously.
+    ///
+    /// Same as [`Store::gc_heap_snapshot_async`].
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub async fn gc_heap_snapshot_async(&mut self) -> crate::GcHeapSnapshot
+    where
+        T: Send + 'static,
+    {
+        self.0.gc_heap_snapshot().await
+    }
+
     /// Configures epoch-deadline expiration to yield to the async
     /// caller and the update the deadline.
     ///
@@ -37,6 +37,48 @@ impl StoreOpaque {
         })
     }
 
+    /// Get statistics about this store's GC heap.
+    pub(crate) fn gc_stats(&self) -> crate::GcStats {
+        let Some(gc_store) = self.gc_store.as_ref() else {
+            return crate::GcStats::default();
+        };
+        let mut stats = crate::GcStats::default();
+        stats.heap_size = gc_store.gc_heap.vmmemory().current_length();
+        stats.used_bytes = gc_store.gc_heap.used_bytes();
+        stats.live_bytes = gc_store.live_bytes;
+        stats.bytes_allocated = gc_store.gc_heap.bytes_allocated();
+        stats.collections = gc_store.collections;
+        stats.total_pause_time = gc_store.pause_times.total;
+        stats.max_pause_time = gc_store.pause_times.max;
+        stats
+    }
+
+    /// Take a snapshot of the objects reachable from this store's GC roots.
+    pub(crate) async fn gc_heap_snapshot(&mut self) -> crate::GcHeapSnapshot {
+        if self.gc_store.is_none() {
+            return crate::GcHeapSnapshot::default();
+        }
+
+        self.trim_gc_liveness_flags(true);
+
+        // Take the GC roots out of `self` so we can trace roots while
+        // borrowing `self` mutably, same as when collecting.
+        let mut roots = core::mem::take(&mut self.gc_roots_list);
+        self.trace_roots(&mut roots).await;
+
+        let root_refs = unsafe { roots.iter() }
+            .map(|root| (root.kind(), root.get()))
+            .filter(|(_, gc_ref)| !gc_ref.is_i31())
+            .collect::<Vec<_>>();
+        let snapshot =
+            crate::GcHeapSnapshot::build(self.engine(), self.unwrap_gc_store(), root_refs);
+
+        roots.clear();
+        self.gc_roots_list = roots;
+
+        snapshot
+    }
+
     // This lives on the Store because it must simultaneously borrow
     // `gc_store` and `gc_roots`, and is invoked from other modules to
     // which we do not want to expose the raw fields for piecewise
@@ -126,9 +126,15 @@ pub(crate) fn from_runtime_box(
     let _ = &coredumpstack;
     #[cfg(feature = "coredump")]
     if let Some(coredump) = coredumpstack {
-        let bt = WasmBacktrace::from_captured(store, coredump.bt, pc);
-        let cd = WasmCoreDump::new(store, bt);
-        error = error.context(cd);
+        let mut frames = coredump.frames.into_iter().map(Some).collect::<Vec<_>>();
+        let mut kept = Vec::new();
+        let bt = WasmBacktrace::from_captured_with(store, coredump.bt, pc, |i| {
+            kept.push(frames[i].take().unwrap());
+        });
+        match WasmCoreDump::new(store, bt, kept) {
+            Ok(cd) => error = error.context(cd),
+            Err(e) => log::warn!("failed to capture a core dump: {e:?}"),
+        }
     }
 
     error
@@ -240,9 +246,13 @@ impl WasmBacktrace {
     /// # }
     /// ```
     pub fn capture(store: impl AsContext) -> WasmBacktrace {
-        let store = store.as_context();
+        Self::capture_opaque(store.as_context().0)
+    }
+
+    /// Same as [`WasmBacktrace::capture`], but for a `StoreOpaque`.
+    pub(crate) fn capture_opaque(store: &StoreOpaque) -> WasmBacktrace {
         if store.engine().config().wasm_backtrace {
-            Self::force_capture(store)
+            Self::from_captured(store, crate::runtime::vm::Backtrace::new(store), None)
         } else {
             WasmBacktrace {
                 wasm_trace: Vec::new(),
@@ -267,13 +277,24 @@ impl WasmBacktrace {
         store: &StoreOpaque,
         runtime_trace: crate::runtime::vm::Backtrace,
         trap_pc: Option<usize>,
+    ) -> Self {
+        Self::from_captured_with(store, runtime_trace, trap_pc, |_| {})
+    }
+
+    /// Same as `from_captured`, but additionally invokes `kept` with the index
+    /// within `runtime_trace` of each frame that's kept in the returned trace.
+    fn from_captured_with(
+        store: &StoreOpaque,
+        runtime_trace: crate::runtime::vm::Backtrace,
+        trap_pc: Option<usize>,
+        mut kept: impl FnMut(usize),
     ) -> Self {
         let mut wasm_trace = Vec::<FrameInfo>::with_capacity(runtime_trace.frames().len());
         let mut hint_wasm_backtrace_details_env = false;
         let wasm_backtrace_details_env_used =
             store.engine().config().wasm_backtrace_details_env_used;
 
-        for frame in runtime_trace.frames() {
+        for (i, frame) in runtime_trace.frames().enumerate() {
             debug_assert!(frame.pc() != 0);
 
             // Note that we need to be careful about the pc we pass in
@@ -318,6 +339,7 @@ impl WasmBacktrace {
             // this store's module registry.
             if let Some((info, module)) = store.modules().lookup_frame_info(pc_to_lookup) {
                 wasm_trace.push(info);
+                kept(i);
 
                 // If this frame has unparsed debug information and the
                 // store's configuration indicates that we were
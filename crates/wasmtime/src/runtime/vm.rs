@@ -218,6 +218,11 @@ pub unsafe trait VMStore: 'static {
     #[cfg(target_has_atomic = "64")]
     fn new_epoch_updated_deadline(&mut self) -> Result<crate::UpdateDeadline>;
 
+    /// Callback invoked whenever the guest debugger pauses execution at
+    /// `frame`. Returns how execution should resume.
+    #[cfg(feature = "guest-debug")]
+    fn debug_pause(&mut self, frame: &crate::DebugFrame) -> Result<crate::DebugAction>;
+
     /// Metadata required for resources for the component model.
     #[cfg(feature = "component-model")]
     fn component_calls(&mut self) -> &mut component::CallContexts;
@@ -426,6 +431,15 @@ impl ModuleRuntimeInfo {
         }
     }
 
+    /// Returns the table of each defined function's code coverage counters,
+    /// if the module was compiled with coverage enabled.
+    fn coverage_counters(&self) -> Option<NonNull<VmPtr<u64>>> {
+        match self {
+            ModuleRuntimeInfo::Module(m) => m.coverage_counters().map(|c| c.table()),
+            ModuleRuntimeInfo::Bare(_) => None,
+        }
+    }
+
     /// Offset information for the current host.
     pub(crate) fn offsets(&self) -> &VMOffsets<HostPtr> {
         match self {
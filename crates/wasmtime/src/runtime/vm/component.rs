@@ -35,7 +35,7 @@ mod handle_table;
 mod libcalls;
 mod resources;
 
-pub use self::handle_table::{HandleTable, RemovedResource};
+pub use self::handle_table::{HandleCounts, HandleTable, RemovedResource};
 #[cfg(feature = "component-model-async")]
 pub use self::handle_table::{TransmitLocalState, Waitable};
 #[cfg(feature = "component-model-async")]
@@ -136,6 +136,7 @@ pub struct ComponentInstance {
 ///   type of the function being called.
 /// * `options` - the `OptionsIndex` which indicates the canonical ABI options
 ///   in use for this call.
+/// * `lowering` - the `LoweredIndex` of the lowered import being called.
 /// * `args_and_results` - pointer to stack-allocated space in the caller where
 ///   all the arguments are stored as well as where the results will be written
 ///   to. The size and initialized bytes of this depends on the core wasm type
@@ -151,6 +152,7 @@ pub type VMLoweringCallee = extern "C" fn(
     data: NonNull<u8>,
     ty: u32,
     options: u32,
+    lowering: u32,
     args_and_results: NonNull<mem::MaybeUninit<ValRaw>>,
     nargs_and_results: usize,
 ) -> bool;
@@ -757,6 +759,19 @@ impl ComponentInstance {
         }
     }
 
+    /// Returns the handle tables of each component instance within this
+    /// component.
+    pub fn handle_tables(&self) -> &PrimaryMap<RuntimeComponentInstanceIndex, HandleTable> {
+        &self.instance_handle_tables
+    }
+
+    /// Returns the ids of all core instances created so far by this component.
+    pub fn instance_ids(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (RuntimeInstanceIndex, InstanceId)> + '_ {
+        self.instances.iter().map(|(index, id)| (index, *id))
+    }
+
     /// Returns the destructor and instance flags for the specified resource
     /// table type.
     ///
@@ -110,6 +110,18 @@ pub struct HandleTable {
     slots: Vec<Slot>,
 }
 
+/// Number of live handles of each kind in a [`HandleTable`], returned by
+/// [`HandleTable::counts`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HandleCounts {
+    /// Number of `own` resource handles.
+    pub own: usize,
+    /// Number of `borrow` resource handles.
+    pub borrow: usize,
+    /// Number of other handles such as tasks, streams, and futures.
+    pub other: usize,
+}
+
 impl Default for HandleTable {
     fn default() -> Self {
         Self {
@@ -127,6 +139,20 @@ impl HandleTable {
             .all(|slot| matches!(slot, Slot::Free { .. }))
     }
 
+    /// Returns the number of live handles of each kind in this table.
+    pub fn counts(&self) -> HandleCounts {
+        let mut counts = HandleCounts::default();
+        for slot in self.slots.iter() {
+            match slot {
+                Slot::Free { .. } => {}
+                Slot::ResourceOwn { .. } => counts.own += 1,
+                Slot::ResourceBorrow { .. } => counts.borrow += 1,
+                _ => counts.other += 1,
+            }
+        }
+        counts
+    }
+
     fn insert(&mut self, slot: Slot) -> Result<u32> {
         let next = self.next as usize;
         if next == self.slots.len() {
@@ -47,6 +47,16 @@ pub struct GcStore {
 
     /// The function-references table for this GC heap.
     pub func_ref_table: FuncRefTable,
+
+    /// The number of collections performed in this GC heap.
+    pub collections: u64,
+
+    /// The times that collections in this GC heap have paused for.
+    pub pause_times: GcPauseTimes,
+
+    /// The number of bytes occupied by objects at the end of the most recent
+    /// collection.
+    pub live_bytes: usize,
 }
 
 impl GcStore {
@@ -59,6 +69,9 @@ impl GcStore {
             gc_heap,
             host_data_table,
             func_ref_table,
+            collections: 0,
+            pause_times: GcPauseTimes::default(),
+            live_bytes: 0,
         }
     }
 
@@ -70,7 +83,12 @@ impl GcStore {
     /// Asynchronously perform garbage collection within this heap.
     pub async fn gc(&mut self, async_yield: bool, roots: GcRootsIter<'_>) {
         let collection = self.gc_heap.gc(roots, &mut self.host_data_table);
-        collect_async(collection, async_yield).await;
+        let times = collect_async(collection, async_yield).await;
+
+        self.collections += 1;
+        self.pause_times.total += times.total;
+        self.pause_times.max = self.pause_times.max.max(times.max);
+        self.live_bytes = self.gc_heap.used_bytes();
     }
 
     /// Get the kind of the given GC reference.
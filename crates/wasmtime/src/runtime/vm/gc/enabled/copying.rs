@@ -0,0 +1,857 @@
+//! The semi-space copying collector.
+//!
+//! The copying collector bump allocates objects into an active region of the
+//! GC heap. When that region fills up, it performs a Cheney-style collection:
+//! every object reachable from the GC roots is copied into a fresh region of
+//! the heap, leaving a forwarding pointer behind in its old header, and then
+//! the old region is discarded wholesale. Because it traces the heap rather
+//! than counting references, it reclaims garbage cycles as well.
+//!
+//! Rather than statically splitting the heap into two halves, the active
+//! region can start anywhere in the heap. The allocation limit is chosen such
+//! that all live objects can always be copied either into the space below the
+//! active region or into the space above it, which lets the heap grow in place
+//! without having to relocate the active region.
+//!
+//! This is a moving collector: GC roots are updated to point at the new copies
+//! of their referents, and compiled Wasm code must keep every GC reference
+//! that is live across a safepoint in a stack map. It does not require any
+//! other GC barriers.
+
+use super::*;
+use crate::hash_map::HashMap;
+use crate::{
+    Engine, EngineWeak,
+    prelude::*,
+    vm::{
+        ExternRefHostDataId, ExternRefHostDataTable, GarbageCollection, GcHeap, GcHeapObject,
+        GcProgress, GcRootsIter, GcRuntime, TypedGcRef, VMGcHeader, VMGcRef, VMMemoryDefinition,
+    },
+};
+use core::ptr::NonNull;
+use core::{alloc::Layout, any::Any, mem, num::NonZeroU32};
+use wasmtime_environ::copying::{ARRAY_LENGTH_OFFSET, CopyingTypeLayouts};
+use wasmtime_environ::{
+    GcArrayLayout, GcLayout, GcStructLayout, GcTypeLayouts, VMGcKind, VMSharedTypeIndex,
+};
+
+#[expect(clippy::cast_possible_truncation, reason = "known to not overflow")]
+const GC_REF_ARRAY_ELEMS_OFFSET: u32 = ARRAY_LENGTH_OFFSET + (mem::size_of::<u32>() as u32);
+
+/// The alignment of every object in the copying collector's heap.
+///
+/// Object sizes are rounded up to a multiple of this alignment, so that
+/// objects can be laid out back-to-back, and walked linearly, in any region of
+/// the heap.
+const OBJECT_ALIGN: u32 = 16;
+
+/// The maximum number of GC roots forwarded in a single collection increment.
+const ROOTS_PER_INCREMENT: usize = 1024;
+
+/// The approximate number of bytes of objects scanned, or swept, in a single
+/// collection increment.
+///
+/// Objects are never split across increments, so a single large array may
+/// exceed this.
+const BYTES_PER_INCREMENT: u32 = 64 * 1024;
+
+/// The semi-space copying collector.
+///
+/// This collector traces the heap, so it can reclaim garbage cycles, and it
+/// compacts the live objects on every collection.
+#[derive(Default)]
+pub struct CopyingCollector {
+    layouts: CopyingTypeLayouts,
+}
+
+unsafe impl GcRuntime for CopyingCollector {
+    fn layouts(&self) -> &dyn GcTypeLayouts {
+        &self.layouts
+    }
+
+    fn new_gc_heap(&self, engine: &Engine) -> Result<Box<dyn GcHeap>> {
+        let heap = CopyingHeap::new(engine)?;
+        Ok(Box::new(heap) as _)
+    }
+}
+
+/// How to trace a GC object.
+enum TraceInfo {
+    /// How to trace an array.
+    Array {
+        /// Whether this array type's elements are GC references, and need
+        /// tracing.
+        gc_ref_elems: bool,
+    },
+
+    /// How to trace a struct.
+    Struct {
+        /// The offsets of each GC reference field that needs tracing in
+        /// instances of this struct type.
+        gc_ref_offsets: Box<[u32]>,
+    },
+}
+
+/// A GC heap for the copying collector.
+struct CopyingHeap {
+    engine: EngineWeak,
+
+    /// For every type that we have allocated in this heap, how do we trace it?
+    trace_infos: HashMap<VMSharedTypeIndex, TraceInfo>,
+
+    /// The number of active no-gc scopes at the current moment.
+    no_gc_count: usize,
+
+    /// The start of the active region, where objects are allocated.
+    start: u32,
+
+    /// Bump-allocation finger indexing within `self.start..self.end`.
+    next: u32,
+
+    /// The allocation limit for the active region.
+    ///
+    /// This is chosen such that `self.next - self.start` bytes of live objects
+    /// always fit either within `OBJECT_ALIGN..self.start` or within
+    /// `self.next..heap_len`. See `CopyingHeap::allocation_limit`.
+    end: u32,
+
+    /// The total number of bytes allocated in this heap.
+    bytes_allocated: u64,
+
+    /// The actual storage for the GC heap.
+    memory: Option<crate::vm::Memory>,
+}
+
+/// The common header for all objects in the copying collector.
+#[repr(C)]
+struct VMCopyingHeader {
+    header: VMGcHeader,
+
+    /// The size of this object, rounded up to `OBJECT_ALIGN`.
+    object_size: u32,
+
+    /// The heap index of this object's new copy, if it has been copied during
+    /// the current collection, or zero otherwise.
+    forwarding: u32,
+}
+
+unsafe impl GcHeapObject for VMCopyingHeader {
+    #[inline]
+    fn is(_header: &VMGcHeader) -> bool {
+        // All objects in the copying collector have a copying header.
+        true
+    }
+}
+
+impl VMCopyingHeader {
+    /// The size of this header's object.
+    #[inline]
+    fn object_size(&self) -> usize {
+        usize::try_from(self.object_size).unwrap()
+    }
+
+    /// The forwarding reference to this object's new copy, if any.
+    #[inline]
+    fn forwarding(&self) -> Option<VMGcRef> {
+        VMGcRef::from_raw_u32(self.forwarding)
+    }
+}
+
+/// The common header for all arrays in the copying collector.
+#[repr(C)]
+struct VMCopyingArrayHeader {
+    header: VMCopyingHeader,
+    length: u32,
+}
+
+unsafe impl GcHeapObject for VMCopyingArrayHeader {
+    #[inline]
+    fn is(header: &VMGcHeader) -> bool {
+        header.kind() == VMGcKind::ArrayRef
+    }
+}
+
+/// The representation of an `externref` in the copying collector.
+#[repr(C)]
+struct VMCopyingExternRef {
+    header: VMCopyingHeader,
+    host_data: ExternRefHostDataId,
+}
+
+unsafe impl GcHeapObject for VMCopyingExternRef {
+    #[inline]
+    fn is(header: &VMGcHeader) -> bool {
+        header.kind() == VMGcKind::ExternRef
+    }
+}
+
+fn copying_ref(gc_ref: &VMGcRef) -> &TypedGcRef<VMCopyingHeader> {
+    debug_assert!(!gc_ref.is_i31());
+    gc_ref.as_typed_unchecked()
+}
+
+impl CopyingHeap {
+    /// Construct a new, default heap for the copying collector.
+    fn new(engine: &Engine) -> Result<Self> {
+        log::trace!("allocating new copying heap");
+        Ok(Self {
+            engine: engine.weak(),
+            trace_infos: HashMap::with_capacity(1),
+            no_gc_count: 0,
+            start: OBJECT_ALIGN,
+            next: OBJECT_ALIGN,
+            end: OBJECT_ALIGN,
+            bytes_allocated: 0,
+            memory: None,
+        })
+    }
+
+    fn engine(&self) -> Engine {
+        self.engine.upgrade().unwrap()
+    }
+
+    /// The length of the GC heap, saturated to the range of heap indices.
+    fn heap_len(&self) -> u32 {
+        let len = self.memory.as_ref().unwrap().byte_size();
+        u32::try_from(len).unwrap_or(u32::MAX)
+    }
+
+    /// Compute the allocation limit for an active region starting at `start`
+    /// in a heap of `heap_len` bytes.
+    ///
+    /// Objects allocated below this limit can always be copied either into the
+    /// space below `start` or into the space above the allocated objects.
+    fn allocation_limit(start: u32, heap_len: u32) -> u32 {
+        let start = u64::from(start);
+        let heap_len = u64::from(heap_len);
+        let min_start = u64::from(OBJECT_ALIGN);
+
+        // Copy live objects below the active region: `next - start <= start -
+        // OBJECT_ALIGN`.
+        let below = (2 * start).saturating_sub(min_start).min(heap_len);
+
+        // Copy live objects above the active region: `next - start <= heap_len
+        // - next`.
+        let above = (heap_len + start) / 2;
+
+        let limit = below.max(above).max(start);
+        let limit = limit / u64::from(OBJECT_ALIGN) * u64::from(OBJECT_ALIGN);
+        u32::try_from(limit).unwrap()
+    }
+
+    /// Reset the active region to begin at `start` with `next` as its
+    /// bump-allocation finger, and recompute its allocation limit.
+    fn set_active_region(&mut self, start: u32, next: u32) {
+        debug_assert_eq!(start % OBJECT_ALIGN, 0);
+        debug_assert_eq!(next % OBJECT_ALIGN, 0);
+        debug_assert!(start <= next);
+        self.start = start;
+        self.next = next;
+        self.end = Self::allocation_limit(start, self.heap_len()).max(next);
+        log::trace!(
+            "copying heap active region: start = {start:#x}, next = {next:#x}, end = {:#x}",
+            self.end
+        );
+    }
+
+    /// Ensure that we have tracing information for the given type.
+    fn ensure_trace_info(&mut self, ty: VMSharedTypeIndex) {
+        if self.trace_infos.contains_key(&ty) {
+            return;
+        }
+
+        self.insert_new_trace_info(ty);
+    }
+
+    fn insert_new_trace_info(&mut self, ty: VMSharedTypeIndex) {
+        debug_assert!(!self.trace_infos.contains_key(&ty));
+
+        let engine = self.engine();
+        let gc_layout = engine
+            .signatures()
+            .layout(ty)
+            .unwrap_or_else(|| panic!("should have a GC layout for {ty:?}"));
+
+        let info = match gc_layout {
+            GcLayout::Array(l) => {
+                if l.elems_are_gc_refs {
+                    debug_assert_eq!(l.elem_offset(0), GC_REF_ARRAY_ELEMS_OFFSET);
+                }
+                TraceInfo::Array {
+                    gc_ref_elems: l.elems_are_gc_refs,
+                }
+            }
+            GcLayout::Struct(l) => TraceInfo::Struct {
+                gc_ref_offsets: l
+                    .fields
+                    .iter()
+                    .filter_map(|f| if f.is_gc_ref { Some(f.offset) } else { None })
+                    .collect(),
+            },
+        };
+
+        let old_entry = self.trace_infos.insert(ty, info);
+        debug_assert!(old_entry.is_none());
+    }
+
+    /// Attempt to bump-allocate an object with the given layout and header.
+    ///
+    /// Returns `Ok(Ok(r))` on success, `Ok(Err(bytes_needed))` when we don't
+    /// have enough heap space but growing the GC heap could make it
+    /// allocatable, and `Err(_)` when we don't have enough space and growing
+    /// the GC heap won't help.
+    fn alloc(&mut self, header: VMGcHeader, layout: Layout) -> Result<Result<VMGcRef, u64>> {
+        debug_assert!(layout.size() >= core::mem::size_of::<VMCopyingHeader>());
+        debug_assert!(layout.align() >= core::mem::align_of::<VMCopyingHeader>());
+        debug_assert!(layout.align() <= usize::try_from(OBJECT_ALIGN).unwrap());
+        debug_assert_eq!(header.reserved_u26(), 0);
+
+        // We must have trace info for every GC type that we allocate in this
+        // heap. The only kinds of GC objects we allocate that do not have an
+        // associated `VMSharedTypeIndex` are `externref`s, and they don't have
+        // any GC edges.
+        if let Some(ty) = header.ty() {
+            self.ensure_trace_info(ty);
+        } else {
+            debug_assert_eq!(header.kind(), VMGcKind::ExternRef);
+        }
+
+        let object_size = match u32::try_from(layout.size())
+            .ok()
+            .and_then(|size| size.checked_next_multiple_of(OBJECT_ALIGN))
+        {
+            Some(size) => size,
+            None => return Err(crate::Trap::AllocationTooLarge.into()),
+        };
+
+        let end_of_object = match self.next.checked_add(object_size) {
+            Some(end) if end <= self.end => end,
+            Some(_) | None => {
+                // Only half of any heap growth is made available to the active
+                // region, since the other half is reserved for copying into
+                // during collection, so ask for double the object's size.
+                return Ok(Err(u64::from(object_size) * 2));
+            }
+        };
+
+        let index = NonZeroU32::new(self.next).unwrap();
+        self.next = end_of_object;
+        self.bytes_allocated += u64::from(object_size);
+
+        let gc_ref = VMGcRef::from_heap_index(index).unwrap();
+        *self.index_mut(copying_ref(&gc_ref)) = VMCopyingHeader {
+            header,
+            object_size,
+            forwarding: 0,
+        };
+        Ok(Ok(gc_ref))
+    }
+
+    /// Deallocate the given object, if it is the most-recently allocated
+    /// object in the active region.
+    ///
+    /// Otherwise, its space is reclaimed at the next collection.
+    fn dealloc(&mut self, gc_ref: VMGcRef) {
+        let index = gc_ref.as_heap_index().unwrap().get();
+        let size = self.index(copying_ref(&gc_ref)).object_size;
+        if index + size == self.next {
+            self.next = index;
+        }
+    }
+
+    /// Copy the given object into to-space at `*free`, if it has not already
+    /// been copied, and return the reference to its new copy.
+    fn forward(&mut self, gc_ref: &VMGcRef, free: &mut u32) -> VMGcRef {
+        if gc_ref.is_i31() {
+            return gc_ref.unchecked_copy();
+        }
+
+        let header = self.index(copying_ref(gc_ref));
+        if let Some(forwarded) = header.forwarding() {
+            return forwarded;
+        }
+
+        let size = header.object_size;
+        let from = usize::try_from(gc_ref.as_heap_index().unwrap().get()).unwrap();
+        let to = usize::try_from(*free).unwrap();
+        let len = usize::try_from(size).unwrap();
+        self.heap_slice_mut().copy_within(from..from + len, to);
+
+        let new_ref = VMGcRef::from_heap_index(NonZeroU32::new(*free).unwrap()).unwrap();
+        *free += size;
+        log::trace!("copied {gc_ref:#p} to {new_ref:#p}");
+
+        self.index_mut(copying_ref(gc_ref)).forwarding = new_ref.as_raw_u32();
+        new_ref
+    }
+
+    /// Forward the GC reference stored at `offset` within the given object, if
+    /// any.
+    fn forward_field(&mut self, gc_ref: &VMGcRef, offset: u32, free: &mut u32) {
+        let raw = self.gc_object_data(gc_ref).read_u32(offset);
+        if let Some(field) = VMGcRef::from_raw_u32(raw) {
+            let new_field = self.forward(&field, free);
+            self.gc_object_data_mut(gc_ref)
+                .write_u32(offset, new_field.as_raw_u32());
+        }
+    }
+
+    /// Forward all of the given to-space object's outgoing edges.
+    fn scan(
+        &mut self,
+        trace_infos: &HashMap<VMSharedTypeIndex, TraceInfo>,
+        gc_ref: &VMGcRef,
+        free: &mut u32,
+    ) {
+        let Some(ty) = self.header(gc_ref).ty() else {
+            debug_assert!(self.header(gc_ref).kind().matches(VMGcKind::ExternRef));
+            return;
+        };
+        match trace_infos
+            .get(&ty)
+            .expect("should have inserted trace info for every GC type allocated in this heap")
+        {
+            TraceInfo::Struct { gc_ref_offsets } => {
+                for offset in gc_ref_offsets {
+                    self.forward_field(gc_ref, *offset, free);
+                }
+            }
+            TraceInfo::Array { gc_ref_elems } => {
+                if !*gc_ref_elems {
+                    return;
+                }
+
+                let len = self.array_len(gc_ref.as_arrayref_unchecked());
+                for i in 0..len {
+                    let elem_offset = GC_REF_ARRAY_ELEMS_OFFSET
+                        + i * u32::try_from(mem::size_of::<u32>()).unwrap();
+                    self.forward_field(gc_ref, elem_offset, free);
+                }
+            }
+        }
+    }
+
+    /// Choose where to copy the live objects of the active region to.
+    ///
+    /// Returns the start of the to-space region.
+    fn to_space_start(&self) -> u32 {
+        let live_upper_bound = self.next - self.start;
+        let to_start = if self.start - OBJECT_ALIGN >= live_upper_bound {
+            OBJECT_ALIGN
+        } else {
+            debug_assert!(
+                u64::from(self.next) + u64::from(live_upper_bound) <= u64::from(self.heap_len())
+            );
+            self.next
+        };
+        log::trace!(
+            "copying live objects from {:#x}..{:#x} to {to_start:#x}",
+            self.start,
+            self.next
+        );
+        to_start
+    }
+
+    /// Copy the referents of up to `ROOTS_PER_INCREMENT` of the given roots
+    /// into to-space, and update the roots to point at the copies.
+    ///
+    /// Returns `true` once all roots have been processed.
+    fn forward_roots(&mut self, roots: &mut GcRootsIter<'_>, free: &mut u32) -> bool {
+        for _ in 0..ROOTS_PER_INCREMENT {
+            let Some(mut root) = roots.next() else {
+                return true;
+            };
+            let gc_ref = root.get();
+            if gc_ref.is_i31() {
+                continue;
+            }
+            let new_ref = self.forward(&gc_ref, free);
+            root.set(new_ref);
+        }
+        false
+    }
+
+    /// Scan to-space objects starting at `*scan`, forwarding their outgoing
+    /// edges, until we've scanned `BYTES_PER_INCREMENT` bytes or caught up
+    /// with `*free`.
+    ///
+    /// Returns `true` once every copied object has been scanned.
+    fn scan_objects(&mut self, scan: &mut u32, free: &mut u32) -> bool {
+        // Take the trace infos out of `self` while scanning, so that we can
+        // borrow them while mutating the heap. No new types are allocated
+        // during collection.
+        let trace_infos = mem::take(&mut self.trace_infos);
+        let limit = scan.saturating_add(BYTES_PER_INCREMENT);
+        while *scan < *free && *scan < limit {
+            let gc_ref = VMGcRef::from_heap_index(NonZeroU32::new(*scan).unwrap()).unwrap();
+            self.scan(&trace_infos, &gc_ref, free);
+            *scan += self.index(copying_ref(&gc_ref)).object_size;
+        }
+        self.trace_infos = trace_infos;
+        debug_assert!(*scan <= *free);
+        *scan == *free
+    }
+
+    /// Walk from-space objects starting at `*index`, freeing the host data of
+    /// every `externref` that was not copied, until we've walked
+    /// `BYTES_PER_INCREMENT` bytes or reached the end of from-space.
+    ///
+    /// Returns `true` once all of from-space has been walked.
+    fn sweep_externrefs(
+        &mut self,
+        index: &mut u32,
+        host_data_table: &mut ExternRefHostDataTable,
+    ) -> bool {
+        let limit = index.saturating_add(BYTES_PER_INCREMENT);
+        while *index < self.next && *index < limit {
+            let gc_ref = VMGcRef::from_heap_index(NonZeroU32::new(*index).unwrap()).unwrap();
+            let header = self.index(copying_ref(&gc_ref));
+            let size = header.object_size;
+            if header.forwarding().is_none() {
+                if let Some(externref) = gc_ref.as_typed::<VMCopyingExternRef>(self) {
+                    let host_data_id = self.index(externref).host_data;
+                    log::trace!("freeing host data {host_data_id:?} of dead {gc_ref:#p}");
+                    host_data_table.dealloc(host_data_id);
+                }
+            }
+            *index += size;
+        }
+        debug_assert!(*index <= self.next);
+        *index == self.next
+    }
+}
+
+unsafe impl GcHeap for CopyingHeap {
+    fn is_attached(&self) -> bool {
+        self.memory.is_some()
+    }
+
+    fn attach(&mut self, memory: crate::vm::Memory) {
+        assert!(!self.is_attached());
+        assert!(!memory.is_shared_memory());
+        self.memory = Some(memory);
+        self.set_active_region(OBJECT_ALIGN, OBJECT_ALIGN);
+    }
+
+    fn detach(&mut self) -> crate::vm::Memory {
+        assert!(self.is_attached());
+
+        let CopyingHeap {
+            engine: _,
+            no_gc_count,
+            start,
+            next,
+            end,
+            bytes_allocated,
+            memory,
+
+            // NB: we will only ever be reused with the same engine, so no need
+            // to clear out our tracing info just to fill it back in with the
+            // same exact stuff.
+            trace_infos: _,
+        } = self;
+
+        *no_gc_count = 0;
+        *start = OBJECT_ALIGN;
+        *next = OBJECT_ALIGN;
+        *end = OBJECT_ALIGN;
+        *bytes_allocated = 0;
+
+        memory.take().unwrap()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self as _
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as _
+    }
+
+    fn enter_no_gc_scope(&mut self) {
+        self.no_gc_count += 1;
+    }
+
+    fn exit_no_gc_scope(&mut self) {
+        self.no_gc_count -= 1;
+    }
+
+    fn take_memory(&mut self) -> crate::vm::Memory {
+        debug_assert!(self.is_attached());
+        self.memory.take().unwrap()
+    }
+
+    unsafe fn replace_memory(&mut self, memory: crate::vm::Memory, _delta_bytes_grown: u64) {
+        debug_assert!(self.memory.is_none());
+        debug_assert!(!memory.is_shared_memory());
+        self.memory = Some(memory);
+        self.set_active_region(self.start, self.next);
+    }
+
+    fn vmmemory(&self) -> VMMemoryDefinition {
+        debug_assert!(self.is_attached());
+        self.memory.as_ref().unwrap().vmmemory()
+    }
+
+    fn clone_gc_ref(&mut self, gc_ref: &VMGcRef) -> VMGcRef {
+        gc_ref.unchecked_copy()
+    }
+
+    fn write_gc_ref(
+        &mut self,
+        _host_data_table: &mut ExternRefHostDataTable,
+        destination: &mut Option<VMGcRef>,
+        source: Option<&VMGcRef>,
+    ) {
+        *destination = source.map(|s| s.unchecked_copy());
+    }
+
+    fn expose_gc_ref_to_wasm(&mut self, _gc_ref: VMGcRef) {
+        // Don't need to do anything special here: references exposed to Wasm
+        // are found, and updated, through stack maps.
+    }
+
+    fn alloc_externref(
+        &mut self,
+        host_data: ExternRefHostDataId,
+    ) -> Result<Result<VMExternRef, u64>> {
+        let gc_ref =
+            match self.alloc(VMGcHeader::externref(), Layout::new::<VMCopyingExternRef>())? {
+                Ok(r) => r,
+                Err(bytes_needed) => return Ok(Err(bytes_needed)),
+            };
+        self.index_mut::<VMCopyingExternRef>(gc_ref.as_typed_unchecked())
+            .host_data = host_data;
+        Ok(Ok(gc_ref.into_externref_unchecked()))
+    }
+
+    fn externref_host_data(&self, externref: &VMExternRef) -> ExternRefHostDataId {
+        let gc_ref = externref.as_gc_ref();
+        debug_assert!(gc_ref.is_typed::<VMCopyingExternRef>(self));
+        self.index::<VMCopyingExternRef>(gc_ref.as_typed_unchecked())
+            .host_data
+    }
+
+    fn object_size(&self, gc_ref: &VMGcRef) -> usize {
+        self.index(copying_ref(gc_ref)).object_size()
+    }
+
+    fn header(&self, gc_ref: &VMGcRef) -> &VMGcHeader {
+        self.index(gc_ref.as_typed_unchecked())
+    }
+
+    fn header_mut(&mut self, gc_ref: &VMGcRef) -> &mut VMGcHeader {
+        self.index_mut(gc_ref.as_typed_unchecked())
+    }
+
+    fn alloc_raw(&mut self, header: VMGcHeader, layout: Layout) -> Result<Result<VMGcRef, u64>> {
+        self.alloc(header, layout)
+    }
+
+    fn alloc_uninit_struct_or_exn(
+        &mut self,
+        ty: VMSharedTypeIndex,
+        layout: &GcStructLayout,
+    ) -> Result<Result<VMGcRef, u64>> {
+        let kind = if layout.is_exception {
+            VMGcKind::ExnRef
+        } else {
+            VMGcKind::StructRef
+        };
+        self.alloc(VMGcHeader::from_kind_and_index(kind, ty), layout.layout())
+    }
+
+    fn dealloc_uninit_struct_or_exn(&mut self, gc_ref: VMGcRef) {
+        self.dealloc(gc_ref);
+    }
+
+    fn alloc_uninit_array(
+        &mut self,
+        ty: VMSharedTypeIndex,
+        length: u32,
+        layout: &GcArrayLayout,
+    ) -> Result<Result<VMArrayRef, u64>> {
+        self.alloc(
+            VMGcHeader::from_kind_and_index(VMGcKind::ArrayRef, ty),
+            layout.layout(length),
+        )
+        .map(|r| {
+            r.map(|r| {
+                self.index_mut::<VMCopyingArrayHeader>(r.as_typed_unchecked())
+                    .length = length;
+                r.into_arrayref_unchecked()
+            })
+        })
+    }
+
+    fn dealloc_uninit_array(&mut self, arrayref: VMArrayRef) {
+        self.dealloc(arrayref.into());
+    }
+
+    fn array_len(&self, arrayref: &VMArrayRef) -> u32 {
+        debug_assert!(arrayref.as_gc_ref().is_typed::<VMCopyingArrayHeader>(self));
+        self.index::<VMCopyingArrayHeader>(arrayref.as_gc_ref().as_typed_unchecked())
+            .length
+    }
+
+    fn used_bytes(&self) -> usize {
+        usize::try_from(self.next - self.start).unwrap()
+    }
+
+    fn bytes_allocated(&self) -> u64 {
+        self.bytes_allocated
+    }
+
+    fn gc<'a>(
+        &'a mut self,
+        roots: GcRootsIter<'a>,
+        host_data_table: &'a mut ExternRefHostDataTable,
+    ) -> Box<dyn GarbageCollection<'a> + 'a> {
+        assert_eq!(self.no_gc_count, 0, "Cannot GC inside a no-GC scope!");
+        Box::new(CopyingCollection {
+            roots,
+            host_data_table,
+            heap: self,
+            phase: CopyingCollectionPhase::Start,
+        })
+    }
+
+    unsafe fn vmctx_gc_heap_data(&self) -> NonNull<u8> {
+        // Compiled code always allocates through the `gc_alloc_raw` libcall and
+        // never accesses any collector-specific data, so just hand out a
+        // pointer to the bump finger, which compiled code never touches.
+        NonNull::from(&self.next).cast()
+    }
+}
+
+struct CopyingCollection<'a> {
+    roots: GcRootsIter<'a>,
+    host_data_table: &'a mut ExternRefHostDataTable,
+    heap: &'a mut CopyingHeap,
+    phase: CopyingCollectionPhase,
+}
+
+enum CopyingCollectionPhase {
+    /// Choose the to-space region.
+    Start,
+    /// Copy the referents of the GC roots.
+    Roots {
+        to_start: u32,
+        free: u32,
+    },
+    /// Cheney-scan the copied objects, copying everything they reference.
+    Scan {
+        to_start: u32,
+        scan: u32,
+        free: u32,
+    },
+    /// Free the host data of dead `externref`s and flip to the to-space.
+    Sweep {
+        to_start: u32,
+        to_next: u32,
+        index: u32,
+    },
+    Done,
+}
+
+impl<'a> GarbageCollection<'a> for CopyingCollection<'a> {
+    fn collect_increment(&mut self) -> GcProgress {
+        match &mut self.phase {
+            CopyingCollectionPhase::Start => {
+                log::trace!("Begin copying collection");
+                let to_start = self.heap.to_space_start();
+                self.phase = CopyingCollectionPhase::Roots {
+                    to_start,
+                    free: to_start,
+                };
+                GcProgress::Continue
+            }
+            CopyingCollectionPhase::Roots { to_start, free } => {
+                if self.heap.forward_roots(&mut self.roots, free) {
+                    log::trace!("Forwarded all GC roots");
+                    self.phase = CopyingCollectionPhase::Scan {
+                        to_start: *to_start,
+                        scan: *to_start,
+                        free: *free,
+                    };
+                }
+                GcProgress::Continue
+            }
+            CopyingCollectionPhase::Scan {
+                to_start,
+                scan,
+                free,
+            } => {
+                if self.heap.scan_objects(scan, free) {
+                    log::trace!("Scanned all live objects");
+                    self.phase = CopyingCollectionPhase::Sweep {
+                        to_start: *to_start,
+                        to_next: *free,
+                        index: self.heap.start,
+                    };
+                }
+                GcProgress::Continue
+            }
+            CopyingCollectionPhase::Sweep {
+                to_start,
+                to_next,
+                index,
+            } => {
+                if !self.heap.sweep_externrefs(index, self.host_data_table) {
+                    return GcProgress::Continue;
+                }
+                self.heap.set_active_region(*to_start, *to_next);
+                log::trace!("End copying collection");
+                self.phase = CopyingCollectionPhase::Done;
+                GcProgress::Complete
+            }
+            CopyingCollectionPhase::Done => GcProgress::Complete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime_environ::copying::{HEADER_ALIGN, HEADER_SIZE};
+
+    #[test]
+    fn vm_copying_header_size_align() {
+        assert_eq!(
+            (HEADER_SIZE as usize),
+            core::mem::size_of::<VMCopyingHeader>()
+        );
+        assert_eq!(
+            (HEADER_ALIGN as usize),
+            core::mem::align_of::<VMCopyingHeader>()
+        );
+    }
+
+    #[test]
+    fn vm_copying_array_header_length_offset() {
+        assert_eq!(
+            ARRAY_LENGTH_OFFSET,
+            u32::try_from(core::mem::offset_of!(VMCopyingArrayHeader, length)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn allocation_limit_leaves_room_to_copy() {
+        for heap_len in [0, 16, 64, 4096, 65536, 1 << 20] {
+            let mut start = OBJECT_ALIGN;
+            while start <= heap_len {
+                let end = CopyingHeap::allocation_limit(start, heap_len);
+                assert!(end >= start);
+                assert!(end <= heap_len.max(start));
+                assert_eq!(end % OBJECT_ALIGN, 0);
+                let live = end - start;
+                assert!(
+                    start - OBJECT_ALIGN >= live
+                        || u64::from(end) + u64::from(live) <= u64::from(heap_len),
+                    "start = {start}, heap_len = {heap_len}, end = {end}",
+                );
+                start += OBJECT_ALIGN;
+            }
+        }
+    }
+}
@@ -139,6 +139,12 @@ struct DrcHeap {
     /// A free list describing which ranges of the heap are available for use.
     free_list: Option<FreeList>,
 
+    /// The number of bytes currently occupied by objects in this heap.
+    used_bytes: usize,
+
+    /// The total number of bytes allocated in this heap.
+    bytes_allocated: u64,
+
     /// An explicit stack to avoid recursion when deallocating one object needs
     /// to dec-ref another object, which can then be deallocated and dec-refs
     /// yet another object, etc...
@@ -165,6 +171,8 @@ impl DrcHeap {
             memory: None,
             vmmemory: None,
             free_list: None,
+            used_bytes: 0,
+            bytes_allocated: 0,
             dec_ref_stack: Some(Vec::with_capacity(1)),
         })
     }
@@ -176,6 +184,7 @@ impl DrcHeap {
     fn dealloc(&mut self, gc_ref: VMGcRef) {
         let drc_ref = drc_ref(&gc_ref);
         let size = self.index(drc_ref).object_size();
+        self.used_bytes -= size;
         let layout = FreeList::layout(size);
         self.free_list
             .as_mut()
@@ -695,6 +704,8 @@ unsafe impl GcHeap for DrcHeap {
             no_gc_count,
             over_approximated_stack_roots,
             free_list,
+            used_bytes,
+            bytes_allocated,
             dec_ref_stack,
             memory,
             vmmemory,
@@ -708,6 +719,8 @@ unsafe impl GcHeap for DrcHeap {
         *no_gc_count = 0;
         **over_approximated_stack_roots = None;
         *free_list = None;
+        *used_bytes = 0;
+        *bytes_allocated = 0;
         *vmmemory = None;
         debug_assert!(dec_ref_stack.as_ref().is_some_and(|s| s.is_empty()));
 
@@ -827,6 +840,8 @@ unsafe impl GcHeap for DrcHeap {
             None => return Ok(Err(u64::try_from(layout.size()).unwrap())),
             Some(index) => VMGcRef::from_heap_index(index).unwrap(),
         };
+        self.used_bytes += layout.size();
+        self.bytes_allocated += u64::try_from(layout.size()).unwrap();
 
         *self.index_mut(drc_ref(&gc_ref)) = VMDrcHeader {
             header,
@@ -891,6 +906,14 @@ unsafe impl GcHeap for DrcHeap {
             .length
     }
 
+    fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn bytes_allocated(&self) -> u64 {
+        self.bytes_allocated
+    }
+
     fn gc<'a>(
         &'a mut self,
         roots: GcRootsIter<'a>,
@@ -327,6 +327,21 @@ unsafe impl GcHeap for NullHeap {
         self.index(arrayref).length
     }
 
+    fn used_bytes(&self) -> usize {
+        if !self.is_attached() {
+            return 0;
+        }
+        // NB: compiled Wasm code bumps `next` directly, but it cannot be
+        // running while we have a borrow of this heap.
+        let next = unsafe { *self.next.get() };
+        usize::try_from(next.get() - 1).unwrap()
+    }
+
+    fn bytes_allocated(&self) -> u64 {
+        // The null collector never frees anything.
+        u64::try_from(self.used_bytes()).unwrap()
+    }
+
     fn gc<'a>(
         &'a mut self,
         _roots: GcRootsIter<'a>,
@@ -8,6 +8,7 @@ use crate::runtime::vm::{
 use crate::vm::VMMemoryDefinition;
 use core::ptr::NonNull;
 use core::slice;
+use core::time::Duration;
 use core::{alloc::Layout, any::Any, marker, mem, ops::Range, ptr};
 use wasmtime_environ::{GcArrayLayout, GcStructLayout, GcTypeLayouts, VMSharedTypeIndex};
 
@@ -338,6 +339,17 @@ pub unsafe trait GcHeap: 'static + Send + Sync {
     /// or incorrect results.
     fn array_len(&self, arrayref: &VMArrayRef) -> u32;
 
+    /// Get the number of bytes currently occupied by objects in this heap.
+    ///
+    /// This includes garbage objects that have not been reclaimed yet.
+    /// Immediately after a collection, it is the size of the live data (plus
+    /// any garbage that the collector is unable to reclaim).
+    fn used_bytes(&self) -> usize;
+
+    /// Get the total number of bytes ever allocated in this heap since it was
+    /// attached to its memory.
+    fn bytes_allocated(&self) -> u64;
+
     ////////////////////////////////////////////////////////////////////////////
     // Garbage Collection Methods
 
@@ -542,7 +554,7 @@ pub unsafe trait GcHeap: 'static + Send + Sync {
 /// This is effectively a builder for a `GcRootsIter` that will be given to a GC
 /// heap when it is time to perform garbage collection.
 #[derive(Default)]
-pub struct GcRootsList(Vec<RawGcRoot>);
+pub struct GcRootsList(Vec<(RawGcRoot, GcRootKind)>);
 
 // Ideally these `*mut`s would be `&mut`s and we wouldn't need as much of this
 // machinery around `GcRootsList`, `RawGcRoot`, `GcRoot`, and `GcRootIter` but
@@ -571,6 +583,44 @@ enum RawGcRoot {
     NonStack(SendSyncPtr<VMGcRef>),
 }
 
+/// Where a GC root came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[cfg_attr(
+    not(feature = "gc"),
+    expect(
+        dead_code,
+        reason = "not worth it at this time to #[cfg] away these variants",
+    )
+)]
+pub enum GcRootKind {
+    /// A GC reference inside a Wasm stack frame.
+    WasmStack,
+    /// A Wasm global.
+    Global,
+    /// A Wasm table element.
+    TableElement,
+    /// A host root created with [`Rooted`][crate::Rooted].
+    UserLifo,
+    /// A host root created with [`OwnedRooted`][crate::OwnedRooted].
+    UserOwned,
+    /// The store's pending exception.
+    PendingException,
+}
+
+impl core::fmt::Display for GcRootKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            GcRootKind::WasmStack => "Wasm stack frame",
+            GcRootKind::Global => "Wasm global",
+            GcRootKind::TableElement => "Wasm table element",
+            GcRootKind::UserLifo => "user LIFO root",
+            GcRootKind::UserOwned => "user owned root",
+            GcRootKind::PendingException => "Pending exception",
+        })
+    }
+}
+
 #[cfg(feature = "gc")]
 impl GcRootsList {
     /// Add a GC root that is inside a Wasm stack frame to this list.
@@ -584,19 +634,20 @@ impl GcRootsList {
             );
             debug_assert!(VMGcRef::from_raw_u32(*ptr_to_root.as_ref()).is_some());
         }
-        self.0.push(RawGcRoot::Stack(ptr_to_root));
+        self.0
+            .push((RawGcRoot::Stack(ptr_to_root), GcRootKind::WasmStack));
     }
 
     /// Add a GC root to this list.
     #[inline]
-    pub unsafe fn add_root(&mut self, ptr_to_root: SendSyncPtr<VMGcRef>, why: &str) {
+    pub unsafe fn add_root(&mut self, ptr_to_root: SendSyncPtr<VMGcRef>, why: GcRootKind) {
         unsafe {
             log::trace!(
                 "Adding non-stack root: {why}: {:#p}",
                 ptr_to_root.as_ref().unchecked_copy()
             );
         }
-        self.0.push(RawGcRoot::NonStack(ptr_to_root))
+        self.0.push((RawGcRoot::NonStack(ptr_to_root), why))
     }
 
     /// Get an iterator over all roots in this list.
@@ -636,8 +687,10 @@ impl<'a> Iterator for GcRootsIter<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        let (raw, kind) = self.list.0.get(self.index).copied()?;
         let root = GcRoot {
-            raw: self.list.0.get(self.index).copied()?,
+            raw,
+            kind,
             _phantom: marker::PhantomData,
         };
         self.index += 1;
@@ -654,6 +707,7 @@ impl<'a> Iterator for GcRootsIter<'a> {
 #[derive(Debug)]
 pub struct GcRoot<'a> {
     raw: RawGcRoot,
+    kind: GcRootKind,
     _phantom: marker::PhantomData<&'a mut VMGcRef>,
 }
 
@@ -664,6 +718,12 @@ impl GcRoot<'_> {
         matches!(self.raw, RawGcRoot::Stack(_))
     }
 
+    /// Where did this GC root come from?
+    #[inline]
+    pub fn kind(&self) -> GcRootKind {
+        self.kind
+    }
+
     /// Get this GC root.
     ///
     /// Does NOT run GC barriers.
@@ -718,7 +778,11 @@ pub trait GarbageCollection<'a>: Send + Sync {
     /// has finished (`GcProgress::Complete`).
     ///
     /// The mutator does *not* run in between increments. This method exists
-    /// solely to allow cooperative yielding
+    /// solely to allow cooperative yielding to other tasks of an async
+    /// executor, so implementations do not need any barriers beyond those
+    /// they already require. Implementations should keep each increment's
+    /// amount of work small so that the executor isn't blocked for long, but
+    /// this doesn't reduce how long the mutator is paused for.
     fn collect_increment(&mut self) -> GcProgress;
 
     /// Run this GC process to completion.
@@ -745,23 +809,77 @@ pub enum GcProgress {
 
 /// Asynchronously run the given garbage collection process to completion,
 /// cooperatively yielding back to the event loop after each increment of work.
+///
+/// The mutator doesn't run until the collection is complete, including while
+/// it is yielded.
+///
+/// Returns the times that the collection ran for between yields.
 pub async fn collect_async<'a>(
     mut collection: Box<dyn GarbageCollection<'a> + 'a>,
     async_yield: bool,
-) {
+) -> GcPauseTimes {
+    let mut times = GcPauseTimes::default();
+    let mut pause = GcPause::start();
     loop {
         match collection.collect_increment() {
             GcProgress::Continue => {
                 if async_yield {
+                    times.record(pause.elapsed());
                     #[cfg(feature = "async")]
-                    crate::runtime::vm::Yield::new().await
+                    crate::runtime::vm::Yield::new().await;
+                    pause = GcPause::start();
                 }
             }
-            GcProgress::Complete => return,
+            GcProgress::Complete => {
+                times.record(pause.elapsed());
+                return times;
+            }
         }
     }
 }
 
+/// The times that a collection paused for, between yields.
+///
+/// Always zero without the `std` feature, since there is no clock to measure
+/// pauses with.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GcPauseTimes {
+    /// The total time of all pauses.
+    pub total: Duration,
+    /// The time of the longest pause.
+    pub max: Duration,
+}
+
+impl GcPauseTimes {
+    fn record(&mut self, pause: Duration) {
+        self.total += pause;
+        self.max = self.max.max(pause);
+    }
+}
+
+/// A timer for how long a collection has run since it last yielded.
+struct GcPause {
+    #[cfg(feature = "std")]
+    start: std::time::Instant,
+}
+
+impl GcPause {
+    fn start() -> Self {
+        GcPause {
+            #[cfg(feature = "std")]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// How long has this pause been running?
+    fn elapsed(&self) -> Duration {
+        #[cfg(feature = "std")]
+        return self.start.elapsed();
+        #[cfg(not(feature = "std"))]
+        return Duration::ZERO;
+    }
+}
+
 #[cfg(all(test, feature = "async"))]
 mod collect_async_tests {
     use super::*;
@@ -124,10 +124,14 @@ pub struct Instance {
     /// If the index is present in the set, the segment has been dropped.
     dropped_data: EntitySet<DataIndex>,
 
-    // TODO: add support for multiple memories; `wmemcheck_state` corresponds to
-    // memory 0.
+    /// Memory checker state for this instance's defined memories.
+    ///
+    /// wmemcheck's default mode only checks the first memory, which holds the
+    /// heap and stack described by `malloc` and the stack pointer, while its
+    /// uninitialized-memory mode tracks every defined memory. Imported
+    /// memories are tracked by the instance that defines them.
     #[cfg(feature = "wmemcheck")]
-    pub(crate) wmemcheck_state: Option<Wmemcheck>,
+    wmemcheck_state: PrimaryMap<DefinedMemoryIndex, Wmemcheck>,
 
     /// Self-pointer back to `Store<T>` and its functions. Not present for
     /// the brief time that `Store<T>` is itself being created. Also not
@@ -156,14 +160,29 @@ impl Instance {
         req: InstanceAllocationRequest,
         memories: PrimaryMap<DefinedMemoryIndex, (MemoryAllocationIndex, Memory)>,
         tables: PrimaryMap<DefinedTableIndex, (TableAllocationIndex, Table)>,
-        memory_tys: &PrimaryMap<MemoryIndex, wasmtime_environ::Memory>,
     ) -> InstanceHandle {
         let module = req.runtime_info.env_module();
         let dropped_elements = EntitySet::with_capacity(module.passive_elements.len());
         let dropped_data = EntitySet::with_capacity(module.passive_data_map.len());
 
-        #[cfg(not(feature = "wmemcheck"))]
-        let _ = memory_tys;
+        #[cfg(feature = "wmemcheck")]
+        let wmemcheck_state = {
+            let engine = req.store.engine();
+            if !engine.config().wmemcheck {
+                PrimaryMap::new()
+            } else if engine.tunables().wmemcheck_uninit {
+                memories
+                    .values()
+                    .map(|(_, memory)| Wmemcheck::new_shadow(memory.byte_size()))
+                    .collect()
+            } else {
+                memories
+                    .values()
+                    .take(1)
+                    .map(|(_, memory)| Wmemcheck::new(memory.byte_size()))
+                    .collect()
+            }
+        };
 
         let mut ret = OwnedInstance::new(Instance {
             id: req.id,
@@ -173,20 +192,7 @@ impl Instance {
             dropped_elements,
             dropped_data,
             #[cfg(feature = "wmemcheck")]
-            wmemcheck_state: {
-                if req.store.engine().config().wmemcheck {
-                    let size = memory_tys
-                        .iter()
-                        .next()
-                        .map(|memory| memory.1.limits.min)
-                        .unwrap_or(0)
-                        * 64
-                        * 1024;
-                    Some(Wmemcheck::new(size.try_into().unwrap()))
-                } else {
-                    None
-                }
-            },
+            wmemcheck_state,
             store: None,
             vmctx: OwnedVMContext::new(),
         });
@@ -346,7 +352,6 @@ impl Instance {
         self.runtime_info.env_module()
     }
 
-    #[cfg(feature = "gc")]
     pub(crate) fn runtime_module(&self) -> Option<&crate::Module> {
         match &self.runtime_info {
             ModuleRuntimeInfo::Module(m) => Some(m),
@@ -736,6 +741,10 @@ impl Instance {
         unsafe { self.vmctx_plus_offset_raw(self.offsets().ptr.vmctx_type_ids_array()) }
     }
 
+    fn coverage_counters(&self) -> NonNull<Option<VmPtr<VmPtr<u64>>>> {
+        unsafe { self.vmctx_plus_offset_raw(self.offsets().vmctx_coverage_counters()) }
+    }
+
     /// Construct a new VMFuncRef for the given function
     /// (imported or defined in this module) and store into the given
     /// location. Used during lazy initialization.
@@ -1282,6 +1291,16 @@ impl Instance {
             self.type_ids_array().write(types.cast().into());
         }
 
+        // Initialize the table of code coverage counters, if any.
+        //
+        // SAFETY: validity of the vmctx means it should be safe to write to it
+        // here.
+        unsafe {
+            let counters = self.runtime_info.coverage_counters();
+            self.coverage_counters()
+                .write(counters.map(|table| table.into()));
+        }
+
         // Initialize the built-in functions
         //
         // SAFETY: the type of the builtin functions field is indeed a pointer
@@ -1542,10 +1561,31 @@ impl Instance {
         unsafe { &mut self.get_unchecked_mut().tables }
     }
 
+    /// Returns the memory checker state of the defined memory `index`, if it
+    /// is being checked.
+    #[cfg(feature = "wmemcheck")]
+    pub(crate) fn wmemcheck_state_mut(
+        self: Pin<&mut Self>,
+        index: DefinedMemoryIndex,
+    ) -> Option<&mut Wmemcheck> {
+        // SAFETY: see `store_mut` above.
+        unsafe { self.get_unchecked_mut().wmemcheck_state.get_mut(index) }
+    }
+
+    /// Returns the memory checker states of all of this instance's checked
+    /// memories.
+    #[cfg(feature = "wmemcheck")]
+    pub(crate) fn wmemcheck_states(&self) -> impl Iterator<Item = &Wmemcheck> {
+        self.wmemcheck_state.values()
+    }
+
+    /// Same as [`Self::wmemcheck_states`], but mutable.
     #[cfg(feature = "wmemcheck")]
-    pub(super) fn wmemcheck_state_mut(self: Pin<&mut Self>) -> &mut Option<Wmemcheck> {
+    pub(crate) fn wmemcheck_states_mut(
+        self: Pin<&mut Self>,
+    ) -> impl Iterator<Item = &mut Wmemcheck> {
         // SAFETY: see `store_mut` above.
-        unsafe { &mut self.get_unchecked_mut().wmemcheck_state }
+        unsafe { self.get_unchecked_mut().wmemcheck_state.values_mut() }
     }
 }
 
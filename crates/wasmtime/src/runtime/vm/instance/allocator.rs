@@ -344,7 +344,6 @@ impl dyn InstanceAllocator + '_ {
                 request,
                 mem::take(&mut guard.memories),
                 mem::take(&mut guard.tables),
-                &module.memories,
             ))
         };
 
@@ -771,18 +770,22 @@ async fn initialize_globals(
 
         let id = store.id();
         let index = module.global_index(index);
-        let mut instance = store.instance_mut(context.instance);
 
+        // Global 0 is assumed to be the stack pointer, and the stack to be in
+        // memory 0.
         #[cfg(feature = "wmemcheck")]
         if index.as_u32() == 0
             && module.globals[index].wasm_ty == wasmtime_environ::WasmValType::I32
         {
-            if let Some(wmemcheck) = instance.as_mut().wmemcheck_state_mut() {
+            let memory = wasmtime_environ::MemoryIndex::from_u32(0);
+            if let Some(wmemcheck) = store.wmemcheck_state(context.instance, memory) {
                 let size = usize::try_from(val.unwrap_i32()).unwrap();
                 wmemcheck.set_stack_size(size);
             }
         }
 
+        let mut instance = store.instance_mut(context.instance);
+
         let global = instance.as_mut().get_exported_global(id, index);
 
         // Note that mutability is bypassed here because this is, by definition,
@@ -75,7 +75,8 @@ use wasmtime_environ::{
 };
 #[cfg(feature = "wmemcheck")]
 use wasmtime_wmemcheck::AccessError::{
-    DoubleMalloc, InvalidFree, InvalidRead, InvalidWrite, OutOfBounds,
+    DoubleMalloc, InvalidFree, InvalidRead, InvalidRealloc, InvalidWrite, OutOfBounds,
+    UninitializedRead,
 };
 
 /// Raw functions which are actually called from compiled code.
@@ -523,7 +524,32 @@ fn memory_copy(
     let dst_index = MemoryIndex::from_u32(dst_index);
     store
         .instance_mut(instance)
-        .memory_copy(dst_index, dst, src_index, src, len)
+        .memory_copy(dst_index, dst, src_index, src, len)?;
+
+    #[cfg(feature = "wmemcheck")]
+    {
+        // The copy succeeded, so these are in bounds.
+        let dst = usize::try_from(dst).unwrap();
+        let src = usize::try_from(src).unwrap();
+        let len = usize::try_from(len).unwrap();
+        let same_memory = src_index == dst_index;
+        let src_defined = same_memory
+            || match store.wmemcheck_state(instance, src_index) {
+                Some(wmemcheck_state) => wmemcheck_state.is_defined(src, len),
+                None => true,
+            };
+        if let Some(wmemcheck_state) = store.wmemcheck_state(instance, dst_index) {
+            if same_memory {
+                wmemcheck_state.copy(dst, src, len);
+            } else if src_defined {
+                wmemcheck_state.define(dst, len);
+            } else {
+                wmemcheck_state.poison(dst, len);
+            }
+        }
+    }
+
+    Ok(())
 }
 
 // Implementation of `memory.fill` for locally defined memories.
@@ -539,7 +565,20 @@ fn memory_fill(
     #[expect(clippy::cast_possible_truncation, reason = "known to truncate here")]
     store
         .instance_mut(instance)
-        .memory_fill(memory_index, dst, val as u8, len)
+        .memory_fill(memory_index, dst, val as u8, len)?;
+
+    #[cfg(feature = "wmemcheck")]
+    {
+        let memory_index = store
+            .instance(instance)
+            .env_module()
+            .memory_index(memory_index);
+        if let Some(wmemcheck_state) = store.wmemcheck_state(instance, memory_index) {
+            wmemcheck_state.define(usize::try_from(dst).unwrap(), usize::try_from(len).unwrap());
+        }
+    }
+
+    Ok(())
 }
 
 // Implementation of `memory.init`.
@@ -556,7 +595,14 @@ fn memory_init(
     let data_index = DataIndex::from_u32(data_index);
     store
         .instance_mut(instance)
-        .memory_init(memory_index, data_index, dst, src, len)
+        .memory_init(memory_index, data_index, dst, src, len)?;
+
+    #[cfg(feature = "wmemcheck")]
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, memory_index) {
+        wmemcheck_state.define(usize::try_from(dst).unwrap(), usize::try_from(len).unwrap());
+    }
+
+    Ok(())
 }
 
 // Implementation of `ref.func`.
@@ -646,7 +692,7 @@ fn grow_gc_heap(store: &mut dyn VMStore, _instance: InstanceId, bytes_needed: u6
 /// Allocate a raw, unininitialized GC object for Wasm code.
 ///
 /// The Wasm code is responsible for initializing the object.
-#[cfg(feature = "gc-drc")]
+#[cfg(any(feature = "gc-drc", feature = "gc-copying"))]
 fn gc_alloc_raw(
     store: &mut dyn VMStore,
     instance: InstanceId,
@@ -1236,6 +1282,46 @@ fn memory_atomic_wait64(
         .atomic_wait64(addr_index, expected, timeout)? as u32)
 }
 
+// Hook invoked before each instruction, when enabled, in code compiled with
+// guest debugging instrumentation.
+unsafe fn debug_hook(
+    store: &mut dyn VMStore,
+    instance: InstanceId,
+    func: u32,
+    offset: u32,
+    entry: u32,
+    values: *mut u8,
+    kinds: *mut u8,
+    num_locals: u32,
+    num_stack: u32,
+) -> Result<()> {
+    #[cfg(feature = "guest-debug")]
+    {
+        // SAFETY: compiled code passes a valid spill area for `num_locals`
+        // locals and `num_stack` operand stack values.
+        unsafe {
+            crate::runtime::debugger::debug_hook(
+                store,
+                instance,
+                func,
+                offset,
+                entry != 0,
+                values,
+                kinds,
+                num_locals,
+                num_stack,
+            )
+        }
+    }
+    #[cfg(not(feature = "guest-debug"))]
+    {
+        let _ = (
+            store, instance, func, offset, entry, values, kinds, num_locals, num_stack,
+        );
+        bail!("guest debugging support was disabled at compile time")
+    }
+}
+
 // Hook for when an instance runs out of fuel.
 fn out_of_gas(store: &mut dyn VMStore, _instance: InstanceId) -> Result<()> {
     block_on!(store, async |store| {
@@ -1250,6 +1336,16 @@ fn out_of_gas(store: &mut dyn VMStore, _instance: InstanceId) -> Result<()> {
     })?
 }
 
+// Hook for when a function is entered with fuel profiling enabled.
+fn fuel_profile_enter(store: &mut dyn VMStore, instance: InstanceId, func: u32, fp: *mut u8) {
+    store.fuel_profile_enter(instance, FuncIndex::from_u32(func), fp.addr());
+}
+
+// Hook for when a function is about to return with fuel profiling enabled.
+fn fuel_profile_exit(store: &mut dyn VMStore, _instance: InstanceId, fp: *mut u8) {
+    store.fuel_profile_exit(fp.addr());
+}
+
 // Hook for when an instance observes that the epoch has changed.
 #[cfg(target_has_atomic = "64")]
 fn new_epoch(store: &mut dyn VMStore, _instance: InstanceId) -> Result<NextEpoch> {
@@ -1308,33 +1404,111 @@ unsafe impl HostResultHasUnwindSentinel for NextEpoch {
 // Hook for validating malloc using wmemcheck_state.
 #[cfg(feature = "wmemcheck")]
 fn check_malloc(store: &mut dyn VMStore, instance: InstanceId, addr: u32, len: u32) -> Result<()> {
-    let instance = store.instance_mut(instance);
-    if let Some(wmemcheck_state) = instance.wmemcheck_state_mut() {
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0)) {
+        if !wmemcheck_state.exit_allocator() {
+            return Ok(());
+        }
         let result = wmemcheck_state.malloc(addr as usize, len as usize);
-        wmemcheck_state.memcheck_on();
-        match result {
-            Ok(()) => {}
-            Err(DoubleMalloc { addr, len }) => {
-                bail!("Double malloc at addr {:#x} of size {}", addr, len)
-            }
-            Err(OutOfBounds { addr, len }) => {
-                bail!("Malloc out of bounds at addr {:#x} of size {}", addr, len);
+        wmemcheck_alloc_result(store, instance, result, addr)?;
+    }
+    Ok(())
+}
+
+// Hook for validating calloc using wmemcheck_state.
+#[cfg(feature = "wmemcheck")]
+fn check_calloc(
+    store: &mut dyn VMStore,
+    instance: InstanceId,
+    addr: u32,
+    count: u32,
+    size: u32,
+) -> Result<()> {
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0)) {
+        if !wmemcheck_state.exit_allocator() || addr == 0 {
+            return Ok(());
+        }
+        let len = (count as usize)
+            .checked_mul(size as usize)
+            .ok_or_else(|| anyhow!("Calloc size overflow: {count} * {size}"))?;
+        let result = wmemcheck_state.calloc(addr as usize, len);
+        wmemcheck_alloc_result(store, instance, result, addr)?;
+    }
+    Ok(())
+}
+
+// Hook for validating realloc using wmemcheck_state.
+#[cfg(feature = "wmemcheck")]
+fn check_realloc(
+    store: &mut dyn VMStore,
+    instance: InstanceId,
+    addr: u32,
+    old_addr: u32,
+    len: u32,
+) -> Result<()> {
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0)) {
+        if !wmemcheck_state.exit_allocator() {
+            return Ok(());
+        }
+        let result = wmemcheck_state.realloc(addr as usize, old_addr as usize, len as usize);
+        wmemcheck_alloc_result(store, instance, result, addr)?;
+    }
+    Ok(())
+}
+
+// Reports an allocation error, or records the program counters of the current
+// Wasm backtrace alongside a successful allocation so that leaks can be
+// attributed to their allocation site. These are only symbolized if the
+// allocation is leaked.
+#[cfg(feature = "wmemcheck")]
+fn wmemcheck_alloc_result(
+    store: &mut dyn VMStore,
+    instance: InstanceId,
+    result: Result<(), wasmtime_wmemcheck::AccessError>,
+    addr: u32,
+) -> Result<()> {
+    match result {
+        Ok(()) => {
+            let store = store.store_opaque_mut();
+            if !store.engine().config().wasm_backtrace {
+                return Ok(());
             }
-            _ => {
-                panic!("unreachable")
+            let mut pcs = Vec::new();
+            crate::runtime::vm::Backtrace::trace(store, |frame| {
+                pcs.push(frame.pc());
+                core::ops::ControlFlow::Continue(())
+            });
+            if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0))
+            {
+                wmemcheck_state.set_backtrace(addr as usize, pcs.into());
             }
+            Ok(())
+        }
+        Err(DoubleMalloc { addr, len }) => {
+            bail!("Double malloc at addr {:#x} of size {}", addr, len)
+        }
+        Err(OutOfBounds { addr, len }) => {
+            bail!("Malloc out of bounds at addr {:#x} of size {}", addr, len);
+        }
+        Err(InvalidRealloc { addr }) => {
+            bail!("Invalid realloc at addr {:#x}", addr)
+        }
+        Err(InvalidFree { addr }) => {
+            bail!("Invalid free at addr {:#x}", addr)
+        }
+        _ => {
+            panic!("unreachable")
         }
     }
-    Ok(())
 }
 
 // Hook for validating free using wmemcheck_state.
 #[cfg(feature = "wmemcheck")]
 fn check_free(store: &mut dyn VMStore, instance: InstanceId, addr: u32) -> Result<()> {
-    let instance = store.instance_mut(instance);
-    if let Some(wmemcheck_state) = instance.wmemcheck_state_mut() {
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0)) {
+        if !wmemcheck_state.exit_allocator() {
+            return Ok(());
+        }
         let result = wmemcheck_state.free(addr as usize);
-        wmemcheck_state.memcheck_on();
         match result {
             Ok(()) => {}
             Err(InvalidFree { addr }) => {
@@ -1349,6 +1523,10 @@ fn check_free(store: &mut dyn VMStore, instance: InstanceId, addr: u32) -> Resul
 }
 
 // Hook for validating load using wmemcheck_state.
+//
+// In wmemcheck's uninitialized-memory mode, loading uninitialized bytes isn't
+// an error; instead this returns 1 so that compiled code can mark the loaded
+// value as uninitialized.
 #[cfg(feature = "wmemcheck")]
 fn check_load(
     store: &mut dyn VMStore,
@@ -1356,15 +1534,17 @@ fn check_load(
     num_bytes: u32,
     addr: u32,
     offset: u32,
-) -> Result<()> {
-    let instance = store.instance_mut(instance);
-    if let Some(wmemcheck_state) = instance.wmemcheck_state_mut() {
+    memory: u32,
+) -> Result<u32> {
+    let memory = MemoryIndex::from_u32(memory);
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, memory) {
         let result = wmemcheck_state.read(addr as usize + offset as usize, num_bytes as usize);
         match result {
             Ok(()) => {}
             Err(InvalidRead { addr, len }) => {
                 bail!("Invalid load at addr {:#x} of size {}", addr, len);
             }
+            Err(UninitializedRead { .. }) => return Ok(1),
             Err(OutOfBounds { addr, len }) => {
                 bail!("Load out of bounds at addr {:#x} of size {}", addr, len);
             }
@@ -1373,7 +1553,7 @@ fn check_load(
             }
         }
     }
-    Ok(())
+    Ok(0)
 }
 
 // Hook for validating store using wmemcheck_state.
@@ -1384,10 +1564,13 @@ fn check_store(
     num_bytes: u32,
     addr: u32,
     offset: u32,
+    memory: u32,
+    poisoned: u32,
 ) -> Result<()> {
-    let instance = store.instance_mut(instance);
-    if let Some(wmemcheck_state) = instance.wmemcheck_state_mut() {
-        let result = wmemcheck_state.write(addr as usize + offset as usize, num_bytes as usize);
+    let memory = MemoryIndex::from_u32(memory);
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, memory) {
+        let (addr, len) = (addr as usize + offset as usize, num_bytes as usize);
+        let result = wmemcheck_state.write(addr, len);
         match result {
             Ok(()) => {}
             Err(InvalidWrite { addr, len }) => {
@@ -1400,48 +1583,56 @@ fn check_store(
                 panic!("unreachable")
             }
         }
+        if poisoned != 0 {
+            wmemcheck_state.poison(addr, len);
+        }
     }
     Ok(())
 }
 
-// Hook for turning wmemcheck load/store validation off when entering a malloc function.
+// Hook for reporting the use of a value computed from uninitialized memory in
+// wmemcheck's uninitialized-memory mode.
+#[cfg(feature = "wmemcheck")]
+fn report_uninit_use(_store: &mut dyn VMStore, _instance: InstanceId, kind: u32) -> Result<()> {
+    match wasmtime_environ::UninitUse::from_u32(kind) {
+        Some(kind) => bail!("Use of uninitialized value as {kind}"),
+        None => bail!("Use of uninitialized value"),
+    }
+}
+
+// Hook for turning wmemcheck load/store validation off when entering a malloc,
+// calloc, realloc, or aligned_alloc function.
 #[cfg(feature = "wmemcheck")]
 fn malloc_start(store: &mut dyn VMStore, instance: InstanceId) {
-    let instance = store.instance_mut(instance);
-    if let Some(wmemcheck_state) = instance.wmemcheck_state_mut() {
-        wmemcheck_state.memcheck_off();
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0)) {
+        wmemcheck_state.enter_allocator();
     }
 }
 
 // Hook for turning wmemcheck load/store validation off when entering a free function.
 #[cfg(feature = "wmemcheck")]
 fn free_start(store: &mut dyn VMStore, instance: InstanceId) {
-    let instance = store.instance_mut(instance);
-    if let Some(wmemcheck_state) = instance.wmemcheck_state_mut() {
-        wmemcheck_state.memcheck_off();
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0)) {
+        wmemcheck_state.enter_allocator();
     }
 }
 
 // Hook for tracking wasm stack updates using wmemcheck_state.
 #[cfg(feature = "wmemcheck")]
-fn update_stack_pointer(_store: &mut dyn VMStore, _instance: InstanceId, _value: u32) {
-    // TODO: stack-tracing has yet to be finalized. All memory below
-    // the address of the top of the stack is marked as valid for
-    // loads and stores.
-    // if let Some(wmemcheck_state) = &mut instance.wmemcheck_state {
-    //     instance.wmemcheck_state.update_stack_pointer(value as usize);
-    // }
-}
-
-// Hook updating wmemcheck_state memory state vector every time memory.grow is called.
-#[cfg(feature = "wmemcheck")]
-fn update_mem_size(store: &mut dyn VMStore, instance: InstanceId, num_pages: u32) {
-    let instance = store.instance_mut(instance);
-    if let Some(wmemcheck_state) = instance.wmemcheck_state_mut() {
-        const KIB: usize = 1024;
-        let num_bytes = num_pages as usize * 64 * KIB;
-        wmemcheck_state.update_mem_size(num_bytes);
+fn update_stack_pointer(store: &mut dyn VMStore, instance: InstanceId, value: u32) -> Result<()> {
+    // TODO: stack-tracing has yet to be finalized for the default mode. All
+    // memory below the address of the top of the stack is marked as valid for
+    // loads and stores. Uninitialized-memory mode poisons new stack frames.
+    if let Some(wmemcheck_state) = store.wmemcheck_state(instance, MemoryIndex::from_u32(0)) {
+        if wmemcheck_state.is_shadow() {
+            if let Err(OutOfBounds { addr, len }) =
+                wmemcheck_state.update_stack_pointer(value as usize)
+            {
+                bail!("Stack pointer out of bounds at addr {addr:#x} of size {len}");
+            }
+        }
     }
+    Ok(())
 }
 
 fn floor_f32(_store: &mut dyn VMStore, _instance: InstanceId, val: f32) -> f32 {
@@ -32,6 +32,8 @@ pub use self::backtrace::Backtrace;
 #[cfg(feature = "gc")]
 pub use wasmtime_unwinder::Frame;
 
+#[cfg(feature = "coredump")]
+pub use self::coredump::CoreDumpFrame;
 pub use self::coredump::CoreDumpStack;
 pub use self::tls::tls_eager_initialize;
 #[cfg(feature = "async")]
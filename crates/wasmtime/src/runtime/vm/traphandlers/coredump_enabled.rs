@@ -1,7 +1,14 @@
 use super::CallThreadState;
+use crate::Val;
 use crate::prelude::*;
-use crate::runtime::vm::{Backtrace, VMStoreContext};
-use wasm_encoder::CoreDumpValue;
+use crate::runtime::module::lookup_code;
+use crate::runtime::vm::{Backtrace, Instance, VMContext, VMStoreContext};
+use crate::store::InstanceId;
+use core::ptr::NonNull;
+use wasmtime_environ::{
+    DEBUG_VALUE_SLOT_SIZE, DebugValueKind, FRAME_STATE_HEADER_SIZE, FRAME_STATE_NUM_LOCALS,
+    FRAME_STATE_NUM_STACK, FrameStateSlot,
+};
 
 /// A WebAssembly Coredump
 #[derive(Debug)]
@@ -9,17 +16,24 @@ pub struct CoreDumpStack {
     /// The backtrace containing the stack frames for the CoreDump
     pub bt: Backtrace,
 
-    /// The locals for each frame in the backtrace.
-    ///
-    /// This is not currently implemented.
-    #[expect(dead_code, reason = "not implemented yet")]
-    pub locals: Vec<Vec<CoreDumpValue>>,
+    /// The state of each frame in the backtrace, in the same order as
+    /// `bt.frames()`.
+    pub frames: Vec<CoreDumpFrame>,
+}
 
-    /// The operands for each stack frame
-    ///
-    /// This is not currently implemented.
-    #[expect(dead_code, reason = "not implemented yet")]
-    pub operand_stack: Vec<Vec<CoreDumpValue>>,
+/// The state of a single stack frame recovered for a core dump.
+#[derive(Debug, Default)]
+pub struct CoreDumpFrame {
+    /// The instance which this frame is executing within, if known.
+    pub instance: Option<InstanceId>,
+
+    /// The values of the frame's locals, or `None` for values which can't be
+    /// represented in core dumps.
+    pub locals: Vec<Option<Val>>,
+
+    /// The values on the frame's operand stack, from bottom to top, or `None`
+    /// for values which can't be represented in core dumps.
+    pub operand_stack: Vec<Option<Val>>,
 }
 
 impl CallThreadState {
@@ -35,10 +49,99 @@ impl CallThreadState {
             Backtrace::new_with_trap_state(vm_store_context, self.unwinder, self, trap_pc_and_fp)
         };
 
-        Some(CoreDumpStack {
-            bt,
-            locals: vec![],
-            operand_stack: vec![],
+        let trap_pc = trap_pc_and_fp.map(|(pc, _)| pc);
+        let frames = bt
+            .frames()
+            .map(|frame| {
+                // See `WasmBacktrace::from_captured` for why the pc is adjusted
+                // for all but the trapping frame.
+                let pc = if Some(frame.pc()) == trap_pc {
+                    frame.pc()
+                } else {
+                    frame.pc() - 1
+                };
+                // SAFETY: `frame` is a live wasm frame on this thread's stack.
+                unsafe { CoreDumpFrame::capture(pc, frame.fp()) }.unwrap_or_default()
+            })
+            .collect();
+
+        Some(CoreDumpStack { bt, frames })
+    }
+}
+
+impl CoreDumpFrame {
+    /// Reads the state of the wasm frame executing at `pc` with the frame
+    /// pointer `fp` out of its frame-state slot, if it has one.
+    ///
+    /// # Safety
+    ///
+    /// The `pc` and `fp` must describe a live wasm frame on the current stack.
+    unsafe fn capture(pc: usize, fp: usize) -> Option<CoreDumpFrame> {
+        let (code, text_offset) = lookup_code(pc)?;
+        let text_offset = u32::try_from(text_offset).ok()?;
+        let slot = FrameStateSlot::lookup(text_offset, code.frame_state_data())?;
+        let base = fp - usize::try_from(slot.fp_offset()).unwrap();
+
+        // SAFETY: compiled code initialized the slot's header, and the number
+        // of values it describes, before `pc` per the frame-state section.
+        unsafe {
+            let base = base as *const u8;
+            let vmctx = base.cast::<*mut VMContext>().read_unaligned();
+            let num_locals = base
+                .add(usize::try_from(FRAME_STATE_NUM_LOCALS).unwrap())
+                .cast::<u32>()
+                .read_unaligned();
+            let num_stack = base
+                .add(usize::try_from(FRAME_STATE_NUM_STACK).unwrap())
+                .cast::<u32>()
+                .read_unaligned();
+            let num_locals = usize::try_from(num_locals).unwrap();
+            let len = num_locals + usize::try_from(num_stack).unwrap();
+            let values = base.add(usize::try_from(FRAME_STATE_HEADER_SIZE).unwrap());
+
+            let instance =
+                NonNull::new(vmctx).map(|vmctx| Instance::from_vmctx(vmctx).as_ref().id());
+            let mut locals = Vec::with_capacity(num_locals);
+            let mut operand_stack = Vec::with_capacity(len - num_locals);
+            for i in 0..len {
+                let val = read_value(values, len, i);
+                if i < num_locals {
+                    locals.push(val);
+                } else {
+                    operand_stack.push(val);
+                }
+            }
+            Some(CoreDumpFrame {
+                instance,
+                locals,
+                operand_stack,
+            })
+        }
+    }
+}
+
+/// Reads the `i`th of `len` values recorded at `values` in the layout
+/// described in `wasmtime_environ::guest_debug`.
+///
+/// Values of types which can't be represented in core dumps are reported as
+/// `None`.
+///
+/// # Safety
+///
+/// The `values` pointer must point to `len` initialized value slots followed
+/// by their kinds.
+unsafe fn read_value(values: *const u8, len: usize, i: usize) -> Option<Val> {
+    let slot_size = usize::try_from(DEBUG_VALUE_SLOT_SIZE).unwrap();
+    // SAFETY: guaranteed by this function's contract.
+    unsafe {
+        let slot = values.add(i * slot_size);
+        let kind = values.add(len * slot_size + i).read();
+        Some(match DebugValueKind::from_u8(kind)? {
+            DebugValueKind::I32 => Val::I32(slot.cast::<i32>().read_unaligned()),
+            DebugValueKind::I64 => Val::I64(slot.cast::<i64>().read_unaligned()),
+            DebugValueKind::F32 => Val::F32(slot.cast::<u32>().read_unaligned()),
+            DebugValueKind::F64 => Val::F64(slot.cast::<u64>().read_unaligned()),
+            _ => return None,
         })
     }
 }
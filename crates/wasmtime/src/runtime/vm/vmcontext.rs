@@ -1169,6 +1169,13 @@ pub struct VMStoreContext {
     /// all traps (or uncaught exceptions).
     pub last_wasm_entry_trap_handler: UnsafeCell<usize>,
 
+    /// Nonzero when code compiled with guest debugging instrumentation should
+    /// call the `debug_hook` builtin before each instruction.
+    ///
+    /// This is updated by the store whenever breakpoints are added or removed
+    /// or single-stepping is toggled.
+    pub debug_hook_enabled: UnsafeCell<usize>,
+
     /// Stack information used by stack switching instructions. See documentation
     /// on `VMStackChain` for details.
     pub stack_chain: UnsafeCell<VMStackChain>,
@@ -1264,6 +1271,7 @@ impl Default for VMStoreContext {
             last_wasm_entry_fp: UnsafeCell::new(0),
             last_wasm_entry_sp: UnsafeCell::new(0),
             last_wasm_entry_trap_handler: UnsafeCell::new(0),
+            debug_hook_enabled: UnsafeCell::new(0),
             stack_chain: UnsafeCell::new(VMStackChain::Absent),
             async_guard_range: ptr::null_mut()..ptr::null_mut(),
         }
@@ -1324,6 +1332,10 @@ mod test_vmstore_context {
             offset_of!(VMStoreContext, last_wasm_entry_trap_handler),
             usize::from(offsets.ptr.vmstore_context_last_wasm_entry_trap_handler())
         );
+        assert_eq!(
+            offset_of!(VMStoreContext, debug_hook_enabled),
+            usize::from(offsets.ptr.vmstore_context_debug_hook_enabled())
+        );
         assert_eq!(
             offset_of!(VMStoreContext, stack_chain),
             usize::from(offsets.ptr.vmstore_context_stack_chain())
@@ -67,6 +67,22 @@ impl CompilerBuilder for Builder {
             bail!("Winch does not currently support generating native debug information");
         }
 
+        if tunables.debug_instrumentation {
+            bail!("Winch does not currently support guest debugging instrumentation");
+        }
+
+        if tunables.fuel_profiling {
+            bail!("Winch does not currently support fuel profiling");
+        }
+
+        if tunables.coverage {
+            bail!("Winch does not currently support code coverage");
+        }
+
+        if tunables.wmemcheck_uninit {
+            bail!("Winch does not currently support wmemcheck's uninitialized-memory mode");
+        }
+
         self.tunables = Some(tunables.clone());
         self.cranelift.set_tunables(tunables)?;
         Ok(())
@@ -4,7 +4,9 @@ use object::write::{Object, SymbolId};
 use std::any::Any;
 use std::mem;
 use std::sync::Mutex;
-use wasmparser::FuncValidatorAllocations;
+use wasmparser::{
+    FuncToValidate, FuncValidatorAllocations, FunctionBody, OperatorsReader, ValidatorResources,
+};
 use wasmtime_cranelift::CompiledFunction;
 use wasmtime_environ::{
     CompileError, CompiledFunctionBody, DefinedFuncIndex, FuncKey, FunctionBodyData, FunctionLoc,
@@ -104,6 +106,34 @@ fn box_dyn_any(x: impl Any + Send + Sync) -> Box<dyn Any + Send + Sync> {
     b
 }
 
+/// Returns the maximum height of the operand stack of the function `body`,
+/// which `validator` validates.
+///
+/// Winch only learns the height of the operand stack as it compiles each
+/// operator, so this runs a separate validation pass to find it up front.
+fn max_stack_height(
+    validator: &FuncToValidate<ValidatorResources>,
+    body: &FunctionBody<'_>,
+) -> Result<u32> {
+    let mut validator = FuncToValidate {
+        resources: &validator.resources,
+        index: validator.index,
+        ty: validator.ty,
+        features: validator.features,
+    }
+    .into_validator(Default::default());
+    let mut reader = body.get_binary_reader();
+    validator.read_locals(&mut reader)?;
+    let mut ops = OperatorsReader::new(reader);
+    let mut max = 0;
+    while !ops.eof() {
+        let offset = ops.original_position();
+        validator.op(offset, &ops.read()?)?;
+        max = max.max(validator.operand_stack_height());
+    }
+    Ok(max)
+}
+
 impl wasmtime_environ::Compiler for Compiler {
     fn inlining_compiler(&self) -> Option<&dyn wasmtime_environ::InliningCompiler> {
         None
@@ -130,6 +160,14 @@ impl wasmtime_environ::Compiler for Compiler {
         let FunctionBodyData {
             body, validator, ..
         } = data;
+        let max_stack_height = if self.tunables.debug_frame_state {
+            Some(
+                max_stack_height(&validator, &body)
+                    .map_err(|e| CompileError::Codegen(format!("{e:?}")))?,
+            )
+        } else {
+            None
+        };
         let mut context = self.get_context(translation);
         let mut validator = validator.into_validator(mem::take(&mut context.allocations));
         let func = self
@@ -142,6 +180,7 @@ impl wasmtime_environ::Compiler for Compiler {
                 &mut context.builtins,
                 &mut validator,
                 &self.tunables,
+                max_stack_height,
             )
             .map_err(|e| CompileError::Codegen(format!("{e:?}")));
         self.save_context(context, validator.into_allocations());
@@ -0,0 +1,414 @@
+//! Support for pre-initializing components.
+//!
+//! A component is pre-initialized by pre-initializing each of the core modules
+//! defined within it. Every module is instrumented individually, the
+//! instrumented component is instantiated, its initialization function is
+//! called, and then the state of each core instance is snapshotted and used to
+//! rewrite the module it was instantiated from.
+
+use crate::info::ModuleContext;
+use crate::{Wizer, parse, snapshot};
+use anyhow::{Context, bail};
+use std::convert::Infallible;
+use wasm_encoder::reencode::{Reencode, ReencodeComponent};
+use wasm_encoder::{ComponentSectionId, RawSection};
+use wasmparser::{
+    ComponentCanonicalSectionReader, ComponentExportSectionReader, ComponentExternalKind,
+    ComponentInstanceSectionReader, Parser, Payload, Validator, WasmFeatures,
+};
+use wasmtime::Store;
+use wasmtime::component::{Component, Instance, types::ComponentItem};
+
+/// The default initialization function for components.
+///
+/// Component export names must be kebab-case, so the default name used for
+/// core modules is not a valid export name for a component.
+const DEFAULT_COMPONENT_INIT_FUNC: &str = "wizer-initialize";
+
+/// Info that we keep track of for a component while it is being wizened.
+///
+/// This is created by [`Wizer::instrument_component`] and then passed to
+/// [`Wizer::snapshot_component`].
+pub struct ComponentContext<'a> {
+    /// The top-level sections of the original component.
+    sections: Vec<Section<'a>>,
+
+    /// The core modules defined within the component, in the order they
+    /// appear in the binary.
+    modules: Vec<(&'a [u8], ModuleContext<'a>)>,
+}
+
+enum Section<'a> {
+    /// A section which is copied over as-is.
+    Raw(RawSection<'a>),
+
+    /// A core module section, referring to an index in
+    /// `ComponentContext::modules`.
+    Module(usize),
+
+    /// A canonical function section, whose uses of component functions are
+    /// renumbered if the initialization function is removed.
+    Canonical(RawSection<'a>, ComponentCanonicalSectionReader<'a>),
+
+    /// A component instance section, whose uses of component functions are
+    /// renumbered if the initialization function is removed.
+    Instances(RawSection<'a>, ComponentInstanceSectionReader<'a>),
+
+    /// A component export section, which may contain the initialization
+    /// function.
+    Exports {
+        raw: RawSection<'a>,
+        reader: ComponentExportSectionReader<'a>,
+        /// The number of component functions defined before this section.
+        funcs: u32,
+    },
+}
+
+impl Wizer {
+    /// Initialize the given Wasm component, snapshot it, and return the
+    /// serialized snapshot as a new, pre-initialized Wasm component.
+    ///
+    /// This is the component equivalent of [`Wizer::run`]. The component's
+    /// initialization function is an export of type `func()`, see
+    /// [`Wizer::get_component_init_func`].
+    pub fn run_component<T>(
+        &self,
+        store: &mut Store<T>,
+        wasm: &[u8],
+        instantiate: impl FnOnce(&mut Store<T>, &Component) -> wasmtime::Result<Instance>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (cx, instrumented_wasm) = self.instrument_component(wasm)?;
+
+        let engine = store.engine();
+        let component = Component::new(engine, &instrumented_wasm)
+            .context("failed to compile the Wasm component")?;
+        self.validate_component_init_func(&component)?;
+
+        let instance = instantiate(store, &component)?;
+        self.initialize_component(store, &instance)?;
+        self.snapshot_component(cx, store, &instance)
+    }
+
+    /// Returns the name of the initialization function that will be run when
+    /// wizening a component.
+    ///
+    /// This is the same as [`Wizer::get_init_func`] unless the default
+    /// initialization function is used, in which case this is
+    /// `"wizer-initialize"`.
+    pub fn get_component_init_func(&self) -> &str {
+        if self.init_func == crate::DEFAULT_INIT_FUNC {
+            DEFAULT_COMPONENT_INIT_FUNC
+        } else {
+            &self.init_func
+        }
+    }
+
+    /// First half of [`Self::run_component`] which instruments the provided
+    /// `wasm` component and produces a new component which should be run by a
+    /// runtime.
+    ///
+    /// After the returned wasm is executed the context returned here and the
+    /// state of the instance should be passed to [`Self::snapshot_component`].
+    pub fn instrument_component<'a>(
+        &self,
+        wasm: &'a [u8],
+    ) -> anyhow::Result<(ComponentContext<'a>, Vec<u8>)> {
+        self.wasm_validate(wasm)?;
+
+        let cx = parse_component(wasm)?;
+        let modules = cx
+            .modules
+            .iter()
+            .map(|(_, module)| crate::instrument::instrument(module))
+            .collect::<Vec<_>>();
+        let instrumented_wasm = cx.encode(|i| &modules[i], None)?;
+
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.wasm_validate(&instrumented_wasm) {
+                panic!("instrumented Wasm is not valid: {error:?}");
+            }
+        }
+
+        Ok((cx, instrumented_wasm))
+    }
+
+    /// Second half of [`Self::run_component`] which takes the
+    /// [`ComponentContext`] returned by [`Self::instrument_component`] and the
+    /// state of the `instance` after it has possibly executed its
+    /// initialization function.
+    ///
+    /// This returns a new WebAssembly component which has all state
+    /// pre-initialized.
+    pub fn snapshot_component<T>(
+        &self,
+        mut cx: ComponentContext<'_>,
+        store: &mut Store<T>,
+        instance: &Instance,
+    ) -> anyhow::Result<Vec<u8>> {
+        log::debug!("Snapshotting the initialized component");
+
+        let mut snapshots = cx.modules.iter().map(|_| None).collect::<Vec<_>>();
+        for (module, core_instance) in instance.core_instances(&mut *store) {
+            // Skip instances of imported modules and of modules synthesized
+            // by Wasmtime, neither of which are part of the output.
+            let Some(index) = module.filter(|i| *i < snapshots.len()) else {
+                continue;
+            };
+            if snapshots[index].is_some() {
+                bail!("core module {index} is instantiated more than once, which is not supported");
+            }
+            let module = &cx.modules[index].1;
+            snapshots[index] = Some(snapshot::snapshot(&mut *store, &core_instance, module)?);
+        }
+
+        // Modules which were never instantiated don't have any state to
+        // snapshot and are left as they were.
+        let modules = cx
+            .modules
+            .iter_mut()
+            .zip(&snapshots)
+            .map(|((wasm, module), snapshot)| match snapshot {
+                Some(snapshot) => self.rewrite(module, &*store, snapshot, None),
+                None => wasm.to_vec(),
+            })
+            .collect::<Vec<_>>();
+
+        let remove_export = if self.get_keep_init_func() {
+            None
+        } else {
+            Some(self.get_component_init_func())
+        };
+        let rewritten_wasm = cx.encode(|i| &modules[i], remove_export)?;
+
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.wasm_validate(&rewritten_wasm) {
+                panic!("rewritten Wasm is not valid: {error:?}");
+            }
+        }
+
+        Ok(rewritten_wasm)
+    }
+
+    /// Check that the component exports an initialization function, and that
+    /// the function has the correct type.
+    fn validate_component_init_func(&self, component: &Component) -> anyhow::Result<()> {
+        log::debug!("Validating the exported initialization function");
+        let init_func = self.get_component_init_func();
+        match component
+            .component_type()
+            .get_export(component.engine(), init_func)
+        {
+            Some(ComponentItem::ComponentFunc(ty)) => {
+                if ty.params().len() != 0 || ty.results().len() != 0 {
+                    bail!(
+                        "the Wasm component's `{init_func}` function export does not have type `func()`"
+                    );
+                }
+            }
+            Some(_) => bail!("the Wasm component's `{init_func}` export is not a function"),
+            None => bail!("the Wasm component does not have a `{init_func}` export"),
+        }
+        Ok(())
+    }
+
+    /// Call the component's initialization function.
+    fn initialize_component<T>(
+        &self,
+        store: &mut Store<T>,
+        instance: &Instance,
+    ) -> anyhow::Result<()> {
+        log::debug!("Calling the initialization function");
+
+        let init_func = self.get_component_init_func();
+        let func = instance
+            .get_typed_func::<(), ()>(&mut *store, init_func)
+            .expect("checked by `validate_component_init_func`");
+        func.call(&mut *store, ())
+            .with_context(|| format!("the `{init_func}` function trapped"))?;
+        func.post_return(&mut *store)?;
+
+        Ok(())
+    }
+}
+
+/// Parse the top-level sections of a component and each of its core modules.
+fn parse_component(wasm: &[u8]) -> anyhow::Result<ComponentContext<'_>> {
+    log::debug!("Parsing the input Wasm component");
+
+    if !Parser::is_component(wasm) {
+        bail!("expected a Wasm component");
+    }
+
+    let mut cx = ComponentContext {
+        sections: Vec::new(),
+        modules: Vec::new(),
+    };
+
+    // Removing the initialization function's export shifts the indices of the
+    // component functions defined after it, so a validator is used to keep
+    // track of how many functions have been defined so far.
+    let mut validator = Validator::new_with_features(WasmFeatures::all());
+
+    // Nested modules are parsed separately below, so track how deep we are
+    // to only look at the component's own sections.
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload.context("failed to parse Wasm")?;
+        let funcs = validator
+            .types(0)
+            .map_or(0, |types| types.component_function_count());
+        validator.payload(&payload)?;
+        match payload {
+            Payload::Version { .. } => {
+                depth += 1;
+                continue;
+            }
+            Payload::End(_) => {
+                depth -= 1;
+                continue;
+            }
+            _ if depth > 1 => continue,
+            _ => {}
+        }
+
+        let Some((id, range)) = payload.as_section() else {
+            continue;
+        };
+        let raw = RawSection {
+            id,
+            data: &wasm[range.clone()],
+        };
+        match payload {
+            Payload::ModuleSection { .. } => {
+                let module = &wasm[range];
+                cx.sections.push(Section::Module(cx.modules.len()));
+                cx.modules.push((module, parse::parse(module)?));
+            }
+            Payload::ComponentSection { .. } => {
+                bail!("nested components are not supported")
+            }
+            Payload::ComponentStartSection { .. } => {
+                bail!("component start functions are not supported")
+            }
+            Payload::ComponentCanonicalSection(reader) => {
+                cx.sections.push(Section::Canonical(raw, reader));
+            }
+            Payload::ComponentInstanceSection(reader) => {
+                cx.sections.push(Section::Instances(raw, reader));
+            }
+            Payload::ComponentExportSection(reader) => {
+                cx.sections.push(Section::Exports { raw, reader, funcs });
+            }
+            _ => cx.sections.push(Section::Raw(raw)),
+        }
+    }
+
+    // Tables may be shared between the instances within a component, so if
+    // any module could mutate a table then every module's tables are
+    // snapshotted.
+    let snapshot_tables = cx.modules.iter().any(|(_, m)| m.snapshot_tables());
+    for (_, module) in cx.modules.iter_mut() {
+        module.set_snapshot_tables(snapshot_tables);
+    }
+
+    Ok(cx)
+}
+
+impl ComponentContext<'_> {
+    /// Encode this component, using `module` to get the bytes of each core
+    /// module.
+    ///
+    /// If `remove_export` is given then the component function export of that
+    /// name is removed, and every use of a component function defined after
+    /// it is renumbered accordingly.
+    fn encode<'b>(
+        &self,
+        module: impl Fn(usize) -> &'b Vec<u8>,
+        remove_export: Option<&str>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut renumber = RenumberFuncs::default();
+        let mut encoder = wasm_encoder::Component::new();
+        for section in self.sections.iter() {
+            match section {
+                Section::Raw(raw) => {
+                    encoder.section(raw);
+                }
+                Section::Module(index) => {
+                    encoder.section(&RawSection {
+                        id: ComponentSectionId::CoreModule as u8,
+                        data: module(*index),
+                    });
+                }
+                Section::Canonical(raw, _) | Section::Instances(raw, _)
+                    if renumber.removed.is_empty() =>
+                {
+                    encoder.section(raw);
+                }
+                Section::Canonical(_, reader) => {
+                    let mut funcs = wasm_encoder::CanonicalFunctionSection::new();
+                    renumber.parse_component_canonical_section(&mut funcs, reader.clone())?;
+                    encoder.section(&funcs);
+                }
+                Section::Instances(_, reader) => {
+                    let mut instances = wasm_encoder::ComponentInstanceSection::new();
+                    renumber.parse_component_instance_section(&mut instances, reader.clone())?;
+                    encoder.section(&instances);
+                }
+                Section::Exports { raw, .. }
+                    if remove_export.is_none() && renumber.removed.is_empty() =>
+                {
+                    encoder.section(raw);
+                }
+                Section::Exports { reader, funcs, .. } => {
+                    let mut exports = wasm_encoder::ComponentExportSection::new();
+                    let mut func = *funcs;
+                    for export in reader.clone() {
+                        let export = export?;
+                        if export.kind != ComponentExternalKind::Func {
+                            renumber.parse_component_export(&mut exports, export)?;
+                            continue;
+                        }
+                        // Exporting a function defines a new function index,
+                        // which is what uses of the export refer to.
+                        if Some(export.name.0) == remove_export {
+                            renumber.removed.push(func);
+                        } else {
+                            renumber.parse_component_export(&mut exports, export)?;
+                        }
+                        func += 1;
+                    }
+                    encoder.section(&exports);
+                }
+            }
+        }
+        if renumber.uses_removed {
+            bail!(
+                "the `{}` export is used within the component, so it can't be removed",
+                remove_export.unwrap()
+            );
+        }
+        Ok(encoder.finish())
+    }
+}
+
+/// A reencoder which renumbers component functions to account for the
+/// removal of the functions defined by some exports.
+#[derive(Default)]
+struct RenumberFuncs {
+    /// The original indices of the removed functions, in ascending order.
+    removed: Vec<u32>,
+    /// Whether any of the removed functions is used.
+    uses_removed: bool,
+}
+
+impl Reencode for RenumberFuncs {
+    type Error = Infallible;
+}
+
+impl ReencodeComponent for RenumberFuncs {
+    fn component_func_index(&mut self, func: u32) -> u32 {
+        self.uses_removed |= self.removed.contains(&func);
+        let shift = self.removed.iter().take_while(|r| **r < func).count();
+        func - u32::try_from(shift).unwrap()
+    }
+}
@@ -33,6 +33,9 @@ pub struct ModuleContext<'a> {
     /// this instrumentation pass adds.
     exports: Vec<wasmparser::Export<'a>>,
 
+    /// The number of types defined in this module.
+    types_len: u32,
+
     /// Maps from function index to the function's type index for all functions
     /// defined and imported in this module.
     functions: Vec<u32>,
@@ -41,6 +44,21 @@ pub struct ModuleContext<'a> {
     /// imported, and aliased in this module.
     tables: Vec<wasmparser::TableType>,
 
+    /// The tables defined (as opposed to imported or aliased) in this module.
+    ///
+    /// These come after all imported tables in the table index space.
+    defined_tables: Vec<wasmparser::Table<'a>>,
+
+    /// The indices of the struct and array types defined in this module.
+    gc_types: Vec<u32>,
+
+    /// Whether the contents of this module's tables need to be snapshotted.
+    ///
+    /// This is only the case when code that may run during initialization
+    /// could mutate a table, as otherwise the module's element segments
+    /// already describe its tables' state.
+    snapshot_tables: bool,
+
     /// Maps from memory index to the memory's type for all memories defined,
     /// imported, and aliased in this module.
     memories: Vec<wasmparser::MemoryType>,
@@ -89,16 +107,36 @@ impl<'a> ModuleContext<'a> {
         self.globals.push(global_type);
     }
 
+    /// Push a new type into this module's type index space.
+    pub(crate) fn push_type(&mut self, ty: &wasmparser::SubType) {
+        let index = self.types_len;
+        self.types_len += 1;
+        match ty.composite_type.inner {
+            wasmparser::CompositeInnerType::Struct(_)
+            | wasmparser::CompositeInnerType::Array(_) => {
+                self.gc_types.push(index);
+            }
+            wasmparser::CompositeInnerType::Func(_) | wasmparser::CompositeInnerType::Cont(_) => {}
+        }
+    }
+
     /// Push a new function into this module's function index space.
     pub(crate) fn push_function(&mut self, func_type: u32) {
         self.functions.push(func_type);
     }
 
-    /// Push a new table into this module's table index space.
-    pub(crate) fn push_table(&mut self, table_type: wasmparser::TableType) {
+    /// Push a new imported table into this module's table index space.
+    pub(crate) fn push_imported_table(&mut self, table_type: wasmparser::TableType) {
+        assert!(self.defined_tables.is_empty());
         self.tables.push(table_type);
     }
 
+    /// Push a new defined table into this module's table index space.
+    pub(crate) fn push_defined_table(&mut self, table: wasmparser::Table<'a>) {
+        self.tables.push(table.ty);
+        self.defined_tables.push(table);
+    }
+
     /// Push a new import into this module.
     pub(crate) fn push_import(&mut self, import: wasmparser::Import<'a>) {
         self.imports.push(import);
@@ -115,7 +153,7 @@ impl<'a> ModuleContext<'a> {
                 self.push_function(ty_idx);
             }
             wasmparser::TypeRef::Table(ty) => {
-                self.push_table(ty);
+                self.push_imported_table(ty);
             }
             wasmparser::TypeRef::Tag(_) => {
                 unreachable!("exceptions are unsupported; checked in validation")
@@ -167,6 +205,68 @@ impl<'a> ModuleContext<'a> {
             .map(|(i, g)| (u32::try_from(i).unwrap(), g))
     }
 
+    /// Iterate over the defined tables in this module.
+    pub(crate) fn defined_tables(
+        &self,
+    ) -> impl Iterator<Item = (u32, &wasmparser::Table<'a>)> + '_ {
+        let start = self.tables.len() - self.defined_tables.len();
+        self.defined_tables
+            .iter()
+            .enumerate()
+            .map(move |(i, t)| (u32::try_from(start + i).unwrap(), t))
+    }
+
+    /// The indices of the struct and array types defined in this module.
+    pub(crate) fn gc_types(&self) -> &[u32] {
+        &self.gc_types
+    }
+
+    /// The number of globals, both imported and defined, in this module.
+    pub(crate) fn globals_len(&self) -> usize {
+        self.globals.len()
+    }
+
+    /// The number of imported globals in this module, which is also the index
+    /// of its first defined global.
+    pub(crate) fn imported_globals_len(&self) -> u32 {
+        self.defined_globals_index
+            .unwrap_or_else(|| u32::try_from(self.globals.len()).unwrap())
+    }
+
+    /// The number of types defined in this module.
+    pub(crate) fn types_len(&self) -> u32 {
+        self.types_len
+    }
+
+    /// Whether any of this module's defined globals hold references, in which
+    /// case the referenced functions and objects need to be snapshotted.
+    pub(crate) fn has_reference_globals(&self) -> bool {
+        self.defined_globals()
+            .any(|(_, g)| matches!(g.content_type, wasmparser::ValType::Ref(_)))
+    }
+
+    /// Whether references held by this module's globals or tables need to be
+    /// snapshotted.
+    pub(crate) fn snapshot_references(&self) -> bool {
+        self.snapshot_tables || self.has_reference_globals()
+    }
+
+    /// The number of functions, both imported and defined, in this module.
+    pub(crate) fn functions_len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Whether the contents of this module's tables need to be snapshotted.
+    pub(crate) fn snapshot_tables(&self) -> bool {
+        self.snapshot_tables
+    }
+
+    /// Configure whether the contents of this module's tables need to be
+    /// snapshotted.
+    pub(crate) fn set_snapshot_tables(&mut self, snapshot: bool) {
+        self.snapshot_tables = snapshot;
+    }
+
     /// Get a slice of this module's original raw sections.
     pub(crate) fn raw_sections(&self) -> &[wasm_encoder::RawSection<'a>] {
         &self.raw_sections
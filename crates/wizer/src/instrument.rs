@@ -4,9 +4,9 @@ use crate::info::ModuleContext;
 use wasm_encoder::SectionId;
 use wasm_encoder::reencode::{Reencode, RoundtripReencoder};
 
-/// Instrument the input Wasm so that it exports its memories and globals,
-/// allowing us to inspect their state after the module is instantiated and
-/// initialized.
+/// Instrument the input Wasm so that it exports its memories, globals and, if
+/// necessary, tables and functions, allowing us to inspect their state after
+/// the module is instantiated and initialized.
 ///
 /// For example, given this input module:
 ///
@@ -62,35 +62,42 @@ pub(crate) fn instrument(module: &ModuleContext<'_>) -> Vec<u8> {
     log::debug!("Instrumenting the input Wasm");
 
     let mut encoder = wasm_encoder::Module::new();
+    let mut added_globals = type_globals(module).is_empty();
+    let mut added_exports = false;
 
     for section in module.raw_sections() {
+        // Like the export section below, a global section may need to be
+        // synthesized if the module doesn't have one.
+        if !added_globals
+            && (section.id == u8::from(SectionId::Export) || follows_export_section(section.id))
+        {
+            encoder.section(&global_section(module, None));
+            added_globals = true;
+        }
+
         match section.id {
+            // Append globals that allow mapping GC objects' types back to the
+            // module's type indices.
+            id if id == u8::from(SectionId::Global) && !added_globals => {
+                encoder.section(&global_section(module, Some(section)));
+                added_globals = true;
+            }
+
             // For the exports section, we need to transitively export internal
             // state so that we can read the initialized state after we call the
             // initialization function.
             id if id == u8::from(SectionId::Export) => {
-                let mut exports = wasm_encoder::ExportSection::new();
-
-                // First, copy over all the original exports.
-                for export in module.exports() {
-                    RoundtripReencoder
-                        .parse_export(&mut exports, *export)
-                        .unwrap();
-                }
-
-                // Now export all of this module's defined globals, memories,
-                // and instantiations under well-known names so we can inspect
-                // them after initialization.
-                for (i, (j, _)) in module.defined_globals().enumerate() {
-                    let name = format!("__wizer_global_{i}");
-                    exports.export(&name, wasm_encoder::ExportKind::Global, j);
-                }
-                for (i, (j, _)) in module.defined_memories().enumerate() {
-                    let name = format!("__wizer_memory_{i}");
-                    exports.export(&name, wasm_encoder::ExportKind::Memory, j);
-                }
-
-                encoder.section(&exports);
+                encoder.section(&export_section(module));
+                added_exports = true;
+            }
+
+            // Modules nested within a component don't necessarily have an
+            // export section, so synthesize one in its proper place if we
+            // reach a section which must come after it.
+            id if !added_exports && follows_export_section(id) => {
+                encoder.section(&export_section(module));
+                added_exports = true;
+                encoder.section(section);
             }
 
             // All other sections don't need instrumentation and can be copied
@@ -101,5 +108,113 @@ pub(crate) fn instrument(module: &ModuleContext<'_>) -> Vec<u8> {
         }
     }
 
+    if !added_globals {
+        encoder.section(&global_section(module, None));
+    }
+    if !added_exports {
+        encoder.section(&export_section(module));
+    }
+
     encoder.finish()
 }
+
+/// The types for which a global is appended to the module, whose type is used
+/// to map GC objects back to the module's type indices when snapshotting.
+fn type_globals<'a>(module: &'a ModuleContext<'_>) -> &'a [u32] {
+    if module.snapshot_references() {
+        module.gc_types()
+    } else {
+        &[]
+    }
+}
+
+fn global_section(
+    module: &ModuleContext<'_>,
+    original: Option<&wasm_encoder::RawSection<'_>>,
+) -> wasm_encoder::GlobalSection {
+    let mut globals = wasm_encoder::GlobalSection::new();
+    if let Some(original) = original {
+        let reader =
+            wasmparser::GlobalSectionReader::new(wasmparser::BinaryReader::new(original.data, 0))
+                .unwrap();
+        RoundtripReencoder
+            .parse_global_section(&mut globals, reader)
+            .unwrap();
+    }
+    for ty in type_globals(module) {
+        let heap_type = wasm_encoder::HeapType::Concrete(*ty);
+        globals.global(
+            wasm_encoder::GlobalType {
+                val_type: wasm_encoder::ValType::Ref(wasm_encoder::RefType {
+                    nullable: true,
+                    heap_type,
+                }),
+                mutable: false,
+                shared: false,
+            },
+            &wasm_encoder::ConstExpr::ref_null(heap_type),
+        );
+    }
+    globals
+}
+
+fn export_section(module: &ModuleContext<'_>) -> wasm_encoder::ExportSection {
+    let mut exports = wasm_encoder::ExportSection::new();
+
+    // First, copy over all the original exports.
+    for export in module.exports() {
+        RoundtripReencoder
+            .parse_export(&mut exports, *export)
+            .unwrap();
+    }
+
+    // Now export all of this module's defined globals, memories, and
+    // instantiations under well-known names so we can inspect them after
+    // initialization.
+    for (i, (j, _)) in module.defined_globals().enumerate() {
+        let name = format!("__wizer_global_{i}");
+        exports.export(&name, wasm_encoder::ExportKind::Global, j);
+    }
+    for (i, (j, _)) in module.defined_memories().enumerate() {
+        let name = format!("__wizer_memory_{i}");
+        exports.export(&name, wasm_encoder::ExportKind::Memory, j);
+    }
+
+    // When tables need to be snapshotted, also export them.
+    if module.snapshot_tables() {
+        for (i, (j, _)) in module.defined_tables().enumerate() {
+            let name = format!("__wizer_table_{i}");
+            exports.export(&name, wasm_encoder::ExportKind::Table, j);
+        }
+    }
+
+    // When references need to be snapshotted export every function, so that
+    // functions can be mapped back to their index, and the globals describing
+    // each GC type, so that objects can be mapped back to their type's index.
+    if module.snapshot_references() {
+        for i in 0..module.functions_len() {
+            let name = format!("__wizer_func_{i}");
+            let index = u32::try_from(i).unwrap();
+            exports.export(&name, wasm_encoder::ExportKind::Func, index);
+        }
+        for (i, ty) in type_globals(module).iter().enumerate() {
+            let name = format!("__wizer_type_{ty}");
+            let index = u32::try_from(module.globals_len() + i).unwrap();
+            exports.export(&name, wasm_encoder::ExportKind::Global, index);
+        }
+    }
+
+    exports
+}
+
+fn follows_export_section(id: u8) -> bool {
+    [
+        SectionId::Start,
+        SectionId::Element,
+        SectionId::DataCount,
+        SectionId::Code,
+        SectionId::Data,
+    ]
+    .iter()
+    .any(|s| u8::from(*s) == id)
+}
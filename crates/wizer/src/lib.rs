@@ -4,6 +4,8 @@
 
 #![deny(missing_docs)]
 
+#[cfg(feature = "component-model")]
+mod component;
 mod info;
 mod instrument;
 mod parse;
@@ -14,11 +16,14 @@ mod snapshot;
 /// especially useful when providing a custom Linker.
 pub use wasmtime;
 
+#[cfg(feature = "component-model")]
+pub use crate::component::ComponentContext;
 pub use crate::info::ModuleContext;
 use anyhow::Context;
 use std::collections::{HashMap, HashSet};
 use wasmtime::{Extern, Module, Result, Store};
 
+const DEFAULT_INIT_FUNC: &str = "wizer.initialize";
 const DEFAULT_KEEP_INIT_FUNC: bool = false;
 
 /// Wizer: the WebAssembly pre-initializer!
@@ -37,11 +42,21 @@ const DEFAULT_KEEP_INIT_FUNC: bool = false;
 ///
 /// * The Wasm module may not import globals, tables, or memories.
 ///
-/// * Reference types are not supported yet. This is tricky because it would
-///   allow the Wasm module to mutate tables, and we would need to be able to
-///   snapshot the new table state, but funcrefs and externrefs don't have
-///   identity and aren't comparable in the Wasm spec, which makes snapshotting
-///   difficult.
+/// * Globals, and tables mutated during initialization, may only contain null
+///   references, `i31ref`s, functions defined in or imported by the module
+///   and structs and arrays whose types are defined by the module. Any other
+///   reference, such as a non-null `externref` or a host function that was
+///   never imported, has no representation in a Wasm module and can't be
+///   snapshotted. Cycles between structs and arrays must go through at least
+///   one mutable and nullable field.
+///
+/// * The `elem.drop` and `data.drop` instructions are not supported as Wizer
+///   can't determine which segments were dropped during initialization.
+///
+/// * Components are supported with the `component-model` feature through
+///   `Wizer::run_component`. Each core module within a component may be
+///   instantiated at most once and components may not contain nested
+///   components.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "clap", derive(clap::Parser))]
 pub struct Wizer {
@@ -49,7 +64,7 @@ pub struct Wizer {
     /// initialize the Wasm module.
     #[cfg_attr(
         feature = "clap",
-        arg(short = 'f', long, default_value = "wizer.initialize")
+        arg(short = 'f', long, default_value = DEFAULT_INIT_FUNC)
     )]
     init_func: String,
 
@@ -138,7 +153,7 @@ impl Wizer {
     /// Construct a new `Wizer` builder.
     pub fn new() -> Self {
         Wizer {
-            init_func: "wizer.initialize".into(),
+            init_func: DEFAULT_INIT_FUNC.into(),
             func_renames: vec![],
             keep_init_func: None,
         }
@@ -146,7 +161,8 @@ impl Wizer {
 
     /// The export name of the initializer function.
     ///
-    /// Defaults to `"wizer.initialize"`.
+    /// Defaults to `"wizer.initialize"`, or `"wizer-initialize"` when
+    /// wizening a component.
     pub fn init_func(&mut self, init_func: impl Into<String>) -> &mut Self {
         self.init_func = init_func.into();
         self
@@ -254,8 +270,8 @@ impl Wizer {
         // Parse rename spec.
         let renames = FuncRenames::parse(&self.func_renames)?;
 
-        let snapshot = snapshot::snapshot(&mut *store, &instance);
-        let rewritten_wasm = self.rewrite(&mut cx, store, &snapshot, &renames);
+        let snapshot = snapshot::snapshot(&mut *store, &instance, &cx)?;
+        let rewritten_wasm = self.rewrite(&mut cx, store, &snapshot, Some(&renames));
 
         if cfg!(debug_assertions) {
             if let Err(error) = self.wasm_validate(&rewritten_wasm) {
@@ -280,54 +296,17 @@ impl Wizer {
 
         // Reject bulk memory stuff that manipulates state we don't
         // snapshot. See the comment inside `wasm_features`.
-        let mut wasm = wasm;
-        let mut parsers = vec![wasmparser::Parser::new(0)];
-        while !parsers.is_empty() {
-            let payload = match parsers.last_mut().unwrap().parse(wasm, true)? {
-                wasmparser::Chunk::NeedMoreData(_) => unreachable!(),
-                wasmparser::Chunk::Parsed { consumed, payload } => {
-                    wasm = &wasm[consumed..];
-                    payload
-                }
-            };
-            match payload {
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            match payload? {
                 wasmparser::Payload::CodeSectionEntry(code) => {
                     let mut ops = code.get_operators_reader()?;
                     while !ops.eof() {
                         match ops.read()? {
-                            // Table mutations aren't allowed as wizer has no
-                            // way to record a snapshot of a table at this time.
-                            // The only table mutations allowed are those from
-                            // active element segments which can be
-                            // deterministically replayed, so disallow all other
-                            // forms of mutating a table.
-                            //
-                            // Ideally Wizer could take a snapshot of a table
-                            // post-instantiation and then ensure that after
-                            // running initialization the table didn't get
-                            // mutated, allowing these instructions, but that's
-                            // also not possible at this time.
-                            wasmparser::Operator::TableCopy { .. } => {
-                                anyhow::bail!("unsupported `table.copy` instruction")
-                            }
-                            wasmparser::Operator::TableInit { .. } => {
-                                anyhow::bail!("unsupported `table.init` instruction")
-                            }
-                            wasmparser::Operator::TableSet { .. } => {
-                                anyhow::bail!("unsupported `table.set` instruction")
-                            }
-                            wasmparser::Operator::TableGrow { .. } => {
-                                anyhow::bail!("unsupported `table.grow` instruction")
-                            }
-                            wasmparser::Operator::TableFill { .. } => {
-                                anyhow::bail!("unsupported `table.fill` instruction")
-                            }
-
                             // Wizer has no way of dynamically determining which
                             // element or data segments were dropped during
                             // execution so instead disallow these instructions
-                            // entirely. Like above it'd be nice to allow them
-                            // but just forbid their execution during the
+                            // entirely. It'd be nice to allow them but just
+                            // forbid their execution during the
                             // initialization function, but that can't be done
                             // easily at this time.
                             wasmparser::Operator::ElemDrop { .. } => {
@@ -341,9 +320,6 @@ impl Wizer {
                         }
                     }
                 }
-                wasmparser::Payload::End(_) => {
-                    parsers.pop();
-                }
                 _ => continue,
             }
         }
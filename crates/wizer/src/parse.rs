@@ -18,12 +18,14 @@ pub(crate) fn parse<'a>(full_wasm: &'a [u8]) -> anyhow::Result<ModuleContext<'a>
         }
 
         match payload {
+            TypeSection(types) => type_section(&mut module, types)?,
             ImportSection(imports) => import_section(&mut module, imports)?,
             FunctionSection(funcs) => function_section(&mut module, funcs)?,
             TableSection(tables) => table_section(&mut module, tables)?,
             MemorySection(mems) => memory_section(&mut module, mems)?,
             GlobalSection(globals) => global_section(&mut module, globals)?,
             ExportSection(exports) => export_section(&mut module, exports)?,
+            CodeSectionEntry(body) => code_section_entry(&mut module, body)?,
             _ => {}
         }
     }
@@ -31,6 +33,18 @@ pub(crate) fn parse<'a>(full_wasm: &'a [u8]) -> anyhow::Result<ModuleContext<'a>
     Ok(module)
 }
 
+fn type_section<'a>(
+    module: &mut ModuleContext<'a>,
+    types: wasmparser::TypeSectionReader<'a>,
+) -> anyhow::Result<()> {
+    for rec_group in types {
+        for ty in rec_group?.types() {
+            module.push_type(ty);
+        }
+    }
+    Ok(())
+}
+
 fn import_section<'a>(
     module: &mut ModuleContext<'a>,
     imports: wasmparser::ImportSectionReader<'a>,
@@ -65,7 +79,7 @@ fn table_section<'a>(
     tables: wasmparser::TableSectionReader<'a>,
 ) -> anyhow::Result<()> {
     for table in tables {
-        module.push_table(table?.ty);
+        module.push_defined_table(table?);
     }
     Ok(())
 }
@@ -115,3 +129,29 @@ fn export_section<'a>(
     }
     Ok(())
 }
+
+fn code_section_entry(
+    module: &mut ModuleContext<'_>,
+    body: wasmparser::FunctionBody<'_>,
+) -> anyhow::Result<()> {
+    // Once we know that tables need to be snapshotted there's no need to look
+    // at any more code.
+    if module.snapshot_tables() {
+        return Ok(());
+    }
+    let mut ops = body.get_operators_reader()?;
+    while !ops.eof() {
+        match ops.read()? {
+            wasmparser::Operator::TableCopy { .. }
+            | wasmparser::Operator::TableInit { .. }
+            | wasmparser::Operator::TableSet { .. }
+            | wasmparser::Operator::TableGrow { .. }
+            | wasmparser::Operator::TableFill { .. } => {
+                module.set_snapshot_tables(true);
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
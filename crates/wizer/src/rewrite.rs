@@ -1,20 +1,24 @@
 //! Final rewrite pass.
 
-use crate::{FuncRenames, Wizer, info::ModuleContext, snapshot::Snapshot};
-use std::convert::TryFrom;
-use wasm_encoder::reencode::{Reencode, RoundtripReencoder};
+use crate::snapshot::{Field, Snapshot};
+use crate::{FuncRenames, Wizer, info::ModuleContext};
+use std::convert::{Infallible, TryFrom};
+use wasm_encoder::reencode::{Error, Reencode, RoundtripReencoder};
 use wasm_encoder::{ConstExpr, SectionId};
 
 impl Wizer {
     /// Given the initialized snapshot, rewrite the Wasm so that it is already
     /// initialized.
     ///
+    /// When `renames` is `None` the module's exports are left untouched, which
+    /// is used for modules nested within a component since the component
+    /// refers to them by name.
     pub(crate) fn rewrite<T>(
         &self,
         module: &mut ModuleContext<'_>,
         store: &wasmtime::Store<T>,
         snapshot: &Snapshot,
-        renames: &FuncRenames,
+        renames: Option<&FuncRenames>,
     ) -> Vec<u8> {
         log::debug!("Rewriting input Wasm to pre-initialized state");
 
@@ -26,12 +30,19 @@ impl Wizer {
         let mut data_section = if snapshot.data_segments.is_empty() {
             None
         } else {
+            let memories = module.defined_memories().collect::<Vec<_>>();
             let mut data_section = wasm_encoder::DataSection::new();
             for seg in &snapshot.data_segments {
+                let (memory_index, ty) = memories[usize::try_from(seg.memory_index).unwrap()];
+                let offset = if ty.memory64 {
+                    ConstExpr::i64_const(seg.offset as i64)
+                } else {
+                    ConstExpr::i32_const(u32::try_from(seg.offset).unwrap() as i32)
+                };
                 data_section.active(
-                    seg.memory_index,
-                    &ConstExpr::i32_const(seg.offset as i32),
-                    seg.data(store).iter().copied(),
+                    memory_index,
+                    &offset,
+                    snapshot.data(seg, store).iter().copied(),
                 );
             }
             Some(data_section)
@@ -49,14 +60,141 @@ impl Wizer {
             }
         };
 
+        // The globals allocating GC objects are inserted before the module's
+        // defined globals, so every use of those must be renumbered. Sections
+        // which may use globals are only re-encoded when that is the case.
+        let mut reencoder = ShiftGlobals {
+            first_defined: module.imported_globals_len(),
+            shift: u32::try_from(snapshot.objects.len()).unwrap(),
+        };
+        let shift_globals = reencoder.shift > 0;
+
+        // References between GC objects which couldn't be set when allocating
+        // them are set by a new function, which is the module's new start
+        // function.
+        let fixup = (!snapshot.fixups.is_empty()).then(|| {
+            (
+                module.types_len(),
+                u32::try_from(module.functions_len()).unwrap(),
+            )
+        });
+
+        // Sections which may need to be added to the module, in order. Each is
+        // removed once the original module's section of the same kind is
+        // found, and otherwise added before the first section which must
+        // follow it.
+        let mut missing = Vec::new();
+        if fixup.is_some() {
+            missing.push(SectionId::Function);
+        }
+        if shift_globals {
+            missing.push(SectionId::Global);
+        }
+        if fixup.is_some() {
+            missing.push(SectionId::Start);
+        }
+        // When tables are snapshotted their contents are encoded as new
+        // element segments.
+        if module.snapshot_tables() {
+            missing.push(SectionId::Element);
+        }
+        if fixup.is_some() {
+            missing.push(SectionId::Code);
+        }
+
         for section in module.raw_sections() {
+            let position = if is_name_section(section) {
+                Some(usize::MAX)
+            } else {
+                section_position(section.id)
+            };
+            if let Some(position) = position {
+                while let Some(id) = missing
+                    .first()
+                    .copied()
+                    .filter(|id| section_position(u8::from(*id)).unwrap() < position)
+                {
+                    missing.remove(0);
+                    add_section(&mut encoder, id, module, snapshot, &mut reencoder, fixup);
+                }
+                missing.retain(|id| u8::from(*id) != section.id);
+            }
+
             match section {
                 // Some tools expect the name custom section to come last, even
                 // though custom sections are allowed in any order. Therefore,
                 // make sure we've added our data section by now.
                 s if is_name_section(s) => {
                     add_data_section(&mut encoder);
-                    encoder.section(s);
+                    if shift_globals {
+                        let reader = wasmparser::CustomSectionReader::new(
+                            wasmparser::BinaryReader::new(s.data, 0),
+                        )
+                        .unwrap();
+                        reencoder
+                            .parse_custom_section(&mut encoder, reader)
+                            .unwrap();
+                    } else {
+                        encoder.section(s);
+                    }
+                }
+
+                // Add the function which sets references between GC objects
+                // to the type, function and code sections.
+                s if s.id == u8::from(SectionId::Type) && fixup.is_some() => {
+                    let reader = wasmparser::TypeSectionReader::new(wasmparser::BinaryReader::new(
+                        s.data, 0,
+                    ))
+                    .unwrap();
+                    let mut types = wasm_encoder::TypeSection::new();
+                    RoundtripReencoder
+                        .parse_type_section(&mut types, reader)
+                        .unwrap();
+                    types.ty().function([], []);
+                    encoder.section(&types);
+                }
+                s if s.id == u8::from(SectionId::Function) && fixup.is_some() => {
+                    encoder.section(&function_section(Some(s), fixup));
+                }
+                s if s.id == u8::from(SectionId::Code) && shift_globals => {
+                    encoder.section(&code_section(Some(s), snapshot, &mut reencoder, fixup));
+                }
+
+                // For the table section, we update the minimum size of each
+                // defined table to the snapshot's initialized size for that
+                // table.
+                s if s.id == u8::from(SectionId::Table) && module.snapshot_tables() => {
+                    let mut tables = wasm_encoder::TableSection::new();
+                    assert_eq!(module.defined_tables().count(), snapshot.tables.len());
+                    for ((_, table), elements) in module.defined_tables().zip(&snapshot.tables) {
+                        let mut ty = reencoder.table_type(table.ty).unwrap();
+                        ty.minimum = u64::try_from(elements.len()).unwrap();
+                        match &table.init {
+                            wasmparser::TableInit::RefNull => tables.table(ty),
+                            wasmparser::TableInit::Expr(init) => {
+                                let init = reencoder.const_expr(init.clone()).unwrap();
+                                tables.table_with_init(ty, &init)
+                            }
+                        };
+                    }
+                    encoder.section(&tables);
+                }
+                s if s.id == u8::from(SectionId::Table) && shift_globals => {
+                    let reader = wasmparser::TableSectionReader::new(
+                        wasmparser::BinaryReader::new(s.data, 0),
+                    )
+                    .unwrap();
+                    let mut tables = wasm_encoder::TableSection::new();
+                    reencoder.parse_table_section(&mut tables, reader).unwrap();
+                    encoder.section(&tables);
+                }
+
+                // Encode the snapshot's table contents alongside the original
+                // element segments.
+                s if s.id == u8::from(SectionId::Element)
+                    && (module.snapshot_tables() || shift_globals) =>
+                {
+                    encoder.section(&element_section(module, snapshot, Some(s), &mut reencoder));
                 }
 
                 // For the memory section, we update the minimum size of each
@@ -79,35 +217,29 @@ impl Wizer {
                 // Encode the initialized global values from the snapshot,
                 // rather than the original values.
                 s if s.id == u8::from(SectionId::Global) => {
-                    let mut globals = wasm_encoder::GlobalSection::new();
-                    for ((_, glob_ty), val) in module.defined_globals().zip(snapshot.globals.iter())
-                    {
-                        let glob_ty = RoundtripReencoder.global_type(glob_ty).unwrap();
-                        globals.global(
-                            glob_ty,
-                            &match val {
-                                wasmtime::Val::I32(x) => ConstExpr::i32_const(*x),
-                                wasmtime::Val::I64(x) => ConstExpr::i64_const(*x),
-                                wasmtime::Val::F32(x) => {
-                                    ConstExpr::f32_const(wasm_encoder::Ieee32::new(*x))
-                                }
-                                wasmtime::Val::F64(x) => {
-                                    ConstExpr::f64_const(wasm_encoder::Ieee64::new(*x))
-                                }
-                                wasmtime::Val::V128(x) => {
-                                    ConstExpr::v128_const(x.as_u128() as i128)
-                                }
-                                _ => unreachable!(),
-                            },
-                        );
-                    }
-                    encoder.section(&globals);
+                    encoder.section(&global_section(module, snapshot));
                 }
 
                 // Remove exports for the wizer initialization
                 // function and WASI reactor _initialize function,
                 // then perform any requested renames.
                 s if s.id == u8::from(SectionId::Export) => {
+                    let Some(renames) = renames else {
+                        if shift_globals {
+                            let reader = wasmparser::ExportSectionReader::new(
+                                wasmparser::BinaryReader::new(s.data, 0),
+                            )
+                            .unwrap();
+                            let mut exports = wasm_encoder::ExportSection::new();
+                            reencoder
+                                .parse_export_section(&mut exports, reader)
+                                .unwrap();
+                            encoder.section(&exports);
+                        } else {
+                            encoder.section(s);
+                        }
+                        continue;
+                    };
                     let mut exports = wasm_encoder::ExportSection::new();
                     for export in module.exports() {
                         if (export.name == self.init_func && !self.get_keep_init_func())
@@ -129,15 +261,25 @@ impl Wizer {
                             .get(export.name)
                             .map_or(export.name, |f| f.as_str());
 
-                        let kind = RoundtripReencoder.export_kind(export.kind).unwrap();
-                        exports.export(field, kind, export.index);
+                        let kind = reencoder.export_kind(export.kind).unwrap();
+                        let index = match export.kind {
+                            wasmparser::ExternalKind::Global => {
+                                reencoder.global_index(export.index).unwrap()
+                            }
+                            _ => export.index,
+                        };
+                        exports.export(field, kind, index);
                     }
                     encoder.section(&exports);
                 }
 
-                // Skip the `start` function -- it's already been run!
+                // Skip the `start` function -- it's already been run! It is
+                // replaced by the function setting references between GC
+                // objects, if there is one.
                 s if s.id == u8::from(SectionId::Start) => {
-                    continue;
+                    if let Some((_, function_index)) = fixup {
+                        encoder.section(&wasm_encoder::StartSection { function_index });
+                    }
                 }
 
                 s if s.id == u8::from(SectionId::DataCount) => {
@@ -158,12 +300,251 @@ impl Wizer {
             }
         }
 
-        // Make sure that we've added our data section to the module.
+        // Make sure that we've added our sections to the module.
+        for id in missing {
+            add_section(&mut encoder, id, module, snapshot, &mut reencoder, fixup);
+        }
         add_data_section(&mut encoder);
         encoder.finish()
     }
 }
 
+/// A reencoder which makes room for the globals allocating GC objects, which
+/// are inserted before the module's defined globals.
+struct ShiftGlobals {
+    /// The index of the first defined global.
+    first_defined: u32,
+    /// The number of globals inserted before it.
+    shift: u32,
+}
+
+impl Reencode for ShiftGlobals {
+    type Error = Infallible;
+
+    fn global_index(&mut self, global: u32) -> Result<u32, Error<Infallible>> {
+        if global < self.first_defined {
+            Ok(global)
+        } else {
+            Ok(global + self.shift)
+        }
+    }
+}
+
+/// The position of each kind of known section within a module, which differs
+/// from their ids since some sections were added later on.
+fn section_position(id: u8) -> Option<usize> {
+    const ORDER: [SectionId; 13] = [
+        SectionId::Type,
+        SectionId::Import,
+        SectionId::Function,
+        SectionId::Table,
+        SectionId::Memory,
+        SectionId::Tag,
+        SectionId::Global,
+        SectionId::Export,
+        SectionId::Start,
+        SectionId::Element,
+        SectionId::DataCount,
+        SectionId::Code,
+        SectionId::Data,
+    ];
+    ORDER.iter().position(|s| u8::from(*s) == id)
+}
+
+/// Add a section which the original module didn't have.
+fn add_section(
+    encoder: &mut wasm_encoder::Module,
+    id: SectionId,
+    module: &ModuleContext<'_>,
+    snapshot: &Snapshot,
+    reencoder: &mut ShiftGlobals,
+    fixup: Option<(u32, u32)>,
+) {
+    match id {
+        SectionId::Function => encoder.section(&function_section(None, fixup)),
+        SectionId::Global => encoder.section(&global_section(module, snapshot)),
+        SectionId::Start => encoder.section(&wasm_encoder::StartSection {
+            function_index: fixup.unwrap().1,
+        }),
+        SectionId::Element => encoder.section(&element_section(module, snapshot, None, reencoder)),
+        SectionId::Code => encoder.section(&code_section(None, snapshot, reencoder, fixup)),
+        _ => unreachable!(),
+    };
+}
+
+/// Build the global section, with the globals allocating GC objects followed
+/// by the module's defined globals with their snapshotted values.
+fn global_section(module: &ModuleContext<'_>, snapshot: &Snapshot) -> wasm_encoder::GlobalSection {
+    let mut globals = wasm_encoder::GlobalSection::new();
+    for object in &snapshot.objects {
+        let ty = wasm_encoder::GlobalType {
+            val_type: wasm_encoder::ValType::Ref(wasm_encoder::RefType {
+                nullable: false,
+                heap_type: wasm_encoder::HeapType::Concrete(object.ty),
+            }),
+            mutable: false,
+            shared: false,
+        };
+        globals.global(ty, &object.init);
+    }
+    for ((_, glob_ty), init) in module.defined_globals().zip(&snapshot.globals) {
+        let glob_ty = RoundtripReencoder.global_type(glob_ty).unwrap();
+        globals.global(glob_ty, init);
+    }
+    globals
+}
+
+/// Build the function section, appending the function which sets references
+/// between GC objects if there is one.
+fn function_section(
+    original: Option<&wasm_encoder::RawSection<'_>>,
+    fixup: Option<(u32, u32)>,
+) -> wasm_encoder::FunctionSection {
+    let mut functions = wasm_encoder::FunctionSection::new();
+    if let Some(original) = original {
+        let reader =
+            wasmparser::FunctionSectionReader::new(wasmparser::BinaryReader::new(original.data, 0))
+                .unwrap();
+        RoundtripReencoder
+            .parse_function_section(&mut functions, reader)
+            .unwrap();
+    }
+    if let Some((ty, _)) = fixup {
+        functions.function(ty);
+    }
+    functions
+}
+
+/// Build the code section, appending the function which sets references
+/// between GC objects if there is one.
+fn code_section(
+    original: Option<&wasm_encoder::RawSection<'_>>,
+    snapshot: &Snapshot,
+    reencoder: &mut ShiftGlobals,
+    fixup: Option<(u32, u32)>,
+) -> wasm_encoder::CodeSection {
+    let mut code = wasm_encoder::CodeSection::new();
+    if let Some(original) = original {
+        let reader =
+            wasmparser::CodeSectionReader::new(wasmparser::BinaryReader::new(original.data, 0))
+                .unwrap();
+        reencoder.parse_code_section(&mut code, reader).unwrap();
+    }
+    if fixup.is_some() {
+        let object =
+            |index: u32| wasm_encoder::Instruction::GlobalGet(reencoder.first_defined + index);
+        let mut func = wasm_encoder::Function::new([]);
+        for fixup in &snapshot.fixups {
+            func.instruction(&object(fixup.object));
+            match fixup.field {
+                Field::Struct(field_index) => {
+                    func.instruction(&object(fixup.value));
+                    func.instruction(&wasm_encoder::Instruction::StructSet {
+                        struct_type_index: fixup.ty,
+                        field_index,
+                    });
+                }
+                Field::Array(index) => {
+                    func.instruction(&wasm_encoder::Instruction::I32Const(index as i32));
+                    func.instruction(&object(fixup.value));
+                    func.instruction(&wasm_encoder::Instruction::ArraySet(fixup.ty));
+                }
+            }
+        }
+        func.instruction(&wasm_encoder::Instruction::End);
+        code.function(&func);
+    }
+    code
+}
+
+/// Build the element section for a module whose tables were snapshotted.
+///
+/// The original segments, if any, are preserved so that the indices of passive
+/// and declared segments don't change, but active segments are turned into
+/// declared segments since their effect is already part of the snapshot. The
+/// snapshot's table contents are then appended as new active segments.
+fn element_section(
+    module: &ModuleContext<'_>,
+    snapshot: &Snapshot,
+    original: Option<&wasm_encoder::RawSection<'_>>,
+    reencoder: &mut ShiftGlobals,
+) -> wasm_encoder::ElementSection {
+    let mut section = wasm_encoder::ElementSection::new();
+
+    if let Some(original) = original {
+        let reader =
+            wasmparser::ElementSectionReader::new(wasmparser::BinaryReader::new(original.data, 0))
+                .unwrap();
+        for element in reader {
+            let mut element = element.unwrap();
+            if let wasmparser::ElementKind::Active { .. } = element.kind {
+                if module.snapshot_tables() {
+                    element.kind = wasmparser::ElementKind::Declared;
+                }
+            }
+            reencoder.parse_element(&mut section, element).unwrap();
+        }
+    }
+
+    if !module.snapshot_tables() {
+        return section;
+    }
+
+    for ((table_index, table), elements) in module.defined_tables().zip(&snapshot.tables) {
+        let ty = RoundtripReencoder.ref_type(table.ty.element_type).unwrap();
+        let offset = |i: usize| {
+            if table.ty.table64 {
+                ConstExpr::i64_const(i64::try_from(i).unwrap())
+            } else {
+                ConstExpr::i32_const(u32::try_from(i).unwrap() as i32)
+            }
+        };
+        let expr = |elem: &Option<ConstExpr>| match elem {
+            Some(elem) => elem.clone(),
+            None => ConstExpr::ref_null(ty.heap_type),
+        };
+
+        match table.init {
+            // Tables are null-initialized by default, so only runs of non-null
+            // elements need to be encoded.
+            wasmparser::TableInit::RefNull => {
+                let mut i = 0;
+                while i < elements.len() {
+                    if elements[i].is_none() {
+                        i += 1;
+                        continue;
+                    }
+                    let start = i;
+                    while i < elements.len() && elements[i].is_some() {
+                        i += 1;
+                    }
+                    let exprs = elements[start..i].iter().map(expr).collect::<Vec<_>>();
+                    section.active(
+                        Some(table_index),
+                        &offset(start),
+                        wasm_encoder::Elements::Expressions(ty, exprs.into()),
+                    );
+                }
+            }
+
+            // Tables with an initializer expression aren't null-initialized,
+            // so every element is encoded.
+            wasmparser::TableInit::Expr(_) => {
+                if !elements.is_empty() {
+                    let exprs = elements.iter().map(expr).collect::<Vec<_>>();
+                    section.active(
+                        Some(table_index),
+                        &offset(0),
+                        wasm_encoder::Elements::Expressions(ty, exprs.into()),
+                    );
+                }
+            }
+        }
+    }
+
+    section
+}
+
 fn is_name_section(s: &wasm_encoder::RawSection) -> bool {
     s.id == u8::from(SectionId::Custom) && {
         let mut reader = wasmparser::BinaryReader::new(s.data, 0);
@@ -1,8 +1,17 @@
+use crate::info::ModuleContext;
+use anyhow::bail;
 use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator};
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
-use wasmtime::{AsContext, AsContextMut};
+use wasm_encoder::{AbstractHeapType, ConstExpr, HeapType, Instruction};
+use wasmtime::{AnyRef, AsContext, AsContextMut, FieldType, Rooted, StorageType, Val, ValType};
 
-const WASM_PAGE_SIZE: u64 = 65_536;
+/// The size of the chunks of memory that are scanned for non-zero bytes in
+/// parallel.
+///
+/// This is independent of the memory's page size, since memories may use
+/// custom page sizes as small as a single byte.
+const CHUNK_SIZE: u64 = 65_536;
 
 /// The maximum number of data segments that we will emit. Most
 /// engines support more than this, but we want to leave some
@@ -11,38 +20,128 @@ const MAX_DATA_SEGMENTS: usize = 10_000;
 
 /// A "snapshot" of Wasm state from its default value after having been initialized.
 pub struct Snapshot {
-    /// Maps global index to its initialized value.
-    pub globals: Vec<wasmtime::Val>,
+    /// Maps global index to an initializer for its initialized value.
+    pub globals: Vec<ConstExpr>,
 
-    /// A new minimum size for each memory (in units of pages).
+    /// A new minimum size for each memory (in units of the memory's pages).
     pub memory_mins: Vec<u64>,
 
+    /// The initialized memories, in the same order as `memory_mins`.
+    memories: Vec<Memory>,
+
     /// Segments of non-zero memory.
     pub data_segments: Vec<DataSegment>,
+
+    /// The contents of each defined table, if the module's tables are being
+    /// snapshotted.
+    ///
+    /// Each entry is an initializer for the reference in that slot of the
+    /// table, or `None` if the slot is null. The length of each table is its
+    /// new minimum size.
+    pub tables: Vec<Vec<Option<ConstExpr>>>,
+
+    /// The GC objects reachable from globals and tables.
+    ///
+    /// Each object is allocated by a new global, and these globals are
+    /// inserted before the module's defined globals so that the initializers
+    /// of the module's globals can refer to them. References to an object
+    /// refer to its global, which preserves the object's identity.
+    pub objects: Vec<Object>,
+
+    /// References between objects which are set by a new start function,
+    /// once every object has been allocated.
+    ///
+    /// Constant expressions can only refer to globals defined before them, so
+    /// this is how cycles between objects are recreated.
+    pub fixups: Vec<Fixup>,
+}
+
+/// A struct or array which is allocated by a global.
+pub struct Object {
+    /// The index of the object's type.
+    pub ty: u32,
+
+    /// The initializer of the global allocating this object.
+    pub init: ConstExpr,
+}
+
+/// A reference to an object, which is stored in a field of another object
+/// after both are allocated.
+pub struct Fixup {
+    /// The index of the object whose field is set, in `Snapshot::objects`.
+    pub object: u32,
+
+    /// The index of the type of `object`.
+    pub ty: u32,
+
+    /// The field of `object` which is set.
+    pub field: Field,
+
+    /// The index of the object which is stored in the field, in
+    /// `Snapshot::objects`.
+    pub value: u32,
+}
+
+/// A field of a struct or array.
+#[derive(Clone, Copy)]
+pub enum Field {
+    /// The field at the given index of a struct.
+    Struct(u32),
+
+    /// The element at the given index of an array.
+    Array(u32),
+}
+
+impl Snapshot {
+    /// Get the initialized contents of the memory covered by `segment`.
+    pub fn data<'a>(&'a self, segment: &DataSegment, ctx: &'a impl AsContext) -> &'a [u8] {
+        let start = usize::try_from(segment.offset).unwrap();
+        let end = start + usize::try_from(segment.len).unwrap();
+        &self.memories[usize::try_from(segment.memory_index).unwrap()].data(ctx)[start..end]
+    }
+}
+
+/// An initialized memory, which is either owned by the store or shared.
+enum Memory {
+    Unshared(wasmtime::Memory),
+    Shared(wasmtime::SharedMemory),
+}
+
+impl Memory {
+    fn size(&self, ctx: &impl AsContext) -> u64 {
+        match self {
+            Memory::Unshared(memory) => memory.size(ctx),
+            Memory::Shared(memory) => memory.size(),
+        }
+    }
+
+    fn data<'a>(&'a self, ctx: &'a impl AsContext) -> &'a [u8] {
+        match self {
+            Memory::Unshared(memory) => memory.data(ctx),
+            Memory::Shared(memory) => {
+                let data = memory.data();
+                // SAFETY: the initialization function has returned and nothing
+                // else is running in the store, so no other thread can be
+                // concurrently modifying the memory while it is being
+                // snapshotted.
+                unsafe { std::slice::from_raw_parts(data.as_ptr().cast::<u8>(), data.len()) }
+            }
+        }
+    }
 }
 
 /// A data segment initializer for a memory.
 #[derive(Clone, Copy)]
 pub struct DataSegment {
-    /// The index of this data segment's memory.
+    /// The index of this data segment's memory among the module's defined
+    /// memories.
     pub memory_index: u32,
 
-    /// This data segment's initialized memory that it originated from.
-    pub memory: wasmtime::Memory,
-
     /// The offset within the memory that `data` should be copied to.
-    pub offset: u32,
+    pub offset: u64,
 
     /// This segment's length.
-    pub len: u32,
-}
-
-impl DataSegment {
-    pub fn data<'a>(&self, ctx: &'a impl AsContext) -> &'a [u8] {
-        let start = usize::try_from(self.offset).unwrap();
-        let end = start + usize::try_from(self.len).unwrap();
-        &self.memory.data(ctx)[start..end]
-    }
+    pub len: u64,
 }
 
 impl DataSegment {
@@ -50,7 +149,7 @@ impl DataSegment {
     ///
     /// `self` must be in front of `other` and they must not overlap with each
     /// other.
-    fn gap(&self, other: &Self) -> u32 {
+    fn gap(&self, other: &Self) -> u64 {
         debug_assert_eq!(self.memory_index, other.memory_index);
         debug_assert!(self.offset + self.len <= other.offset);
         other.offset - (self.offset + self.len)
@@ -71,28 +170,40 @@ impl DataSegment {
     }
 }
 
-/// Snapshot the given instance's globals, memories, and instances from the Wasm
-/// defaults.
-//
-// TODO: when we support reference types, we will have to snapshot tables.
-pub fn snapshot(ctx: &mut impl AsContextMut, instance: &wasmtime::Instance) -> Snapshot {
+/// Snapshot the given instance's globals, memories, tables, and instances from
+/// the Wasm defaults.
+///
+/// The `instance` must be an instance of the instrumented version of `module`.
+pub fn snapshot(
+    ctx: &mut impl AsContextMut,
+    instance: &wasmtime::Instance,
+    module: &ModuleContext<'_>,
+) -> anyhow::Result<Snapshot> {
     log::debug!("Snapshotting the initialized state");
 
-    let globals = snapshot_globals(&mut *ctx, instance);
-    let (memory_mins, data_segments) = snapshot_memories(&mut *ctx, instance);
+    let mut references = References::new(&mut *ctx, instance, module);
+    let globals = snapshot_globals(&mut *ctx, instance, &mut references)?;
+    let (memories, memory_mins, data_segments) = snapshot_memories(&mut *ctx, instance);
+    let tables = snapshot_tables(&mut *ctx, instance, &mut references)?;
+    let (objects, fixups) = references.finish(&mut *ctx)?;
 
-    Snapshot {
+    Ok(Snapshot {
         globals,
         memory_mins,
+        memories,
         data_segments,
-    }
+        tables,
+        objects,
+        fixups,
+    })
 }
 
 /// Get the initialized values of all globals.
 fn snapshot_globals(
     ctx: &mut impl AsContextMut,
     instance: &wasmtime::Instance,
-) -> Vec<wasmtime::Val> {
+    references: &mut References,
+) -> anyhow::Result<Vec<ConstExpr>> {
     log::debug!("Snapshotting global values");
     let mut globals = vec![];
     let mut index = 0;
@@ -101,12 +212,348 @@ fn snapshot_globals(
         match instance.get_global(&mut *ctx, &name) {
             None => break,
             Some(global) => {
-                globals.push(global.get(&mut *ctx));
+                let val = global.get(&mut *ctx);
+                let location = || format!("global {index}");
+                globals.push(references.const_expr(&mut *ctx, val, &location)?);
                 index += 1;
             }
         }
     }
-    globals
+    Ok(globals)
+}
+
+/// Get the contents of all tables.
+///
+/// Tables are only exported by the instrumentation pass when they need to be
+/// snapshotted, so this returns nothing otherwise.
+fn snapshot_tables(
+    ctx: &mut impl AsContextMut,
+    instance: &wasmtime::Instance,
+    references: &mut References,
+) -> anyhow::Result<Vec<Vec<Option<ConstExpr>>>> {
+    log::debug!("Snapshotting tables");
+
+    let mut tables = vec![];
+    let mut table_index = 0;
+    while let Some(table) = instance.get_table(&mut *ctx, &format!("__wizer_table_{table_index}")) {
+        let size = table.size(&*ctx);
+        let mut elements = Vec::with_capacity(usize::try_from(size).unwrap());
+        for i in 0..size {
+            let elem = table.get(&mut *ctx, i).unwrap();
+            if elem.is_null() {
+                elements.push(None);
+                continue;
+            }
+            let location = || format!("table {table_index}: element {i}");
+            elements.push(Some(references.const_expr(
+                &mut *ctx,
+                elem.into(),
+                &location,
+            )?));
+        }
+        tables.push(elements);
+        table_index += 1;
+    }
+
+    Ok(tables)
+}
+
+/// Serializes the values of globals and table elements into constant
+/// expressions, mapping references back to the module's functions and types.
+struct References {
+    /// Maps each of the module's functions to its index.
+    ///
+    /// Function references don't have an identity in Wasm itself, so they are
+    /// identified by their definition, that is the instance defining them and
+    /// their index within it. This way a reference to a function defined by
+    /// another instance of a component, for example one stored in a table by
+    /// that instance, is found as long as this module imports the function.
+    funcs: HashMap<(usize, usize), u32>,
+
+    /// The module's struct and array types along with their indices.
+    types: Vec<(u32, wasmtime::HeapType)>,
+
+    /// The index of the global allocating the first object.
+    first_object_global: u32,
+
+    /// The objects which have been serialized so far.
+    objects: Vec<Object>,
+
+    /// Maps the raw reference of each serialized object to its index in
+    /// `objects`.
+    object_indices: HashMap<u32, u32>,
+
+    /// The raw references of the objects which are currently being
+    /// serialized, used to detect cycles which can't be recreated.
+    in_progress: HashSet<u32>,
+
+    /// Fields which are set after allocating their objects, along with the
+    /// objects stored in them, which may not have been serialized yet.
+    deferred: Vec<(u32, u32, Field, Rooted<AnyRef>, String)>,
+}
+
+impl References {
+    fn new(
+        ctx: &mut impl AsContextMut,
+        instance: &wasmtime::Instance,
+        module: &ModuleContext<'_>,
+    ) -> Self {
+        // Functions and types are only exported by the instrumentation pass
+        // when references need to be snapshotted.
+        let mut funcs = HashMap::new();
+        let mut index = 0;
+        while let Some(func) = instance.get_func(&mut *ctx, &format!("__wizer_func_{index}")) {
+            funcs.entry(func.definition_key(&*ctx)).or_insert(index);
+            index += 1;
+        }
+
+        let globals = instance
+            .exports(ctx.as_context_mut())
+            .filter_map(|export| {
+                let index = export.name().strip_prefix("__wizer_type_")?.parse().ok()?;
+                Some((index, export.into_global()?))
+            })
+            .collect::<Vec<(u32, wasmtime::Global)>>();
+        let types = globals
+            .into_iter()
+            .map(|(index, global)| {
+                let ty = global.ty(&*ctx);
+                (index, ty.content().unwrap_ref().heap_type().clone())
+            })
+            .collect();
+
+        References {
+            funcs,
+            types,
+            first_object_global: module.imported_globals_len(),
+            objects: Vec::new(),
+            object_indices: HashMap::new(),
+            in_progress: HashSet::new(),
+            deferred: Vec::new(),
+        }
+    }
+
+    /// Build a constant expression which evaluates to `val`.
+    ///
+    /// `location` describes where `val` came from for error messages.
+    fn const_expr(
+        &mut self,
+        ctx: &mut impl AsContextMut,
+        val: Val,
+        location: &dyn Fn() -> String,
+    ) -> anyhow::Result<ConstExpr> {
+        let mut insns = vec![];
+        self.push(ctx, val, &mut insns, location)?;
+        Ok(ConstExpr::extended(insns))
+    }
+
+    /// Push the instructions which produce `val` onto `insns`.
+    fn push(
+        &mut self,
+        ctx: &mut impl AsContextMut,
+        val: Val,
+        insns: &mut Vec<Instruction<'static>>,
+        location: &dyn Fn() -> String,
+    ) -> anyhow::Result<()> {
+        // Null references are encoded with the bottom type of their hierarchy
+        // since it is a subtype of every other type in the hierarchy.
+        let null = |ty| Instruction::RefNull(HeapType::Abstract { shared: false, ty });
+        let insn = match val {
+            Val::I32(x) => Instruction::I32Const(x),
+            Val::I64(x) => Instruction::I64Const(x),
+            Val::F32(x) => Instruction::F32Const(wasm_encoder::Ieee32::new(x)),
+            Val::F64(x) => Instruction::F64Const(wasm_encoder::Ieee64::new(x)),
+            Val::V128(x) => Instruction::V128Const(x.as_u128() as i128),
+            Val::FuncRef(None) => null(AbstractHeapType::NoFunc),
+            Val::ExternRef(None) => null(AbstractHeapType::NoExtern),
+            Val::AnyRef(None) => null(AbstractHeapType::None),
+            Val::ExnRef(None) => null(AbstractHeapType::NoExn),
+            Val::ContRef(None) => null(AbstractHeapType::NoCont),
+            Val::FuncRef(Some(func)) => match self.funcs.get(&func.definition_key(&*ctx)) {
+                Some(index) => Instruction::RefFunc(*index),
+                None => bail!(
+                    "cannot snapshot {}: it is a function which is neither defined in \
+                     nor imported by the module, such as a host function",
+                    location()
+                ),
+            },
+            Val::AnyRef(Some(any)) => match any.as_i31(&*ctx)? {
+                Some(i31) => {
+                    insns.push(Instruction::I32Const(i31.get_i32()));
+                    Instruction::RefI31
+                }
+                None => {
+                    let index = self.object(ctx, any, location)?;
+                    Instruction::GlobalGet(self.first_object_global + index)
+                }
+            },
+            Val::ExternRef(Some(_)) | Val::ExnRef(Some(_)) | Val::ContRef(Some(_)) => bail!(
+                "cannot snapshot {}: it is a non-null reference which is neither a \
+                 function nor a GC object",
+                location()
+            ),
+        };
+        insns.push(insn);
+        Ok(())
+    }
+
+    /// Serialize the struct or array `object`, if it hasn't been already, and
+    /// return its index in `self.objects`.
+    fn object(
+        &mut self,
+        ctx: &mut impl AsContextMut,
+        object: Rooted<AnyRef>,
+        location: &dyn Fn() -> String,
+    ) -> anyhow::Result<u32> {
+        let raw = object.to_raw(&mut *ctx)?;
+        if let Some(index) = self.object_indices.get(&raw) {
+            return Ok(*index);
+        }
+        // Every object reachable through fields which can't be set later must
+        // be allocated first, which is impossible if they form a cycle.
+        if !self.in_progress.insert(raw) {
+            bail!(
+                "cannot snapshot {}: it references a cycle of GC objects through \
+                 immutable or non-nullable fields",
+                location()
+            );
+        }
+
+        let not_found = || {
+            anyhow::anyhow!(
+                "cannot snapshot {}: it references a GC object whose type is not \
+                 defined by the module",
+                location()
+            )
+        };
+
+        let mut insns = vec![];
+        let mut deferred = vec![];
+        let ty = if let Some(object) = object.as_struct(&*ctx)? {
+            let ty = object.ty(&*ctx)?;
+            let index = self
+                .types
+                .iter()
+                .find_map(|(index, t)| {
+                    let t = t.as_concrete_struct()?;
+                    wasmtime::StructType::eq(t, &ty).then_some(*index)
+                })
+                .ok_or_else(not_found)?;
+            for (i, field_ty) in ty.fields().enumerate() {
+                let field = object.field(&mut *ctx, i)?;
+                let i = Field::Struct(u32::try_from(i).unwrap());
+                self.push_field(
+                    ctx,
+                    field,
+                    &field_ty,
+                    i,
+                    &mut insns,
+                    &mut deferred,
+                    location,
+                )?;
+            }
+            insns.push(Instruction::StructNew(index));
+            index
+        } else if let Some(object) = object.as_array(&*ctx)? {
+            let ty = object.ty(&*ctx)?;
+            let index = self
+                .types
+                .iter()
+                .find_map(|(index, t)| {
+                    let t = t.as_concrete_array()?;
+                    wasmtime::ArrayType::eq(t, &ty).then_some(*index)
+                })
+                .ok_or_else(not_found)?;
+            let field_ty = ty.field_type();
+            let len = object.len(&*ctx)?;
+            for i in 0..len {
+                let elem = object.get(&mut *ctx, i)?;
+                let i = Field::Array(i);
+                self.push_field(ctx, elem, &field_ty, i, &mut insns, &mut deferred, location)?;
+            }
+            insns.push(Instruction::ArrayNewFixed {
+                array_type_index: index,
+                array_size: len,
+            });
+            index
+        } else {
+            bail!(
+                "cannot snapshot {}: it references a GC object which is neither a \
+                 struct nor an array",
+                location()
+            );
+        };
+
+        self.in_progress.remove(&raw);
+        let index = u32::try_from(self.objects.len()).unwrap();
+        self.objects.push(Object {
+            ty,
+            init: ConstExpr::extended(insns),
+        });
+        self.object_indices.insert(raw, index);
+        let location = location();
+        self.deferred.extend(
+            deferred
+                .into_iter()
+                .map(|(field, value)| (index, ty, field, value, location.clone())),
+        );
+        Ok(index)
+    }
+
+    /// Push the instructions which produce the initial value of a field of an
+    /// object onto `insns`.
+    ///
+    /// Mutable and nullable fields referring to objects which haven't been
+    /// serialized yet are initialized to null and pushed onto `deferred` to
+    /// be set once every object has been allocated.
+    fn push_field(
+        &mut self,
+        ctx: &mut impl AsContextMut,
+        val: Val,
+        ty: &FieldType,
+        field: Field,
+        insns: &mut Vec<Instruction<'static>>,
+        deferred: &mut Vec<(Field, Rooted<AnyRef>)>,
+        location: &dyn Fn() -> String,
+    ) -> anyhow::Result<()> {
+        let settable = ty.mutability().is_var()
+            && matches!(ty.element_type(), StorageType::ValType(ValType::Ref(r)) if r.is_nullable());
+        if let Val::AnyRef(Some(any)) = &val {
+            if settable && any.as_i31(&*ctx)?.is_none() {
+                let raw = any.to_raw(&mut *ctx)?;
+                match self.object_indices.get(&raw) {
+                    Some(index) => {
+                        insns.push(Instruction::GlobalGet(self.first_object_global + index));
+                    }
+                    None => {
+                        insns.push(Instruction::RefNull(HeapType::Abstract {
+                            shared: false,
+                            ty: AbstractHeapType::None,
+                        }));
+                        deferred.push((field, *any));
+                    }
+                }
+                return Ok(());
+            }
+        }
+        self.push(ctx, val, insns, location)
+    }
+
+    /// Serialize the objects referenced by deferred fields, and return every
+    /// serialized object along with the fields to set after allocating them.
+    fn finish(mut self, ctx: &mut impl AsContextMut) -> anyhow::Result<(Vec<Object>, Vec<Fixup>)> {
+        let mut fixups = vec![];
+        while let Some((object, ty, field, value, location)) = self.deferred.pop() {
+            let value = self.object(ctx, value, &|| location.clone())?;
+            fixups.push(Fixup {
+                object,
+                ty,
+                field,
+                value,
+            });
+        }
+        Ok((self.objects, fixups))
+    }
 }
 
 /// Find the initialized minimum page size of each memory, as well as all
@@ -114,33 +561,37 @@ fn snapshot_globals(
 fn snapshot_memories(
     ctx: &mut impl AsContextMut,
     instance: &wasmtime::Instance,
-) -> (Vec<u64>, Vec<DataSegment>) {
+) -> (Vec<Memory>, Vec<u64>, Vec<DataSegment>) {
     log::debug!("Snapshotting memories");
 
     // Find and record non-zero regions of memory (in parallel).
+    let mut memories = vec![];
     let mut memory_mins = vec![];
     let mut data_segments = vec![];
     let mut memory_index = 0;
     loop {
         let name = format!("__wizer_memory_{memory_index}");
         let memory = match instance.get_memory(&mut *ctx, &name) {
-            None => break,
-            Some(memory) => memory,
+            Some(memory) => Memory::Unshared(memory),
+            None => match instance.get_shared_memory(&mut *ctx, &name) {
+                Some(memory) => Memory::Shared(memory),
+                None => break,
+            },
         };
         memory_mins.push(memory.size(&*ctx));
 
-        let num_wasm_pages = memory.size(&*ctx);
-
         let memory_data = memory.data(&*ctx);
-
-        // Consider each Wasm page in parallel. Create data segments for each
-        // region of non-zero memory.
-        data_segments.par_extend((0..num_wasm_pages).into_par_iter().flat_map(|i| {
-            let page_end = ((i + 1) * WASM_PAGE_SIZE) as usize;
-            let mut start = (i * WASM_PAGE_SIZE) as usize;
+        let memory_len = u64::try_from(memory_data.len()).unwrap();
+        let num_chunks = memory_len.div_ceil(CHUNK_SIZE);
+
+        // Consider each chunk of memory in parallel. Create data segments for
+        // each region of non-zero memory.
+        data_segments.par_extend((0..num_chunks).into_par_iter().flat_map(|i| {
+            let chunk_end = usize::try_from(((i + 1) * CHUNK_SIZE).min(memory_len)).unwrap();
+            let mut start = usize::try_from(i * CHUNK_SIZE).unwrap();
             let mut segments = vec![];
-            while start < page_end {
-                let nonzero = match memory_data[start..page_end]
+            while start < chunk_end {
+                let nonzero = match memory_data[start..chunk_end]
                     .iter()
                     .position(|byte| *byte != 0)
                 {
@@ -148,40 +599,40 @@ fn snapshot_memories(
                     Some(i) => i,
                 };
                 start += nonzero;
-                let end = memory_data[start..page_end]
+                let end = memory_data[start..chunk_end]
                     .iter()
                     .position(|byte| *byte == 0)
-                    .map_or(page_end, |zero| start + zero);
+                    .map_or(chunk_end, |zero| start + zero);
                 segments.push(DataSegment {
                     memory_index,
-                    memory,
-                    offset: u32::try_from(start).unwrap(),
-                    len: u32::try_from(end - start).unwrap(),
+                    offset: u64::try_from(start).unwrap(),
+                    len: u64::try_from(end - start).unwrap(),
                 });
                 start = end;
             }
             segments
         }));
 
+        memories.push(memory);
         memory_index += 1;
     }
 
     if data_segments.is_empty() {
-        return (memory_mins, data_segments);
+        return (memories, memory_mins, data_segments);
     }
 
     // Sort data segments to enforce determinism in the face of the
     // parallelism above.
     data_segments.sort_by_key(|s| (s.memory_index, s.offset));
 
-    // Merge any contiguous segments (caused by spanning a Wasm page boundary,
+    // Merge any contiguous segments (caused by spanning a chunk boundary,
     // and therefore created in separate logical threads above) or pages that
     // are within four bytes of each other. Four because this is the minimum
     // overhead of defining a new active data segment: one for the memory index
     // LEB, two for the memory offset init expression (one for the `i32.const`
     // opcode and another for the constant immediate LEB), and finally one for
     // the data length LEB).
-    const MIN_ACTIVE_SEGMENT_OVERHEAD: u32 = 4;
+    const MIN_ACTIVE_SEGMENT_OVERHEAD: u64 = 4;
     let mut merged_data_segments = Vec::with_capacity(data_segments.len());
     merged_data_segments.push(data_segments[0]);
     for b in &data_segments[1..] {
@@ -209,7 +660,7 @@ fn snapshot_memories(
 
     remove_excess_segments(&mut merged_data_segments);
 
-    (memory_mins, merged_data_segments)
+    (memories, memory_mins, merged_data_segments)
 }
 
 /// Engines apply a limit on how many segments a module may contain, and Wizer
@@ -225,9 +676,7 @@ fn remove_excess_segments(merged_data_segments: &mut Vec<DataSegment>) {
 
     #[derive(Clone, Copy, PartialEq, Eq)]
     struct GapIndex {
-        gap: u32,
-        // Use a `u32` instead of `usize` to fit `GapIndex` within a word on
-        // 64-bit systems, using less memory.
+        gap: u64,
         index: u32,
     }
 
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Engine, Store};
+use wasmtime_wizer::Wizer;
+use wat::parse_str as wat_to_wasm;
+
+fn wizen(wat: &str) -> Result<Vec<u8>> {
+    let _ = env_logger::try_init();
+    let wasm = wat_to_wasm(wat)?;
+    let mut store = Store::new(&Engine::default(), ());
+    Wizer::new().run_component(&mut store, &wasm, |store, component| {
+        Linker::new(store.engine()).instantiate(store, component)
+    })
+}
+
+fn run_wat(expected: i32, wat: &str) -> Result<()> {
+    let wasm = wizen(wat)?;
+    log::debug!(
+        "=== Wizened Wasm ==========================================================\n\
+      {}\n\
+      ===========================================================================",
+        wasmprinter::print_bytes(&wasm).unwrap()
+    );
+
+    let engine = Engine::default();
+    let component = Component::new(&engine, &wasm).context("Wasm test case failed to compile")?;
+    anyhow::ensure!(
+        component
+            .get_export_index(None, "wizer-initialize")
+            .is_none(),
+        "the initialization function should no longer be exported"
+    );
+
+    let mut store = Store::new(&engine, ());
+    let instance = Linker::new(&engine).instantiate(&mut store, &component)?;
+    let run = instance.get_typed_func::<(), (i32,)>(&mut store, "run")?;
+    let (actual,) = run.call(&mut store, ())?;
+    run.post_return(&mut store)?;
+    anyhow::ensure!(
+        expected == actual,
+        "expected `{expected}`, found `{actual}`",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn basic_component() -> Result<()> {
+    run_wat(
+        49,
+        r#"
+(component
+  (core module $m
+    (memory 1)
+    (global $g (mut i32) i32.const 0)
+    (func (export "init")
+      i32.const 42
+      global.set $g
+      i32.const 100
+      i32.const 7
+      i32.store)
+    (func (export "run") (result i32)
+      global.get $g
+      i32.const 100
+      i32.load
+      i32.add))
+  (core instance $i (instantiate $m))
+  (func (export "run") (result s32) (canon lift (core func $i "run")))
+  (func (export "wizer-initialize") (canon lift (core func $i "init")))
+)
+        "#,
+    )
+}
+
+#[test]
+fn component_with_multiple_modules() -> Result<()> {
+    run_wat(
+        3,
+        r#"
+(component
+  (core module $a
+    (memory (export "memory") 1)
+    (func (export "init")
+      i32.const 0
+      i32.const 1
+      i32.store))
+  (core module $b
+    (import "a" "memory" (memory 1))
+    (global $g (mut i32) i32.const 0)
+    (func (export "init")
+      i32.const 2
+      global.set $g)
+    (func (export "run") (result i32)
+      i32.const 0
+      i32.load
+      global.get $g
+      i32.add))
+  (core instance $a (instantiate $a))
+  (core instance $b (instantiate $b (with "a" (instance $a))))
+  (core module $init
+    (import "a" "init" (func $a))
+    (import "b" "init" (func $b))
+    (func (export "init")
+      call $a
+      call $b))
+  (core instance $init (instantiate $init
+    (with "a" (instance $a))
+    (with "b" (instance $b))))
+  (func (export "run") (result s32) (canon lift (core func $b "run")))
+  (func (export "wizer-initialize") (canon lift (core func $init "init")))
+)
+        "#,
+    )
+}
+
+#[test]
+fn table_of_functions_from_another_instance() -> Result<()> {
+    run_wat(
+        10,
+        r#"
+(component
+  (core module $lib
+    (func $ten (export "ten") (result i32)
+      i32.const 10)
+    (func (export "get-ten") (result funcref)
+      ref.func $ten))
+  (core module $main
+    (import "lib" "ten" (func (result i32)))
+    (import "lib" "get-ten" (func $get_ten (result funcref)))
+    (type $t (func (result i32)))
+    (table 1 funcref)
+    (func (export "init")
+      i32.const 0
+      call $get_ten
+      table.set)
+    (func (export "run") (result i32)
+      i32.const 0
+      call_indirect (type $t)))
+  (core instance $lib (instantiate $lib))
+  (core instance $main (instantiate $main (with "lib" (instance $lib))))
+  (func (export "run") (result s32) (canon lift (core func $main "run")))
+  (func (export "wizer-initialize") (canon lift (core func $main "init")))
+)
+        "#,
+    )
+}
+
+#[test]
+fn module_instantiated_twice() -> Result<()> {
+    let result = wizen(
+        r#"
+(component
+  (core module $m
+    (global $g (mut i32) i32.const 0)
+    (func (export "init")))
+  (core instance $i (instantiate $m))
+  (core instance $j (instantiate $m))
+  (func (export "wizer-initialize") (canon lift (core func $i "init")))
+)
+        "#,
+    );
+    anyhow::ensure!(
+        result.is_err(),
+        "expected an error when wizening, but didn't get one"
+    );
+    Ok(())
+}
+
+#[test]
+fn init_func_exported_before_other_items() -> Result<()> {
+    // Removing the initialization function's export renumbers `$run` and the
+    // function exported by `$inst`.
+    run_wat(
+        42,
+        r#"
+(component
+  (core module $m
+    (global $g (mut i32) i32.const 0)
+    (func (export "init")
+      i32.const 42
+      global.set $g)
+    (func (export "run") (result i32)
+      global.get $g))
+  (core instance $i (instantiate $m))
+  (func $init (canon lift (core func $i "init")))
+  (export "wizer-initialize" (func $init))
+  (func $run (result s32) (canon lift (core func $i "run")))
+  (instance $inst (export "run" (func $run)))
+  (export "inst" (instance $inst))
+  (export "run" (func $run))
+)
+        "#,
+    )
+}
+
+#[test]
+fn init_func_export_used_within_component() -> Result<()> {
+    let result = wizen(
+        r#"
+(component
+  (core module $m
+    (func (export "init")))
+  (core instance $i (instantiate $m))
+  (func $init (canon lift (core func $i "init")))
+  (export $exported "wizer-initialize" (func $init))
+  (export "init-again" (func $exported))
+)
+        "#,
+    );
+    let err = result.unwrap_err().to_string();
+    anyhow::ensure!(
+        err.contains("export is used within the component"),
+        "unexpected error: {err}"
+    );
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "component-model")]
+mod component;
 mod make_linker;
 mod preloads;
 mod tests;
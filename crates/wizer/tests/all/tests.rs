@@ -20,6 +20,11 @@ fn store() -> Result<Store<p1::WasiP1Ctx>> {
     let mut wasi = WasiCtxBuilder::new();
     let mut config = Config::new();
     config.relaxed_simd_deterministic(true);
+    config.wasm_memory64(true);
+    config.wasm_custom_page_sizes(true);
+    config.wasm_threads(true);
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
     let engine = Engine::new(&config)?;
     Ok(Store::new(&engine, wasi.build_p1()))
 }
@@ -172,6 +177,71 @@ fn multi_memory() -> Result<()> {
 "#,
     )
 }
+#[test]
+fn memory64() -> Result<()> {
+    run_wat(
+        &[],
+        42,
+        r#"
+(module
+ (memory i64 1)
+ (func (export "wizer.initialize")
+       i64.const 1
+       memory.grow
+       drop
+       i64.const 70000
+       i32.const 42
+       i32.store)
+ (func (export "run") (result i32)
+       i64.const 70000
+       i32.load))
+"#,
+    )
+}
+
+#[test]
+fn custom_page_size() -> Result<()> {
+    run_wat(
+        &[],
+        109,
+        r#"
+(module
+ (memory 2 (pagesize 1))
+ (func (export "wizer.initialize")
+       i32.const 100
+       memory.grow
+       drop
+       i32.const 98
+       i32.const 7
+       i32.store)
+ (func (export "run") (result i32)
+       i32.const 98
+       i32.load
+       memory.size
+       i32.add))
+"#,
+    )
+}
+
+#[test]
+fn shared_memory() -> Result<()> {
+    run_wat(
+        &[],
+        13,
+        r#"
+(module
+ (memory 1 2 shared)
+ (func (export "wizer.initialize")
+       i32.const 8
+       i32.const 13
+       i32.atomic.store)
+ (func (export "run") (result i32)
+       i32.const 8
+       i32.atomic.load))
+"#,
+    )
+}
+
 #[test]
 fn reject_imported_memory() -> Result<()> {
     fails_wizening(
@@ -203,70 +273,203 @@ fn reject_imported_table() -> Result<()> {
 }
 
 #[test]
-fn reject_table_copy() -> Result<()> {
-    let result = run_wat(
+fn table_copy() -> Result<()> {
+    run_wat(
         &[],
-        42,
+        2,
         r#"
 (module
+  (type $sig (func (result i32)))
   (table 3 funcref)
 
-  (func $f (result i32) (i32.const 0))
-  (func $g (result i32) (i32.const 0))
-  (func $h (result i32) (i32.const 0))
+  (func $f (result i32) (i32.const 1))
+  (func $g (result i32) (i32.const 2))
+  (func $h (result i32) (i32.const 3))
 
-  (func (export "main")
+  (func (export "wizer.initialize")
     i32.const 0
     i32.const 1
     i32.const 1
     table.copy)
 
+  (func (export "run") (result i32)
+    i32.const 0
+    call_indirect (type $sig))
+
   (elem (i32.const 0) $f $g $h)
 )
 "#,
-    );
-    assert!(result.is_err());
+    )
+}
 
-    let err = result.unwrap_err();
-    assert!(
-        err.to_string()
-            .contains("unsupported `table.copy` instruction")
-    );
+#[test]
+fn table_get_set() -> Result<()> {
+    run_wat(
+        &[],
+        3,
+        r#"
+(module
+  (type $sig (func (result i32)))
+  (table 3 funcref)
 
-    Ok(())
+  (func $f (result i32) (i32.const 1))
+  (func $g (result i32) (i32.const 2))
+  (func $h (result i32) (i32.const 3))
+
+  (func (export "wizer.initialize")
+    i32.const 0
+    i32.const 2
+    table.get
+    table.set
+    i32.const 2
+    ref.null func
+    table.set)
+
+  (func (export "run") (result i32)
+    i32.const 0
+    call_indirect (type $sig))
+
+  (elem (i32.const 0) $f $g $h)
+)
+"#,
+    )
 }
 
 #[test]
-fn reject_table_get_set() -> Result<()> {
-    let wat = r#"
+fn table_grow() -> Result<()> {
+    run_wat(
+        &[],
+        42,
+        r#"
+(module
+  (type $sig (func (result i32)))
+  (elem declare func $f)
+  (func $f (result i32) (i32.const 42))
+  (table 0 funcref)
+
+  (func (export "wizer.initialize")
+    ref.null func
+    i32.const 2
+    table.grow
+    drop
+    ref.func $f
+    i32.const 1
+    table.grow
+    drop)
+
+  (func (export "run") (result i32)
+    (if (i32.ne (table.size) (i32.const 3))
+      (then unreachable))
+    i32.const 2
+    call_indirect (type $sig))
+)
+"#,
+    )
+}
+
+#[test]
+fn table_init() -> Result<()> {
+    run_wat(
+        &[],
+        7,
+        r#"
 (module
+  (type $sig (func (result i32)))
   (table 3 funcref)
 
-  (func $f (result i32) (i32.const 0))
-  (func $g (result i32) (i32.const 0))
-  (func $h (result i32) (i32.const 0))
+  (func $f (result i32) (i32.const 1))
+  (func $g (result i32) (i32.const 2))
+  (func $h (result i32) (i32.const 3))
 
-  (func (export "main")
+  (elem $elem func $f $g $h)
+
+  (func (export "wizer.initialize")
+    i32.const 0
+    i32.const 2
+    i32.const 1
+    table.init $elem)
+
+  (func (export "run") (result i32)
+    ;; The passive segment is still usable after initialization.
+    i32.const 1
+    i32.const 1
+    i32.const 1
+    table.init $elem
     i32.const 0
+    call_indirect (type $sig)
     i32.const 1
+    call_indirect (type $sig)
+    i32.mul
+    i32.const 2
     table.get
+    ref.is_null
+    i32.add)
+)
+"#,
+    )
+}
+
+#[test]
+fn table_with_imported_func() -> Result<()> {
+    run_wat(
+        &[],
+        42,
+        r#"
+(module
+  (import "x" "f" (func $import))
+  (type $sig (func (result i32)))
+  (table 2 funcref)
+  (elem declare func $import $f)
+
+  (func $f (result i32) (i32.const 42))
+
+  (func (export "wizer.initialize")
+    i32.const 0
+    ref.func $import
+    table.set
+    i32.const 1
+    ref.func $f
     table.set)
 
-  (elem (i32.const 0) $f $g $h)
+  (func (export "run") (result i32)
+    i32.const 0
+    table.get
+    ref.is_null
+    (if (then unreachable))
+    i32.const 1
+    call_indirect (type $sig))
 )
-"#;
+"#,
+    )
+}
 
-    let _ = env_logger::try_init();
-    let wizer = Wizer::new();
-    let wasm = wat_to_wasm(wat)?;
-    let result = wizen_and_run_wasm(&[], 42, &wasm, wizer);
+#[test]
+fn reject_table_with_host_func() -> Result<()> {
+    let wasm = wat_to_wasm(
+        r#"
+(module
+  (import "" "get" (func $get (result funcref)))
+  (table 1 funcref)
+  (func (export "wizer.initialize")
+    i32.const 0
+    call $get
+    table.set)
+)
+"#,
+    )?;
 
-    assert!(result.is_err());
+    let mut store = store()?;
+    let result = get_wizer().run(&mut store, &wasm, |store, module| {
+        let host = wasmtime::Func::wrap(&mut *store, || 1_i32);
+        let mut linker = Linker::new(store.engine());
+        linker.func_wrap("", "get", move || Some(host))?;
+        linker.instantiate(store, module)
+    });
 
     let err = result.unwrap_err();
     assert!(
         err.to_string()
-            .contains("unsupported `table.set` instruction"),
+            .contains("neither defined in nor imported by the module"),
         "bad error: {err}",
     );
 
@@ -274,63 +477,208 @@ fn reject_table_get_set() -> Result<()> {
 }
 
 #[test]
-fn reject_table_get_set_with_reference_types_enabled() -> Result<()> {
-    let result = run_wat(
+fn global_with_func_ref() -> Result<()> {
+    run_wat(
+        &[],
+        2,
+        r#"
+(module
+  (type $sig (func (result i32)))
+  (global $g (mut (ref null $sig)) (ref.null $sig))
+
+  (func $f (type $sig) (i32.const 1))
+  (func $h (type $sig) (i32.const 2))
+  (elem declare func $f $h)
+
+  (func (export "wizer.initialize")
+    (global.set $g (ref.func $h)))
+
+  (func (export "run") (result i32)
+    (call_ref $sig (global.get $g)))
+)
+"#,
+    )
+}
+
+#[test]
+fn global_with_i31_ref() -> Result<()> {
+    run_wat(
+        &[],
+        -5,
+        r#"
+(module
+  (global $g (mut i31ref) (ref.null i31))
+  (func (export "wizer.initialize")
+    (global.set $g (ref.i31 (i32.const -5))))
+  (func (export "run") (result i32)
+    (i31.get_s (global.get $g)))
+)
+"#,
+    )
+}
+
+#[test]
+fn global_with_gc_objects() -> Result<()> {
+    run_wat(
+        &[],
+        17,
+        r#"
+(module
+  (type $point (struct (field i32) (field (mut i64))))
+  (type $list (struct (field (ref null $point)) (field (ref null $list))))
+  (type $bytes (array (mut i8)))
+
+  (global $list (mut (ref null $list)) (ref.null $list))
+  (global $bytes (mut (ref null $bytes)) (ref.null $bytes))
+
+  (func (export "wizer.initialize")
+    (global.set $list
+      (struct.new $list
+        (struct.new $point (i32.const 1) (i64.const 2))
+        (struct.new $list (ref.null $point) (ref.null $list))))
+    (global.set $bytes (array.new $bytes (i32.const 3) (i32.const 4))))
+
+  (func (export "run") (result i32)
+    ;; 1 + 2 + 4 * 3 + 2
+    (struct.get $point 0 (struct.get $list 0 (global.get $list)))
+    (i32.wrap_i64 (struct.get $point 1 (struct.get $list 0 (global.get $list))))
+    i32.add
+    (i32.mul
+      (array.len (global.get $bytes))
+      (array.get_u $bytes (global.get $bytes) (i32.const 3)))
+    i32.add
+    (ref.is_null (struct.get $list 0 (struct.get $list 1 (global.get $list))))
+    (ref.is_null (struct.get $list 1 (struct.get $list 1 (global.get $list))))
+    i32.add
+    i32.add)
+)
+"#,
+    )
+}
+
+#[test]
+fn table_with_gc_objects() -> Result<()> {
+    run_wat(
         &[],
         42,
         r#"
-      (module
-        (table 3 funcref)
+(module
+  (type $box (struct (field i32)))
+  (table 2 anyref)
 
-        (func $f (result i32) (i32.const 0))
-        (func $g (result i32) (i32.const 0))
-        (func $h (result i32) (i32.const 0))
+  (func (export "wizer.initialize")
+    (table.set (i32.const 1) (struct.new $box (i32.const 42))))
 
-        (func (export "main")
-          i32.const 0
-          i32.const 1
-          table.get
-          table.set)
+  (func (export "run") (result i32)
+    (struct.get $box 0 (ref.cast (ref $box) (table.get (i32.const 1)))))
+)
+"#,
+    )
+}
 
-        (elem (i32.const 0) $f $g $h)
-      )"#,
-    );
-    assert!(result.is_err());
+#[test]
+fn shared_gc_object() -> Result<()> {
+    run_wat(
+        &[],
+        5,
+        r#"
+(module
+  (type $box (struct (field (mut i32))))
+  (global $a (mut (ref null $box)) (ref.null $box))
+  (global $b (mut (ref null $box)) (ref.null $box))
+  (table 1 anyref)
 
-    let err = result.unwrap_err();
-    assert!(
-        err.to_string()
-            .contains("unsupported `table.set` instruction"),
-    );
+  (func (export "wizer.initialize")
+    (global.set $a (struct.new $box (i32.const 1)))
+    (global.set $b (global.get $a))
+    (table.set (i32.const 0) (global.get $a)))
 
-    Ok(())
+  (func (export "run") (result i32)
+    ;; The object's identity is preserved, so writes through one reference
+    ;; are visible through the others.
+    (struct.set $box 0 (global.get $a) (i32.const 3))
+    (struct.get $box 0 (global.get $b))
+    (ref.eq (global.get $a) (global.get $b))
+    i32.add
+    (ref.eq (global.get $a) (ref.cast (ref $box) (table.get (i32.const 0))))
+    i32.add)
+)
+"#,
+    )
 }
 
 #[test]
-fn reject_table_grow_with_reference_types_enabled() -> anyhow::Result<()> {
-    let wat = r#"
-      (module
-        (elem declare func $f)
-        (func $f)
-        (table 0 funcref)
-        (func (export "_initialize") (result i32)
-            ref.func $f
-            i32.const 1
-            table.grow
-        )
-      )"#;
+fn cyclic_gc_objects() -> Result<()> {
+    run_wat(
+        &[],
+        6,
+        r#"
+(module
+  (type $node (struct (field i32) (field (mut (ref null $node)))))
+  (type $self (array (mut anyref)))
+  (global $ring (mut (ref null $node)) (ref.null $node))
+  (global $self (mut (ref null $self)) (ref.null $self))
 
-    let _ = env_logger::try_init();
-    let wizer = Wizer::new();
-    let wasm = wat_to_wasm(wat)?;
-    let result = wizen_and_run_wasm(&[], 42, &wasm, wizer);
+  (func (export "wizer.initialize")
+    (local $a (ref $node))
+    (local $b (ref $node))
+    (local.set $a (struct.new $node (i32.const 1) (ref.null $node)))
+    (local.set $b (struct.new $node (i32.const 2) (local.get $a)))
+    (struct.set $node 1 (local.get $a) (local.get $b))
+    (global.set $ring (local.get $a))
+    (global.set $self (array.new $self (ref.null any) (i32.const 2)))
+    (array.set $self (global.get $self) (i32.const 1) (global.get $self)))
 
-    assert!(result.is_err());
+  (func (export "run") (result i32)
+    (local $n (ref null $node))
+    ;; 1 + 2 + 1 + 2
+    (local.set $n (global.get $ring))
+    (struct.get $node 0 (local.get $n))
+    (local.set $n (struct.get $node 1 (local.get $n)))
+    (struct.get $node 0 (local.get $n))
+    i32.add
+    (local.set $n (struct.get $node 1 (local.get $n)))
+    (struct.get $node 0 (local.get $n))
+    i32.add
+    (local.set $n (struct.get $node 1 (local.get $n)))
+    (struct.get $node 0 (local.get $n))
+    i32.add
+    (ref.eq (local.get $n) (struct.get $node 1 (global.get $ring)))
+    (ref.eq
+      (global.get $self)
+      (ref.cast (ref $self) (array.get $self (global.get $self) (i32.const 1))))
+    i32.and
+    i32.const 1
+    i32.sub
+    i32.add)
+)
+"#,
+    )
+}
 
-    let err = result.unwrap_err();
+#[test]
+fn reject_gc_cycle_through_non_nullable_fields() -> Result<()> {
+    let wasm = wat_to_wasm(
+        r#"
+(module
+  (type $box (struct (field (mut (ref any)))))
+  (global $a (mut (ref null $box)) (ref.null $box))
+  (func (export "wizer.initialize")
+    (local $a (ref $box))
+    (local.set $a (struct.new $box (ref.i31 (i32.const 0))))
+    (struct.set $box 0 (local.get $a) (struct.new $box (local.get $a)))
+    (global.set $a (local.get $a)))
+)
+"#,
+    )?;
+
+    let err = get_wizer()
+        .run(&mut store()?, &wasm, instantiate)
+        .unwrap_err();
     assert!(
         err.to_string()
-            .contains("unsupported `table.grow` instruction")
+            .contains("cycle of GC objects through immutable or non-nullable fields"),
+        "bad error: {err}",
     );
 
     Ok(())
@@ -360,40 +708,6 @@ fn indirect_call_with_reference_types() -> anyhow::Result<()> {
     wizen_and_run_wasm(&[], 42, &wasm, wizer)
 }
 
-#[test]
-fn reject_table_init() -> Result<()> {
-    let result = run_wat(
-        &[],
-        42,
-        r#"
-(module
-  (table 3 funcref)
-
-  (func $f (result i32) (i32.const 0))
-  (func $g (result i32) (i32.const 0))
-  (func $h (result i32) (i32.const 0))
-
-  (elem $elem func $f $g $h)
-
-  (func (export "main")
-    i32.const 0
-    i32.const 0
-    i32.const 3
-    table.init $elem)
-)
-"#,
-    );
-    assert!(result.is_err());
-
-    let err = result.unwrap_err();
-    assert!(
-        err.to_string()
-            .contains("unsupported `table.init` instruction")
-    );
-
-    Ok(())
-}
-
 #[test]
 fn reject_elem_drop() -> Result<()> {
     let result = run_wat(
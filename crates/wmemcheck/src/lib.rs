@@ -7,14 +7,21 @@
 
 use std::cmp::*;
 use std::collections::HashMap;
+use std::fmt;
 
 /// Memory checker for wasm guest.
 pub struct Wmemcheck {
     metadata: Vec<MemState>,
     mallocs: HashMap<usize, usize>,
+    backtraces: HashMap<usize, Box<[usize]>>,
     pub stack_pointer: usize,
     max_stack_size: usize,
     pub flag: bool,
+    allocator_depth: usize,
+    total_allocs: usize,
+    total_frees: usize,
+    total_bytes_allocated: usize,
+    shadow: bool,
 }
 
 /// Error types for memory checker.
@@ -28,12 +35,16 @@ pub enum AccessError {
     InvalidWrite { addr: usize, len: usize },
     /// Free of non-malloc'd pointer.
     InvalidFree { addr: usize },
+    /// Realloc of non-malloc'd pointer.
+    InvalidRealloc { addr: usize },
     /// Access out of bounds of heap or stack.
     OutOfBounds { addr: usize, len: usize },
+    /// Read of bytes that were never written, in shadow mode.
+    UninitializedRead { addr: usize, len: usize },
 }
 
 /// Memory state for memory checker.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MemState {
     /// Unallocated memory.
     Unallocated,
@@ -43,6 +54,107 @@ pub enum MemState {
     ValidToReadWrite,
 }
 
+/// A block of memory that was allocated and never freed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leak {
+    /// Address of the leaked block.
+    pub addr: usize,
+    /// Size of the leaked block in bytes.
+    pub len: usize,
+    /// Wasm backtrace of the allocation, if one was recorded.
+    pub backtrace: Option<String>,
+}
+
+/// Summary of heap usage and leaked blocks, printed like Valgrind's leak
+/// check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeakSummary {
+    /// Blocks still allocated, sorted by address.
+    pub leaks: Vec<Leak>,
+    /// Number of allocations performed.
+    pub total_allocs: usize,
+    /// Number of frees performed.
+    pub total_frees: usize,
+    /// Total number of bytes allocated.
+    pub total_bytes_allocated: usize,
+}
+
+impl LeakSummary {
+    /// Number of bytes still allocated.
+    pub fn leaked_bytes(&self) -> usize {
+        self.leaks.iter().map(|l| l.len).sum()
+    }
+}
+
+impl fmt::Display for LeakSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const PREFIX: &str = "==wmemcheck==";
+
+        writeln!(f, "{PREFIX} HEAP SUMMARY:")?;
+        writeln!(
+            f,
+            "{PREFIX}     in use at exit: {} bytes in {} blocks",
+            self.leaked_bytes(),
+            self.leaks.len()
+        )?;
+        writeln!(
+            f,
+            "{PREFIX}   total heap usage: {} allocs, {} frees, {} bytes allocated",
+            self.total_allocs, self.total_frees, self.total_bytes_allocated
+        )?;
+
+        if self.leaks.is_empty() {
+            writeln!(f, "{PREFIX}")?;
+            return writeln!(
+                f,
+                "{PREFIX} All heap blocks were freed -- no leaks are possible"
+            );
+        }
+
+        // Group leaked blocks with the same allocation stack into one loss
+        // record, and list records from smallest to largest like Valgrind.
+        let mut records: Vec<(Option<&str>, usize, usize)> = Vec::new();
+        for leak in &self.leaks {
+            let backtrace = leak.backtrace.as_deref();
+            match records.iter_mut().find(|r| r.0 == backtrace) {
+                Some(record) => {
+                    record.1 += leak.len;
+                    record.2 += 1;
+                }
+                None => records.push((backtrace, leak.len, 1)),
+            }
+        }
+        records.sort_by_key(|r| (r.1, r.2));
+
+        let num_records = records.len();
+        for (i, (backtrace, bytes, blocks)) in records.into_iter().enumerate() {
+            writeln!(f, "{PREFIX}")?;
+            writeln!(
+                f,
+                "{PREFIX} {bytes} bytes in {blocks} blocks are definitely lost in loss record {} of {num_records}",
+                i + 1
+            )?;
+            match backtrace {
+                Some(backtrace) => {
+                    for line in backtrace.lines() {
+                        writeln!(f, "{PREFIX}    {line}")?;
+                    }
+                }
+                None => writeln!(f, "{PREFIX}    <no backtrace recorded>")?,
+            }
+        }
+
+        writeln!(f, "{PREFIX}")?;
+        writeln!(f, "{PREFIX} LEAK SUMMARY:")?;
+        writeln!(
+            f,
+            "{PREFIX}    definitely lost: {} bytes in {} blocks",
+            self.leaked_bytes(),
+            self.leaks.len()
+        )
+    }
+}
+
 impl Wmemcheck {
     /// Initializes memory checker instance.
     pub fn new(mem_size: usize) -> Wmemcheck {
@@ -51,9 +163,45 @@ impl Wmemcheck {
         Wmemcheck {
             metadata,
             mallocs,
+            backtraces: HashMap::new(),
             stack_pointer: 0,
             max_stack_size: 0,
             flag: true,
+            allocator_depth: 0,
+            total_allocs: 0,
+            total_frees: 0,
+            total_bytes_allocated: 0,
+            shadow: false,
+        }
+    }
+
+    /// Initializes a memory checker instance in shadow mode.
+    ///
+    /// Rather than only checking accesses to malloc'd memory and the stack,
+    /// shadow mode tracks whether every byte of memory is defined, similar to
+    /// MemorySanitizer. All memory starts out defined, since Wasm memory is
+    /// zero-initialized; malloc, free, and new stack frames poison memory, and
+    /// writes define it again. Reads of poisoned bytes return
+    /// `UninitializedRead`, which the caller propagates to the loaded value
+    /// rather than reporting right away.
+    pub fn new_shadow(mem_size: usize) -> Wmemcheck {
+        let mut wmemcheck = Wmemcheck::new(0);
+        wmemcheck.metadata = vec![MemState::ValidToReadWrite; mem_size];
+        wmemcheck.shadow = true;
+        wmemcheck
+    }
+
+    /// Whether this memory checker is in shadow mode.
+    pub fn is_shadow(&self) -> bool {
+        self.shadow
+    }
+
+    /// The state that freed memory is left in.
+    fn freed_state(&self) -> MemState {
+        if self.shadow {
+            MemState::ValidToWrite
+        } else {
+            MemState::Unallocated
         }
     }
 
@@ -62,29 +210,125 @@ impl Wmemcheck {
         if !self.is_in_bounds_heap(addr, len) {
             return Err(AccessError::OutOfBounds { addr, len });
         }
-        for i in addr..addr + len {
-            match self.metadata[i] {
-                MemState::ValidToWrite => {
-                    return Err(AccessError::DoubleMalloc { addr, len });
-                }
-                MemState::ValidToReadWrite => {
-                    return Err(AccessError::DoubleMalloc { addr, len });
+        if self.shadow {
+            // In shadow mode all memory is accessible, so only the start of
+            // existing allocations can be checked.
+            if self.mallocs.contains_key(&addr) {
+                return Err(AccessError::DoubleMalloc { addr, len });
+            }
+        } else {
+            for i in addr..addr + len {
+                match self.metadata[i] {
+                    MemState::ValidToWrite => {
+                        return Err(AccessError::DoubleMalloc { addr, len });
+                    }
+                    MemState::ValidToReadWrite => {
+                        return Err(AccessError::DoubleMalloc { addr, len });
+                    }
+                    _ => {}
                 }
-                _ => {}
             }
         }
         for i in addr..addr + len {
             self.metadata[i] = MemState::ValidToWrite;
         }
         self.mallocs.insert(addr, len);
+        self.total_allocs += 1;
+        self.total_bytes_allocated += len;
+        Ok(())
+    }
+
+    /// Updates memory checker memory state metadata when calloc is called.
+    ///
+    /// Same as `malloc`, except that the allocated memory is defined since
+    /// calloc zeroes it.
+    pub fn calloc(&mut self, addr: usize, len: usize) -> Result<(), AccessError> {
+        self.malloc(addr, len)?;
+        for i in addr..addr + len {
+            self.metadata[i] = MemState::ValidToReadWrite;
+        }
+        Ok(())
+    }
+
+    /// Updates memory checker memory state metadata when realloc is called.
+    ///
+    /// `old_addr` is the pointer passed to realloc, and `addr` is the pointer
+    /// it returned. The first `min(old_len, len)` bytes keep their
+    /// definedness, since realloc copies them to the new block.
+    pub fn realloc(&mut self, addr: usize, old_addr: usize, len: usize) -> Result<(), AccessError> {
+        if old_addr == 0 {
+            // `realloc(NULL, len)` behaves like `malloc(len)`.
+            return self.malloc(addr, len);
+        }
+        if addr == 0 {
+            // A null return either means that allocation failed and the old
+            // block is untouched, or that `realloc(p, 0)` freed it.
+            if len == 0 {
+                return self.free(old_addr);
+            }
+            return Ok(());
+        }
+        let Some(&old_len) = self.mallocs.get(&old_addr) else {
+            return Err(AccessError::InvalidRealloc { addr: old_addr });
+        };
+        if !self.is_in_bounds_heap(addr, len) {
+            return Err(AccessError::OutOfBounds { addr, len });
+        }
+
+        let copied = self.metadata[old_addr..old_addr + min(old_len, len)].to_vec();
+        let backtrace = self.backtraces.remove(&old_addr);
+        self.mallocs.remove(&old_addr);
+        let freed = self.freed_state();
+        for i in old_addr..old_addr + old_len {
+            self.metadata[i] = freed;
+        }
+
+        if let Err(e) = self.malloc(addr, len) {
+            // Restore the old block so that later frees of it don't report
+            // spurious errors.
+            self.metadata[old_addr..old_addr + copied.len()].clone_from_slice(&copied);
+            for i in old_addr + copied.len()..old_addr + old_len {
+                self.metadata[i] = MemState::ValidToWrite;
+            }
+            self.mallocs.insert(old_addr, old_len);
+            if let Some(backtrace) = backtrace {
+                self.backtraces.insert(old_addr, backtrace);
+            }
+            return Err(e);
+        }
+        self.metadata[addr..addr + copied.len()].clone_from_slice(&copied);
+
+        // Account for realloc as a free of the old block followed by an
+        // allocation of the new one.
+        self.total_frees += 1;
         Ok(())
     }
 
+    /// Records the program counters of the Wasm backtrace of the allocation at
+    /// `addr`, to be symbolized and shown if it is leaked.
+    pub fn set_backtrace(&mut self, addr: usize, backtrace: Box<[usize]>) {
+        if self.mallocs.contains_key(&addr) {
+            self.backtraces.insert(addr, backtrace);
+        }
+    }
+
     /// Updates memory checker memory state metadata when a load occurs.
     pub fn read(&mut self, addr: usize, len: usize) -> Result<(), AccessError> {
         if !self.flag {
             return Ok(());
         }
+        if self.shadow {
+            if addr + len > self.metadata.len() {
+                return Err(AccessError::OutOfBounds { addr, len });
+            }
+            if self.metadata[addr..addr + len]
+                .iter()
+                .any(|s| *s != MemState::ValidToReadWrite)
+            {
+                return Err(AccessError::UninitializedRead { addr, len });
+            }
+            return Ok(());
+        }
         if !(self.is_in_bounds_stack(addr, len) || self.is_in_bounds_heap(addr, len)) {
             return Err(AccessError::OutOfBounds { addr, len });
         }
@@ -107,6 +351,13 @@ impl Wmemcheck {
         if !self.flag {
             return Ok(());
         }
+        if self.shadow {
+            if addr + len > self.metadata.len() {
+                return Err(AccessError::OutOfBounds { addr, len });
+            }
+            self.define(addr, len);
+            return Ok(());
+        }
         if !(self.is_in_bounds_stack(addr, len) || self.is_in_bounds_heap(addr, len)) {
             return Err(AccessError::OutOfBounds { addr, len });
         }
@@ -133,12 +384,45 @@ impl Wmemcheck {
             }
         }
         self.mallocs.remove(&addr);
+        self.backtraces.remove(&addr);
+        let freed = self.freed_state();
         for i in addr..addr + len {
-            self.metadata[i] = MemState::Unallocated;
+            self.metadata[i] = freed;
         }
+        self.total_frees += 1;
         Ok(())
     }
 
+    /// Whether any allocation has been recorded.
+    pub fn has_allocated(&self) -> bool {
+        self.total_allocs > 0
+    }
+
+    /// Returns a summary of heap usage and of all blocks that are still
+    /// allocated, using `symbolize` to format the program counters recorded
+    /// with `set_backtrace`.
+    pub fn leak_summary(
+        &self,
+        mut symbolize: impl FnMut(&[usize]) -> Option<String>,
+    ) -> LeakSummary {
+        let mut leaks = self
+            .mallocs
+            .iter()
+            .map(|(&addr, &len)| Leak {
+                addr,
+                len,
+                backtrace: self.backtraces.get(&addr).and_then(|pcs| symbolize(pcs)),
+            })
+            .collect::<Vec<_>>();
+        leaks.sort_by_key(|l| l.addr);
+        LeakSummary {
+            leaks,
+            total_allocs: self.total_allocs,
+            total_frees: self.total_frees,
+            total_bytes_allocated: self.total_bytes_allocated,
+        }
+    }
+
     fn is_in_bounds_heap(&self, addr: usize, len: usize) -> bool {
         self.max_stack_size <= addr && addr + len <= self.metadata.len()
     }
@@ -149,6 +433,18 @@ impl Wmemcheck {
 
     /// Updates memory checker metadata when stack pointer is updated.
     pub fn update_stack_pointer(&mut self, new_sp: usize) -> Result<(), AccessError> {
+        if self.shadow {
+            // The stack grows down, so moving the stack pointer down allocates
+            // a new, uninitialized frame.
+            if new_sp < self.stack_pointer {
+                let end = min(self.stack_pointer, self.metadata.len());
+                for i in new_sp..end {
+                    self.metadata[i] = MemState::ValidToWrite;
+                }
+            }
+            self.stack_pointer = new_sp;
+            return Ok(());
+        }
         if new_sp > self.max_stack_size {
             return Err(AccessError::OutOfBounds {
                 addr: self.stack_pointer,
@@ -177,9 +473,56 @@ impl Wmemcheck {
         self.flag = false;
     }
 
+    /// Called on entry to an allocator function such as malloc or free.
+    ///
+    /// Turns memory checking off while the allocator manipulates its own
+    /// metadata.
+    pub fn enter_allocator(&mut self) {
+        self.allocator_depth += 1;
+        self.memcheck_off();
+    }
+
+    /// Called on exit from an allocator function.
+    ///
+    /// Returns whether this is the outermost allocator call, in which case the
+    /// caller should record the allocation or free. Allocators commonly call
+    /// each other (for example, realloc calling malloc and free), and only the
+    /// outermost call reflects what the program requested.
+    pub fn exit_allocator(&mut self) -> bool {
+        self.allocator_depth = self.allocator_depth.saturating_sub(1);
+        if self.allocator_depth == 0 {
+            self.memcheck_on();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns how many allocator calls are currently active.
+    pub fn allocator_depth(&self) -> usize {
+        self.allocator_depth
+    }
+
+    /// Restores the number of active allocator calls to `depth` after calls
+    /// entered since then were unwound without returning, for example by a
+    /// trap, turning memory checking back on if none remain.
+    pub fn unwind_allocator(&mut self, depth: usize) {
+        self.allocator_depth = depth;
+        if depth == 0 {
+            self.memcheck_on();
+        } else {
+            self.memcheck_off();
+        }
+    }
+
     /// Initializes stack and stack pointer in memory checker metadata.
     pub fn set_stack_size(&mut self, stack_size: usize) {
         self.max_stack_size = stack_size + 1;
+        if self.shadow {
+            // Frames are poisoned as the stack pointer moves down.
+            self.stack_pointer = stack_size;
+            return;
+        }
         // TODO: temporary solution to initialize the entire stack
         // while keeping stack tracing plumbing in place
         self.stack_pointer = stack_size;
@@ -188,9 +531,70 @@ impl Wmemcheck {
 
     /// Updates memory checker metadata size when memory.grow is called.
     pub fn update_mem_size(&mut self, num_bytes: usize) {
-        let to_append = vec![MemState::Unallocated; num_bytes];
+        let state = if self.shadow {
+            MemState::ValidToReadWrite
+        } else {
+            MemState::Unallocated
+        };
+        let to_append = vec![state; num_bytes];
         self.metadata.extend(to_append);
     }
+
+    /// Size of the tracked memory, in bytes.
+    pub fn mem_size(&self) -> usize {
+        self.metadata.len()
+    }
+
+    /// Marks memory as defined, for example when it is written by
+    /// `memory.fill`, `memory.init`, or the host. Only has an effect in shadow
+    /// mode.
+    pub fn define(&mut self, addr: usize, len: usize) {
+        if !self.shadow {
+            return;
+        }
+        let end = min(addr.saturating_add(len), self.metadata.len());
+        for i in min(addr, end)..end {
+            self.metadata[i] = MemState::ValidToReadWrite;
+        }
+    }
+
+    /// Whether all `len` bytes at `addr` are defined. Always true outside of
+    /// shadow mode.
+    pub fn is_defined(&self, addr: usize, len: usize) -> bool {
+        if !self.shadow {
+            return true;
+        }
+        let end = min(addr.saturating_add(len), self.metadata.len());
+        self.metadata[min(addr, end)..end]
+            .iter()
+            .all(|s| *s == MemState::ValidToReadWrite)
+    }
+
+    /// Marks memory as undefined, for example when a Wasm store writes a value
+    /// that was computed from uninitialized memory. Only has an effect in
+    /// shadow mode.
+    pub fn poison(&mut self, addr: usize, len: usize) {
+        if !self.shadow {
+            return;
+        }
+        let end = min(addr.saturating_add(len), self.metadata.len());
+        for i in min(addr, end)..end {
+            self.metadata[i] = MemState::ValidToWrite;
+        }
+    }
+
+    /// Copies the definedness of `len` bytes from `src` to `dst`, when memory
+    /// is copied with `memory.copy`. Only has an effect in shadow mode.
+    pub fn copy(&mut self, dst: usize, src: usize, len: usize) {
+        if !self.shadow {
+            return;
+        }
+        let size = self.metadata.len();
+        if src.saturating_add(len) > size || dst.saturating_add(len) > size {
+            return;
+        }
+        self.metadata.copy_within(src..src + len, dst);
+    }
 }
 
 #[test]
@@ -385,3 +789,188 @@ fn from_test_program() {
     assert!(wmemcheck_state.write(70832, 1).is_ok());
     assert!(wmemcheck_state.read(1138, 1).is_ok());
 }
+
+#[test]
+fn calloc_is_defined() {
+    let mut wmemcheck_state = Wmemcheck::new(640 * 1024);
+
+    assert!(wmemcheck_state.calloc(0x1000, 32).is_ok());
+    assert!(wmemcheck_state.read(0x1000, 32).is_ok());
+    assert!(wmemcheck_state.free(0x1000).is_ok());
+}
+
+#[test]
+fn realloc_preserves_definedness() {
+    let mut wmemcheck_state = Wmemcheck::new(640 * 1024);
+
+    assert!(wmemcheck_state.malloc(0x1000, 8).is_ok());
+    assert!(wmemcheck_state.write(0x1000, 4).is_ok());
+    assert!(wmemcheck_state.realloc(0x2000, 0x1000, 16).is_ok());
+    assert_eq!(wmemcheck_state.mallocs, HashMap::from([(0x2000, 16)]));
+    assert!(wmemcheck_state.read(0x2000, 4).is_ok());
+    assert_eq!(
+        wmemcheck_state.read(0x2004, 4),
+        Err(AccessError::InvalidRead {
+            addr: 0x2004,
+            len: 4
+        })
+    );
+    assert_eq!(
+        wmemcheck_state.write(0x1000, 4),
+        Err(AccessError::InvalidWrite {
+            addr: 0x1000,
+            len: 4
+        })
+    );
+
+    // Growing in place.
+    assert!(wmemcheck_state.realloc(0x2000, 0x2000, 32).is_ok());
+    assert!(wmemcheck_state.read(0x2000, 4).is_ok());
+    assert_eq!(wmemcheck_state.mallocs, HashMap::from([(0x2000, 32)]));
+
+    // Null pointers.
+    assert!(wmemcheck_state.realloc(0x3000, 0, 8).is_ok());
+    assert!(wmemcheck_state.realloc(0, 0x3000, 0).is_ok());
+    assert_eq!(wmemcheck_state.mallocs, HashMap::from([(0x2000, 32)]));
+
+    assert_eq!(
+        wmemcheck_state.realloc(0x4000, 0x5000, 8),
+        Err(AccessError::InvalidRealloc { addr: 0x5000 })
+    );
+}
+
+#[test]
+fn nested_allocator_calls() {
+    let mut wmemcheck_state = Wmemcheck::new(640 * 1024);
+
+    wmemcheck_state.enter_allocator();
+    wmemcheck_state.enter_allocator();
+    assert!(!wmemcheck_state.flag);
+    assert!(!wmemcheck_state.exit_allocator());
+    assert!(!wmemcheck_state.flag);
+    assert!(wmemcheck_state.exit_allocator());
+    assert!(wmemcheck_state.flag);
+}
+
+#[test]
+fn leak_summary() {
+    let mut wmemcheck_state = Wmemcheck::new(640 * 1024);
+
+    assert!(wmemcheck_state.malloc(0x1000, 32).is_ok());
+    wmemcheck_state.set_backtrace(0x1000, Box::new([0x10]));
+    assert!(wmemcheck_state.malloc(0x2000, 16).is_ok());
+    wmemcheck_state.set_backtrace(0x2000, Box::new([0x10]));
+    assert!(wmemcheck_state.malloc(0x3000, 8).is_ok());
+    assert!(wmemcheck_state.free(0x3000).is_ok());
+
+    let symbolize = |pcs: &[usize]| Some(format!("0: {:#x} - main", pcs[0]));
+    let summary = wmemcheck_state.leak_summary(symbolize);
+    assert_eq!(summary.total_allocs, 3);
+    assert_eq!(summary.total_frees, 1);
+    assert_eq!(summary.total_bytes_allocated, 56);
+    assert_eq!(summary.leaked_bytes(), 48);
+    assert_eq!(
+        summary.leaks,
+        [
+            Leak {
+                addr: 0x1000,
+                len: 32,
+                backtrace: Some("0: 0x10 - main".to_string()),
+            },
+            Leak {
+                addr: 0x2000,
+                len: 16,
+                backtrace: Some("0: 0x10 - main".to_string()),
+            },
+        ]
+    );
+
+    let report = summary.to_string();
+    assert!(report.contains("in use at exit: 48 bytes in 2 blocks"));
+    assert!(report.contains("3 allocs, 1 frees, 56 bytes allocated"));
+    assert!(report.contains("48 bytes in 2 blocks are definitely lost in loss record 1 of 1"));
+    assert!(report.contains("==wmemcheck==    0: 0x10 - main"));
+
+    assert!(wmemcheck_state.free(0x1000).is_ok());
+    assert!(wmemcheck_state.free(0x2000).is_ok());
+    assert!(
+        wmemcheck_state
+            .leak_summary(symbolize)
+            .to_string()
+            .contains("All heap blocks were freed -- no leaks are possible")
+    );
+}
+
+#[test]
+fn shadow_heap() {
+    let mut wmemcheck_state = Wmemcheck::new_shadow(640 * 1024);
+    wmemcheck_state.set_stack_size(1024);
+
+    // Memory outside of allocations is defined.
+    assert!(wmemcheck_state.read(0x8000, 4).is_ok());
+
+    assert!(wmemcheck_state.malloc(0x1000, 32).is_ok());
+    assert_eq!(
+        wmemcheck_state.read(0x1000, 4),
+        Err(AccessError::UninitializedRead {
+            addr: 0x1000,
+            len: 4
+        })
+    );
+    assert!(wmemcheck_state.write(0x1000, 4).is_ok());
+    assert!(wmemcheck_state.read(0x1000, 4).is_ok());
+
+    wmemcheck_state.copy(0x2000, 0x1000, 8);
+    assert!(wmemcheck_state.read(0x2000, 4).is_ok());
+    assert!(wmemcheck_state.read(0x2004, 4).is_err());
+
+    wmemcheck_state.define(0x1004, 4);
+    assert!(wmemcheck_state.read(0x1000, 8).is_ok());
+
+    assert!(wmemcheck_state.free(0x1000).is_ok());
+    assert!(wmemcheck_state.read(0x1000, 4).is_err());
+    assert_eq!(
+        wmemcheck_state.read(640 * 1024, 1),
+        Err(AccessError::OutOfBounds {
+            addr: 640 * 1024,
+            len: 1
+        })
+    );
+}
+
+#[test]
+fn shadow_stack() {
+    let mut wmemcheck_state = Wmemcheck::new_shadow(640 * 1024);
+    wmemcheck_state.set_stack_size(1024);
+    assert!(wmemcheck_state.read(1000, 4).is_ok());
+
+    // Allocate a 32-byte frame.
+    assert!(wmemcheck_state.update_stack_pointer(1024 - 32).is_ok());
+    assert!(wmemcheck_state.read(1000, 4).is_err());
+    assert!(wmemcheck_state.write(1000, 4).is_ok());
+    assert!(wmemcheck_state.read(1000, 4).is_ok());
+    assert!(wmemcheck_state.read(1004, 4).is_err());
+    assert!(wmemcheck_state.update_stack_pointer(1024).is_ok());
+}
+
+#[test]
+fn shadow_poison() {
+    let mut wmemcheck_state = Wmemcheck::new_shadow(64);
+    assert_eq!(wmemcheck_state.mem_size(), 64);
+
+    wmemcheck_state.poison(16, 8);
+    assert!(wmemcheck_state.read(16, 4).is_err());
+    wmemcheck_state.define(16, 4);
+    assert!(wmemcheck_state.read(16, 4).is_ok());
+    assert!(wmemcheck_state.read(20, 4).is_err());
+
+    // Growing memory adds defined, zeroed bytes.
+    wmemcheck_state.update_mem_size(64);
+    assert_eq!(wmemcheck_state.mem_size(), 128);
+    assert!(wmemcheck_state.read(64, 64).is_ok());
+
+    // Non-shadow memory checkers don't track definedness this way.
+    let mut wmemcheck_state = Wmemcheck::new(64);
+    wmemcheck_state.poison(16, 8);
+    assert!(wmemcheck_state.malloc(16, 8).is_ok());
+}
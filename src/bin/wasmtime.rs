@@ -86,6 +86,10 @@ enum Subcommand {
     #[cfg(feature = "completion")]
     Completion(CompletionCommand),
 
+    /// Pretty-prints a core dump generated by Wasmtime
+    #[cfg(feature = "coredump")]
+    Coredump(wasmtime_cli::commands::CoredumpCommand),
+
     /// Inspect `*.cwasm` files output from Wasmtime
     #[cfg(feature = "objdump")]
     Objdump(wasmtime_cli::commands::ObjdumpCommand),
@@ -127,6 +131,9 @@ impl Wasmtime {
             #[cfg(feature = "completion")]
             Subcommand::Completion(c) => c.execute(),
 
+            #[cfg(feature = "coredump")]
+            Subcommand::Coredump(c) => c.execute(),
+
             #[cfg(feature = "objdump")]
             Subcommand::Objdump(c) => c.execute(),
 
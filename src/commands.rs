@@ -35,6 +35,11 @@ mod settings;
 #[cfg(feature = "cranelift")]
 pub use self::settings::*;
 
+#[cfg(feature = "coredump")]
+mod coredump;
+#[cfg(feature = "coredump")]
+pub use self::coredump::*;
+
 #[cfg(feature = "objdump")]
 mod objdump;
 #[cfg(feature = "objdump")]
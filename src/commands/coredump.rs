@@ -0,0 +1,250 @@
+//! Implementation of the `wasmtime coredump` CLI command.
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use wasmparser::{
+    BinaryReader, CoreDumpInstancesSection, CoreDumpModulesSection, CoreDumpStackSection,
+    CoreDumpValue, KnownCustom, Parser as WasmParser, Payload,
+};
+
+/// Pretty-prints a core dump generated with `wasmtime run -D coredump=...`.
+#[derive(Parser)]
+pub struct CoredumpCommand {
+    /// The path to the core dump file.
+    #[arg(value_name = "FILE")]
+    coredump: PathBuf,
+}
+
+/// A component instance described in the `wasmtime-components` section.
+struct ComponentInstance<'a> {
+    name: Option<&'a str>,
+    instances: Vec<u32>,
+    handles: (u32, u32, u32),
+    children: Vec<ComponentInstance<'a>>,
+}
+
+impl<'a> ComponentInstance<'a> {
+    fn name(&self) -> &'a str {
+        self.name.unwrap_or("<component>")
+    }
+
+    /// Writes this component instance and those nested within it, labeled by
+    /// their path from the root such as `0.1`.
+    fn render(&self, out: &mut String, label: &str, indent: usize) -> std::fmt::Result {
+        writeln!(out, "{:indent$}{label}: {}", "", self.name())?;
+        let core = self
+            .instances
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        writeln!(out, "{:indent$}  core instances: [{}]", "", core.join(", "))?;
+        let (own, borrow, other) = self.handles;
+        writeln!(
+            out,
+            "{:indent$}  handles: {own} own, {borrow} borrow, {other} other",
+            ""
+        )?;
+        for (i, child) in self.children.iter().enumerate() {
+            child.render(out, &format!("{label}.{i}"), indent + 2)?;
+        }
+        Ok(())
+    }
+
+    /// Finds the component instance which created the core instance
+    /// `instance`, returning its label and name.
+    fn find(&self, label: String, instance: u32) -> Option<(String, &'a str)> {
+        if self.instances.contains(&instance) {
+            return Some((label, self.name()));
+        }
+        self.children
+            .iter()
+            .enumerate()
+            .find_map(|(i, child)| child.find(format!("{label}.{i}"), instance))
+    }
+}
+
+/// A component-level call described in the `wasmtime-components` section.
+struct ComponentCall<'a> {
+    import: bool,
+    instance: u32,
+    name: &'a str,
+}
+
+impl CoredumpCommand {
+    /// Executes the command.
+    pub fn execute(self) -> Result<()> {
+        let bytes = std::fs::read(&self.coredump)
+            .with_context(|| format!("failed to read core dump: {}", self.coredump.display()))?;
+        print!("{}", render(&bytes)?);
+        Ok(())
+    }
+}
+
+fn render(bytes: &[u8]) -> Result<String> {
+    let mut name = None;
+    let mut modules = None;
+    let mut instances = None;
+    let mut stack = None;
+    let mut components = None;
+
+    for payload in WasmParser::new(0).parse_all(bytes) {
+        let Payload::CustomSection(section) = payload? else {
+            continue;
+        };
+        match section.as_known() {
+            KnownCustom::CoreDump(dump) => name = Some(dump.name),
+            KnownCustom::CoreDumpModules(s) => modules = Some(s),
+            KnownCustom::CoreDumpInstances(s) => instances = Some(s),
+            KnownCustom::CoreDumpStack(s) => stack = Some(s),
+            _ if section.name() == "wasmtime-components" => {
+                components = Some(
+                    read_components(section.data())
+                        .context("failed to parse `wasmtime-components` section")?,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let Some(name) = name else {
+        bail!("input is not a core dump: no `core` custom section found");
+    };
+
+    let mut out = String::new();
+    writeln!(out, "core dump of `{name}`")?;
+    let modules = modules.unwrap_or(CoreDumpModulesSection { modules: vec![] });
+    let instances = instances.unwrap_or(CoreDumpInstancesSection { instances: vec![] });
+    let (components, calls) = components.unwrap_or_default();
+
+    writeln!(out, "modules:")?;
+    for (i, module) in modules.modules.iter().enumerate() {
+        writeln!(out, "  {i}: {module}")?;
+    }
+
+    writeln!(out, "instances:")?;
+    for (i, instance) in instances.instances.iter().enumerate() {
+        let module = modules
+            .modules
+            .get(instance.module_index as usize)
+            .copied()
+            .unwrap_or("<unknown>");
+        writeln!(out, "  {i}: instance of module {module}")?;
+    }
+
+    if !components.is_empty() {
+        writeln!(out, "component instances:")?;
+        for (i, component) in components.iter().enumerate() {
+            component.render(&mut out, &i.to_string(), 2)?;
+        }
+    }
+
+    if !calls.is_empty() {
+        writeln!(out, "component calls:")?;
+        for call in calls.iter() {
+            let kind = if call.import { "import" } else { "export" };
+            let component = components
+                .get(call.instance as usize)
+                .map(|c| c.name())
+                .unwrap_or("<component>");
+            writeln!(
+                out,
+                "  {kind} `{}` of component instance {} ({component})",
+                call.name, call.instance
+            )?;
+        }
+    }
+
+    if let Some(CoreDumpStackSection { name, frames }) = stack {
+        writeln!(out, "stack `{name}`:")?;
+        for (i, frame) in frames.iter().enumerate() {
+            let component = components
+                .iter()
+                .enumerate()
+                .find_map(|(i, c)| c.find(i.to_string(), frame.instanceidx))
+                .map(|(label, name)| format!(" in component instance {label} ({name})"))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "  {i}: instance {} func {} @ {:#x}{component}",
+                frame.instanceidx, frame.funcidx, frame.codeoffset
+            )?;
+            if !frame.locals.is_empty() {
+                writeln!(out, "    locals: [{}]", values(&frame.locals))?;
+            }
+            if !frame.stack.is_empty() {
+                writeln!(out, "    stack: [{}]", values(&frame.stack))?;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn values(values: &[CoreDumpValue]) -> String {
+    values
+        .iter()
+        .map(|v| match v {
+            CoreDumpValue::Missing => "?".to_string(),
+            CoreDumpValue::I32(x) => format!("i32 {x}"),
+            CoreDumpValue::I64(x) => format!("i64 {x}"),
+            CoreDumpValue::F32(x) => format!("f32 {}", f32::from_bits(x.bits())),
+            CoreDumpValue::F64(x) => format!("f64 {}", f64::from_bits(x.bits())),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parses the `wasmtime-components` section written by
+/// `wasmtime::WasmCoreDump::serialize`.
+fn read_components(data: &[u8]) -> Result<(Vec<ComponentInstance<'_>>, Vec<ComponentCall<'_>>)> {
+    let mut reader = BinaryReader::new(data, 0);
+    let components = read_component_instances(&mut reader)?;
+
+    let mut calls = Vec::new();
+    for _ in 0..reader.read_var_u32()? {
+        let import = match reader.read_u8()? {
+            0x00 => false,
+            0x01 => true,
+            b => bail!("invalid component call kind {b:#x}"),
+        };
+        calls.push(ComponentCall {
+            import,
+            instance: reader.read_var_u32()?,
+            name: reader.read_string()?,
+        });
+    }
+
+    Ok((components, calls))
+}
+
+fn read_component_instances<'a>(
+    reader: &mut BinaryReader<'a>,
+) -> Result<Vec<ComponentInstance<'a>>> {
+    let mut components = Vec::new();
+    for _ in 0..reader.read_var_u32()? {
+        let name = match reader.read_u8()? {
+            0x00 => None,
+            0x01 => Some(reader.read_string()?),
+            b => bail!("invalid component name flag {b:#x}"),
+        };
+        let mut instances = Vec::new();
+        for _ in 0..reader.read_var_u32()? {
+            instances.push(reader.read_var_u32()?);
+        }
+        let handles = (
+            reader.read_var_u32()?,
+            reader.read_var_u32()?,
+            reader.read_var_u32()?,
+        );
+        let children = read_component_instances(reader)?;
+        components.push(ComponentInstance {
+            name,
+            instances,
+            handles,
+            children,
+        });
+    }
+    Ok(components)
+}
@@ -16,6 +16,11 @@ use wasi_common::sync::{Dir, TcpListener, WasiCtxBuilder, ambient_authority};
 use wasmtime::{Engine, Func, Module, Store, StoreLimits, Val, ValType};
 use wasmtime_wasi::{WasiCtxView, WasiView};
 
+#[cfg(feature = "debug-adapter")]
+mod debug_adapter;
+#[cfg(feature = "profiling")]
+mod sampling;
+
 #[cfg(feature = "wasi-config")]
 use wasmtime_wasi_config::{WasiConfig, WasiConfigVariables};
 #[cfg(feature = "wasi-http")]
@@ -23,7 +28,7 @@ use wasmtime_wasi_http::{
     DEFAULT_OUTGOING_BODY_BUFFER_CHUNKS, DEFAULT_OUTGOING_BODY_CHUNK_SIZE, WasiHttpCtx,
 };
 #[cfg(feature = "wasi-keyvalue")]
-use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx, WasiKeyValueCtxBuilder};
+use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx};
 #[cfg(feature = "wasi-nn")]
 use wasmtime_wasi_nn::wit::WasiNnView;
 #[cfg(feature = "wasi-threads")]
@@ -63,6 +68,25 @@ pub struct RunCommand {
     #[arg(long)]
     pub argv0: Option<String>,
 
+    /// Write the code coverage of the WebAssembly being run to `PATH` in the
+    /// LCOV format once it finishes.
+    ///
+    /// Lines are mapped through the DWARF debug information of the wasm when
+    /// it has any. Otherwise the wasm file itself is reported as the source
+    /// file with the offsets of instructions in it as line numbers.
+    #[arg(long, value_name = "PATH")]
+    pub coverage: Option<PathBuf>,
+
+    /// Serve the Debug Adapter Protocol on the given address, waiting for a
+    /// debugger such as VS Code to connect before running the module.
+    ///
+    /// Breakpoints on source lines are mapped through the module's DWARF debug
+    /// information, and breakpoints on functions or raw wasm offsets can be
+    /// used for modules without it. Only core wasm modules are supported.
+    #[cfg(feature = "debug-adapter")]
+    #[arg(long, value_name = "ADDR")]
+    pub debug_adapter: Option<std::net::SocketAddr>,
+
     /// The WebAssembly module to run and arguments to pass to it.
     ///
     /// Arguments passed to the wasm module will be configured as WASI CLI
@@ -82,7 +106,7 @@ pub struct Preloads {
         value_name = "NAME=MODULE_PATH",
         value_parser = parse_preloads,
     )]
-    modules: Vec<(String, PathBuf)>,
+    pub(crate) modules: Vec<(String, PathBuf)>,
 }
 
 /// Dispatch between either a core or component linker.
@@ -113,26 +137,94 @@ impl RunCommand {
             .load_module(&engine, self.module_and_args[0].as_ref())?;
         let (mut store, mut linker) = self.new_store_and_linker(&engine, &main)?;
 
-        self.instantiate_and_run(&engine, &mut linker, &main, &mut store)?;
+        #[cfg(feature = "debug-adapter")]
+        let debug_adapter = match (self.debug_adapter, &main) {
+            (Some(addr), RunTarget::Core(module)) => {
+                if self.run.common.wasm.timeout.is_some()
+                    || matches!(
+                        self.run.profile,
+                        Some(Profile::Guest { .. } | Profile::Sample { .. })
+                    )
+                {
+                    bail!(
+                        "--debug-adapter cannot be combined with --timeout or \
+                         --profile=guest/sample since they all use epoch interruption"
+                    );
+                }
+                Some(debug_adapter::DebugAdapter::start(
+                    addr,
+                    module,
+                    self.module_and_args[0].as_ref(),
+                    &mut store,
+                )?)
+            }
+            #[cfg(feature = "component-model")]
+            (Some(_), RunTarget::Component(_)) => {
+                bail!("--debug-adapter is only supported for core wasm modules")
+            }
+            (None, _) => None,
+        };
+
+        let result = self.instantiate_and_run(&engine, &mut linker, &main, &mut store);
+
+        #[cfg(feature = "debug-adapter")]
+        if let Some(debug_adapter) = debug_adapter {
+            debug_adapter.finish(result.is_ok());
+        }
+
+        // Like Valgrind, report leaks even if the program exited with an
+        // error.
+        #[cfg(feature = "wmemcheck")]
+        if self.run.common.wasm.wmemcheck == Some(true) {
+            for summary in store.wmemcheck_leak_summaries() {
+                eprint!("{summary}");
+            }
+        }
+
+        // Coverage is still written if the wasm traps or exits with an error
+        // since that's when it's most interesting.
+        if let Some(path) = &self.coverage {
+            if let Err(e) = self.write_coverage(&main, path) {
+                eprintln!("failed writing coverage at {}: {e:#}", path.display());
+            }
+        }
+
+        result?;
         Ok(())
     }
 
     /// Creates a new `Engine` with the configuration for this command.
     pub fn new_engine(&mut self) -> Result<Engine> {
         let mut config = self.run.common.config(None)?;
+        self.run.configure_trusted_keys(&mut config)?;
         config.async_support(true);
 
         if self.run.common.wasm.timeout.is_some() {
             config.epoch_interruption(true);
         }
+        if self.coverage.is_some() {
+            config.coverage(true);
+        }
+        #[cfg(feature = "debug-adapter")]
+        if self.debug_adapter.is_some() {
+            // Epochs are used to interrupt the program when the debugger asks
+            // to pause it.
+            config.guest_debug(true);
+            config.epoch_interruption(true);
+        }
         match self.run.profile {
             Some(Profile::Native(s)) => {
                 config.profiler(s);
             }
-            Some(Profile::Guest { .. }) => {
+            Some(Profile::Guest { .. } | Profile::Sample { .. }) => {
                 // Further configured down below as well.
                 config.epoch_interruption(true);
             }
+            Some(Profile::Fuel { .. }) => {
+                // Further configured down below as well.
+                config.consume_fuel(true);
+                config.fuel_profiling(true);
+            }
             None => {}
         }
 
@@ -197,6 +289,9 @@ impl RunCommand {
         // fuel amount to this store.
         if let Some(fuel) = self.run.common.wasm.fuel {
             store.set_fuel(fuel)?;
+        } else if let Some(Profile::Fuel { .. }) = self.run.profile {
+            // Fuel is only being consumed to profile, so don't let it run out.
+            store.set_fuel(u64::MAX)?;
         }
 
         Ok((store, linker))
@@ -341,6 +436,31 @@ impl RunCommand {
         Ok(result)
     }
 
+    /// Writes the coverage of `main` as an LCOV tracefile to `path`.
+    fn write_coverage(&self, main: &RunTarget, path: &Path) -> Result<()> {
+        use std::io::Write;
+
+        let coverage: Vec<_> = match main {
+            RunTarget::Core(module) => module.coverage().into_iter().collect(),
+            #[cfg(feature = "component-model")]
+            RunTarget::Component(component) => component.coverage(),
+        };
+        let source_name = self.module_and_args[0].to_string_lossy();
+        let mut output = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for (i, module) in coverage.iter().enumerate() {
+            // Each core module of a component has its own offsets, so give
+            // them distinct names.
+            let source_name = if coverage.len() > 1 {
+                format!("{source_name}#module{i}")
+            } else {
+                source_name.to_string()
+            };
+            module.write_lcov(&mut output, &source_name)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+
     fn setup_epoch_handler(
         &self,
         store: &mut Store<Host>,
@@ -363,6 +483,22 @@ impl RunCommand {
             }
         }
 
+        if let Some(Profile::Sample { path, interval }) = &self.run.profile {
+            #[cfg(feature = "profiling")]
+            return Ok(self.setup_sampling_profiler(
+                store,
+                main_target,
+                profiled_modules,
+                path,
+                *interval,
+            ));
+            #[cfg(not(feature = "profiling"))]
+            {
+                let _ = (profiled_modules, path, interval, main_target);
+                bail!("support for profiling disabled at compile time");
+            }
+        }
+
         if let Some(timeout) = self.run.common.wasm.timeout {
             store.set_epoch_deadline(1);
             let engine = store.engine().clone();
@@ -372,6 +508,30 @@ impl RunCommand {
             });
         }
 
+        if let Some(Profile::Fuel { path }) = &self.run.profile {
+            let path = path.clone();
+            return Ok(Box::new(move |store| {
+                let report = store.fuel_profile().map(|profile| profile.to_string());
+                let result = report.and_then(|report| match &path {
+                    Some(path) => std::fs::write(path, report).map_err(anyhow::Error::new),
+                    None => {
+                        eprintln!();
+                        eprint!("{report}");
+                        Ok(())
+                    }
+                });
+                match (result, &path) {
+                    (Err(e), Some(path)) => eprintln!("failed writing profile at {path}: {e:#}"),
+                    (Err(e), None) => eprintln!("failed writing profile: {e:#}"),
+                    (Ok(()), Some(path)) => {
+                        eprintln!();
+                        eprintln!("Profile written to: {path}");
+                    }
+                    (Ok(()), None) => {}
+                }
+            }));
+        }
+
         Ok(Box::new(|_store| {}))
     }
 
@@ -384,7 +544,7 @@ impl RunCommand {
         path: &str,
         interval: std::time::Duration,
     ) -> Box<dyn FnOnce(&mut Store<Host>)> {
-        use wasmtime::{AsContext, GuestProfiler, StoreContext, StoreContextMut, UpdateDeadline};
+        use wasmtime::{AsContext, GuestProfiler, StoreContext, StoreContextMut};
 
         let module_name = self.module_and_args[0].to_str().unwrap_or("<main module>");
         store.data_mut().guest_profiler = match main_target {
@@ -418,39 +578,15 @@ impl RunCommand {
             Ok(())
         });
 
-        if let Some(timeout) = self.run.common.wasm.timeout {
-            let mut timeout = (timeout.as_secs_f64() / interval.as_secs_f64()).ceil() as u64;
-            assert!(timeout > 0);
-            store.epoch_deadline_callback(move |store| {
-                sample(store, |profiler, store| {
-                    profiler.sample(store, std::time::Duration::ZERO)
-                });
-                timeout -= 1;
-                if timeout == 0 {
-                    bail!("timeout exceeded");
-                }
-                Ok(UpdateDeadline::Continue(1))
-            });
-        } else {
-            store.epoch_deadline_callback(move |store| {
-                sample(store, |profiler, store| {
-                    profiler.sample(store, std::time::Duration::ZERO)
-                });
-                Ok(UpdateDeadline::Continue(1))
+        let ticker = self.sample_on_epoch(store, interval, |store| {
+            sample(store, |profiler, store| {
+                profiler.sample(store, std::time::Duration::ZERO)
             });
-        }
-
-        store.set_epoch_deadline(1);
-        let engine = store.engine().clone();
-        thread::spawn(move || {
-            loop {
-                thread::sleep(interval);
-                engine.increment_epoch();
-            }
         });
 
         let path = path.to_string();
         return Box::new(move |store| {
+            drop(ticker);
             let profiler = Arc::try_unwrap(store.data_mut().guest_profiler.take().unwrap())
                 .expect("profiling doesn't support threads yet");
             if let Err(e) = std::fs::File::create(&path)
@@ -466,6 +602,94 @@ impl RunCommand {
         });
     }
 
+    #[cfg(feature = "profiling")]
+    fn setup_sampling_profiler(
+        &self,
+        store: &mut Store<Host>,
+        main_target: &RunTarget,
+        profiled_modules: Vec<(String, Module)>,
+        path: &str,
+        interval: std::time::Duration,
+    ) -> Box<dyn FnOnce(&mut Store<Host>)> {
+        use wasmtime::SamplingProfiler;
+
+        let module_name = self.module_and_args[0].to_str().unwrap_or("<main module>");
+        let profiler = match main_target {
+            RunTarget::Core(_m) => SamplingProfiler::new(interval, profiled_modules),
+            RunTarget::Component(component) => SamplingProfiler::new_component(
+                module_name,
+                interval,
+                component.clone(),
+                profiled_modules,
+            ),
+        };
+        let profiler = Arc::new(Mutex::new(profiler));
+
+        let sampler = profiler.clone();
+        let ticker = self.sample_on_epoch(store, interval, move |store| {
+            sampler.lock().unwrap().sample(&store);
+        });
+
+        let path = path.to_string();
+        let title = module_name.to_string();
+        return Box::new(move |_store| {
+            drop(ticker);
+            let profiler = profiler.lock().unwrap();
+            let extension = Path::new(&path).extension().and_then(|e| e.to_str());
+            let result = std::fs::File::create(&path)
+                .map_err(anyhow::Error::new)
+                .and_then(|output| {
+                    let output = std::io::BufWriter::new(output);
+                    match extension {
+                        Some("svg") => sampling::write_flamegraph_svg(&profiler, output, &title),
+                        Some("pb" | "pprof") => sampling::write_pprof(&profiler, output),
+                        _ => profiler.write_collapsed(output),
+                    }
+                });
+            if let Err(e) = result {
+                eprintln!("failed writing profile at {path}: {e:#}");
+            } else {
+                eprintln!();
+                eprintln!(
+                    "Profile with {} samples written to: {path}",
+                    profiler.samples()
+                );
+            }
+        });
+    }
+
+    /// Calls `sample` every `interval` while wasm is running in `store`, by
+    /// incrementing the engine's epoch from a background thread, and enforces
+    /// `--timeout` in terms of the number of samples taken.
+    ///
+    /// The background thread stops once the returned ticker is dropped.
+    #[cfg(feature = "profiling")]
+    fn sample_on_epoch(
+        &self,
+        store: &mut Store<Host>,
+        interval: std::time::Duration,
+        mut sample: impl FnMut(wasmtime::StoreContextMut<Host>) + Send + Sync + 'static,
+    ) -> EpochTicker {
+        let mut timeout = self
+            .run
+            .common
+            .wasm
+            .timeout
+            .map(|timeout| (timeout.as_secs_f64() / interval.as_secs_f64()).ceil() as u64);
+        store.epoch_deadline_callback(move |store| {
+            sample(store);
+            if let Some(timeout) = &mut timeout {
+                *timeout = timeout.saturating_sub(1);
+                if *timeout == 0 {
+                    bail!("timeout exceeded");
+                }
+            }
+            Ok(wasmtime::UpdateDeadline::Continue(1))
+        });
+        store.set_epoch_deadline(1);
+        EpochTicker::spawn(store.engine().clone(), interval)
+    }
+
     async fn load_main_module(
         &self,
         store: &mut Store<Host>,
@@ -929,14 +1153,7 @@ impl RunCommand {
                         bail!("Cannot enable wasi-config for core wasm modules");
                     }
                     CliLinker::Component(linker) => {
-                        let vars = WasiConfigVariables::from_iter(
-                            self.run
-                                .common
-                                .wasi
-                                .config_var
-                                .iter()
-                                .map(|v| (v.key.clone(), v.value.clone())),
-                        );
+                        let vars = self.run.wasi_config_providers().load()?;
 
                         wasmtime_wasi_config::add_to_linker(linker, |h| {
                             WasiConfig::new(Arc::get_mut(h.wasi_config.as_mut().unwrap()).unwrap())
@@ -961,16 +1178,9 @@ impl RunCommand {
                         bail!("Cannot enable wasi-keyvalue for core wasm modules");
                     }
                     CliLinker::Component(linker) => {
-                        let ctx = WasiKeyValueCtxBuilder::new()
-                            .in_memory_data(
-                                self.run
-                                    .common
-                                    .wasi
-                                    .keyvalue_in_memory_data
-                                    .iter()
-                                    .map(|v| (v.key.clone(), v.value.clone())),
-                            )
-                            .build();
+                        let ctx = self.run.wasi_keyvalue_ctx(
+                            self.run.wasi_keyvalue_files(Default::default(), &[])?,
+                        );
 
                         wasmtime_wasi_keyvalue::add_to_linker(linker, |h| {
                             let ctx = h.wasip1_ctx.as_mut().expect("wasip2 is not configured");
@@ -1259,6 +1469,30 @@ impl wasmtime_wasi_http::p3::WasiHttpView for Host {
     }
 }
 
+/// A background thread which increments an engine's epoch at a fixed interval
+/// until it's dropped.
+#[cfg(feature = "profiling")]
+struct EpochTicker {
+    _stop: std::sync::mpsc::Sender<()>,
+}
+
+#[cfg(feature = "profiling")]
+impl EpochTicker {
+    fn spawn(engine: Engine, interval: std::time::Duration) -> EpochTicker {
+        let (stop, stopped) = std::sync::mpsc::channel::<()>();
+        thread::spawn(move || {
+            // Nothing is ever sent, so this only returns early once the
+            // ticker's sender has been dropped.
+            while let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+                stopped.recv_timeout(interval)
+            {
+                engine.increment_epoch();
+            }
+        });
+        EpochTicker { _stop: stop }
+    }
+}
+
 fn ctx_set_listenfd(mut num_fd: usize, builder: &mut WasiCtxBuilder) -> Result<usize> {
     let _ = &mut num_fd;
     let _ = &mut *builder;
@@ -0,0 +1,1052 @@
+//! Implementation of `wasmtime run --debug-adapter`, a server speaking the
+//! [Debug Adapter Protocol] (DAP) so that editors such as VS Code can debug a
+//! WebAssembly module running in Wasmtime.
+//!
+//! A single client is accepted on the configured address before the module
+//! starts running. Source breakpoints are mapped to wasm offsets through the
+//! module's DWARF debug information, and function and instruction breakpoints
+//! can be used for modules without any. Execution pauses through
+//! [`Store::debug_handler`] which serves requests from the client until it
+//! resumes execution.
+//!
+//! Requests are read from the client on a separate thread. A `pause` request
+//! is answered right away and interrupts the running program through epoch
+//! interruption, which turns on single-stepping so that it pauses before its
+//! next instruction. Breakpoint requests are also answered there while the
+//! program runs, and the running program installs the new breakpoints the
+//! next time it checks its epoch deadline. All other requests are only served
+//! while the program is paused or before it starts.
+//!
+//! [Debug Adapter Protocol]: https://microsoft.github.io/debug-adapter-protocol/
+
+use super::Host;
+use anyhow::{Context as _, Result, anyhow, bail};
+use base64::Engine as _;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use wasmtime::{
+    AsContext, AsContextMut, Breakpoint, DebugAction, DebugFrame, DebugPauseReason, Engine, Module,
+    Store, StoreContextMut, UpdateDeadline, Val, WasmBacktrace,
+};
+
+/// The only thread reported to clients.
+const THREAD_ID: u64 = 1;
+
+/// The longest header line accepted from the client.
+const MAX_HEADER_LEN: u64 = 1024;
+
+/// The largest message body accepted from the client.
+const MAX_MESSAGE_LEN: usize = 16 << 20;
+
+/// `variablesReference` values of the scopes of the paused frame.
+const LOCALS: u64 = 1;
+const OPERAND_STACK: u64 = 2;
+const GLOBALS: u64 = 3;
+const MEMORIES: u64 = 4;
+
+/// A debug adapter session attached to a `Store`.
+pub struct DebugAdapter {
+    session: Arc<Mutex<Session>>,
+}
+
+impl DebugAdapter {
+    /// Listens on `addr`, waits for a client to connect and configure the
+    /// session, and then installs a debug handler in `store` which pauses at
+    /// the client's breakpoints.
+    ///
+    /// The store's engine must have epoch interruption enabled, and the
+    /// session takes over the store's epoch deadline callback to pause the
+    /// program when the client asks to.
+    ///
+    /// The `wasm` path is the main module's file, which is read again for its
+    /// DWARF and name sections.
+    pub fn start(
+        addr: SocketAddr,
+        module: &Module,
+        wasm: &Path,
+        store: &mut Store<Host>,
+    ) -> Result<DebugAdapter> {
+        let info = std::fs::read(wasm)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| ModuleInfo::parse(&bytes))
+            .unwrap_or_else(|e| {
+                log::warn!("failed to read debug information from the main module: {e:#}");
+                ModuleInfo::default()
+            });
+
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("failed to bind debug adapter to `{addr}`"))?;
+        eprintln!(
+            "Waiting for a debug adapter client on {}",
+            listener.local_addr()?
+        );
+        let (stream, peer) = listener.accept()?;
+        log::debug!("debug adapter client connected from {peer}");
+
+        let info = Arc::new(info);
+        let breakpoints = Breakpoints {
+            module: module.clone(),
+            info: info.clone(),
+            source: HashMap::new(),
+            function: Vec::new(),
+            instruction: Vec::new(),
+            changed: false,
+        };
+        let mut session = Session {
+            conn: Connection::spawn(stream, store.engine().clone(), breakpoints)?,
+            module: module.clone(),
+            info,
+            installed: Vec::new(),
+            step: None,
+            launched: false,
+            disconnected: false,
+        };
+        session.configure(store)?;
+        session.conn.shared.serving.store(false, Ordering::SeqCst);
+
+        let session = Arc::new(Mutex::new(session));
+        let interrupted = session.clone();
+        store.epoch_deadline_callback(move |mut store| {
+            let mut session = interrupted.lock().unwrap();
+            session.sync_breakpoints(store.as_context_mut())?;
+            if session.conn.shared.pause_requested.load(Ordering::SeqCst) {
+                store.single_step(true)?;
+            }
+            Ok(UpdateDeadline::Continue(1))
+        });
+        store.set_epoch_deadline(1);
+
+        let handler = session.clone();
+        store.debug_handler(move |store, frame| handler.lock().unwrap().pause(store, frame));
+        Ok(DebugAdapter { session })
+    }
+
+    /// Notifies the client that the program has finished running.
+    pub fn finish(self, success: bool) {
+        let mut session = self.session.lock().unwrap();
+        if session.disconnected {
+            return;
+        }
+        let exit_code = if success { 0 } else { 1 };
+        let result = session
+            .conn
+            .event("exited", json!({ "exitCode": exit_code }))
+            .and_then(|()| session.conn.event("terminated", json!({})));
+        if let Err(e) = result {
+            log::debug!("failed to notify debug adapter client of exit: {e:#}");
+        }
+    }
+}
+
+/// A request received from the client.
+#[derive(serde_derive::Deserialize)]
+struct Request {
+    seq: u64,
+    command: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+/// The client connection, framing messages with `Content-Length` headers.
+///
+/// Requests are read by a separate thread, see [`read_requests`], and
+/// received here through a channel.
+struct Connection {
+    requests: mpsc::Receiver<Result<Request>>,
+    shared: Arc<Shared>,
+}
+
+/// State shared between a session and the thread reading its requests.
+struct Shared {
+    writer: Mutex<Writer>,
+    /// Whether the session is serving requests, either because the program
+    /// is paused in the debug handler or because it hasn't started yet.
+    serving: AtomicBool,
+    /// Whether the client asked to pause the running program, until it
+    /// pauses.
+    pause_requested: AtomicBool,
+    /// The breakpoints requested by the client, which may be changed by
+    /// either side.
+    breakpoints: Mutex<Breakpoints>,
+}
+
+struct Writer {
+    stream: TcpStream,
+    seq: u64,
+}
+
+impl Connection {
+    /// Starts reading requests from `stream` on a new thread, which
+    /// increments `engine`'s epoch to interrupt the program when the client
+    /// asks to pause it or changes `breakpoints` while it runs.
+    fn spawn(stream: TcpStream, engine: Engine, breakpoints: Breakpoints) -> Result<Connection> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let shared = Arc::new(Shared {
+            writer: Mutex::new(Writer { stream, seq: 0 }),
+            serving: AtomicBool::new(true),
+            pause_requested: AtomicBool::new(false),
+            breakpoints: Mutex::new(breakpoints),
+        });
+        let (tx, requests) = mpsc::channel();
+        let thread_shared = shared.clone();
+        thread::spawn(move || read_requests(reader, &thread_shared, &engine, &tx));
+        Ok(Connection { requests, shared })
+    }
+
+    /// Waits for the next request, returning `None` once the client is gone.
+    fn recv(&mut self) -> Result<Option<Request>> {
+        self.requests.recv().ok().transpose()
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.shared.event(event, body)
+    }
+
+    fn respond(&mut self, request: &Request, body: Result<Value>) -> Result<()> {
+        self.shared.respond(request, body)
+    }
+}
+
+impl Shared {
+    fn send(&self, mut message: Value) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.seq += 1;
+        message["seq"] = writer.seq.into();
+        let body = serde_json::to_string(&message)?;
+        write!(
+            writer.stream,
+            "Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )?;
+        writer.stream.flush()?;
+        Ok(())
+    }
+
+    fn event(&self, event: &str, body: Value) -> Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn respond(&self, request: &Request, body: Result<Value>) -> Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "command": request.command,
+        });
+        match body {
+            Ok(body) => {
+                response["success"] = true.into();
+                response["body"] = body;
+            }
+            Err(e) => {
+                response["success"] = false.into();
+                response["message"] = format!("{e:#}").into();
+            }
+        }
+        self.send(response)
+    }
+}
+
+/// Reads requests from the client until it disconnects, sending them to the
+/// session through `tx`.
+///
+/// `pause` requests are instead handled here since the session only serves
+/// requests while the program is paused. They're answered right away, and
+/// the program is interrupted by incrementing the engine's epoch. Breakpoint
+/// requests received while the program runs are answered here too, and the
+/// epoch is incremented so that the program installs the new breakpoints.
+fn read_requests(
+    mut reader: BufReader<TcpStream>,
+    shared: &Shared,
+    engine: &Engine,
+    tx: &mpsc::Sender<Result<Request>>,
+) {
+    loop {
+        let request = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => return,
+            Err(e) => {
+                let _ = tx.send(Err(e));
+                return;
+            }
+        };
+        let body = if request.command == "pause" {
+            if !shared.serving.load(Ordering::SeqCst) {
+                shared.pause_requested.store(true, Ordering::SeqCst);
+                engine.increment_epoch();
+            }
+            Ok(json!({}))
+        } else if shared.serving.load(Ordering::SeqCst) {
+            if tx.send(Ok(request)).is_err() {
+                return;
+            }
+            continue;
+        } else {
+            match request.command.as_str() {
+                "configurationDone" => Ok(json!({})),
+                "threads" => Ok(threads()),
+                "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+                _ => match shared.breakpoints.lock().unwrap().set(&request) {
+                    Some(body) => {
+                        engine.increment_epoch();
+                        body
+                    }
+                    None => {
+                        if tx.send(Ok(request)).is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                },
+            }
+        };
+        if let Err(e) = shared.respond(&request, body) {
+            log::debug!("failed to respond to debug adapter client: {e:#}");
+            return;
+        }
+    }
+}
+
+/// Reads the next request, returning `None` once the client is gone.
+fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Option<Request>> {
+    loop {
+        let mut len = None;
+        loop {
+            let mut line = String::new();
+            let n = reader.by_ref().take(MAX_HEADER_LEN).read_line(&mut line)?;
+            if !line.ends_with('\n') {
+                if n as u64 == MAX_HEADER_LEN {
+                    bail!("debug adapter message header is too long");
+                }
+                return Ok(None);
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                len = Some(value.trim().parse::<usize>()?);
+            }
+        }
+        let len = len.context("debug adapter message is missing `Content-Length`")?;
+        if len > MAX_MESSAGE_LEN {
+            bail!("debug adapter message of {len} bytes is too large");
+        }
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body)?;
+        let message: Value = serde_json::from_slice(&body)?;
+        // No reverse requests are sent so no responses are expected, and
+        // anything else is ignored.
+        if message["type"] == "request" {
+            return Ok(Some(serde_json::from_value(message)?));
+        }
+    }
+}
+
+/// How a step requested by the client proceeds before pausing again.
+enum Step {
+    /// Pause at the next instruction.
+    Instruction,
+    /// Pause at the next source line, entering calls.
+    In { from: (PathBuf, u64) },
+    /// Pause at the next source line without entering calls.
+    Over {
+        from: Option<(PathBuf, u64)>,
+        depth: usize,
+    },
+    /// Pause once the current function returns.
+    Out { depth: usize },
+}
+
+/// The breakpoints requested by the client.
+struct Breakpoints {
+    module: Module,
+    info: Arc<ModuleInfo>,
+    source: HashMap<PathBuf, Vec<Breakpoint>>,
+    function: Vec<Breakpoint>,
+    instruction: Vec<Breakpoint>,
+    /// Whether the requested breakpoints changed since they were last added
+    /// to the store.
+    changed: bool,
+}
+
+impl Breakpoints {
+    /// Handles the requests setting breakpoints, returning `None` for any
+    /// other request.
+    fn set(&mut self, request: &Request) -> Option<Result<Value>> {
+        let body = match request.command.as_str() {
+            "setBreakpoints" => self.set_source(&request.arguments),
+            "setFunctionBreakpoints" => self.set_function(&request.arguments),
+            "setInstructionBreakpoints" => Ok(self.set_instruction(&request.arguments)),
+            _ => return None,
+        };
+        self.changed = true;
+        Some(body)
+    }
+
+    fn set_source(&mut self, args: &Value) -> Result<Value> {
+        let path = args["source"]["path"]
+            .as_str()
+            .context("missing source path")?;
+        let mut breakpoints = Vec::new();
+        let mut results = Vec::new();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let line = bp["line"].as_u64().context("missing breakpoint line")?;
+            match self.info.resolve_line(Path::new(path), line) {
+                Some((line, offsets)) => {
+                    breakpoints.extend(
+                        offsets
+                            .into_iter()
+                            .map(|offset| Breakpoint::offset(&self.module, offset)),
+                    );
+                    results.push(json!({ "verified": true, "line": line }));
+                }
+                None => results.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": "no code found for this line in the DWARF debug information",
+                })),
+            }
+        }
+        self.source.insert(path.into(), breakpoints);
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    fn set_function(&mut self, args: &Value) -> Result<Value> {
+        let mut breakpoints = Vec::new();
+        let mut results = Vec::new();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let name = bp["name"].as_str().context("missing function name")?;
+            let breakpoint = self
+                .info
+                .func_names
+                .get(name)
+                .copied()
+                .or_else(|| name.parse().ok())
+                .ok_or_else(|| anyhow!("unknown function `{name}`"))
+                .and_then(|index| Breakpoint::function(&self.module, index));
+            match breakpoint {
+                Ok(breakpoint) => {
+                    breakpoints.push(breakpoint);
+                    results.push(json!({ "verified": true }));
+                }
+                Err(e) => results.push(json!({
+                    "verified": false,
+                    "message": format!("{e:#}"),
+                })),
+            }
+        }
+        self.function = breakpoints;
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    fn set_instruction(&mut self, args: &Value) -> Value {
+        let mut breakpoints = Vec::new();
+        let mut results = Vec::new();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let offset = bp["instructionReference"]
+                .as_str()
+                .and_then(parse_offset)
+                .and_then(|offset| {
+                    let delta = bp["offset"].as_i64().unwrap_or(0);
+                    u32::try_from(i64::from(offset) + delta).ok()
+                });
+            match offset {
+                Some(offset) => {
+                    breakpoints.push(Breakpoint::offset(&self.module, offset));
+                    results.push(json!({
+                        "verified": true,
+                        "instructionReference": format!("0x{offset:x}"),
+                    }));
+                }
+                None => results.push(json!({
+                    "verified": false,
+                    "message": "invalid instruction reference",
+                })),
+            }
+        }
+        self.instruction = breakpoints;
+        json!({ "breakpoints": results })
+    }
+
+    fn clear(&mut self) {
+        self.source.clear();
+        self.function.clear();
+        self.instruction.clear();
+        self.changed = true;
+    }
+
+    fn requested(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.source
+            .values()
+            .flatten()
+            .chain(&self.function)
+            .chain(&self.instruction)
+    }
+}
+
+struct Session {
+    conn: Connection,
+    module: Module,
+    info: Arc<ModuleInfo>,
+    /// The breakpoints currently added to the store.
+    installed: Vec<Breakpoint>,
+    step: Option<Step>,
+    launched: bool,
+    disconnected: bool,
+}
+
+impl Session {
+    /// Serves requests until the client finishes configuring the session with
+    /// `configurationDone`.
+    fn configure(&mut self, store: &mut Store<Host>) -> Result<()> {
+        loop {
+            let Some(request) = self.conn.recv()? else {
+                bail!("debug adapter client disconnected before configuration finished");
+            };
+            match request.command.as_str() {
+                "configurationDone" => return self.conn.respond(&request, Ok(json!({}))),
+                "disconnect" => {
+                    self.conn.respond(&request, Ok(json!({})))?;
+                    self.detach(store.as_context_mut())?;
+                    return Ok(());
+                }
+                _ => {
+                    let body = self.handle(store.as_context_mut(), &request);
+                    self.conn.respond(&request, body)?;
+                    if request.command == "initialize" {
+                        self.conn.event("initialized", json!({}))?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Invoked by the store's debug handler whenever execution pauses.
+    fn pause(
+        &mut self,
+        mut store: StoreContextMut<'_, Host>,
+        frame: &DebugFrame,
+    ) -> Result<DebugAction> {
+        if self.disconnected {
+            return Ok(DebugAction::Continue);
+        }
+        self.conn.shared.serving.store(true, Ordering::SeqCst);
+        let action = self.serve_paused(store.as_context_mut(), frame);
+        self.conn.shared.serving.store(false, Ordering::SeqCst);
+        // Breakpoints may have been set by the thread reading requests just
+        // before it saw that the program was paused.
+        self.sync_breakpoints(store)?;
+        action
+    }
+
+    /// Reports why the program paused and serves requests until the client
+    /// resumes it.
+    fn serve_paused(
+        &mut self,
+        mut store: StoreContextMut<'_, Host>,
+        frame: &DebugFrame,
+    ) -> Result<DebugAction> {
+        let backtrace = WasmBacktrace::capture(&store);
+        let pause_requested = self
+            .conn
+            .shared
+            .pause_requested
+            .swap(false, Ordering::SeqCst);
+        let reason = match frame.reason() {
+            DebugPauseReason::Breakpoint => {
+                self.step = None;
+                "breakpoint"
+            }
+            DebugPauseReason::Step if pause_requested => {
+                self.step = None;
+                "pause"
+            }
+            DebugPauseReason::Step => match self.step.take() {
+                // Single-stepping without a step request only happens when
+                // stopping on entry.
+                None => "entry",
+                Some(step) => {
+                    if !self.step_done(&step, frame, &backtrace) {
+                        self.step = Some(step);
+                        return Ok(DebugAction::Step);
+                    }
+                    "step"
+                }
+            },
+        };
+        self.conn.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )?;
+
+        loop {
+            let Some(request) = self.conn.recv()? else {
+                self.detach(store.as_context_mut())?;
+                return Ok(DebugAction::Continue);
+            };
+            match request.command.as_str() {
+                "continue" => {
+                    self.conn
+                        .respond(&request, Ok(json!({ "allThreadsContinued": true })))?;
+                    return Ok(DebugAction::Continue);
+                }
+                "next" | "stepIn" | "stepOut" => {
+                    self.step = Some(self.start_step(&request, frame, &backtrace));
+                    self.conn.respond(&request, Ok(json!({})))?;
+                    return Ok(DebugAction::Step);
+                }
+                "disconnect" | "terminate" => {
+                    let terminate = request.command == "terminate"
+                        || request.arguments["terminateDebuggee"]
+                            .as_bool()
+                            .unwrap_or(self.launched);
+                    self.conn.respond(&request, Ok(json!({})))?;
+                    if terminate {
+                        bail!("program terminated by the debugger");
+                    }
+                    self.detach(store.as_context_mut())?;
+                    return Ok(DebugAction::Continue);
+                }
+                _ => {}
+            }
+            let body = match request.command.as_str() {
+                "stackTrace" => Ok(self.stack_trace(frame, &backtrace)),
+                "scopes" => Ok(scopes(&request)),
+                "variables" => variables(store.as_context_mut(), frame, &request),
+                "readMemory" => read_memory(store.as_context_mut(), frame, &request),
+                _ => self.handle(store.as_context_mut(), &request),
+            };
+            self.conn.respond(&request, body)?;
+        }
+    }
+
+    /// Handles requests which are valid both before the program starts and
+    /// while it is paused.
+    fn handle(&mut self, mut store: StoreContextMut<'_, Host>, request: &Request) -> Result<Value> {
+        let args = &request.arguments;
+        match request.command.as_str() {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsFunctionBreakpoints": true,
+                "supportsInstructionBreakpoints": true,
+                "supportsReadMemoryRequest": true,
+                "supportsSteppingGranularity": true,
+                "supportsTerminateRequest": true,
+            })),
+            "launch" | "attach" => {
+                self.launched = request.command == "launch";
+                if args["stopOnEntry"].as_bool() == Some(true) {
+                    store.single_step(true)?;
+                }
+                Ok(json!({}))
+            }
+            "threads" => Ok(threads()),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            _ => {
+                let body = self.conn.shared.breakpoints.lock().unwrap().set(request);
+                match body {
+                    Some(body) => {
+                        self.sync_breakpoints(store)?;
+                        body
+                    }
+                    None => bail!("unsupported request `{}`", request.command),
+                }
+            }
+        }
+    }
+
+    /// Replaces the breakpoints in the store with those currently requested
+    /// by the client, if they changed.
+    fn sync_breakpoints(&mut self, mut store: StoreContextMut<'_, Host>) -> Result<()> {
+        let mut breakpoints = self.conn.shared.breakpoints.lock().unwrap();
+        if !breakpoints.changed {
+            return Ok(());
+        }
+        breakpoints.changed = false;
+        for breakpoint in self.installed.drain(..) {
+            store.remove_breakpoint(&breakpoint);
+        }
+        for breakpoint in breakpoints.requested() {
+            store.add_breakpoint(breakpoint.clone())?;
+            self.installed.push(breakpoint.clone());
+        }
+        Ok(())
+    }
+
+    /// Removes all breakpoints and lets the program run to completion.
+    fn detach(&mut self, mut store: StoreContextMut<'_, Host>) -> Result<()> {
+        self.disconnected = true;
+        self.step = None;
+        self.conn.shared.breakpoints.lock().unwrap().clear();
+        self.sync_breakpoints(store.as_context_mut())?;
+        store.single_step(false)
+    }
+
+    /// Returns the source location of `offset` in `module`, if known.
+    fn location(&self, module: &Module, offset: u32) -> Option<(PathBuf, u64)> {
+        if module.image_range() != self.module.image_range() {
+            return None;
+        }
+        self.info
+            .lookup(offset)
+            .map(|row| (row.path.clone(), row.line))
+    }
+
+    fn start_step(&self, request: &Request, frame: &DebugFrame, backtrace: &WasmBacktrace) -> Step {
+        let depth = backtrace.frames().len();
+        // Steps from code without line information, such as in modules other
+        // than the main one, fall back to instructions.
+        let from = match request.arguments["granularity"].as_str() {
+            Some("instruction") => None,
+            _ => self.location(frame.module(), frame.offset()),
+        };
+        match (request.command.as_str(), from) {
+            ("stepOut", _) => Step::Out { depth },
+            ("stepIn", Some(from)) => Step::In { from },
+            ("stepIn", None) => Step::Instruction,
+            (_, from) => Step::Over { from, depth },
+        }
+    }
+
+    /// Returns whether `step` has finished at the instruction `frame` is
+    /// paused at.
+    fn step_done(&self, step: &Step, frame: &DebugFrame, backtrace: &WasmBacktrace) -> bool {
+        let here = || self.location(frame.module(), frame.offset());
+        let depth = backtrace.frames().len();
+        match step {
+            Step::Instruction => true,
+            // Stepping into code without line information pauses right
+            // away, as if stepping by instruction.
+            Step::In { from } => here().as_ref() != Some(from),
+            Step::Over {
+                from: None,
+                depth: start,
+            } => depth <= *start,
+            Step::Over { from, depth: start } => {
+                depth < *start
+                    || (depth == *start && here().is_some_and(|here| Some(here) != *from))
+            }
+            Step::Out { depth: start } => depth < *start,
+        }
+    }
+
+    fn stack_trace(&self, frame: &DebugFrame, backtrace: &WasmBacktrace) -> Value {
+        let mut frames = Vec::new();
+        for (i, info) in backtrace.frames().iter().enumerate() {
+            let offset = if i == 0 {
+                Some(frame.offset())
+            } else {
+                info.module_offset().and_then(|o| u32::try_from(o).ok())
+            };
+            let name = info
+                .func_name()
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("wasm-function[{}]", info.func_index()));
+            frames.push(self.stack_frame(i, name, info.module(), offset));
+        }
+        if frames.is_empty() {
+            let name = frame
+                .func_name()
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("wasm-function[{}]", frame.func_index()));
+            frames.push(self.stack_frame(0, name, frame.module(), Some(frame.offset())));
+        }
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+
+    fn stack_frame(&self, id: usize, name: String, module: &Module, offset: Option<u32>) -> Value {
+        let mut frame = json!({ "id": id, "name": name, "line": 0, "column": 0 });
+        let Some(offset) = offset else {
+            return frame;
+        };
+        frame["instructionPointerReference"] = format!("0x{offset:x}").into();
+        if let Some((path, line)) = self.location(module, offset) {
+            frame["source"] = json!({
+                "name": path.file_name().map(|n| n.to_string_lossy()),
+                "path": path.to_string_lossy(),
+            });
+            frame["line"] = line.into();
+            frame["column"] = 1.into();
+        }
+        frame
+    }
+}
+
+fn threads() -> Value {
+    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })
+}
+
+fn scopes(request: &Request) -> Value {
+    // Only the paused frame, the first one in the stack trace, can be
+    // inspected.
+    if request.arguments["frameId"].as_u64() != Some(0) {
+        return json!({ "scopes": [] });
+    }
+    let scope = |name: &str, reference: u64| json!({ "name": name, "variablesReference": reference, "expensive": false });
+    json!({
+        "scopes": [
+            scope("Locals", LOCALS),
+            scope("Operand Stack", OPERAND_STACK),
+            scope("Globals", GLOBALS),
+            scope("Memories", MEMORIES),
+        ]
+    })
+}
+
+fn variables(
+    mut store: StoreContextMut<'_, Host>,
+    frame: &DebugFrame,
+    request: &Request,
+) -> Result<Value> {
+    let mut variables = Vec::new();
+    match request.arguments["variablesReference"].as_u64() {
+        Some(LOCALS) => {
+            for (i, val) in frame.locals().iter().enumerate() {
+                variables.push(variable(&store, format!("local{i}"), val.as_ref())?);
+            }
+        }
+        Some(OPERAND_STACK) => {
+            for (i, val) in frame.operand_stack().iter().enumerate() {
+                variables.push(variable(&store, format!("stack{i}"), val.as_ref())?);
+            }
+        }
+        Some(GLOBALS) => {
+            let mut i = 0;
+            while let Some(global) = frame.global(&store, i) {
+                let val = global.get(&mut store);
+                variables.push(variable(&store, format!("global{i}"), Some(&val))?);
+                i += 1;
+            }
+        }
+        Some(MEMORIES) => {
+            let mut i = 0;
+            while let Some(memory) = frame.memory(&store, i) {
+                variables.push(json!({
+                    "name": format!("memory{i}"),
+                    "value": format!("{} bytes", memory.data_size(&store)),
+                    "variablesReference": 0,
+                    "memoryReference": format!("memory{i}"),
+                }));
+                i += 1;
+            }
+        }
+        _ => bail!("unknown variables reference"),
+    }
+    Ok(json!({ "variables": variables }))
+}
+
+/// Describes a variable, whose value is `None` if the debugger can't inspect
+/// it.
+fn variable(store: impl AsContext, name: String, val: Option<&Val>) -> Result<Value> {
+    let Some(val) = val else {
+        return Ok(json!({
+            "name": name,
+            "value": "<unavailable>",
+            "variablesReference": 0,
+        }));
+    };
+    Ok(json!({
+        "name": name,
+        "value": format_val(val),
+        "type": val.ty(store)?.to_string(),
+        "variablesReference": 0,
+    }))
+}
+
+fn read_memory(
+    store: StoreContextMut<'_, Host>,
+    frame: &DebugFrame,
+    request: &Request,
+) -> Result<Value> {
+    let args = &request.arguments;
+    let index = args["memoryReference"]
+        .as_str()
+        .and_then(|r| r.strip_prefix("memory"))
+        .and_then(|i| i.parse::<u32>().ok())
+        .context("invalid memory reference")?;
+    let memory = frame
+        .memory(&store, index)
+        .with_context(|| format!("no memory {index}"))?;
+    let start = args["offset"].as_u64().unwrap_or(0);
+    let count = args["count"].as_u64().context("missing count")?;
+    let data = memory.data(&store);
+    let start_index = usize::try_from(start).unwrap_or(usize::MAX).min(data.len());
+    let end = usize::try_from(start.saturating_add(count))
+        .unwrap_or(usize::MAX)
+        .min(data.len());
+    let bytes = &data[start_index..end];
+    Ok(json!({
+        "address": format!("0x{start:x}"),
+        "data": base64::engine::general_purpose::STANDARD.encode(bytes),
+        "unreadableBytes": count - bytes.len() as u64,
+    }))
+}
+
+fn format_val(val: &Val) -> String {
+    match *val {
+        Val::I32(i) => i.to_string(),
+        Val::I64(i) => i.to_string(),
+        Val::F32(bits) => f32::from_bits(bits).to_string(),
+        Val::F64(bits) => f64::from_bits(bits).to_string(),
+        Val::V128(v) => format!("0x{:032x}", v.as_u128()),
+        _ => match val.ref_() {
+            Some(r) if r.is_null() => "null".to_string(),
+            _ => "<ref>".to_string(),
+        },
+    }
+}
+
+/// Parses an instruction reference, a wasm offset in hexadecimal with a `0x`
+/// prefix or in decimal.
+fn parse_offset(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// A row of the DWARF line table, mapping a wasm offset to a source line.
+struct LineRow {
+    offset: u32,
+    path: PathBuf,
+    line: u64,
+}
+
+/// Debugging information read from the main module's binary.
+#[derive(Default)]
+struct ModuleInfo {
+    /// Statement rows of the DWARF line table, sorted by offset.
+    lines: Vec<LineRow>,
+    /// Function indices by their name in the name section or their export
+    /// name.
+    func_names: HashMap<String, u32>,
+}
+
+impl ModuleInfo {
+    fn parse(bytes: &[u8]) -> Result<ModuleInfo> {
+        #[cfg(feature = "wat")]
+        let bytes = &wat::parse_bytes(bytes)?;
+
+        let mut info = ModuleInfo::default();
+        let mut sections = HashMap::new();
+        let mut code_start = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+            match payload? {
+                wasmparser::Payload::CodeSectionStart { range, .. } => {
+                    code_start = u32::try_from(range.start)?;
+                }
+                wasmparser::Payload::ExportSection(exports) => {
+                    for export in exports {
+                        let export = export?;
+                        if export.kind == wasmparser::ExternalKind::Func {
+                            info.func_names
+                                .entry(export.name.to_string())
+                                .or_insert(export.index);
+                        }
+                    }
+                }
+                wasmparser::Payload::CustomSection(section) => {
+                    if section.name().starts_with(".debug_") {
+                        sections.insert(section.name(), section.data());
+                    } else if let wasmparser::KnownCustom::Name(names) = section.as_known() {
+                        for name in names {
+                            let wasmparser::Name::Function(names) = name? else {
+                                continue;
+                            };
+                            for naming in names {
+                                let naming = naming?;
+                                info.func_names
+                                    .insert(naming.name.to_string(), naming.index);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if sections.contains_key(".debug_line") {
+            info.lines = parse_lines(&sections, code_start)?;
+        }
+        Ok(info)
+    }
+
+    /// Returns the line table row covering `offset`.
+    fn lookup(&self, offset: u32) -> Option<&LineRow> {
+        let i = self.lines.partition_point(|row| row.offset <= offset);
+        self.lines[..i].last()
+    }
+
+    /// Resolves a breakpoint on `line` of the source file `path` to the first
+    /// line at or after it with code, returning that line and the offsets of
+    /// its statements.
+    fn resolve_line(&self, path: &Path, line: u64) -> Option<(u64, Vec<u32>)> {
+        let rows = self
+            .lines
+            .iter()
+            .filter(|row| row.path.ends_with(path) || path.ends_with(&row.path));
+        let line = rows
+            .clone()
+            .map(|row| row.line)
+            .filter(|l| *l >= line)
+            .min()?;
+        let offsets = rows.filter(|row| row.line == line).map(|row| row.offset);
+        Some((line, offsets.collect()))
+    }
+}
+
+/// Runs the DWARF line programs of all units, translating addresses, which
+/// are relative to the start of the code section, to wasm offsets.
+fn parse_lines(sections: &HashMap<&str, &[u8]>, code_start: u32) -> Result<Vec<LineRow>> {
+    let dwarf = gimli::Dwarf::load(|id| -> Result<_> {
+        let data = sections.get(id.name()).copied().unwrap_or(&[]);
+        Ok(gimli::EndianSlice::new(data, gimli::LittleEndian))
+    })?;
+
+    let mut lines = Vec::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let Some(program) = unit.line_program.clone() else {
+            continue;
+        };
+        let mut rows = program.rows();
+        while let Some((header, row)) = rows.next_row()? {
+            if row.end_sequence() || !row.is_stmt() {
+                continue;
+            }
+            let (Some(file), Some(line)) = (row.file(header), row.line()) else {
+                continue;
+            };
+            let mut path = PathBuf::new();
+            if let Some(dir) = &unit.comp_dir {
+                path.push(&*dir.to_string_lossy());
+            }
+            if let Some(dir) = file.directory(header) {
+                path.push(&*dwarf.attr_string(&unit, dir)?.to_string_lossy());
+            }
+            path.push(
+                &*dwarf
+                    .attr_string(&unit, file.path_name())?
+                    .to_string_lossy(),
+            );
+            lines.push(LineRow {
+                offset: code_start + u32::try_from(row.address())?,
+                path,
+                line: line.get(),
+            });
+        }
+    }
+    lines.sort_by_key(|row| row.offset);
+    Ok(lines)
+}
@@ -0,0 +1,333 @@
+//! Output formats for `wasmtime run --profile=sample`.
+//!
+//! The [`SamplingProfiler`] itself only aggregates samples, so the flame
+//! graph and pprof encodings of its profile live here rather than in the
+//! `wasmtime` crate.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::SystemTime;
+use wasmtime::SamplingProfiler;
+
+/// Write the profile as a self-contained SVG flame graph.
+///
+/// The `title` is displayed at the top of the image.
+pub fn write_flamegraph_svg(
+    profiler: &SamplingProfiler,
+    mut output: impl Write,
+    title: &str,
+) -> Result<()> {
+    let mut root = FlameNode::default();
+    for (stack, count) in profiler.collapsed_stacks() {
+        root.insert(&stack, count);
+    }
+
+    const WIDTH: f64 = 1200.0;
+    const PAD: f64 = 10.0;
+    const FRAME_HEIGHT: f64 = 16.0;
+    const TITLE_HEIGHT: f64 = 40.0;
+    let depth = root.depth();
+    let height = TITLE_HEIGHT + (depth + 1) as f64 * FRAME_HEIGHT + PAD;
+    let total = root.total.max(1);
+    let scale = (WIDTH - 2.0 * PAD) / total as f64;
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        "<?xml version=\"1.0\" standalone=\"no\"?>\n\
+         <svg version=\"1.1\" width=\"{WIDTH}\" height=\"{height}\" \
+         viewBox=\"0 0 {WIDTH} {height}\" xmlns=\"http://www.w3.org/2000/svg\">\n\
+         <style>text {{ font-family: monospace; font-size: 12px; fill: #000; }} \
+         rect:hover {{ stroke: #000; }}</style>\n\
+         <rect x=\"0\" y=\"0\" width=\"100%\" height=\"100%\" fill=\"#f8f8f8\"/>\n\
+         <text x=\"{}\" y=\"24\" text-anchor=\"middle\" style=\"font-size: 17px\">{}</text>\n",
+        WIDTH / 2.0,
+        XmlEscape(title),
+    )?;
+
+    // The root frame is drawn at the bottom, with callees stacked above
+    // their callers.
+    let mut work = vec![(&root, "all", 0usize, PAD)];
+    while let Some((node, name, level, x)) = work.pop() {
+        let width = node.total as f64 * scale;
+        let y = height - PAD - (level + 1) as f64 * FRAME_HEIGHT;
+        let percent = node.total as f64 * 100.0 / total as f64;
+        let (r, g, b) = frame_color(name);
+        write!(
+            svg,
+            "<g><title>{} ({} samples, {percent:.2}%)</title>\
+             <rect x=\"{x:.2}\" y=\"{y:.2}\" width=\"{width:.2}\" height=\"{}\" \
+             fill=\"rgb({r},{g},{b})\" rx=\"2\" ry=\"2\"/>",
+            XmlEscape(name),
+            node.total,
+            FRAME_HEIGHT - 1.0,
+        )?;
+        // Assume roughly 7 pixels per character of the monospace font, and
+        // only label frames with room for at least three characters.
+        let fits = (width - 6.0) / 7.0;
+        if fits >= 3.0 {
+            let fits = fits.floor() as usize;
+            let text: String = if name.chars().count() <= fits {
+                name.to_string()
+            } else {
+                name.chars().take(fits - 2).chain("..".chars()).collect()
+            };
+            write!(
+                svg,
+                "<text x=\"{:.2}\" y=\"{:.2}\">{}</text>",
+                x + 3.0,
+                y + FRAME_HEIGHT - 4.5,
+                XmlEscape(&text),
+            )?;
+        }
+        svg.push_str("</g>\n");
+
+        let mut child_x = x;
+        for (child_name, child) in node.children.iter() {
+            work.push((child, child_name.as_str(), level + 1, child_x));
+            child_x += child.total as f64 * scale;
+        }
+    }
+    svg.push_str("</svg>\n");
+
+    output.write_all(svg.as_bytes())?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Write the profile in the [pprof] protobuf format.
+///
+/// The profile is written uncompressed, which `go tool pprof` and other
+/// consumers accept. Each sample records both a sample count and an estimate
+/// of CPU time based on the sampling interval. Functions are attributed to
+/// their module through the `filename` field, and each location's address is
+/// the offset of the sampled instruction within the original WebAssembly
+/// binary.
+///
+/// [pprof]: https://github.com/google/pprof/blob/main/proto/profile.proto
+pub fn write_pprof(profiler: &SamplingProfiler, mut output: impl Write) -> Result<()> {
+    let mut strings = PprofStrings::default();
+    let mut profile = Vec::new();
+    let interval = u64::try_from(profiler.interval().as_nanos()).unwrap_or(u64::MAX);
+
+    let samples = strings.intern("samples");
+    let count = strings.intern("count");
+    let cpu = strings.intern("cpu");
+    let nanoseconds = strings.intern("nanoseconds");
+    for (ty, unit) in [(samples, count), (cpu, nanoseconds)] {
+        let mut value_type = Vec::new();
+        proto::int(&mut value_type, 1, ty);
+        proto::int(&mut value_type, 2, unit);
+        proto::message(&mut profile, 1, &value_type);
+    }
+
+    // Assign location ids in a deterministic order.
+    let mut stacks: Vec<_> = profiler.stacks().collect();
+    stacks.sort();
+    let mut location_ids = HashMap::new();
+    let mut locations = Vec::new();
+    for (stack, count) in stacks {
+        let mut ids = Vec::with_capacity(stack.len());
+        // pprof stacks list the newest frame first.
+        for frame in stack.iter().rev() {
+            let next = locations.len() as u64 + 1;
+            let id = *location_ids.entry(*frame).or_insert_with(|| {
+                locations.push((next, *frame));
+                next
+            });
+            ids.push(id);
+        }
+        // Values are `int64`s, whose two's complement encoding is the same as
+        // that of a `uint64` when they're non-negative.
+        let count = count.min(i64::MAX as u64);
+        let mut sample = Vec::new();
+        proto::packed(&mut sample, 1, ids.iter().copied());
+        proto::packed(
+            &mut sample,
+            2,
+            [count, count.saturating_mul(interval).min(i64::MAX as u64)],
+        );
+        proto::message(&mut profile, 2, &sample);
+    }
+
+    for (id, frame) in locations {
+        let mut line = Vec::new();
+        proto::int(&mut line, 1, frame.function() as u64 + 1);
+        let mut msg = Vec::new();
+        proto::int(&mut msg, 1, id);
+        proto::int(&mut msg, 3, u64::from(frame.wasm_offset().unwrap_or(0)));
+        proto::message(&mut msg, 4, &line);
+        proto::message(&mut profile, 4, &msg);
+    }
+
+    for (i, function) in profiler.functions().iter().enumerate() {
+        let name = strings.intern(&function.label());
+        let system_name = strings.intern(function.name());
+        let filename = strings.intern(function.module());
+        let mut msg = Vec::new();
+        proto::int(&mut msg, 1, i as u64 + 1);
+        proto::int(&mut msg, 2, name);
+        proto::int(&mut msg, 3, system_name);
+        proto::int(&mut msg, 4, filename);
+        proto::message(&mut profile, 5, &msg);
+    }
+
+    for s in strings.strings.iter() {
+        proto::bytes(&mut profile, 6, s.as_bytes());
+    }
+
+    let time = profiler
+        .start_time()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let nanos = |d: std::time::Duration| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX);
+    proto::int(&mut profile, 9, nanos(time));
+    proto::int(&mut profile, 10, nanos(profiler.elapsed()));
+    let mut period_type = Vec::new();
+    proto::int(&mut period_type, 1, cpu);
+    proto::int(&mut period_type, 2, nanoseconds);
+    proto::message(&mut profile, 11, &period_type);
+    proto::int(&mut profile, 12, interval.min(i64::MAX as u64));
+
+    output.write_all(&profile)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// A node in the call tree used to lay out a flame graph.
+#[derive(Default)]
+struct FlameNode {
+    total: u64,
+    children: Vec<(String, FlameNode)>,
+}
+
+impl FlameNode {
+    /// Insert a stack, oldest frame first. Stacks must be inserted in sorted
+    /// order so that children end up sorted by name.
+    fn insert(&mut self, stack: &[String], count: u64) {
+        self.total += count;
+        let Some((first, rest)) = stack.split_first() else {
+            return;
+        };
+        if self.children.last().is_none_or(|(name, _)| name != first) {
+            self.children.push((first.clone(), FlameNode::default()));
+        }
+        self.children.last_mut().unwrap().1.insert(rest, count);
+    }
+
+    fn depth(&self) -> usize {
+        self.children
+            .iter()
+            .map(|(_, c)| c.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Picks a color in the classic "hot" flame graph palette, derived from the
+/// frame's name so that the same function is always drawn the same color.
+fn frame_color(name: &str) -> (u8, u8, u8) {
+    // FNV-1a
+    let mut hash: u32 = 0x811c9dc5;
+    for b in name.bytes() {
+        hash ^= u32::from(b);
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    let [h0, h1, h2, _] = hash.to_le_bytes();
+    (205 + h0 % 50, h1 % 230, h2 % 55)
+}
+
+struct XmlEscape<'a>(&'a str);
+
+impl std::fmt::Display for XmlEscape<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '&' => f.write_str("&amp;")?,
+                '"' => f.write_str("&quot;")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The string table of a pprof profile, whose first entry must be the empty
+/// string.
+struct PprofStrings {
+    strings: Vec<String>,
+    indices: HashMap<String, u64>,
+}
+
+impl Default for PprofStrings {
+    fn default() -> Self {
+        let mut strings = PprofStrings {
+            strings: Vec::new(),
+            indices: HashMap::new(),
+        };
+        strings.intern("");
+        strings
+    }
+}
+
+impl PprofStrings {
+    fn intern(&mut self, s: &str) -> u64 {
+        if let Some(i) = self.indices.get(s) {
+            return *i;
+        }
+        let i = self.strings.len() as u64;
+        self.strings.push(s.to_string());
+        self.indices.insert(s.to_string(), i);
+        i
+    }
+}
+
+/// Just enough of a protobuf encoder to write pprof profiles.
+mod proto {
+    fn varint(out: &mut Vec<u8>, mut v: u64) {
+        loop {
+            let [byte, ..] = v.to_le_bytes();
+            v >>= 7;
+            if v == 0 {
+                out.push(byte & 0x7f);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn key(out: &mut Vec<u8>, field: u32, wire_type: u32) {
+        varint(out, u64::from((field << 3) | wire_type));
+    }
+
+    /// Writes a varint field, omitting it if it has the default value of
+    /// zero.
+    pub fn int(out: &mut Vec<u8>, field: u32, v: u64) {
+        if v != 0 {
+            key(out, field, 0);
+            varint(out, v);
+        }
+    }
+
+    pub fn bytes(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+        key(out, field, 2);
+        varint(out, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+    }
+
+    pub fn message(out: &mut Vec<u8>, field: u32, msg: &[u8]) {
+        bytes(out, field, msg)
+    }
+
+    pub fn packed(out: &mut Vec<u8>, field: u32, values: impl IntoIterator<Item = u64>) {
+        let mut buf = Vec::new();
+        for v in values {
+            varint(&mut buf, v);
+        }
+        bytes(out, field, &buf);
+    }
+}
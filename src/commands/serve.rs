@@ -1,38 +1,46 @@
 use crate::common::{Profile, RunCommon, RunTarget};
-use anyhow::{Result, bail};
+use anyhow::{Context as _, Result, bail};
 use bytes::Bytes;
 use clap::Parser;
 use http::{Response, StatusCode};
 use http_body_util::BodyExt as _;
 use http_body_util::combinators::BoxBody;
+use std::collections::HashSet;
 use std::convert::Infallible;
+use std::ffi::OsStr;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Instant;
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         Arc, Mutex,
-        atomic::{AtomicBool, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     },
     time::Duration,
 };
 use tokio::io::{self, AsyncWrite};
-use tokio::sync::Notify;
+use tokio::net::TcpStream;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
 use wasmtime::component::{Component, Linker, ResourceTable};
 use wasmtime::{Engine, Store, StoreLimits, UpdateDeadline};
+use wasmtime_cli_flags::WasmOptions;
+use wasmtime_cli_flags::opt::WasmtimeOptionValue;
 use wasmtime_wasi::p2::{StreamError, StreamResult};
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
 use wasmtime_wasi_http::bindings as p2;
-use wasmtime_wasi_http::io::TokioIo;
+use wasmtime_wasi_http::bindings::http::types::Scheme;
+use wasmtime_wasi_http::io::{TokioExecutor, TokioIo};
 use wasmtime_wasi_http::{
     DEFAULT_OUTGOING_BODY_BUFFER_CHUNKS, DEFAULT_OUTGOING_BODY_CHUNK_SIZE, WasiHttpCtx,
     WasiHttpView,
 };
 
 #[cfg(feature = "wasi-config")]
-use wasmtime_wasi_config::{WasiConfig, WasiConfigVariables};
+use wasmtime_wasi_config::{WasiConfig, WasiConfigProviders, WasiConfigVariables};
 #[cfg(feature = "wasi-keyvalue")]
 use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx, WasiKeyValueCtxBuilder};
 #[cfg(feature = "wasi-nn")]
@@ -54,7 +62,7 @@ struct Host {
     nn: Option<WasiNnCtx>,
 
     #[cfg(feature = "wasi-config")]
-    wasi_config: Option<WasiConfigVariables>,
+    wasi_config: Option<Arc<WasiConfigVariables>>,
 
     #[cfg(feature = "wasi-keyvalue")]
     wasi_keyvalue: Option<WasiKeyValueCtx>,
@@ -123,14 +131,119 @@ pub struct ServeCommand {
     #[arg(long, value_name = "SOCKADDR")]
     shutdown_addr: Option<SocketAddr>,
 
+    /// Path to a PEM-encoded certificate chain to serve HTTPS with.
+    ///
+    /// When specified the server only accepts TLS connections and negotiates
+    /// either HTTP/2 or HTTP/1.1 with clients through ALPN. Connections which
+    /// don't complete the TLS handshake within 10 seconds are closed.
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// Path to the PEM-encoded private key of the certificate passed to
+    /// `--tls-cert`.
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Maximum number of requests handled at the same time.
+    ///
+    /// Requests beyond this limit wait until a request being handled
+    /// finishes. If unspecified there is no limit.
+    #[arg(long, value_name = "N")]
+    max_concurrent_requests: Option<usize>,
+
+    /// Maximum number of requests waiting because of
+    /// `--max-concurrent-requests`.
+    ///
+    /// Requests beyond this limit are rejected with a 503 Service Unavailable
+    /// response. If unspecified any number of requests may wait.
+    #[arg(long, value_name = "N", requires = "max_concurrent_requests")]
+    max_queued_requests: Option<usize>,
+
+    /// Reuse an instance of the component for multiple requests, one at a
+    /// time, instead of creating a new instance for each request.
+    ///
+    /// Only components which declare that they're safe to reuse, with a
+    /// `wasmtime-serve-reuse-instances` custom section, are reused as state
+    /// left behind by one request is observable to the following ones. The
+    /// section may be in any core module of the component but isn't found in
+    /// precompiled components. Environment variables such as `REQUEST_ID` and
+    /// the log prefix of an instance are those of the request which created
+    /// it, while fuel and timeouts apply to each request. An instance which
+    /// traps or times out is never reused.
+    #[arg(long)]
+    reuse_instances: bool,
+
+    /// Maximum number of requests a single instance handles with
+    /// `--reuse-instances`. If unspecified there is no limit.
+    #[arg(long, value_name = "N", requires = "reuse_instances")]
+    instance_reuse_max_requests: Option<u64>,
+
+    /// Maximum time since its creation during which an instance is reused
+    /// with `--reuse-instances`. If unspecified there is no limit.
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "reuse_instances",
+        value_parser = parse_duration,
+    )]
+    instance_reuse_max_age: Option<Duration>,
+
+    /// Reload components when their files are modified.
+    ///
+    /// Components are compiled again in the background, once their file has
+    /// stopped changing, and handle new requests once compiled, while
+    /// requests already being handled finish with the previous version.
+    ///
+    /// Note that on Unix components are always reloaded when `SIGHUP` is
+    /// received, even without this flag, rather than the server exiting.
+    #[arg(long)]
+    watch: bool,
+
     /// Disable log prefixes of wasi-http handlers.
     /// if unspecified, logs will be prefixed with 'stdout|stderr [{req_id}] :: '
     #[arg(long)]
     no_logging_prefix: bool,
 
+    /// Path to a TOML file routing requests to different components.
+    ///
+    /// Each `[[route]]` table in the file has the `component` to run, relative
+    /// to the file, and optionally the `host` and `path` prefix of the
+    /// requests it handles along with the `args` to configure it with, which
+    /// are options of this command such as `--env` or `-S`. A request is
+    /// handled by the matching route with the longest `path`, preferring
+    /// routes with a `host`.
+    ///
+    /// Other options passed to this command are the defaults of every route,
+    /// which its `args` may override. Options configuring the engine, such
+    /// as `-O` or most `-W` options, are shared by all routes and can't be
+    /// used in the `args` of a route.
+    #[arg(long, value_name = "FILE")]
+    routes: Option<PathBuf>,
+
     /// The WebAssembly component to run.
-    #[arg(value_name = "WASM", required = true)]
-    component: PathBuf,
+    #[arg(
+        value_name = "WASM",
+        required_unless_present = "routes",
+        conflicts_with = "routes"
+    )]
+    component: Option<PathBuf>,
+
+    /// The `wasi:config` variables, loaded once in `execute` and reloaded in
+    /// the background so that changes to config files are picked up without
+    /// restarting the server.
+    #[cfg(feature = "wasi-config")]
+    #[arg(skip)]
+    wasi_config: Option<Arc<WasiConfigCache>>,
+
+    /// The `wasi:keyvalue` stores backed by files, opened once in `execute`
+    /// and shared by all requests. The in-memory store is created anew for
+    /// each request, so that requests don't observe each other.
+    ///
+    /// With `--routes` the files passed to `wasmtime serve` are opened once
+    /// and shared by all routes, which each add their own files to them.
+    #[cfg(feature = "wasi-keyvalue")]
+    #[arg(skip)]
+    wasi_keyvalue: Option<WasiKeyValueCtxBuilder>,
 }
 
 impl ServeCommand {
@@ -140,7 +253,25 @@ impl ServeCommand {
 
         // We force cli errors before starting to listen for connections so then
         // we don't accidentally delay them to the first request.
+        self.prepare()?;
+        let routes = match &self.routes {
+            Some(path) => load_routes(path, &self)?,
+            None => Vec::new(),
+        };
 
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .enable_io()
+            .build()?;
+
+        runtime.block_on(self.serve(routes))?;
+
+        Ok(())
+    }
+
+    /// Validates the options of this command and sets up the state shared by
+    /// all requests.
+    fn prepare(&mut self) -> Result<()> {
         if self.run.common.wasi.nn == Some(true) {
             #[cfg(not(feature = "wasi-nn"))]
             {
@@ -152,6 +283,10 @@ impl ServeCommand {
             bail!("wasi-threads does not support components yet")
         }
 
+        if self.max_concurrent_requests == Some(0) {
+            bail!("`--max-concurrent-requests` must be greater than zero");
+        }
+
         // The serve command requires both wasi-http and the component model, so
         // we enable those by default here.
         if self.run.common.wasi.http.replace(true) == Some(false) {
@@ -161,16 +296,143 @@ impl ServeCommand {
             bail!("components are required for the serve command, and must not be disabled");
         }
 
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .enable_time()
-            .enable_io()
-            .build()?;
+        if self.run.common.wasi.config == Some(true) {
+            #[cfg(feature = "wasi-config")]
+            {
+                let providers = self.run.wasi_config_providers();
+                self.wasi_config = Some(Arc::new(WasiConfigCache::new(providers)?));
+            }
+        }
+
+        // With `--routes` the stores are opened here even if only routes
+        // enable wasi-keyvalue, since files can only be opened once and are
+        // shared with routes. The stores of a route, along with its own
+        // files, are already set up by `inherit`.
+        #[cfg(feature = "wasi-keyvalue")]
+        if (self.run.common.wasi.keyvalue == Some(true) || self.routes.is_some())
+            && self.wasi_keyvalue.is_none()
+        {
+            self.wasi_keyvalue = Some(self.run.wasi_keyvalue_files(Default::default(), &[])?);
+        }
+
+        Ok(())
+    }
 
-        runtime.block_on(self.serve())?;
+    /// Configures this command, parsed from the `args` of a route, with the
+    /// options passed to `wasmtime serve` in `top` as defaults.
+    ///
+    /// Options configuring the engine are shared by all routes so they may
+    /// only be given once on the command line, and an error is returned if
+    /// the args of the route change them.
+    fn inherit(&mut self, top: &ServeCommand) -> Result<()> {
+        let common = &mut self.run.common;
+        common.opts = top.run.common.opts.clone();
+        common.codegen = top.run.common.codegen.clone();
+        common.debug = top.run.common.debug.clone();
+        common.wasm = top.run.common.wasm.clone();
+        common.wasi = top.run.common.wasi.clone();
+        common.configure()?;
+
+        // Only the limits applied to each store may differ between routes,
+        // along with the amount of fuel if fuel is enabled at all.
+        let engine_wasm = |wasm: &WasmOptions| WasmOptions {
+            timeout: None,
+            fuel: wasm.fuel.map(|_| 0),
+            max_table_elements: None,
+            max_instances: None,
+            max_tables: None,
+            max_memories: None,
+            trap_on_grow_failure: None,
+            ..wasm.clone()
+        };
+        if common.opts != top.run.common.opts
+            || common.codegen != top.run.common.codegen
+            || common.debug != top.run.common.debug
+            || engine_wasm(&common.wasm) != engine_wasm(&top.run.common.wasm)
+        {
+            bail!(
+                "`-O`, `-C` and `-D` options, and `-W` options other than `timeout`, `fuel` \
+                 and the `max-*` store limits, configure the engine shared by all routes \
+                 and must be passed to `wasmtime serve` instead of the args of a route"
+            );
+        }
+        #[cfg(feature = "signing")]
+        if !self.run.trusted_keys.is_empty() {
+            bail!(
+                "`--trusted-key` must be passed to `wasmtime serve` instead of the args of a route"
+            );
+        }
+
+        // Variables of the route replace those of the same name passed to
+        // `wasmtime serve`.
+        let vars = std::mem::take(&mut self.run.vars);
+        self.run.vars = top
+            .run
+            .vars
+            .iter()
+            .filter(|(key, _)| !vars.iter().any(|(k, _)| k == key))
+            .cloned()
+            .collect();
+        self.run.vars.extend(vars);
+        self.run.dirs = top
+            .run
+            .dirs
+            .iter()
+            .cloned()
+            .chain(self.run.dirs.drain(..))
+            .collect();
+        self.run.allow_precompiled |= top.run.allow_precompiled;
+
+        // Stores backed by files passed to `wasmtime serve` were opened by
+        // `top` and are shared, so only the route's own files are opened.
+        #[cfg(feature = "wasi-keyvalue")]
+        if let Some(shared) = &top.wasi_keyvalue {
+            self.wasi_keyvalue = Some(
+                self.run
+                    .wasi_keyvalue_files(shared.clone(), &top.run.common.wasi.keyvalue_file)?,
+            );
+        }
 
+        self.max_concurrent_requests = self.max_concurrent_requests.or(top.max_concurrent_requests);
+        self.max_queued_requests = self.max_queued_requests.or(top.max_queued_requests);
+        self.reuse_instances |= top.reuse_instances;
+        self.instance_reuse_max_requests = self
+            .instance_reuse_max_requests
+            .or(top.instance_reuse_max_requests);
+        self.instance_reuse_max_age = self.instance_reuse_max_age.or(top.instance_reuse_max_age);
+        self.no_logging_prefix |= top.no_logging_prefix;
         Ok(())
     }
 
+    /// Loads the certificate and key passed with `--tls-cert` and
+    /// `--tls-key`, if any, for accepting TLS connections.
+    fn tls_acceptor(&self) -> Result<Option<TlsAcceptor>> {
+        let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) else {
+            return Ok(None);
+        };
+        let open = |path: &PathBuf| -> Result<_> {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            Ok(std::io::BufReader::new(file))
+        };
+        let certs = rustls_pemfile::certs(&mut open(cert)?)
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("failed to read certificates from {}", cert.display()))?;
+        if certs.is_empty() {
+            bail!("no certificates found in {}", cert.display());
+        }
+        let key = rustls_pemfile::private_key(&mut open(key)?)
+            .with_context(|| format!("failed to read private key from {}", key.display()))?
+            .with_context(|| format!("no private key found in {}", key.display()))?;
+
+        let mut config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("invalid TLS certificate or key")?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Some(TlsAcceptor::from(Arc::new(config))))
+    }
+
     fn new_store(&self, engine: &Engine, req_id: u64) -> Result<Store<Host>> {
         let mut builder = WasiCtxBuilder::new();
         self.run.configure_wasip2(&mut builder)?;
@@ -229,33 +491,15 @@ impl ServeCommand {
         if self.run.common.wasi.config == Some(true) {
             #[cfg(feature = "wasi-config")]
             {
-                let vars = WasiConfigVariables::from_iter(
-                    self.run
-                        .common
-                        .wasi
-                        .config_var
-                        .iter()
-                        .map(|v| (v.key.clone(), v.value.clone())),
-                );
+                let vars = self.wasi_config.as_ref().unwrap().current();
                 host.wasi_config.replace(vars);
             }
         }
 
-        if self.run.common.wasi.keyvalue == Some(true) {
-            #[cfg(feature = "wasi-keyvalue")]
-            {
-                let ctx = WasiKeyValueCtxBuilder::new()
-                    .in_memory_data(
-                        self.run
-                            .common
-                            .wasi
-                            .keyvalue_in_memory_data
-                            .iter()
-                            .map(|v| (v.key.clone(), v.value.clone())),
-                    )
-                    .build();
-                host.wasi_keyvalue.replace(ctx);
-            }
+        #[cfg(feature = "wasi-keyvalue")]
+        if let Some(files) = &self.wasi_keyvalue {
+            host.wasi_keyvalue
+                .replace(self.run.wasi_keyvalue_ctx(files.clone()));
         }
 
         let mut store = Store::new(engine, host);
@@ -324,7 +568,7 @@ impl ServeCommand {
             #[cfg(feature = "wasi-config")]
             {
                 wasmtime_wasi_config::add_to_linker(linker, |h| {
-                    WasiConfig::from(h.wasi_config.as_ref().unwrap())
+                    WasiConfig::from(&**h.wasi_config.as_ref().unwrap())
                 })?;
             }
         }
@@ -353,17 +597,80 @@ impl ServeCommand {
         Ok(())
     }
 
-    async fn serve(mut self) -> Result<()> {
-        use hyper::server::conn::http1;
+    /// Compiles the component to serve, ready to handle requests.
+    fn load(&self, engine: &Engine) -> Result<Arc<LoadedComponent>> {
+        let (component, instance_pre) = self.instantiate_pre(engine)?;
+        let reusable = self.reuse_instances && self.declares_reuse()?;
+        Ok(LoadedComponent::new(component, instance_pre, reusable))
+    }
+
+    /// Returns whether the component declares that its instances may handle
+    /// more than one request with a [`REUSE_INSTANCES_SECTION`] custom
+    /// section, warning if it doesn't.
+    fn declares_reuse(&self) -> Result<bool> {
+        let Some(path) = &self.component else {
+            bail!("no component to serve");
+        };
+        let bytes =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let declared = wasmparser::Parser::new(0)
+            .parse_all(&bytes)
+            .any(|payload| match payload {
+                Ok(wasmparser::Payload::CustomSection(section)) => {
+                    section.name() == REUSE_INSTANCES_SECTION
+                }
+                _ => false,
+            });
+        if !declared {
+            eprintln!(
+                "warning: {} doesn't have a `{REUSE_INSTANCES_SECTION}` custom section, so \
+                 its instances won't be reused despite `--reuse-instances`",
+                path.display()
+            );
+        }
+        Ok(declared)
+    }
 
+    /// Creates the linker and loads the component of this command.
+    fn instantiate_pre(&self, engine: &Engine) -> Result<(Component, ProxyPre)> {
+        let mut linker = Linker::new(engine);
+
+        self.add_to_linker(&mut linker)?;
+
+        let Some(path) = &self.component else {
+            bail!("no component to serve");
+        };
+        let component = match self.run.load_module(engine, path)? {
+            RunTarget::Core(_) => bail!("The serve command currently requires a component"),
+            RunTarget::Component(c) => c,
+        };
+
+        let instance = linker.instantiate_pre(&component)?;
+        #[cfg(feature = "component-model-async")]
+        let instance = match wasmtime_wasi_http::p3::bindings::ProxyPre::new(instance.clone()) {
+            Ok(pre) => ProxyPre::P3(pre),
+            Err(_) => ProxyPre::P2(p2::ProxyPre::new(instance)?),
+        };
+        #[cfg(not(feature = "component-model-async"))]
+        let instance = ProxyPre::P2(p2::ProxyPre::new(instance)?);
+
+        Ok((component, instance))
+    }
+
+    async fn serve(mut self, routes: Vec<RouteConfig>) -> Result<()> {
         let mut config = self
             .run
             .common
             .config(use_pooling_allocator_by_default().unwrap_or(None))?;
+        self.run.configure_trusted_keys(&mut config)?;
         config.wasm_component_model(true);
         config.async_support(true);
 
-        if self.run.common.wasm.timeout.is_some() {
+        let timeouts = std::iter::once(&self)
+            .chain(routes.iter().map(|route| &route.cmd))
+            .filter_map(|cmd| cmd.run.common.wasm.timeout)
+            .min();
+        if timeouts.is_some() {
             config.epoch_interruption(true);
         }
 
@@ -372,29 +679,38 @@ impl ServeCommand {
                 config.profiler(s);
             }
             Some(Profile::Guest { .. }) => {
+                if self.reuse_instances {
+                    bail!("guest profiling is not supported with `--reuse-instances`");
+                }
+                if !routes.is_empty() {
+                    bail!("guest profiling is not supported with `--routes`");
+                }
                 config.epoch_interruption(true);
             }
+            Some(Profile::Sample { .. } | Profile::Fuel { .. }) => {
+                bail!("this profiling strategy is not supported by `wasmtime serve`");
+            }
             None => {}
         }
 
         let engine = Engine::new(&config)?;
-        let mut linker = Linker::new(&engine);
-
-        self.add_to_linker(&mut linker)?;
 
-        let component = match self.run.load_module(&engine, &self.component)? {
-            RunTarget::Core(_) => bail!("The serve command currently requires a component"),
-            RunTarget::Component(c) => c,
-        };
-
-        let instance = linker.instantiate_pre(&component)?;
-        #[cfg(feature = "component-model-async")]
-        let instance = match wasmtime_wasi_http::p3::bindings::ProxyPre::new(instance.clone()) {
-            Ok(pre) => ProxyPre::P3(pre),
-            Err(_) => ProxyPre::P2(p2::ProxyPre::new(instance)?),
+        let mut routes = routes
+            .into_iter()
+            .map(|route| {
+                let loaded = route.cmd.load(&engine)?;
+                Ok(Route {
+                    host: route.host,
+                    path: route.path,
+                    handler: ProxyHandler::new(route.cmd, engine.clone(), loaded),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let main = if routes.is_empty() {
+            Some(self.load(&engine)?)
+        } else {
+            None
         };
-        #[cfg(not(feature = "component-model-async"))]
-        let instance = ProxyPre::P2(p2::ProxyPre::new(instance)?);
 
         // Spawn background task(s) waiting for graceful shutdown signals. This
         // always listens for ctrl-c but additionally can listen for a TCP
@@ -420,6 +736,8 @@ impl ServeCommand {
             });
         }
 
+        let tls = self.tls_acceptor()?;
+
         let socket = match &self.addr {
             SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4()?,
             SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6()?,
@@ -436,20 +754,44 @@ impl ServeCommand {
         socket.bind(self.addr)?;
         let listener = socket.listen(100)?;
 
-        eprintln!("Serving HTTP on http://{}/", listener.local_addr()?);
+        if tls.is_some() {
+            eprintln!("Serving HTTPS on https://{}/", listener.local_addr()?);
+        } else {
+            eprintln!("Serving HTTP on http://{}/", listener.local_addr()?);
+        }
 
         log::info!("Listening on {}", self.addr);
 
         let epoch_interval = if let Some(Profile::Guest { interval, .. }) = self.run.profile {
             Some(interval)
-        } else if let Some(t) = self.run.common.wasm.timeout {
+        } else if let Some(t) = timeouts {
             Some(EPOCH_INTERRUPT_PERIOD.min(t))
         } else {
             None
         };
         let _epoch_thread = epoch_interval.map(|t| EpochThread::spawn(t, engine.clone()));
 
-        let handler = ProxyHandler::new(self, engine, instance);
+        let watch = self.watch;
+
+        // Without `--routes` all requests are handled by the component passed
+        // on the command line.
+        if let Some(loaded) = main {
+            routes.push(Route {
+                host: None,
+                path: String::new(),
+                handler: ProxyHandler::new(self, engine, loaded),
+            });
+        }
+        let routes: Arc<[Route]> = routes.into();
+
+        tokio::task::spawn({
+            let routes = routes.clone();
+            async move {
+                if let Err(e) = reload_components(routes, watch).await {
+                    eprintln!("error: {e:?}");
+                }
+            }
+        });
 
         loop {
             // Wait for a socket, but also "race" against shutdown to break out
@@ -459,54 +801,25 @@ impl ServeCommand {
                 _ = shutdown.requested.notified() => break,
                 v = listener.accept() => v?,
             };
-            let comp = component.clone();
-            let stream = TokioIo::new(stream);
-            let h = handler.clone();
+            let routes = routes.clone();
+            let tls = tls.clone();
             let shutdown_guard = shutdown.clone().increment();
             tokio::task::spawn(async move {
-                if let Err(e) = http1::Builder::new()
-                    .keep_alive(true)
-                    .serve_connection(
-                        stream,
-                        hyper::service::service_fn(move |req| {
-                            let comp = comp.clone();
-                            let h = h.clone();
-                            async move {
-                                use http_body_util::{BodyExt, Full};
-                                match handle_request(h, req, comp).await {
-                                    Ok(r) => Ok::<_, Infallible>(r),
-                                    Err(e) => {
-                                        eprintln!("error: {e:?}");
-                                        let error_html = "\
-<!doctype html>
-<html>
-<head>
-    <title>500 Internal Server Error</title>
-</head>
-<body>
-    <center>
-        <h1>500 Internal Server Error</h1>
-        <hr>
-        wasmtime
-    </center>
-</body>
-</html>";
-                                        Ok(Response::builder()
-                                            .status(StatusCode::INTERNAL_SERVER_ERROR)
-                                            .header("Content-Type", "text/html; charset=UTF-8")
-                                            .body(
-                                                Full::new(bytes::Bytes::from(error_html))
-                                                    .map_err(|_| unreachable!())
-                                                    .boxed(),
-                                            )
-                                            .unwrap())
-                                    }
-                                }
-                            }
-                        }),
-                    )
-                    .await
-                {
+                let result = match tls {
+                    Some(tls) => match tls_handshake(&tls, stream).await {
+                        Some(stream) => {
+                            let h2 = stream.get_ref().1.alpn_protocol() == Some(&b"h2"[..]);
+                            let scheme = Scheme::Https;
+                            serve_connection(TokioIo::new(stream), h2, scheme, routes).await
+                        }
+                        None => Ok(()),
+                    },
+                    None => {
+                        let scheme = Scheme::Http;
+                        serve_connection(TokioIo::new(stream), false, scheme, routes).await
+                    }
+                };
+                if let Err(e) = result {
                     eprintln!("error: {e:?}");
                 }
                 drop(shutdown_guard);
@@ -531,6 +844,212 @@ impl ServeCommand {
     }
 }
 
+/// Performs the TLS handshake of a new connection, giving up after
+/// `TLS_HANDSHAKE_TIMEOUT`.
+async fn tls_handshake(tls: &TlsAcceptor, stream: TcpStream) -> Option<TlsStream<TcpStream>> {
+    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+        Ok(Ok(stream)) => Some(stream),
+        Ok(Err(e)) => {
+            eprintln!("error: TLS handshake failed: {e}");
+            None
+        }
+        Err(_) => {
+            eprintln!("error: TLS handshake timed out");
+            None
+        }
+    }
+}
+
+/// Serves HTTP requests arriving on `stream` with `routes`, speaking HTTP/2 if
+/// `h2` is set and HTTP/1.1 otherwise.
+async fn serve_connection<T>(
+    stream: T,
+    h2: bool,
+    scheme: Scheme,
+    routes: Arc<[Route]>,
+) -> hyper::Result<()>
+where
+    T: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    use hyper::server::conn::{http1, http2};
+
+    let service = hyper::service::service_fn(move |req| {
+        let h = find_route(&routes, &req).cloned();
+        let scheme = scheme.clone();
+        async move {
+            use http_body_util::{BodyExt, Full};
+            let Some(h) = h else {
+                return Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(BoxBody::default())
+                        .unwrap(),
+                );
+            };
+            match handle_request(h, req, scheme).await {
+                Ok(r) => Ok::<_, Infallible>(r),
+                Err(e) => {
+                    eprintln!("error: {e:?}");
+                    let error_html = "\
+<!doctype html>
+<html>
+<head>
+    <title>500 Internal Server Error</title>
+</head>
+<body>
+    <center>
+        <h1>500 Internal Server Error</h1>
+        <hr>
+        wasmtime
+    </center>
+</body>
+</html>";
+                    Ok(Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .header("Content-Type", "text/html; charset=UTF-8")
+                        .body(
+                            Full::new(bytes::Bytes::from(error_html))
+                                .map_err(|_| unreachable!())
+                                .boxed(),
+                        )
+                        .unwrap())
+                }
+            }
+        }
+    });
+
+    if h2 {
+        http2::Builder::new(TokioExecutor)
+            .serve_connection(stream, service)
+            .await
+    } else {
+        http1::Builder::new()
+            .keep_alive(true)
+            .serve_connection(stream, service)
+            .await
+    }
+}
+
+/// A route parsed from the file passed to `--routes`.
+struct RouteConfig {
+    host: Option<String>,
+    path: String,
+    cmd: ServeCommand,
+}
+
+/// Parses the file passed to `--routes`.
+///
+/// Options given to `wasmtime serve` itself are the defaults of each route,
+/// which `top` holds.
+fn load_routes(file: &Path, top: &ServeCommand) -> Result<Vec<RouteConfig>> {
+    #[derive(serde_derive::Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct RoutesFile {
+        #[serde(default)]
+        route: Vec<RouteEntry>,
+    }
+
+    #[derive(serde_derive::Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct RouteEntry {
+        component: PathBuf,
+        host: Option<String>,
+        path: Option<String>,
+        #[serde(default)]
+        args: Vec<String>,
+    }
+
+    let contents = std::fs::read_to_string(file)
+        .with_context(|| format!("failed to read routes file {}", file.display()))?;
+    let entries = toml::from_str::<RoutesFile>(&contents)
+        .with_context(|| format!("failed to parse routes file {}", file.display()))?
+        .route;
+    if entries.is_empty() {
+        bail!("no routes found in {}", file.display());
+    }
+
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let mut seen = HashSet::new();
+    let mut routes = Vec::new();
+    for entry in entries {
+        let path = entry.path.unwrap_or_else(|| "/".to_string());
+        if !path.starts_with('/') {
+            bail!("route path `{path}` must start with `/`");
+        }
+        // Stored without a trailing `/` so that `/api` and `/api/` are the
+        // same prefix and `/` matches everything.
+        let path = path.trim_end_matches('/').to_string();
+        let host = entry.host.map(|host| host.to_ascii_lowercase());
+        if !seen.insert((host.clone(), path.clone())) {
+            bail!(
+                "multiple routes for host `{}` and path `{path}/`",
+                host.as_deref().unwrap_or("*"),
+            );
+        }
+
+        let component = dir.join(&entry.component);
+        let args = std::iter::once(OsStr::new("serve"))
+            .chain(entry.args.iter().map(OsStr::new))
+            .chain([component.as_os_str()]);
+        let mut cmd = ServeCommand::try_parse_from(args)
+            .with_context(|| format!("invalid args for {}", entry.component.display()))?;
+        if cmd.routes.is_some()
+            || cmd.tls_cert.is_some()
+            || cmd.shutdown_addr.is_some()
+            || cmd.run.profile.is_some()
+            || cmd.watch
+        {
+            bail!(
+                "`--routes`, `--tls-cert`, `--shutdown-addr`, `--profile` and `--watch` \
+                 apply to the whole server and can't be used in the args of a route"
+            );
+        }
+        cmd.inherit(top)
+            .with_context(|| format!("invalid args for {}", entry.component.display()))?;
+        cmd.prepare()?;
+
+        routes.push(RouteConfig { host, path, cmd });
+    }
+    Ok(routes)
+}
+
+/// A component handling the requests to a host and path prefix.
+struct Route {
+    /// The lowercase host of the requests handled, or `None` for any host.
+    host: Option<String>,
+    /// The prefix of the path of the requests handled, without a trailing
+    /// `/`.
+    path: String,
+    handler: ProxyHandler,
+}
+
+/// Returns the handler of the route which should handle `req`, if any.
+fn find_route<'a>(routes: &'a [Route], req: &Request) -> Option<&'a ProxyHandler> {
+    // HTTP/2 requests carry the host in their URI while HTTP/1.1 requests
+    // usually only have a `Host` header.
+    let header = req
+        .headers()
+        .get(http::header::HOST)
+        .and_then(|host| http::uri::Authority::try_from(host.as_bytes()).ok());
+    let host = req.uri().host().or(header.as_ref().map(|a| a.host()));
+    let path = req.uri().path();
+
+    routes
+        .iter()
+        .filter(|route| {
+            let host_matches = match &route.host {
+                Some(expected) => host.is_some_and(|host| host.eq_ignore_ascii_case(expected)),
+                None => true,
+            };
+            let path_matches = path
+                .strip_prefix(route.path.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+            host_matches && path_matches
+        })
+        .max_by_key(|route| (route.host.is_some(), route.path.len()))
+        .map(|route| &route.handler)
+}
+
 /// Helper structure to manage graceful shutdown int he accept loop above.
 #[derive(Default)]
 struct GracefulShutdown {
@@ -581,6 +1100,10 @@ impl GracefulShutdown {
     }
 }
 
+/// How long clients have to complete the TLS handshake before their connection
+/// is closed, so that slow clients can't hold up graceful shutdown.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// When executing with a timeout enabled, this is how frequently epoch
 /// interrupts will be executed to check for timeouts. If guest profiling
 /// is enabled, the guest epoch period will be used.
@@ -723,8 +1246,38 @@ fn setup_guest_profiler(
 struct ProxyHandlerInner {
     cmd: ServeCommand,
     engine: Engine,
-    instance_pre: ProxyPre,
     next_id: AtomicU64,
+    limit: Option<RequestLimit>,
+    /// The version of the component which new requests are handled with,
+    /// replaced when the component is reloaded.
+    current: Mutex<Arc<LoadedComponent>>,
+}
+
+/// A version of the component being served.
+struct LoadedComponent {
+    component: Component,
+    instance_pre: ProxyPre,
+    /// Whether instances may handle more than one request, which requires
+    /// both `--reuse-instances` and the component to declare that it's safe.
+    reusable: bool,
+    /// Instances which finished handling a request and may be reused for
+    /// another one, only populated if `reusable`.
+    idle: Mutex<Vec<ProxyInstance>>,
+    /// The requests being handled with this version of the component, which
+    /// finish with it even after the component has been reloaded.
+    requests: Arc<GracefulShutdown>,
+}
+
+impl LoadedComponent {
+    fn new(component: Component, instance_pre: ProxyPre, reusable: bool) -> Arc<LoadedComponent> {
+        Arc::new(LoadedComponent {
+            component,
+            instance_pre,
+            reusable,
+            idle: Mutex::new(Vec::new()),
+            requests: Arc::new(GracefulShutdown::default()),
+        })
+    }
 }
 
 enum ProxyPre {
@@ -749,24 +1302,289 @@ enum Proxy {
     P3(wasmtime_wasi_http::p3::bindings::Proxy),
 }
 
+/// An instance of the proxy component along with its store.
+struct ProxyInstance {
+    store: Store<Host>,
+    proxy: Proxy,
+    /// When this instance was created.
+    created: Instant,
+    /// How many requests this instance has handled.
+    requests: u64,
+}
+
+/// Limit on the number of requests handled at the same time, set with
+/// `--max-concurrent-requests`.
+struct RequestLimit {
+    permits: Arc<Semaphore>,
+    /// The maximum number of requests waiting for a permit, or `None` if
+    /// unbounded.
+    max_queued: Option<usize>,
+    /// The number of requests currently waiting for a permit.
+    queued: Arc<AtomicUsize>,
+}
+
+impl RequestLimit {
+    /// Waits for a permit to handle a request, returning `None` if the
+    /// request should be rejected because too many are already waiting.
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+
+        // Decrement the queue length again even if the request is dropped
+        // while waiting, e.g. because the client disconnected.
+        struct Queued(Arc<AtomicUsize>);
+        impl Drop for Queued {
+            fn drop(&mut self) {
+                self.0.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed);
+        let _queued = Queued(self.queued.clone());
+        if self.max_queued.is_some_and(|max| queued >= max) {
+            return None;
+        }
+        Some(self.permits.clone().acquire_owned().await.unwrap())
+    }
+}
+
 impl ProxyHandlerInner {
     fn next_req_id(&self) -> u64 {
         self.next_id.fetch_add(1, Ordering::Relaxed)
     }
+
+    /// Returns whether `instance` may still handle more requests according
+    /// to `--instance-reuse-max-requests` and `--instance-reuse-max-age`.
+    fn can_reuse(&self, instance: &ProxyInstance) -> bool {
+        self.cmd.reuse_instances
+            && self
+                .cmd
+                .instance_reuse_max_requests
+                .is_none_or(|max| instance.requests < max)
+            && self
+                .cmd
+                .instance_reuse_max_age
+                .is_none_or(|max| instance.created.elapsed() < max)
+    }
+
+    /// Returns the current version of the component along with a guard
+    /// which should be held while handling a request with it.
+    fn current(&self) -> (Arc<LoadedComponent>, impl Drop + Send + use<>) {
+        // The guard is taken while holding the lock so that `reload` can't
+        // close `requests` in between.
+        let current = self.current.lock().unwrap();
+        let guard = current.requests.clone().increment();
+        (current.clone(), guard)
+    }
+
+    /// Takes an idle instance of `loaded` which can handle another request,
+    /// if any.
+    ///
+    /// The fuel and epoch deadline of the instance are reset so that each
+    /// request gets the same budget regardless of previous requests.
+    fn take_idle(&self, loaded: &LoadedComponent) -> Result<Option<ProxyInstance>> {
+        loop {
+            let Some(mut instance) = loaded.idle.lock().unwrap().pop() else {
+                return Ok(None);
+            };
+            if !self.can_reuse(&instance) {
+                continue;
+            }
+            if let Some(fuel) = self.cmd.run.common.wasm.fuel {
+                instance.store.set_fuel(fuel)?;
+            }
+            if self.cmd.run.common.wasm.timeout.is_some() {
+                instance.store.set_epoch_deadline(1);
+            }
+            return Ok(Some(instance));
+        }
+    }
+
+    /// Makes `instance` of `loaded`, which successfully finished handling a
+    /// request, available to handle another one.
+    fn release(&self, loaded: &LoadedComponent, mut instance: ProxyInstance) {
+        instance.requests += 1;
+        if loaded.reusable && self.can_reuse(&instance) {
+            loaded.idle.lock().unwrap().push(instance);
+        }
+    }
 }
 
 #[derive(Clone)]
 struct ProxyHandler(Arc<ProxyHandlerInner>);
 
 impl ProxyHandler {
-    fn new(cmd: ServeCommand, engine: Engine, instance_pre: ProxyPre) -> Self {
+    fn new(cmd: ServeCommand, engine: Engine, loaded: Arc<LoadedComponent>) -> Self {
+        let limit = cmd.max_concurrent_requests.map(|max| RequestLimit {
+            permits: Arc::new(Semaphore::new(max)),
+            max_queued: cmd.max_queued_requests,
+            queued: Arc::new(AtomicUsize::new(0)),
+        });
         Self(Arc::new(ProxyHandlerInner {
             cmd,
             engine,
-            instance_pre,
             next_id: AtomicU64::from(0),
+            limit,
+            current: Mutex::new(loaded),
         }))
     }
+
+    /// Returns when the component file of this handler was last modified.
+    fn modified(&self) -> Option<std::time::SystemTime> {
+        let path = self.0.cmd.component.as_ref()?;
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Compiles the component of this handler again and, if successful,
+    /// handles new requests with it.
+    ///
+    /// Requests already being handled finish with the previous version of the
+    /// component.
+    async fn reload(&self) {
+        let inner = self.0.clone();
+        let path = inner.cmd.component.clone().unwrap_or_default();
+        let result = tokio::task::spawn_blocking(move || inner.cmd.load(&inner.engine))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result);
+        let new = match result {
+            Ok(new) => new,
+            Err(e) => {
+                eprintln!("error: failed to reload {}: {e:?}", path.display());
+                return;
+            }
+        };
+
+        let old = std::mem::replace(&mut *self.0.current.lock().unwrap(), new);
+        eprintln!("Reloaded {}", path.display());
+
+        if old.requests.close() {
+            return;
+        }
+        tokio::task::spawn(async move {
+            old.requests.complete.notified().await;
+            log::info!(
+                "Finished requests to the previous version of {}",
+                path.display()
+            );
+        });
+    }
+}
+
+/// The `wasi:config` variables of a command, loaded from its providers up
+/// front and then reloaded in the background so that requests neither wait
+/// on nor fail because of the providers.
+#[cfg(feature = "wasi-config")]
+struct WasiConfigCache {
+    providers: WasiConfigProviders,
+    /// The variables of the last successful load.
+    current: Mutex<Arc<WasiConfigVariables>>,
+    /// Whether the last reload failed, so that failures are reported once
+    /// instead of on every reload.
+    failed: AtomicBool,
+}
+
+#[cfg(feature = "wasi-config")]
+impl WasiConfigCache {
+    fn new(providers: WasiConfigProviders) -> Result<WasiConfigCache> {
+        let vars = providers.load()?;
+        Ok(WasiConfigCache {
+            providers,
+            current: Mutex::new(Arc::new(vars)),
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns the variables to handle a request with.
+    fn current(&self) -> Arc<WasiConfigVariables> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Loads the variables again, keeping the previous ones if that fails.
+    fn reload(&self) {
+        match self.providers.load() {
+            Ok(vars) => {
+                *self.current.lock().unwrap() = Arc::new(vars);
+                self.failed.store(false, Ordering::Relaxed);
+            }
+            Err(e) => {
+                if !self.failed.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "error: failed to reload wasi:config variables, keeping the \
+                         previous ones: {e:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The name of the custom section with which a component declares that its
+/// instances may handle more than one request with `--reuse-instances`.
+///
+/// The contents of the section are ignored, and it may be in any of the core
+/// modules of the component.
+const REUSE_INSTANCES_SECTION: &str = "wasmtime-serve-reuse-instances";
+
+/// How often component files are checked for changes with `--watch`.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reloads the components of `routes` when `SIGHUP` is received or, with
+/// `--watch`, when their files are modified.
+///
+/// The `wasi:config` variables of each route are reloaded on `SIGHUP` and
+/// every [`WATCH_INTERVAL`], even without `--watch`, which is cheap as config
+/// files are only parsed again when modified.
+///
+/// Note that this replaces the default behavior of `SIGHUP`, which is to
+/// terminate the process, regardless of `watch`.
+async fn reload_components(routes: Arc<[Route]>, watch: bool) -> Result<()> {
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+    // The modification time of each component when it was last loaded,
+    // along with its modification time when last checked.
+    let mut modified = routes
+        .iter()
+        .map(|route| {
+            let modified = route.handler.modified();
+            (modified, modified)
+        })
+        .collect::<Vec<_>>();
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+    loop {
+        let hangup_received = async {
+            #[cfg(unix)]
+            hangup.recv().await;
+            #[cfg(not(unix))]
+            std::future::pending::<()>().await;
+        };
+        let reload_all = tokio::select! {
+            _ = hangup_received => true,
+            _ = interval.tick() => false,
+        };
+
+        for (route, (loaded, seen)) in routes.iter().zip(&mut modified) {
+            #[cfg(feature = "wasi-config")]
+            if let Some(config) = &route.handler.0.cmd.wasi_config {
+                config.reload();
+            }
+            if !watch && !reload_all {
+                continue;
+            }
+
+            let now = route.handler.modified();
+            // Wait for a modified file to stay the same for a whole interval
+            // before reloading it, so that files which are still being
+            // written aren't compiled.
+            let stable = now == *seen;
+            *seen = now;
+            if reload_all || (now != *loaded && stable) {
+                *loaded = now;
+                route.handler.reload().await;
+            }
+        }
+    }
 }
 
 type Request = hyper::Request<hyper::body::Incoming>;
@@ -774,10 +1592,25 @@ type Request = hyper::Request<hyper::body::Incoming>;
 async fn handle_request(
     ProxyHandler(inner): ProxyHandler,
     req: Request,
-    component: Component,
+    scheme: Scheme,
 ) -> Result<hyper::Response<BoxBody<Bytes, anyhow::Error>>> {
     let (sender, receiver) = tokio::sync::oneshot::channel();
 
+    // The permit is held until the guest finishes running, which may be after
+    // the response has been sent.
+    let permit = match &inner.limit {
+        Some(limit) => match limit.acquire().await {
+            Some(permit) => Some(permit),
+            None => {
+                log::warn!("Rejecting request to {}, too many queued", req.uri());
+                return Ok(Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .body(BoxBody::default())?);
+            }
+        },
+        None => None,
+    };
+
     let req_id = inner.next_req_id();
 
     log::info!(
@@ -786,17 +1619,36 @@ async fn handle_request(
         req.uri()
     );
 
-    let mut store = inner.cmd.new_store(&inner.engine, req_id)?;
-
-    let write_profile = setup_epoch_handler(&inner.cmd, &mut store, component.clone())?;
+    let (loaded, request_guard) = inner.current();
+    let (instance, write_profile) = match inner.take_idle(&loaded)? {
+        Some(instance) => (instance, Box::new(|_: &mut Store<Host>| {}) as WriteProfile),
+        None => {
+            let mut store = inner.cmd.new_store(&inner.engine, req_id)?;
+            let write_profile =
+                setup_epoch_handler(&inner.cmd, &mut store, loaded.component.clone())?;
+            let proxy = loaded.instance_pre.instantiate(&mut store).await?;
+            let instance = ProxyInstance {
+                store,
+                proxy,
+                created: Instant::now(),
+                requests: 0,
+            };
+            (instance, write_profile)
+        }
+    };
+    let ProxyInstance {
+        mut store,
+        proxy,
+        created,
+        requests,
+    } = instance;
     let timeout = inner.cmd.run.common.wasm.timeout.unwrap_or(Duration::MAX);
 
-    match inner.instance_pre.instantiate(&mut store).await? {
+    match proxy {
         Proxy::P2(proxy) => {
-            let req = store
-                .data_mut()
-                .new_incoming_request(p2::http::types::Scheme::Http, req)?;
+            let req = store.data_mut().new_incoming_request(scheme, req)?;
             let out = store.data_mut().new_response_outparam(sender)?;
+            let inner = inner.clone();
             let task = tokio::task::spawn(async move {
                 let result = tokio::time::timeout(
                     timeout,
@@ -812,6 +1664,17 @@ async fn handle_request(
                 }
 
                 write_profile(&mut store);
+                inner.release(
+                    &loaded,
+                    ProxyInstance {
+                        store,
+                        proxy: Proxy::P2(proxy),
+                        created,
+                        requests,
+                    },
+                );
+                drop(permit);
+                drop(request_guard);
 
                 Ok(())
             });
@@ -845,9 +1708,10 @@ async fn handle_request(
             use wasmtime_wasi_http::p3::bindings::http::types::{ErrorCode, Request};
 
             let (tx, rx) = tokio::sync::oneshot::channel();
+            let inner = inner.clone();
 
             tokio::task::spawn(async move {
-                let guest_result = store.run_concurrent(async move |store| {
+                let guest_result = store.run_concurrent(async |store| {
                     let (req, body) = req.into_parts();
                     let body = body.map_err(ErrorCode::from_hyper_request_error);
                     let req = http::Request::from_parts(req, body);
@@ -871,6 +1735,17 @@ async fn handle_request(
                 }
 
                 write_profile(&mut store);
+                inner.release(
+                    &loaded,
+                    ProxyInstance {
+                        store,
+                        proxy: Proxy::P3(proxy),
+                        created,
+                        requests,
+                    },
+                );
+                drop(permit);
+                drop(request_guard);
 
                 anyhow::Ok(())
             });
@@ -1005,6 +1880,10 @@ impl AsyncWrite for LogStream {
     }
 }
 
+fn parse_duration(s: &str) -> Result<Duration> {
+    WasmtimeOptionValue::parse(Some(s))
+}
+
 /// The pooling allocator is tailor made for the `wasmtime serve` use case, so
 /// try to use it when we can. The main cost of the pooling allocator, however,
 /// is the virtual memory required to run it. Not all systems support the same
@@ -1,10 +1,16 @@
+#[cfg(feature = "component-model")]
+use crate::commands::run::CliLinker;
 use crate::commands::run::{CliInstance, Preloads, RunCommand};
 use crate::common::{RunCommon, RunTarget};
+#[cfg(feature = "component-model")]
+use anyhow::bail;
 use anyhow::{Context, Result};
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::PathBuf;
 use wasmtime::Module;
+#[cfg(feature = "component-model")]
+use wasmtime::component::Component;
 use wasmtime_wizer::Wizer;
 
 #[derive(clap::Parser)]
@@ -54,34 +60,25 @@ impl WizerCommand {
         #[cfg(feature = "wat")]
         let wasm = wat::parse_bytes(&wasm)?;
 
-        // Instrument the input wasm with wizer.
-        let (cx, instrumented_wasm) = self.wizer.instrument(&wasm)?;
-
-        // Execute a rough equivalent of
-        // `wasmtime run --invoke <..> <instrumented-wasm>`
         let mut run = RunCommand {
             run: self.run,
             argv0: None,
-            invoke: Some(self.wizer.get_init_func().to_string()),
+            invoke: None,
             module_and_args: vec![self.input.clone().into()],
             preloads: self.preloads.clone(),
-        };
-        let engine = run.new_engine()?;
-        let main = RunTarget::Core(Module::new(&engine, &instrumented_wasm)?);
-        let (mut store, mut linker) = run.new_store_and_linker(&engine, &main)?;
-        #[allow(
-            irrefutable_let_patterns,
-            reason = "infallible when components are disabled"
-        )]
-        let CliInstance::Core(instance) =
-            run.instantiate_and_run(&engine, &mut linker, &main, &mut store)?
-        else {
-            unreachable!()
+            coverage: None,
+            #[cfg(feature = "debug-adapter")]
+            debug_adapter: None,
         };
 
-        // Use our state to capture a snapshot with Wizer and then serialize
-        // that.
-        let final_wasm = self.wizer.snapshot(cx, &mut store, &instance)?;
+        #[cfg(feature = "component-model")]
+        let final_wasm = if wasmparser::Parser::is_component(&wasm) {
+            wizen_component(&self.wizer, &mut run, &wasm)?
+        } else {
+            wizen_module(&self.wizer, &mut run, &wasm)?
+        };
+        #[cfg(not(feature = "component-model"))]
+        let final_wasm = wizen_module(&self.wizer, &mut run, &wasm)?;
 
         match &self.output {
             Some(file) => fs::write(file, &final_wasm).context("failed to write output file")?,
@@ -92,3 +89,67 @@ impl WizerCommand {
         Ok(())
     }
 }
+
+fn wizen_module(wizer: &Wizer, run: &mut RunCommand, wasm: &[u8]) -> Result<Vec<u8>> {
+    // Instrument the input wasm with wizer.
+    let (cx, instrumented_wasm) = wizer.instrument(wasm)?;
+
+    // Execute a rough equivalent of
+    // `wasmtime run --invoke <..> <instrumented-wasm>`
+    run.invoke = Some(wizer.get_init_func().to_string());
+    let engine = run.new_engine()?;
+    let main = RunTarget::Core(Module::new(&engine, &instrumented_wasm)?);
+    let (mut store, mut linker) = run.new_store_and_linker(&engine, &main)?;
+    #[allow(
+        irrefutable_let_patterns,
+        reason = "infallible when components are disabled"
+    )]
+    let CliInstance::Core(instance) =
+        run.instantiate_and_run(&engine, &mut linker, &main, &mut store)?
+    else {
+        unreachable!()
+    };
+
+    // Use our state to capture a snapshot with Wizer and then serialize
+    // that.
+    wizer.snapshot(cx, &mut store, &instance)
+}
+
+#[cfg(feature = "component-model")]
+fn wizen_component(wizer: &Wizer, run: &mut RunCommand, wasm: &[u8]) -> Result<Vec<u8>> {
+    if !run.preloads.modules.is_empty() {
+        bail!("--preload cannot be used with components");
+    }
+
+    let (cx, instrumented_wasm) = wizer.instrument_component(wasm)?;
+
+    let engine = run.new_engine()?;
+    let component = Component::new(&engine, &instrumented_wasm)?;
+    let main = RunTarget::Component(component.clone());
+    let (mut store, linker) = run.new_store_and_linker(&engine, &main)?;
+    let CliLinker::Component(linker) = linker else {
+        unreachable!()
+    };
+
+    // Unlike `wasmtime run --invoke` the initialization function is
+    // called directly here as its (empty) results must not be printed to
+    // stdout, which may be where the output is written.
+    let init_func = wizer.get_component_init_func();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .enable_io()
+        .build()?;
+    let instance = runtime.block_on(async {
+        let instance = linker.instantiate_async(&mut store, &component).await?;
+        let func = instance
+            .get_typed_func::<(), ()>(&mut store, init_func)
+            .with_context(|| format!("failed to find a `{init_func}: func()` export"))?;
+        func.call_async(&mut store, ())
+            .await
+            .with_context(|| format!("the `{init_func}` function trapped"))?;
+        func.post_return_async(&mut store).await?;
+        anyhow::Ok(instance)
+    })?;
+
+    wizer.snapshot_component(cx, &mut store, &instance)
+}
@@ -4,7 +4,7 @@ use anyhow::{Context, Result, anyhow, bail};
 use clap::Parser;
 use std::net::TcpListener;
 use std::{fs::File, path::Path, time::Duration};
-use wasmtime::{Engine, Module, Precompiled, StoreLimits, StoreLimitsBuilder};
+use wasmtime::{Config, Engine, Module, Precompiled, StoreLimits, StoreLimitsBuilder};
 use wasmtime_cli_flags::{CommonOptions, opt::WasmtimeOptionValue};
 use wasmtime_wasi::WasiCtxBuilder;
 
@@ -57,7 +57,20 @@ pub struct RunCommon {
     #[arg(long = "allow-precompiled")]
     pub allow_precompiled: bool,
 
-    /// Profiling strategy (valid options are: perfmap, jitdump, vtune, guest)
+    /// Trust precompiled modules signed with the Ed25519 public key in the
+    /// PEM file at `PATH`.
+    ///
+    /// When specified precompiled modules are only executed if they were
+    /// signed, for example via `wasmtime compile --sign-key`, by one of the
+    /// trusted keys. Modules signed by a trusted key don't additionally
+    /// require `--allow-precompiled`. This option may be specified multiple
+    /// times to trust multiple keys.
+    #[cfg(feature = "signing")]
+    #[arg(long = "trusted-key", value_name = "PATH")]
+    pub trusted_keys: Vec<std::path::PathBuf>,
+
+    /// Profiling strategy (valid options are: perfmap, jitdump, vtune, guest,
+    /// sample, fuel)
     ///
     /// The perfmap, jitdump, and vtune profiling strategies integrate Wasmtime
     /// with external profilers such as `perf`. The guest profiling strategy
@@ -65,13 +78,31 @@ pub struct RunCommon {
     /// `wasmtime-guest-profile.json` by default which can be viewed at
     /// https://profiler.firefox.com/.
     ///
-    /// The `guest` option can be additionally configured as:
+    /// The sample profiling strategy also samples in-process, but aggregates
+    /// samples by WebAssembly function and writes them to
+    /// `wasmtime-profile.svg` by default. The output format is chosen by the
+    /// file extension: `.svg` writes a flame graph, `.pb` or `.pprof` writes a
+    /// pprof profile, and anything else writes collapsed stacks suitable for
+    /// flame graph tools.
+    ///
+    /// The `guest` and `sample` options can be additionally configured as:
     ///
     ///     --profile=guest[,path[,interval]]
+    ///     --profile=sample[,path[,interval]]
     ///
     /// where `path` is where to write the profile and `interval` is the
     /// duration between samples. When used with `--wasm-timeout` the timeout
     /// will be rounded up to the nearest multiple of this interval.
+    ///
+    /// The fuel profiling strategy counts the fuel consumed by each
+    /// WebAssembly function, which is deterministic across runs and machines,
+    /// and reports it when execution finishes. It's configured as:
+    ///
+    ///     --profile=fuel[,path]
+    ///
+    /// where the report is written to `path` if given, or to stderr
+    /// otherwise. Unless `-W fuel=N` is also passed, execution isn't limited
+    /// by fuel.
     #[arg(
         long,
         value_name = "STRATEGY",
@@ -142,6 +173,26 @@ impl RunCommon {
         limits.build()
     }
 
+    /// Configures `config` to trust the keys passed via `--trusted-key`.
+    #[cfg_attr(
+        not(feature = "signing"),
+        expect(unused_variables, reason = "only used to trust keys")
+    )]
+    pub fn configure_trusted_keys(&self, config: &mut Config) -> Result<()> {
+        #[cfg(feature = "signing")]
+        for path in &self.trusted_keys {
+            use ed25519_dalek::pkcs8::DecodePublicKey;
+
+            let pem = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read trusted key: {}", path.display()))?;
+            let key = ed25519_dalek::VerifyingKey::from_public_key_pem(&pem)
+                .map_err(|e| anyhow!("{e}"))
+                .with_context(|| format!("invalid Ed25519 public key: {}", path.display()))?;
+            config.trust_signing_key(key.as_bytes())?;
+        }
+        Ok(())
+    }
+
     pub fn ensure_allow_precompiled(&self) -> Result<()> {
         if self.allow_precompiled {
             Ok(())
@@ -219,10 +270,19 @@ impl RunCommon {
         #[cfg(feature = "component-model")] deserialize_component: impl FnOnce() -> Result<Component>,
     ) -> Result<RunTarget> {
         Ok(match Engine::detect_precompiled(bytes) {
+            #[cfg(feature = "signing")]
+            Some(Precompiled::Module) if !self.trusted_keys.is_empty() => {
+                RunTarget::Core(Module::deserialize_verified(engine, bytes)?)
+            }
             Some(Precompiled::Module) => {
                 self.ensure_allow_precompiled()?;
                 RunTarget::Core(deserialize_module()?)
             }
+            #[cfg(all(feature = "signing", feature = "component-model"))]
+            Some(Precompiled::Component) if !self.trusted_keys.is_empty() => {
+                self.ensure_allow_components()?;
+                RunTarget::Component(Component::deserialize_verified(engine, bytes)?)
+            }
             #[cfg(feature = "component-model")]
             Some(Precompiled::Component) => {
                 self.ensure_allow_precompiled()?;
@@ -396,12 +456,99 @@ impl RunCommon {
 
         Ok(())
     }
+
+    /// Creates the `wasi:config` providers described by the `-S config-*`
+    /// flags.
+    ///
+    /// Environment prefixes are layered first, then config files, and finally
+    /// explicit `-S config-var` values, so later sources override earlier
+    /// ones.
+    #[cfg(feature = "wasi-config")]
+    pub fn wasi_config_providers(&self) -> wasmtime_wasi_config::WasiConfigProviders {
+        use wasmtime_wasi_config::{ConfigFile, EnvPrefix, WasiConfigVariables};
+
+        let mut providers = wasmtime_wasi_config::WasiConfigProviders::new();
+        for prefix in self.common.wasi.config_var_prefix.iter() {
+            providers.push(EnvPrefix::new(prefix.clone()));
+        }
+        for file in self.common.wasi.config_file.iter() {
+            providers.push(ConfigFile::new(file));
+        }
+        providers.push(WasiConfigVariables::from_iter(
+            self.common
+                .wasi
+                .config_var
+                .iter()
+                .map(|v| (v.key.clone(), v.value.clone())),
+        ));
+        providers
+    }
+
+    /// Opens the `wasi:keyvalue` stores backed by files with
+    /// `-S keyvalue-file` and adds them to `builder`, except for those in
+    /// `opened` which `builder` already holds.
+    ///
+    /// Clones of the returned builder share the opened files, and
+    /// [`RunCommon::wasi_keyvalue_ctx`] adds the in-memory store to it.
+    #[cfg(feature = "wasi-keyvalue")]
+    pub fn wasi_keyvalue_files(
+        &self,
+        mut builder: wasmtime_wasi_keyvalue::WasiKeyValueCtxBuilder,
+        opened: &[wasmtime_cli_flags::KeyValuePair],
+    ) -> Result<wasmtime_wasi_keyvalue::WasiKeyValueCtxBuilder> {
+        for store in self.common.wasi.keyvalue_file.iter() {
+            if opened.contains(store) {
+                continue;
+            }
+            if store.value.is_empty() {
+                bail!(
+                    "missing path for key-value store `{}`, expected `-S keyvalue-file=<identifier>=<path>`",
+                    store.key
+                );
+            }
+            let backend = wasmtime_wasi_keyvalue::FileBackend::open(&store.value)
+                .with_context(|| format!("failed to open key-value database `{}`", store.value))?;
+            builder = builder.backend(store.key.clone(), backend);
+        }
+        Ok(builder)
+    }
+
+    /// Creates a `wasi:keyvalue` context with the stores of `files` and a new
+    /// in-memory store holding the data of `-S keyvalue-in-memory-data`.
+    #[cfg(feature = "wasi-keyvalue")]
+    pub fn wasi_keyvalue_ctx(
+        &self,
+        files: wasmtime_wasi_keyvalue::WasiKeyValueCtxBuilder,
+    ) -> wasmtime_wasi_keyvalue::WasiKeyValueCtx {
+        // A file registered for the empty identifier takes precedence over
+        // the in-memory store.
+        if self
+            .common
+            .wasi
+            .keyvalue_file
+            .iter()
+            .any(|s| s.key.is_empty())
+        {
+            return files.build();
+        }
+        files
+            .in_memory_data(
+                self.common
+                    .wasi
+                    .keyvalue_in_memory_data
+                    .iter()
+                    .map(|v| (v.key.clone(), v.value.clone())),
+            )
+            .build()
+    }
 }
 
 #[derive(Clone, PartialEq)]
 pub enum Profile {
     Native(wasmtime::ProfilingStrategy),
     Guest { path: String, interval: Duration },
+    Sample { path: String, interval: Duration },
+    Fuel { path: Option<String> },
 }
 
 impl Profile {
@@ -425,6 +572,22 @@ impl Profile {
                 path: path.to_string(),
                 interval: WasmtimeOptionValue::parse(Some(dur))?,
             }),
+            ["sample"] => Ok(Profile::Sample {
+                path: "wasmtime-profile.svg".to_string(),
+                interval: Duration::from_millis(10),
+            }),
+            ["sample", path] => Ok(Profile::Sample {
+                path: path.to_string(),
+                interval: Duration::from_millis(10),
+            }),
+            ["sample", path, dur] => Ok(Profile::Sample {
+                path: path.to_string(),
+                interval: WasmtimeOptionValue::parse(Some(dur))?,
+            }),
+            ["fuel"] => Ok(Profile::Fuel { path: None }),
+            ["fuel", path] => Ok(Profile::Fuel {
+                path: Some(path.to_string()),
+            }),
             _ => bail!("unknown profiling strategy: {s}"),
         }
     }
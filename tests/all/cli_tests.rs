@@ -109,6 +109,45 @@ fn run_coredump_smoketest() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn run_component_coredump() -> Result<()> {
+    let coredump_file = NamedTempFile::new()?;
+    let coredump_arg = format!("-Dcoredump={}", coredump_file.path().display());
+    let err = run_wasmtime(&[
+        "run",
+        "--invoke",
+        "run()",
+        "-Ccache=n",
+        "-Wcomponent-model",
+        &coredump_arg,
+        "tests/all/cli_tests/component-coredump.wat",
+    ])
+    .unwrap_err();
+    assert!(err.to_string().contains("core dumped at"));
+
+    let output = run_wasmtime(&["coredump", coredump_file.path().to_str().unwrap()])?;
+    assert!(
+        output.contains(
+            "component instances:\n  \
+             0: trapper\n    \
+             core instances: []\n    \
+             handles: 0 own, 0 borrow, 0 other\n    \
+             0.0: inner\n      \
+             core instances: [0]\n"
+        ),
+        "{output}"
+    );
+    assert!(
+        output.contains("export `run` of component instance 0 (trapper)"),
+        "{output}"
+    );
+    assert!(
+        output.contains("in component instance 0.0 (inner)"),
+        "{output}"
+    );
+    Ok(())
+}
+
 // Running simple wat
 #[test]
 fn run_wasmtime_simple_wat() -> Result<()> {
@@ -726,6 +765,31 @@ fn wasm_flags_without_subcommand() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "wmemcheck")]
+fn wmemcheck_reports_leaks() -> Result<()> {
+    let output = get_wasmtime_command()?
+        .args(&[
+            "run",
+            "-Wwmemcheck",
+            "tests/all/cli_tests/wmemcheck-leak.wat",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("total heap usage: 2 allocs, 1 frees, 40 bytes allocated"),
+        "bad stderr: {stderr}",
+    );
+    assert!(
+        stderr.contains("24 bytes in 1 blocks are definitely lost in loss record 1 of 1"),
+        "bad stderr: {stderr}",
+    );
+    assert!(stderr.contains(": malloc ("), "bad stderr: {stderr}");
+    assert!(stderr.contains(": leak ("), "bad stderr: {stderr}");
+    Ok(())
+}
+
 #[test]
 fn wasi_misaligned_pointer() -> Result<()> {
     let output = get_wasmtime_command()?
@@ -1114,6 +1178,7 @@ mod test_programs {
     use std::thread::{self, JoinHandle};
     use test_programs_artifacts::*;
     use tokio::net::TcpStream;
+    use wasmtime_wasi_http::io::TokioExecutor;
 
     macro_rules! assert_test_exists {
         ($name:ident) => {
@@ -1510,6 +1575,8 @@ mod test_programs {
         child: Option<Child>,
         stdout: Option<JoinHandle<io::Result<Vec<u8>>>>,
         stderr: Option<JoinHandle<io::Result<Vec<u8>>>>,
+        /// Warnings printed before the server started listening.
+        warnings: String,
         addr: SocketAddr,
         shutdown_addr: SocketAddr,
     }
@@ -1542,9 +1609,15 @@ mod test_programs {
             // This is done to figure out what `:0` was bound to in the child
             // process.
             let mut line = String::new();
+            let mut warnings = String::new();
             let mut stderr = BufReader::new(child.stderr.take().unwrap());
             let mut read_addr_from_line = |prefix: &str| -> Result<SocketAddr> {
                 stderr.read_line(&mut line)?;
+                while line.starts_with("warning:") {
+                    warnings.push_str(&line);
+                    line.truncate(0);
+                    stderr.read_line(&mut line)?;
+                }
 
                 if !line.starts_with(prefix) {
                     bail!("input line `{line}` didn't start with `{prefix}`");
@@ -1562,7 +1635,7 @@ mod test_programs {
                 }
             };
             let shutdown_addr = read_addr_from_line("Listening for shutdown");
-            let addr = read_addr_from_line("Serving HTTP on");
+            let addr = read_addr_from_line("Serving HTTP");
             let (shutdown_addr, addr) = match (shutdown_addr, addr) {
                 (Ok(a), Ok(b)) => (a, b),
                 // If either failed kill the child and otherwise try to shepherd
@@ -1587,6 +1660,7 @@ mod test_programs {
                 })),
 
                 child: Some(child),
+                warnings,
                 addr,
                 shutdown_addr,
             })
@@ -1623,7 +1697,11 @@ mod test_programs {
 
             Ok((
                 String::from_utf8_lossy(&output.stdout).into_owned(),
-                String::from_utf8_lossy(&output.stderr).into_owned(),
+                format!(
+                    "{}{}",
+                    self.warnings,
+                    String::from_utf8_lossy(&output.stderr)
+                ),
             ))
         }
 
@@ -1647,6 +1725,54 @@ mod test_programs {
             Ok(http::Response::from_parts(parts, body))
         }
 
+        /// Send a request to this server over TLS, negotiating `alpn` as the
+        /// protocol, and wait for the response.
+        async fn send_tls_request(
+            &self,
+            alpn: &[u8],
+            req: http::Request<String>,
+        ) -> Result<http::Response<String>> {
+            let mut roots = rustls::RootCertStore::empty();
+            let pem = std::fs::read(TLS_CERT)?;
+            for cert in rustls_pemfile::certs(&mut &pem[..]) {
+                roots.add(cert?)?;
+            }
+            let mut config = rustls::ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            config.alpn_protocols = vec![alpn.to_vec()];
+            let connector = tokio_rustls::TlsConnector::from(std::sync::Arc::new(config));
+
+            let tcp = TcpStream::connect(&self.addr)
+                .await
+                .context("failed to connect")?;
+            let domain = rustls::pki_types::ServerName::try_from("localhost")?;
+            let tls = connector
+                .connect(domain, tcp)
+                .await
+                .context("failed TLS handshake")?;
+            assert_eq!(tls.get_ref().1.alpn_protocol(), Some(alpn));
+            let tls = wasmtime_wasi_http::io::TokioIo::new(tls);
+
+            let response = if alpn == b"h2" {
+                let (mut send, conn) = hyper::client::conn::http2::handshake(TokioExecutor, tls)
+                    .await
+                    .context("failed http handshake")?;
+                tokio::task::spawn(conn);
+                send.send_request(req).await
+            } else {
+                let (mut send, conn) = hyper::client::conn::http1::handshake(tls)
+                    .await
+                    .context("failed http handshake")?;
+                tokio::task::spawn(conn);
+                send.send_request(req).await
+            };
+            let (parts, body) = response.context("error sending request")?.into_parts();
+            let body = body.collect().await.context("failed to read body")?;
+            let body = std::str::from_utf8(&body.to_bytes())?.to_string();
+            Ok(http::Response::from_parts(parts, body))
+        }
+
         async fn start_requests(
             &self,
         ) -> Result<(
@@ -1664,6 +1790,9 @@ mod test_programs {
         }
     }
 
+    const TLS_CERT: &str = "tests/all/cli_tests/localhost.crt";
+    const TLS_KEY: &str = "tests/all/cli_tests/localhost.key";
+
     // Don't leave child processes running by accident so kill the child process
     // if our server goes away.
     impl Drop for WasmtimeServe {
@@ -1735,6 +1864,283 @@ mod test_programs {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn p2_cli_serve_tls() -> Result<()> {
+        let server = WasmtimeServe::new(P2_CLI_SERVE_ECHO_ENV_COMPONENT, |cmd| {
+            cmd.arg(format!("--tls-cert={TLS_CERT}"));
+            cmd.arg(format!("--tls-key={TLS_KEY}"));
+            cmd.arg("--env=FOO=bar");
+            cmd.arg("-Scli");
+        })?;
+
+        for alpn in [&b"h2"[..], b"http/1.1"] {
+            let resp = server
+                .send_tls_request(
+                    alpn,
+                    hyper::Request::builder()
+                        .uri("https://localhost/")
+                        .header("env", "FOO")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await?;
+            assert!(resp.status().is_success());
+            assert_eq!(
+                resp.headers().get("env"),
+                Some(&HeaderValue::from_static("bar"))
+            );
+        }
+
+        // Plaintext connections are rejected.
+        assert!(
+            server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri("http://localhost/")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await
+                .is_err()
+        );
+
+        server.finish()?;
+        Ok(())
+    }
+
+    /// Sends `count` requests one at a time to `server`, returning the value
+    /// of the `request-id` header of each response.
+    ///
+    /// The server must run with `--max-concurrent-requests=1`, so that each
+    /// request waits for the previous one to release its instance.
+    async fn serve_request_ids(server: &WasmtimeServe, count: usize) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for _ in 0..count {
+            let resp = server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri("http://localhost/")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await?;
+            assert!(resp.status().is_success());
+            ids.push(resp.headers()["request-id"].to_str()?.to_string());
+        }
+        Ok(ids)
+    }
+
+    #[tokio::test]
+    async fn p2_cli_serve_reusable() -> Result<()> {
+        let server = WasmtimeServe::new(P2_CLI_SERVE_REUSABLE_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("-Wfuel=1000000");
+            cmd.arg("--max-concurrent-requests=1");
+            cmd.arg("--reuse-instances");
+            cmd.arg("--instance-reuse-max-requests=2");
+        })?;
+
+        // Each instance is identified by the id of the request which created
+        // it, and handles two requests.
+        let ids = serve_request_ids(&server, 5).await?;
+        assert_eq!(ids, ["0", "0", "2", "2", "4"]);
+
+        server.finish()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn p2_cli_serve_reuse_instances_undeclared() -> Result<()> {
+        let server = WasmtimeServe::new(P2_CLI_SERVE_ECHO_ENV_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("--max-concurrent-requests=1");
+            cmd.arg("--reuse-instances");
+        })?;
+
+        // Components which don't declare that they're safe to reuse get a new
+        // instance for each request.
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let resp = server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri("http://localhost/")
+                        .header("env", "REQUEST_ID")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await?;
+            assert!(resp.status().is_success());
+            ids.push(resp.headers()["env"].to_str()?.to_string());
+        }
+        assert_eq!(ids, ["0", "1"]);
+
+        let (_, stderr) = server.finish()?;
+        assert!(
+            stderr.contains("custom section, so its instances won't be reused"),
+            "bad stderr: {stderr}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn p2_cli_serve_max_concurrent_requests() -> Result<()> {
+        let server = WasmtimeServe::new(P2_CLI_SERVE_SLEEP_COMPONENT, |cmd| {
+            cmd.arg("-Scli").arg("-Wtimeout=2s");
+            cmd.arg("--max-concurrent-requests=1");
+            cmd.arg("--max-queued-requests=0");
+        })?;
+
+        let request = || {
+            hyper::Request::builder()
+                .uri("http://localhost/")
+                .body(String::new())
+                .context("failed to make request")
+        };
+        let (first, second) = tokio::join!(server.send_request(request()?), async {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            server.send_request(request()?).await
+        });
+
+        // The first request times out while the second is rejected as it
+        // arrives while the first one is still being handled.
+        assert!(first?.status().is_server_error());
+        assert_eq!(second?.status(), hyper::StatusCode::SERVICE_UNAVAILABLE);
+
+        let (_, stderr) = server.finish()?;
+        assert!(stderr.contains("guest timed out"), "bad stderr: {stderr}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn p2_cli_serve_routes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let routes = dir.path().join("routes.toml");
+        std::fs::write(
+            &routes,
+            format!(
+                r#"
+                    [[route]]
+                    component = {P2_CLI_SERVE_ECHO_ENV_COMPONENT:?}
+                    path = "/a"
+                    args = ["--env=FOO=a"]
+
+                    [[route]]
+                    component = {P2_CLI_SERVE_ECHO_ENV_COMPONENT:?}
+                    host = "localhost"
+                "#
+            ),
+        )?;
+        let server = WasmtimeServe::spawn(
+            get_wasmtime_command()?
+                .arg("serve")
+                .arg("--addr=127.0.0.1:0")
+                .arg("-Scli")
+                .arg("--env=FOO=b")
+                .arg(format!("--routes={}", routes.display())),
+        )?;
+
+        for (uri, expected) in [
+            ("http://localhost/a", Some("a")),
+            ("http://localhost/a/b", Some("a")),
+            ("http://example.com/a/b", Some("a")),
+            ("http://localhost/", Some("b")),
+            ("http://localhost/ab", Some("b")),
+            ("http://example.com/ab", None),
+        ] {
+            let resp = server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri(uri)
+                        .header("env", "FOO")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await?;
+            match expected {
+                Some(expected) => {
+                    assert!(resp.status().is_success(), "bad status for {uri}");
+                    assert_eq!(resp.headers()["env"], expected, "bad route for {uri}");
+                }
+                None => assert_eq!(resp.status(), hyper::StatusCode::NOT_FOUND),
+            }
+        }
+
+        server.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn p2_cli_serve_routes_engine_options() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let routes = dir.path().join("routes.toml");
+        std::fs::write(
+            &routes,
+            format!(
+                r#"
+                    [[route]]
+                    component = {P2_CLI_SERVE_ECHO_ENV_COMPONENT:?}
+                    args = ["-Oopt-level=0"]
+                "#
+            ),
+        )?;
+        let output = get_wasmtime_command()?
+            .arg("serve")
+            .arg("--addr=127.0.0.1:0")
+            .arg(format!("--routes={}", routes.display()))
+            .output()?;
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("configure the engine shared by all routes"),
+            "bad stderr: {stderr}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn p2_cli_serve_watch() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let wasm = dir.path().join("component.wasm");
+        std::fs::copy(P2_CLI_SERVE_ECHO_ENV_COMPONENT, &wasm)?;
+        let server = WasmtimeServe::new(wasm.to_str().unwrap(), |cmd| {
+            cmd.arg("-Scli").arg("--env=FOO=bar").arg("--watch");
+        })?;
+        let request = || {
+            hyper::Request::builder()
+                .uri("http://localhost/")
+                .header("env", "FOO")
+                .body(String::new())
+                .context("failed to make request")
+        };
+
+        let resp = server.send_request(request()?).await?;
+        assert_eq!(
+            resp.headers().get("env"),
+            Some(&HeaderValue::from_static("bar"))
+        );
+
+        // Replace the component with one which doesn't echo environment
+        // variables and wait for requests to be handled by it.
+        std::fs::copy(P2_CLI_SERVE_AUTHORITY_AND_SCHEME_COMPONENT, &wasm)?;
+        let start = std::time::Instant::now();
+        loop {
+            let resp = server.send_request(request()?).await?;
+            assert!(resp.status().is_success());
+            if resp.headers().get("env").is_none() {
+                break;
+            }
+            if start.elapsed() > std::time::Duration::from_secs(60) {
+                bail!("component was never reloaded");
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        let (_, stderr) = server.finish()?;
+        assert!(stderr.contains("Reloaded"), "bad stderr: {stderr}");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn p2_cli_serve_outgoing_body_config() -> Result<()> {
         let server = WasmtimeServe::new(P2_CLI_SERVE_ECHO_ENV_COMPONENT, |cmd| {
@@ -2046,6 +2452,51 @@ start a print 1234
         Ok(())
     }
 
+    #[tokio::test]
+    async fn p2_cli_serve_config_file_reload() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "hello = \"world\"\n")?;
+        let server = WasmtimeServe::new(P2_CLI_SERVE_CONFIG_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("-Sconfig");
+            cmd.arg(format!("-Sconfig-file={}", path.display()));
+        })?;
+        let get = || async {
+            let resp = server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri("http://localhost/")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await?;
+            assert!(resp.status().is_success());
+            Ok::<_, anyhow::Error>(resp.into_body())
+        };
+        assert_eq!(get().await?, "world");
+
+        // A file which fails to parse keeps the previous values, which are
+        // replaced once the file is fixed. Wait for the server to try to
+        // reload the broken file before fixing it.
+        std::fs::write(&path, "hello = \n")?;
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        assert_eq!(get().await?, "world");
+        std::fs::write(&path, "hello = \"there\"\n")?;
+        let start = std::time::Instant::now();
+        while get().await? != "there" {
+            assert!(start.elapsed() < std::time::Duration::from_secs(30));
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        let (_, stderr) = server.finish()?;
+        assert!(
+            stderr.contains("failed to reload wasi:config variables"),
+            "bad stderr: {stderr}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn p2_cli_config() -> Result<()> {
         run_wasmtime(&[
@@ -2057,6 +2508,20 @@ start a print 1234
         Ok(())
     }
 
+    #[test]
+    fn p2_cli_config_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "hello = \"world\"\n")?;
+        run_wasmtime(&[
+            "run",
+            "-Sconfig",
+            &format!("-Sconfig-file={}", path.display()),
+            CONFIG_GET_COMPONENT,
+        ])?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn p2_cli_serve_keyvalue() -> Result<()> {
         let server = WasmtimeServe::new(P2_CLI_SERVE_KEYVALUE_COMPONENT, |cmd| {
@@ -2079,6 +2544,60 @@ start a print 1234
         Ok(())
     }
 
+    #[tokio::test]
+    async fn p2_cli_serve_routes_keyvalue_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let shared = dir.path().join("shared.db");
+        let own = dir.path().join("own.db");
+        let routes = dir.path().join("routes.toml");
+        std::fs::write(
+            &routes,
+            format!(
+                r#"
+                    [[route]]
+                    component = {P2_CLI_SERVE_KEYVALUE_COMPONENT:?}
+                    path = "/a"
+
+                    [[route]]
+                    component = {P2_CLI_SERVE_KEYVALUE_COMPONENT:?}
+                    path = "/b"
+                    args = ["-Skeyvalue-file=own={}"]
+                "#,
+                own.display(),
+            ),
+        )?;
+        // Both routes use the file passed to `wasmtime serve`, which is only
+        // opened once since opening it again would fail.
+        let server = WasmtimeServe::spawn(
+            get_wasmtime_command()?
+                .arg("serve")
+                .arg("--addr=127.0.0.1:0")
+                .arg("-Scli")
+                .arg("-Skeyvalue")
+                .arg("-Skeyvalue-in-memory-data=hello=world")
+                .arg(format!("-Skeyvalue-file=shared={}", shared.display()))
+                .arg(format!("--routes={}", routes.display())),
+        )?;
+
+        for uri in ["http://localhost/a", "http://localhost/b"] {
+            let resp = server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri(uri)
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await?;
+            assert!(resp.status().is_success(), "bad status for {uri}");
+            assert_eq!(resp.body(), "world");
+        }
+        assert!(shared.exists());
+        assert!(own.exists());
+
+        server.finish()?;
+        Ok(())
+    }
+
     #[test]
     fn p2_cli_keyvalue() -> Result<()> {
         run_wasmtime(&[
@@ -2359,6 +2878,73 @@ fn profile_guest() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn profile_sample() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for file in ["out.svg", "out.folded", "out.pb"] {
+        let path = dir.path().join(file);
+        let output = run_wasmtime_for_output(
+            &[
+                &format!("--profile=sample,{},1ms", path.display()),
+                "tests/all/cli_tests/sample-spin.wat",
+            ],
+            None,
+        )?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        println!("> stderr:\n{stderr}");
+        assert!(output.status.success());
+        assert!(stderr.contains("Profile with"));
+    }
+
+    // Nearly all of the time is spent spinning, so that stack must have been
+    // sampled.
+    let folded = std::fs::read_to_string(dir.path().join("out.folded"))?;
+    println!("> folded:\n{folded}");
+    let spin = folded
+        .lines()
+        .find_map(|line| line.strip_prefix("main;spin "))
+        .expect("no samples of `spin`");
+    assert!(spin.parse::<u64>()? > 0);
+    assert!(folded.lines().all(|line| line.starts_with("main")));
+
+    let svg = std::fs::read_to_string(dir.path().join("out.svg"))?;
+    assert!(svg.starts_with("<?xml"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains("<title>main ("));
+    assert!(svg.contains("<title>spin ("));
+
+    let pprof = std::fs::read(dir.path().join("out.pb"))?;
+    for string in ["samples", "nanoseconds", "main", "spin"] {
+        assert!(
+            pprof.windows(string.len()).any(|w| w == string.as_bytes()),
+            "`{string}` missing from pprof profile"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn coverage() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.lcov");
+    let output = run_wasmtime_for_output(
+        &[
+            &format!("--coverage={}", path.display()),
+            "tests/all/cli_tests/simple.wat",
+            "--invoke",
+            "simple",
+            "4",
+        ],
+        None,
+    )?;
+    assert!(output.status.success());
+    let lcov = std::fs::read_to_string(&path)?;
+    assert!(lcov.contains("SF:tests/all/cli_tests/simple.wat\n"));
+    assert!(lcov.contains("FNH:1\n"));
+    assert!(lcov.trim_end().ends_with("end_of_record"));
+    Ok(())
+}
+
 #[test]
 fn unreachable_without_wasi() -> Result<()> {
     let output = run_wasmtime_for_output(
@@ -2623,3 +3209,302 @@ fn big_table_in_pooling_allocator() -> Result<()> {
     ])?;
     Ok(())
 }
+
+/// A minimal Debug Adapter Protocol client for `wasmtime run --debug-adapter`.
+struct DapClient {
+    reader: std::io::BufReader<std::net::TcpStream>,
+    writer: std::net::TcpStream,
+    seq: u64,
+    events: Vec<serde_json::Value>,
+}
+
+impl DapClient {
+    fn connect(addr: &str) -> Result<DapClient> {
+        let stream = std::net::TcpStream::connect(addr)?;
+        Ok(DapClient {
+            reader: std::io::BufReader::new(stream.try_clone()?),
+            writer: stream,
+            seq: 0,
+            events: Vec::new(),
+        })
+    }
+
+    fn recv(&mut self) -> Result<serde_json::Value> {
+        use std::io::{BufRead, Read};
+
+        let mut header = String::new();
+        self.reader.read_line(&mut header)?;
+        let len = header
+            .trim()
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()?;
+        self.reader.read_line(&mut header)?;
+        let mut body = vec![0; len];
+        self.reader.read_exact(&mut body)?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Sends a request and returns the body of its successful response.
+    fn request(
+        &mut self,
+        command: &str,
+        arguments: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        self.seq += 1;
+        let body = serde_json::json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        write!(self.writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        loop {
+            let message = self.recv()?;
+            if message["type"] == "event" {
+                self.events.push(message);
+                continue;
+            }
+            assert_eq!(message["request_seq"], self.seq);
+            if message["success"] != true {
+                bail!("`{command}` failed: {message}");
+            }
+            return Ok(message["body"].clone());
+        }
+    }
+
+    /// Waits for the event `name` and returns its body.
+    fn event(&mut self, name: &str) -> Result<serde_json::Value> {
+        loop {
+            if let Some(i) = self.events.iter().position(|e| e["event"] == name) {
+                return Ok(self.events.remove(i)["body"].clone());
+            }
+            let message = self.recv()?;
+            self.events.push(message);
+        }
+    }
+
+    fn variables(&mut self, reference: u64) -> Result<Vec<String>> {
+        let body = self.request(
+            "variables",
+            serde_json::json!({ "variablesReference": reference }),
+        )?;
+        Ok(body["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["value"].as_str().unwrap().to_string())
+            .collect())
+    }
+}
+
+#[test]
+fn run_debug_adapter() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+
+    let mut child = get_wasmtime_command()?
+        .args([
+            "run",
+            "-Ccache=n",
+            "--debug-adapter=127.0.0.1:0",
+            "--invoke=add",
+            "tests/all/cli_tests/debug-adapter.wat",
+            "1",
+            "2",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let addr = loop {
+        let mut line = String::new();
+        if stderr.read_line(&mut line)? == 0 {
+            bail!("wasmtime exited without listening for a debugger");
+        }
+        if let Some(addr) = line
+            .trim()
+            .strip_prefix("Waiting for a debug adapter client on ")
+        {
+            break addr.to_string();
+        }
+    };
+
+    let mut client = DapClient::connect(&addr)?;
+    let capabilities = client.request("initialize", serde_json::json!({ "adapterID": "test" }))?;
+    assert_eq!(capabilities["supportsFunctionBreakpoints"], true);
+    client.event("initialized")?;
+    client.request("launch", serde_json::json!({}))?;
+    let breakpoints = client.request(
+        "setFunctionBreakpoints",
+        serde_json::json!({ "breakpoints": [{ "name": "add" }, { "name": "missing" }] }),
+    )?;
+    assert_eq!(breakpoints["breakpoints"][0]["verified"], true);
+    assert_eq!(breakpoints["breakpoints"][1]["verified"], false);
+    client.request("configurationDone", serde_json::json!({}))?;
+
+    let stopped = client.event("stopped")?;
+    assert_eq!(stopped["reason"], "breakpoint");
+    let trace = client.request("stackTrace", serde_json::json!({ "threadId": 1 }))?;
+    assert_eq!(trace["stackFrames"][0]["name"], "add");
+    let scopes = client.request("scopes", serde_json::json!({ "frameId": 0 }))?;
+    let scope = |name: &str| {
+        scopes["scopes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|s| s["name"] == name)
+            .unwrap()["variablesReference"]
+            .as_u64()
+            .unwrap()
+    };
+    assert_eq!(client.variables(scope("Locals"))?, ["1", "2"]);
+    assert_eq!(client.variables(scope("Globals"))?, ["0"]);
+    let memory = client.request(
+        "readMemory",
+        serde_json::json!({ "memoryReference": "memory0", "count": 5 }),
+    )?;
+    assert_eq!(memory["data"], "aGVsbG8=");
+
+    client.request(
+        "next",
+        serde_json::json!({ "threadId": 1, "granularity": "instruction" }),
+    )?;
+    assert_eq!(client.event("stopped")?["reason"], "step");
+    assert_eq!(client.variables(scope("Operand Stack"))?, ["0"]);
+
+    client.request("continue", serde_json::json!({ "threadId": 1 }))?;
+    assert_eq!(client.event("exited")?["exitCode"], 0);
+    client.event("terminated")?;
+
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    Ok(())
+}
+
+#[test]
+fn run_debug_adapter_pause() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+
+    let mut child = get_wasmtime_command()?
+        .args([
+            "run",
+            "-Ccache=n",
+            "--debug-adapter=127.0.0.1:0",
+            "--invoke=spin",
+            "tests/all/cli_tests/debug-adapter-spin.wat",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let addr = loop {
+        let mut line = String::new();
+        if stderr.read_line(&mut line)? == 0 {
+            bail!("wasmtime exited without listening for a debugger");
+        }
+        if let Some(addr) = line
+            .trim()
+            .strip_prefix("Waiting for a debug adapter client on ")
+        {
+            break addr.to_string();
+        }
+    };
+
+    let mut client = DapClient::connect(&addr)?;
+    client.request("initialize", serde_json::json!({ "adapterID": "test" }))?;
+    client.event("initialized")?;
+    client.request("launch", serde_json::json!({}))?;
+    client.request("configurationDone", serde_json::json!({}))?;
+
+    // The guest never returns on its own, so this only stops if the request
+    // is serviced while it's running.
+    client.request("pause", serde_json::json!({ "threadId": 1 }))?;
+    assert_eq!(client.event("stopped")?["reason"], "pause");
+    let trace = client.request("stackTrace", serde_json::json!({ "threadId": 1 }))?;
+    assert_eq!(trace["stackFrames"][0]["name"], "spin");
+
+    client.request(
+        "disconnect",
+        serde_json::json!({ "terminateDebuggee": true }),
+    )?;
+    let output = child.wait_with_output()?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+#[test]
+fn run_debug_adapter_breakpoint_while_running() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+
+    let wat = "tests/all/cli_tests/debug-adapter-spin.wat";
+    let wasm = wat::parse_file(wat)?;
+    let mut br = None;
+    for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+        if let wasmparser::Payload::CodeSectionEntry(body) = payload? {
+            let mut ops = body.get_operators_reader()?;
+            while !ops.eof() {
+                let (op, offset) = ops.read_with_offset()?;
+                if let wasmparser::Operator::Br { .. } = op {
+                    br = Some(offset);
+                }
+            }
+        }
+    }
+    let Some(br) = br else {
+        bail!("no `br` instruction in `{wat}`");
+    };
+
+    let mut child = get_wasmtime_command()?
+        .args([
+            "run",
+            "-Ccache=n",
+            "--debug-adapter=127.0.0.1:0",
+            "--invoke=spin",
+            wat,
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let addr = loop {
+        let mut line = String::new();
+        if stderr.read_line(&mut line)? == 0 {
+            bail!("wasmtime exited without listening for a debugger");
+        }
+        if let Some(addr) = line
+            .trim()
+            .strip_prefix("Waiting for a debug adapter client on ")
+        {
+            break addr.to_string();
+        }
+    };
+
+    let mut client = DapClient::connect(&addr)?;
+    client.request("initialize", serde_json::json!({ "adapterID": "test" }))?;
+    client.event("initialized")?;
+    client.request("launch", serde_json::json!({}))?;
+    client.request("configurationDone", serde_json::json!({}))?;
+
+    // The breakpoint is set once the guest is already spinning, so this only
+    // stops if it's installed while it's running.
+    let response = client.request(
+        "setInstructionBreakpoints",
+        serde_json::json!({
+            "breakpoints": [{ "instructionReference": format!("0x{br:x}") }],
+        }),
+    )?;
+    assert_eq!(response["breakpoints"][0]["verified"], true);
+    assert_eq!(client.event("stopped")?["reason"], "breakpoint");
+    let trace = client.request("stackTrace", serde_json::json!({ "threadId": 1 }))?;
+    assert_eq!(trace["stackFrames"][0]["name"], "spin");
+
+    client.request(
+        "disconnect",
+        serde_json::json!({ "terminateDebuggee": true }),
+    )?;
+    let output = child.wait_with_output()?;
+    assert!(!output.status.success());
+    Ok(())
+}
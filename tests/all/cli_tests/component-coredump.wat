@@ -0,0 +1,11 @@
+(component $trapper
+  (component $inner
+    (core module $m
+      (func (export "run") unreachable)
+    )
+    (core instance $i (instantiate $m))
+    (func (export "run") (canon lift (core func $i "run")))
+  )
+  (instance $i (instantiate $inner))
+  (export "run" (func $i "run"))
+)
@@ -0,0 +1,5 @@
+(module
+  (func $spin (export "spin")
+    (loop $l
+      br $l))
+)
@@ -0,0 +1,13 @@
+(module
+  (global $calls (mut i32) (i32.const 0))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "hello")
+  (func $add (export "add") (param i32 i32) (result i32)
+    global.get $calls
+    i32.const 1
+    i32.add
+    global.set $calls
+    local.get 0
+    local.get 1
+    i32.add)
+)
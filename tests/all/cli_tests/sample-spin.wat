@@ -0,0 +1,7 @@
+(module
+  (func $spin (param i32)
+    (loop $l
+      (br_if $l (local.tee 0 (i32.sub (local.get 0) (i32.const 1))))))
+
+  (func $main (export "_start")
+    (call $spin (i32.const 500000000))))
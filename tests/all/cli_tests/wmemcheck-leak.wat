@@ -0,0 +1,21 @@
+;; A bump allocator whose second allocation is never freed.
+(module
+  (memory 2)
+  (global $__stack_pointer (mut i32) (i32.const 1024))
+  (global $heap (mut i32) (i32.const 65536))
+
+  (func $malloc (param i32) (result i32)
+    (local i32)
+    (local.set 1 (global.get $heap))
+    (global.set $heap (i32.add (local.get 1) (local.get 0)))
+    (local.get 1))
+
+  (func $free (param i32))
+
+  (func $leak
+    (drop (call $malloc (i32.const 24))))
+
+  (func (export "_start")
+    (call $free (call $malloc (i32.const 16)))
+    (call $leak))
+)
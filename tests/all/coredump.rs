@@ -1,5 +1,6 @@
 use anyhow::bail;
 use wasmtime::*;
+use wasmtime_test_macros::wasmtime_test;
 
 #[test]
 #[cfg_attr(miri, ignore)]
@@ -259,3 +260,289 @@ fn multiple_globals_memories_and_instances() -> Result<()> {
 
     Ok(())
 }
+
+#[wasmtime_test(strategies(not(CraneliftPulley)))]
+fn coredump_has_locals(config: &mut Config) -> Result<()> {
+    config.coredump_on_trap(true);
+    config.coredump_locals(true);
+    let engine = Engine::new(&config).unwrap();
+    let mut store = Store::<()>::new(&engine, ());
+
+    let wat = r#"
+      (module
+          (func $a (export "a") (param i32) (local i64)
+              i64.const 42
+              local.set 1
+              i32.const 7
+              local.get 0
+              call $b
+              drop
+              drop
+          )
+          (func $b (param i32) (result i32) (local f32)
+              f32.const 1.5
+              local.set 1
+              local.get 0
+              i32.const 0
+              i32.div_u
+          )
+      )
+    "#;
+
+    let module = Module::new(store.engine(), wat)?;
+    let _other = Instance::new(&mut store, &module, &[])?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let a_func = instance.get_typed_func::<i32, ()>(&mut store, "a")?;
+
+    let e = a_func.call(&mut store, 3).unwrap_err();
+    let cd = e.downcast_ref::<WasmCoreDump>().unwrap();
+    assert_eq!(cd.frames().len(), 2);
+
+    let vals = |vals: &[Option<Val>]| {
+        vals.iter()
+            .map(|v| match v {
+                Some(Val::I32(x)) => format!("i32:{x}"),
+                Some(Val::I64(x)) => format!("i64:{x}"),
+                Some(Val::F32(x)) => format!("f32:{}", f32::from_bits(*x)),
+                _ => format!("{v:?}"),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vals(cd.frame_locals(0)), ["i32:3", "f32:1.5"]);
+    assert_eq!(vals(cd.frame_operand_stack(0)), ["i32:3", "i32:0"]);
+    assert_eq!(vals(cd.frame_locals(1)), ["i32:3", "i64:42"]);
+    assert_eq!(vals(cd.frame_operand_stack(1)), ["i32:7", "i32:3"]);
+
+    for i in 0..2 {
+        let frame_instance = cd.frame_instance(i).unwrap();
+        assert_eq!(format!("{frame_instance:?}"), format!("{instance:?}"));
+    }
+
+    // The same values are recorded in the serialized core dump.
+    use wasmparser::{BinaryReader, CoreDumpStackSection, CoreDumpValue, Parser, Payload};
+    let bytes = cd.serialize(&mut store, "locals");
+    let stack = Parser::new(0)
+        .parse_all(&bytes)
+        .find_map(|payload| match payload.unwrap() {
+            Payload::CustomSection(section) if section.name() == "corestack" => {
+                let reader = BinaryReader::new(section.data(), section.data_offset());
+                Some(CoreDumpStackSection::new(reader).unwrap())
+            }
+            _ => None,
+        })
+        .unwrap();
+    let dumped = |vals: &[CoreDumpValue]| {
+        vals.iter()
+            .map(|v| match v {
+                CoreDumpValue::I32(x) => format!("i32:{x}"),
+                CoreDumpValue::I64(x) => format!("i64:{x}"),
+                CoreDumpValue::F32(x) => format!("f32:{}", f32::from_bits(x.bits())),
+                _ => format!("{v:?}"),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(stack.frames.len(), 2);
+    assert_eq!(dumped(&stack.frames[0].locals), ["i32:3", "f32:1.5"]);
+    assert_eq!(dumped(&stack.frames[0].stack), ["i32:3", "i32:0"]);
+    assert_eq!(dumped(&stack.frames[1].locals), ["i32:3", "i64:42"]);
+    assert_eq!(dumped(&stack.frames[1].stack), ["i32:7", "i32:3"]);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn coredump_component_export() -> Result<()> {
+    let mut config = Config::default();
+    config.coredump_on_trap(true);
+    let engine = Engine::new(&config)?;
+    let mut store = Store::<()>::new(&engine, ());
+
+    let component = component::Component::new(
+        &engine,
+        r#"
+          (component $my-component
+              (core module $m
+                  (func (export "run") unreachable)
+              )
+              (core instance $i (instantiate $m))
+              (func $run (canon lift (core func $i "run")))
+              (instance $api (export "run" (func $run)))
+              (export "my:pkg/api" (instance $api))
+          )
+        "#,
+    )?;
+    let linker = component::Linker::new(&engine);
+    let instance = linker.instantiate(&mut store, &component)?;
+    let api = instance
+        .get_export_index(&mut store, None, "my:pkg/api")
+        .unwrap();
+    let run = instance
+        .get_export_index(&mut store, Some(&api), "run")
+        .unwrap();
+    let run = instance.get_typed_func::<(), ()>(&mut store, &run)?;
+
+    let e = run.call(&mut store, ()).unwrap_err();
+    let cd = e.downcast_ref::<WasmCoreDump>().unwrap();
+
+    assert_eq!(cd.component_instances().len(), 1);
+    let component = &cd.component_instances()[0];
+    assert_eq!(component.name(), Some("my-component"));
+    assert_eq!(component.instances(), [0]);
+    assert_eq!(
+        cd.component_calls(),
+        [CoreDumpComponentCall::Export {
+            instance: 0,
+            name: "my:pkg/api#run".to_string(),
+        }]
+    );
+    let _ = cd.serialize(&mut store, "component");
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn coredump_component_tree() -> Result<()> {
+    let mut config = Config::default();
+    config.coredump_on_trap(true);
+    let engine = Engine::new(&config)?;
+    let mut store = Store::<()>::new(&engine, ());
+
+    let component = component::Component::new(
+        &engine,
+        r#"
+          (component $outer
+              (component $inner
+                  (core module $m
+                      (func (export "run") unreachable)
+                  )
+                  (core instance $i (instantiate $m))
+                  (func (export "run") (canon lift (core func $i "run")))
+              )
+              (instance $a (instantiate $inner))
+              (instance $b (instantiate $inner))
+              (export "run" (func $b "run"))
+          )
+        "#,
+    )?;
+    let linker = component::Linker::new(&engine);
+    let instance = linker.instantiate(&mut store, &component)?;
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+
+    let e = run.call(&mut store, ()).unwrap_err();
+    let cd = e.downcast_ref::<WasmCoreDump>().unwrap();
+
+    assert_eq!(cd.component_instances().len(), 1);
+    let outer = &cd.component_instances()[0];
+    assert_eq!(outer.name(), Some("outer"));
+    assert!(outer.instances().is_empty());
+    assert_eq!(outer.children().len(), 2);
+    for (i, inner) in outer.children().iter().enumerate() {
+        assert_eq!(inner.name(), Some("inner"));
+        assert_eq!(inner.instances(), [i]);
+        assert_eq!(inner.handles().own(), 0);
+        assert!(inner.children().is_empty());
+    }
+    assert_eq!(
+        cd.component_calls(),
+        [CoreDumpComponentCall::Export {
+            instance: 0,
+            name: "run".to_string(),
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn coredump_component_import() -> Result<()> {
+    let mut config = Config::default();
+    config.coredump_on_trap(true);
+    let engine = Engine::new(&config)?;
+    let mut store = Store::<()>::new(&engine, ());
+
+    let component = component::Component::new(
+        &engine,
+        r#"
+          (component
+              (import "host" (instance $host
+                  (export "ok" (func))
+                  (export "fail" (func))
+              ))
+              (core func $ok (canon lower (func $host "ok")))
+              (core func $fail (canon lower (func $host "fail")))
+              (core module $m
+                  (import "" "ok" (func $ok))
+                  (import "" "fail" (func $fail))
+                  (func (export "run") call $ok call $fail)
+              )
+              (core instance $i (instantiate $m
+                  (with "" (instance
+                      (export "ok" (func $ok))
+                      (export "fail" (func $fail))
+                  ))
+              ))
+              (func (export "run") (canon lift (core func $i "run")))
+          )
+        "#,
+    )?;
+    let mut linker = component::Linker::new(&engine);
+    let mut host = linker.instance("host")?;
+    host.func_wrap("ok", |_, (): ()| Ok(()))?;
+    host.func_wrap("fail", |_, (): ()| -> Result<()> { bail!("host failure") })?;
+    let instance = linker.instantiate(&mut store, &component)?;
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+
+    let e = run.call(&mut store, ()).unwrap_err();
+    let cd = e.downcast_ref::<WasmCoreDump>().unwrap();
+    assert_eq!(
+        cd.component_calls(),
+        [
+            CoreDumpComponentCall::Export {
+                instance: 0,
+                name: "run".to_string(),
+            },
+            CoreDumpComponentCall::Import {
+                instance: 0,
+                name: "host#fail".to_string(),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(miri, ignore)]
+async fn coredump_component_call_async() -> Result<()> {
+    let mut config = Config::default();
+    config.coredump_on_trap(true);
+    config.async_support(true);
+    let engine = Engine::new(&config)?;
+    let mut store = Store::<()>::new(&engine, ());
+
+    let component = component::Component::new(
+        &engine,
+        r#"
+          (component
+              (core module $m
+                  (func (export "run") unreachable)
+              )
+              (core instance $i (instantiate $m))
+              (func (export "run") (canon lift (core func $i "run")))
+          )
+        "#,
+    )?;
+    let linker = component::Linker::new(&engine);
+    let instance = linker.instantiate_async(&mut store, &component).await?;
+    let run = instance.get_func(&mut store, "run").unwrap();
+
+    let e = run.call_async(&mut store, &[], &mut []).await.unwrap_err();
+    let cd = e.downcast_ref::<WasmCoreDump>().unwrap();
+    assert_eq!(
+        cd.component_calls(),
+        [CoreDumpComponentCall::Export {
+            instance: 0,
+            name: "run".to_string(),
+        }]
+    );
+    Ok(())
+}
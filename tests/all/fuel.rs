@@ -382,3 +382,123 @@ fn ensure_stack_alignment(config: &mut Config) -> Result<()> {
     );
     Ok(())
 }
+
+#[wasmtime_test(strategies(not(Winch)))]
+#[cfg_attr(miri, ignore)]
+fn fuel_profile_attributes_fuel_to_functions(config: &mut Config) -> Result<()> {
+    config.consume_fuel(true);
+    config.fuel_profiling(true);
+    let engine = Engine::new(config)?;
+    let mut store = Store::new(&engine, ());
+    let module = Module::new(
+        &engine,
+        r#"
+(module
+  (func $leaf (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1))
+  )
+  (func $mid (export "mid") (param i32) (result i32)
+    (call $leaf (call $leaf (local.get 0)))
+  )
+  (func (export "trap")
+    (drop (call $leaf (i32.const 0)))
+    unreachable
+  )
+)
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let mid = instance.get_typed_func::<i32, i32>(&mut store, "mid")?;
+    store.set_fuel(1_000)?;
+    assert_eq!(mid.call(&mut store, 1)?, 3);
+
+    // Each function costs one fuel on entry plus one per instruction, so
+    // `leaf` costs 4 per call and `mid` costs 4 itself.
+    let profile = store.fuel_profile()?;
+    assert_eq!(profile.total_fuel(), 12);
+    assert_eq!(profile.entries()[0].name(), "leaf");
+    let leaf = profile_entry(&profile, "leaf");
+    assert_eq!(leaf.calls(), 2);
+    assert_eq!(leaf.exclusive_fuel(), 8);
+    assert_eq!(leaf.inclusive_fuel(), 8);
+    let mid_entry = profile_entry(&profile, "mid");
+    assert_eq!(mid_entry.calls(), 1);
+    assert_eq!(mid_entry.exclusive_fuel(), 4);
+    assert_eq!(mid_entry.inclusive_fuel(), 12);
+
+    // Setting fuel isn't counted as consumption, and frames unwound by a trap
+    // are popped from the profile rather than becoming callers of later
+    // calls.
+    store.set_fuel(1_000)?;
+    let trap = instance.get_typed_func::<(), ()>(&mut store, "trap")?;
+    assert!(trap.call(&mut store, ()).is_err());
+    assert_eq!(mid.call(&mut store, 1)?, 3);
+    let profile = store.fuel_profile()?;
+    assert_eq!(profile_entry(&profile, "leaf").calls(), 5);
+    let mid_entry = profile_entry(&profile, "mid");
+    assert_eq!(mid_entry.calls(), 2);
+    assert_eq!(mid_entry.inclusive_fuel(), 24);
+
+    Ok(())
+}
+
+fn profile_entry<'a>(profile: &'a FuelProfile, name: &str) -> &'a FuelProfileEntry {
+    profile.entries().iter().find(|e| e.name() == name).unwrap()
+}
+
+#[test]
+fn fuel_profiling_requires_fuel() {
+    let mut config = Config::new();
+    config.fuel_profiling(true);
+    assert!(Engine::new(&config).is_err());
+}
+
+#[tokio::test]
+#[cfg_attr(miri, ignore)]
+async fn fuel_profile_across_async_yields() -> Result<()> {
+    let mut config = Config::new();
+    config.async_support(true);
+    config.consume_fuel(true);
+    config.fuel_profiling(true);
+    let engine = Engine::new(&config)?;
+    let mut store = Store::new(&engine, ());
+    let module = Module::new(
+        &engine,
+        r#"
+(module
+  (func $leaf (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1))
+  )
+  (func $mid (export "mid") (param i32) (result i32)
+    (call $leaf (call $leaf (local.get 0)))
+  )
+)
+        "#,
+    )?;
+    let instance = Instance::new_async(&mut store, &module, &[]).await?;
+    let mid = instance.get_typed_func::<i32, i32>(&mut store, "mid")?;
+
+    // Suspend the fiber running `mid` several times part-way through; the
+    // profile should be the same as for a synchronous call.
+    store.set_fuel(1_000)?;
+    store.fuel_async_yield_interval(Some(2))?;
+    assert_eq!(mid.call_async(&mut store, 1).await?, 3);
+    let profile = store.fuel_profile()?;
+    assert_eq!(profile.total_fuel(), 12);
+    let leaf = profile_entry(&profile, "leaf");
+    assert_eq!(leaf.calls(), 2);
+    assert_eq!(leaf.exclusive_fuel(), 8);
+    let mid_entry = profile_entry(&profile, "mid");
+    assert_eq!(mid_entry.exclusive_fuel(), 4);
+    assert_eq!(mid_entry.inclusive_fuel(), 12);
+    Ok(())
+}
+
+#[test]
+fn fuel_profiling_rejects_stack_switching() {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    config.fuel_profiling(true);
+    config.wasm_stack_switching(true);
+    assert!(Engine::new(&config).is_err());
+}
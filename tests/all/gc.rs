@@ -1802,3 +1802,345 @@ fn array_init_elem_oom() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn copying_collects_cycles() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut config = Config::new();
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+    config.collector(Collector::Copying);
+
+    let engine = Engine::new(&config)?;
+
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (type $node (struct (field externref) (field (mut (ref null $node)))))
+                (func (export "make_cycle") (param externref)
+                    (local $a (ref null $node))
+                    (local $b (ref null $node))
+                    (local.set $a (struct.new $node (local.get 0) (ref.null $node)))
+                    (local.set $b (struct.new $node (ref.null extern) (local.get $a)))
+                    (struct.set $node 1 (local.get $a) (local.get $b))
+                )
+            )
+        "#,
+    )?;
+
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let make_cycle =
+        instance.get_typed_func::<Option<Rooted<ExternRef>>, ()>(&mut store, "make_cycle")?;
+
+    let num_refs_dropped = Arc::new(AtomicUsize::new(0));
+    let len = 100;
+    for _ in 0..len {
+        let mut scope = RootScope::new(&mut store);
+        let externref = ExternRef::new(&mut scope, CountDrops(num_refs_dropped.clone()))?;
+        make_cycle.call(&mut scope, Some(externref))?;
+    }
+
+    // Every cycle is garbage now, and tracing should reclaim all of them.
+    store.gc(None);
+    assert_eq!(num_refs_dropped.load(SeqCst), len);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn copying_preserves_live_objects() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut config = Config::new();
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+    config.collector(Collector::Copying);
+
+    let engine = Engine::new(&config)?;
+
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (type $cons (struct (field i32) (field (ref null $cons))))
+                (type $arr (array (mut (ref null $cons))))
+                (global $list (mut (ref null $cons)) (ref.null $cons))
+                (global $arr (mut (ref null $arr)) (ref.null $arr))
+
+                (func (export "push") (param i32)
+                    (global.set $list
+                        (struct.new $cons (local.get 0) (global.get $list)))
+                    (global.set $arr
+                        (array.new $arr (global.get $list) (i32.const 4)))
+                )
+
+                ;; Allocate lots of garbage, forcing collections, while holding
+                ;; a reference on the stack, and then sum the list, the
+                ;; on-stack object, and the array's first element.
+                (func (export "churn") (param $n i32) (result i32)
+                    (local $s (ref null $cons))
+                    (local $l (ref null $cons))
+                    (local $sum i32)
+                    (local.set $s (struct.new $cons (i32.const 1000) (ref.null $cons)))
+                    (loop $garbage
+                        (drop (struct.new $cons (local.get $n) (ref.null $cons)))
+                        (drop (array.new $arr (ref.null $cons) (i32.const 8)))
+                        (local.tee $n (i32.sub (local.get $n) (i32.const 1)))
+                        (br_if $garbage)
+                    )
+                    (local.set $sum (struct.get $cons 0 (local.get $s)))
+                    (local.set $l (global.get $list))
+                    (block $done
+                        (loop $sum
+                            (br_if $done (ref.is_null (local.get $l)))
+                            (local.set $sum
+                                (i32.add (local.get $sum)
+                                    (struct.get $cons 0 (local.get $l))))
+                            (local.set $l (struct.get $cons 1 (local.get $l)))
+                            (br $sum)
+                        )
+                    )
+                    (i32.add
+                        (local.get $sum)
+                        (struct.get $cons 0
+                            (array.get $arr (global.get $arr) (i32.const 3))))
+                )
+            )
+        "#,
+    )?;
+
+    // Cap the GC heap's size so that `churn` must collect, rather than grow
+    // the heap, while its on-stack reference is live.
+    let limits = StoreLimitsBuilder::new().memory_size(1 << 20).build();
+    let mut store = Store::new(&engine, limits);
+    store.limiter(|limits| limits);
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let push = instance.get_typed_func::<i32, ()>(&mut store, "push")?;
+    let churn = instance.get_typed_func::<i32, i32>(&mut store, "churn")?;
+
+    for i in 1..=10 {
+        push.call(&mut store, i)?;
+    }
+    let expected = 1000 + (1..=10).sum::<i32>() + 10;
+    for _ in 0..3 {
+        assert_eq!(churn.call(&mut store, 100_000)?, expected);
+        store.gc(None);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(miri, ignore)]
+async fn copying_gc_async_yields_between_increments() -> Result<()> {
+    use crate::async_functions::CountPending;
+
+    let _ = env_logger::try_init();
+
+    let module_wat = r#"
+        (module
+            (type $s (struct (field i32)))
+            (type $a (array (mut (ref null $s))))
+            (global $g (mut (ref null $a)) (ref.null $a))
+
+            (func (export "init") (param $n i32)
+                (local $i i32)
+                (local $arr (ref $a))
+                (local.set $arr (array.new $a (ref.null $s) (local.get $n)))
+                (loop $fill
+                    (array.set $a (local.get $arr) (local.get $i)
+                        (struct.new $s (local.get $i)))
+                    (local.tee $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $fill (i32.lt_u (local.get $n)))
+                )
+                (global.set $g (local.get $arr))
+            )
+
+            (func (export "sum") (result i32)
+                (local $i i32)
+                (local $sum i32)
+                (loop $sum
+                    (local.set $sum
+                        (i32.add (local.get $sum)
+                            (struct.get $s 0
+                                (array.get $a (global.get $g) (local.get $i)))))
+                    (local.tee $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $sum (i32.lt_u (array.len (global.get $g))))
+                )
+                (local.get $sum)
+            )
+        )
+    "#;
+
+    let n = 10_000;
+    let expected = (0..n).sum::<i32>();
+
+    let mut config = Config::new();
+    config.async_support(true);
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+    config.collector(Collector::Copying);
+
+    let engine = Engine::new(&config)?;
+    let module = Module::new(&engine, module_wat)?;
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new_async(&mut store, &module, &[]).await?;
+    let init = instance.get_typed_func::<i32, ()>(&mut store, "init")?;
+    let sum = instance.get_typed_func::<(), i32>(&mut store, "sum")?;
+
+    init.call_async(&mut store, n).await?;
+    let (_, pending) = CountPending::new(Box::pin(store.gc_async(None))).await;
+
+    // The live objects here span many increments of the copying collector, and
+    // the collection yields after each of them.
+    assert!(pending > 1, "expected the collection to yield: {pending}");
+
+    assert_eq!(sum.call_async(&mut store, ()).await?, expected);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn gc_stats_count_allocations_and_collections() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut config = Config::new();
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+
+    let engine = Engine::new(&config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (type $s (struct (field i32)))
+                (func (export "alloc") (param i32)
+                    (loop $l
+                        (drop (struct.new $s (local.get 0)))
+                        (br_if $l (local.tee 0 (i32.sub (local.get 0) (i32.const 1))))
+                    )
+                )
+            )
+        "#,
+    )?;
+
+    let mut store = Store::new(&engine, ());
+    assert_eq!(store.gc_stats().collections, 0);
+    assert_eq!(store.gc_stats().bytes_allocated, 0);
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let alloc = instance.get_typed_func::<i32, ()>(&mut store, "alloc")?;
+    alloc.call(&mut store, 100)?;
+
+    let before = store.gc_stats();
+    assert!(before.heap_size > 0);
+    assert!(before.bytes_allocated >= 100 * 8);
+    assert!(before.used_bytes as u64 <= before.bytes_allocated);
+
+    store.gc(None);
+    let after = store.gc_stats();
+    assert_eq!(after.collections, before.collections + 1);
+    assert_eq!(after.bytes_allocated, before.bytes_allocated);
+    assert!(after.live_bytes <= before.used_bytes);
+    assert!(after.max_pause_time <= after.total_pause_time);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn gc_heap_snapshot_records_objects_edges_and_roots() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut config = Config::new();
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+
+    let engine = Engine::new(&config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (type $pair (struct (field externref) (field i31ref)))
+                (type $arr (array (mut (ref null $pair))))
+                (global $g (mut (ref null $arr)) (ref.null $arr))
+                (func (export "init") (param externref)
+                    (local $p (ref null $pair))
+                    (local.set $p (struct.new $pair (local.get 0) (ref.i31 (i32.const 7))))
+                    (global.set $g (array.new $arr (local.get $p) (i32.const 3)))
+                )
+            )
+        "#,
+    )?;
+
+    let mut store = Store::new(&engine, ());
+    let snapshot = store.gc_heap_snapshot();
+    assert!(snapshot.objects().is_empty());
+    assert!(snapshot.roots().is_empty());
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let init = instance.get_typed_func::<Option<Rooted<ExternRef>>, ()>(&mut store, "init")?;
+    {
+        let mut scope = RootScope::new(&mut store);
+        let externref = ExternRef::new(&mut scope, 42_u32)?;
+        init.call(&mut scope, Some(externref))?;
+    }
+
+    let collections = store.gc_stats().collections;
+    let snapshot = store.gc_heap_snapshot();
+
+    // The array, the struct, and the externref. The i31ref is not an object.
+    let objects = snapshot.objects();
+    assert_eq!(objects.len(), 3);
+
+    let global_roots = snapshot
+        .roots()
+        .iter()
+        .filter(|r| r.kind() == GcRootKind::Global)
+        .collect::<Vec<_>>();
+    assert_eq!(global_roots.len(), 1);
+
+    let array = &objects[global_roots[0].object()];
+    assert_eq!(array.kind(), GcHeapSnapshotObjectKind::Array);
+    assert_eq!(array.edges().len(), 3);
+    let pair_index = array.edges()[0].object();
+    for (i, edge) in array.edges().iter().enumerate() {
+        assert_eq!(
+            *edge,
+            GcHeapSnapshotEdge::Element {
+                index: u32::try_from(i).unwrap(),
+                object: pair_index,
+            }
+        );
+    }
+
+    let pair = &objects[pair_index];
+    assert_eq!(pair.kind(), GcHeapSnapshotObjectKind::Struct);
+    assert!(pair.type_name().contains("struct"), "{}", pair.type_name());
+    assert_eq!(pair.edges().len(), 1);
+    let GcHeapSnapshotEdge::Field { field: 0, object } = pair.edges()[0] else {
+        panic!("unexpected edge: {:?}", pair.edges()[0]);
+    };
+    assert_eq!(objects[object].kind(), GcHeapSnapshotObjectKind::ExternRef);
+    assert_eq!(objects[object].type_name(), "externref");
+    assert!(snapshot.total_size() > 0);
+
+    let json = snapshot.to_json();
+    assert!(json.starts_with("{\"objects\":["));
+    assert!(json.contains("\"kind\":\"Wasm global\""));
+
+    let chrome = snapshot.to_chrome_heap_snapshot();
+    assert!(chrome.contains("\"node_count\":4"));
+    assert!(chrome.contains("\"(GC roots)\""));
+
+    // Taking a snapshot doesn't collect anything.
+    assert_eq!(store.gc_stats().collections, collections);
+
+    Ok(())
+}
@@ -0,0 +1,356 @@
+#![cfg(not(miri))]
+
+use std::sync::{Arc, Mutex};
+use wasmtime::*;
+
+fn engine() -> Result<Engine> {
+    let mut config = Config::new();
+    config.guest_debug(true);
+    Engine::new(&config)
+}
+
+#[test]
+fn breakpoint_on_function_sees_locals() -> Result<()> {
+    let engine = engine()?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (func (export "f") (param i32 f64) (result i32)
+                    (local i64)
+                    local.get 0)
+            )
+        "#,
+    )?;
+    let mut store = Store::new(&engine, Vec::new());
+    store.debug_handler(|mut store, frame| {
+        assert_eq!(frame.reason(), DebugPauseReason::Breakpoint);
+        assert_eq!(frame.func_index(), 0);
+        assert!(frame.operand_stack().is_empty());
+        let locals = frame
+            .locals()
+            .iter()
+            .map(|v| format!("{:?}", v.as_ref().unwrap()))
+            .collect::<Vec<_>>();
+        store.data_mut().push(locals);
+        Ok(DebugAction::Continue)
+    });
+    store.add_breakpoint(Breakpoint::function(&module, 0)?)?;
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let f = instance.get_typed_func::<(i32, f64), i32>(&mut store, "f")?;
+    assert_eq!(f.call(&mut store, (7, 1.5))?, 7);
+    assert_eq!(
+        *store.data(),
+        [[
+            format!("{:?}", Val::I32(7)),
+            format!("{:?}", Val::F64(1.5f64.to_bits())),
+            format!("{:?}", Val::I64(0)),
+        ]]
+    );
+    Ok(())
+}
+
+#[test]
+fn single_step_sees_operand_stack() -> Result<()> {
+    let engine = engine()?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (func (export "f") (result i32)
+                    i32.const 1
+                    i32.const 2
+                    i32.add)
+            )
+        "#,
+    )?;
+    let mut store = Store::new(&engine, Vec::new());
+    store.debug_handler(|mut store, frame| {
+        assert_eq!(frame.reason(), DebugPauseReason::Step);
+        let stack = frame
+            .operand_stack()
+            .iter()
+            .map(|v| v.as_ref().unwrap().unwrap_i32())
+            .collect::<Vec<_>>();
+        store.data_mut().push((frame.offset(), stack));
+        Ok(DebugAction::Step)
+    });
+    store.single_step(true)?;
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let f = instance.get_typed_func::<(), i32>(&mut store, "f")?;
+    assert_eq!(f.call(&mut store, ())?, 3);
+
+    let stacks = store
+        .data()
+        .iter()
+        .map(|(_, stack)| stack.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(stacks, [vec![], vec![1], vec![1, 2], vec![3]]);
+
+    // Offsets are strictly increasing through straight-line code, and a
+    // breakpoint on one of them pauses only there.
+    let offsets = store.data().iter().map(|(o, _)| *o).collect::<Vec<_>>();
+    assert!(offsets.windows(2).all(|w| w[0] < w[1]));
+
+    store.single_step(false)?;
+    store.data_mut().clear();
+    store.debug_handler(|mut store, frame| {
+        assert_eq!(frame.reason(), DebugPauseReason::Breakpoint);
+        let stack = frame
+            .operand_stack()
+            .iter()
+            .map(|v| v.as_ref().unwrap().unwrap_i32())
+            .collect::<Vec<_>>();
+        store.data_mut().push((frame.offset(), stack));
+        Ok(DebugAction::Continue)
+    });
+    store.add_breakpoint(Breakpoint::offset(&module, offsets[2]))?;
+    assert_eq!(f.call(&mut store, ())?, 3);
+    assert_eq!(*store.data(), [(offsets[2], vec![1, 2])]);
+    Ok(())
+}
+
+#[test]
+fn single_step_sees_updated_locals() -> Result<()> {
+    let engine = engine()?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (func (export "f") (param i32 externref) (result i32)
+                    (local i32)
+                    (loop $l
+                        local.get 2
+                        local.get 0
+                        i32.add
+                        local.tee 2
+                        i32.const 10
+                        i32.lt_u
+                        br_if $l)
+                    local.get 2)
+            )
+        "#,
+    )?;
+    let mut store = Store::new(&engine, Vec::new());
+    store.debug_handler(|mut store, frame| {
+        let locals = frame.locals();
+        assert_eq!(locals.len(), 3);
+        let externref = locals[1].as_ref().unwrap().unwrap_externref().copied();
+        let value = *externref
+            .unwrap()
+            .data(&store)?
+            .unwrap()
+            .downcast_ref::<i32>()
+            .unwrap();
+        assert_eq!(value, 42);
+        let local = locals[2].as_ref().unwrap().unwrap_i32();
+        store.data_mut().push(local);
+        Ok(DebugAction::Step)
+    });
+    store.single_step(true)?;
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let f = instance.get_typed_func::<(i32, Option<Rooted<ExternRef>>), i32>(&mut store, "f")?;
+    let externref = ExternRef::new(&mut store, 42)?;
+    assert_eq!(f.call(&mut store, (4, Some(externref)))?, 12);
+
+    // The local is observed as it's updated by each iteration.
+    let mut seen = store.data().clone();
+    seen.dedup();
+    assert_eq!(seen, [0, 4, 8, 12]);
+    Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "stack-switching", unix, target_arch = "x86_64"))]
+fn continuation_references_are_unavailable() -> Result<()> {
+    let mut config = Config::new();
+    config
+        .guest_debug(true)
+        .wasm_function_references(true)
+        .wasm_exceptions(true)
+        .wasm_stack_switching(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (type $ft (func))
+                (type $ct (cont $ft))
+                (func (export "f") (param i32)
+                    (local (ref null $ct))
+                    nop)
+            )
+        "#,
+    )?;
+    let mut store = Store::new(&engine, Vec::new());
+    store.debug_handler(|mut store, frame| {
+        let locals = frame
+            .locals()
+            .iter()
+            .map(|v| v.as_ref().map(|v| v.unwrap_i32()))
+            .collect::<Vec<_>>();
+        store.data_mut().push(locals);
+        Ok(DebugAction::Continue)
+    });
+    store.add_breakpoint(Breakpoint::function(&module, 0)?)?;
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let f = instance.get_typed_func::<i32, ()>(&mut store, "f")?;
+    f.call(&mut store, 7)?;
+    assert_eq!(*store.data(), [vec![Some(7), None]]);
+    Ok(())
+}
+
+#[test]
+fn inspect_globals_and_memory() -> Result<()> {
+    let engine = engine()?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (global (mut i32) (i32.const 42))
+                (memory 1)
+                (data (i32.const 8) "hello")
+                (func (export "f"))
+            )
+        "#,
+    )?;
+    let mut store = Store::new(&engine, None);
+    store.debug_handler(|mut store, frame| {
+        let global = frame.global(&store, 0).unwrap();
+        assert!(frame.global(&store, 1).is_none());
+        let memory = frame.memory(&store, 0).unwrap();
+        assert!(frame.memory(&store, 1).is_none());
+        let value = global.get(&mut store).unwrap_i32();
+        let bytes = memory.data(&store)[8..13].to_vec();
+        *store.data_mut() = Some((value, bytes));
+        Ok(DebugAction::Continue)
+    });
+    store.add_breakpoint(Breakpoint::function(&module, 0)?)?;
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let f = instance.get_typed_func::<(), ()>(&mut store, "f")?;
+    f.call(&mut store, ())?;
+    assert_eq!(*store.data(), Some((42, b"hello".to_vec())));
+    Ok(())
+}
+
+#[test]
+fn remove_breakpoint() -> Result<()> {
+    let engine = engine()?;
+    let module = Module::new(&engine, r#"(module (func (export "f")))"#)?;
+    let mut store = Store::new(&engine, 0);
+    store.debug_handler(|mut store, _frame| {
+        *store.data_mut() += 1;
+        Ok(DebugAction::Continue)
+    });
+    let breakpoint = Breakpoint::function(&module, 0)?;
+    store.add_breakpoint(breakpoint.clone())?;
+    store.add_breakpoint(breakpoint.clone())?;
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let f = instance.get_typed_func::<(), ()>(&mut store, "f")?;
+    f.call(&mut store, ())?;
+    assert_eq!(*store.data(), 1);
+
+    assert!(store.remove_breakpoint(&breakpoint));
+    assert!(!store.remove_breakpoint(&breakpoint));
+    f.call(&mut store, ())?;
+    assert_eq!(*store.data(), 1);
+    Ok(())
+}
+
+#[test]
+fn handler_error_traps() -> Result<()> {
+    let engine = engine()?;
+    let module = Module::new(&engine, r#"(module (func (export "f")))"#)?;
+    let mut store = Store::new(&engine, ());
+    store.debug_handler(|_store, _frame| anyhow::bail!("stopped by debugger"));
+    store.add_breakpoint(Breakpoint::function(&module, 0)?)?;
+
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let f = instance.get_typed_func::<(), ()>(&mut store, "f")?;
+    let err = f.call(&mut store, ()).unwrap_err();
+    assert!(
+        format!("{err:?}").contains("stopped by debugger"),
+        "bad error: {err:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn requires_guest_debug() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, r#"(module (func))"#)?;
+    let mut store = Store::new(&engine, ());
+    let err = store
+        .add_breakpoint(Breakpoint::function(&module, 0)?)
+        .unwrap_err();
+    assert!(err.to_string().contains("Config::guest_debug"), "{err}");
+    assert!(store.single_step(true).is_err());
+    store.single_step(false)?;
+    Ok(())
+}
+
+#[test]
+fn function_breakpoint_validation() -> Result<()> {
+    let engine = engine()?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "" "" (func))
+                (func)
+            )
+        "#,
+    )?;
+    assert!(Breakpoint::function(&module, 0).is_err());
+    assert!(Breakpoint::function(&module, 1).is_ok());
+    assert!(Breakpoint::function(&module, 2).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn async_handler() -> Result<()> {
+    struct Handler(Arc<Mutex<Vec<u32>>>);
+
+    #[async_trait::async_trait]
+    impl DebugHandler<()> for Handler {
+        async fn handle_pause(
+            &self,
+            _store: StoreContextMut<'_, ()>,
+            frame: &DebugFrame,
+        ) -> Result<DebugAction> {
+            tokio::task::yield_now().await;
+            self.0.lock().unwrap().push(frame.func_index());
+            Ok(DebugAction::Continue)
+        }
+    }
+
+    let mut config = Config::new();
+    config.guest_debug(true).async_support(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (func $a)
+                (func (export "f") call $a)
+            )
+        "#,
+    )?;
+    let hits = Arc::new(Mutex::new(Vec::new()));
+    let mut store = Store::new(&engine, ());
+    store.debug_handler_async(Handler(hits.clone()));
+    store.add_breakpoint(Breakpoint::function(&module, 0)?)?;
+    store.add_breakpoint(Breakpoint::function(&module, 1)?)?;
+
+    let instance = Instance::new_async(&mut store, &module, &[]).await?;
+    let f = instance.get_typed_func::<(), ()>(&mut store, "f")?;
+    f.call_async(&mut store, ()).await?;
+    assert_eq!(*hits.lock().unwrap(), [1, 0]);
+    Ok(())
+}
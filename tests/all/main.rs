@@ -21,6 +21,7 @@ mod func;
 mod funcref;
 mod gc;
 mod globals;
+mod guest_debug;
 mod host_funcs;
 mod i31ref;
 mod iloop;
@@ -52,6 +53,8 @@ mod traps;
 mod types;
 mod wait_notify;
 mod winch_engine_features;
+#[cfg(feature = "wmemcheck")]
+mod wmemcheck;
 
 /// A helper to compile a module in a new store with reference types enabled.
 pub(crate) fn ref_types_module(
@@ -649,3 +649,56 @@ fn deserialize_raw_fails_for_native() {
         );
     }
 }
+
+#[wasmtime_test(strategies(not(Winch)))]
+#[cfg_attr(miri, ignore)]
+fn coverage_counts_blocks_and_branches(config: &mut Config) -> Result<()> {
+    config.coverage(true);
+    let engine = Engine::new(config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (func $countdown (export "countdown") (param i32)
+                    (loop $l
+                        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                        (br_if $l (local.get 0))))
+                (func (export "never")))
+        "#,
+    )?;
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let countdown = instance.get_typed_func::<i32, ()>(&mut store, "countdown")?;
+    countdown.call(&mut store, 3)?;
+
+    let coverage = module.coverage().unwrap();
+    let [countdown, never] = coverage.functions() else {
+        panic!("unexpected functions: {:?}", coverage.functions());
+    };
+    assert_eq!(countdown.name(), "countdown");
+    assert_eq!(countdown.calls(), 1);
+    assert!(countdown.blocks().iter().any(|b| b.count() == 3));
+    let [branch] = countdown.branches() else {
+        panic!("unexpected branches: {:?}", countdown.branches());
+    };
+    assert_eq!((branch.taken(), branch.not_taken()), (2, 1));
+    assert_eq!(never.calls(), 0);
+
+    let mut lcov = Vec::new();
+    coverage.write_lcov(&mut lcov, "test.wasm")?;
+    let lcov = String::from_utf8(lcov)?;
+    assert!(lcov.contains("SF:test.wasm\n"));
+    assert!(lcov.contains(",countdown\n"));
+    assert!(lcov.contains("FNH:1\n"));
+    assert!(lcov.contains(",2\n"));
+    assert!(lcov.trim_end().ends_with("end_of_record"));
+    Ok(())
+}
+
+#[test]
+fn coverage_disabled_by_default() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, "(module (func))")?;
+    assert!(module.coverage().is_none());
+    Ok(())
+}
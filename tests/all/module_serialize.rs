@@ -154,3 +154,90 @@ fn detect_precompiled() -> Result<()> {
     );
     Ok(())
 }
+
+#[cfg(feature = "signing")]
+fn signing_engines(secret_key: &[u8; 32], public_key: &[u8; 32]) -> Result<(Engine, Engine)> {
+    let mut config = Config::new();
+    config.signing_key(secret_key);
+    let signer = Engine::new(&config)?;
+
+    let mut config = Config::new();
+    config.trust_signing_key(public_key)?;
+    let verifier = Engine::new(&config)?;
+    Ok((signer, verifier))
+}
+
+#[cfg(feature = "signing")]
+const SECRET_KEY: [u8; 32] = [
+    0xb6, 0x98, 0x96, 0x1e, 0x86, 0x80, 0xdd, 0x31, 0xf1, 0x2b, 0x1f, 0x34, 0x99, 0x54, 0x97, 0x11,
+    0xd6, 0x38, 0x80, 0xc0, 0x72, 0x7b, 0xd7, 0x21, 0x3f, 0x7a, 0x75, 0x23, 0xb0, 0x4a, 0x30, 0x95,
+];
+
+#[cfg(feature = "signing")]
+const PUBLIC_KEY: [u8; 32] = [
+    0xf5, 0x8c, 0x50, 0x23, 0x57, 0x39, 0xf1, 0x25, 0x7b, 0x6c, 0x6a, 0x81, 0xb4, 0xff, 0x18, 0xe3,
+    0x8e, 0xad, 0xa9, 0x08, 0xcc, 0x56, 0x1c, 0xb6, 0x55, 0xf5, 0x4a, 0xcd, 0xbc, 0xc3, 0x6d, 0xd3,
+];
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[cfg(feature = "signing")]
+fn deserialize_verified() -> Result<()> {
+    let (signer, verifier) = signing_engines(&SECRET_KEY, &PUBLIC_KEY)?;
+    let buffer =
+        signer.precompile_module(b"(module (func (export \"run\") (result i32) i32.const 42))")?;
+
+    let module = Module::deserialize_verified(&verifier, &buffer)?;
+    let mut store = Store::new(&verifier, ());
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<(), i32>(&mut store, "run")?;
+    assert_eq!(run.call(&mut store, ())?, 42);
+
+    // Signatures aren't required by the unverified path.
+    unsafe { Module::deserialize(&verifier, &buffer)? };
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[cfg(feature = "signing")]
+fn deserialize_verified_rejects_tampering() -> Result<()> {
+    let (signer, verifier) = signing_engines(&SECRET_KEY, &PUBLIC_KEY)?;
+    let buffer = signer.precompile_module(b"(module)")?;
+
+    // Flipping any byte, not just those in the text section, invalidates the
+    // signature.
+    for i in [0x40, buffer.len() / 2, buffer.len() - 1] {
+        let mut tampered = buffer.clone();
+        tampered[i] ^= 1;
+        assert!(Module::deserialize_verified(&verifier, &tampered).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[cfg(feature = "signing")]
+fn deserialize_verified_rejects_untrusted() -> Result<()> {
+    let (signer, verifier) = signing_engines(&SECRET_KEY, &PUBLIC_KEY)?;
+
+    let unsigned = Engine::default().precompile_module(b"(module)")?;
+    let err = Module::deserialize_verified(&verifier, &unsigned).unwrap_err();
+    assert_eq!(err.to_string(), "precompiled artifact is not signed");
+
+    let signed = signer.precompile_module(b"(module)")?;
+    let err = Module::deserialize_verified(&Engine::default(), &signed).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "no trusted signing keys are configured for this engine"
+    );
+
+    let (other_signer, _) = signing_engines(&[1; 32], &PUBLIC_KEY)?;
+    let signed = other_signer.precompile_module(b"(module)")?;
+    let err = Module::deserialize_verified(&verifier, &signed).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "precompiled artifact was not signed by a trusted key"
+    );
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use wasmtime::*;
+
+// Global 0 is the stack pointer, and moving it down poisons the new frame at
+// 1008..1024.
+const UNINIT: &str = r#"
+    (module
+        (import "" "host" (func $host (param i32)))
+        (memory (export "memory") 1)
+        (global $sp (mut i32) (i32.const 1024))
+
+        (func $alloca
+            (global.set $sp (i32.sub (global.get $sp) (i32.const 16))))
+
+        (func (export "branch") (result i32)
+            call $alloca
+            (if (result i32) (i32.load (i32.const 1008))
+                (then (i32.const 1))
+                (else (i32.const 0))))
+
+        (func (export "copy") (result i32)
+            (local i32)
+            call $alloca
+            (i32.store (i32.const 2048) (i32.add (i32.load (i32.const 1008)) (i32.const 1)))
+            (local.set 0 (i32.load (i32.const 2048)))
+            (drop (local.get 0))
+            i32.const 0)
+
+        (func (export "copy-then-call")
+            call $alloca
+            (i32.store (i32.const 2048) (i32.load (i32.const 1012)))
+            (call $host (i32.load (i32.const 2048))))
+
+        (func (export "address") (result i32)
+            call $alloca
+            (i32.load (i32.load (i32.const 1008))))
+
+        (func (export "loop") (result i32)
+            (local i32)
+            call $alloca
+            (local.set 0 (i32.const 3))
+            (loop $l (result i32)
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br_if $l (local.get 0))
+                (i32.load (i32.const 1008)))
+            (br_if 0 (i32.const 0))
+            (if (result i32) (then (i32.const 1)) (else (i32.const 0))))
+    )
+"#;
+
+fn instantiate() -> Result<(Store<()>, Instance)> {
+    let mut config = Config::new();
+    config.wmemcheck(true);
+    config.wmemcheck_uninit(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::new(&engine, UNINIT)?;
+    let mut store = Store::new(&engine, ());
+    let host = Func::wrap(&mut store, |_: i32| {});
+    let instance = Instance::new(&mut store, &module, &[host.into()])?;
+    Ok((store, instance))
+}
+
+fn assert_uninit_use(err: Error, kind: &str) {
+    let msg = format!("{err:?}");
+    assert!(
+        msg.contains(&format!("Use of uninitialized value as {kind}")),
+        "unexpected error: {msg}"
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn branch_on_uninitialized_value() -> Result<()> {
+    let (mut store, instance) = instantiate()?;
+    let branch = instance.get_typed_func::<(), i32>(&mut store, "branch")?;
+    assert_uninit_use(
+        branch.call(&mut store, ()).unwrap_err(),
+        "conditional branch",
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn copying_uninitialized_value_is_not_reported() -> Result<()> {
+    let (mut store, instance) = instantiate()?;
+    let copy = instance.get_typed_func::<(), i32>(&mut store, "copy")?;
+    assert_eq!(copy.call(&mut store, ())?, 0);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn uninitialized_value_through_memory_to_host() -> Result<()> {
+    let (mut store, instance) = instantiate()?;
+    let copy = instance.get_typed_func::<(), ()>(&mut store, "copy-then-call")?;
+    assert_uninit_use(
+        copy.call(&mut store, ()).unwrap_err(),
+        "argument to an imported function",
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn uninitialized_address() -> Result<()> {
+    let (mut store, instance) = instantiate()?;
+    let address = instance.get_typed_func::<(), i32>(&mut store, "address")?;
+    assert_uninit_use(
+        address.call(&mut store, ()).unwrap_err(),
+        "memory address or table index",
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn uninitialized_value_through_loop_results() -> Result<()> {
+    let (mut store, instance) = instantiate()?;
+    let func = instance.get_typed_func::<(), i32>(&mut store, "loop")?;
+    assert_uninit_use(func.call(&mut store, ()).unwrap_err(), "conditional branch");
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn host_writes_define_memory() -> Result<()> {
+    let (mut store, instance) = instantiate()?;
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+
+    // Poison the frame, then initialize it from the host before the guest
+    // branches on it again, through another instance importing the memory.
+    let branch = instance.get_typed_func::<(), i32>(&mut store, "branch")?;
+    assert!(branch.call(&mut store, ()).is_err());
+    memory.write(&mut store, 1008, &1u32.to_le_bytes())?;
+    memory.wmemcheck_define(&mut store, 1012, 4);
+
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "memory" (memory 1))
+                (func (export "read") (result i32)
+                    (if (result i32)
+                        (i32.add (i32.load (i32.const 1008)) (i32.load (i32.const 1012)))
+                        (then (i32.const 1))
+                        (else (i32.const 0)))))
+        "#,
+    )?;
+    let other = Instance::new(&mut store, &module, &[memory.into()])?;
+    let read = other.get_typed_func::<(), i32>(&mut store, "read")?;
+    assert_eq!(read.call(&mut store, ())?, 1);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn leak_summary() -> Result<()> {
+    let mut config = Config::new();
+    config.wmemcheck(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory 2)
+                (global $__stack_pointer (mut i32) (i32.const 1024))
+                (global $heap (mut i32) (i32.const 65536))
+
+                (func $malloc (param i32) (result i32)
+                    (local i32)
+                    (local.set 1 (global.get $heap))
+                    (global.set $heap (i32.add (local.get 1) (local.get 0)))
+                    (local.get 1))
+
+                (func $free (param i32))
+
+                (func $leak
+                    (drop (call $malloc (i32.const 24))))
+
+                (func (export "run")
+                    (call $free (call $malloc (i32.const 16)))
+                    (call $leak)))
+        "#,
+    )?;
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &module, &[])?;
+    assert!(store.wmemcheck_leak_summaries().is_empty());
+
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+    run.call(&mut store, ())?;
+
+    let summaries = store.wmemcheck_leak_summaries();
+    assert_eq!(summaries.len(), 1);
+    let summary = &summaries[0];
+    assert_eq!(summary.total_allocs, 2);
+    assert_eq!(summary.total_frees, 1);
+    assert_eq!(summary.total_bytes_allocated, 40);
+    assert_eq!(summary.leaks.len(), 1);
+    assert_eq!(summary.leaks[0].addr, 65536 + 16);
+    assert_eq!(summary.leaks[0].len, 24);
+    let backtrace = summary.leaks[0].backtrace.as_deref().unwrap();
+    let frames = backtrace.lines().collect::<Vec<_>>();
+    assert_eq!(frames.len(), 3, "{backtrace}");
+    assert!(frames[0].starts_with("at ") && frames[0].contains(": malloc ("));
+    assert!(frames[1].starts_with("by ") && frames[1].contains(": leak ("));
+    assert!(frames[2].contains(": <wasm function 3> ("), "{backtrace}");
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn trap_in_allocator_is_unwound() -> Result<()> {
+    let mut config = Config::new();
+    config.wmemcheck(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory 2)
+                (global $__stack_pointer (mut i32) (i32.const 1024))
+                (global $heap (mut i32) (i32.const 65536))
+
+                (func $malloc (export "malloc") (param i32) (result i32)
+                    (local i32)
+                    (if (i32.eqz (local.get 0)) (then unreachable))
+                    (local.set 1 (global.get $heap))
+                    (global.set $heap (i32.add (local.get 1) (local.get 0)))
+                    (local.get 1))
+
+                (func $free (param i32))
+
+                (func (export "load-freed") (result i32)
+                    (local i32)
+                    (local.set 0 (call $malloc (i32.const 16)))
+                    (call $free (local.get 0))
+                    (i32.load (local.get 0))))
+        "#,
+    )?;
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &module, &[])?;
+
+    // The trap leaves `malloc` without returning, which must not leave
+    // checking turned off as if the program were still in the allocator.
+    let malloc = instance.get_typed_func::<i32, i32>(&mut store, "malloc")?;
+    malloc.call(&mut store, 0).unwrap_err();
+
+    let load_freed = instance.get_typed_func::<(), i32>(&mut store, "load-freed")?;
+    let err = load_freed.call(&mut store, ()).unwrap_err();
+    assert!(format!("{err:?}").contains("Invalid load"), "{err:?}");
+    let summaries = store.wmemcheck_leak_summaries();
+    assert_eq!(summaries[0].total_allocs, 1);
+    assert_eq!(summaries[0].total_frees, 1);
+    Ok(())
+}
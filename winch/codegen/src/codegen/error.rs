@@ -100,6 +100,9 @@ pub(crate) enum InternalError {
     /// Invalid two argument form.
     #[error("Invalid two argument form")]
     InvalidTwoArgumentForm,
+    /// The frame-state area is too small for the current values.
+    #[error("Frame-state area exceeded")]
+    FrameStateAreaExceeded,
 }
 
 impl CodeGenError {
@@ -191,6 +194,10 @@ impl CodeGenError {
         Self::Internal(InternalError::InvalidOperandCombination)
     }
 
+    pub(crate) const fn frame_state_area_exceeded() -> Self {
+        Self::Internal(InternalError::FrameStateAreaExceeded)
+    }
+
     pub(crate) const fn unimplemented_masm_instruction() -> Self {
         Self::UnimplementedMasmInstruction
     }
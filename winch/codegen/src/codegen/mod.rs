@@ -1,5 +1,5 @@
 use crate::{
-    abi::{ABIOperand, ABISig, RetArea, vmctx},
+    abi::{ABIOperand, ABISig, LocalSlot, RetArea, vmctx},
     codegen::BlockSig,
     isa::reg::{Reg, RegClass, writable},
     masm::{
@@ -20,10 +20,13 @@ use wasmparser::{
     BinaryReader, FuncValidator, MemArg, Operator, OperatorsReader, ValidatorResources,
     VisitOperator, VisitSimdOperator,
 };
-use wasmtime_cranelift::{TRAP_BAD_SIGNATURE, TRAP_HEAP_MISALIGNED, TRAP_TABLE_OUT_OF_BOUNDS};
+use wasmtime_cranelift::{
+    FrameStateLocation, TRAP_BAD_SIGNATURE, TRAP_HEAP_MISALIGNED, TRAP_TABLE_OUT_OF_BOUNDS,
+};
 use wasmtime_environ::{
-    FUNCREF_MASK, GlobalIndex, MemoryIndex, PtrSize, TableIndex, Tunables, TypeIndex, WasmHeapType,
-    WasmValType,
+    DEBUG_VALUE_SLOT_SIZE, DebugValueKind, FRAME_STATE_HEADER_SIZE, FRAME_STATE_NUM_LOCALS,
+    FRAME_STATE_NUM_STACK, FUNCREF_MASK, GlobalIndex, MemoryIndex, PtrSize, TableIndex, Tunables,
+    TypeIndex, WasmHeapType, WasmValType, records_frame_state,
 };
 
 mod context;
@@ -115,6 +118,10 @@ where
 
     /// Local counter to track fuel consumption.
     pub fuel_consumed: i64,
+
+    /// The code offset right after the frame state is first recorded, from
+    /// which on the frame-state area holds valid state.
+    pub frame_state_start: Option<CodeOffset>,
     phase: PhantomData<P>,
 }
 
@@ -139,6 +146,7 @@ where
             control_frames: Default::default(),
             // Empty functions should consume at least 1 fuel unit.
             fuel_consumed: 1,
+            frame_state_start: None,
             phase: PhantomData,
         }
     }
@@ -199,6 +207,7 @@ where
             source_location: self.source_location,
             control_frames: self.control_frames,
             fuel_consumed: self.fuel_consumed,
+            frame_state_start: self.frame_state_start,
             phase: PhantomData,
         })
     }
@@ -250,6 +259,17 @@ where
         Ok(())
     }
 
+    /// Returns the location of the frame-state area, if the frame state has
+    /// been recorded, given the distance from the frame pointer to the base
+    /// from which locals are addressed.
+    pub fn frame_state_location(&self, locals_base: u32) -> Option<FrameStateLocation> {
+        let area = self.context.frame.frame_state_area?;
+        Some(FrameStateLocation {
+            valid_from: self.frame_state_start?,
+            fp_offset: locals_base + area.offset,
+        })
+    }
+
     /// Pops a control frame from the control frame stack.
     pub fn pop_control_frame(&mut self) -> Result<ControlStackFrame> {
         self.control_frames
@@ -315,6 +335,8 @@ where
         body: BinaryReader<'a>,
         validator: &mut FuncValidator<ValidatorResources>,
     ) -> Result<()> {
+        self.maybe_record_frame_state()?;
+
         self.maybe_emit_fuel_check()?;
 
         self.maybe_emit_epoch_check()?;
@@ -412,6 +434,11 @@ where
                 if self.tunables.consume_fuel {
                     self.fuel_before_visit_op(operator)?;
                 }
+
+                // Handle frame state for core dumps.
+                if self.context.reachable && records_frame_state(operator) {
+                    self.maybe_record_frame_state()?;
+                }
                 Ok(())
             }
 
@@ -1106,6 +1133,111 @@ where
         Ok(())
     }
 
+    /// Records the current locals and value stack in the frame-state area, if
+    /// the frame has one, so that they can be recovered in core dumps.
+    pub fn maybe_record_frame_state(&mut self) -> Result<()> {
+        let Some(area) = self.context.frame.frame_state_area else {
+            return Ok(());
+        };
+        let num_locals = self.context.frame.num_wasm_locals();
+        let num_stack = u32::try_from(self.context.stack.len())?;
+        ensure!(
+            num_locals + num_stack <= area.capacity,
+            CodeGenError::frame_state_area_exceeded()
+        );
+
+        // Fields of the area are addressed like locals, at `offset` bytes from
+        // the start of the area.
+        let field = |offset: u32| LocalSlot::new(WasmValType::I64, area.offset - offset);
+        let value = |i: u32| field(FRAME_STATE_HEADER_SIZE + i * DEBUG_VALUE_SLOT_SIZE);
+        let kind = |i: u32| {
+            field(FRAME_STATE_HEADER_SIZE + (num_locals + num_stack) * DEBUG_VALUE_SLOT_SIZE + i)
+        };
+
+        let addr = self.masm.local_address(&field(0))?;
+        self.masm.store_ptr(vmctx!(M), addr)?;
+        let addr = self.masm.local_address(&field(FRAME_STATE_NUM_LOCALS))?;
+        self.masm
+            .store(RegImm::i32(num_locals as i32), addr, OperandSize::S32)?;
+        let addr = self.masm.local_address(&field(FRAME_STATE_NUM_STACK))?;
+        self.masm
+            .store(RegImm::i32(num_stack as i32), addr, OperandSize::S32)?;
+
+        for i in 0..num_locals {
+            let slot = *self.context.frame.get_wasm_local(i);
+            let src = self.masm.local_address(&slot)?;
+            let dst = self.masm.local_address(&value(i))?;
+            Self::copy_frame_state_value(self.masm, src, dst, slot.ty)?;
+            let addr = self.masm.local_address(&kind(i))?;
+            let kind = DebugValueKind::from_wasm_type(&slot.ty) as i32;
+            self.masm.store(RegImm::i32(kind), addr, OperandSize::S8)?;
+        }
+
+        for (i, val) in self.context.stack.inner().iter().enumerate() {
+            let i = num_locals + u32::try_from(i)?;
+            let dst = self.masm.local_address(&value(i))?;
+            match *val {
+                Val::I32(v) => self.masm.store(RegImm::i32(v), dst, OperandSize::S32)?,
+                Val::I64(v) => self.masm.store(RegImm::i64(v), dst, OperandSize::S64)?,
+                Val::F32(v) => {
+                    let bits = v.bits() as i32;
+                    self.masm.store(RegImm::i32(bits), dst, OperandSize::S32)?
+                }
+                Val::F64(v) => {
+                    let bits = v.bits() as i64;
+                    self.masm.store(RegImm::i64(bits), dst, OperandSize::S64)?
+                }
+                Val::V128(v) => {
+                    self.masm
+                        .store(RegImm::i64(v as i64), dst, OperandSize::S64)?;
+                    let slot = value(i);
+                    let high = LocalSlot::new(slot.ty, slot.offset - 8);
+                    let addr = self.masm.local_address(&high)?;
+                    self.masm
+                        .store(RegImm::i64((v >> 64) as i64), addr, OperandSize::S64)?;
+                }
+                Val::Reg(r) => self.masm.store(r.reg.into(), dst, r.ty.try_into()?)?,
+                Val::Local(local) => {
+                    let slot = *self.context.frame.get_wasm_local(local.index);
+                    let src = self.masm.local_address(&slot)?;
+                    Self::copy_frame_state_value(self.masm, src, dst, local.ty)?;
+                }
+                Val::Memory(mem) => {
+                    let src = self.masm.address_from_sp(mem.slot.offset)?;
+                    Self::copy_frame_state_value(self.masm, src, dst, mem.ty)?;
+                }
+            }
+            let addr = self.masm.local_address(&kind(i))?;
+            let kind = DebugValueKind::from_wasm_type(&val.ty()) as i32;
+            self.masm.store(RegImm::i32(kind), addr, OperandSize::S8)?;
+        }
+
+        if self.frame_state_start.is_none() {
+            self.frame_state_start = Some(self.masm.current_code_offset()?);
+        }
+        Ok(())
+    }
+
+    /// Copies a value of type `ty` from `src` into the frame-state area at
+    /// `dst`.
+    fn copy_frame_state_value(
+        masm: &mut M,
+        src: M::Address,
+        dst: M::Address,
+        ty: WasmValType,
+    ) -> Result<()> {
+        let size = ty.try_into()?;
+        // References are pointers, which live in integer registers.
+        let class = match ty {
+            WasmValType::Ref(_) => WasmValType::I64,
+            ty => ty,
+        };
+        masm.with_scratch_for(class, |masm, scratch| {
+            masm.load(src, scratch.writable(), size)?;
+            masm.store(scratch.inner().into(), dst, size)
+        })
+    }
+
     /// Checks if fuel consumption is enabled and emits a series of instructions
     /// that check the current fuel usage by performing a zero-comparison with
     /// the number of units stored in `VMStoreContext`.
@@ -8,7 +8,7 @@ use smallvec::SmallVec;
 use std::marker::PhantomData;
 use std::ops::Range;
 use wasmparser::{BinaryReader, FuncValidator, ValidatorResources};
-use wasmtime_environ::{TypeConvert, WasmValType};
+use wasmtime_environ::{DEBUG_VALUE_SLOT_SIZE, FRAME_STATE_HEADER_SIZE, TypeConvert, WasmValType};
 
 /// WebAssembly locals.
 // TODO:
@@ -75,6 +75,17 @@ impl DefinedLocals {
     }
 }
 
+/// The area of the frame in which the locals and value stack are recorded
+/// for core dumps, see `wasmtime_environ::frame_state` for its layout.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FrameStateArea {
+    /// The offset of the start of the area, in the same terms as the offset of
+    /// a [`LocalSlot`].
+    pub offset: u32,
+    /// The maximum number of values that the area can hold.
+    pub capacity: u32,
+}
+
 /// Frame handler abstraction.
 pub(crate) struct Frame<P: CodeGenPhase> {
     /// The size of the entire local area; the arguments plus the function defined locals.
@@ -93,12 +104,23 @@ pub(crate) struct Frame<P: CodeGenPhase> {
 
     /// The slot holding the address of the results area.
     pub results_base_slot: Option<LocalSlot>,
+
+    /// The area for recording frame state, if any.
+    pub frame_state_area: Option<FrameStateArea>,
     marker: PhantomData<P>,
 }
 
 impl Frame<Prologue> {
     /// Allocate a new [`Frame`].
-    pub fn new<A: ABI>(sig: &ABISig, defined_locals: &DefinedLocals) -> Result<Frame<Prologue>> {
+    ///
+    /// When `max_stack_height` is given, an area large enough to record all of
+    /// the locals and that many value stack entries is reserved at the end of
+    /// the locals.
+    pub fn new<A: ABI>(
+        sig: &ABISig,
+        defined_locals: &DefinedLocals,
+        max_stack_height: Option<u32>,
+    ) -> Result<Frame<Prologue>> {
         let (special_locals, mut wasm_locals, defined_locals_start) =
             Self::compute_arg_slots::<A>(sig)?;
 
@@ -148,6 +170,19 @@ impl Frame<Prologue> {
             (None, defined_locals_end)
         };
 
+        let (frame_state_area, locals_size) = match max_stack_height {
+            Some(height) => {
+                let capacity = u32::try_from(wasm_locals.len())? + height;
+                let size = FRAME_STATE_HEADER_SIZE + capacity * (DEBUG_VALUE_SLOT_SIZE + 1);
+                let offset = align_to(locals_size + size, DEBUG_VALUE_SLOT_SIZE);
+                (
+                    Some(FrameStateArea { offset, capacity }),
+                    align_to(offset, stack_align.into()),
+                )
+            }
+            None => (None, locals_size),
+        };
+
         Ok(Self {
             wasm_locals,
             special_locals,
@@ -156,6 +191,7 @@ impl Frame<Prologue> {
                 defined_locals_start..(defined_locals_start + defined_locals.stack_size),
             ),
             results_base_slot,
+            frame_state_area,
             marker: PhantomData,
         })
     }
@@ -174,6 +210,7 @@ impl Frame<Prologue> {
             locals_size: self.locals_size,
             defined_locals_range: self.defined_locals_range,
             results_base_slot: self.results_base_slot,
+            frame_state_area: self.frame_state_area,
             marker: PhantomData,
         }
     }
@@ -263,6 +300,11 @@ impl Frame<Emission> {
             .unwrap_or_else(|| panic!(" Expected WebAssembly local at slot: {index}"))
     }
 
+    /// Returns the number of WebAssembly locals, including parameters.
+    pub fn num_wasm_locals(&self) -> u32 {
+        u32::try_from(self.wasm_locals.len()).unwrap()
+    }
+
     /// Get the [`LocalSlot`] for a special local.
     ///
     /// # Panics
@@ -89,6 +89,7 @@ impl TargetIsa for Aarch64 {
         builtins: &mut BuiltinFunctions,
         validator: &mut FuncValidator<ValidatorResources>,
         tunables: &Tunables,
+        max_stack_height: Option<u32>,
     ) -> Result<CompiledFunction> {
         let pointer_bytes = self.pointer_bytes();
         let vmoffsets = VMOffsets::new(pointer_bytes, &translation.module);
@@ -108,20 +109,25 @@ impl TargetIsa for Aarch64 {
         let type_converter = TypeConverter::new(env.translation, env.types);
         let defined_locals =
             DefinedLocals::new::<abi::Aarch64ABI>(&type_converter, &mut body, validator)?;
-        let frame = Frame::new::<abi::Aarch64ABI>(&abi_sig, &defined_locals)?;
+        let frame = Frame::new::<abi::Aarch64ABI>(&abi_sig, &defined_locals, max_stack_height)?;
         let regalloc = RegAlloc::from(gpr_bit_set(), fpr_bit_set());
         let codegen_context = CodeGenContext::new(regalloc, stack, frame, &vmoffsets);
         let codegen = CodeGen::new(tunables, &mut masm, codegen_context, env, abi_sig);
 
         let mut body_codegen = codegen.emit_prologue()?;
         body_codegen.emit(body, validator)?;
+        // Locals are addressed from the shadow stack pointer, which starts below
+        // its saved value in the frame.
+        let frame_state =
+            body_codegen.frame_state_location(abi::SHADOW_STACK_POINTER_SLOT_SIZE.into());
         let names = body_codegen.env.take_name_map();
         let base = body_codegen.source_location.base;
-        Ok(CompiledFunction::new(
-            masm.finalize(base)?,
-            names,
-            self.function_alignment(),
-        ))
+        let mut func =
+            CompiledFunction::new(masm.finalize(base)?, names, self.function_alignment());
+        if let Some(location) = frame_state {
+            func.set_frame_state(location);
+        }
+        Ok(func)
     }
 
     fn text_section_builder(&self, num_funcs: usize) -> Box<dyn TextSectionBuilder> {
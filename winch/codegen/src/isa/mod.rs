@@ -166,6 +166,10 @@ pub trait TargetIsa: Send + Sync {
     }
 
     /// Compile a function.
+    ///
+    /// When `tunables.debug_frame_state` is enabled, `max_stack_height` must
+    /// be the maximum height of the function's operand stack, which is used to
+    /// size the area in which the frame state is recorded.
     fn compile_function(
         &self,
         sig: &WasmFuncType,
@@ -175,6 +179,7 @@ pub trait TargetIsa: Send + Sync {
         builtins: &mut BuiltinFunctions,
         validator: &mut FuncValidator<ValidatorResources>,
         tunables: &Tunables,
+        max_stack_height: Option<u32>,
     ) -> Result<CompiledFunction>;
 
     /// Get the default calling convention of the underlying target triple.